@@ -0,0 +1,180 @@
+//! 热点路径微基准：R-tree 插入/查询/KNN、RESP 解码、GeoJSON 转换
+//!
+//! 运行: cargo bench --bench hot_paths
+//!
+//! R-tree 基准覆盖 10k/100k/1M 三个规模，调整分裂策略等算法时用来对比前后的性能。
+//! 只跑一部分: cargo bench --bench hot_paths -- rtree_knn/100000
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use spatio::protocol::RespParser;
+use spatio::storage::geometry_utils::{geojson_to_geometry, geometry_to_geojson};
+use spatio::{RTree, Rectangle};
+
+const TREE_SIZES: [usize; 3] = [10_000, 100_000, 1_000_000];
+const MAX_ENTRIES: usize = 16;
+const K: usize = 10;
+/// 范围查询窗口的边长（度），约 1km
+const SEARCH_WINDOW: f64 = 0.01;
+const POLYGON_VERTICES: usize = 1_000;
+
+/// 北京周边的随机点
+fn random_points(size: usize, seed: u64) -> Vec<(f64, f64)> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..size)
+        .map(|_| (rng.gen_range(115.0..118.0), rng.gen_range(39.0..41.0)))
+        .collect()
+}
+
+fn point_geojson(lon: f64, lat: f64) -> String {
+    format!(r#"{{"type":"Point","coordinates":[{},{}]}}"#, lon, lat)
+}
+
+/// 以 (116.4, 39.9) 为中心、`vertices` 个顶点的闭合多边形
+fn polygon_geojson(vertices: usize) -> String {
+    let ring: Vec<String> = (0..=vertices)
+        .map(|i| {
+            let angle = std::f64::consts::TAU * (i % vertices) as f64 / vertices as f64;
+            format!(
+                "[{},{}]",
+                116.4 + 0.5 * angle.cos(),
+                39.9 + 0.5 * angle.sin()
+            )
+        })
+        .collect();
+    format!(
+        r#"{{"type":"Polygon","coordinates":[[{}]]}}"#,
+        ring.join(",")
+    )
+}
+
+fn build_tree(points: &[(f64, f64)]) -> RTree {
+    let mut tree = RTree::new(MAX_ENTRIES);
+    for (i, (lon, lat)) in points.iter().enumerate() {
+        tree.insert_geojson(format!("item_{}", i), &point_geojson(*lon, *lat));
+    }
+    tree
+}
+
+fn bench_rtree(c: &mut Criterion) {
+    let queries = random_points(256, 7);
+
+    for size in TREE_SIZES {
+        let points = random_points(size, 42);
+
+        // 插入：从空树逐条插入 size 个条目，直接反映 ChooseLeaf 和分裂策略的开销
+        let rects: Vec<(Rectangle, String)> = points
+            .iter()
+            .enumerate()
+            .map(|(i, (lon, lat))| (Rectangle::from_point(*lon, *lat), format!("item_{}", i)))
+            .collect();
+        let mut group = c.benchmark_group("rtree_insert");
+        group.sample_size(10);
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &rects, |b, rects| {
+            b.iter(|| {
+                let mut tree = RTree::new(MAX_ENTRIES);
+                for (rect, id) in rects {
+                    tree.insert(*rect, id.clone());
+                }
+                black_box(tree.len())
+            });
+        });
+        group.finish();
+
+        let tree = build_tree(&points);
+
+        let mut group = c.benchmark_group("rtree_search");
+        group.bench_with_input(BenchmarkId::from_parameter(size), &tree, |b, tree| {
+            let mut i = 0;
+            b.iter(|| {
+                let (lon, lat) = queries[i % queries.len()];
+                i += 1;
+                let window = Rectangle::new(lon, lat, lon + SEARCH_WINDOW, lat + SEARCH_WINDOW);
+                black_box(tree.search_bbox(&window))
+            });
+        });
+        group.finish();
+
+        let mut group = c.benchmark_group("rtree_knn");
+        group.bench_with_input(BenchmarkId::from_parameter(size), &tree, |b, tree| {
+            let mut i = 0;
+            b.iter(|| {
+                let (lon, lat) = queries[i % queries.len()];
+                i += 1;
+                black_box(tree.nearby(lon, lat, K, None))
+            });
+        });
+        group.finish();
+    }
+}
+
+fn resp_command(args: &[&str]) -> Vec<u8> {
+    let mut frame = format!("*{}\r\n", args.len());
+    for arg in args {
+        frame.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+    }
+    frame.into_bytes()
+}
+
+fn bench_resp(c: &mut Criterion) {
+    let point = point_geojson(116.397, 39.908);
+    let polygon = polygon_geojson(POLYGON_VERTICES);
+    let set_point = resp_command(&["SET", "fleet", "truck1", &point]);
+    let set_polygon = resp_command(&["SET", "zones", "zone1", &polygon]);
+    // 100 条流水线命令，按帧依次解码
+    let pipeline: Vec<u8> = (0..100)
+        .flat_map(|i| resp_command(&["GET", "fleet", &format!("truck{}", i)]))
+        .collect();
+
+    let mut group = c.benchmark_group("resp_decode");
+    for (name, input) in [
+        ("set_point", &set_point),
+        ("set_polygon", &set_polygon),
+        ("pipeline_100", &pipeline),
+    ] {
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), input, |b, input| {
+            b.iter(|| {
+                let mut offset = 0;
+                while let Some((value, consumed)) = RespParser::decode(&input[offset..]).unwrap() {
+                    black_box(value);
+                    offset += consumed;
+                }
+                offset
+            });
+        });
+    }
+    group.finish();
+}
+
+fn bench_geojson(c: &mut Criterion) {
+    let inputs = [
+        ("point", point_geojson(116.397, 39.908)),
+        ("polygon_1000", polygon_geojson(POLYGON_VERTICES)),
+    ];
+
+    let mut group = c.benchmark_group("geojson_parse");
+    for (name, geojson) in &inputs {
+        group.bench_with_input(BenchmarkId::from_parameter(name), geojson, |b, geojson| {
+            b.iter(|| black_box(geojson_to_geometry(geojson).unwrap()));
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("geojson_serialize");
+    for (name, geojson) in &inputs {
+        let geometry = geojson_to_geometry(geojson).unwrap();
+        group.bench_with_input(
+            BenchmarkId::from_parameter(name),
+            &geometry,
+            |b, geometry| {
+                b.iter(|| black_box(geometry_to_geojson(geometry).to_string()));
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_rtree, bench_resp, bench_geojson);
+criterion_main!(benches);
@@ -0,0 +1,55 @@
+//! NEARBY (KNN) 查询基准测试：对比精确查询与不同 epsilon 的近似查询
+//!
+//! 运行: cargo bench --bench knn
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use spatio::RTree;
+
+const TREE_SIZE: usize = 200_000;
+const K: usize = 20;
+const EPSILONS: [f64; 4] = [0.0, 0.1, 0.5, 1.0];
+
+/// 在北京周边随机生成点数据构建 R-tree
+fn build_tree(size: usize) -> RTree {
+    let mut rng = StdRng::seed_from_u64(42);
+    let mut tree = RTree::new(16);
+
+    for i in 0..size {
+        let lon = rng.gen_range(115.0..118.0);
+        let lat = rng.gen_range(39.0..41.0);
+        let geojson = format!(r#"{{"type":"Point","coordinates":[{},{}]}}"#, lon, lat);
+        tree.insert_geojson(format!("item_{}", i), &geojson);
+    }
+
+    tree
+}
+
+fn bench_nearby_epsilon(c: &mut Criterion) {
+    let tree = build_tree(TREE_SIZE);
+
+    let mut rng = StdRng::seed_from_u64(7);
+    let queries: Vec<(f64, f64)> = (0..64)
+        .map(|_| (rng.gen_range(115.0..118.0), rng.gen_range(39.0..41.0)))
+        .collect();
+
+    let mut group = c.benchmark_group("nearby_epsilon");
+    for epsilon in EPSILONS {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(epsilon),
+            &epsilon,
+            |b, &epsilon| {
+                let mut i = 0;
+                b.iter(|| {
+                    let (lon, lat) = queries[i % queries.len()];
+                    i += 1;
+                    black_box(tree.nearby_approx(lon, lat, K, None, epsilon))
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_nearby_epsilon);
+criterion_main!(benches);
@@ -1,6 +1,11 @@
+use std::collections::BTreeSet;
 use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
 
+use spatio::client::replay::replay_aof;
 use spatio::client::{CliArgs, ClientConnection, OutputFormatter};
+use spatio::protocol::parser::RespValue;
 use spatio::Result;
 
 fn main() -> Result<()> {
@@ -14,36 +19,180 @@ fn main() -> Result<()> {
 
     // 创建连接
     let mut connection = ClientConnection::new(&args.host, args.port);
-
-    if args.should_run_interactive() {
+    let formatter = OutputFormatter::new(args.format, args.precision);
+
+    if args.postgis_table().is_some() {
+        // PostGIS 导入导出模式
+        run_postgis_mode(&args)?;
+    } else if let Some(path) = &args.replay_aof {
+        // AOF 重放模式
+        run_replay_mode(&mut connection, path, args.speed.unwrap_or(1.0))?;
+    } else if let Some(interval) = args.watch {
+        // 持续查询模式
+        run_watch_mode(
+            &mut connection,
+            &formatter,
+            &args.command,
+            Duration::from_secs_f64(interval),
+        )?;
+    } else if args.should_run_interactive() {
         // 交互模式
-        run_interactive_mode(&mut connection, &args.host, args.port)?;
+        run_interactive_mode(&mut connection, &formatter, &args.host, args.port)?;
     } else {
         // 直接命令模式
-        run_command_mode(&mut connection, &args.command)?;
+        run_command_mode(&mut connection, &formatter, &args.command)?;
     }
 
     Ok(())
 }
 
-fn run_command_mode(connection: &mut ClientConnection, command: &[String]) -> Result<()> {
+fn run_command_mode(
+    connection: &mut ClientConnection,
+    formatter: &OutputFormatter,
+    command: &[String],
+) -> Result<()> {
     // 连接到服务器
     connection.connect()?;
 
     // 执行命令
-    let response = connection.send_command(command)?;
+    let response = connection.request(command)?;
 
     // 格式化并输出结果
-    let formatted = OutputFormatter::format_response(&response);
+    let formatted = formatter.render(&response);
     println!("{}", formatted);
 
     // 断开连接
     connection.disconnect()?;
 
+    // 服务器返回错误时以非零状态退出，便于脚本判断
+    if matches!(response, RespValue::Error(_)) {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+fn run_watch_mode(
+    connection: &mut ClientConnection,
+    formatter: &OutputFormatter,
+    command: &[String],
+    interval: Duration,
+) -> Result<()> {
+    connection.connect()?;
+
+    let started = Instant::now();
+    let mut previous: Option<BTreeSet<String>> = None;
+    for iteration in 1.. {
+        match connection.request(command) {
+            Ok(response) => {
+                println!(
+                    "Every {:.1}s: {}    (#{}, {:.0}s elapsed)",
+                    interval.as_secs_f64(),
+                    command.join(" "),
+                    iteration,
+                    started.elapsed().as_secs_f64()
+                );
+                println!("{}", formatter.render(&response));
+
+                // 第一次只输出结果，之后输出与上一次相比的变化
+                let current = formatter.result_ids(&response);
+                if let Some(previous) = &previous {
+                    println!("{}", OutputFormatter::format_watch_diff(previous, &current));
+                }
+                println!();
+                previous = Some(current);
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                // 连接断开后在下一轮重连
+                if !connection.is_connected() {
+                    if let Err(e) = connection.connect() {
+                        eprintln!("Failed to reconnect: {}", e);
+                    }
+                }
+            }
+        }
+
+        std::thread::sleep(interval);
+    }
+
+    Ok(())
+}
+
+fn run_replay_mode(connection: &mut ClientConnection, path: &Path, speed: f64) -> Result<()> {
+    connection.connect()?;
+
+    let stats = replay_aof(path.to_path_buf(), connection, speed)?;
+    println!(
+        "Replayed {} commands in {:.2}s ({} errors, {} skipped lines, max lag {:.1}ms)",
+        stats.sent,
+        stats.elapsed.as_secs_f64(),
+        stats.errors,
+        stats.skipped,
+        stats.max_lag.as_secs_f64() * 1000.0
+    );
+
+    connection.disconnect()?;
     Ok(())
 }
 
-fn run_interactive_mode(connection: &mut ClientConnection, host: &str, port: u16) -> Result<()> {
+#[cfg(feature = "postgres")]
+fn run_postgis_mode(args: &CliArgs) -> Result<()> {
+    use spatio::client::postgis::{export_collection, import_table, PostgisTable};
+    use spatio::client::AsyncClientConnection;
+
+    let table = PostgisTable::new(args.postgis_table().unwrap_or_default())
+        .with_id_column(&args.id_column)
+        .with_geom_column(&args.geom_column)
+        .with_columns(args.columns.clone());
+    let url = args.postgres_url.as_deref().unwrap_or_default();
+    let collection = args.collection.as_deref().unwrap_or_default();
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let mut connection = AsyncClientConnection::connect(&args.host, args.port).await?;
+        let stats = if args.postgis_import.is_some() {
+            let stats =
+                import_table(url, &table, args.source_srid, &mut connection, collection).await?;
+            println!(
+                "Imported {} objects from {} into '{}' in {:.2}s ({} rejected)",
+                stats.migrated,
+                table.qualified_name(),
+                collection,
+                stats.elapsed.as_secs_f64(),
+                stats.failed
+            );
+            stats
+        } else {
+            let stats = export_collection(&mut connection, collection, url, &table).await?;
+            println!(
+                "Exported {} objects from '{}' into {} in {:.2}s",
+                stats.migrated,
+                collection,
+                table.qualified_name(),
+                stats.elapsed.as_secs_f64()
+            );
+            stats
+        };
+        if let Some(error) = &stats.last_error {
+            eprintln!("Last rejected object: {}", error);
+        }
+        connection.disconnect().await?;
+        Ok(())
+    })
+}
+
+#[cfg(not(feature = "postgres"))]
+fn run_postgis_mode(_args: &CliArgs) -> Result<()> {
+    Err("spatio-cli was built without PostGIS support, rebuild with --features postgres".into())
+}
+
+fn run_interactive_mode(
+    connection: &mut ClientConnection,
+    formatter: &OutputFormatter,
+    host: &str,
+    port: u16,
+) -> Result<()> {
     println!("spatio-cli interactive mode");
     println!("{}", OutputFormatter::format_connecting_message(host, port));
 
@@ -106,7 +255,7 @@ fn run_interactive_mode(connection: &mut ClientConnection, host: &str, port: u16
                         // 执行普通命令
                         match connection.send_command(&parts) {
                             Ok(response) => {
-                                println!("{}", OutputFormatter::format_response(&response));
+                                println!("{}", formatter.render(&response));
                             }
                             Err(e) => {
                                 eprintln!("Error: {}", e);
@@ -175,11 +324,18 @@ fn parse_command_line(input: &str) -> Vec<String> {
                     let id = &remaining[..space_pos];
                     let geojson = &remaining[space_pos + 1..];
 
-                    // 移除 geojson 外层的引号（如果有）
-                    let geojson = remove_outer_quotes(geojson);
-
                     result.push(id.to_string());
-                    result.push(geojson.to_string());
+                    // HASH geohash 中没有 JSON，按空白分割
+                    let is_hash = geojson
+                        .split_whitespace()
+                        .next()
+                        .is_some_and(|word| word.eq_ignore_ascii_case("HASH"));
+                    if is_hash {
+                        result.extend(geojson.split_whitespace().map(str::to_string));
+                    } else {
+                        // 移除 geojson 外层的引号（如果有）
+                        result.push(remove_outer_quotes(geojson).to_string());
+                    }
                 } else {
                     // 只有 id，没有 geojson
                     result.push(remaining.to_string());
@@ -1,15 +1,46 @@
 use clap::Parser;
-use spatio::server::TcpServer;
+use spatio::rtree::algorithms::knn::DistanceMetric;
+use spatio::server::{systemd, PidFile, TcpServer, TrackingAllocator};
+use spatio::storage::DataDirLock;
 use spatio::{Result, SpatioConfig};
+use std::path::PathBuf;
 use tracing::{info, Level};
 
+/// 统计分配字节数，INFO memory 和 MEMORY STATS 的数据来源；
+/// `jemalloc` / `mimalloc` feature 替换被包装的分配器，INFO 的 `allocator` 字段显示其名称
+#[cfg(feature = "jemalloc")]
+#[global_allocator]
+static GLOBAL: TrackingAllocator<tikv_jemallocator::Jemalloc> =
+    TrackingAllocator::new(tikv_jemallocator::Jemalloc, "jemalloc");
+
+#[cfg(all(feature = "mimalloc", not(feature = "jemalloc")))]
+#[global_allocator]
+static GLOBAL: TrackingAllocator<mimalloc::MiMalloc> =
+    TrackingAllocator::new(mimalloc::MiMalloc, "mimalloc");
+
+#[cfg(not(any(feature = "jemalloc", feature = "mimalloc")))]
+#[global_allocator]
+static GLOBAL: TrackingAllocator = TrackingAllocator::system();
+
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    after_help = "Any configuration value can be overridden with --<section>.<key> <value>,\n\
+                  e.g. --server.port 7000 --aof.sync-policy always (highest priority,\n\
+                  above SPATIO__* environment variables and the config file)."
+)]
 struct Args {
     /// 配置文件路径
     #[arg(short, long, default_value = "spatio.toml")]
     config: String,
 
+    /// 选择配置文件中的 `[profile.<name>]`，合并在基础配置之上（也可用 SPATIO_PROFILE 环境变量）
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
     /// 生成默认配置文件并退出
     #[arg(long)]
     generate_config: bool,
@@ -25,11 +56,33 @@ struct Args {
     /// Log level (overrides config file)
     #[arg(long)]
     log_level: Option<String>,
+
+    /// 纯内存模式：关闭 AOF，不创建也不锁定数据目录，重启后数据丢失（用于 CI 和缓存部署）
+    #[arg(long)]
+    ephemeral: bool,
+
+    /// 演练恢复：将 AOF 重放到临时内存数据库，报告统计与不一致后退出（不修改数据文件）
+    ///
+    /// 未指定文件时使用配置中的 AOF 文件
+    #[arg(long, value_name = "AOF_FILE", num_args = 0..=1)]
+    verify_recovery: Option<Option<PathBuf>>,
+
+    /// 检查配置：报告未知的键并验证配置，打印合并后的最终配置后退出（不创建任何文件）
+    #[arg(long)]
+    check_config: bool,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = Args::parse();
+    // `--section.key value` 形式的配置覆盖在 clap 之前分离出来
+    let (cli_args, overrides) = match spatio::config::split_overrides(std::env::args()) {
+        Ok(split) => split,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            std::process::exit(2);
+        }
+    };
+    let args = Args::parse_from(cli_args);
 
     // 生成默认配置文件
     if args.generate_config {
@@ -40,8 +93,14 @@ async fn main() -> Result<()> {
         return Ok(());
     }
 
-    // 加载配置
-    let mut config = SpatioConfig::from_file(&args.config)?;
+    // 加载配置（拼写错误的键会被忽略，启动后以警告的形式报告）
+    let profile = args
+        .profile
+        .clone()
+        .or_else(|| std::env::var("SPATIO_PROFILE").ok())
+        .filter(|name| !name.is_empty());
+    let (mut config, unknown_keys) =
+        SpatioConfig::load_with_warnings(&args.config, profile.as_deref(), &overrides)?;
 
     // 命令行参数覆盖配置文件
     if let Some(host) = args.host {
@@ -53,23 +112,78 @@ async fn main() -> Result<()> {
     if let Some(log_level) = args.log_level {
         config.logging.level = log_level;
     }
+    if args.ephemeral {
+        config.set_ephemeral();
+    }
+
+    if args.check_config {
+        std::process::exit(
+            if check_config(&config, profile.as_deref(), &unknown_keys)? {
+                0
+            } else {
+                1
+            },
+        );
+    }
+
+    // 演练恢复：在验证配置之前执行，避免创建数据目录
+    if let Some(path) = args.verify_recovery {
+        let path = path.unwrap_or_else(|| config.aof.filename.clone());
+        let clean = verify_recovery(path).await?;
+        std::process::exit(if clean { 0 } else { 1 });
+    }
 
-    // 验证配置
+    // 验证配置并创建数据目录
     config.validate()?;
+    config.prepare_directories()?;
+
+    // 锁定数据目录（以及不在数据目录下的 AOF 所在目录），防止两个实例同时追加同一个 AOF。
+    // 锁在进程退出时由操作系统释放；纯内存模式不使用数据目录
+    let _data_dir_locks = if config.storage.ephemeral {
+        Vec::new()
+    } else {
+        match lock_data_dirs(&config) {
+            Ok(locks) => locks,
+            Err(e) => {
+                eprintln!("❌ {}", e);
+                std::process::exit(1);
+            }
+        }
+    };
 
     // 初始化日志系统
     init_logging(&config.logging);
+    for key in &unknown_keys {
+        tracing::warn!("⚠️  {}", key);
+    }
+
+    // PID 文件在拿到数据目录锁之后写入，不会覆盖正在运行的实例的 PID
+    let _pid_file = match &config.server.pidfile {
+        Some(path) => match PidFile::create(path) {
+            Ok(pid_file) => Some(pid_file),
+            Err(e) => {
+                eprintln!("❌ Failed to write PID file {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
 
     info!("🚀 Starting Spatio server...");
     info!("📦 Version: {}", env!("CARGO_PKG_VERSION"));
+    if let Some(profile) = &profile {
+        info!("🗂️  Config profile: {}", profile);
+    }
     println!();
 
     // 打印配置摘要
     config.print_summary();
 
     // 创建数据库实例
-    let _db = if config.aof.enabled {
-        use spatio::rtree::algorithms::aof::{AofConfig as AofWriterConfig, AofSyncPolicy};
+    let mut _db = if config.aof.enabled {
+        use spatio::rtree::algorithms::aof::{
+            AofConfig as AofWriterConfig, AofFormat, AofSyncPolicy,
+        };
 
         // 转换同步策略
         let sync_policy = match config.aof.sync_policy.as_str() {
@@ -79,47 +193,312 @@ async fn main() -> Result<()> {
             _ => AofSyncPolicy::EverySecond,
         };
 
-        let aof_config =
-            AofWriterConfig::new(config.aof.filename.clone()).set_sync_policy(sync_policy);
+        let format = match config.aof.format.as_str() {
+            "framed" => AofFormat::Framed,
+            _ => AofFormat::JsonLines,
+        };
+
+        let mut aof_config = AofWriterConfig::new(config.aof.filename.clone())
+            .set_sync_policy(sync_policy)
+            .with_format(format)
+            .with_flush_thresholds(config.aof.flush_bytes, config.aof.flush_records);
+        if config.aof.stall_threshold_ms > 0 {
+            aof_config = aof_config.with_stall_detection(
+                std::time::Duration::from_millis(config.aof.stall_threshold_ms),
+                config.aof.stall_fallback,
+            );
+        }
+        if config.aof.group_commit_ms > 0 {
+            aof_config = aof_config
+                .with_group_commit(std::time::Duration::from_millis(config.aof.group_commit_ms));
+        }
+        if config.aof.auto_rewrite_enabled {
+            aof_config = aof_config.with_auto_rewrite(
+                config.aof.auto_rewrite_min_size * 1024 * 1024,
+                config.aof.auto_rewrite_percentage,
+            );
+        }
 
         info!(
             "💾 AOF enabled with sync policy: {}",
             config.aof.sync_policy
         );
 
-        let db = spatio::storage::GeoDatabase::with_aof(aof_config)?;
-
-        // 从 AOF 恢复数据
+        // 在打开 AOF writer 之前处理写了一半的末尾记录，否则新记录会接在损坏的记录后面
         if config.aof.filename.exists() {
-            info!("📖 Recovering from AOF file...");
-            let (commands, errors) = db.recover_from_aof(config.aof.filename.clone()).await?;
-
-            if errors > 0 {
-                tracing::warn!("⚠️  Recovered {} commands with {} errors", commands, errors);
-            } else {
-                info!("✅ Successfully recovered {} commands", commands);
+            if let Err(e) = check_aof_tail(&config) {
+                eprintln!("❌ {}", e);
+                std::process::exit(1);
             }
         }
 
-        db
-    } else {
-        info!("⚠️  AOF disabled - data will not be persisted");
+        spatio::storage::GeoDatabase::with_aof(aof_config)?
+            .with_snapshot_path(config.snapshot.path.clone())
+    } else if config.storage.ephemeral {
+        info!("🫧 Ephemeral mode - in-memory only, recovery and persistence are skipped");
         spatio::storage::GeoDatabase::new()
+    } else {
+        info!("⚠️  AOF disabled - only snapshots (SAVE/BGSAVE) are persisted");
+        spatio::storage::GeoDatabase::new().with_snapshot_path(config.snapshot.path.clone())
     };
 
+    // 空闲 collection 卸载（恢复的 collection 从创建时开始计算空闲时间，卸载任务在加载完成后才启动）
+    if config.storage.unload_idle_minutes > 0 {
+        let unload_config = spatio::storage::UnloadConfig::new(
+            std::time::Duration::from_secs(config.storage.unload_idle_minutes * 60),
+            config.storage.data_dir.join("cold"),
+        );
+        _db = _db.with_idle_unloading(unload_config)?;
+        info!(
+            "🧊 Idle collections are unloaded after {} minutes",
+            config.storage.unload_idle_minutes
+        );
+    }
+
+    // 磁盘空间监控：启动时先检查一次，之后由服务器定期刷新
+    if config.storage.min_free_disk_mb > 0 {
+        let disk_config = spatio::storage::DiskConfig::new(
+            config.storage.data_dir.clone(),
+            config.storage.min_free_disk_mb * 1024 * 1024,
+        );
+        _db = _db.with_disk_monitor(spatio::storage::DiskMonitor::new(disk_config));
+        match _db.refresh_disk_space() {
+            Ok(()) => info!(
+                "💽 Writes are refused when free disk space drops below {} MB",
+                config.storage.min_free_disk_mb
+            ),
+            Err(e) => tracing::warn!("⚠️  Failed to check free disk space: {}", e),
+        }
+    }
+
+    if let Some(precision) = config.server.output_precision {
+        _db = _db.with_output_precision(precision);
+        info!("📐 Output coordinates rounded to {} decimals", precision);
+    }
+    // validate() 已检查过名称
+    if let Some(metric) = DistanceMetric::parse(&config.server.distance_metric) {
+        _db = _db.with_distance_metric(metric);
+        if metric != DistanceMetric::Haversine {
+            info!("📏 NEARBY distances default to {}", metric.name());
+        }
+    }
+    _db = _db.with_stats_retention(config.server.stats_retention_hours);
+    if !config.storage.ephemeral {
+        _db = _db.with_export_dir(config.storage.data_dir.join("export"));
+    }
+    if !config.storage.strict_geojson.is_empty() {
+        info!(
+            "📏 RFC 7946 strict mode for collections: {}",
+            config.storage.strict_geojson.join(", ")
+        );
+        _db = _db.with_strict_geojson(config.storage.strict_geojson.clone());
+    }
+    if config.server.debug_testing {
+        _db = _db.with_debug_testing(true);
+        tracing::warn!("🧪 DEBUG SET-ACTIVE-EXPIRE and DEBUG SLEEP are enabled");
+    }
+
+    info!(
+        "🧩 Features: {}",
+        spatio::commands::features::enabled_features(&_db).join(", ")
+    );
+
     info!(
         "🌐 Server listening on {}:{}",
         config.server.host, config.server.port
     );
     println!();
 
-    // 启动服务器（传入配置和数据库实例）
-    let server = TcpServer::new(config, _db);
-    server.start().await?;
+    // 启动服务器（传入配置和数据库实例），监听端口之后再加载快照和重放 AOF
+    let server = TcpServer::new(config.clone(), _db);
+    server
+        .start_loading(recover(server.database(), &config))
+        .await?;
 
     Ok(())
 }
 
+/// 启动加载：先加载快照，再从 AOF 恢复快照之后的写入
+async fn recover(db: &spatio::storage::GeoDatabase, config: &SpatioConfig) -> Result<()> {
+    if config.storage.ephemeral && !config.aof.enabled {
+        return Ok(());
+    }
+    load_snapshot(db, config).await?;
+    if config.aof.enabled && config.aof.filename.exists() {
+        info!("📖 Recovering from AOF file...");
+        let _ = systemd::notify_status("Recovering from AOF");
+        let (commands, errors) = db.recover_from_aof(config.aof.filename.clone()).await?;
+
+        if errors > 0 {
+            tracing::warn!("⚠️  Recovered {} commands with {} errors", commands, errors);
+        } else {
+            info!("✅ Successfully recovered {} commands", commands);
+        }
+    }
+    Ok(())
+}
+
+/// 报告未知的键、验证配置并打印合并后的最终配置，返回配置是否有效
+///
+/// 未知的键只是警告，不影响结果
+fn check_config(
+    config: &SpatioConfig,
+    profile: Option<&str>,
+    unknown_keys: &[spatio::config::UnknownKey],
+) -> Result<bool> {
+    if let Some(profile) = profile {
+        println!("🗂️  Using profile '{}'", profile);
+    }
+    for key in unknown_keys {
+        println!("⚠️  {}", key);
+    }
+
+    let valid = match config.validate() {
+        Ok(()) => {
+            println!("✅ Configuration is valid");
+            true
+        }
+        Err(e) => {
+            println!("❌ Invalid configuration: {}", e);
+            false
+        }
+    };
+
+    println!();
+    println!("# Effective configuration (defaults < config file < profile < SPATIO__* env < command line)");
+    print!(
+        "{}",
+        toml::to_string_pretty(config).map_err(|e| format!("Failed to serialize config: {}", e))?
+    );
+    Ok(valid)
+}
+
+/// 加载快照文件（如果存在）
+async fn load_snapshot(db: &spatio::storage::GeoDatabase, config: &SpatioConfig) -> Result<()> {
+    if !config.snapshot.path.exists() {
+        return Ok(());
+    }
+
+    info!("📖 Loading snapshot {}...", config.snapshot.path.display());
+    let _ = systemd::notify_status("Loading snapshot");
+    match db.load_snapshot(&config.snapshot.path).await? {
+        Some(summary) => info!(
+            "✅ Loaded {} collections ({} objects) from snapshot",
+            summary.collections, summary.objects
+        ),
+        None => {
+            info!("⏭️  Snapshot is older than the last AOF rewrite, recovering from the AOF only")
+        }
+    }
+    Ok(())
+}
+
+/// 锁定数据目录；AOF 文件和快照文件不在数据目录下时同时锁定其所在目录
+fn lock_data_dirs(config: &SpatioConfig) -> Result<Vec<DataDirLock>> {
+    let mut dirs = vec![config.storage.data_dir.canonicalize()?];
+    let mut files = vec![&config.snapshot.path];
+    if config.aof.enabled {
+        files.push(&config.aof.filename);
+    }
+    for file in files {
+        let dir = match file.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.canonicalize()?,
+            _ => std::env::current_dir()?,
+        };
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+
+    let mut locks = Vec::with_capacity(dirs.len());
+    for dir in dirs {
+        locks.push(DataDirLock::acquire(&dir)?);
+    }
+    Ok(locks)
+}
+
+/// 检查 AOF 末尾的损坏记录：`load_truncated` 时截断并继续，否则返回错误拒绝启动
+fn check_aof_tail(config: &SpatioConfig) -> Result<()> {
+    use spatio::rtree::algorithms::aof::{find_corrupted_tail, truncate_corrupted_tail};
+
+    let path = &config.aof.filename;
+    if !config.aof.load_truncated {
+        return match find_corrupted_tail(path)? {
+            Some(tail) => Err(format!(
+                "AOF file {} ends with a corrupted record at byte {} ({} bytes); \
+                 set aof.load_truncated = true to truncate it automatically, \
+                 or truncate the file to {} bytes by hand",
+                path.display(),
+                tail.offset,
+                tail.discarded_bytes,
+                tail.offset
+            )
+            .into()),
+            None => Ok(()),
+        };
+    }
+
+    if let Some(tail) = truncate_corrupted_tail(path)? {
+        tracing::warn!("!!! ============================================================ !!!");
+        tracing::warn!(
+            "!!! AOF {} ended with a corrupted record (likely a torn write after power loss)",
+            path.display()
+        );
+        tracing::warn!(
+            "!!! Truncated {} bytes at offset {}; the last write before the crash is lost",
+            tail.discarded_bytes,
+            tail.offset
+        );
+        tracing::warn!("!!! ============================================================ !!!");
+    }
+    Ok(())
+}
+
+/// 将 AOF 重放到临时内存数据库并打印恢复报告，返回是否完全一致
+async fn verify_recovery(path: PathBuf) -> Result<bool> {
+    if !path.exists() {
+        eprintln!("❌ AOF file not found: {}", path.display());
+        return Ok(false);
+    }
+
+    println!("🔍 Verifying recovery from {}", path.display());
+
+    // 不带 AOF writer 的内存数据库，重放不会写回任何文件
+    let db = spatio::storage::GeoDatabase::new();
+    let report = db.recover_from_aof_with_report(path).await?;
+    let stats = db.stats().await?;
+
+    println!("   Lines:           {}", report.total_lines);
+    println!("   Commands:        {}", report.commands);
+    println!("   Skipped:         {}", report.skipped);
+    println!("   Last sequence:   {}", report.last_seq);
+    println!("   Parse errors:    {}", report.parse_errors.len());
+    println!("   Inconsistencies: {}", report.inconsistencies.len());
+    if let Some(tail) = report.corrupted_tail {
+        println!(
+            "   Torn tail:       {} bytes at offset {} (truncated on startup)",
+            tail.discarded_bytes, tail.offset
+        );
+    }
+    println!("   Collections:     {}", stats.collections_count);
+    println!("   Objects:         {}", stats.total_items);
+
+    for error in &report.parse_errors {
+        println!("   ✗ parse error: {}", error);
+    }
+    for issue in &report.inconsistencies {
+        println!("   ✗ {}", issue);
+    }
+
+    if report.is_clean() {
+        println!("✅ Recovery verification passed");
+    } else {
+        println!("⚠️  Recovery verification found problems");
+    }
+
+    Ok(report.is_clean())
+}
+
 /// 初始化日志系统
 fn init_logging(config: &spatio::config::LoggingConfig) {
     use tracing_subscriber::layer::SubscriberExt;
@@ -0,0 +1,57 @@
+//! 构建脚本：启用 `grpc` feature 时根据 `proto/spatio.proto` 生成 tonic 服务端和客户端代码
+//!
+//! 只用 `tonic_build::manual` 生成服务部分，构建环境不需要 protoc；
+//! 消息类型在 `server/grpc/pb.rs` 中用 prost 的 derive 定义，字段编号与 proto 文件保持一致
+
+fn main() {
+    #[cfg(feature = "grpc")]
+    grpc::generate();
+}
+
+#[cfg(feature = "grpc")]
+mod grpc {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    /// (方法名, 路由名, 请求类型, 响应类型, 是否为服务端流)
+    const METHODS: &[(&str, &str, &str, &str, bool)] = &[
+        ("set", "Set", "SetRequest", "SetResponse", false),
+        ("get", "Get", "GetRequest", "GetResponse", false),
+        ("delete", "Delete", "DeleteRequest", "DeleteResponse", false),
+        (
+            "intersects",
+            "Intersects",
+            "IntersectsRequest",
+            "GeoObject",
+            true,
+        ),
+        ("nearby", "Nearby", "NearbyRequest", "NearbyResult", true),
+        (
+            "subscribe",
+            "Subscribe",
+            "SubscribeRequest",
+            "SubscribeEvent",
+            true,
+        ),
+    ];
+
+    pub fn generate() {
+        println!("cargo:rerun-if-changed=proto/spatio.proto");
+
+        let mut service = Service::builder().name("Spatio").package("spatio.v1");
+        for &(name, route, input, output, streaming) in METHODS {
+            let mut method = Method::builder()
+                .name(name)
+                .route_name(route)
+                .input_type(format!("super::{}", input))
+                .output_type(format!("super::{}", output))
+                .codec_path("tonic::codec::ProstCodec");
+            if streaming {
+                method = method.server_streaming();
+            }
+            service = service.method(method.build());
+        }
+
+        // 生成 $OUT_DIR/spatio.v1.Spatio.rs，由 server/grpc/pb.rs include
+        Builder::new().compile(&[service.build()]);
+    }
+}
@@ -0,0 +1,215 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+
+use crate::client::{ClientConnection, ClientError};
+use crate::protocol::parser::{RespHeader, RespValue};
+use crate::protocol::RespParser;
+
+type ClientResult<T> = std::result::Result<T, ClientError>;
+
+/// 异步客户端连接
+///
+/// 除了一次性读取完整回复，还支持以流的方式逐个读取数组元素，
+/// 适合 INTERSECTS / NEARBY 这类可能返回大量结果的命令。
+pub struct AsyncClientConnection {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+    /// 上一个流式回复中尚未读取的元素数量
+    unread: usize,
+}
+
+impl AsyncClientConnection {
+    pub async fn connect(host: &str, port: u16) -> ClientResult<Self> {
+        let stream = TcpStream::connect(format!("{}:{}", host, port)).await?;
+        let (reader, writer) = stream.into_split();
+        Ok(Self {
+            reader: BufReader::new(reader),
+            writer,
+            unread: 0,
+        })
+    }
+
+    /// 发送命令并返回完整的原始回复，错误回复以 `RespValue::Error` 形式返回
+    pub async fn send_command(&mut self, cmd: &[String]) -> ClientResult<RespValue> {
+        self.write_command(cmd).await?;
+        self.read_value().await
+    }
+
+    /// 发送命令，服务器的错误回复转换为 `Err(ClientError::Server)`
+    pub async fn query(&mut self, cmd: &[String]) -> ClientResult<RespValue> {
+        match self.send_command(cmd).await? {
+            RespValue::Error(message) => Err(ClientError::Server(message)),
+            value => Ok(value),
+        }
+    }
+
+    /// 发送命令并以流的方式读取回复
+    ///
+    /// 数组回复会逐个元素返回，其他回复作为单个元素返回。
+    /// 顶层错误回复直接返回 `Err(ClientError::Server)`。
+    /// 未读完的流在下一次发送命令前会被自动丢弃。
+    pub async fn stream_command(&mut self, cmd: &[String]) -> ClientResult<ReplyStream<'_>> {
+        self.write_command(cmd).await?;
+
+        let (remaining, first) = match self.read_header().await? {
+            RespHeader::Value(RespValue::Error(message)) => {
+                return Err(ClientError::Server(message))
+            }
+            RespHeader::Value(RespValue::Array(None)) => (0, None),
+            RespHeader::Array(len) => (len, None),
+            RespHeader::Value(value) => (0, Some(value)),
+            RespHeader::Bulk(len) => (0, Some(self.read_bulk(len).await?)),
+            header => (0, Some(self.read_aggregate(header).await?)),
+        };
+
+        self.unread = remaining;
+        Ok(ReplyStream { conn: self, first })
+    }
+
+    pub async fn disconnect(mut self) -> ClientResult<()> {
+        self.writer.shutdown().await?;
+        Ok(())
+    }
+
+    async fn write_command(&mut self, cmd: &[String]) -> ClientResult<()> {
+        // 丢弃上一个未读完的流式回复，保证请求和回复一一对应
+        while self.unread > 0 {
+            self.read_value().await?;
+            self.unread -= 1;
+        }
+
+        let command = ClientConnection::build_resp_command(cmd);
+        self.writer.write_all(command.as_bytes()).await?;
+        Ok(())
+    }
+
+    async fn read_header(&mut self) -> ClientResult<RespHeader> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line).await? == 0 {
+            return Err(ClientError::Closed);
+        }
+        RespParser::parse_header(&line).map_err(|e| ClientError::Protocol(e.to_string()))
+    }
+
+    async fn read_bulk(&mut self, len: usize) -> ClientResult<RespValue> {
+        // 内容加结尾的 \r\n
+        let mut buf = vec![0; len + 2];
+        self.reader.read_exact(&mut buf).await?;
+        buf.truncate(len);
+        Ok(RespValue::BulkString(Some(buf)))
+    }
+
+    fn read_value(&mut self) -> Pin<Box<dyn Future<Output = ClientResult<RespValue>> + Send + '_>> {
+        Box::pin(async move {
+            match self.read_header().await? {
+                RespHeader::Value(value) => Ok(value),
+                RespHeader::Bulk(len) => self.read_bulk(len).await,
+                header => self.read_aggregate(header).await,
+            }
+        })
+    }
+
+    async fn read_aggregate(&mut self, header: RespHeader) -> ClientResult<RespValue> {
+        let len = header.element_count().unwrap_or(0);
+        let mut values = Vec::with_capacity(len.min(64));
+        for _ in 0..len {
+            values.push(self.read_value().await?);
+        }
+        Ok(header.into_aggregate(values))
+    }
+}
+
+/// 流式回复：逐个读取数组元素
+pub struct ReplyStream<'a> {
+    conn: &'a mut AsyncClientConnection,
+    first: Option<RespValue>,
+}
+
+impl ReplyStream<'_> {
+    /// 剩余未读取的元素数量
+    pub fn remaining(&self) -> usize {
+        self.conn.unread + self.first.is_some() as usize
+    }
+
+    /// 读取下一个元素，读完后返回 None；元素为错误回复时返回 `Err(ClientError::Server)`
+    pub async fn next(&mut self) -> Option<ClientResult<RespValue>> {
+        let value = if let Some(value) = self.first.take() {
+            Ok(value)
+        } else if self.conn.unread > 0 {
+            self.conn.unread -= 1;
+            self.conn.read_value().await
+        } else {
+            return None;
+        };
+
+        Some(value.and_then(|value| match value {
+            RespValue::Error(message) => Err(ClientError::Server(message)),
+            value => Ok(value),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_stream_command() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+
+            // 流式读取的数组回复（只会读取一部分）
+            let _ = socket.read(&mut buf).await.unwrap();
+            socket
+                .write_all(b"*3\r\n$3\r\none\r\n*2\r\n$3\r\ntwo\r\n:2\r\n$5\r\nthree\r\n")
+                .await
+                .unwrap();
+
+            // 错误回复
+            let _ = socket.read(&mut buf).await.unwrap();
+            socket.write_all(b"-ERR nope\r\n").await.unwrap();
+
+            // 普通回复
+            let _ = socket.read(&mut buf).await.unwrap();
+            socket.write_all(b"+PONG\r\n").await.unwrap();
+        });
+
+        let mut conn = AsyncClientConnection::connect("127.0.0.1", port)
+            .await
+            .unwrap();
+
+        let mut stream = conn.stream_command(&["KEYS".to_string()]).await.unwrap();
+        assert_eq!(stream.remaining(), 3);
+        assert_eq!(
+            stream.next().await.unwrap().unwrap(),
+            RespValue::bulk("one")
+        );
+        assert_eq!(
+            stream.next().await.unwrap().unwrap(),
+            RespValue::Array(Some(vec![RespValue::bulk("two"), RespValue::Integer(2),]))
+        );
+        assert_eq!(stream.remaining(), 1);
+        // 剩余的元素在下一次请求前被丢弃
+
+        let err = conn
+            .stream_command(&["NOPE".to_string()])
+            .await
+            .err()
+            .unwrap();
+        assert!(err.is_server_error());
+
+        let reply = conn.query(&["PING".to_string()]).await.unwrap();
+        assert_eq!(reply, RespValue::SimpleString("PONG".to_string()));
+
+        server.await.unwrap();
+    }
+}
@@ -1,5 +1,9 @@
+use std::path::PathBuf;
+
 use clap::Parser;
 
+use crate::client::formatter::OutputFormat;
+
 #[derive(Parser, Debug)]
 #[command(
     name = "spatio-cli",
@@ -19,6 +23,62 @@ pub struct CliArgs {
     #[arg(short = 'i', long = "interactive")]
     pub interactive: bool,
 
+    /// Output format for query results
+    #[arg(long = "format", value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+
+    /// Round coordinates in GeoJSON output to N decimal places
+    #[arg(long = "precision", value_name = "N")]
+    pub precision: Option<usize>,
+
+    /// Re-run the command every SECONDS and show which ids were added or removed
+    #[arg(long = "watch", value_name = "SECONDS")]
+    pub watch: Option<f64>,
+
+    /// Replay the write commands of an AOF file against the server, keeping the recorded pace
+    #[arg(long = "replay-aof", value_name = "FILE")]
+    pub replay_aof: Option<PathBuf>,
+
+    /// Replay speed multiplier for --replay-aof (2 = twice as fast, 0 = as fast as possible)
+    #[arg(long = "speed", value_name = "MULTIPLIER")]
+    pub speed: Option<f64>,
+
+    /// Import a PostGIS table (optionally schema-qualified) into --collection
+    #[arg(
+        long = "postgis-import",
+        value_name = "TABLE",
+        conflicts_with = "postgis_export"
+    )]
+    pub postgis_import: Option<String>,
+
+    /// Export --collection into an existing PostGIS table, upserting rows by id
+    #[arg(long = "postgis-export", value_name = "TABLE")]
+    pub postgis_export: Option<String>,
+
+    /// PostgreSQL connection URL for --postgis-import / --postgis-export
+    #[arg(long = "postgres-url", value_name = "URL")]
+    pub postgres_url: Option<String>,
+
+    /// Collection to import into or export from
+    #[arg(long = "collection", value_name = "NAME")]
+    pub collection: Option<String>,
+
+    /// Table column holding the object id
+    #[arg(long = "id-column", value_name = "COLUMN", default_value = "id")]
+    pub id_column: String,
+
+    /// Table column holding the geometry
+    #[arg(long = "geom-column", value_name = "COLUMN", default_value = "geom")]
+    pub geom_column: String,
+
+    /// Comma-separated table columns migrated as GeoJSON properties
+    #[arg(long = "columns", value_name = "COLUMNS", value_delimiter = ',')]
+    pub columns: Vec<String>,
+
+    /// SRID of the table geometries when the column has none set (SRID 0)
+    #[arg(long = "source-srid", value_name = "SRID")]
+    pub source_srid: Option<i32>,
+
     /// Command to execute (if not in interactive mode)
     #[arg(trailing_var_arg = true)]
     pub command: Vec<String>,
@@ -30,6 +90,56 @@ impl CliArgs {
     }
 
     pub fn validate(&self) -> Result<(), String> {
+        if let Some(speed) = self.speed {
+            if !speed.is_finite() || speed < 0.0 {
+                return Err("Replay speed must be a non-negative number".to_string());
+            }
+            if self.replay_aof.is_none() {
+                return Err("--speed requires --replay-aof".to_string());
+            }
+        }
+
+        if let Some(table) = self.postgis_table() {
+            if self.replay_aof.is_some()
+                || self.interactive
+                || !self.command.is_empty()
+                || self.watch.is_some()
+            {
+                return Err(
+                    "--postgis-import/--postgis-export cannot be combined with a command or another mode"
+                        .to_string(),
+                );
+            }
+            if table.is_empty() {
+                return Err("PostGIS table name must not be empty".to_string());
+            }
+            if self.postgres_url.is_none() {
+                return Err("--postgres-url is required for PostGIS migration".to_string());
+            }
+            if self.collection.as_deref().is_none_or(str::is_empty) {
+                return Err("--collection is required for PostGIS migration".to_string());
+            }
+            if self.source_srid.is_some() && self.postgis_export.is_some() {
+                return Err("--source-srid only applies to --postgis-import".to_string());
+            }
+            if self.port == 0 {
+                return Err("Port must be greater than 0".to_string());
+            }
+            return Ok(());
+        }
+
+        if self.replay_aof.is_some() {
+            if self.interactive || !self.command.is_empty() || self.watch.is_some() {
+                return Err(
+                    "--replay-aof cannot be combined with a command or another mode".to_string(),
+                );
+            }
+            if self.port == 0 {
+                return Err("Port must be greater than 0".to_string());
+            }
+            return Ok(());
+        }
+
         if !self.interactive && self.command.is_empty() {
             return Err(
                 "No command specified. Use -i for interactive mode or provide a command."
@@ -41,9 +151,25 @@ impl CliArgs {
             return Err("Port must be greater than 0".to_string());
         }
 
+        if let Some(interval) = self.watch {
+            if !interval.is_finite() || interval <= 0.0 {
+                return Err("Watch interval must be a positive number of seconds".to_string());
+            }
+            if self.command.is_empty() {
+                return Err("--watch requires a command to execute".to_string());
+            }
+        }
+
         Ok(())
     }
 
+    /// PostGIS 迁移的表名（导入或导出）
+    pub fn postgis_table(&self) -> Option<&str> {
+        self.postgis_import
+            .as_deref()
+            .or(self.postgis_export.as_deref())
+    }
+
     pub fn should_run_interactive(&self) -> bool {
         self.interactive || self.command.is_empty()
     }
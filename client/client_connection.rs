@@ -1,12 +1,13 @@
-use std::io::{Read, Write};
+use std::io::{BufRead, BufReader, Write};
 use std::net::TcpStream;
 
+use crate::client::ClientError;
 use crate::protocol::parser::RespValue;
 use crate::protocol::RespParser;
 use crate::Result;
 
 pub struct ClientConnection {
-    stream: Option<TcpStream>,
+    stream: Option<BufReader<TcpStream>>,
     host: String,
     port: u16,
 }
@@ -21,52 +22,70 @@ impl ClientConnection {
     }
 
     pub fn connect(&mut self) -> Result<()> {
+        self.open()?;
+        Ok(())
+    }
+
+    fn open(&mut self) -> std::io::Result<()> {
         let addr = format!("{}:{}", self.host, self.port);
         let stream = TcpStream::connect(&addr)?;
-        self.stream = Some(stream);
+        self.stream = Some(BufReader::new(stream));
         Ok(())
     }
 
+    /// 发送命令并返回原始回复，错误回复以 `RespValue::Error` 形式返回（用于格式化输出）
     pub fn send_command(&mut self, cmd: &[String]) -> Result<RespValue> {
+        Ok(self.request(cmd)?)
+    }
+
+    /// 发送命令并返回原始回复，连接错误使用 [`ClientError`] 区分
+    ///
+    /// 连接层面的错误（IO、协议错误、连接关闭）会断开连接，`is_connected` 随之变为 false
+    pub fn request(&mut self, cmd: &[String]) -> std::result::Result<RespValue, ClientError> {
         if self.stream.is_none() {
-            self.connect()?;
+            self.open()?;
         }
 
-        // 构建 RESP 命令
-        let command = Self::build_resp_command(cmd);
-
-        let stream = self.stream.as_mut().unwrap();
+        let result = self.exchange(cmd);
+        if matches!(
+            result,
+            Err(ClientError::Io(_) | ClientError::Protocol(_) | ClientError::Closed)
+        ) {
+            self.stream = None;
+        }
+        result
+    }
 
-        // 发送命令
-        stream.write_all(command.as_bytes())?;
-
-        // 读取响应
-        let mut buffer = Vec::new();
-        let mut temp = [0; 4096];
-
-        loop {
-            let n = stream.read(&mut temp)?;
-            if n == 0 {
-                break;
-            }
-            buffer.extend_from_slice(&temp[..n]);
-
-            // 简单检查是否收到完整响应（以 \r\n 结尾）
-            if buffer.len() >= 2 && &buffer[buffer.len() - 2..] == b"\r\n" {
-                break;
-            }
+    /// 发送命令，服务器的错误回复转换为 `Err(ClientError::Server)`
+    pub fn query(&mut self, cmd: &[String]) -> std::result::Result<RespValue, ClientError> {
+        match self.request(cmd)? {
+            RespValue::Error(message) => Err(ClientError::Server(message)),
+            value => Ok(value),
         }
+    }
+
+    fn exchange(&mut self, cmd: &[String]) -> std::result::Result<RespValue, ClientError> {
+        let reader = self.stream.as_mut().unwrap();
 
-        // 解析响应
-        let parser = RespParser::new();
-        let response = parser.parse(&buffer)?;
+        // 发送命令
+        let command = Self::build_resp_command(cmd);
+        reader.get_mut().write_all(command.as_bytes())?;
 
-        Ok(response)
+        // 读取完整的回复：解析器会一直读取直到值完整，不依赖单次 read 的边界
+        if reader.fill_buf()?.is_empty() {
+            return Err(ClientError::Closed);
+        }
+        RespParser::new()
+            .parse_from(reader)
+            .map_err(|e| match e.downcast::<std::io::Error>() {
+                Ok(io) => ClientError::Io(*io),
+                Err(e) => ClientError::Protocol(e.to_string()),
+            })
     }
 
     pub fn disconnect(&mut self) -> Result<()> {
         if let Some(stream) = self.stream.take() {
-            stream.shutdown(std::net::Shutdown::Both)?;
+            stream.get_ref().shutdown(std::net::Shutdown::Both)?;
         }
         Ok(())
     }
@@ -75,7 +94,7 @@ impl ClientConnection {
         self.stream.is_some()
     }
 
-    fn build_resp_command(cmd: &[String]) -> String {
+    pub(crate) fn build_resp_command(cmd: &[String]) -> String {
         if cmd.is_empty() {
             return String::new();
         }
@@ -94,6 +113,8 @@ impl ClientConnection {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
 
     #[test]
     fn test_build_resp_command() {
@@ -105,4 +126,51 @@ mod tests {
         let result = ClientConnection::build_resp_command(&cmd);
         assert_eq!(result, "*2\r\n$4\r\nPING\r\n$5\r\nhello\r\n");
     }
+
+    #[test]
+    fn test_large_reply_and_typed_errors() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = std::thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 1024];
+
+            // 第一个回复：分多次写出，且每段都以 \r\n 结尾
+            let _ = socket.read(&mut buf).unwrap();
+            socket.write_all(b"*3\r\n$3\r\nfoo\r\n").unwrap();
+            socket.flush().unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            socket.write_all(b"$3\r\nbar\r\n:42\r\n").unwrap();
+
+            // 第二个回复：错误
+            let _ = socket.read(&mut buf).unwrap();
+            socket.write_all(b"-ERR unknown command\r\n").unwrap();
+        });
+
+        let mut connection = ClientConnection::new("127.0.0.1", port);
+        connection.connect().unwrap();
+
+        let reply = connection.query(&["KEYS".to_string()]).unwrap();
+        assert_eq!(
+            reply,
+            RespValue::Array(Some(vec![
+                RespValue::bulk("foo"),
+                RespValue::bulk("bar"),
+                RespValue::Integer(42),
+            ]))
+        );
+
+        let err = connection.query(&["NOPE".to_string()]).unwrap_err();
+        assert!(err.is_server_error());
+        assert_eq!(err.to_string(), "ERR unknown command");
+        assert!(connection.is_connected());
+
+        server.join().unwrap();
+
+        // 服务器关闭后，连接错误会断开连接
+        let err = connection.query(&["PING".to_string()]).unwrap_err();
+        assert!(!err.is_server_error());
+        assert!(!connection.is_connected());
+    }
 }
@@ -0,0 +1,62 @@
+use crate::protocol::ErrorCode;
+
+/// 客户端错误
+///
+/// 区分服务器返回的错误回复和连接/协议层面的错误
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    /// 服务器返回的错误回复（RESP `-ERR ...`）
+    #[error("{0}")]
+    Server(String),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Protocol error: {0}")]
+    Protocol(String),
+    /// 服务器关闭了连接
+    #[error("Connection closed by server")]
+    Closed,
+}
+
+impl ClientError {
+    /// 是否为服务器返回的错误回复（连接仍然可用）
+    pub fn is_server_error(&self) -> bool {
+        matches!(self, ClientError::Server(_))
+    }
+
+    /// 服务器错误回复的错误码（非服务器错误时返回 None）
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            ClientError::Server(message) => Some(ErrorCode::from_message(message)),
+            _ => None,
+        }
+    }
+
+    /// 是否值得退避后重试：连接错误和暂时性的服务器错误（LOADING、BUSY 等）
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ClientError::Server(_) => self.code().is_some_and(|code| code.is_retryable()),
+            ClientError::Io(_) | ClientError::Closed => true,
+            ClientError::Protocol(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_error_codes() {
+        let error = ClientError::Server("LOADING server is loading the dataset".to_string());
+        assert_eq!(error.code(), Some(ErrorCode::Loading));
+        assert!(error.is_retryable());
+
+        let error = ClientError::Server("ERR invalid GeoJSON".to_string());
+        assert_eq!(error.code(), Some(ErrorCode::Err));
+        assert!(!error.is_retryable());
+
+        assert_eq!(ClientError::Closed.code(), None);
+        assert!(ClientError::Closed.is_retryable());
+        assert!(!ClientError::Protocol("bad reply".to_string()).is_retryable());
+    }
+}
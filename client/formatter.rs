@@ -1,9 +1,538 @@
 use crate::protocol::parser::RespValue;
 use colored::*;
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
 
-pub struct OutputFormatter;
+/// 查询结果的输出格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// redis-cli 风格的文本输出
+    #[default]
+    Text,
+    /// 对齐的表格，适合人工阅读
+    Table,
+    /// JSON，GeoJSON 字符串会被展开为对象
+    Json,
+    /// GeoJSON FeatureCollection，距离等附加信息放在 properties 中
+    Geojson,
+    /// CSV，适合通过管道交给其他工具
+    Csv,
+}
+
+/// 结果中的一行：GeoJSON 对象及其附加列（例如 NEARBY 的距离）
+struct Row {
+    geojson: Option<Value>,
+    cells: Vec<String>,
+}
+
+pub struct OutputFormatter {
+    format: OutputFormat,
+    /// 坐标保留的小数位数，None 表示不处理
+    precision: Option<usize>,
+}
 
 impl OutputFormatter {
+    pub fn new(format: OutputFormat, precision: Option<usize>) -> Self {
+        Self { format, precision }
+    }
+
+    /// 按选定的格式渲染回复；错误回复和简单值在所有格式下都使用文本形式
+    pub fn render(&self, value: &RespValue) -> String {
+        let rows = match value {
+            RespValue::Array(Some(values)) => values.iter().map(|v| self.to_row(v)).collect(),
+            RespValue::BulkString(Some(_)) => vec![self.to_row(value)],
+            RespValue::Set(values) => values.iter().map(|v| self.to_row(v)).collect(),
+            RespValue::Array(None) | RespValue::BulkString(None) => Vec::new(),
+            // 映射和推送帧不是结果行，只有 JSON 格式会转换结构
+            RespValue::Map(_) | RespValue::Push(_) if self.format == OutputFormat::Json => {
+                return serde_json::to_string_pretty(&self.to_json(value)).unwrap_or_default()
+            }
+            _ => return Self::format_response(value),
+        };
+
+        match self.format {
+            OutputFormat::Text => match self.precision {
+                Some(_) => Self::format_response(&self.apply_precision(value)),
+                None => Self::format_response(value),
+            },
+            OutputFormat::Table => Self::render_table(&rows),
+            OutputFormat::Json => {
+                serde_json::to_string_pretty(&self.to_json(value)).unwrap_or_default()
+            }
+            OutputFormat::Geojson => Self::render_geojson(rows),
+            OutputFormat::Csv => Self::render_csv(&rows),
+        }
+    }
+
+    /// 结果中每一行的标识，用于 watch 模式比较前后两次结果
+    ///
+    /// GeoJSON 行优先使用 Feature id，没有 id 时使用几何体本身；其他行使用第一列
+    pub fn result_ids(&self, value: &RespValue) -> BTreeSet<String> {
+        let rows: Vec<Row> = match value {
+            RespValue::Array(Some(values)) => values.iter().map(|v| self.to_row(v)).collect(),
+            RespValue::BulkString(Some(_)) => vec![self.to_row(value)],
+            _ => Vec::new(),
+        };
+
+        rows.into_iter()
+            .filter_map(|row| match row.geojson {
+                Some(geojson) => feature_id(&geojson).or_else(|| Some(geojson.to_string())),
+                None => row.cells.into_iter().next(),
+            })
+            .collect()
+    }
+
+    /// 渲染两次结果之间新增和移除的标识
+    pub fn format_watch_diff(previous: &BTreeSet<String>, current: &BTreeSet<String>) -> String {
+        let added: Vec<String> = current
+            .difference(previous)
+            .map(|id| format!("+ {}", id).green().to_string())
+            .collect();
+        let removed: Vec<String> = previous
+            .difference(current)
+            .map(|id| format!("- {}", id).red().to_string())
+            .collect();
+
+        if added.is_empty() && removed.is_empty() {
+            return "(no changes)".dimmed().to_string();
+        }
+        added
+            .into_iter()
+            .chain(removed)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// 按 Tile38 的 JSON 格式包装命令的回复，用于服务端的 `OUTPUT json` 模式
+    ///
+    /// 所有回复都有 `ok` 和 `elapsed`（命令耗时），错误回复为 `{"ok":false,"err":...}`。
+    /// GET 的对象放在 `object` 中（POINT、BOUNDS、HASH 分别为 `point`、`bounds`、`hash`），INTERSECTS/NEARBY 的结果放在 `objects` 中（每项带 `id`、`object`，
+    /// 以及 NEARBY 的 `distance`、FIELDS 的 `fields` 或 NOFIELDS 的 `bbox`），SCAN 的 ID 放在 `ids` 中，
+    /// 带游标时有 `cursor`，SERVER 和 STATS 的统计放在 `stats` 中；其他命令的回复放在 `result` 中，只回复 OK 的命令只有 `ok`
+    pub fn tile38_json(
+        command: &str,
+        args: &[RespValue],
+        reply: &RespValue,
+        elapsed: std::time::Duration,
+    ) -> Value {
+        let formatter = Self::new(OutputFormat::Json, None);
+        let mut body = serde_json::Map::new();
+        let command = command.to_uppercase();
+        let has_option = |name: &str| {
+            args.iter()
+                .any(|arg| arg.as_str().is_some_and(|s| s.eq_ignore_ascii_case(name)))
+        };
+        let paged = has_option("CURSOR");
+        let number = |value: &RespValue| {
+            let text = Self::scalar_text(value);
+            text.parse::<f64>().map_or(json!(text), |n| json!(n))
+        };
+        let lat_lon = |pair: &RespValue| match pair {
+            RespValue::Array(Some(pair)) if pair.len() == 2 => {
+                json!({"lat": number(&pair[0]), "lon": number(&pair[1])})
+            }
+            _ => Value::Null,
+        };
+
+        let ok = match (command.as_str(), reply) {
+            (_, RespValue::Error(message)) => {
+                body.insert("err".to_string(), json!(message));
+                false
+            }
+            ("GET", RespValue::BulkString(None)) => {
+                body.insert("err".to_string(), json!("id not found"));
+                false
+            }
+            ("GET", RespValue::BulkString(Some(_))) if has_option("HASH") => {
+                body.insert("hash".to_string(), json!(Self::scalar_text(reply)));
+                true
+            }
+            ("GET", RespValue::BulkString(Some(_))) => {
+                body.insert("object".to_string(), formatter.to_json(reply));
+                true
+            }
+            // POINT 回复 [lat, lon]，BOUNDS 回复 [[minlat, minlon], [maxlat, maxlon]]
+            ("GET", RespValue::Array(Some(items))) if has_option("BOUNDS") && items.len() == 2 => {
+                let bounds = json!({"sw": lat_lon(&items[0]), "ne": lat_lon(&items[1])});
+                body.insert("bounds".to_string(), bounds);
+                true
+            }
+            ("GET", RespValue::Array(Some(_))) => {
+                body.insert("point".to_string(), lat_lon(reply));
+                true
+            }
+            ("OUTPUT", RespValue::BulkString(Some(_))) => {
+                body.insert("output".to_string(), json!(Self::scalar_text(reply)));
+                true
+            }
+            ("PING", _) => {
+                let pong = Self::scalar_text(reply).to_lowercase();
+                body.insert("ping".to_string(), json!(pong));
+                true
+            }
+            ("INTERSECTS" | "NEARBY", RespValue::Integer(count)) => {
+                body.insert("count".to_string(), json!(count));
+                true
+            }
+            ("INTERSECTS" | "NEARBY", RespValue::Array(items)) => {
+                let (cursor, items) = match items.as_deref() {
+                    Some([cursor, RespValue::Array(items)]) if paged => {
+                        (Some(Self::scalar_text(cursor)), items.as_deref())
+                    }
+                    items => (None, items),
+                };
+                let objects: Vec<Value> = items
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|item| formatter.tile38_object(item))
+                    .collect();
+                body.insert("count".to_string(), json!(objects.len()));
+                body.insert("objects".to_string(), Value::Array(objects));
+                if let Some(cursor) = cursor {
+                    body.insert("cursor".to_string(), json!(cursor));
+                }
+                true
+            }
+            ("SCAN", RespValue::Array(Some(page))) if page.len() == 2 => {
+                let ids = formatter.to_json(&page[1]);
+                let count = ids.as_array().map_or(0, Vec::len);
+                body.insert("ids".to_string(), ids);
+                body.insert("count".to_string(), json!(count));
+                body.insert("cursor".to_string(), json!(Self::scalar_text(&page[0])));
+                true
+            }
+            // SERVER 的 `key:value` 文本和 STATS 的字段/值数组都转换为对象
+            ("SERVER", RespValue::BulkString(Some(_))) => {
+                let stats: serde_json::Map<String, Value> = Self::scalar_text(reply)
+                    .lines()
+                    .filter_map(|line| line.split_once(':'))
+                    .map(|(key, value)| (key.to_string(), number(&RespValue::bulk(value))))
+                    .collect();
+                body.insert("stats".to_string(), Value::Object(stats));
+                true
+            }
+            ("STATS", RespValue::Array(Some(items))) if !has_option("HISTORY") => {
+                let stats: Vec<Value> = items
+                    .iter()
+                    .map(|item| match item {
+                        RespValue::Array(Some(fields)) => Value::Object(
+                            fields
+                                .chunks(2)
+                                .filter(|pair| pair.len() == 2)
+                                .map(|pair| {
+                                    let value = match &pair[1] {
+                                        // bounds 是 JSON 数组
+                                        RespValue::BulkString(Some(bytes)) => {
+                                            serde_json::from_slice(bytes)
+                                                .unwrap_or_else(|_| formatter.to_json(&pair[1]))
+                                        }
+                                        other => formatter.to_json(other),
+                                    };
+                                    (Self::scalar_text(&pair[0]), value)
+                                })
+                                .collect(),
+                        ),
+                        _ => Value::Null,
+                    })
+                    .collect();
+                body.insert("stats".to_string(), Value::Array(stats));
+                true
+            }
+            (_, RespValue::SimpleString(_)) => true,
+            (_, value) => {
+                body.insert("result".to_string(), formatter.to_json(value));
+                true
+            }
+        };
+        body.insert("ok".to_string(), json!(ok));
+        body.insert("elapsed".to_string(), json!(format!("{:?}", elapsed)));
+        Value::Object(body)
+    }
+
+    /// INTERSECTS/NEARBY 的一项结果
+    fn tile38_object(&self, item: &RespValue) -> Value {
+        let mut object = serde_json::Map::new();
+        let cells: Vec<&RespValue> = match item {
+            RespValue::Array(Some(cells)) => cells.iter().collect(),
+            other => vec![other],
+        };
+        for cell in cells {
+            let text = Self::scalar_text(cell);
+            if let Some(geojson) = self.parse_geojson(&text) {
+                if let Some(id) = feature_id(&geojson) {
+                    object.entry("id").or_insert(json!(id));
+                }
+                object.insert("object".to_string(), geojson);
+            } else if !object.contains_key("id") {
+                object.insert("id".to_string(), json!(text));
+            } else if let Ok(value @ (Value::Object(_) | Value::Array(_))) =
+                serde_json::from_str::<Value>(&text)
+            {
+                // FIELDS 的字段对象，或 NOFIELDS 的边界框
+                let key = if value.is_object() { "fields" } else { "bbox" };
+                object.insert(key.to_string(), value);
+            } else if let Ok(distance) = text.parse::<f64>() {
+                object.insert("distance".to_string(), json!(distance));
+            }
+        }
+        Value::Object(object)
+    }
+
+    fn to_row(&self, value: &RespValue) -> Row {
+        let cells: Vec<&RespValue> = match value {
+            RespValue::Array(Some(values)) => values.iter().collect(),
+            other => vec![other],
+        };
+
+        let mut row = Row {
+            geojson: None,
+            cells: Vec::new(),
+        };
+        for cell in cells {
+            let text = Self::scalar_text(cell);
+            if row.geojson.is_none() {
+                if let Some(geojson) = self.parse_geojson(&text) {
+                    row.geojson = Some(geojson);
+                    continue;
+                }
+            }
+            row.cells.push(text);
+        }
+        row
+    }
+
+    fn scalar_text(value: &RespValue) -> String {
+        match value {
+            RespValue::SimpleString(s) | RespValue::Error(s) => s.clone(),
+            RespValue::Integer(i) => i.to_string(),
+            RespValue::BulkString(Some(s)) => String::from_utf8_lossy(s).into_owned(),
+            RespValue::BulkString(None) | RespValue::Array(None) => String::new(),
+            RespValue::Array(Some(values)) | RespValue::Set(values) | RespValue::Push(values) => {
+                values
+                    .iter()
+                    .map(Self::scalar_text)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            }
+            RespValue::Null => String::new(),
+            RespValue::Boolean(b) => b.to_string(),
+            RespValue::Double(n) => n.to_string(),
+            RespValue::BigNumber(n) => n.clone(),
+            RespValue::Map(pairs) => pairs
+                .iter()
+                .flat_map(|(key, value)| [Self::scalar_text(key), Self::scalar_text(value)])
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+
+    /// 解析 GeoJSON 对象（包含 type 字段的 JSON 对象），并按精度截断坐标
+    fn parse_geojson(&self, text: &str) -> Option<Value> {
+        if !text.trim_start().starts_with('{') {
+            return None;
+        }
+        let mut value: Value = serde_json::from_str(text).ok()?;
+        value.get("type")?.as_str()?;
+        if let Some(precision) = self.precision {
+            truncate_coordinates(&mut value, precision, false);
+        }
+        Some(value)
+    }
+
+    fn apply_precision(&self, value: &RespValue) -> RespValue {
+        match value {
+            RespValue::BulkString(Some(_)) => {
+                match value.as_str().and_then(|s| self.parse_geojson(s)) {
+                    Some(geojson) => RespValue::bulk(geojson.to_string()),
+                    None => value.clone(),
+                }
+            }
+            RespValue::Array(Some(values)) => RespValue::Array(Some(
+                values.iter().map(|v| self.apply_precision(v)).collect(),
+            )),
+            _ => value.clone(),
+        }
+    }
+
+    fn to_json(&self, value: &RespValue) -> Value {
+        match value {
+            RespValue::SimpleString(s) => json!(s),
+            RespValue::Error(e) => json!({ "error": e }),
+            RespValue::Integer(i) => json!(i),
+            RespValue::BulkString(Some(s)) => {
+                let text = String::from_utf8_lossy(s);
+                self.parse_geojson(&text).unwrap_or_else(|| json!(text))
+            }
+            RespValue::BulkString(None) | RespValue::Array(None) | RespValue::Null => Value::Null,
+            RespValue::Array(Some(values)) | RespValue::Set(values) | RespValue::Push(values) => {
+                Value::Array(values.iter().map(|v| self.to_json(v)).collect())
+            }
+            RespValue::Boolean(b) => json!(b),
+            // JSON 没有 inf 和 nan，非有限值保留为文本
+            RespValue::Double(n) => {
+                serde_json::Number::from_f64(*n).map_or_else(|| json!(n.to_string()), Value::Number)
+            }
+            RespValue::BigNumber(n) => json!(n),
+            // 键都是文本时输出对象，否则输出键值对数组
+            RespValue::Map(pairs) => match pairs
+                .iter()
+                .map(|(key, value)| match key {
+                    RespValue::SimpleString(k) => Some((k.clone(), self.to_json(value))),
+                    RespValue::BulkString(Some(_)) => {
+                        key.as_str().map(|k| (k.to_string(), self.to_json(value)))
+                    }
+                    _ => None,
+                })
+                .collect::<Option<serde_json::Map<_, _>>>()
+            {
+                Some(object) => Value::Object(object),
+                None => Value::Array(
+                    pairs
+                        .iter()
+                        .map(|(key, value)| json!([self.to_json(key), self.to_json(value)]))
+                        .collect(),
+                ),
+            },
+        }
+    }
+
+    /// 表格列：序号、id、几何类型、几何体（紧凑 JSON），以及附加列
+    fn table_records(rows: &[Row]) -> (Vec<String>, Vec<Vec<String>>) {
+        let has_geojson = rows.iter().any(|row| row.geojson.is_some());
+        let extra = rows.iter().map(|row| row.cells.len()).max().unwrap_or(0);
+
+        let mut header = vec!["#".to_string()];
+        if has_geojson {
+            header.extend(["id", "type", "geometry"].map(String::from));
+        }
+        for i in 0..extra {
+            header.push(match (has_geojson, i) {
+                (true, 0) => "value".to_string(),
+                (_, i) => format!("col{}", i + 1),
+            });
+        }
+
+        let records = rows
+            .iter()
+            .enumerate()
+            .map(|(i, row)| {
+                let mut record = vec![(i + 1).to_string()];
+                if has_geojson {
+                    let geojson = row.geojson.as_ref();
+                    let geometry = geojson.map(|g| g.get("geometry").unwrap_or(g));
+                    record.push(geojson.and_then(feature_id).unwrap_or_default());
+                    record.push(
+                        geometry
+                            .and_then(|g| g.get("type"))
+                            .and_then(|t| t.as_str())
+                            .unwrap_or_default()
+                            .to_string(),
+                    );
+                    record.push(
+                        geometry
+                            .and_then(|g| g.get("coordinates"))
+                            .map(|c| c.to_string())
+                            .unwrap_or_default(),
+                    );
+                }
+                record.extend(row.cells.iter().cloned());
+                record.resize(header.len(), String::new());
+                record
+            })
+            .collect();
+
+        (header, records)
+    }
+
+    fn render_table(rows: &[Row]) -> String {
+        if rows.is_empty() {
+            return "(empty)".yellow().to_string();
+        }
+
+        let (header, records) = Self::table_records(rows);
+        let widths: Vec<usize> = (0..header.len())
+            .map(|col| {
+                records
+                    .iter()
+                    .map(|r| r[col].chars().count())
+                    .chain(std::iter::once(header[col].chars().count()))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let line = |cells: &[String]| {
+            cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join(" | ")
+                .trim_end()
+                .to_string()
+        };
+
+        let mut out = vec![line(&header)];
+        out.push(
+            widths
+                .iter()
+                .map(|w| "-".repeat(*w))
+                .collect::<Vec<_>>()
+                .join("-+-"),
+        );
+        out.extend(records.iter().map(|r| line(r)));
+        out.join("\n")
+    }
+
+    fn render_csv(rows: &[Row]) -> String {
+        let (header, records) = Self::table_records(rows);
+        std::iter::once(&header)
+            .chain(records.iter())
+            .map(|record| {
+                record
+                    .iter()
+                    .map(|cell| csv_escape(cell))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn render_geojson(rows: Vec<Row>) -> String {
+        let features: Vec<Value> = rows
+            .into_iter()
+            .filter_map(|row| {
+                let geojson = row.geojson?;
+                let mut feature = if geojson.get("type").and_then(|t| t.as_str()) == Some("Feature")
+                {
+                    geojson
+                } else {
+                    json!({ "type": "Feature", "properties": {}, "geometry": geojson })
+                };
+
+                // NEARBY 的距离等附加列放入 properties
+                if let Some(first) = row.cells.first() {
+                    let value = first
+                        .parse::<f64>()
+                        .map(|n| json!(n))
+                        .unwrap_or_else(|_| json!(first));
+                    if !feature["properties"].is_object() {
+                        feature["properties"] = json!({});
+                    }
+                    feature["properties"]["distance"] = value;
+                }
+                Some(feature)
+            })
+            .collect();
+
+        serde_json::to_string_pretty(&json!({
+            "type": "FeatureCollection",
+            "features": features,
+        }))
+        .unwrap_or_default()
+    }
+
     pub fn format_response(value: &RespValue) -> String {
         match value {
             RespValue::SimpleString(s) => Self::format_simple_string(s),
@@ -11,6 +540,13 @@ impl OutputFormatter {
             RespValue::Integer(i) => Self::format_integer(*i),
             RespValue::BulkString(s) => Self::format_bulk_string(s),
             RespValue::Array(arr) => Self::format_array(arr),
+            RespValue::Null => "(nil)".red().to_string(),
+            RespValue::Boolean(b) => format!("({})", b).cyan().to_string(),
+            RespValue::Double(n) => format!("(double) {}", n.to_string().cyan()),
+            RespValue::BigNumber(n) => format!("(big number) {}", n.cyan()),
+            RespValue::Map(pairs) => Self::format_map(pairs),
+            RespValue::Set(values) => Self::format_items(values),
+            RespValue::Push(values) => Self::format_push(values),
         }
     }
 
@@ -26,13 +562,13 @@ impl OutputFormatter {
         format!("(integer) {}", i.to_string().cyan())
     }
 
-    fn format_bulk_string(s: &Option<String>) -> String {
+    fn format_bulk_string(s: &Option<Vec<u8>>) -> String {
         match s {
             Some(s) => {
                 if s.is_empty() {
                     "(empty string)".yellow().to_string()
                 } else {
-                    s.clone()
+                    String::from_utf8_lossy(s).into_owned()
                 }
             }
             None => "(nil)".red().to_string(),
@@ -41,33 +577,71 @@ impl OutputFormatter {
 
     fn format_array(arr: &Option<Vec<RespValue>>) -> String {
         match arr {
-            Some(values) => {
-                if values.is_empty() {
-                    "(empty array)".yellow().to_string()
-                } else {
-                    let mut result = String::new();
-                    for (i, value) in values.iter().enumerate() {
-                        let formatted_value = match value {
-                            RespValue::BulkString(Some(s)) => s.clone(),
-                            RespValue::BulkString(None) => "(nil)".to_string(),
-                            RespValue::Integer(n) => n.to_string(),
-                            RespValue::SimpleString(s) => s.clone(),
-                            RespValue::Error(e) => format!("(error) {}", e),
-                            RespValue::Array(_) => Self::format_response(value), // 递归处理嵌套数组
-                        };
-                        result.push_str(&format!(
-                            "{}) {}\n",
-                            (i + 1).to_string().blue(),
-                            formatted_value
-                        ));
-                    }
-                    result.trim_end().to_string()
-                }
-            }
+            Some(values) => Self::format_items(values),
             None => "(nil)".red().to_string(),
         }
     }
 
+    fn format_items(values: &[RespValue]) -> String {
+        if values.is_empty() {
+            return "(empty array)".yellow().to_string();
+        }
+        let mut result = String::new();
+        for (i, value) in values.iter().enumerate() {
+            result.push_str(&format!(
+                "{}) {}\n",
+                (i + 1).to_string().blue(),
+                Self::format_item(value)
+            ));
+        }
+        result.trim_end().to_string()
+    }
+
+    /// 聚合类型中的元素：标量不带类型前缀，嵌套的聚合类型递归处理
+    fn format_item(value: &RespValue) -> String {
+        match value {
+            RespValue::BulkString(Some(s)) => String::from_utf8_lossy(s).into_owned(),
+            RespValue::BulkString(None) => "(nil)".to_string(),
+            RespValue::Integer(n) => n.to_string(),
+            RespValue::SimpleString(s) => s.clone(),
+            RespValue::Error(e) => format!("(error) {}", e),
+            _ => Self::format_response(value),
+        }
+    }
+
+    /// RESP3 映射，与 redis-cli 相同的 `1# key => value` 形式
+    fn format_map(pairs: &[(RespValue, RespValue)]) -> String {
+        if pairs.is_empty() {
+            return "(empty hash)".yellow().to_string();
+        }
+        pairs
+            .iter()
+            .enumerate()
+            .map(|(i, (key, value))| {
+                format!(
+                    "{}# {} => {}",
+                    (i + 1).to_string().blue(),
+                    Self::format_item(key),
+                    Self::format_item(value)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// RESP3 推送帧：第一个元素是消息类型（例如 `fence`），其余元素逐行显示
+    fn format_push(values: &[RespValue]) -> String {
+        let Some((kind, rest)) = values.split_first() else {
+            return "(push)".magenta().to_string();
+        };
+        let mut result = format!("{} {}", "(push)".magenta(), Self::format_item(kind));
+        for value in rest {
+            result.push('\n');
+            result.push_str(&Self::format_item(value));
+        }
+        result
+    }
+
     pub fn format_prompt(host: &str, port: u16) -> String {
         format!("{}:{}> ", host.blue(), port.to_string().blue())
     }
@@ -102,6 +676,52 @@ Use Ctrl+C or Ctrl+D to exit interactive mode.
     }
 }
 
+/// Feature 的 id：优先使用顶层 id，其次是 properties.id
+fn feature_id(geojson: &Value) -> Option<String> {
+    let id = geojson
+        .get("id")
+        .or_else(|| geojson.get("properties").and_then(|p| p.get("id")))?;
+    Some(match id {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    })
+}
+
+/// 将 coordinates / bbox 中的数字截断到指定小数位数
+fn truncate_coordinates(value: &mut Value, precision: usize, in_coordinates: bool) {
+    match value {
+        Value::Number(n) if in_coordinates => {
+            if let Some(f) = n.as_f64() {
+                let factor = 10f64.powi(precision as i32);
+                let rounded = (f * factor).round() / factor;
+                if let Some(number) = serde_json::Number::from_f64(rounded) {
+                    *n = number;
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                truncate_coordinates(item, precision, in_coordinates);
+            }
+        }
+        Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                let is_coordinates = key == "coordinates" || key == "bbox";
+                truncate_coordinates(item, precision, is_coordinates);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn csv_escape(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -116,7 +736,7 @@ mod tests {
 
     #[test]
     fn test_format_bulk_string() {
-        let value = RespValue::BulkString(Some("hello".to_string()));
+        let value = RespValue::bulk("hello");
         let result = OutputFormatter::format_response(&value);
         assert!(result.contains("hello"));
 
@@ -132,4 +752,221 @@ mod tests {
         assert!(result.contains("42"));
         assert!(result.contains("integer"));
     }
+
+    fn nearby_reply() -> RespValue {
+        RespValue::Array(Some(vec![
+            RespValue::Array(Some(vec![
+                RespValue::bulk(
+                    r#"{"type":"Feature","id":"truck1","properties":{},"geometry":{"type":"Point","coordinates":[116.123456,39.987654]}}"#
+                        .to_string(),
+                ),
+                RespValue::bulk("12.50"),
+            ])),
+            RespValue::Array(Some(vec![
+                RespValue::bulk(
+                    r#"{"type":"Point","coordinates":[116.5,40.0]}"#.to_string(),
+                ),
+                RespValue::bulk("800.00"),
+            ])),
+        ]))
+    }
+
+    #[test]
+    fn test_render_table() {
+        let formatter = OutputFormatter::new(OutputFormat::Table, Some(2));
+        let result = formatter.render(&nearby_reply());
+        let lines: Vec<&str> = result.lines().collect();
+
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("# | id"));
+        assert!(lines[2].contains("truck1"));
+        assert!(lines[2].contains("[116.12,39.99]"));
+        assert!(lines[2].ends_with("12.50"));
+        assert!(lines[3].contains("Point"));
+    }
+
+    #[test]
+    fn test_render_csv() {
+        let formatter = OutputFormatter::new(OutputFormat::Csv, None);
+        let result = formatter.render(&nearby_reply());
+        let lines: Vec<&str> = result.lines().collect();
+
+        assert_eq!(lines[0], "#,id,type,geometry,value");
+        assert_eq!(lines[1], "1,truck1,Point,\"[116.123456,39.987654]\",12.50");
+        assert_eq!(lines[2], "2,,Point,\"[116.5,40.0]\",800.00");
+    }
+
+    #[test]
+    fn test_render_geojson() {
+        let formatter = OutputFormatter::new(OutputFormat::Geojson, Some(1));
+        let result = formatter.render(&nearby_reply());
+        let collection: Value = serde_json::from_str(&result).unwrap();
+
+        assert_eq!(collection["type"], "FeatureCollection");
+        let features = collection["features"].as_array().unwrap();
+        assert_eq!(features.len(), 2);
+        assert_eq!(features[0]["id"], "truck1");
+        assert_eq!(features[0]["properties"]["distance"], 12.5);
+        assert_eq!(features[0]["geometry"]["coordinates"], json!([116.1, 40.0]));
+        assert_eq!(features[1]["geometry"]["type"], "Point");
+    }
+
+    #[test]
+    fn test_render_json_and_errors() {
+        let formatter = OutputFormatter::new(OutputFormat::Json, None);
+        let result = formatter.render(&RespValue::Array(Some(vec![
+            RespValue::bulk("fleet"),
+            RespValue::bulk("zones"),
+        ])));
+        assert_eq!(
+            serde_json::from_str::<Value>(&result).unwrap(),
+            json!(["fleet", "zones"])
+        );
+
+        let result = formatter.render(&RespValue::Error("ERR boom".to_string()));
+        assert!(result.contains("(error)"));
+    }
+
+    #[test]
+    fn test_tile38_json() {
+        let elapsed = std::time::Duration::from_micros(25);
+        let nearby = OutputFormatter::tile38_json("nearby", &[], &nearby_reply(), elapsed);
+        assert_eq!(nearby["ok"], json!(true));
+        assert_eq!(nearby["elapsed"], json!("25µs"));
+        assert_eq!(nearby["count"], json!(2));
+        assert_eq!(nearby["objects"][0]["id"], json!("truck1"));
+        assert_eq!(nearby["objects"][0]["distance"], json!(12.5));
+        assert_eq!(nearby["objects"][0]["object"]["type"], json!("Feature"));
+
+        // CURSOR 分页和 NOFIELDS 的边界框
+        let page = RespValue::Array(Some(vec![
+            RespValue::bulk("0"),
+            RespValue::Array(Some(vec![RespValue::Array(Some(vec![
+                RespValue::bulk("truck1"),
+                RespValue::bulk("[116.4,39.9,116.4,39.9]"),
+            ]))])),
+        ]));
+        let args = [RespValue::bulk("CURSOR"), RespValue::bulk("0")];
+        let page = OutputFormatter::tile38_json("INTERSECTS", &args, &page, elapsed);
+        assert_eq!(page["cursor"], json!("0"));
+        assert_eq!(
+            page["objects"],
+            json!([{"id": "truck1", "bbox": [116.4, 39.9, 116.4, 39.9]}])
+        );
+
+        let missing =
+            OutputFormatter::tile38_json("GET", &[], &RespValue::BulkString(None), elapsed);
+        assert_eq!(missing["ok"], json!(false));
+        assert_eq!(missing["err"], json!("id not found"));
+        let error = RespValue::Error("ERR boom".to_string());
+        let error = OutputFormatter::tile38_json("SET", &[], &error, elapsed);
+        assert_eq!(error["err"], json!("ERR boom"));
+
+        let ok = RespValue::SimpleString("OK".to_string());
+        let ok = OutputFormatter::tile38_json("SET", &[], &ok, elapsed);
+        assert_eq!(ok.as_object().unwrap().len(), 2);
+        let pong = RespValue::SimpleString("PONG".to_string());
+        let pong = OutputFormatter::tile38_json("PING", &[], &pong, elapsed);
+        assert_eq!(pong["ping"], json!("pong"));
+        let ttl = OutputFormatter::tile38_json("TTL", &[], &RespValue::Integer(-1), elapsed);
+        assert_eq!(ttl["result"], json!(-1));
+
+        // GET 的 POINT、BOUNDS 和 HASH
+        let pair = |lat: &str, lon: &str| {
+            RespValue::Array(Some(vec![RespValue::bulk(lat), RespValue::bulk(lon)]))
+        };
+        let args = [RespValue::bulk("POINT")];
+        let point = OutputFormatter::tile38_json("GET", &args, &pair("57", "10.5"), elapsed);
+        assert_eq!(point["point"], json!({"lat": 57.0, "lon": 10.5}));
+        let args = [RespValue::bulk("BOUNDS")];
+        let reply = RespValue::Array(Some(vec![pair("56", "10"), pair("58", "11")]));
+        let bounds = OutputFormatter::tile38_json("GET", &args, &reply, elapsed);
+        assert_eq!(bounds["bounds"]["ne"], json!({"lat": 58.0, "lon": 11.0}));
+        let args = [RespValue::bulk("HASH"), RespValue::bulk("5")];
+        let hash = OutputFormatter::tile38_json("GET", &args, &RespValue::bulk("ezs42"), elapsed);
+        assert_eq!(hash["hash"], json!("ezs42"));
+
+        let server = RespValue::bulk("# Server\r\nuptime_in_seconds:5\r\nconnected_clients:2\r\n");
+        let server = OutputFormatter::tile38_json("SERVER", &[], &server, elapsed);
+        assert_eq!(
+            server["stats"],
+            json!({"uptime_in_seconds": 5.0, "connected_clients": 2.0})
+        );
+        let stats = RespValue::Array(Some(vec![
+            RespValue::Array(Some(vec![
+                RespValue::bulk("objects"),
+                RespValue::Integer(2),
+                RespValue::bulk("bounds"),
+                RespValue::bulk("[1,-4,3,2]"),
+            ])),
+            RespValue::BulkString(None),
+        ]));
+        let args = [RespValue::bulk("fleet"), RespValue::bulk("missing")];
+        let stats = OutputFormatter::tile38_json("STATS", &args, &stats, elapsed);
+        assert_eq!(
+            stats["stats"],
+            json!([{"objects": 2, "bounds": [1, -4, 3, 2]}, null])
+        );
+    }
+
+    #[test]
+    fn test_format_resp3_values() {
+        let hello = RespValue::Map(vec![
+            (RespValue::bulk("server"), RespValue::bulk("spatio")),
+            (RespValue::bulk("proto"), RespValue::Integer(3)),
+        ]);
+        let result = OutputFormatter::format_response(&hello);
+        assert!(result.contains("# server => spatio\n"));
+        assert!(result.contains("# proto => 3"));
+        let result = OutputFormatter::format_response(&RespValue::Double(1.5));
+        assert!(result.starts_with("(double) ") && result.contains("1.5"));
+
+        let event = r#"{"fence":1,"detect":"enter","id":"truck1"}"#;
+        let push = RespValue::Push(vec![RespValue::bulk("fence"), RespValue::bulk(event)]);
+        let result = OutputFormatter::format_response(&push);
+        assert!(result.contains("(push)"));
+        assert!(result.ends_with(&format!(" fence\n{}", event)));
+
+        let formatter = OutputFormatter::new(OutputFormat::Json, None);
+        let json: Value = serde_json::from_str(&formatter.render(&hello)).unwrap();
+        assert_eq!(json, json!({"server": "spatio", "proto": 3}));
+        let json: Value = serde_json::from_str(&formatter.render(&push)).unwrap();
+        assert_eq!(json[1], json!(event));
+    }
+
+    #[test]
+    fn test_watch_diff() {
+        let formatter = OutputFormatter::new(OutputFormat::Text, None);
+
+        let previous = formatter.result_ids(&nearby_reply());
+        assert_eq!(previous.len(), 2);
+        assert!(previous.contains("truck1"));
+
+        let current = formatter.result_ids(&RespValue::Array(Some(vec![
+            RespValue::Array(Some(vec![
+                RespValue::bulk(
+                    r#"{"type":"Feature","id":"truck2","properties":{},"geometry":{"type":"Point","coordinates":[1,2]}}"#
+                        .to_string(),
+                ),
+                RespValue::bulk("1.00"),
+            ])),
+            RespValue::Array(Some(vec![
+                RespValue::bulk(
+                    r#"{"type":"Feature","id":"truck1","properties":{},"geometry":{"type":"Point","coordinates":[1,2]}}"#
+                        .to_string(),
+                ),
+                RespValue::bulk("2.00"),
+            ])),
+        ])));
+
+        let diff = OutputFormatter::format_watch_diff(&previous, &current);
+        let lines: Vec<&str> = diff.lines().collect();
+        assert_eq!(lines.len(), 2);
+        // 测试时不检查颜色代码，只检查内容
+        assert!(lines[0].contains("+ truck2"));
+        assert!(lines[1].contains("- {"));
+
+        let diff = OutputFormatter::format_watch_diff(&current, &current);
+        assert!(diff.contains("(no changes)"));
+    }
 }
@@ -1,7 +1,14 @@
+pub mod async_connection;
 pub mod cli_args;
 pub mod client_connection;
+pub mod error;
 pub mod formatter;
+#[cfg(feature = "postgres")]
+pub mod postgis;
+pub mod replay;
 
+pub use async_connection::{AsyncClientConnection, ReplyStream};
 pub use cli_args::CliArgs;
 pub use client_connection::ClientConnection;
-pub use formatter::OutputFormatter;
+pub use error::ClientError;
+pub use formatter::{OutputFormat, OutputFormatter};
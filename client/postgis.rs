@@ -0,0 +1,446 @@
+//! PostGIS 迁移：把 PostGIS 表导入为 collection，或把 collection 导出回 PostGIS 表
+//!
+//! 坐标转换交给 PostGIS（`ST_Transform`）：导入时统一转换为 WGS84（EPSG:4326），
+//! 导出时转换为目标几何列的 SRID。除 ID 和几何列外，所选的列作为 GeoJSON Feature 的
+//! properties 保存，导出时按列名写回同名列
+
+use std::time::{Duration, Instant};
+
+use futures_util::TryStreamExt;
+use serde_json::{Map, Value};
+use sqlx::{Connection, PgConnection, Row};
+
+use crate::client::AsyncClientConnection;
+use crate::protocol::parser::RespValue;
+
+/// spatio 中几何体使用的坐标系
+pub const WGS84_SRID: i32 = 4326;
+
+/// 导出时每次 SCAN 的对象数量
+const EXPORT_SCAN_COUNT: usize = 500;
+
+/// 引用 PostgreSQL 标识符，内部的双引号加倍
+pub fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// 参与迁移的 PostGIS 表
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PostgisTable {
+    pub schema: Option<String>,
+    pub name: String,
+    /// 对象 ID 所在的列，导出时需要有唯一约束
+    pub id_column: String,
+    pub geom_column: String,
+    /// 作为 properties 迁移的其他列
+    pub columns: Vec<String>,
+}
+
+impl PostgisTable {
+    /// `table` 可以带 schema，如 `public.roads`
+    pub fn new(table: &str) -> Self {
+        let (schema, name) = match table.split_once('.') {
+            Some((schema, name)) => (Some(schema.to_string()), name.to_string()),
+            None => (None, table.to_string()),
+        };
+        Self {
+            schema,
+            name,
+            id_column: "id".to_string(),
+            geom_column: "geom".to_string(),
+            columns: Vec::new(),
+        }
+    }
+
+    pub fn with_id_column(mut self, column: impl Into<String>) -> Self {
+        self.id_column = column.into();
+        self
+    }
+
+    pub fn with_geom_column(mut self, column: impl Into<String>) -> Self {
+        self.geom_column = column.into();
+        self
+    }
+
+    pub fn with_columns(mut self, columns: Vec<String>) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// 引用后的表名（带 schema 时为 `"schema"."table"`）
+    pub fn qualified_name(&self) -> String {
+        match &self.schema {
+            Some(schema) => format!("{}.{}", quote_ident(schema), quote_ident(&self.name)),
+            None => quote_ident(&self.name),
+        }
+    }
+
+    /// 导入查询：每行返回 ID、WGS84 下的 GeoJSON 几何体和所选列组成的 JSON 对象
+    ///
+    /// 几何列的 SRID 为 0（未设置）时需要通过 `source_srid` 指明原始坐标系。
+    /// 几何体为 NULL 的行不导入
+    pub fn import_query(&self, source_srid: Option<i32>) -> String {
+        let geom = format!("t.{}::geometry", quote_ident(&self.geom_column));
+        let geom = match source_srid {
+            Some(srid) => format!("ST_SetSRID({}, {})", geom, srid),
+            None => geom,
+        };
+        let properties = if self.columns.is_empty() {
+            "'{}'".to_string()
+        } else {
+            let columns: Vec<String> = self
+                .columns
+                .iter()
+                .map(|c| format!("t.{}", quote_ident(c)))
+                .collect();
+            format!(
+                "(SELECT row_to_json(p) FROM (SELECT {}) p)::text",
+                columns.join(", ")
+            )
+        };
+        format!(
+            "SELECT t.{id}::text AS id, ST_AsGeoJSON(ST_Transform({geom}, {wgs84})) AS geometry, \
+             {properties} AS properties FROM {table} t WHERE t.{geom_column} IS NOT NULL",
+            id = quote_ident(&self.id_column),
+            geom = geom,
+            wgs84 = WGS84_SRID,
+            properties = properties,
+            table = self.qualified_name(),
+            geom_column = quote_ident(&self.geom_column),
+        )
+    }
+
+    /// 查询几何列 SRID 的语句，参数依次为 schema（NULL 表示当前 schema）、表名和列名
+    pub fn srid_query() -> &'static str {
+        "SELECT srid FROM geometry_columns \
+         WHERE f_table_schema = COALESCE($1, current_schema()) \
+         AND f_table_name = $2 AND f_geometry_column = $3"
+    }
+
+    /// 导出语句：按 ID 插入或更新一行，参数为 GeoJSON 几何体和包含 ID、所选列的 JSON 对象
+    ///
+    /// 列值通过 `json_populate_record` 按表的列类型转换，几何体从 WGS84 转换为 `srid`
+    pub fn export_query(&self, srid: i32) -> String {
+        let geometry = if srid == WGS84_SRID || srid == 0 {
+            format!("ST_SetSRID(ST_GeomFromGeoJSON($1), {})", WGS84_SRID)
+        } else {
+            format!(
+                "ST_Transform(ST_SetSRID(ST_GeomFromGeoJSON($1), {}), {})",
+                WGS84_SRID, srid
+            )
+        };
+
+        let mut targets = vec![quote_ident(&self.id_column), quote_ident(&self.geom_column)];
+        let mut values = vec![format!("p.{}", quote_ident(&self.id_column)), geometry];
+        let mut updates = vec![format!(
+            "{0} = EXCLUDED.{0}",
+            quote_ident(&self.geom_column)
+        )];
+        for column in &self.columns {
+            let column = quote_ident(column);
+            values.push(format!("p.{}", column));
+            updates.push(format!("{0} = EXCLUDED.{0}", column));
+            targets.push(column);
+        }
+
+        format!(
+            "INSERT INTO {table} ({targets}) SELECT {values} \
+             FROM json_populate_record(NULL::{table}, $2::json) p \
+             ON CONFLICT ({id}) DO UPDATE SET {updates}",
+            table = self.qualified_name(),
+            targets = targets.join(", "),
+            values = values.join(", "),
+            id = quote_ident(&self.id_column),
+            updates = updates.join(", "),
+        )
+    }
+
+    /// 把 collection 中的对象转换为导出参数：（GeoJSON 几何体，列值 JSON 对象）
+    ///
+    /// Feature 的 properties 中只保留所选的列，几何体对象直接导出（所选列为 NULL）
+    pub fn export_record(&self, id: &str, geojson: &str) -> Result<(String, String), String> {
+        let value: Value =
+            serde_json::from_str(geojson).map_err(|e| format!("invalid GeoJSON: {}", e))?;
+        let (geometry, properties) = match value.get("type").and_then(Value::as_str) {
+            Some("Feature") => (
+                value.get("geometry").cloned().unwrap_or(Value::Null),
+                value.get("properties").and_then(Value::as_object).cloned(),
+            ),
+            Some("FeatureCollection") => {
+                return Err("FeatureCollection cannot be exported as a single row".to_string())
+            }
+            _ => (value, None),
+        };
+        if geometry.is_null() {
+            return Err("object has no geometry".to_string());
+        }
+
+        let mut record = Map::new();
+        for column in &self.columns {
+            if let Some(value) = properties.as_ref().and_then(|p| p.get(column)) {
+                record.insert(column.clone(), value.clone());
+            }
+        }
+        record.insert(self.id_column.clone(), Value::String(id.to_string()));
+        Ok((geometry.to_string(), Value::Object(record).to_string()))
+    }
+}
+
+/// 把导入的几何体和列值组合为 GeoJSON Feature
+pub fn import_feature(geometry: &str, properties: &str) -> Result<String, String> {
+    let geometry: Value =
+        serde_json::from_str(geometry).map_err(|e| format!("invalid geometry: {}", e))?;
+    let properties: Value =
+        serde_json::from_str(properties).map_err(|e| format!("invalid properties: {}", e))?;
+    Ok(serde_json::json!({
+        "type": "Feature",
+        "properties": properties,
+        "geometry": geometry,
+    })
+    .to_string())
+}
+
+/// 迁移结果
+#[derive(Debug, Clone, Default)]
+pub struct MigrationStats {
+    /// 成功迁移的对象数
+    pub migrated: u64,
+    /// 被拒绝的对象数（导入时 SET 返回错误）
+    pub failed: u64,
+    /// 最后一个被拒绝对象的 ID 和原因
+    pub last_error: Option<String>,
+    pub elapsed: Duration,
+}
+
+/// 把 PostGIS 表导入 collection，已存在的同 ID 对象会被覆盖
+///
+/// 行以流的方式读取，表再大也不会整体载入内存。被服务器拒绝的对象只计数，
+/// 数据库或连接错误会中止导入
+pub async fn import_table(
+    database_url: &str,
+    table: &PostgisTable,
+    source_srid: Option<i32>,
+    connection: &mut AsyncClientConnection,
+    collection: &str,
+) -> crate::Result<MigrationStats> {
+    let mut pg = PgConnection::connect(database_url).await?;
+    let query = table.import_query(source_srid);
+    let mut rows = sqlx::query(&query).fetch(&mut pg);
+    let mut stats = MigrationStats::default();
+    let started = Instant::now();
+
+    while let Some(row) = rows.try_next().await? {
+        let id: String = row.try_get("id")?;
+        let geometry: String = row.try_get("geometry")?;
+        let properties: String = row.try_get("properties")?;
+        let feature =
+            import_feature(&geometry, &properties).map_err(|e| format!("row '{}': {}", id, e))?;
+
+        let cmd = vec![
+            "SET".to_string(),
+            collection.to_string(),
+            id.clone(),
+            feature,
+        ];
+        match connection.send_command(&cmd).await? {
+            RespValue::Error(message) => {
+                stats.failed += 1;
+                stats.last_error = Some(format!("{}: {}", id, message));
+            }
+            _ => stats.migrated += 1,
+        }
+    }
+
+    drop(rows);
+    pg.close().await?;
+    stats.elapsed = started.elapsed();
+    Ok(stats)
+}
+
+/// 把 collection 导出到已存在的 PostGIS 表，按 ID 插入或更新
+///
+/// 所有行在一个事务中写入，任何一行失败都会回滚整个导出
+pub async fn export_collection(
+    connection: &mut AsyncClientConnection,
+    collection: &str,
+    database_url: &str,
+    table: &PostgisTable,
+) -> crate::Result<MigrationStats> {
+    let mut pg = PgConnection::connect(database_url).await?;
+    let srid: Option<i32> = sqlx::query_scalar(PostgisTable::srid_query())
+        .bind(&table.schema)
+        .bind(&table.name)
+        .bind(&table.geom_column)
+        .fetch_optional(&mut pg)
+        .await?;
+    let query = table.export_query(srid.unwrap_or(WGS84_SRID));
+
+    let mut tx = pg.begin().await?;
+    let mut stats = MigrationStats::default();
+    let started = Instant::now();
+    let mut cursor = "0".to_string();
+    loop {
+        let page = connection
+            .query(&[
+                "SCAN".to_string(),
+                collection.to_string(),
+                cursor,
+                "COUNT".to_string(),
+                EXPORT_SCAN_COUNT.to_string(),
+            ])
+            .await?;
+        let (next, ids) = parse_scan_page(page)?;
+
+        for id in ids {
+            let cmd = vec!["GET".to_string(), collection.to_string(), id.clone()];
+            // SCAN 和 GET 之间被删除或过期的对象跳过
+            let Some(geojson) = connection.query(&cmd).await?.as_str().map(str::to_string) else {
+                continue;
+            };
+            let (geometry, record) = table
+                .export_record(&id, &geojson)
+                .map_err(|e| format!("object '{}': {}", id, e))?;
+            sqlx::query(&query)
+                .bind(geometry)
+                .bind(record)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("object '{}': {}", id, e))?;
+            stats.migrated += 1;
+        }
+
+        if next == "0" {
+            break;
+        }
+        cursor = next;
+    }
+
+    tx.commit().await?;
+    pg.close().await?;
+    stats.elapsed = started.elapsed();
+    Ok(stats)
+}
+
+/// 解析 SCAN 的回复：[下一页游标, [对象 ID...]]
+fn parse_scan_page(page: RespValue) -> crate::Result<(String, Vec<String>)> {
+    let invalid = || "unexpected SCAN reply".to_string();
+    let RespValue::Array(Some(mut parts)) = page else {
+        return Err(invalid().into());
+    };
+    if parts.len() != 2 {
+        return Err(invalid().into());
+    }
+    let RespValue::Array(Some(ids)) = parts.pop().unwrap() else {
+        return Err(invalid().into());
+    };
+    let next = parts[0].as_str().ok_or_else(invalid)?.to_string();
+    let ids = ids
+        .iter()
+        .map(|id| id.as_str().map(str::to_string).ok_or_else(invalid))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((next, ids))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roads() -> PostgisTable {
+        PostgisTable::new("gis.roads")
+            .with_id_column("road_id")
+            .with_geom_column("shape")
+            .with_columns(vec!["name".to_string(), "lanes".to_string()])
+    }
+
+    #[test]
+    fn test_quote_ident() {
+        assert_eq!(quote_ident("roads"), "\"roads\"");
+        assert_eq!(quote_ident("we\"ird"), "\"we\"\"ird\"");
+        assert_eq!(PostgisTable::new("roads").qualified_name(), "\"roads\"");
+        assert_eq!(roads().qualified_name(), "\"gis\".\"roads\"");
+    }
+
+    #[test]
+    fn test_import_query() {
+        assert_eq!(
+            roads().import_query(None),
+            "SELECT t.\"road_id\"::text AS id, \
+             ST_AsGeoJSON(ST_Transform(t.\"shape\"::geometry, 4326)) AS geometry, \
+             (SELECT row_to_json(p) FROM (SELECT t.\"name\", t.\"lanes\") p)::text AS properties \
+             FROM \"gis\".\"roads\" t WHERE t.\"shape\" IS NOT NULL"
+        );
+
+        // SRID 未设置时先指定原始坐标系；没有所选列时 properties 为空对象
+        let query = PostgisTable::new("roads").import_query(Some(3857));
+        assert!(query.contains("ST_Transform(ST_SetSRID(t.\"geom\"::geometry, 3857), 4326)"));
+        assert!(query.contains("'{}' AS properties"));
+    }
+
+    #[test]
+    fn test_export_query() {
+        assert_eq!(
+            roads().export_query(3857),
+            "INSERT INTO \"gis\".\"roads\" (\"road_id\", \"shape\", \"name\", \"lanes\") \
+             SELECT p.\"road_id\", \
+             ST_Transform(ST_SetSRID(ST_GeomFromGeoJSON($1), 4326), 3857), p.\"name\", p.\"lanes\" \
+             FROM json_populate_record(NULL::\"gis\".\"roads\", $2::json) p \
+             ON CONFLICT (\"road_id\") DO UPDATE SET \"shape\" = EXCLUDED.\"shape\", \
+             \"name\" = EXCLUDED.\"name\", \"lanes\" = EXCLUDED.\"lanes\""
+        );
+        // 目标已经是 WGS84 时不做转换
+        assert!(PostgisTable::new("roads")
+            .export_query(WGS84_SRID)
+            .contains("SELECT p.\"id\", ST_SetSRID(ST_GeomFromGeoJSON($1), 4326) FROM"));
+    }
+
+    #[test]
+    fn test_feature_round_trip() {
+        let feature = import_feature(
+            r#"{"type":"LineString","coordinates":[[1,2],[3,4]]}"#,
+            r#"{"name":"Ring Rd","lanes":4}"#,
+        )
+        .unwrap();
+        let value: Value = serde_json::from_str(&feature).unwrap();
+        assert_eq!(value["type"], "Feature");
+        assert_eq!(value["properties"]["lanes"], 4);
+
+        // 未选择的属性不导出，ID 写入 ID 列
+        let stored = feature.replace("\"lanes\":4", "\"lanes\":4,\"color\":\"red\"");
+        let (geometry, record) = roads().export_record("r1", &stored).unwrap();
+        assert_eq!(
+            geometry,
+            r#"{"coordinates":[[1,2],[3,4]],"type":"LineString"}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Value>(&record).unwrap(),
+            serde_json::json!({"road_id": "r1", "name": "Ring Rd", "lanes": 4})
+        );
+
+        let (_, record) = roads()
+            .export_record("p1", r#"{"type":"Point","coordinates":[1,2]}"#)
+            .unwrap();
+        assert_eq!(record, r#"{"road_id":"p1"}"#);
+        assert!(roads()
+            .export_record("c1", r#"{"type":"FeatureCollection","features":[]}"#)
+            .is_err());
+        assert!(roads()
+            .export_record(
+                "f1",
+                r#"{"type":"Feature","properties":{},"geometry":null}"#
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_scan_page() {
+        let page = RespValue::Array(Some(vec![
+            RespValue::bulk("12"),
+            RespValue::Array(Some(vec![RespValue::bulk("a"), RespValue::bulk("b")])),
+        ]));
+        assert_eq!(
+            parse_scan_page(page).unwrap(),
+            ("12".to_string(), vec!["a".to_string(), "b".to_string()])
+        );
+        assert!(parse_scan_page(RespValue::Integer(1)).is_err());
+    }
+}
@@ -0,0 +1,314 @@
+//! 把 AOF 中的写命令重放到目标服务器，用于在预发环境复现生产负载、做容量规划
+//!
+//! 命令按记录的时间戳间隔发送，可以按倍速加快或放慢；倍速为 0 时不等待，尽快发送。
+//! AOF 逐行读取，文件再大也不会整体载入内存
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::client::{ClientConnection, ClientError};
+use crate::protocol::parser::RespValue;
+use crate::rtree::algorithms::aof::{AofCommand, AofError, AofReader};
+
+/// 把 AOF 记录转换为等价的客户端命令，重写标记没有对应的命令，返回空列表
+pub fn aof_to_command(cmd: &AofCommand) -> Vec<String> {
+    match cmd {
+        AofCommand::Insert {
+            collection,
+            key,
+            geojson,
+            tags,
+            ..
+        } => {
+            let mut args = vec![
+                "SET".to_string(),
+                collection.clone(),
+                key.clone(),
+                geojson.clone(),
+            ];
+            for tag in tags {
+                args.push("TAG".to_string());
+                args.push(tag.clone());
+            }
+            args
+        }
+        AofCommand::String {
+            collection,
+            key,
+            value,
+            tags,
+            ..
+        } => {
+            let mut args = vec![
+                "SET".to_string(),
+                collection.clone(),
+                key.clone(),
+                "STRING".to_string(),
+                value.clone(),
+            ];
+            for tag in tags {
+                args.push("TAG".to_string());
+                args.push(tag.clone());
+            }
+            args
+        }
+        AofCommand::Delete {
+            collection, key, ..
+        } => vec!["DELETE".to_string(), collection.clone(), key.clone()],
+        // 过期时刻换算为相对记录时间的秒数，按记录时的剩余时间重放
+        AofCommand::Expire {
+            collection,
+            key,
+            at: Some(at),
+            ts,
+            ..
+        } => vec![
+            "EXPIRE".to_string(),
+            collection.clone(),
+            key.clone(),
+            format!("{}", at.saturating_sub(*ts) as f64 / 1e9),
+        ],
+        AofCommand::Expire {
+            collection,
+            key,
+            at: None,
+            ..
+        } => vec!["PERSIST".to_string(), collection.clone(), key.clone()],
+        AofCommand::Drop { collection, .. } => vec!["DROP".to_string(), collection.clone()],
+        AofCommand::SetChan { name, args, .. } => ["SETCHAN".to_string(), name.clone()]
+            .into_iter()
+            .chain(args.iter().cloned())
+            .collect(),
+        AofCommand::DelChan { name, .. } => vec!["DELCHAN".to_string(), name.clone()],
+        AofCommand::Rewrite { .. } => Vec::new(),
+    }
+}
+
+/// 根据记录的时间戳计算每条命令的发送时刻
+#[derive(Debug, Clone)]
+pub struct ReplaySchedule {
+    speed: f64,
+    /// 第一条命令的时间戳（纳秒），作为时间轴的起点
+    origin: Option<u64>,
+}
+
+impl ReplaySchedule {
+    /// `speed` 为倍速（2.0 表示两倍速），0 表示不等待
+    pub fn new(speed: f64) -> Self {
+        Self {
+            speed,
+            origin: None,
+        }
+    }
+
+    /// 时间戳为 `ts` 的命令相对重放开始时应发送的时刻，不需要等待时返回 None
+    ///
+    /// 时间戳早于起点（时钟回拨、合并过的 AOF）的命令立即发送
+    pub fn due(&mut self, ts: u64) -> Option<Duration> {
+        if self.speed == 0.0 {
+            return None;
+        }
+        let origin = *self.origin.get_or_insert(ts);
+        let offset = ts.saturating_sub(origin) as f64 / self.speed;
+        Some(Duration::from_nanos(offset as u64))
+    }
+}
+
+/// 重放结果
+#[derive(Debug, Clone, Default)]
+pub struct ReplayStats {
+    /// 发送的命令数
+    pub sent: u64,
+    /// 服务器回复错误的命令数
+    pub errors: u64,
+    /// 无法解析而跳过的 AOF 行数
+    pub skipped: u64,
+    /// 实际发送时间落后于计划的最大值，持续增长说明目标服务器跟不上这个倍速
+    pub max_lag: Duration,
+    /// 重放总耗时
+    pub elapsed: Duration,
+}
+
+/// 把 AOF 重放到已连接的服务器
+///
+/// 服务器的错误回复只计数，连接错误会中止重放
+pub fn replay_aof(
+    path: PathBuf,
+    connection: &mut ClientConnection,
+    speed: f64,
+) -> std::result::Result<ReplayStats, ClientError> {
+    let mut reader = AofReader::open(path.clone()).map_err(|e| match e {
+        AofError::FileNotFound => {
+            ClientError::Protocol(format!("AOF file not found: {}", path.display()))
+        }
+        e => ClientError::Protocol(e.to_string()),
+    })?;
+    let mut schedule = ReplaySchedule::new(speed);
+    let mut stats = ReplayStats::default();
+    let started = Instant::now();
+
+    loop {
+        let cmd = match reader.read_next() {
+            Ok(Some(cmd)) => cmd,
+            Ok(None) => break,
+            // 损坏的行已经被读走，继续下一行
+            Err(AofError::InvalidCommand { .. }) => {
+                stats.skipped += 1;
+                continue;
+            }
+            Err(e) => return Err(ClientError::Protocol(e.to_string())),
+        };
+
+        let args = aof_to_command(&cmd);
+        if args.is_empty() {
+            continue;
+        }
+        if let Some(due) = schedule.due(cmd.timestamp()) {
+            let now = started.elapsed();
+            if due > now {
+                std::thread::sleep(due - now);
+            } else {
+                stats.max_lag = stats.max_lag.max(now - due);
+            }
+        }
+
+        if let RespValue::Error(_) = connection.request(&args)? {
+            stats.errors += 1;
+        }
+        stats.sent += 1;
+    }
+
+    stats.elapsed = started.elapsed();
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::RespParser;
+    use std::io::{BufReader, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_aof_to_command() {
+        let insert = AofCommand::insert("fleet".into(), "bus1".into(), "{}".into())
+            .with_tags(vec!["bus".into(), "line42".into()]);
+        assert_eq!(
+            aof_to_command(&insert),
+            vec!["SET", "fleet", "bus1", "{}", "TAG", "bus", "TAG", "line42"]
+        );
+        let string = AofCommand::string("fleet".into(), "note".into(), "hi".into())
+            .with_tags(vec!["memo".into()]);
+        assert_eq!(
+            aof_to_command(&string),
+            vec!["SET", "fleet", "note", "STRING", "hi", "TAG", "memo"]
+        );
+        assert_eq!(
+            aof_to_command(&AofCommand::delete("fleet".into(), "bus1".into())),
+            vec!["DELETE", "fleet", "bus1"]
+        );
+        let expire = AofCommand::expire("fleet".into(), "bus1".into(), Some(2_500_000_000))
+            .with_timestamp(1_000_000_000);
+        assert_eq!(
+            aof_to_command(&expire),
+            vec!["EXPIRE", "fleet", "bus1", "1.5"]
+        );
+        assert_eq!(
+            aof_to_command(&AofCommand::expire("fleet".into(), "bus1".into(), None)),
+            vec!["PERSIST", "fleet", "bus1"]
+        );
+        assert_eq!(
+            aof_to_command(&AofCommand::drop("fleet".into())),
+            vec!["DROP", "fleet"]
+        );
+        let set_chan = AofCommand::set_chan(
+            "zone".into(),
+            vec!["NEARBY".into(), "fleet".into(), "POINT".into()],
+        );
+        assert_eq!(
+            aof_to_command(&set_chan),
+            vec!["SETCHAN", "zone", "NEARBY", "fleet", "POINT"]
+        );
+        assert_eq!(
+            aof_to_command(&AofCommand::del_chan("zone".into())),
+            vec!["DELCHAN", "zone"]
+        );
+        assert!(aof_to_command(&AofCommand::rewrite(0)).is_empty());
+    }
+
+    #[test]
+    fn test_schedule_speed() {
+        let mut schedule = ReplaySchedule::new(2.0);
+        assert_eq!(schedule.due(5_000_000_000), Some(Duration::ZERO));
+        assert_eq!(schedule.due(7_000_000_000), Some(Duration::from_secs(1)));
+        // 时间戳倒退时立即发送
+        assert_eq!(schedule.due(1_000_000_000), Some(Duration::ZERO));
+
+        let mut schedule = ReplaySchedule::new(0.5);
+        schedule.due(0);
+        assert_eq!(schedule.due(1_000_000_000), Some(Duration::from_secs(2)));
+
+        let mut schedule = ReplaySchedule::new(0.0);
+        assert_eq!(schedule.due(0), None);
+        assert_eq!(schedule.due(u64::MAX), None);
+    }
+
+    #[test]
+    fn test_replay_to_server() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("appendonly.aof");
+        let records = [
+            AofCommand::insert("fleet".into(), "truck1".into(), "{}".into()).with_timestamp(0),
+            AofCommand::delete("fleet".into(), "truck1".into()).with_timestamp(40_000_000),
+            AofCommand::drop("fleet".into()).with_timestamp(80_000_000),
+        ];
+        let mut content = String::new();
+        for (i, record) in records.iter().enumerate() {
+            content.push_str(&serde_json::to_string(record).unwrap());
+            content.push('\n');
+            if i == 0 {
+                content.push_str("{not json\n");
+            }
+        }
+        std::fs::write(&path, content).unwrap();
+
+        // 记录收到的命令，DELETE 回复错误
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (socket, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(socket);
+            let mut received = Vec::new();
+            for _ in 0..3 {
+                let RespValue::Array(Some(args)) =
+                    RespParser::new().parse_from(&mut reader).unwrap()
+                else {
+                    panic!("expected array");
+                };
+                let name = args[0].as_str().unwrap().to_string();
+                let reply: &[u8] = if name == "DELETE" {
+                    b"-ERR nope\r\n"
+                } else {
+                    b"+OK\r\n"
+                };
+                reader.get_mut().write_all(reply).unwrap();
+                received.push(name);
+            }
+            received
+        });
+
+        let mut connection = ClientConnection::new("127.0.0.1", port);
+        connection.connect().unwrap();
+        let stats = replay_aof(path, &mut connection, 4.0).unwrap();
+
+        assert_eq!(server.join().unwrap(), vec!["SET", "DELETE", "DROP"]);
+        assert_eq!(stats.sent, 3);
+        assert_eq!(stats.errors, 1);
+        assert_eq!(stats.skipped, 1);
+        // 80ms 的记录跨度按四倍速需要 20ms
+        assert!(stats.elapsed >= Duration::from_millis(20));
+
+        let err = replay_aof(dir.path().join("missing.aof"), &mut connection, 1.0).unwrap_err();
+        assert!(err.to_string().contains("AOF file not found"));
+    }
+}
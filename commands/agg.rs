@@ -0,0 +1,132 @@
+use crate::commands::args::ArgumentParser;
+use crate::commands::Command;
+use crate::protocol::{parser::RespValue, RespResponse};
+use crate::storage::GeoDatabase;
+use crate::Result;
+use std::sync::Arc;
+
+/// AGG 命令：按网格或六边形分箱统计对象数量，用于服务端生成热力图数据
+pub struct AggCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl AggCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+impl Command for AggCommand {
+    fn name(&self) -> &'static str {
+        "AGG"
+    }
+
+    fn execute(
+        &self,
+        args: &[RespValue],
+    ) -> impl std::future::Future<Output = Result<String>> + Send {
+        let database = Arc::clone(&self.database);
+
+        // 同步解析参数
+        let parse_result = ArgumentParser::new(args, "AGG").parse_agg_args();
+
+        async move {
+            // 检查参数解析结果
+            let parsed_args = match parse_result {
+                Ok(args) => args,
+                Err(err_msg) => {
+                    return Ok(RespResponse::error(&err_msg));
+                }
+            };
+
+            match database
+                .aggregate(
+                    &parsed_args.collection_id,
+                    &parsed_args.bounds,
+                    parsed_args.binning,
+                    parsed_args.field.as_deref(),
+                )
+                .await
+            {
+                Ok(stats) => {
+                    if stats.is_empty() {
+                        return Ok(RespResponse::array(None));
+                    }
+
+                    // 每个格子: [lon, lat, count]，指定 FIELD 时为 [lon, lat, count, sum, avg]
+                    let resp_values: Vec<RespValue> = stats
+                        .into_iter()
+                        .map(|stat| {
+                            let mut cell = vec![
+                                RespValue::bulk(stat.center[0].to_string()),
+                                RespValue::bulk(stat.center[1].to_string()),
+                                RespValue::Integer(stat.count as i64),
+                            ];
+                            if parsed_args.field.is_some() {
+                                cell.push(RespValue::bulk(stat.sum.to_string()));
+                                cell.push(RespValue::BulkString(
+                                    stat.avg().map(|avg| avg.to_string().into_bytes()),
+                                ));
+                            }
+                            RespValue::Array(Some(cell))
+                        })
+                        .collect();
+
+                    Ok(RespResponse::array(Some(&resp_values)))
+                }
+                Err(e) => Ok(RespResponse::command_error(
+                    "aggregate query failed",
+                    e.as_ref(),
+                )),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::args;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_agg_command() {
+        let database = Arc::new(GeoDatabase::new());
+        for (id, lon, lat, speed) in [
+            ("v1", 116.401, 39.901, 10.0),
+            ("v2", 116.402, 39.902, 30.0),
+            ("v3", 116.415, 39.905, 50.0),
+        ] {
+            let feature = json!({
+                "type": "Feature",
+                "properties": {"speed": speed},
+                "geometry": {"type": "Point", "coordinates": [lon, lat]}
+            });
+            database
+                .set("fleet", id, &feature.to_string())
+                .await
+                .unwrap();
+        }
+
+        let cmd = AggCommand::new(Arc::clone(&database));
+        let args = args(&[
+            "fleet", "BOUNDS", "116.4", "39.9", "116.42", "39.91", "GRID", "0.01", "FIELD", "speed",
+        ]);
+
+        let result = cmd.execute(&args).await.unwrap();
+
+        // 两个格子：v1、v2 在同一个格子，v3 在相邻格子
+        assert!(result.starts_with("*2\r\n*5\r\n"));
+        assert!(result.contains(":2\r\n$2\r\n40\r\n$2\r\n20\r\n"));
+        assert!(result.contains(":1\r\n$2\r\n50\r\n$2\r\n50\r\n"));
+
+        // 不存在的 collection
+        let mut args = args;
+        args[0] = RespValue::bulk("missing");
+        let result = cmd.execute(&args).await.unwrap();
+        assert_eq!(
+            result,
+            "-NOINDEX aggregate query failed: collection 'missing' does not exist\r\n"
+        );
+    }
+}
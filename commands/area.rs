@@ -0,0 +1,227 @@
+use crate::rtree::rectangle::Rectangle;
+use geo::{Coord, Geometry, LineString, Polygon, Rect};
+
+/// 地球平均半径（米），与 NEARBY 的 Haversine 距离相同
+const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+
+/// CIRCLE 近似为正多边形时的边数：半径 500 米时边的中点离圆周不到 1 米
+const CIRCLE_SEGMENTS: usize = 64;
+
+/// 瓦片和 QUADKEY 的最大缩放级别
+pub const MAX_TILE_ZOOM: u32 = 30;
+
+/// Web 墨卡托能表示的最大纬度
+const MAX_MERCATOR_LAT: f64 = 85.051_128_779_806_59;
+
+/// `CIRCLE lon lat meters`：以 (lon, lat) 为圆心、meters 为半径的圆，近似为球面上的正多边形
+pub fn circle(lon: f64, lat: f64, meters: f64) -> std::result::Result<Geometry, String> {
+    check_lon_lat(lon, lat)?;
+    if !(meters > 0.0 && meters.is_finite()) {
+        return Err("ERR CIRCLE radius must be greater than 0".to_string());
+    }
+
+    let (lon_rad, lat_rad) = (lon.to_radians(), lat.to_radians());
+    let angular = meters / EARTH_RADIUS_METERS;
+    let mut ring: Vec<Coord> = (0..CIRCLE_SEGMENTS)
+        .map(|i| {
+            // 球面上从圆心沿方位角 bearing 前进 meters 到达的点
+            let bearing = 2.0 * std::f64::consts::PI * i as f64 / CIRCLE_SEGMENTS as f64;
+            let lat2 = (lat_rad.sin() * angular.cos()
+                + lat_rad.cos() * angular.sin() * bearing.cos())
+            .asin();
+            let lon2 = lon_rad
+                + (bearing.sin() * angular.sin() * lat_rad.cos())
+                    .atan2(angular.cos() - lat_rad.sin() * lat2.sin());
+            Coord {
+                x: lon2.to_degrees(),
+                y: lat2.to_degrees(),
+            }
+        })
+        .collect();
+    ring.push(ring[0]);
+    Ok(Geometry::Polygon(Polygon::new(
+        LineString::new(ring),
+        vec![],
+    )))
+}
+
+/// `BOUNDS minlat minlon maxlat maxlon`：经纬度矩形（与 Tile38 相同，纬度在前）
+pub fn bounds(
+    min_lat: f64,
+    min_lon: f64,
+    max_lat: f64,
+    max_lon: f64,
+) -> std::result::Result<Geometry, String> {
+    check_lon_lat(min_lon, min_lat)?;
+    check_lon_lat(max_lon, max_lat)?;
+    if min_lat > max_lat || min_lon > max_lon {
+        return Err("ERR invalid BOUNDS: minimum must not exceed maximum".to_string());
+    }
+    Ok(rect_geometry(Rectangle::new(
+        min_lon, min_lat, max_lon, max_lat,
+    )))
+}
+
+/// `TILE x y z`：XYZ（slippy map）瓦片覆盖的范围
+pub fn tile(x: u64, y: u64, z: u32) -> std::result::Result<Geometry, String> {
+    tile_bounds(x, y, z).map(rect_geometry)
+}
+
+/// `QUADKEY key`：Bing 地图 quadkey 对应瓦片覆盖的范围
+pub fn quadkey(key: &str) -> std::result::Result<Geometry, String> {
+    let (x, y, z) = quadkey_to_tile(key)?;
+    tile(x, y, z)
+}
+
+/// 瓦片的经纬度范围
+pub fn tile_bounds(x: u64, y: u64, z: u32) -> std::result::Result<Rectangle, String> {
+    if z > MAX_TILE_ZOOM {
+        return Err(format!(
+            "ERR invalid TILE zoom: must be between 0 and {}, got {}",
+            MAX_TILE_ZOOM, z
+        ));
+    }
+    let n = 1u64 << z;
+    if x >= n || y >= n {
+        return Err(format!(
+            "ERR invalid TILE: x and y must be less than {} at zoom {}",
+            n, z
+        ));
+    }
+
+    let n = n as f64;
+    let lon = |x: u64| x as f64 / n * 360.0 - 180.0;
+    let lat = |y: u64| {
+        (std::f64::consts::PI * (1.0 - 2.0 * y as f64 / n))
+            .sinh()
+            .atan()
+            .to_degrees()
+            .clamp(-MAX_MERCATOR_LAT, MAX_MERCATOR_LAT)
+    };
+    // 瓦片的 y 从北往南增大
+    Ok(Rectangle::new(lon(x), lat(y + 1), lon(x + 1), lat(y)))
+}
+
+/// 把 quadkey 转换为瓦片坐标 (x, y, z)，每一位 0-3 依次给出 x 和 y 的一个二进制位
+pub fn quadkey_to_tile(key: &str) -> std::result::Result<(u64, u64, u32), String> {
+    if key.is_empty() || key.len() > MAX_TILE_ZOOM as usize {
+        return Err(format!(
+            "ERR invalid QUADKEY: expected 1 to {} digits, got '{}'",
+            MAX_TILE_ZOOM, key
+        ));
+    }
+
+    let (mut x, mut y) = (0u64, 0u64);
+    for digit in key.chars() {
+        let d = digit
+            .to_digit(4)
+            .ok_or_else(|| format!("ERR invalid QUADKEY: digits must be 0-3, got '{}'", key))?;
+        x = (x << 1) | u64::from(d & 1);
+        y = (y << 1) | u64::from(d >> 1);
+    }
+    Ok((x, y, key.len() as u32))
+}
+
+fn rect_geometry(rect: Rectangle) -> Geometry {
+    let rect = Rect::new(
+        Coord {
+            x: rect.min[0],
+            y: rect.min[1],
+        },
+        Coord {
+            x: rect.max[0],
+            y: rect.max[1],
+        },
+    );
+    Geometry::Polygon(rect.to_polygon())
+}
+
+fn check_lon_lat(lon: f64, lat: f64) -> std::result::Result<(), String> {
+    if !(-180.0..=180.0).contains(&lon) {
+        return Err(format!(
+            "ERR invalid longitude: must be between -180 and 180, got {}",
+            lon
+        ));
+    }
+    if !(-90.0..=90.0).contains(&lat) {
+        return Err(format!(
+            "ERR invalid latitude: must be between -90 and 90, got {}",
+            lat
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtree::algorithms::knn::haversine_distance;
+    use geo::{Contains, Point};
+
+    #[test]
+    fn test_circle() {
+        let Geometry::Polygon(polygon) = circle(116.4, 39.9, 500.0).unwrap() else {
+            panic!("expected a polygon");
+        };
+        assert_eq!(polygon.exterior().0.len(), CIRCLE_SEGMENTS + 1);
+        for coord in polygon.exterior().coords() {
+            let distance = haversine_distance(116.4, 39.9, coord.x, coord.y);
+            assert!((distance - 500.0).abs() < 0.01, "{}", distance);
+        }
+        // 约 111 米一个点：400 米处在圆内，600 米处在圆外
+        assert!(polygon.contains(&Point::new(116.4, 39.9036)));
+        assert!(!polygon.contains(&Point::new(116.4, 39.9054)));
+
+        assert!(circle(116.4, 39.9, 0.0).is_err());
+        assert!(circle(181.0, 39.9, 10.0).is_err());
+    }
+
+    #[test]
+    fn test_bounds() {
+        let Geometry::Polygon(polygon) = bounds(39.0, 116.0, 40.0, 117.0).unwrap() else {
+            panic!("expected a polygon");
+        };
+        assert!(polygon.contains(&Point::new(116.5, 39.5)));
+        assert!(!polygon.contains(&Point::new(39.5, 116.5)));
+
+        assert!(bounds(40.0, 116.0, 39.0, 117.0).is_err());
+        assert!(bounds(39.0, 116.0, 91.0, 117.0).is_err());
+    }
+
+    #[test]
+    fn test_tile_bounds() {
+        let world = tile_bounds(0, 0, 0).unwrap();
+        assert_eq!((world.min[0], world.max[0]), (-180.0, 180.0));
+        assert!((world.max[1] - MAX_MERCATOR_LAT).abs() < 1e-9);
+
+        // 缩放级别 1 的右下角瓦片：东经、南半球
+        let tile = tile_bounds(1, 1, 1).unwrap();
+        assert_eq!((tile.min[0], tile.max[0]), (0.0, 180.0));
+        assert!(tile.max[1].abs() < 1e-9 && tile.min[1] < -85.0);
+
+        assert!(tile_bounds(2, 0, 1).is_err());
+        assert!(tile_bounds(0, 0, MAX_TILE_ZOOM + 1).is_err());
+    }
+
+    #[test]
+    fn test_quadkey_to_tile() {
+        // Bing 地图文档中的例子
+        assert_eq!(quadkey_to_tile("213").unwrap(), (3, 5, 3));
+        assert_eq!(quadkey_to_tile("0").unwrap(), (0, 0, 1));
+        assert_eq!(quadkey_to_tile("3").unwrap(), (1, 1, 1));
+        assert_eq!(
+            tile_bounds(3, 5, 3).unwrap(),
+            match quadkey("213").unwrap() {
+                Geometry::Polygon(polygon) => {
+                    let rect = geo::BoundingRect::bounding_rect(&polygon).unwrap();
+                    Rectangle::new(rect.min().x, rect.min().y, rect.max().x, rect.max().y)
+                }
+                _ => panic!("expected a polygon"),
+            }
+        );
+
+        assert!(quadkey_to_tile("").is_err());
+        assert!(quadkey_to_tile("124").is_err());
+        assert!(quadkey_to_tile(&"1".repeat(31)).is_err());
+    }
+}
@@ -1,6 +1,23 @@
+use crate::commands::area;
+use crate::commands::fields::FieldSelection;
+use crate::commands::json::JsonPath;
 use crate::protocol::parser::RespValue;
-use crate::storage::geometry_utils::geojson_to_geometry;
-use geo::Geometry;
+use crate::rtree::algorithms::aggregate::Binning;
+use crate::rtree::algorithms::cursor::ScanCursor;
+use crate::rtree::algorithms::filter::{FieldRange, GeometryKind, ObjectFilter};
+use crate::rtree::algorithms::hull::HullKind;
+use crate::rtree::algorithms::knn::DistanceMetric;
+use crate::rtree::algorithms::overlay::OverlayOp;
+use crate::rtree::algorithms::search::SearchOrder;
+use crate::rtree::rectangle::Rectangle;
+use crate::storage::geohash;
+use crate::storage::geometry_utils::{geojson_to_geometry, MAX_COORDINATE_PRECISION};
+use geo::{BoundingRect, Geometry, Rect};
+
+/// 围栏推送对象的变化，不能只返回数量或 id
+const FENCE_OUTPUT_ERROR: &str =
+    "ERR FENCE cannot be combined with IDS, COUNT, POINTS, BOUNDS or HASHES";
+const DWELL_ERROR: &str = "ERR DWELL requires FENCE";
 
 /// 参数解析工具
 pub struct ArgumentParser<'a> {
@@ -26,15 +43,106 @@ impl<'a> ArgumentParser<'a> {
         Ok(())
     }
 
-    /// 获取字符串参数
+    /// 获取字符串参数，内容必须是合法的 UTF-8
     pub fn get_string(&self, index: usize, param_name: &str) -> std::result::Result<&str, String> {
+        std::str::from_utf8(self.get_bytes(index, param_name)?)
+            .map_err(|_| format!("ERR invalid {}: expected UTF-8 string", param_name))
+    }
+
+    /// 获取原始字节参数，不检查编码
+    pub fn get_bytes(&self, index: usize, param_name: &str) -> std::result::Result<&[u8], String> {
         match self.args.get(index) {
-            Some(RespValue::BulkString(Some(s))) => Ok(s),
+            Some(RespValue::BulkString(Some(bytes))) => Ok(bytes),
             Some(_) => Err(format!("ERR invalid {}: expected string", param_name)),
             None => Err(format!("ERR missing {} parameter", param_name)),
         }
     }
 
+    /// 获取并解析 FIELDS 的字段列表
+    pub fn get_fields(&self, index: usize) -> std::result::Result<FieldSelection, String> {
+        if index >= self.args.len() {
+            return Err("ERR FIELDS option requires a comma-separated list of fields".to_string());
+        }
+        let spec = self.get_string(index, "FIELDS value")?;
+        FieldSelection::parse(spec)
+    }
+
+    /// 获取 PRECISION 选项的小数位数
+    pub fn get_precision(&self, index: usize) -> std::result::Result<u32, String> {
+        if index >= self.args.len() {
+            return Err("ERR PRECISION option requires a number of decimal places".to_string());
+        }
+        let value = self.get_string(index, "PRECISION value")?;
+        match value.parse::<u32>() {
+            Ok(decimals) if decimals <= MAX_COORDINATE_PRECISION => Ok(decimals),
+            _ => Err(format!(
+                "ERR invalid PRECISION value: expected an integer between 0 and {}, got '{}'",
+                MAX_COORDINATE_PRECISION, value
+            )),
+        }
+    }
+
+    /// 解析查询的输出选择（OBJECTS、IDS、COUNT、POINTS、BOUNDS、HASHES n），返回占用的参数个数
+    fn set_query_output(
+        &self,
+        index: usize,
+        output: &mut Option<QueryOutput>,
+    ) -> std::result::Result<usize, String> {
+        let keyword = self.get_string(index, "output")?.to_uppercase();
+        let (value, consumed) = match keyword.as_str() {
+            "OBJECTS" => (QueryOutput::Objects, 1),
+            "IDS" => (QueryOutput::Ids, 1),
+            "COUNT" => (QueryOutput::Count, 1),
+            "POINTS" => (QueryOutput::Points, 1),
+            "BOUNDS" => (QueryOutput::Bounds, 1),
+            "HASHES" => match self.get_integer(index + 1, "HASHES precision")? {
+                p @ 1..=geohash::MAX_PRECISION => (QueryOutput::Hashes(p), 2),
+                p => {
+                    return Err(format!(
+                        "ERR invalid HASHES precision: expected 1 to {}, got {}",
+                        geohash::MAX_PRECISION,
+                        p
+                    ))
+                }
+            },
+            _ => return Err(format!("ERR unknown output '{}'", keyword)),
+        };
+        match output {
+            Some(previous) if *previous == value => {
+                Err(format!("ERR duplicate {} keyword", keyword))
+            }
+            Some(_) => Err(
+                "ERR only one of OBJECTS, IDS, COUNT, POINTS, BOUNDS or HASHES can be given"
+                    .to_string(),
+            ),
+            None => {
+                *output = Some(value);
+                Ok(consumed)
+            }
+        }
+    }
+
+    /// 获取分页游标，`0` 表示从头开始
+    pub fn get_cursor(&self, index: usize) -> std::result::Result<ScanCursor, String> {
+        if index >= self.args.len() {
+            return Err("ERR CURSOR option requires a cursor".to_string());
+        }
+        let token = self.get_string(index, "cursor")?;
+        ScanCursor::parse(token).ok_or_else(|| format!("ERR invalid cursor '{}'", token))
+    }
+
+    /// 获取 TAG / WHERETAG 选项的标签值，标签不能为空
+    pub fn get_tag(&self, index: usize, option: &str) -> std::result::Result<String, String> {
+        if index >= self.args.len() {
+            return Err(format!("ERR {} option requires a tag name", option));
+        }
+        let tag = self.get_string(index, "tag")?;
+        if tag.is_empty() {
+            return Err(format!("ERR {} value must not be empty", option));
+        }
+        Ok(tag.to_string())
+    }
+
     /// 获取并解析 GeoJSON 参数
     pub fn get_geojson(&self, index: usize) -> std::result::Result<serde_json::Value, String> {
         let geojson_str = self.get_string(index, "GeoJSON")?;
@@ -64,6 +172,47 @@ impl<'a> ArgumentParser<'a> {
         geojson_to_geometry(geojson_str).map_err(|e| format!("ERR invalid GeoJSON geometry: {}", e))
     }
 
+    /// 获取 INTERSECTS 的查询区域，返回几何体和区域之后第一个参数的位置
+    ///
+    /// 除 GeoJSON 外还支持 Tile38 的写法：`CIRCLE lon lat meters`、
+    /// `BOUNDS minlat minlon maxlat maxlon`、`TILE x y z` 和 `QUADKEY key`
+    pub fn get_query_area(&self, index: usize) -> std::result::Result<(Geometry, usize), String> {
+        let shape = self.get_string(index, "GeoJSON")?.to_uppercase();
+        let arity = match shape.as_str() {
+            "CIRCLE" | "TILE" => 3,
+            "BOUNDS" => 4,
+            "QUADKEY" => 1,
+            _ => return Ok((self.get_geometry(index)?, index + 1)),
+        };
+        if index + arity >= self.args.len() {
+            return Err(format!("ERR {} requires {} values", shape, arity));
+        }
+
+        let float = |offset: usize, name: &str| self.get_float(index + offset, name);
+        let geometry = match shape.as_str() {
+            "CIRCLE" => area::circle(
+                float(1, "longitude")?,
+                float(2, "latitude")?,
+                float(3, "CIRCLE radius")?,
+            )?,
+            "BOUNDS" => area::bounds(
+                float(1, "BOUNDS minlat")?,
+                float(2, "BOUNDS minlon")?,
+                float(3, "BOUNDS maxlat")?,
+                float(4, "BOUNDS maxlon")?,
+            )?,
+            "TILE" => {
+                let x = self.get_integer(index + 1, "TILE x")?;
+                let y = self.get_integer(index + 2, "TILE y")?;
+                let z = self.get_integer(index + 3, "TILE z")?;
+                let z = u32::try_from(z).unwrap_or(u32::MAX);
+                area::tile(x as u64, y as u64, z)?
+            }
+            _ => area::quadkey(self.get_string(index + 1, "QUADKEY")?)?,
+        };
+        Ok((geometry, index + arity + 1))
+    }
+
     /// 验证 GeoJSON 基本格式
     fn validate_geojson(&self, geojson: &serde_json::Value) -> std::result::Result<(), String> {
         if !geojson.is_object() {
@@ -78,30 +227,124 @@ impl<'a> ArgumentParser<'a> {
     }
 
     /// 解析 SET 命令的参数
+    /// 语法: SET collection id geojson|HASH geohash|STRING value [TAG tag ...] [EX seconds]
     pub fn parse_set_args(&self) -> std::result::Result<SetArgs, String> {
-        self.check_arg_count(3)?;
+        if self.args.len() < 3 {
+            return Err(format!(
+                "ERR wrong number of arguments for 'SET' command. Expected at least 3, got {}",
+                self.args.len()
+            ));
+        }
 
         let collection_id = self.get_string(0, "collection ID")?;
         let item_id = self.get_string(1, "item ID")?;
         let geojson = self.get_string(2, "GeoJSON")?;
 
+        // HASH geohash：存储 geohash 格子的中心点；STRING value：存储文本
+        let is_string = geojson.eq_ignore_ascii_case("STRING");
+        let (geojson, mut i) = if geojson.eq_ignore_ascii_case("HASH") {
+            let (lon, lat) = geohash::decode(self.get_string(3, "geohash")?)?;
+            let point = serde_json::json!({"type": "Point", "coordinates": [lon, lat]});
+            (point.to_string(), 4)
+        } else if is_string {
+            (self.get_string(3, "string value")?.to_string(), 4)
+        } else {
+            (geojson.to_string(), 3)
+        };
+
+        let mut tags = Vec::new();
+        let mut expire_seconds = None;
+        while i < self.args.len() {
+            let key = self.get_string(i, "option key")?.to_uppercase();
+            match key.as_str() {
+                "TAG" => tags.push(self.get_tag(i + 1, "TAG")?),
+                "EX" if expire_seconds.is_some() => {
+                    return Err("ERR duplicate EX keyword".to_string())
+                }
+                "EX" => {
+                    let seconds = self.get_float(i + 1, "EX seconds")?;
+                    if seconds <= 0.0 {
+                        return Err("ERR invalid expire time in 'SET' command".to_string());
+                    }
+                    expire_seconds = Some(seconds);
+                }
+                _ => return Err(format!("ERR unknown option '{}' for SET command", key)),
+            }
+            i += 2;
+        }
+
         Ok(SetArgs {
             collection_id: collection_id.to_string(),
             item_id: item_id.to_string(),
-            geojson: geojson.to_string(),
+            geojson,
+            tags,
+            expire_seconds,
+            is_string,
         })
     }
 
     /// 解析 GET 命令的参数
+    /// 语法: GET collection id [PART n] [PRECISION n] [OBJECT|POINT|BOUNDS|HASH precision]
     pub fn parse_get_args(&self) -> std::result::Result<GetArgs, String> {
-        self.check_arg_count(2)?;
+        if self.args.len() < 2 {
+            self.check_arg_count(2)?;
+        }
 
         let collection_id = self.get_string(0, "collection ID")?;
         let item_id = self.get_string(1, "item ID")?;
 
+        let mut part = None;
+        let mut precision = None;
+        let mut output = None;
+        let mut i = 2;
+        while i < self.args.len() {
+            let option = self.get_string(i, "option key")?;
+            let keyword = option.to_uppercase();
+            let value = match keyword.as_str() {
+                "OBJECT" | "POINT" | "BOUNDS" | "HASH" if output.is_some() => {
+                    return Err(
+                        "ERR only one of OBJECT, POINT, BOUNDS or HASH can be given".to_string()
+                    )
+                }
+                "OBJECT" => Some(GetOutput::Object),
+                "POINT" => Some(GetOutput::Point),
+                "BOUNDS" => Some(GetOutput::Bounds),
+                "HASH" => {
+                    i += 1;
+                    match self.get_integer(i, "HASH precision")? {
+                        p @ 1..=geohash::MAX_PRECISION => Some(GetOutput::Hash(p)),
+                        p => {
+                            return Err(format!(
+                                "ERR invalid HASH precision: expected 1 to {}, got {}",
+                                geohash::MAX_PRECISION,
+                                p
+                            ))
+                        }
+                    }
+                }
+                "PART" if part.is_none() => {
+                    i += 1;
+                    part = Some(self.get_integer(i, "PART index")?);
+                    None
+                }
+                "PRECISION" if precision.is_none() => {
+                    i += 1;
+                    precision = Some(self.get_precision(i)?);
+                    None
+                }
+                "PART" | "PRECISION" => return Err(format!("ERR duplicate {} keyword", keyword)),
+                _ => return Err(format!("ERR unknown option '{}' for GET command", option)),
+            };
+            output = output.or(value);
+            i += 1;
+        }
+
         Ok(GetArgs {
             collection_id: collection_id.to_string(),
             item_id: item_id.to_string(),
+            part,
+            precision,
+            output: output.unwrap_or(GetOutput::Object),
         })
     }
 
@@ -118,8 +361,122 @@ impl<'a> ArgumentParser<'a> {
         })
     }
 
+    /// 解析 PDEL 命令的参数
+    /// 语法: PDEL collection pattern
+    pub fn parse_pdel_args(&self) -> std::result::Result<PdelArgs, String> {
+        self.check_arg_count(2)?;
+
+        let collection_id = self.get_string(0, "collection ID")?;
+        let pattern = self.get_string(1, "pattern")?;
+
+        Ok(PdelArgs {
+            collection_id: collection_id.to_string(),
+            pattern: pattern.to_string(),
+        })
+    }
+
+    /// 解析 JSET 命令的参数
+    /// 语法: JSET collection id path value [RAW|STR]
+    ///
+    /// 默认值是有效的 JSON（数字、true、对象等）时按 JSON 解析，否则作为字符串；
+    /// RAW 要求值是有效的 JSON，STR 总是作为字符串
+    pub fn parse_jset_args(&self) -> std::result::Result<JsetArgs, String> {
+        if self.args.len() != 4 && self.args.len() != 5 {
+            return Err(format!(
+                "ERR wrong number of arguments for '{}' command. Usage: JSET collection id path value [RAW|STR]",
+                self.command_name
+            ));
+        }
+
+        let collection_id = self.get_string(0, "collection ID")?;
+        let item_id = self.get_string(1, "item ID")?;
+        let path = JsonPath::parse(self.get_string(2, "path")?)?;
+        let value = self.get_string(3, "value")?;
+
+        let mode = match self.args.len() {
+            5 => self.get_string(4, "option")?.to_uppercase(),
+            _ => String::new(),
+        };
+        let value = match mode.as_str() {
+            "" => serde_json::from_str(value)
+                .unwrap_or_else(|_| serde_json::Value::String(value.to_string())),
+            "RAW" => serde_json::from_str(value)
+                .map_err(|e| format!("ERR invalid JSON value for RAW: {}", e))?,
+            "STR" => serde_json::Value::String(value.to_string()),
+            other => {
+                return Err(format!(
+                    "ERR unknown option '{}' for JSET command, expected RAW or STR",
+                    other
+                ))
+            }
+        };
+
+        Ok(JsetArgs {
+            collection_id: collection_id.to_string(),
+            item_id: item_id.to_string(),
+            path,
+            value,
+        })
+    }
+
+    /// 解析 JGET 命令的参数
+    /// 语法: JGET collection id path [RAW]
+    pub fn parse_jget_args(&self) -> std::result::Result<JgetArgs, String> {
+        let raw = match self.args.len() {
+            3 => false,
+            4 if self.get_string(3, "option")?.eq_ignore_ascii_case("RAW") => true,
+            4 => {
+                return Err(format!(
+                    "ERR unknown option '{}' for JGET command, expected RAW",
+                    self.get_string(3, "option")?
+                ))
+            }
+            _ => {
+                return Err(format!(
+                    "ERR wrong number of arguments for '{}' command. Usage: JGET collection id path [RAW]",
+                    self.command_name
+                ))
+            }
+        };
+
+        Ok(JgetArgs {
+            collection_id: self.get_string(0, "collection ID")?.to_string(),
+            item_id: self.get_string(1, "item ID")?.to_string(),
+            path: JsonPath::parse(self.get_string(2, "path")?)?,
+            raw,
+        })
+    }
+
+    /// 解析 JDEL 命令的参数
+    /// 语法: JDEL collection id path
+    pub fn parse_jdel_args(&self) -> std::result::Result<JdelArgs, String> {
+        self.check_arg_count(3)?;
+
+        Ok(JdelArgs {
+            collection_id: self.get_string(0, "collection ID")?.to_string(),
+            item_id: self.get_string(1, "item ID")?.to_string(),
+            path: JsonPath::parse(self.get_string(2, "path")?)?,
+        })
+    }
+
+    /// 解析 EXPIRE 命令的参数
+    /// 语法: EXPIRE collection id seconds
+    pub fn parse_expire_args(&self) -> std::result::Result<ExpireArgs, String> {
+        self.check_arg_count(3)?;
+
+        let collection_id = self.get_string(0, "collection ID")?;
+        let item_id = self.get_string(1, "item ID")?;
+        let seconds = self.get_float(2, "seconds")?;
+
+        Ok(ExpireArgs {
+            collection_id: collection_id.to_string(),
+            item_id: item_id.to_string(),
+            seconds,
+        })
+    }
+
     /// 解析 INTERSECTS 命令的参数
-    /// 语法: INTERSECTS collection geojson [WITHIN true|false] [LIMIT n]
+    /// 语法: INTERSECTS collection geojson [WITHIN true|false] [LIMIT n] [ORDER CENTER|ID|NONE] [FIELDS f1,f2] [NOFIELDS|NOGEOM] [OBJECTS|IDS|COUNT|POINTS|BOUNDS|HASHES n] [PRECISION n] [FENCE [DWELL seconds]]
     pub fn parse_intersects_args(&self) -> std::result::Result<IntersectsArgs, String> {
         // 至少需要2个参数: collection 和 geojson
         if self.args.len() < 2 {
@@ -130,13 +487,22 @@ impl<'a> ArgumentParser<'a> {
         }
 
         let collection_id = self.get_string(0, "collection ID")?;
-        let geometry = self.get_geometry(1)?;
+        let (geometry, mut i) = self.get_query_area(1)?;
 
-        // 解析可选参数: WITHIN 和 LIMIT
+        // 解析可选参数: WITHIN、LIMIT、ORDER、FIELDS 和 NOFIELDS/NOGEOM
         let mut within = false; // 默认为 false (相交查询)
         let mut limit = 0; // 默认无限制
-
-        let mut i = 2;
+        let mut order = SearchOrder::Tree; // 默认按树内顺序遍历
+        let mut fields = None; // 默认返回完整 GeoJSON
+        let mut bbox_only = false; // 默认返回完整 GeoJSON
+        let mut output = None; // 默认返回对象
+        let mut precision = None; // 默认使用全局设置
+        let mut tags = Vec::new(); // 默认不按标签过滤
+        let mut cursor = None; // 默认不分页
+        let mut fence = false; // 默认执行查询
+        let mut dwell = None; // 默认不产生停留事件
+
+        let area_end = i;
         while i < self.args.len() {
             let key = self.get_string(i, "option key")?.to_uppercase();
 
@@ -167,10 +533,61 @@ impl<'a> ArgumentParser<'a> {
                     limit = self.get_integer(i + 1, "LIMIT value")?;
                     i += 2;
                 }
+                "ORDER" => {
+                    if i + 1 >= self.args.len() {
+                        return Err(
+                            "ERR ORDER option requires a value (CENTER, ID or NONE)".to_string()
+                        );
+                    }
+                    let value = self.get_string(i + 1, "ORDER value")?;
+                    order = match value.to_uppercase().as_str() {
+                        "CENTER" => SearchOrder::Center,
+                        "ID" => SearchOrder::Id,
+                        "NONE" => SearchOrder::Tree,
+                        _ => {
+                            return Err(format!(
+                                "ERR invalid ORDER value: expected CENTER, ID or NONE, got {}",
+                                value
+                            ))
+                        }
+                    };
+                    i += 2;
+                }
+                "FIELDS" => {
+                    fields = Some(self.get_fields(i + 1)?);
+                    i += 2;
+                }
+                "NOFIELDS" | "NOGEOM" => {
+                    bbox_only = true;
+                    i += 1;
+                }
+                "OBJECTS" | "IDS" | "COUNT" | "POINTS" | "BOUNDS" | "HASHES" => {
+                    i += self.set_query_output(i, &mut output)?;
+                }
+                "PRECISION" => {
+                    precision = Some(self.get_precision(i + 1)?);
+                    i += 2;
+                }
+                "WHERETAG" => {
+                    tags.push(self.get_tag(i + 1, "WHERETAG")?);
+                    i += 2;
+                }
+                "CURSOR" => {
+                    cursor = Some(self.get_cursor(i + 1)?);
+                    i += 2;
+                }
+                "FENCE" => {
+                    fence = true;
+                    i += 1;
+                }
+                "DWELL" => {
+                    dwell = Some(self.get_dwell(i + 1)?);
+                    i += 2;
+                }
                 _ => {
-                    // 向后兼容: 如果只有3个参数且第3个是数字，当作 limit
-                    if self.args.len() == 3 && i == 2 {
-                        if let Ok(parsed_limit) = self.get_integer(2, "limit") {
+                    // 向后兼容: 如果区域之后只有一个参数且是数字，当作 limit
+                    if self.args.len() == area_end + 1 && i == area_end {
+                        if let Ok(parsed_limit) = self.get_integer(i, "limit") {
                             limit = parsed_limit;
                             break;
                         }
@@ -183,11 +600,47 @@ impl<'a> ArgumentParser<'a> {
             }
         }
 
+        if bbox_only && fields.is_some() {
+            return Err("ERR FIELDS cannot be combined with NOFIELDS or NOGEOM".to_string());
+        }
+        let output = output.unwrap_or(QueryOutput::Objects);
+        if output != QueryOutput::Objects && (fields.is_some() || bbox_only) {
+            return Err(
+                "ERR FIELDS, NOFIELDS and NOGEOM cannot be combined with IDS, COUNT, POINTS, BOUNDS or HASHES"
+                    .to_string(),
+            );
+        }
+        if output == QueryOutput::Count && cursor.is_some() {
+            return Err("ERR COUNT cannot be combined with CURSOR".to_string());
+        }
+        if fence && output != QueryOutput::Objects {
+            return Err(FENCE_OUTPUT_ERROR.to_string());
+        }
+        // 游标分页总是按 ID 升序
+        if cursor.is_some() && order == SearchOrder::Center {
+            return Err("ERR CURSOR cannot be combined with ORDER CENTER".to_string());
+        }
+        if fence && cursor.is_some() {
+            return Err("ERR FENCE cannot be combined with CURSOR".to_string());
+        }
+        if dwell.is_some() && !fence {
+            return Err(DWELL_ERROR.to_string());
+        }
+
         Ok(IntersectsArgs {
             collection_id: collection_id.to_string(),
             geometry,
             limit,
             within,
+            order,
+            fields,
+            bbox_only,
+            output,
+            precision,
+            tags,
+            cursor,
+            fence,
+            dwell,
         })
     }
 
@@ -203,17 +656,153 @@ impl<'a> ArgumentParser<'a> {
             .map_err(|_| format!("ERR invalid {}: expected positive integer", param_name))
     }
 
+    /// DWELL 的秒数，必须大于 0
+    fn get_dwell(&self, index: usize) -> std::result::Result<u64, String> {
+        if index >= self.args.len() {
+            return Err("ERR DWELL option requires a value in seconds".to_string());
+        }
+        match self.get_integer(index, "DWELL seconds")? {
+            0 => Err("ERR invalid DWELL seconds: must be greater than 0".to_string()),
+            seconds => Ok(seconds as u64),
+        }
+    }
+
+    /// 获取有限浮点数参数
+    pub fn get_float(&self, index: usize, param_name: &str) -> std::result::Result<f64, String> {
+        let str_val = self.get_string(index, param_name)?;
+        str_val
+            .parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| {
+                format!(
+                    "ERR invalid {}: expected number, got '{}'",
+                    param_name, str_val
+                )
+            })
+    }
+
     /// 解析 DROP 命令的参数
+    /// 语法: DROP collection|pattern [DRYRUN]
     pub fn parse_drop_args(&self) -> std::result::Result<DropArgs, String> {
-        self.check_arg_count(1)?;
+        if self.args.is_empty() || self.args.len() > 2 {
+            return Err(format!(
+                "ERR wrong number of arguments for 'DROP' command. Expected 1 or 2, got {}. Usage: DROP collection|pattern [DRYRUN]",
+                self.args.len()
+            ));
+        }
 
         let collection_id = self.get_string(0, "collection ID")?;
 
+        let dry_run = match self.args.len() {
+            2 => {
+                let option = self.get_string(1, "option")?;
+                if !option.eq_ignore_ascii_case("DRYRUN") {
+                    return Err(format!(
+                        "ERR unknown option '{}' for DROP command, expected DRYRUN",
+                        option
+                    ));
+                }
+                true
+            }
+            _ => false,
+        };
+
         Ok(DropArgs {
             collection_id: collection_id.to_string(),
+            dry_run,
+        })
+    }
+
+    /// 解析 SCAN 命令的参数
+    /// 语法: SCAN collection cursor [COUNT n]
+    pub fn parse_scan_args(&self) -> std::result::Result<ScanArgs, String> {
+        if self.args.len() != 2 && self.args.len() != 4 {
+            return Err(
+                "ERR wrong number of arguments for 'SCAN' command. Usage: SCAN collection cursor [COUNT n]"
+                    .to_string(),
+            );
+        }
+
+        let collection_id = self.get_string(0, "collection ID")?;
+        let cursor = self.get_cursor(1)?;
+
+        let mut count = DEFAULT_SCAN_COUNT;
+        if self.args.len() == 4 {
+            let key = self.get_string(2, "option key")?;
+            if !key.eq_ignore_ascii_case("COUNT") {
+                return Err(format!("ERR unknown option '{}' for SCAN command", key));
+            }
+            count = self.get_integer(3, "COUNT value")?;
+            if count == 0 {
+                return Err("ERR COUNT must be positive".to_string());
+            }
+        }
+
+        Ok(ScanArgs {
+            collection_id: collection_id.to_string(),
+            cursor,
+            count,
         })
     }
 
+    /// 解析 KEYS 命令的参数
+    /// 语法: KEYS [pattern] [MATCH pattern] [STATS [SEP separator]]
+    ///
+    /// 模式可以直接作为第一个参数，也可以用 MATCH 给出，只能给出一个
+    pub fn parse_keys_args(&self) -> std::result::Result<KeysArgs, String> {
+        let mut pattern = None;
+        let mut stats = None;
+
+        let mut i = 0;
+        if !self.args.is_empty() {
+            let first = self.get_string(0, "pattern")?;
+            if !first.eq_ignore_ascii_case("STATS") && !first.eq_ignore_ascii_case("MATCH") {
+                pattern = Some(first.to_string());
+                i = 1;
+            }
+        }
+
+        while i < self.args.len() {
+            let key = self.get_string(i, "option key")?.to_uppercase();
+            match key.as_str() {
+                "MATCH" => {
+                    if pattern.is_some() {
+                        return Err("ERR only one pattern can be given".to_string());
+                    }
+                    if i + 1 >= self.args.len() {
+                        return Err("ERR MATCH option requires a pattern".to_string());
+                    }
+                    pattern = Some(self.get_string(i + 1, "pattern")?.to_string());
+                    i += 2;
+                }
+                "STATS" if stats.is_none() => {
+                    stats = Some(DEFAULT_NAMESPACE_SEPARATOR.to_string());
+                    i += 1;
+                }
+                "SEP" if stats.is_some() => {
+                    if i + 1 >= self.args.len() {
+                        return Err("ERR SEP option requires a separator".to_string());
+                    }
+                    let separator = self.get_string(i + 1, "separator")?;
+                    if separator.is_empty() {
+                        return Err("ERR separator must not be empty".to_string());
+                    }
+                    stats = Some(separator.to_string());
+                    i += 2;
+                }
+                _ => {
+                    return Err(format!(
+                        "ERR unknown option '{}' for KEYS command. Usage: KEYS [pattern] [MATCH pattern] [STATS [SEP separator]]",
+                        key
+                    ))
+                }
+            }
+        }
+
+        Ok(KeysArgs { pattern, stats })
+    }
+
     /// 解析 NEARBY 命令的参数
     /// 语法: NEARBY collection POINT lon lat [COUNT k] [RADIUS meters]
     ///
@@ -231,108 +820,567 @@ impl<'a> ArgumentParser<'a> {
         // 至少需要 4 个参数: collection, POINT, lon, lat
         if self.args.len() < 4 {
             return Err(format!(
-                "ERR wrong number of arguments for 'NEARBY' command. Expected at least 4, got {}. Usage: NEARBY collection POINT lon lat [COUNT k] [RADIUS meters]",
+                "ERR wrong number of arguments for 'NEARBY' command. Expected at least 4, got {}. Usage: NEARBY collection POINT lon lat [radius] [LIMIT n] [OBJECTS|IDS|COUNT|POINTS|BOUNDS|HASHES n] [RADIUS meters] [APPROX epsilon] [WITHIN GEOJSON geojson] [TYPE point|linestring|polygon ...] [WHERE field min max ...] [DISTANCE haversine|euclidean|cosines] [FIELDS f1,f2] [WHERETAG tag ...] [PRECISION n] [FENCE [DWELL seconds]]",
+                self.args.len()
+            ));
+        }
+
+        // 解析 collection ID
+        let collection_id = self.get_string(0, "collection ID")?;
+
+        // 验证 POINT 关键字
+        let point_keyword = self.get_string(1, "POINT keyword")?;
+        if point_keyword.to_uppercase() != "POINT" {
+            return Err(format!(
+                "ERR invalid syntax: expected 'POINT', got '{}'",
+                point_keyword
+            ));
+        }
+
+        // 解析经度和纬度
+        let lon_str = self.get_string(2, "longitude")?;
+        let lat_str = self.get_string(3, "latitude")?;
+
+        let query_lon: f64 = lon_str
+            .parse()
+            .map_err(|_| format!("ERR invalid longitude: expected number, got '{}'", lon_str))?;
+        let query_lat: f64 = lat_str
+            .parse()
+            .map_err(|_| format!("ERR invalid latitude: expected number, got '{}'", lat_str))?;
+
+        // 解析可选参数
+        let mut k: Option<usize> = None;
+        let mut max_radius: Option<f64> = None;
+        let mut output = None;
+        let mut epsilon: Option<f64> = None;
+        let mut fields: Option<FieldSelection> = None;
+        let mut tags = Vec::new();
+        let mut region: Option<Geometry> = None;
+        let mut filter = ObjectFilter::default();
+        let mut metric: Option<DistanceMetric> = None;
+        let mut precision: Option<u32> = None;
+        let mut fence = false;
+        let mut dwell: Option<u64> = None;
+        let mut i = 4;
+
+        // POINT lon lat radius：紧跟在坐标之后的数字是半径（米），与 RADIUS meters 相同
+        if self.args.len() > 4 && self.get_string(4, "radius")?.parse::<f64>().is_ok() {
+            max_radius = Some(self.get_radius(4)?);
+            i += 1;
+        }
+
+        while i < self.args.len() {
+            let keyword = self.get_string(i, "keyword")?;
+            let keyword_upper = keyword.to_uppercase();
+
+            // COUNT 后面跟着整数时是 LIMIT 的旧写法，否则只返回匹配的数量
+            let is_limit = keyword_upper == "LIMIT"
+                || (keyword_upper == "COUNT"
+                    && i + 1 < self.args.len()
+                    && self.get_string(i + 1, "count")?.parse::<i64>().is_ok());
+            if is_limit {
+                if i + 1 >= self.args.len() {
+                    return Err(format!("ERR {} keyword requires a value", keyword_upper));
+                }
+                if k.is_some() {
+                    return Err("ERR duplicate LIMIT keyword".to_string());
+                }
+                let count_val = self.get_integer(i + 1, "count")?;
+                if count_val == 0 {
+                    return Err("ERR count must be greater than 0".to_string());
+                }
+                k = Some(count_val);
+                i += 2;
+            } else if matches!(
+                keyword_upper.as_str(),
+                "OBJECTS" | "IDS" | "COUNT" | "POINTS" | "BOUNDS" | "HASHES"
+            ) {
+                i += self.set_query_output(i, &mut output)?;
+            } else if keyword_upper == "RADIUS" {
+                if i + 1 >= self.args.len() {
+                    return Err("ERR RADIUS keyword requires a value".to_string());
+                }
+                if max_radius.is_some() {
+                    return Err("ERR duplicate RADIUS keyword".to_string());
+                }
+                max_radius = Some(self.get_radius(i + 1)?);
+                i += 2;
+            } else if keyword_upper == "APPROX" {
+                if i + 1 >= self.args.len() {
+                    return Err("ERR APPROX keyword requires a value".to_string());
+                }
+                if epsilon.is_some() {
+                    return Err("ERR duplicate APPROX keyword".to_string());
+                }
+                let epsilon_str = self.get_string(i + 1, "epsilon")?;
+                let epsilon_val: f64 = epsilon_str.parse().map_err(|_| {
+                    format!(
+                        "ERR invalid epsilon: expected number, got '{}'",
+                        epsilon_str
+                    )
+                })?;
+                if !epsilon_val.is_finite() || epsilon_val < 0.0 {
+                    return Err("ERR epsilon must be a non-negative number".to_string());
+                }
+                epsilon = Some(epsilon_val);
+                i += 2;
+            } else if keyword_upper == "WITHIN" {
+                // WITHIN GEOJSON geojson：只在完全包含于该区域内的对象中查找
+                if i + 2 >= self.args.len() {
+                    return Err("ERR WITHIN keyword requires GEOJSON and a geometry".to_string());
+                }
+                if region.is_some() {
+                    return Err("ERR duplicate WITHIN keyword".to_string());
+                }
+                let kind = self.get_string(i + 1, "WITHIN type")?;
+                if kind.to_uppercase() != "GEOJSON" {
+                    return Err(format!(
+                        "ERR invalid syntax: expected 'GEOJSON' after WITHIN, got '{}'",
+                        kind
+                    ));
+                }
+                region = Some(self.get_geometry(i + 2)?);
+                i += 3;
+            } else if keyword_upper == "FIELDS" {
+                if fields.is_some() {
+                    return Err("ERR duplicate FIELDS keyword".to_string());
+                }
+                fields = Some(self.get_fields(i + 1)?);
+                i += 2;
+            } else if keyword_upper == "WHERETAG" {
+                tags.push(self.get_tag(i + 1, "WHERETAG")?);
+                i += 2;
+            } else if keyword_upper == "TYPE" {
+                // 多个 TYPE 之间是“或”的关系
+                if i + 1 >= self.args.len() {
+                    return Err("ERR TYPE keyword requires a geometry type".to_string());
+                }
+                let name = self.get_string(i + 1, "geometry type")?;
+                let kind = GeometryKind::parse(name).ok_or_else(|| {
+                    format!(
+                        "ERR invalid TYPE '{}': expected POINT, LINESTRING or POLYGON",
+                        name
+                    )
+                })?;
+                if !filter.kinds.contains(&kind) {
+                    filter.kinds.push(kind);
+                }
+                i += 2;
+            } else if keyword_upper == "WHERE" {
+                // 多个 WHERE 之间是“与”的关系
+                filter.fields.push(self.get_field_range(i + 1)?);
+                i += 4;
+            } else if keyword_upper == "DISTANCE" {
+                if i + 1 >= self.args.len() {
+                    return Err("ERR DISTANCE keyword requires a metric".to_string());
+                }
+                if metric.is_some() {
+                    return Err("ERR duplicate DISTANCE keyword".to_string());
+                }
+                let name = self.get_string(i + 1, "distance metric")?;
+                metric = Some(DistanceMetric::parse(name).ok_or_else(|| {
+                    format!(
+                        "ERR invalid DISTANCE '{}': expected HAVERSINE, EUCLIDEAN or COSINES",
+                        name
+                    )
+                })?);
+                i += 2;
+            } else if keyword_upper == "PRECISION" {
+                if precision.is_some() {
+                    return Err("ERR duplicate PRECISION keyword".to_string());
+                }
+                precision = Some(self.get_precision(i + 1)?);
+                i += 2;
+            } else if keyword_upper == "FENCE" {
+                fence = true;
+                i += 1;
+            } else if keyword_upper == "DWELL" {
+                dwell = Some(self.get_dwell(i + 1)?);
+                i += 2;
+            } else {
+                return Err(format!(
+                    "ERR invalid keyword: expected 'LIMIT', 'COUNT', 'RADIUS', 'APPROX', 'WITHIN', 'TYPE', 'WHERE', 'DISTANCE', 'FIELDS', 'WHERETAG', 'PRECISION', 'FENCE' or 'DWELL', got '{}'",
+                    keyword
+                ));
+            }
+        }
+
+        // 验证经纬度范围，平面坐标（DISTANCE EUCLIDEAN）不受限制
+        if metric != Some(DistanceMetric::Euclidean) {
+            if !(-180.0..=180.0).contains(&query_lon) {
+                return Err(format!(
+                    "ERR invalid longitude: must be between -180 and 180, got {}",
+                    query_lon
+                ));
+            }
+            if !(-90.0..=90.0).contains(&query_lat) {
+                return Err(format!(
+                    "ERR invalid latitude: must be between -90 and 90, got {}",
+                    query_lat
+                ));
+            }
+        }
+
+        let output = output.unwrap_or(QueryOutput::Objects);
+        // 围栏是以查询点为圆心、RADIUS 为半径的圆
+        if fence && (max_radius.is_none() || k.is_some() || output == QueryOutput::Count) {
+            return Err(
+                "ERR FENCE requires RADIUS and cannot be combined with LIMIT or COUNT".to_string(),
+            );
+        }
+        if fence && output != QueryOutput::Objects {
+            return Err(FENCE_OUTPUT_ERROR.to_string());
+        }
+        if dwell.is_some() && !fence {
+            return Err(DWELL_ERROR.to_string());
+        }
+        if fields.is_some() && output != QueryOutput::Objects {
+            return Err(
+                "ERR FIELDS cannot be combined with IDS, COUNT, POINTS, BOUNDS or HASHES"
+                    .to_string(),
+            );
+        }
+
+        // 验证至少有一个参数
+        if k.is_none() && max_radius.is_none() {
+            return Err(
+                "ERR at least one of LIMIT or RADIUS must be specified. Usage: NEARBY collection POINT lon lat [radius] [LIMIT n] [OBJECTS|IDS|COUNT|POINTS|BOUNDS|HASHES n] [RADIUS meters] [APPROX epsilon] [WITHIN GEOJSON geojson] [TYPE point|linestring|polygon ...] [WHERE field min max ...] [DISTANCE haversine|euclidean|cosines] [FIELDS f1,f2] [WHERETAG tag ...] [PRECISION n] [FENCE [DWELL seconds]]".to_string()
+            );
+        }
+
+        Ok(NearbyArgs {
+            collection_id: collection_id.to_string(),
+            query_lon,
+            query_lat,
+            k,
+            max_radius,
+            epsilon: epsilon.unwrap_or(0.0),
+            fields,
+            tags,
+            region,
+            filter,
+            metric,
+            precision,
+            fence,
+            dwell,
+            output,
+        })
+    }
+
+    /// 解析 WHERE field min max，min 和 max 可以是 -inf / +inf
+    fn get_field_range(&self, index: usize) -> std::result::Result<FieldRange, String> {
+        if index + 2 >= self.args.len() {
+            return Err("ERR WHERE keyword requires a field, min and max".to_string());
+        }
+        let field = self.get_string(index, "field")?;
+        if field.is_empty() {
+            return Err("ERR WHERE field must not be empty".to_string());
+        }
+        let bound = |offset: usize| -> std::result::Result<f64, String> {
+            let bound_str = self.get_string(index + offset, "WHERE bound")?;
+            bound_str
+                .parse::<f64>()
+                .ok()
+                .filter(|v| !v.is_nan())
+                .ok_or_else(|| {
+                    format!(
+                        "ERR invalid WHERE bound: expected number, got '{}'",
+                        bound_str
+                    )
+                })
+        };
+        let (min, max) = (bound(1)?, bound(2)?);
+        if min > max {
+            return Err("ERR WHERE min must not be greater than max".to_string());
+        }
+        Ok(FieldRange {
+            field: field.to_string(),
+            min,
+            max,
+        })
+    }
+
+    /// 解析 NEARBY 的半径（米），必须大于 0
+    fn get_radius(&self, index: usize) -> std::result::Result<f64, String> {
+        let radius_str = self.get_string(index, "radius")?;
+        let radius: f64 = radius_str
+            .parse()
+            .map_err(|_| format!("ERR invalid radius: expected number, got '{}'", radius_str))?;
+        if radius.is_nan() || radius <= 0.0 {
+            return Err("ERR radius must be greater than 0".to_string());
+        }
+        Ok(radius)
+    }
+
+    /// 解析 GEOMOP 命令的参数
+    /// 语法: GEOMOP INTERSECTION|UNION|DIFFERENCE|XOR collection id1 id2
+    pub fn parse_geomop_args(&self) -> std::result::Result<GeomopArgs, String> {
+        if self.args.len() != 4 {
+            return Err(format!(
+                "ERR wrong number of arguments for 'GEOMOP' command. Expected 4, got {}. Usage: GEOMOP INTERSECTION|UNION|DIFFERENCE|XOR collection id1 id2",
                 self.args.len()
             ));
         }
 
-        // 解析 collection ID
-        let collection_id = self.get_string(0, "collection ID")?;
+        let op_name = self.get_string(0, "operation")?;
+        let op = OverlayOp::parse(op_name).ok_or_else(|| {
+            format!(
+                "ERR invalid operation: expected INTERSECTION, UNION, DIFFERENCE or XOR, got '{}'",
+                op_name
+            )
+        })?;
+
+        Ok(GeomopArgs {
+            op,
+            collection_id: self.get_string(1, "collection ID")?.to_string(),
+            first_id: self.get_string(2, "first item ID")?.to_string(),
+            second_id: self.get_string(3, "second item ID")?.to_string(),
+        })
+    }
+
+    /// 解析 RENDER 命令的参数
+    /// 语法: RENDER collection TILE x y z|QUADKEY key|BOUNDS minlat minlon maxlat maxlon
+    pub fn parse_render_args(&self) -> std::result::Result<RenderArgs, String> {
+        const USAGE: &str =
+            "Usage: RENDER collection TILE x y z|QUADKEY key|BOUNDS minlat minlon maxlat maxlon";
+        if self.args.len() < 3 {
+            return Err(format!(
+                "ERR wrong number of arguments for 'RENDER' command. {}",
+                USAGE
+            ));
+        }
+
+        let collection_id = self.get_string(0, "collection ID")?;
+        let shape = self.get_string(1, "area")?.to_uppercase();
+        if !matches!(shape.as_str(), "TILE" | "QUADKEY" | "BOUNDS") {
+            return Err(format!(
+                "ERR unknown area '{}' for RENDER. {}",
+                shape, USAGE
+            ));
+        }
+        let (area, next) = self.get_query_area(1)?;
+        if next < self.args.len() {
+            return Err(format!(
+                "ERR unexpected argument '{}' for RENDER command",
+                self.get_string(next, "argument")?
+            ));
+        }
+        let bounds = area
+            .bounding_rect()
+            .ok_or_else(|| "ERR RENDER area is empty".to_string())?;
+
+        Ok(RenderArgs {
+            collection_id: collection_id.to_string(),
+            area,
+            bounds,
+        })
+    }
+
+    /// 解析 HULL 命令的参数
+    /// 语法: HULL collection [AREA geojson] [CONCAVE concavity]
+    pub fn parse_hull_args(&self) -> std::result::Result<HullArgs, String> {
+        if self.args.is_empty() {
+            return Err(
+                "ERR wrong number of arguments for 'HULL' command. Usage: HULL collection [AREA geojson] [CONCAVE concavity]"
+                    .to_string(),
+            );
+        }
+
+        let collection_id = self.get_string(0, "collection ID")?;
+        let mut area = None;
+        let mut kind = HullKind::Convex;
+
+        let mut i = 1;
+        while i < self.args.len() {
+            let key = self.get_string(i, "option key")?.to_uppercase();
+            if i + 1 >= self.args.len() {
+                return Err(format!("ERR {} option requires a value", key));
+            }
+
+            match key.as_str() {
+                "AREA" => area = Some(self.get_geometry(i + 1)?),
+                "CONCAVE" => {
+                    let concavity = self.get_float(i + 1, "concavity")?;
+                    if concavity <= 0.0 {
+                        return Err("ERR concavity must be greater than 0".to_string());
+                    }
+                    kind = HullKind::Concave { concavity };
+                }
+                _ => return Err(format!("ERR unknown option '{}' for HULL command", key)),
+            }
+            i += 2;
+        }
+
+        Ok(HullArgs {
+            collection_id: collection_id.to_string(),
+            area,
+            kind,
+        })
+    }
+
+    /// 解析 AGG 命令的参数
+    /// 语法: AGG collection BOUNDS minlon minlat maxlon maxlat GRID|HEX size [FIELD name]
+    pub fn parse_agg_args(&self) -> std::result::Result<AggArgs, String> {
+        const USAGE: &str =
+            "Usage: AGG collection BOUNDS minlon minlat maxlon maxlat GRID|HEX size [FIELD name]";
+
+        if self.args.len() != 8 && self.args.len() != 10 {
+            return Err(format!(
+                "ERR wrong number of arguments for 'AGG' command. {}",
+                USAGE
+            ));
+        }
+
+        let collection_id = self.get_string(0, "collection ID")?;
+
+        if !self
+            .get_string(1, "BOUNDS keyword")?
+            .eq_ignore_ascii_case("BOUNDS")
+        {
+            return Err(format!("ERR invalid syntax: expected 'BOUNDS'. {}", USAGE));
+        }
+        let min_lon = self.get_float(2, "min longitude")?;
+        let min_lat = self.get_float(3, "min latitude")?;
+        let max_lon = self.get_float(4, "max longitude")?;
+        let max_lat = self.get_float(5, "max latitude")?;
+        if min_lon > max_lon || min_lat > max_lat {
+            return Err("ERR invalid bounds: min must not be greater than max".to_string());
+        }
+
+        let size = self.get_float(7, "bin size")?;
+        if size <= 0.0 {
+            return Err("ERR bin size must be greater than 0".to_string());
+        }
+        let binning = match self.get_string(6, "binning")?.to_uppercase().as_str() {
+            "GRID" => Binning::Grid { size },
+            "HEX" => Binning::Hex { size },
+            other => {
+                return Err(format!(
+                    "ERR invalid binning: expected 'GRID' or 'HEX', got '{}'",
+                    other
+                ))
+            }
+        };
+
+        let field = if self.args.len() == 10 {
+            if !self
+                .get_string(8, "FIELD keyword")?
+                .eq_ignore_ascii_case("FIELD")
+            {
+                return Err(format!("ERR invalid syntax: expected 'FIELD'. {}", USAGE));
+            }
+            Some(self.get_string(9, "field name")?.to_string())
+        } else {
+            None
+        };
+
+        Ok(AggArgs {
+            collection_id: collection_id.to_string(),
+            bounds: Rectangle::new(min_lon, min_lat, max_lon, max_lat),
+            binning,
+            field,
+        })
+    }
 
-        // 验证 POINT 关键字
-        let point_keyword = self.get_string(1, "POINT keyword")?;
-        if point_keyword.to_uppercase() != "POINT" {
+    /// 解析 SNAP 命令的参数
+    /// 语法: SNAP collection lon lat
+    pub fn parse_snap_args(&self) -> std::result::Result<SnapArgs, String> {
+        if self.args.len() != 3 {
             return Err(format!(
-                "ERR invalid syntax: expected 'POINT', got '{}'",
-                point_keyword
+                "ERR wrong number of arguments for 'SNAP' command. Expected 3, got {}. Usage: SNAP collection lon lat",
+                self.args.len()
             ));
         }
 
-        // 解析经度和纬度
-        let lon_str = self.get_string(2, "longitude")?;
-        let lat_str = self.get_string(3, "latitude")?;
-
-        let query_lon: f64 = lon_str
-            .parse()
-            .map_err(|_| format!("ERR invalid longitude: expected number, got '{}'", lon_str))?;
-        let query_lat: f64 = lat_str
-            .parse()
-            .map_err(|_| format!("ERR invalid latitude: expected number, got '{}'", lat_str))?;
+        let collection_id = self.get_string(0, "collection ID")?;
+        let lon = self.get_float(1, "longitude")?;
+        let lat = self.get_float(2, "latitude")?;
 
         // 验证经纬度范围
-        if !(-180.0..=180.0).contains(&query_lon) {
+        if !(-180.0..=180.0).contains(&lon) {
             return Err(format!(
                 "ERR invalid longitude: must be between -180 and 180, got {}",
-                query_lon
+                lon
             ));
         }
-        if !(-90.0..=90.0).contains(&query_lat) {
+        if !(-90.0..=90.0).contains(&lat) {
             return Err(format!(
                 "ERR invalid latitude: must be between -90 and 90, got {}",
-                query_lat
+                lat
             ));
         }
 
-        // 解析可选的 COUNT 和 RADIUS 参数
-        let mut k: Option<usize> = None;
-        let mut max_radius: Option<f64> = None;
-        let mut i = 4;
+        Ok(SnapArgs {
+            collection_id: collection_id.to_string(),
+            lon,
+            lat,
+        })
+    }
 
-        while i < self.args.len() {
-            let keyword = self.get_string(i, "keyword")?;
-            let keyword_upper = keyword.to_uppercase();
+    /// 解析 CLUSTER 命令的参数
+    /// 语法: CLUSTER collection EPS meters MINPTS n [BOUNDS minlon minlat maxlon maxlat]
+    pub fn parse_cluster_args(&self) -> std::result::Result<ClusterArgs, String> {
+        const USAGE: &str =
+            "Usage: CLUSTER collection EPS meters MINPTS n [BOUNDS minlon minlat maxlon maxlat]";
 
-            if keyword_upper == "COUNT" {
-                if i + 1 >= self.args.len() {
-                    return Err("ERR COUNT keyword requires a value".to_string());
-                }
-                if k.is_some() {
-                    return Err("ERR duplicate COUNT keyword".to_string());
-                }
-                let count_val = self.get_integer(i + 1, "count")?;
-                if count_val == 0 {
-                    return Err("ERR count must be greater than 0".to_string());
-                }
-                k = Some(count_val);
-                i += 2;
-            } else if keyword_upper == "RADIUS" {
-                if i + 1 >= self.args.len() {
-                    return Err("ERR RADIUS keyword requires a value".to_string());
+        if self.args.is_empty() {
+            return Err(format!(
+                "ERR wrong number of arguments for 'CLUSTER' command. {}",
+                USAGE
+            ));
+        }
+
+        let collection_id = self.get_string(0, "collection ID")?;
+        let mut eps = None;
+        let mut min_points = None;
+        let mut bounds = None;
+
+        let mut i = 1;
+        while i < self.args.len() {
+            let key = self.get_string(i, "option key")?.to_uppercase();
+            match key.as_str() {
+                "EPS" => {
+                    let value = self.get_float(i + 1, "eps")?;
+                    if value <= 0.0 {
+                        return Err("ERR eps must be greater than 0".to_string());
+                    }
+                    eps = Some(value);
+                    i += 2;
                 }
-                if max_radius.is_some() {
-                    return Err("ERR duplicate RADIUS keyword".to_string());
+                "MINPTS" => {
+                    let value = self.get_integer(i + 1, "minpts")?;
+                    if value == 0 {
+                        return Err("ERR minpts must be greater than 0".to_string());
+                    }
+                    min_points = Some(value);
+                    i += 2;
                 }
-                let radius_str = self.get_string(i + 1, "radius")?;
-                let radius_val: f64 = radius_str.parse().map_err(|_| {
-                    format!("ERR invalid radius: expected number, got '{}'", radius_str)
-                })?;
-                if radius_val <= 0.0 {
-                    return Err("ERR radius must be greater than 0".to_string());
+                "BOUNDS" => {
+                    let min_lon = self.get_float(i + 1, "min longitude")?;
+                    let min_lat = self.get_float(i + 2, "min latitude")?;
+                    let max_lon = self.get_float(i + 3, "max longitude")?;
+                    let max_lat = self.get_float(i + 4, "max latitude")?;
+                    if min_lon > max_lon || min_lat > max_lat {
+                        return Err(
+                            "ERR invalid bounds: min must not be greater than max".to_string()
+                        );
+                    }
+                    bounds = Some(Rectangle::new(min_lon, min_lat, max_lon, max_lat));
+                    i += 5;
                 }
-                max_radius = Some(radius_val);
-                i += 2;
-            } else {
-                return Err(format!(
-                    "ERR invalid keyword: expected 'COUNT' or 'RADIUS', got '{}'",
-                    keyword
-                ));
+                _ => return Err(format!("ERR unknown option '{}' for CLUSTER command", key)),
             }
         }
 
-        // 验证至少有一个参数
-        if k.is_none() && max_radius.is_none() {
-            return Err(
-                "ERR at least one of COUNT or RADIUS must be specified. Usage: NEARBY collection POINT lon lat [COUNT k] [RADIUS meters]".to_string()
-            );
+        match (eps, min_points) {
+            (Some(eps), Some(min_points)) => Ok(ClusterArgs {
+                collection_id: collection_id.to_string(),
+                eps,
+                min_points,
+                bounds,
+            }),
+            _ => Err(format!("ERR EPS and MINPTS are required. {}", USAGE)),
         }
-
-        Ok(NearbyArgs {
-            collection_id: collection_id.to_string(),
-            query_lon,
-            query_lat,
-            k,
-            max_radius,
-        })
     }
 }
 
@@ -342,6 +1390,9 @@ pub struct SetArgs {
     pub collection_id: String,
     pub item_id: String,
     pub geojson: String,
+    pub tags: Vec<String>,           // TAG 选项，可重复
+    pub expire_seconds: Option<f64>, // EX 选项，存活秒数（可以带小数）
+    pub is_string: bool,             // STRING 对象，geojson 为文本值
 }
 
 /// GET 命令的解析结果
@@ -349,6 +1400,51 @@ pub struct SetArgs {
 pub struct GetArgs {
     pub collection_id: String,
     pub item_id: String,
+    pub part: Option<usize>, // GeometryCollection 中的部分序号（从 0 开始），None 表示整个对象
+    pub precision: Option<u32>, // 输出坐标的小数位数，None 表示使用全局设置
+    pub output: GetOutput,
+}
+
+/// GET 返回的内容
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GetOutput {
+    /// 存储的 GeoJSON（默认）
+    Object,
+    /// 几何体的质心 `[lat, lon]`
+    Point,
+    /// 外接矩形 `[[minlat, minlon], [maxlat, maxlon]]`
+    Bounds,
+    /// 质心的 geohash，参数为字符数
+    Hash(usize),
+}
+
+/// INTERSECTS 和 NEARBY 返回的内容，只需要数量或 id 的客户端不必接收完整的 GeoJSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryOutput {
+    /// 存储的 GeoJSON（默认），可以用 FIELDS 选择字段
+    Objects,
+    /// 只返回 id
+    Ids,
+    /// 只返回匹配的数量
+    Count,
+    /// 每个对象的质心 `[lat, lon]`
+    Points,
+    /// 每个对象的外接矩形 `[[minlat, minlon], [maxlat, maxlon]]`
+    Bounds,
+    /// 每个对象质心的 geohash，参数为字符数
+    Hashes(usize),
+}
+
+impl QueryOutput {
+    /// POINTS、BOUNDS 和 HASHES 与 GET 的 POINT、BOUNDS 和 HASH 格式相同
+    pub fn accessor(self) -> Option<GetOutput> {
+        match self {
+            QueryOutput::Points => Some(GetOutput::Point),
+            QueryOutput::Bounds => Some(GetOutput::Bounds),
+            QueryOutput::Hashes(length) => Some(GetOutput::Hash(length)),
+            QueryOutput::Objects | QueryOutput::Ids | QueryOutput::Count => None,
+        }
+    }
 }
 
 /// DELETE 命令的解析结果
@@ -358,19 +1454,94 @@ pub struct DeleteArgs {
     pub item_id: String,
 }
 
+/// JSET 命令的解析结果
+#[derive(Debug)]
+pub struct JsetArgs {
+    pub collection_id: String,
+    pub item_id: String,
+    pub path: JsonPath,
+    pub value: serde_json::Value,
+}
+
+/// JGET 命令的解析结果
+#[derive(Debug)]
+pub struct JgetArgs {
+    pub collection_id: String,
+    pub item_id: String,
+    pub path: JsonPath,
+    /// 字符串也按 JSON 编码（带引号）返回
+    pub raw: bool,
+}
+
+/// JDEL 命令的解析结果
+#[derive(Debug)]
+pub struct JdelArgs {
+    pub collection_id: String,
+    pub item_id: String,
+    pub path: JsonPath,
+}
+
+/// PDEL 命令的解析结果
+#[derive(Debug)]
+pub struct PdelArgs {
+    pub collection_id: String,
+    /// 对象 ID 的 glob 模式，`*` 匹配任意字符，`?` 匹配单个字符
+    pub pattern: String,
+}
+
+/// EXPIRE 命令的解析结果
+#[derive(Debug)]
+pub struct ExpireArgs {
+    pub collection_id: String,
+    pub item_id: String,
+    /// 存活时间（秒），不大于 0 时立即删除
+    pub seconds: f64,
+}
+
 /// INTERSECTS 命令的解析结果
 #[derive(Debug)]
 pub struct IntersectsArgs {
     pub collection_id: String,
     pub geometry: Geometry,
     pub limit: usize,
-    pub within: bool, // true: 包含在内，false: 相交
+    pub within: bool,                   // true: 包含在内，false: 相交
+    pub order: SearchOrder,             // 达到 limit 时的遍历顺序提示
+    pub fields: Option<FieldSelection>, // None 表示返回完整 GeoJSON
+    pub bbox_only: bool,                // NOFIELDS/NOGEOM：每个结果只返回 id 和 bbox
+    pub output: QueryOutput,            // 每个结果返回的内容，COUNT 只返回数量
+    pub precision: Option<u32>,         // 输出坐标的小数位数，None 表示使用全局设置
+    pub tags: Vec<String>,              // WHERETAG 过滤，对象需带有所有标签
+    pub cursor: Option<ScanCursor>,     // CURSOR 分页，LIMIT 为每页数量
+    pub fence: bool,                    // FENCE：在连接上注册地理围栏，不返回查询结果
+    pub dwell: Option<u64>,             // DWELL 秒数：对象在围栏内停留这么久时产生 dwell 事件
+}
+
+/// KEYS STATS 默认的命名空间分隔符（例如 `gps:2024-01-01` 的命名空间为 `gps`）
+pub const DEFAULT_NAMESPACE_SEPARATOR: &str = ":";
+
+/// SCAN 未指定 COUNT 时每页返回的对象数量
+pub const DEFAULT_SCAN_COUNT: usize = 10;
+
+/// SCAN 命令的解析结果
+#[derive(Debug)]
+pub struct ScanArgs {
+    pub collection_id: String,
+    pub cursor: ScanCursor,
+    pub count: usize,
+}
+
+/// KEYS 命令的解析结果
+#[derive(Debug)]
+pub struct KeysArgs {
+    pub pattern: Option<String>, // glob 模式，None 表示所有 collection
+    pub stats: Option<String>,   // Some(分隔符) 表示按命名空间前缀统计
 }
 
 /// DROP 命令的解析结果
 #[derive(Debug)]
 pub struct DropArgs {
-    pub collection_id: String,
+    pub collection_id: String, // 包含 `*` 或 `?` 时按 glob 模式匹配
+    pub dry_run: bool,         // 只列出将被删除的 collection
 }
 
 /// NEARBY 命令的解析结果
@@ -379,23 +1550,83 @@ pub struct NearbyArgs {
     pub collection_id: String,
     pub query_lon: f64,
     pub query_lat: f64,
-    pub k: Option<usize>,        // None 表示不限制数量
-    pub max_radius: Option<f64>, // None 表示不限制半径（米）
+    pub k: Option<usize>,               // LIMIT n，None 表示不限制数量
+    pub max_radius: Option<f64>,        // None 表示不限制半径（米）
+    pub epsilon: f64,                   // 近似因子，0 表示精确查询
+    pub fields: Option<FieldSelection>, // None 表示返回完整 GeoJSON
+    pub tags: Vec<String>,              // WHERETAG 过滤，对象需带有所有标签
+    pub region: Option<Geometry>,       // WITHIN GEOJSON 区域，None 表示不限制
+    pub filter: ObjectFilter,           // TYPE 和 WHERE 过滤，在 KNN 遍历时生效
+    pub metric: Option<DistanceMetric>, // DISTANCE 距离度量，None 表示使用全局设置
+    pub precision: Option<u32>,         // 输出坐标的小数位数，None 表示使用全局设置
+    pub fence: bool,                    // FENCE：在连接上注册地理围栏，不返回查询结果
+    pub dwell: Option<u64>,             // DWELL 秒数：对象在围栏内停留这么久时产生 dwell 事件
+    pub output: QueryOutput,            // 每个结果返回的内容，COUNT 只返回数量
+}
+
+/// GEOMOP 命令的解析结果
+#[derive(Debug)]
+pub struct GeomopArgs {
+    pub op: OverlayOp,
+    pub collection_id: String,
+    pub first_id: String,
+    pub second_id: String,
+}
+
+/// RENDER 命令的解析结果
+#[derive(Debug)]
+pub struct RenderArgs {
+    pub collection_id: String,
+    pub area: Geometry, // 查询区域（瓦片或矩形）
+    pub bounds: Rect,   // 裁剪范围，与查询区域相同
+}
+
+/// HULL 命令的解析结果
+#[derive(Debug)]
+pub struct HullArgs {
+    pub collection_id: String,
+    pub area: Option<Geometry>, // None 表示整个 collection
+    pub kind: HullKind,
+}
+
+/// SNAP 命令的解析结果
+#[derive(Debug)]
+pub struct SnapArgs {
+    pub collection_id: String,
+    pub lon: f64,
+    pub lat: f64,
+}
+
+/// CLUSTER 命令的解析结果
+#[derive(Debug)]
+pub struct ClusterArgs {
+    pub collection_id: String,
+    pub eps: f64, // 邻域半径（米）
+    pub min_points: usize,
+    pub bounds: Option<Rectangle>,
+}
+
+/// AGG 命令的解析结果
+#[derive(Debug)]
+pub struct AggArgs {
+    pub collection_id: String,
+    pub bounds: Rectangle,
+    pub binning: Binning,
+    pub field: Option<String>, // 需要求和/平均的数值属性
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::commands::args;
     use serde_json::json;
 
     #[test]
     fn test_argument_parser_set_success() {
         let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("truck1".to_string())),
-            RespValue::BulkString(Some(
-                json!({"type": "Point", "coordinates": [1.0, 2.0]}).to_string(),
-            )),
+            RespValue::bulk("fleet"),
+            RespValue::bulk("truck1"),
+            RespValue::bulk(json!({"type": "Point", "coordinates": [1.0, 2.0]}).to_string()),
         ];
 
         let parser = ArgumentParser::new(&args, "SET");
@@ -411,12 +1642,87 @@ mod tests {
         assert!(parsed.geojson.contains("2.0"));
     }
 
+    #[test]
+    fn test_parse_set_args_with_tags() {
+        let point = json!({"type": "Point", "coordinates": [1.0, 2.0]}).to_string();
+        let values = args(&["fleet", "bus1", &point, "TAG", "bus", "tag", "line42"]);
+
+        let parsed = ArgumentParser::new(&values, "SET")
+            .parse_set_args()
+            .unwrap();
+        assert_eq!(parsed.tags, vec!["bus", "line42"]);
+
+        let values = args(&["fleet", "bus1", &point, "TAG"]);
+        let err = ArgumentParser::new(&values, "SET")
+            .parse_set_args()
+            .unwrap_err();
+        assert!(err.contains("TAG option requires a tag name"));
+
+        let values = args(&["fleet", "bus1", &point, "COLOR", "red"]);
+        let err = ArgumentParser::new(&values, "SET")
+            .parse_set_args()
+            .unwrap_err();
+        assert!(err.contains("unknown option 'COLOR'"));
+    }
+
+    #[test]
+    fn test_parse_set_args_with_ex() {
+        let point = json!({"type": "Point", "coordinates": [1.0, 2.0]}).to_string();
+        let parse = |options: &[&str]| {
+            let mut list = vec!["fleet", "bus1", point.as_str()];
+            list.extend_from_slice(options);
+            let values = args(&list);
+            ArgumentParser::new(&values, "SET").parse_set_args()
+        };
+
+        let parsed = parse(&["EX", "1.5", "TAG", "bus"]).unwrap();
+        assert_eq!(parsed.expire_seconds, Some(1.5));
+        assert_eq!(parsed.tags, vec!["bus"]);
+        assert_eq!(parse(&[]).unwrap().expire_seconds, None);
+
+        assert!(parse(&["EX", "0"])
+            .unwrap_err()
+            .contains("invalid expire time"));
+        assert!(parse(&["EX", "-1"])
+            .unwrap_err()
+            .contains("invalid expire time"));
+        assert!(parse(&["EX", "soon"])
+            .unwrap_err()
+            .contains("invalid EX seconds"));
+        assert!(parse(&["EX", "10", "ex", "20"])
+            .unwrap_err()
+            .contains("duplicate EX"));
+    }
+
+    #[test]
+    fn test_parse_wheretag_args() {
+        let area = json!({"type": "Point", "coordinates": [1.0, 2.0]}).to_string();
+        let values = args(&["fleet", &area, "WHERETAG", "bus", "WHERETAG", "line42"]);
+        let parsed = ArgumentParser::new(&values, "INTERSECTS")
+            .parse_intersects_args()
+            .unwrap();
+        assert_eq!(parsed.tags, vec!["bus", "line42"]);
+
+        let values = args(&[
+            "fleet", "POINT", "116.4", "39.9", "COUNT", "5", "WHERETAG", "bus",
+        ]);
+        let parsed = ArgumentParser::new(&values, "NEARBY")
+            .parse_nearby_args()
+            .unwrap();
+        assert_eq!(parsed.tags, vec!["bus"]);
+
+        let values = args(&[
+            "fleet", "POINT", "116.4", "39.9", "COUNT", "5", "WHERETAG", "",
+        ]);
+        let err = ArgumentParser::new(&values, "NEARBY")
+            .parse_nearby_args()
+            .unwrap_err();
+        assert!(err.contains("WHERETAG value must not be empty"));
+    }
+
     #[test]
     fn test_argument_parser_get_success() {
-        let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("truck1".to_string())),
-        ];
+        let args = vec![RespValue::bulk("fleet"), RespValue::bulk("truck1")];
 
         let parser = ArgumentParser::new(&args, "GET");
         let result = parser.parse_get_args();
@@ -427,9 +1733,22 @@ mod tests {
         assert_eq!(parsed.item_id, "truck1");
     }
 
+    #[test]
+    fn test_argument_parser_non_utf8() {
+        let args = vec![RespValue::bulk("fleet"), RespValue::bulk(&b"truck\xff"[..])];
+
+        // 原始字节可以取出，需要文本的参数报告编码错误而不是协议错误
+        let parser = ArgumentParser::new(&args, "GET");
+        assert_eq!(parser.get_bytes(1, "item ID").unwrap(), b"truck\xff");
+        assert_eq!(
+            parser.parse_get_args().unwrap_err(),
+            "ERR invalid item ID: expected UTF-8 string"
+        );
+    }
+
     #[test]
     fn test_argument_parser_invalid_arg_count() {
-        let args = vec![RespValue::BulkString(Some("fleet".to_string()))];
+        let args = vec![RespValue::bulk("fleet")];
 
         let parser = ArgumentParser::new(&args, "SET");
         let result = parser.parse_set_args();
@@ -441,9 +1760,9 @@ mod tests {
     #[test]
     fn test_argument_parser_invalid_geojson() {
         let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("truck1".to_string())),
-            RespValue::BulkString(Some("invalid json".to_string())),
+            RespValue::bulk("fleet"),
+            RespValue::bulk("truck1"),
+            RespValue::bulk("invalid json"),
         ];
 
         let parser = ArgumentParser::new(&args, "SET");
@@ -458,9 +1777,9 @@ mod tests {
 
     #[test]
     fn test_get_geometry_point() {
-        let args = vec![RespValue::BulkString(Some(
+        let args = vec![RespValue::bulk(
             json!({"type": "Point", "coordinates": [10.5, 20.7]}).to_string(),
-        ))];
+        )];
 
         let parser = ArgumentParser::new(&args, "TEST");
         let result = parser.get_geometry(0);
@@ -489,7 +1808,7 @@ mod tests {
             ]]
         });
 
-        let args = vec![RespValue::BulkString(Some(polygon_geojson.to_string()))];
+        let args = vec![RespValue::bulk(polygon_geojson.to_string())];
 
         let parser = ArgumentParser::new(&args, "TEST");
         let result = parser.get_geometry(0);
@@ -517,7 +1836,7 @@ mod tests {
             }
         });
 
-        let args = vec![RespValue::BulkString(Some(feature_geojson.to_string()))];
+        let args = vec![RespValue::bulk(feature_geojson.to_string())];
 
         let parser = ArgumentParser::new(&args, "TEST");
         let result = parser.get_geometry(0);
@@ -535,9 +1854,7 @@ mod tests {
 
     #[test]
     fn test_get_geometry_invalid_json() {
-        let args = vec![RespValue::BulkString(Some(
-            "invalid json string".to_string(),
-        ))];
+        let args = vec![RespValue::bulk("invalid json string".to_string())];
 
         let parser = ArgumentParser::new(&args, "TEST");
         let result = parser.get_geometry(0);
@@ -564,7 +1881,7 @@ mod tests {
             "coordinates": [1.0, 2.0]
         });
 
-        let args = vec![RespValue::BulkString(Some(invalid_geojson.to_string()))];
+        let args = vec![RespValue::bulk(invalid_geojson.to_string())];
 
         let parser = ArgumentParser::new(&args, "TEST");
         let result = parser.get_geometry(0);
@@ -576,12 +1893,12 @@ mod tests {
     #[test]
     fn test_parse_nearby_args_success_with_count() {
         let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("POINT".to_string())),
-            RespValue::BulkString(Some("116.4".to_string())),
-            RespValue::BulkString(Some("39.9".to_string())),
-            RespValue::BulkString(Some("COUNT".to_string())),
-            RespValue::BulkString(Some("10".to_string())),
+            RespValue::bulk("fleet"),
+            RespValue::bulk("POINT"),
+            RespValue::bulk("116.4"),
+            RespValue::bulk("39.9"),
+            RespValue::bulk("COUNT"),
+            RespValue::bulk("10"),
         ];
 
         let parser = ArgumentParser::new(&args, "NEARBY");
@@ -599,12 +1916,12 @@ mod tests {
     #[test]
     fn test_parse_nearby_args_success_with_radius() {
         let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("POINT".to_string())),
-            RespValue::BulkString(Some("116.4".to_string())),
-            RespValue::BulkString(Some("39.9".to_string())),
-            RespValue::BulkString(Some("RADIUS".to_string())),
-            RespValue::BulkString(Some("1000".to_string())),
+            RespValue::bulk("fleet"),
+            RespValue::bulk("POINT"),
+            RespValue::bulk("116.4"),
+            RespValue::bulk("39.9"),
+            RespValue::bulk("RADIUS"),
+            RespValue::bulk("1000"),
         ];
 
         let parser = ArgumentParser::new(&args, "NEARBY");
@@ -622,14 +1939,14 @@ mod tests {
     #[test]
     fn test_parse_nearby_args_success_with_both() {
         let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("POINT".to_string())),
-            RespValue::BulkString(Some("116.4".to_string())),
-            RespValue::BulkString(Some("39.9".to_string())),
-            RespValue::BulkString(Some("COUNT".to_string())),
-            RespValue::BulkString(Some("10".to_string())),
-            RespValue::BulkString(Some("RADIUS".to_string())),
-            RespValue::BulkString(Some("5000".to_string())),
+            RespValue::bulk("fleet"),
+            RespValue::bulk("POINT"),
+            RespValue::bulk("116.4"),
+            RespValue::bulk("39.9"),
+            RespValue::bulk("COUNT"),
+            RespValue::bulk("10"),
+            RespValue::bulk("RADIUS"),
+            RespValue::bulk("5000"),
         ];
 
         let parser = ArgumentParser::new(&args, "NEARBY");
@@ -645,10 +1962,10 @@ mod tests {
     #[test]
     fn test_parse_nearby_args_missing_count_and_radius() {
         let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("POINT".to_string())),
-            RespValue::BulkString(Some("116.4".to_string())),
-            RespValue::BulkString(Some("39.9".to_string())),
+            RespValue::bulk("fleet"),
+            RespValue::bulk("POINT"),
+            RespValue::bulk("116.4"),
+            RespValue::bulk("39.9"),
         ];
 
         let parser = ArgumentParser::new(&args, "NEARBY");
@@ -657,18 +1974,18 @@ mod tests {
         assert!(result.is_err());
         assert!(result
             .unwrap_err()
-            .contains("at least one of COUNT or RADIUS"));
+            .contains("at least one of LIMIT or RADIUS"));
     }
 
     #[test]
     fn test_parse_nearby_args_invalid_longitude() {
         let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("POINT".to_string())),
-            RespValue::BulkString(Some("200.0".to_string())), // 无效经度
-            RespValue::BulkString(Some("39.9".to_string())),
-            RespValue::BulkString(Some("COUNT".to_string())),
-            RespValue::BulkString(Some("10".to_string())),
+            RespValue::bulk("fleet"),
+            RespValue::bulk("POINT"),
+            RespValue::bulk("200.0"), // 无效经度
+            RespValue::bulk("39.9"),
+            RespValue::bulk("COUNT"),
+            RespValue::bulk("10"),
         ];
 
         let parser = ArgumentParser::new(&args, "NEARBY");
@@ -681,12 +1998,12 @@ mod tests {
     #[test]
     fn test_parse_nearby_args_missing_point_keyword() {
         let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("NOTPOINT".to_string())), // 错误关键字
-            RespValue::BulkString(Some("116.4".to_string())),
-            RespValue::BulkString(Some("39.9".to_string())),
-            RespValue::BulkString(Some("COUNT".to_string())),
-            RespValue::BulkString(Some("10".to_string())),
+            RespValue::bulk("fleet"),
+            RespValue::bulk("NOTPOINT"), // 错误关键字
+            RespValue::bulk("116.4"),
+            RespValue::bulk("39.9"),
+            RespValue::bulk("COUNT"),
+            RespValue::bulk("10"),
         ];
 
         let parser = ArgumentParser::new(&args, "NEARBY");
@@ -696,15 +2013,57 @@ mod tests {
         assert!(result.unwrap_err().contains("expected 'POINT'"));
     }
 
+    #[test]
+    fn test_parse_nearby_args_radius_limit_and_count() {
+        let parse = |options: &[&str]| {
+            let mut list = vec!["fleet", "POINT", "116.4", "39.9"];
+            list.extend_from_slice(options);
+            let values = args(&list);
+            ArgumentParser::new(&values, "NEARBY").parse_nearby_args()
+        };
+
+        let parsed = parse(&["500", "LIMIT", "10", "COUNT"]).unwrap();
+        assert_eq!(parsed.max_radius, Some(500.0));
+        assert_eq!(parsed.k, Some(10));
+        assert_eq!(parsed.output, QueryOutput::Count);
+
+        // COUNT k 仍然表示数量上限
+        let parsed = parse(&["COUNT", "5"]).unwrap();
+        assert_eq!(parsed.k, Some(5));
+        assert_eq!(parsed.output, QueryOutput::Objects);
+        let parsed = parse(&["RADIUS", "100", "COUNT", "WHERETAG", "taxi"]).unwrap();
+        assert_eq!(parsed.k, None);
+        assert_eq!(parsed.output, QueryOutput::Count);
+
+        assert!(parse(&["0"])
+            .unwrap_err()
+            .contains("radius must be greater than 0"));
+        assert!(parse(&["500", "RADIUS", "100"])
+            .unwrap_err()
+            .contains("duplicate RADIUS"));
+        assert!(parse(&["LIMIT", "2", "COUNT", "3"])
+            .unwrap_err()
+            .contains("duplicate LIMIT"));
+        assert!(parse(&["500", "COUNT", "COUNT"])
+            .unwrap_err()
+            .contains("duplicate COUNT"));
+        assert!(parse(&["LIMIT"])
+            .unwrap_err()
+            .contains("LIMIT keyword requires a value"));
+        assert!(parse(&["500", "COUNT", "FENCE"])
+            .unwrap_err()
+            .contains("FENCE requires RADIUS"));
+    }
+
     #[test]
     fn test_parse_nearby_args_zero_count() {
         let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("POINT".to_string())),
-            RespValue::BulkString(Some("116.4".to_string())),
-            RespValue::BulkString(Some("39.9".to_string())),
-            RespValue::BulkString(Some("COUNT".to_string())),
-            RespValue::BulkString(Some("0".to_string())), // k = 0
+            RespValue::bulk("fleet"),
+            RespValue::bulk("POINT"),
+            RespValue::bulk("116.4"),
+            RespValue::bulk("39.9"),
+            RespValue::bulk("COUNT"),
+            RespValue::bulk("0"), // k = 0
         ];
 
         let parser = ArgumentParser::new(&args, "NEARBY");
@@ -713,4 +2072,405 @@ mod tests {
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("count must be greater than 0"));
     }
+
+    #[test]
+    fn test_parse_nearby_args_approx() {
+        let args = vec![
+            RespValue::bulk("fleet"),
+            RespValue::bulk("POINT"),
+            RespValue::bulk("116.4"),
+            RespValue::bulk("39.9"),
+            RespValue::bulk("COUNT"),
+            RespValue::bulk("10"),
+            RespValue::bulk("APPROX"),
+            RespValue::bulk("0.25"),
+        ];
+
+        let parser = ArgumentParser::new(&args, "NEARBY");
+        let parsed = parser.parse_nearby_args().unwrap();
+        assert_eq!(parsed.k, Some(10));
+        assert_eq!(parsed.epsilon, 0.25);
+
+        // 负数被拒绝
+        let mut args = args;
+        args[7] = RespValue::bulk("-1");
+        let parser = ArgumentParser::new(&args, "NEARBY");
+        let result = parser.parse_nearby_args();
+        assert!(result
+            .unwrap_err()
+            .contains("epsilon must be a non-negative"));
+    }
+
+    #[test]
+    fn test_parse_nearby_args_within() {
+        let polygon = json!({
+            "type": "Polygon",
+            "coordinates": [[[116.0, 39.0], [117.0, 39.0], [117.0, 40.0], [116.0, 39.0]]]
+        })
+        .to_string();
+
+        let values = args(&[
+            "fleet", "POINT", "116.4", "39.9", "COUNT", "5", "WITHIN", "geojson", &polygon,
+        ]);
+        let parsed = ArgumentParser::new(&values, "NEARBY")
+            .parse_nearby_args()
+            .unwrap();
+        assert_eq!(parsed.k, Some(5));
+        assert!(matches!(parsed.region, Some(Geometry::Polygon(_))));
+
+        let values = args(&["fleet", "POINT", "116.4", "39.9", "WITHIN", &polygon]);
+        let err = ArgumentParser::new(&values, "NEARBY")
+            .parse_nearby_args()
+            .unwrap_err();
+        assert!(err.contains("requires GEOJSON"));
+
+        let values = args(&[
+            "fleet", "POINT", "116.4", "39.9", "WITHIN", "BOUNDS", &polygon,
+        ]);
+        let err = ArgumentParser::new(&values, "NEARBY")
+            .parse_nearby_args()
+            .unwrap_err();
+        assert!(err.contains("expected 'GEOJSON' after WITHIN"));
+
+        let values = args(&[
+            "fleet", "POINT", "116.4", "39.9", "WITHIN", "GEOJSON", "{bad", "COUNT", "1",
+        ]);
+        let err = ArgumentParser::new(&values, "NEARBY")
+            .parse_nearby_args()
+            .unwrap_err();
+        assert!(err.starts_with("ERR invalid GeoJSON"));
+
+        let values = args(&[
+            "fleet", "POINT", "116.4", "39.9", "WITHIN", "GEOJSON", &polygon, "WITHIN", "GEOJSON",
+            &polygon,
+        ]);
+        let err = ArgumentParser::new(&values, "NEARBY")
+            .parse_nearby_args()
+            .unwrap_err();
+        assert!(err.contains("duplicate WITHIN"));
+    }
+
+    #[test]
+    fn test_parse_nearby_args_type_and_where() {
+        let nearby = |extra: &[&str]| {
+            let mut list = vec!["fleet", "POINT", "116.4", "39.9", "LIMIT", "5"];
+            list.extend_from_slice(extra);
+            ArgumentParser::new(&args(&list), "NEARBY").parse_nearby_args()
+        };
+
+        let parsed = nearby(&[]).unwrap();
+        assert!(parsed.filter.is_empty());
+
+        let parsed = nearby(&[
+            "TYPE", "point", "type", "POLYGON", "WHERE", "speed", "10", "+inf", "WHERE", "age",
+            "-inf", "5",
+        ])
+        .unwrap();
+        assert_eq!(
+            parsed.filter.kinds,
+            vec![GeometryKind::Point, GeometryKind::Polygon]
+        );
+        assert_eq!(
+            parsed.filter.fields,
+            vec![
+                FieldRange {
+                    field: "speed".to_string(),
+                    min: 10.0,
+                    max: f64::INFINITY,
+                },
+                FieldRange {
+                    field: "age".to_string(),
+                    min: f64::NEG_INFINITY,
+                    max: 5.0,
+                },
+            ]
+        );
+
+        assert!(nearby(&["TYPE", "circle"])
+            .unwrap_err()
+            .starts_with("ERR invalid TYPE 'circle'"));
+        assert!(nearby(&["TYPE"])
+            .unwrap_err()
+            .contains("requires a geometry type"));
+        assert!(nearby(&["WHERE", "speed", "10"])
+            .unwrap_err()
+            .contains("requires a field, min and max"));
+        assert!(nearby(&["WHERE", "speed", "fast", "10"])
+            .unwrap_err()
+            .starts_with("ERR invalid WHERE bound"));
+        assert!(nearby(&["WHERE", "speed", "10", "1"])
+            .unwrap_err()
+            .contains("min must not be greater than max"));
+    }
+
+    #[test]
+    fn test_parse_nearby_args_distance() {
+        let nearby = |list: &[&str]| ArgumentParser::new(&args(list), "NEARBY").parse_nearby_args();
+
+        let parsed = nearby(&["fleet", "POINT", "1", "2", "LIMIT", "1"]).unwrap();
+        assert_eq!(parsed.metric, None);
+        let parsed = nearby(&[
+            "fleet", "POINT", "1", "2", "LIMIT", "1", "DISTANCE", "Cosines",
+        ])
+        .unwrap();
+        assert_eq!(parsed.metric, Some(DistanceMetric::SphericalLawOfCosines));
+
+        // 平面坐标不受经纬度范围限制
+        let parsed = nearby(&[
+            "floor",
+            "POINT",
+            "250",
+            "120",
+            "LIMIT",
+            "1",
+            "DISTANCE",
+            "euclidean",
+        ])
+        .unwrap();
+        assert_eq!(parsed.metric, Some(DistanceMetric::Euclidean));
+        assert!(nearby(&["floor", "POINT", "250", "120", "LIMIT", "1"])
+            .unwrap_err()
+            .contains("invalid longitude"));
+
+        assert!(
+            nearby(&["fleet", "POINT", "1", "2", "LIMIT", "1", "DISTANCE", "taxicab"])
+                .unwrap_err()
+                .starts_with("ERR invalid DISTANCE 'taxicab'")
+        );
+        assert!(nearby(&[
+            "fleet",
+            "POINT",
+            "1",
+            "2",
+            "LIMIT",
+            "1",
+            "DISTANCE",
+            "euclidean",
+            "DISTANCE",
+            "haversine",
+        ])
+        .unwrap_err()
+        .contains("duplicate DISTANCE"));
+    }
+
+    #[test]
+    fn test_parse_intersects_args_order() {
+        let polygon = json!({
+            "type": "Polygon",
+            "coordinates": [[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 0.0]]]
+        })
+        .to_string();
+        let args = vec![
+            RespValue::bulk("fleet"),
+            RespValue::bulk(polygon.clone()),
+            RespValue::bulk("LIMIT"),
+            RespValue::bulk("10"),
+            RespValue::bulk("ORDER"),
+            RespValue::bulk("center"),
+        ];
+
+        let parsed = ArgumentParser::new(&args, "INTERSECTS")
+            .parse_intersects_args()
+            .unwrap();
+        assert_eq!(parsed.limit, 10);
+        assert_eq!(parsed.order, SearchOrder::Center);
+
+        let args = vec![
+            RespValue::bulk("fleet"),
+            RespValue::bulk(polygon.clone()),
+            RespValue::bulk("ORDER"),
+            RespValue::bulk("id"),
+        ];
+        let parsed = ArgumentParser::new(&args, "INTERSECTS")
+            .parse_intersects_args()
+            .unwrap();
+        assert_eq!(parsed.order, SearchOrder::Id);
+
+        let args = vec![
+            RespValue::bulk("fleet"),
+            RespValue::bulk(polygon),
+            RespValue::bulk("ORDER"),
+            RespValue::bulk("random"),
+        ];
+        let result = ArgumentParser::new(&args, "INTERSECTS").parse_intersects_args();
+        assert!(result.unwrap_err().contains("invalid ORDER value"));
+    }
+
+    #[test]
+    fn test_parse_fields_option() {
+        let args = vec![
+            RespValue::bulk("fleet"),
+            RespValue::bulk("POINT"),
+            RespValue::bulk("116.4"),
+            RespValue::bulk("39.9"),
+            RespValue::bulk("FIELDS"),
+            RespValue::bulk("speed,heading"),
+            RespValue::bulk("COUNT"),
+            RespValue::bulk("5"),
+        ];
+        let parsed = ArgumentParser::new(&args, "NEARBY")
+            .parse_nearby_args()
+            .unwrap();
+        assert_eq!(parsed.fields.unwrap().fields(), &["speed", "heading"]);
+
+        let point = json!({"type": "Point", "coordinates": [0.0, 0.0]}).to_string();
+        let args = vec![
+            RespValue::bulk("fleet"),
+            RespValue::bulk(point.clone()),
+            RespValue::bulk("FIELDS"),
+            RespValue::bulk("id"),
+        ];
+        let parsed = ArgumentParser::new(&args, "INTERSECTS")
+            .parse_intersects_args()
+            .unwrap();
+        assert_eq!(parsed.fields.unwrap().fields(), &["id"]);
+
+        // 缺少字段列表
+        let args = vec![
+            RespValue::bulk("fleet"),
+            RespValue::bulk(point),
+            RespValue::bulk("FIELDS"),
+        ];
+        let result = ArgumentParser::new(&args, "INTERSECTS").parse_intersects_args();
+        assert!(result.unwrap_err().contains("FIELDS option requires"));
+    }
+
+    #[test]
+    fn test_parse_agg_args() {
+        let values = args(&["fleet", "BOUNDS", "116", "39", "117", "40", "grid", "0.01"]);
+        let parsed = ArgumentParser::new(&values, "AGG")
+            .parse_agg_args()
+            .unwrap();
+        assert_eq!(parsed.binning, Binning::Grid { size: 0.01 });
+        assert_eq!(parsed.bounds, Rectangle::new(116.0, 39.0, 117.0, 40.0));
+        assert_eq!(parsed.field, None);
+
+        let values = args(&[
+            "fleet", "BOUNDS", "116", "39", "117", "40", "HEX", "0.05", "FIELD", "speed",
+        ]);
+        let parsed = ArgumentParser::new(&values, "AGG")
+            .parse_agg_args()
+            .unwrap();
+        assert_eq!(parsed.binning, Binning::Hex { size: 0.05 });
+        assert_eq!(parsed.field.as_deref(), Some("speed"));
+
+        let values = args(&["fleet", "BOUNDS", "117", "39", "116", "40", "GRID", "0.01"]);
+        let result = ArgumentParser::new(&values, "AGG").parse_agg_args();
+        assert!(result.unwrap_err().contains("invalid bounds"));
+
+        let values = args(&["fleet", "BOUNDS", "116", "39", "117", "40", "GRID", "0"]);
+        let result = ArgumentParser::new(&values, "AGG").parse_agg_args();
+        assert!(result.unwrap_err().contains("bin size"));
+
+        let values = args(&["fleet", "BOUNDS", "116", "39", "117", "40", "H3", "7"]);
+        let result = ArgumentParser::new(&values, "AGG").parse_agg_args();
+        assert!(result.unwrap_err().contains("expected 'GRID' or 'HEX'"));
+    }
+
+    #[test]
+    fn test_parse_hull_args() {
+        let args = vec![RespValue::bulk("fleet")];
+        let parsed = ArgumentParser::new(&args, "HULL")
+            .parse_hull_args()
+            .unwrap();
+        assert!(parsed.area.is_none());
+        assert_eq!(parsed.kind, HullKind::Convex);
+
+        let area = json!({
+            "type": "Polygon",
+            "coordinates": [[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 0.0]]]
+        })
+        .to_string();
+        let args = vec![
+            RespValue::bulk("fleet"),
+            RespValue::bulk("concave"),
+            RespValue::bulk("2"),
+            RespValue::bulk("AREA"),
+            RespValue::bulk(area),
+        ];
+        let parsed = ArgumentParser::new(&args, "HULL")
+            .parse_hull_args()
+            .unwrap();
+        assert!(parsed.area.is_some());
+        assert_eq!(parsed.kind, HullKind::Concave { concavity: 2.0 });
+
+        let args = vec![RespValue::bulk("fleet"), RespValue::bulk("CONCAVE")];
+        let result = ArgumentParser::new(&args, "HULL").parse_hull_args();
+        assert!(result.unwrap_err().contains("requires a value"));
+    }
+
+    #[test]
+    fn test_parse_cluster_args() {
+        let values = args(&["fleet", "eps", "500", "MINPTS", "3"]);
+        let parsed = ArgumentParser::new(&values, "CLUSTER")
+            .parse_cluster_args()
+            .unwrap();
+        assert_eq!(parsed.eps, 500.0);
+        assert_eq!(parsed.min_points, 3);
+        assert!(parsed.bounds.is_none());
+
+        let values = args(&[
+            "fleet", "BOUNDS", "116", "39", "117", "40", "MINPTS", "5", "EPS", "250.5",
+        ]);
+        let parsed = ArgumentParser::new(&values, "CLUSTER")
+            .parse_cluster_args()
+            .unwrap();
+        assert_eq!(parsed.eps, 250.5);
+        assert!(parsed.bounds.is_some());
+
+        // 缺少必需参数或参数非法
+        for parts in [
+            &["fleet", "EPS", "500"][..],
+            &["fleet", "EPS", "0", "MINPTS", "3"][..],
+            &["fleet", "EPS", "500", "MINPTS", "0"][..],
+            &["fleet", "EPS", "500", "MINPTS", "3", "BOUNDS", "1", "2"][..],
+        ] {
+            let values = args(parts);
+            assert!(ArgumentParser::new(&values, "CLUSTER")
+                .parse_cluster_args()
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_parse_snap_args() {
+        let values = args(&["roads", "116.4", "39.9"]);
+        let parsed = ArgumentParser::new(&values, "SNAP")
+            .parse_snap_args()
+            .unwrap();
+        assert_eq!(parsed.collection_id, "roads");
+        assert_eq!((parsed.lon, parsed.lat), (116.4, 39.9));
+
+        let values = args(&["roads", "116.4", "95"]);
+        let result = ArgumentParser::new(&values, "SNAP").parse_snap_args();
+        assert!(result.unwrap_err().contains("invalid latitude"));
+
+        let values = args(&["roads", "116.4"]);
+        assert!(ArgumentParser::new(&values, "SNAP")
+            .parse_snap_args()
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_geomop_args() {
+        let values = args(&["union", "zones", "a", "b"]);
+        let parsed = ArgumentParser::new(&values, "GEOMOP")
+            .parse_geomop_args()
+            .unwrap();
+        assert_eq!(parsed.op, OverlayOp::Union);
+        assert_eq!(parsed.collection_id, "zones");
+        assert_eq!(
+            (parsed.first_id.as_str(), parsed.second_id.as_str()),
+            ("a", "b")
+        );
+
+        let values = args(&["BUFFER", "zones", "a", "b"]);
+        let result = ArgumentParser::new(&values, "GEOMOP").parse_geomop_args();
+        assert!(result.unwrap_err().contains("invalid operation"));
+
+        let values = args(&["UNION", "zones", "a"]);
+        assert!(ArgumentParser::new(&values, "GEOMOP")
+            .parse_geomop_args()
+            .is_err());
+    }
 }
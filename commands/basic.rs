@@ -1,7 +1,10 @@
+use crate::commands::features::feature_values;
 use crate::commands::Command;
 use crate::protocol::parser::RespValue;
 use crate::protocol::RespResponse;
+use crate::storage::GeoDatabase;
 use crate::Result;
+use std::sync::Arc;
 
 pub struct PingCommand;
 
@@ -15,15 +18,68 @@ impl Command for PingCommand {
     }
 }
 
-pub struct HelloCommand;
+/// HELLO 命令
+///
+/// 语法: HELLO [protover]
+///
+/// 不带参数时返回问候语；带协议版本时进行握手，返回
+/// `server, spatio, version, <版本>, proto, <协议>, features, [...]`。
+/// RESP2 以数组回复，RESP3 以映射回复；协商 RESP3 后围栏事件以推送帧发送
+pub struct HelloCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl HelloCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
 
 impl Command for HelloCommand {
     fn name(&self) -> &'static str {
         "HELLO"
     }
 
-    async fn execute(&self, _args: &[RespValue]) -> Result<String> {
-        Ok(RespResponse::simple_string("Hello, World!"))
+    async fn execute(&self, args: &[RespValue]) -> Result<String> {
+        let protover = match args {
+            [] => return Ok(RespResponse::simple_string("Hello, World!")),
+            [RespValue::BulkString(Some(protover))] => String::from_utf8_lossy(protover),
+            _ => {
+                return Ok(RespResponse::error(
+                    "ERR wrong number of arguments for 'HELLO' command",
+                ))
+            }
+        };
+
+        let proto = match protover.as_ref() {
+            "2" => 2,
+            "3" => 3,
+            _ => {
+                return Ok(RespResponse::error(&format!(
+                    "NOPROTO unsupported protocol version '{}', only 2 and 3 are supported",
+                    protover
+                )))
+            }
+        };
+
+        let bulk = |s: &str| RespValue::bulk(s);
+        let pairs = vec![
+            (bulk("server"), bulk("spatio")),
+            (bulk("version"), bulk(env!("CARGO_PKG_VERSION"))),
+            (bulk("proto"), RespValue::Integer(proto)),
+            (
+                bulk("features"),
+                RespValue::Array(Some(feature_values(&self.database))),
+            ),
+        ];
+        if proto == 3 {
+            return Ok(RespResponse::map(&pairs));
+        }
+        let values: Vec<RespValue> = pairs
+            .into_iter()
+            .flat_map(|(key, value)| [key, value])
+            .collect();
+        Ok(RespResponse::array(Some(&values)))
     }
 }
 
@@ -52,11 +108,31 @@ mod tests {
 
     #[tokio::test]
     async fn test_hello_command() {
-        let command = HelloCommand;
+        let command = HelloCommand::new(Arc::new(GeoDatabase::new()));
         let result = command.execute(&[]).await.unwrap();
         assert_eq!(result, "+Hello, World!\r\n");
     }
 
+    #[tokio::test]
+    async fn test_hello_handshake() {
+        let command = HelloCommand::new(Arc::new(GeoDatabase::new()));
+
+        let args = vec![RespValue::bulk("2")];
+        let result = command.execute(&args).await.unwrap();
+        assert!(result.starts_with("*8\r\n$6\r\nserver\r\n$6\r\nspatio\r\n"));
+        assert!(result.contains(":2\r\n$8\r\nfeatures\r\n*"));
+        assert!(result.contains("$7\r\ngeojson\r\n"));
+
+        let args = vec![RespValue::bulk("3")];
+        let result = command.execute(&args).await.unwrap();
+        assert!(result.starts_with("%4\r\n$6\r\nserver\r\n$6\r\nspatio\r\n"));
+        assert!(result.contains("$5\r\nproto\r\n:3\r\n"));
+
+        let args = vec![RespValue::bulk("4")];
+        let result = command.execute(&args).await.unwrap();
+        assert!(result.starts_with("-NOPROTO"));
+    }
+
     #[tokio::test]
     async fn test_quit_command() {
         let command = QuitCommand;
@@ -0,0 +1,120 @@
+use crate::commands::args::ArgumentParser;
+use crate::commands::Command;
+use crate::protocol::{parser::RespValue, RespResponse};
+use crate::storage::GeoDatabase;
+use crate::Result;
+use std::sync::Arc;
+
+/// CLUSTER 命令：在服务端对 collection 做 DBSCAN 密度聚类，避免客户端拉取全部点
+pub struct ClusterCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl ClusterCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+impl Command for ClusterCommand {
+    fn name(&self) -> &'static str {
+        "CLUSTER"
+    }
+
+    fn execute(
+        &self,
+        args: &[RespValue],
+    ) -> impl std::future::Future<Output = Result<String>> + Send {
+        let database = Arc::clone(&self.database);
+
+        // 同步解析参数
+        let parse_result = ArgumentParser::new(args, "CLUSTER").parse_cluster_args();
+
+        async move {
+            // 检查参数解析结果
+            let parsed_args = match parse_result {
+                Ok(args) => args,
+                Err(err_msg) => {
+                    return Ok(RespResponse::error(&err_msg));
+                }
+            };
+
+            match database
+                .cluster(
+                    &parsed_args.collection_id,
+                    parsed_args.eps,
+                    parsed_args.min_points,
+                    parsed_args.bounds.as_ref(),
+                )
+                .await
+            {
+                Ok(clusters) => {
+                    if clusters.is_empty() {
+                        return Ok(RespResponse::array(None));
+                    }
+
+                    // 每个聚类: [cluster_id, lon, lat, count, [id1, id2, ...]]
+                    let resp_values: Vec<RespValue> = clusters
+                        .into_iter()
+                        .map(|cluster| {
+                            let count = cluster.members.len() as i64;
+                            let members =
+                                cluster.members.into_iter().map(RespValue::bulk).collect();
+                            RespValue::Array(Some(vec![
+                                RespValue::Integer(cluster.id as i64),
+                                RespValue::bulk(cluster.centroid[0].to_string()),
+                                RespValue::bulk(cluster.centroid[1].to_string()),
+                                RespValue::Integer(count),
+                                RespValue::Array(Some(members)),
+                            ]))
+                        })
+                        .collect();
+
+                    Ok(RespResponse::array(Some(&resp_values)))
+                }
+                Err(e) => Ok(RespResponse::command_error(
+                    "cluster query failed",
+                    e.as_ref(),
+                )),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::args;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_cluster_command() {
+        let database = Arc::new(GeoDatabase::new());
+        let points = [
+            ("a", 116.400, 39.9),
+            ("b", 116.401, 39.9),
+            ("c", 116.402, 39.9),
+            ("lonely", 120.0, 30.0),
+        ];
+        for (id, lon, lat) in points {
+            let point = json!({"type": "Point", "coordinates": [lon, lat]});
+            database.set("fleet", id, &point.to_string()).await.unwrap();
+        }
+
+        let cmd = ClusterCommand::new(Arc::clone(&database));
+        let values = args(&["fleet", "EPS", "150", "MINPTS", "2"]);
+        let result = cmd.execute(&values).await.unwrap();
+
+        assert!(result.starts_with("*1\r\n*5\r\n:0\r\n"));
+        assert!(result.contains(":3\r\n*3\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\nc\r\n"));
+        assert!(!result.contains("lonely"));
+
+        // 缺少 MINPTS
+        let values = args(&["fleet", "EPS", "150"]);
+        assert!(cmd.execute(&values).await.unwrap().starts_with("-ERR"));
+
+        // 不存在的 collection
+        let values = args(&["trucks", "EPS", "150", "MINPTS", "2"]);
+        assert!(cmd.execute(&values).await.unwrap().starts_with("-NOINDEX "));
+    }
+}
@@ -0,0 +1,504 @@
+use crate::commands::Command;
+use crate::protocol::{parser::RespValue, RespResponse};
+use crate::rtree::algorithms::validate::TreeReport;
+use crate::storage::GeoDatabase;
+use crate::Result;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 示例城市所在的 collection
+pub const DEMO_CITIES: &str = "demo:cities";
+
+/// 示例国家轮廓所在的 collection
+pub const DEMO_COUNTRIES: &str = "demo:countries";
+
+/// 示例城市：(ID, 名称, 国家代码, 经度, 纬度, 都市区人口)
+#[rustfmt::skip]
+const CITIES: &[(&str, &str, &str, f64, f64, u64)] = &[
+    ("tokyo", "Tokyo", "JP", 139.6917, 35.6895, 37_400_000),
+    ("osaka", "Osaka", "JP", 135.5023, 34.6937, 19_100_000),
+    ("delhi", "Delhi", "IN", 77.2090, 28.6139, 31_000_000),
+    ("mumbai", "Mumbai", "IN", 72.8777, 19.0760, 20_400_000),
+    ("shanghai", "Shanghai", "CN", 121.4737, 31.2304, 27_000_000),
+    ("beijing", "Beijing", "CN", 116.4074, 39.9042, 20_400_000),
+    ("singapore", "Singapore", "SG", 103.8198, 1.3521, 5_900_000),
+    ("sydney", "Sydney", "AU", 151.2093, -33.8688, 5_300_000),
+    ("melbourne", "Melbourne", "AU", 144.9631, -37.8136, 5_100_000),
+    ("cairo", "Cairo", "EG", 31.2357, 30.0444, 21_300_000),
+    ("alexandria", "Alexandria", "EG", 29.9187, 31.2001, 5_400_000),
+    ("lagos", "Lagos", "NG", 3.3792, 6.5244, 14_900_000),
+    ("nairobi", "Nairobi", "KE", 36.8219, -1.2921, 4_900_000),
+    ("cape_town", "Cape Town", "ZA", 18.4241, -33.9249, 4_800_000),
+    ("istanbul", "Istanbul", "TR", 28.9784, 41.0082, 15_400_000),
+    ("moscow", "Moscow", "RU", 37.6173, 55.7558, 12_600_000),
+    ("london", "London", "GB", -0.1276, 51.5072, 9_500_000),
+    ("paris", "Paris", "FR", 2.3522, 48.8566, 11_100_000),
+    ("lyon", "Lyon", "FR", 4.8357, 45.7640, 1_700_000),
+    ("marseille", "Marseille", "FR", 5.3698, 43.2965, 1_600_000),
+    ("madrid", "Madrid", "ES", -3.7038, 40.4168, 6_700_000),
+    ("barcelona", "Barcelona", "ES", 2.1734, 41.3851, 5_600_000),
+    ("rome", "Rome", "IT", 12.4964, 41.9028, 4_300_000),
+    ("berlin", "Berlin", "DE", 13.4050, 52.5200, 3_600_000),
+    ("hamburg", "Hamburg", "DE", 9.9937, 53.5511, 1_800_000),
+    ("munich", "Munich", "DE", 11.5820, 48.1351, 1_500_000),
+    ("new_york", "New York", "US", -74.0060, 40.7128, 18_800_000),
+    ("los_angeles", "Los Angeles", "US", -118.2437, 34.0522, 12_400_000),
+    ("toronto", "Toronto", "CA", -79.3832, 43.6532, 6_300_000),
+    ("mexico_city", "Mexico City", "MX", -99.1332, 19.4326, 21_800_000),
+    ("sao_paulo", "São Paulo", "BR", -46.6333, -23.5505, 22_000_000),
+    ("buenos_aires", "Buenos Aires", "AR", -58.3816, -34.6037, 15_200_000),
+];
+
+/// 示例国家：(ID, 名称, 轮廓)，轮廓是手工简化的粗略边界，只用于演示查询
+const COUNTRIES: &[(&str, &str, &[[f64; 2]])] = &[
+    (
+        "FR",
+        "France",
+        &[
+            [-1.8, 43.4],
+            [3.2, 42.4],
+            [7.5, 43.8],
+            [6.8, 46.4],
+            [8.2, 48.9],
+            [4.2, 50.0],
+            [2.5, 51.1],
+            [-1.6, 49.6],
+            [-4.7, 48.4],
+            [-1.2, 46.2],
+            [-1.8, 43.4],
+        ],
+    ),
+    (
+        "ES",
+        "Spain",
+        &[
+            [-9.3, 43.2],
+            [-1.8, 43.4],
+            [3.2, 42.4],
+            [3.3, 41.9],
+            [0.3, 40.4],
+            [-0.5, 38.7],
+            [-2.1, 36.7],
+            [-5.6, 36.0],
+            [-7.4, 37.2],
+            [-7.0, 39.0],
+            [-9.5, 38.8],
+            [-8.9, 42.0],
+            [-9.3, 43.2],
+        ],
+    ),
+    (
+        "DE",
+        "Germany",
+        &[
+            [5.9, 50.8],
+            [6.1, 53.5],
+            [8.6, 53.9],
+            [9.9, 54.8],
+            [14.2, 53.9],
+            [14.6, 52.6],
+            [15.0, 51.1],
+            [12.1, 50.3],
+            [13.8, 48.7],
+            [13.0, 47.5],
+            [7.6, 47.6],
+            [8.2, 48.9],
+            [6.4, 49.5],
+            [5.9, 50.8],
+        ],
+    ),
+    (
+        "EG",
+        "Egypt",
+        &[
+            [25.0, 31.6],
+            [34.2, 31.3],
+            [34.9, 29.5],
+            [36.9, 22.0],
+            [25.0, 22.0],
+            [25.0, 31.6],
+        ],
+    ),
+    (
+        "AU",
+        "Australia",
+        &[
+            [113.5, -22.0],
+            [129.0, -14.9],
+            [136.8, -12.2],
+            [142.5, -10.7],
+            [145.4, -14.9],
+            [153.6, -28.2],
+            [150.0, -37.5],
+            [146.3, -39.1],
+            [140.6, -38.0],
+            [131.1, -31.5],
+            [115.0, -34.3],
+            [113.5, -22.0],
+        ],
+    ),
+];
+
+/// DEBUG 命令：开发和试用时的辅助操作
+///
+/// 语法:
+/// - DEBUG LOADDEMO：写入示例数据，新用户不需要准备数据就可以试用 NEARBY/INTERSECTS。
+///   城市（Point，属性为 name/country/population）写入 `demo:cities`，
+///   国家的粗略轮廓（Polygon，属性为 name/iso）写入 `demo:countries`，已存在的同名对象被覆盖。
+///   与 SET 一样写入 AOF，返回 [[collection, 对象数量], ...]
+/// - DEBUG VALIDATE collection：检查 collection 的 R-tree 结构（见 `RTree::validate`），返回
+///   [valid, 0|1, depth, n, leaf_nodes, n, index_nodes, n, entries, n, fill, 比例, violations, [...]]，
+///   collection 不存在时返回 nil。用于排查删除和下溢处理留下的结构问题
+///
+/// 以下子命令只用于编写确定性的测试和诊断过期行为，需要配置 `server.debug_testing = true`：
+/// - DEBUG SET-ACTIVE-EXPIRE 0|1：关闭/开启后台主动过期，关闭后过期对象只在 GET/TTL 读取时删除
+/// - DEBUG SLEEP seconds：等待指定秒数（可以带小数）后回复 OK，只阻塞当前连接
+pub struct DebugCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl DebugCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+enum DebugSubcommand {
+    LoadDemo,
+    Validate(String),
+    SetActiveExpire(bool),
+    Sleep(Duration),
+}
+
+impl DebugSubcommand {
+    /// 需要 `server.debug_testing` 才能执行的子命令名称
+    fn testing_name(&self) -> Option<&'static str> {
+        match self {
+            Self::LoadDemo | Self::Validate(_) => None,
+            Self::SetActiveExpire(_) => Some("SET-ACTIVE-EXPIRE"),
+            Self::Sleep(_) => Some("SLEEP"),
+        }
+    }
+}
+
+fn parse_args(args: &[RespValue]) -> std::result::Result<DebugSubcommand, String> {
+    let strings: Vec<&str> = args
+        .iter()
+        .map(|arg| match arg {
+            RespValue::BulkString(Some(_)) => arg
+                .as_str()
+                .ok_or_else(|| "ERR invalid argument: expected UTF-8 string".to_string()),
+            _ => Err("ERR invalid argument: expected string".to_string()),
+        })
+        .collect::<std::result::Result<_, _>>()?;
+
+    let Some((subcommand, rest)) = strings.split_first() else {
+        return Err("ERR wrong number of arguments for 'DEBUG' command".to_string());
+    };
+    match (subcommand.to_uppercase().as_str(), rest) {
+        ("LOADDEMO", []) => Ok(DebugSubcommand::LoadDemo),
+        ("LOADDEMO", _) => {
+            Err("ERR wrong number of arguments for 'DEBUG LOADDEMO' command".to_string())
+        }
+        ("VALIDATE", [collection]) => Ok(DebugSubcommand::Validate(collection.to_string())),
+        ("SET-ACTIVE-EXPIRE", [flag]) => match *flag {
+            "0" => Ok(DebugSubcommand::SetActiveExpire(false)),
+            "1" => Ok(DebugSubcommand::SetActiveExpire(true)),
+            _ => Err(format!(
+                "ERR invalid flag '{}' for 'DEBUG SET-ACTIVE-EXPIRE', expected 0 or 1",
+                flag
+            )),
+        },
+        ("SLEEP", [seconds]) => seconds
+            .parse::<f64>()
+            .ok()
+            .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+            .map(DebugSubcommand::Sleep)
+            .ok_or_else(|| {
+                format!(
+                    "ERR invalid seconds '{}' for 'DEBUG SLEEP', expected non-negative number",
+                    seconds
+                )
+            }),
+        ("VALIDATE" | "SET-ACTIVE-EXPIRE" | "SLEEP", _) => Err(format!(
+            "ERR wrong number of arguments for 'DEBUG {}' command",
+            subcommand.to_uppercase()
+        )),
+        _ => Err(format!(
+            "ERR unknown DEBUG subcommand '{}', expected LOADDEMO, VALIDATE, SET-ACTIVE-EXPIRE or SLEEP",
+            subcommand
+        )),
+    }
+}
+
+/// 写入示例数据，返回每个 collection 写入的对象数量
+async fn load_demo(database: &GeoDatabase) -> Result<Vec<(String, usize)>> {
+    for (id, name, country, lon, lat, population) in CITIES {
+        let feature = json!({
+            "type": "Feature",
+            "properties": {"name": name, "country": country, "population": population},
+            "geometry": {"type": "Point", "coordinates": [lon, lat]}
+        });
+        database.set(DEMO_CITIES, id, &feature.to_string()).await?;
+    }
+    for (iso, name, outline) in COUNTRIES {
+        let feature = json!({
+            "type": "Feature",
+            "properties": {"name": name, "iso": iso},
+            "geometry": {"type": "Polygon", "coordinates": [outline]}
+        });
+        database
+            .set(DEMO_COUNTRIES, iso, &feature.to_string())
+            .await?;
+    }
+    Ok(vec![
+        (DEMO_CITIES.to_string(), CITIES.len()),
+        (DEMO_COUNTRIES.to_string(), COUNTRIES.len()),
+    ])
+}
+
+/// DEBUG VALIDATE 的回复，字段与 STATS 一样成对排列
+fn validate_reply(report: &TreeReport) -> RespValue {
+    let violations = report
+        .violations
+        .iter()
+        .map(|violation| RespValue::bulk(violation.as_str()))
+        .collect();
+    RespValue::Array(Some(vec![
+        RespValue::bulk("valid"),
+        RespValue::Integer(report.is_valid() as i64),
+        RespValue::bulk("depth"),
+        RespValue::Integer(report.depth as i64),
+        RespValue::bulk("leaf_nodes"),
+        RespValue::Integer(report.leaf_nodes as i64),
+        RespValue::bulk("index_nodes"),
+        RespValue::Integer(report.index_nodes as i64),
+        RespValue::bulk("entries"),
+        RespValue::Integer(report.entries as i64),
+        RespValue::bulk("fill"),
+        RespValue::bulk(format!("{:.2}", report.fill)),
+        RespValue::bulk("violations"),
+        RespValue::Array(Some(violations)),
+    ]))
+}
+
+impl Command for DebugCommand {
+    fn name(&self) -> &'static str {
+        "DEBUG"
+    }
+
+    fn execute(
+        &self,
+        args: &[RespValue],
+    ) -> impl std::future::Future<Output = Result<String>> + Send {
+        let database = Arc::clone(&self.database);
+
+        // 同步解析参数
+        let parse_result = parse_args(args);
+
+        async move {
+            let subcommand = match parse_result {
+                Ok(subcommand) => subcommand,
+                Err(err_msg) => return Ok(RespResponse::error(&err_msg)),
+            };
+            if let Some(name) = subcommand.testing_name() {
+                if !database.debug_testing_enabled() {
+                    return Ok(RespResponse::error(&format!(
+                        "ERR DEBUG {} is disabled, set server.debug_testing = true to enable it",
+                        name
+                    )));
+                }
+            }
+
+            match subcommand {
+                DebugSubcommand::LoadDemo => match load_demo(&database).await {
+                    Ok(loaded) => {
+                        let values: Vec<RespValue> = loaded
+                            .into_iter()
+                            .map(|(name, count)| {
+                                RespValue::Array(Some(vec![
+                                    RespValue::bulk(name),
+                                    RespValue::Integer(count as i64),
+                                ]))
+                            })
+                            .collect();
+                        Ok(RespResponse::array(Some(&values)))
+                    }
+                    Err(e) => Ok(RespResponse::command_error(
+                        "failed to load demo data",
+                        e.as_ref(),
+                    )),
+                },
+                DebugSubcommand::Validate(collection) => {
+                    match database.validate_collection(&collection).await {
+                        Ok(Some(report)) => {
+                            Ok(RespResponse::value_to_string(&validate_reply(&report)))
+                        }
+                        Ok(None) => Ok(RespResponse::bulk_string(None)),
+                        Err(e) => Ok(RespResponse::command_error(
+                            "failed to validate",
+                            e.as_ref(),
+                        )),
+                    }
+                }
+                DebugSubcommand::SetActiveExpire(enabled) => {
+                    database.set_active_expire(enabled);
+                    Ok(RespResponse::simple_string("OK"))
+                }
+                DebugSubcommand::Sleep(duration) => {
+                    tokio::time::sleep(duration).await;
+                    Ok(RespResponse::simple_string("OK"))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::args;
+    use geo::{Geometry, Point};
+
+    #[tokio::test]
+    async fn test_debug_loaddemo() {
+        let database = Arc::new(GeoDatabase::new());
+        let cmd = DebugCommand::new(Arc::clone(&database));
+
+        let result = cmd.execute(&args(&["loaddemo"])).await.unwrap();
+        let expected = format!(
+            "*2\r\n*2\r\n${}\r\n{}\r\n:{}\r\n*2\r\n${}\r\n{}\r\n:{}\r\n",
+            DEMO_CITIES.len(),
+            DEMO_CITIES,
+            CITIES.len(),
+            DEMO_COUNTRIES.len(),
+            DEMO_COUNTRIES,
+            COUNTRIES.len()
+        );
+        assert_eq!(result, expected);
+
+        // 每个城市都落在所属国家的轮廓内（有轮廓的国家）
+        for (iso, _, _) in COUNTRIES {
+            let country = database.get(DEMO_COUNTRIES, iso).await.unwrap().unwrap();
+            let mut inside: Vec<String> = database
+                .intersects(DEMO_CITIES, &country.geometry, 0, true)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|item| item.id)
+                .collect();
+            inside.sort();
+            let mut expected: Vec<String> = CITIES
+                .iter()
+                .filter(|city| city.2 == *iso)
+                .map(|city| city.0.to_string())
+                .collect();
+            expected.sort();
+            assert_eq!(inside, expected, "{}", iso);
+        }
+
+        // 离埃菲尔铁塔最近的城市是巴黎
+        let nearest = database
+            .nearby(DEMO_CITIES, 2.2945, 48.8584, 1, None)
+            .await
+            .unwrap();
+        assert_eq!(nearest[0].0.id, "paris");
+
+        // 重复加载覆盖同名对象
+        cmd.execute(&args(&["LOADDEMO"])).await.unwrap();
+        let counts = database.collection_counts().await;
+        assert!(counts.contains(&(DEMO_CITIES.to_string(), CITIES.len())));
+        let paris = Geometry::Point(Point::new(2.3522, 48.8566));
+        let france = database
+            .intersects(DEMO_COUNTRIES, &paris, 0, false)
+            .await
+            .unwrap();
+        assert_eq!(france.len(), 1);
+        assert!(france[0].geojson.contains("France"));
+    }
+
+    #[tokio::test]
+    async fn test_debug_validate() {
+        let database = Arc::new(GeoDatabase::new());
+        let cmd = DebugCommand::new(Arc::clone(&database));
+        cmd.execute(&args(&["LOADDEMO"])).await.unwrap();
+
+        let result = cmd
+            .execute(&args(&["VALIDATE", DEMO_CITIES]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("*14\r\n$5\r\nvalid\r\n:1\r\n"));
+        assert!(result.contains(&format!("$7\r\nentries\r\n:{}\r\n", CITIES.len())));
+        assert!(result.ends_with("$10\r\nviolations\r\n*0\r\n"));
+
+        let result = cmd.execute(&args(&["VALIDATE", "nope"])).await.unwrap();
+        assert_eq!(result, "$-1\r\n");
+        let result = cmd.execute(&args(&["VALIDATE"])).await.unwrap();
+        assert!(result.contains("wrong number of arguments for 'DEBUG VALIDATE'"));
+    }
+
+    #[tokio::test]
+    async fn test_debug_command_errors() {
+        let cmd = DebugCommand::new(Arc::new(GeoDatabase::new()));
+
+        let result = cmd.execute(&args(&[])).await.unwrap();
+        assert!(result.contains("wrong number of arguments"));
+        let result = cmd.execute(&args(&["LOADDEMO", "extra"])).await.unwrap();
+        assert!(result.contains("wrong number of arguments for 'DEBUG LOADDEMO'"));
+        let result = cmd.execute(&args(&["SEGFAULT"])).await.unwrap();
+        assert!(result.starts_with("-ERR unknown DEBUG subcommand 'SEGFAULT'"));
+        let result = cmd.execute(&args(&["sleep"])).await.unwrap();
+        assert!(result.contains("wrong number of arguments for 'DEBUG SLEEP'"));
+        let result = cmd.execute(&args(&["SLEEP", "-1"])).await.unwrap();
+        assert!(result.starts_with("-ERR invalid seconds '-1'"));
+        let result = cmd
+            .execute(&args(&["SET-ACTIVE-EXPIRE", "yes"]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("-ERR invalid flag 'yes'"));
+
+        // 测试命令默认关闭
+        let result = cmd.execute(&args(&["SLEEP", "0"])).await.unwrap();
+        assert!(result.starts_with("-ERR DEBUG SLEEP is disabled"));
+        let result = cmd
+            .execute(&args(&["SET-ACTIVE-EXPIRE", "0"]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("-ERR DEBUG SET-ACTIVE-EXPIRE is disabled"));
+    }
+
+    #[tokio::test]
+    async fn test_debug_testing_commands() {
+        let database = Arc::new(GeoDatabase::new().with_debug_testing(true));
+        let cmd = DebugCommand::new(Arc::clone(&database));
+
+        // 关闭主动过期后，后台清理不再删除过期对象
+        let point = r#"{"type":"Point","coordinates":[1.0,2.0]}"#;
+        database.set("fleet", "truck1", point).await.unwrap();
+        database
+            .expire("fleet", "truck1", Duration::from_millis(1))
+            .await
+            .unwrap();
+        let result = cmd
+            .execute(&args(&["set-active-expire", "0"]))
+            .await
+            .unwrap();
+        assert_eq!(result, "+OK\r\n");
+        assert!(!database.active_expire_enabled());
+
+        let started = std::time::Instant::now();
+        let result = cmd.execute(&args(&["SLEEP", "0.02"])).await.unwrap();
+        assert_eq!(result, "+OK\r\n");
+        assert!(started.elapsed() >= Duration::from_millis(20));
+
+        assert_eq!(database.expire_due(0).await.unwrap(), 0);
+        assert_eq!(database.stats().await.unwrap().total_items, 1);
+
+        cmd.execute(&args(&["SET-ACTIVE-EXPIRE", "1"]))
+            .await
+            .unwrap();
+        assert_eq!(database.expire_due(0).await.unwrap(), 1);
+        assert_eq!(database.stats().await.unwrap().total_items, 0);
+    }
+}
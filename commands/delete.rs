@@ -51,7 +51,47 @@ impl Command for DeleteCommand {
                     // 未找到项目，返回 0
                     Ok(RespResponse::integer(0))
                 }
-                Err(e) => Ok(RespResponse::error(&format!("ERR failed to delete: {}", e))),
+                Err(e) => Ok(RespResponse::command_error("failed to delete", e.as_ref())),
+            }
+        }
+    }
+}
+
+/// PDEL collection pattern：删除 ID 匹配 glob 模式的所有对象，返回删除的数量
+pub struct PdelCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl PdelCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+impl Command for PdelCommand {
+    fn name(&self) -> &'static str {
+        "PDEL"
+    }
+
+    fn execute(
+        &self,
+        args: &[RespValue],
+    ) -> impl std::future::Future<Output = Result<String>> + Send {
+        let database = Arc::clone(&self.database);
+        let parse_result = ArgumentParser::new(args, "PDEL").parse_pdel_args();
+
+        async move {
+            let parsed_args = match parse_result {
+                Ok(args) => args,
+                Err(err_msg) => return Ok(RespResponse::error(&err_msg)),
+            };
+
+            match database
+                .pdel(&parsed_args.collection_id, &parsed_args.pattern)
+                .await
+            {
+                Ok(deleted) => Ok(RespResponse::integer(deleted as i64)),
+                Err(e) => Ok(RespResponse::command_error("failed to delete", e.as_ref())),
             }
         }
     }
@@ -78,10 +118,7 @@ mod tests {
 
         let cmd = DeleteCommand::new(Arc::clone(&database));
 
-        let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("truck1".to_string())),
-        ];
+        let args = vec![RespValue::bulk("fleet"), RespValue::bulk("truck1")];
 
         let result = cmd.execute(&args).await.unwrap();
         assert_eq!(result, RespResponse::integer(1));
@@ -96,10 +133,7 @@ mod tests {
         let database = Arc::new(GeoDatabase::new());
         let cmd = DeleteCommand::new(database);
 
-        let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("nonexistent".to_string())),
-        ];
+        let args = vec![RespValue::bulk("fleet"), RespValue::bulk("nonexistent")];
 
         let result = cmd.execute(&args).await.unwrap();
         assert_eq!(result, RespResponse::integer(0));
@@ -111,7 +145,7 @@ mod tests {
         let cmd = DeleteCommand::new(database);
 
         // 参数太少
-        let args = vec![RespValue::BulkString(Some("fleet".to_string()))];
+        let args = vec![RespValue::bulk("fleet")];
 
         let result = cmd.execute(&args).await.unwrap();
         assert!(result.contains("ERR"));
@@ -143,10 +177,7 @@ mod tests {
 
         let cmd = DeleteCommand::new(Arc::clone(&database));
 
-        let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("truck1".to_string())),
-        ];
+        let args = vec![RespValue::bulk("fleet"), RespValue::bulk("truck1")];
 
         // 第一次删除，应该返回 1
         let result = cmd.execute(&args).await.unwrap();
@@ -156,4 +187,53 @@ mod tests {
         let result = cmd.execute(&args).await.unwrap();
         assert_eq!(result, RespResponse::integer(0));
     }
+
+    #[tokio::test]
+    async fn test_pdel_command() {
+        let database = Arc::new(GeoDatabase::new());
+        let point_json = json!({
+            "type": "Point",
+            "coordinates": [-122.4194, 37.7749]
+        })
+        .to_string();
+
+        // 超过一个批次的匹配对象
+        for i in 0..2500 {
+            database
+                .set("fleet", &format!("truck{}", i), &point_json)
+                .await
+                .unwrap();
+        }
+        database.set("fleet", "bus1", &point_json).await.unwrap();
+
+        let cmd = PdelCommand::new(Arc::clone(&database));
+        let pdel = |pattern: &str| {
+            vec![
+                RespValue::bulk("fleet"),
+                RespValue::bulk(pattern.to_string()),
+            ]
+        };
+
+        let result = cmd.execute(&pdel("truck*")).await.unwrap();
+        assert_eq!(result, RespResponse::integer(2500));
+        assert!(database.get("fleet", "truck7").await.unwrap().is_none());
+        assert!(database.get("fleet", "bus1").await.unwrap().is_some());
+
+        // 不含通配符时只删除同名对象
+        let result = cmd.execute(&pdel("bus")).await.unwrap();
+        assert_eq!(result, RespResponse::integer(0));
+        let result = cmd.execute(&pdel("bus?")).await.unwrap();
+        assert_eq!(result, RespResponse::integer(1));
+        assert_eq!(
+            database.collection_counts().await,
+            vec![("fleet".to_string(), 0)]
+        );
+
+        let args = vec![RespValue::bulk("missing"), RespValue::bulk("*")];
+        let result = cmd.execute(&args).await.unwrap();
+        assert_eq!(result, RespResponse::integer(0));
+
+        let result = cmd.execute(&[RespValue::bulk("fleet")]).await.unwrap();
+        assert!(result.contains("ERR"));
+    }
 }
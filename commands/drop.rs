@@ -1,17 +1,42 @@
 use crate::commands::args::ArgumentParser;
 use crate::commands::Command;
 use crate::protocol::{parser::RespValue, RespResponse};
-use crate::storage::GeoDatabase;
+use crate::storage::namespace;
+use crate::storage::{is_glob_pattern, GeoDatabase, Namespace};
 use crate::Result;
 use std::sync::Arc;
 
+/// 批量删除的回复：每个 collection 为 [名称, 对象数量]，没有匹配时为空数组
+fn dropped_reply(dropped: Vec<(String, usize)>) -> String {
+    let values: Vec<RespValue> = dropped
+        .into_iter()
+        .map(|(name, count)| {
+            RespValue::Array(Some(vec![
+                RespValue::bulk(name),
+                RespValue::Integer(count as i64),
+            ]))
+        })
+        .collect();
+    RespResponse::array(Some(&values))
+}
+
 pub struct DropCommand {
     database: Arc<GeoDatabase>,
+    namespace: Namespace,
 }
 
 impl DropCommand {
     pub fn new(database: Arc<GeoDatabase>) -> Self {
-        Self { database }
+        Self {
+            database,
+            namespace: Namespace::default(),
+        }
+    }
+
+    /// 只删除指定命名空间中的 collection
+    pub fn with_namespace(mut self, namespace: Namespace) -> Self {
+        self.namespace = namespace;
+        self
     }
 }
 
@@ -25,6 +50,7 @@ impl Command for DropCommand {
         args: &[RespValue],
     ) -> impl std::future::Future<Output = Result<String>> + Send {
         let database = Arc::clone(&self.database);
+        let namespace = self.namespace;
 
         // 同步解析参数
         let parse_result = ArgumentParser::new(args, "DROP").parse_drop_args();
@@ -37,17 +63,41 @@ impl Command for DropCommand {
                     return Ok(RespResponse::error(&err_msg));
                 }
             };
+            if namespace::is_reserved(&parsed_args.collection_id) {
+                return Ok(RespResponse::error(&format!(
+                    "ERR collection name '{}' is reserved, names starting with '@<n>:' belong to SELECT namespaces",
+                    parsed_args.collection_id
+                )));
+            }
+
+            // 通配符或 DRYRUN：返回 [[collection, 对象数量], ...]
+            if parsed_args.dry_run || is_glob_pattern(&parsed_args.collection_id) {
+                let pattern = &parsed_args.collection_id;
+                return match database
+                    .drop_matching_in(namespace, pattern, parsed_args.dry_run)
+                    .await
+                {
+                    Ok(dropped) => Ok(dropped_reply(dropped)),
+                    Err(e) => Ok(RespResponse::command_error(
+                        "failed to drop collections",
+                        e.as_ref(),
+                    )),
+                };
+            }
 
             // 执行删除 collection 操作
-            match database.drop_collection(&parsed_args.collection_id).await {
+            match database
+                .drop_collection(&namespace.scope(&parsed_args.collection_id))
+                .await
+            {
                 Ok(count) => {
                     // 返回删除的项目数量
                     Ok(RespResponse::integer(count as i64))
                 }
-                Err(e) => Ok(RespResponse::error(&format!(
-                    "ERR failed to drop collection: {}",
-                    e
-                ))),
+                Err(e) => Ok(RespResponse::command_error(
+                    "failed to drop collection",
+                    e.as_ref(),
+                )),
             }
         }
     }
@@ -56,6 +106,7 @@ impl Command for DropCommand {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::commands::args;
     use serde_json::json;
 
     #[tokio::test]
@@ -78,7 +129,7 @@ mod tests {
 
         let cmd = DropCommand::new(Arc::clone(&database));
 
-        let args = vec![RespValue::BulkString(Some("fleet".to_string()))];
+        let args = vec![RespValue::bulk("fleet")];
 
         let result = cmd.execute(&args).await.unwrap();
         // 应该返回删除的项目数量（2个）
@@ -94,7 +145,7 @@ mod tests {
         let database = Arc::new(GeoDatabase::new());
         let cmd = DropCommand::new(database);
 
-        let args = vec![RespValue::BulkString(Some("nonexistent".to_string()))];
+        let args = vec![RespValue::bulk("nonexistent")];
 
         let result = cmd.execute(&args).await.unwrap();
         // 删除不存在的集合应该返回0
@@ -112,4 +163,44 @@ mod tests {
         let result = cmd.execute(&args).await.unwrap();
         assert!(result.contains("wrong number of arguments"));
     }
+
+    async fn set_point(database: &GeoDatabase, collection: &str, id: &str) {
+        let point = json!({"type": "Point", "coordinates": [1.0, 2.0]}).to_string();
+        database.set(collection, id, &point).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_drop_command_pattern_dry_run() {
+        let database = Arc::new(GeoDatabase::new());
+        set_point(&database, "gps:2024-01-01", "a").await;
+        set_point(&database, "gps:2024-01-01", "b").await;
+        set_point(&database, "gps:2024-01-02", "c").await;
+        set_point(&database, "fleet", "truck1").await;
+        let cmd = DropCommand::new(Arc::clone(&database));
+
+        let result = cmd.execute(&args(&["gps:*", "DRYRUN"])).await.unwrap();
+        assert_eq!(
+            result,
+            "*2\r\n*2\r\n$14\r\ngps:2024-01-01\r\n:2\r\n*2\r\n$14\r\ngps:2024-01-02\r\n:1\r\n"
+        );
+        // DRYRUN 不做任何修改
+        assert_eq!(database.collection_names().await.len(), 3);
+
+        let result = cmd.execute(&args(&["gps:*"])).await.unwrap();
+        assert!(result.starts_with("*2\r\n"));
+        assert_eq!(database.collection_names().await, vec!["fleet".to_string()]);
+
+        // 没有匹配时返回空数组
+        let result = cmd.execute(&args(&["gps:*"])).await.unwrap();
+        assert_eq!(result, "*0\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_drop_command_unknown_option() {
+        let database = Arc::new(GeoDatabase::new());
+        let cmd = DropCommand::new(database);
+
+        let result = cmd.execute(&args(&["fleet", "NOW"])).await.unwrap();
+        assert!(result.contains("unknown option 'NOW'"));
+    }
 }
@@ -0,0 +1,258 @@
+use crate::commands::args::ArgumentParser;
+use crate::commands::Command;
+use crate::protocol::{parser::RespValue, RespResponse};
+use crate::storage::{GeoDatabase, Ttl};
+use crate::Result;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// EXPIRE 命令
+///
+/// 语法: EXPIRE collection id seconds
+///
+/// 设置对象在 `seconds` 秒（可以带小数）之后过期，返回 1；对象不存在时返回 0。
+/// `seconds` 不大于 0 时立即删除对象。SET 覆盖对象时会清除过期时间
+pub struct ExpireCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl ExpireCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+impl Command for ExpireCommand {
+    fn name(&self) -> &'static str {
+        "EXPIRE"
+    }
+
+    fn execute(
+        &self,
+        args: &[RespValue],
+    ) -> impl std::future::Future<Output = Result<String>> + Send {
+        let database = Arc::clone(&self.database);
+        let parse_result = ArgumentParser::new(args, "EXPIRE").parse_expire_args();
+
+        async move {
+            let parsed_args = match parse_result {
+                Ok(args) => args,
+                Err(err_msg) => return Ok(RespResponse::error(&err_msg)),
+            };
+
+            // 超出 Duration 范围的存活时间按最大值处理
+            let ttl =
+                Duration::try_from_secs_f64(parsed_args.seconds.max(0.0)).unwrap_or(Duration::MAX);
+            match database
+                .expire(&parsed_args.collection_id, &parsed_args.item_id, ttl)
+                .await
+            {
+                Ok(updated) => Ok(RespResponse::integer(updated as i64)),
+                Err(e) => Ok(RespResponse::command_error(
+                    "failed to set expiration",
+                    e.as_ref(),
+                )),
+            }
+        }
+    }
+}
+
+/// TTL 命令
+///
+/// 语法: TTL collection id
+///
+/// 返回对象剩余的存活秒数（四舍五入）；没有过期时间时返回 -1，对象不存在时返回 -2
+pub struct TtlCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl TtlCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+impl Command for TtlCommand {
+    fn name(&self) -> &'static str {
+        "TTL"
+    }
+
+    fn execute(
+        &self,
+        args: &[RespValue],
+    ) -> impl std::future::Future<Output = Result<String>> + Send {
+        let database = Arc::clone(&self.database);
+        let parse_result = ArgumentParser::new(args, "TTL").parse_delete_args();
+
+        async move {
+            let parsed_args = match parse_result {
+                Ok(args) => args,
+                Err(err_msg) => return Ok(RespResponse::error(&err_msg)),
+            };
+
+            match database
+                .ttl(&parsed_args.collection_id, &parsed_args.item_id)
+                .await
+            {
+                Ok(Ttl::Missing) => Ok(RespResponse::integer(-2)),
+                Ok(Ttl::Persistent) => Ok(RespResponse::integer(-1)),
+                Ok(Ttl::Expires(remaining)) => Ok(RespResponse::integer(
+                    remaining.as_secs_f64().round().min(i64::MAX as f64) as i64,
+                )),
+                Err(e) => Ok(RespResponse::command_error(
+                    "failed to read TTL",
+                    e.as_ref(),
+                )),
+            }
+        }
+    }
+}
+
+/// PERSIST 命令
+///
+/// 语法: PERSIST collection id
+///
+/// 清除对象的过期时间，返回 1；对象不存在或没有过期时间时返回 0
+pub struct PersistCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl PersistCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+impl Command for PersistCommand {
+    fn name(&self) -> &'static str {
+        "PERSIST"
+    }
+
+    fn execute(
+        &self,
+        args: &[RespValue],
+    ) -> impl std::future::Future<Output = Result<String>> + Send {
+        let database = Arc::clone(&self.database);
+        let parse_result = ArgumentParser::new(args, "PERSIST").parse_delete_args();
+
+        async move {
+            let parsed_args = match parse_result {
+                Ok(args) => args,
+                Err(err_msg) => return Ok(RespResponse::error(&err_msg)),
+            };
+
+            match database
+                .persist(&parsed_args.collection_id, &parsed_args.item_id)
+                .await
+            {
+                Ok(updated) => Ok(RespResponse::integer(updated as i64)),
+                Err(e) => Ok(RespResponse::command_error(
+                    "failed to clear expiration",
+                    e.as_ref(),
+                )),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::args;
+    use crate::storage::MockClock;
+
+    const POINT: &str = r#"{"type":"Point","coordinates":[1.0,2.0]}"#;
+
+    #[tokio::test]
+    async fn test_expire_ttl_persist() {
+        let clock = Arc::new(MockClock::new(1_000_000_000_000));
+        let database = Arc::new(GeoDatabase::new().with_clock(clock.clone()));
+        database.set("fleet", "truck1", POINT).await.unwrap();
+        let expire = ExpireCommand::new(Arc::clone(&database));
+        let ttl = TtlCommand::new(Arc::clone(&database));
+        let persist = PersistCommand::new(Arc::clone(&database));
+
+        assert_eq!(
+            ttl.execute(&args(&["fleet", "truck1"])).await.unwrap(),
+            ":-1\r\n"
+        );
+        assert_eq!(
+            ttl.execute(&args(&["fleet", "nope"])).await.unwrap(),
+            ":-2\r\n"
+        );
+        assert_eq!(
+            expire
+                .execute(&args(&["fleet", "nope", "10"]))
+                .await
+                .unwrap(),
+            ":0\r\n"
+        );
+
+        assert_eq!(
+            expire
+                .execute(&args(&["fleet", "truck1", "10"]))
+                .await
+                .unwrap(),
+            ":1\r\n"
+        );
+        clock.advance(Duration::from_millis(2_400));
+        assert_eq!(
+            ttl.execute(&args(&["fleet", "truck1"])).await.unwrap(),
+            ":8\r\n"
+        );
+
+        assert_eq!(
+            persist.execute(&args(&["fleet", "truck1"])).await.unwrap(),
+            ":1\r\n"
+        );
+        assert_eq!(
+            persist.execute(&args(&["fleet", "truck1"])).await.unwrap(),
+            ":0\r\n"
+        );
+        assert_eq!(
+            ttl.execute(&args(&["fleet", "truck1"])).await.unwrap(),
+            ":-1\r\n"
+        );
+
+        // 到期后 GET 和 TTL 都看不到对象
+        expire
+            .execute(&args(&["fleet", "truck1", "0.5"]))
+            .await
+            .unwrap();
+        clock.advance(Duration::from_millis(500));
+        assert!(database.get("fleet", "truck1").await.unwrap().is_none());
+        assert_eq!(
+            ttl.execute(&args(&["fleet", "truck1"])).await.unwrap(),
+            ":-2\r\n"
+        );
+        assert_eq!(database.expired_objects(), 1);
+
+        // 非正数立即删除
+        database.set("fleet", "truck2", POINT).await.unwrap();
+        assert_eq!(
+            expire
+                .execute(&args(&["fleet", "truck2", "-1"]))
+                .await
+                .unwrap(),
+            ":1\r\n"
+        );
+        assert!(database.get("fleet", "truck2").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_expire_argument_errors() {
+        let database = Arc::new(GeoDatabase::new());
+        let expire = ExpireCommand::new(Arc::clone(&database));
+        let ttl = TtlCommand::new(database);
+
+        let result = expire.execute(&args(&["fleet", "truck1"])).await.unwrap();
+        assert!(result.contains("wrong number of arguments for 'EXPIRE'"));
+        let result = expire
+            .execute(&args(&["fleet", "truck1", "soon"]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("-ERR invalid seconds"));
+        let result = ttl.execute(&args(&["fleet"])).await.unwrap();
+        assert!(result.contains("wrong number of arguments for 'TTL'"));
+    }
+}
@@ -0,0 +1,141 @@
+use crate::commands::Command;
+use crate::protocol::{parser::RespValue, RespResponse};
+use crate::storage::GeoDatabase;
+use crate::Result;
+use std::sync::Arc;
+
+/// 编译进服务器的能力，与运行时配置无关
+///
+/// 客户端通过 FEATURES 或 `HELLO 2` 获取列表后按需启用功能，不必逐个试探命令。
+/// 新增命令或选项时在这里登记对应的名称
+const BUILTIN_FEATURES: &[&str] = &[
+    "geojson",    // SET/GET 使用 GeoJSON
+    "fields",     // NEARBY/INTERSECTS 的 FIELDS 投影
+    "order-id",   // INTERSECTS ORDER ID
+    "approx-knn", // NEARBY APPROX
+    "aggregate",  // AGG
+    "hull",       // HULL
+    "dbscan",     // CLUSTER
+    "snap",       // SNAP
+    "overlay",    // GEOMOP
+    "tags",       // SET TAG / WHERETAG
+    "memory",     // MEMORY USAGE/STATS/RESET-PEAK
+    "nofields",   // INTERSECTS NOFIELDS/NOGEOM
+    "precision",  // GET/INTERSECTS/NEARBY PRECISION
+    "parts",      // GeometryCollection 分部分索引，GET PART
+    "cursor",     // SCAN / INTERSECTS CURSOR 游标分页
+    "stats",      // STATS HISTORY
+    "demo",       // DEBUG LOADDEMO
+    "http",       // 同一端口上的 HTTP 和 WebSocket 接入
+    "ttl",        // EXPIRE/TTL/PERSIST
+    "fence",      // NEARBY/INTERSECTS FENCE 地理围栏事件
+    "resp3",      // HELLO 3，围栏事件以推送帧发送
+    "namespaces", // SELECT n 命名空间
+    "multi",      // MULTI/EXEC/DISCARD 事务
+    "import",     // IMPORT OBJECT 批量导入 FeatureCollection
+    "render",     // RENDER 按瓦片裁剪输出
+];
+
+/// 返回当前服务器启用的能力：内置能力加上运行时启用的持久化功能
+pub fn enabled_features(database: &GeoDatabase) -> Vec<&'static str> {
+    let mut features = BUILTIN_FEATURES.to_vec();
+    if cfg!(feature = "grpc") {
+        features.push("grpc");
+    }
+    if database.aof_enabled() {
+        features.push("aof");
+    }
+    if database.snapshot_path().is_some() {
+        features.push("snapshot");
+    }
+    if database.export_dir().is_some() {
+        features.push("export");
+    }
+    if database.idle_unloading_enabled() {
+        features.push("cold-storage");
+    }
+    if database.read_through_enabled() {
+        features.push("read-through");
+    }
+    if !database.strict_geojson_patterns().is_empty() {
+        features.push("rfc7946");
+    }
+    features
+}
+
+/// 将能力列表编码为 RESP 数组元素
+pub fn feature_values(database: &GeoDatabase) -> Vec<RespValue> {
+    enabled_features(database)
+        .into_iter()
+        .map(|name| RespValue::bulk(name.to_string()))
+        .collect()
+}
+
+/// FEATURES 命令：列出服务器启用的能力
+pub struct FeaturesCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl FeaturesCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+impl Command for FeaturesCommand {
+    fn name(&self) -> &'static str {
+        "FEATURES"
+    }
+
+    fn execute(
+        &self,
+        args: &[RespValue],
+    ) -> impl std::future::Future<Output = Result<String>> + Send {
+        let database = Arc::clone(&self.database);
+        let args_len = args.len();
+
+        async move {
+            if args_len != 0 {
+                return Ok(RespResponse::error(
+                    "ERR wrong number of arguments for 'FEATURES' command",
+                ));
+            }
+
+            Ok(RespResponse::array(Some(&feature_values(&database))))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtree::algorithms::aof::AofConfig;
+
+    #[tokio::test]
+    async fn test_features_command() {
+        let database = Arc::new(GeoDatabase::new());
+        let cmd = FeaturesCommand::new(Arc::clone(&database));
+
+        let result = cmd.execute(&[]).await.unwrap();
+        assert!(result.starts_with(&format!("*{}\r\n", BUILTIN_FEATURES.len())));
+        assert!(result.contains("$7\r\ngeojson\r\n"));
+        assert!(!result.contains("aof"));
+
+        let args = vec![RespValue::bulk("extra")];
+        assert!(cmd.execute(&args).await.unwrap().starts_with("-ERR"));
+    }
+
+    #[test]
+    fn test_runtime_features() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = GeoDatabase::with_aof(AofConfig::new(dir.path().join("test.aof"))).unwrap();
+
+        let features = enabled_features(&database);
+        assert!(features.contains(&"aof"));
+        assert!(!features.contains(&"snapshot"));
+        assert!(!features.contains(&"cold-storage"));
+
+        let database = database.with_snapshot_path(dir.path().join("dump.spdb"));
+        assert!(enabled_features(&database).contains(&"snapshot"));
+    }
+}
@@ -0,0 +1,146 @@
+use crate::rtree::GeoItem;
+use crate::storage::geometry_utils::geojson_properties;
+use serde_json::Value;
+
+/// 结果字段投影（`FIELDS f1,f2`）
+///
+/// - `id`：对象 ID
+/// - `geometry`：几何体（只有显式请求时才会输出）
+/// - 其他名称：Feature 的 properties 中的同名字段，不存在时为 null
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldSelection {
+    fields: Vec<String>,
+}
+
+impl FieldSelection {
+    /// 解析逗号分隔的字段列表
+    pub fn parse(spec: &str) -> std::result::Result<Self, String> {
+        let mut fields: Vec<String> = Vec::new();
+        for field in spec.split(',').map(str::trim) {
+            if field.is_empty() {
+                return Err(format!(
+                    "ERR invalid FIELDS value: empty field in '{}'",
+                    spec
+                ));
+            }
+            if !fields.iter().any(|f| f == field) {
+                fields.push(field.to_string());
+            }
+        }
+        Ok(Self { fields })
+    }
+
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    fn wants_geometry(&self) -> bool {
+        self.fields.iter().any(|f| f == "geometry")
+    }
+
+    /// 将对象投影为只包含所选字段的 JSON 对象字符串
+    pub fn project(&self, item: &GeoItem) -> String {
+        let (properties, geometry) = if self.wants_geometry() {
+            match serde_json::from_str::<Value>(&item.geojson) {
+                Ok(Value::Object(mut object)) => {
+                    let properties = match object.remove("properties") {
+                        Some(Value::Object(properties)) => Some(properties),
+                        _ => None,
+                    };
+                    // Feature 取 geometry 字段，裸几何体本身就是 geometry
+                    let geometry = match object.get("type").and_then(Value::as_str) {
+                        Some("Feature") => object.remove("geometry"),
+                        _ => Some(Value::Object(object)),
+                    };
+                    (properties, geometry)
+                }
+                _ => (None, None),
+            }
+        } else {
+            // 未请求几何体时只解析 properties
+            (geojson_properties(&item.geojson), None)
+        };
+
+        // 按请求的字段顺序输出（serde_json 的 Map 会按 key 排序）
+        let entries: Vec<String> = self
+            .fields
+            .iter()
+            .map(|field| {
+                let value = match field.as_str() {
+                    "id" => Value::String(item.id.clone()),
+                    "geometry" => geometry.clone().unwrap_or(Value::Null),
+                    name => properties
+                        .as_ref()
+                        .and_then(|p| p.get(name))
+                        .cloned()
+                        .unwrap_or(Value::Null),
+                };
+                format!("{}:{}", Value::String(field.clone()), value)
+            })
+            .collect();
+
+        format!("{{{}}}", entries.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::geometry_utils::geojson_to_geometry;
+    use serde_json::json;
+
+    fn item(id: &str, geojson: Value) -> GeoItem {
+        GeoItem {
+            id: id.to_string(),
+            geometry: geojson_to_geometry(&geojson.to_string()).unwrap(),
+            geojson: geojson.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_fields() {
+        let selection = FieldSelection::parse("speed, heading,speed").unwrap();
+        assert_eq!(selection.fields(), &["speed", "heading"]);
+
+        assert!(FieldSelection::parse("speed,,heading").is_err());
+        assert!(FieldSelection::parse("").is_err());
+    }
+
+    #[test]
+    fn test_project_feature() {
+        let truck = item(
+            "truck1",
+            json!({
+                "type": "Feature",
+                "properties": {"speed": 42.5, "heading": 90, "driver": "alice"},
+                "geometry": {"type": "Point", "coordinates": [116.4, 39.9]}
+            }),
+        );
+
+        let selection = FieldSelection::parse("id,speed,heading,missing").unwrap();
+        let projected: Value = serde_json::from_str(&selection.project(&truck)).unwrap();
+        assert_eq!(
+            projected,
+            json!({"id": "truck1", "speed": 42.5, "heading": 90, "missing": null})
+        );
+
+        let selection = FieldSelection::parse("geometry").unwrap();
+        let projected: Value = serde_json::from_str(&selection.project(&truck)).unwrap();
+        assert_eq!(
+            projected,
+            json!({"geometry": {"type": "Point", "coordinates": [116.4, 39.9]}})
+        );
+    }
+
+    #[test]
+    fn test_project_bare_geometry() {
+        let point = item("p1", json!({"type": "Point", "coordinates": [1.0, 2.0]}));
+
+        let selection = FieldSelection::parse("speed,geometry").unwrap();
+        let projected: Value = serde_json::from_str(&selection.project(&point)).unwrap();
+        assert_eq!(
+            projected,
+            json!({"speed": null, "geometry": {"type": "Point", "coordinates": [1.0, 2.0]}})
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use crate::commands::args::ArgumentParser;
+use crate::commands::Command;
+use crate::protocol::{parser::RespValue, RespResponse};
+use crate::storage::geometry_utils::geometry_to_geojson;
+use crate::storage::GeoDatabase;
+use crate::Result;
+use std::sync::Arc;
+
+/// GEOMOP 命令：计算两个已存储面状对象的交集/并集/差集/对称差，返回 GeoJSON 几何体
+pub struct GeomopCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl GeomopCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+impl Command for GeomopCommand {
+    fn name(&self) -> &'static str {
+        "GEOMOP"
+    }
+
+    fn execute(
+        &self,
+        args: &[RespValue],
+    ) -> impl std::future::Future<Output = Result<String>> + Send {
+        let database = Arc::clone(&self.database);
+
+        // 同步解析参数
+        let parse_result = ArgumentParser::new(args, "GEOMOP").parse_geomop_args();
+
+        async move {
+            // 检查参数解析结果
+            let parsed_args = match parse_result {
+                Ok(args) => args,
+                Err(err_msg) => {
+                    return Ok(RespResponse::error(&err_msg));
+                }
+            };
+
+            match database
+                .overlay(
+                    &parsed_args.collection_id,
+                    &parsed_args.first_id,
+                    &parsed_args.second_id,
+                    parsed_args.op,
+                )
+                .await
+            {
+                // 任一对象不存在时返回 nil
+                Ok(Some(geometry)) => Ok(RespResponse::bulk_string(Some(
+                    &geometry_to_geojson(&geometry).to_string(),
+                ))),
+                Ok(None) => Ok(RespResponse::bulk_string(None)),
+                Err(e) => Ok(RespResponse::command_error(
+                    "geometry operation failed",
+                    e.as_ref(),
+                )),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::args;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_geomop_command() {
+        let database = Arc::new(GeoDatabase::new());
+        let a = json!({
+            "type": "Feature",
+            "properties": {"name": "zone a"},
+            "geometry": {
+                "type": "Polygon",
+                "coordinates": [[[0.0, 0.0], [2.0, 0.0], [2.0, 2.0], [0.0, 2.0], [0.0, 0.0]]]
+            }
+        });
+        let b = json!({
+            "type": "Polygon",
+            "coordinates": [[[1.0, 1.0], [3.0, 1.0], [3.0, 3.0], [1.0, 3.0], [1.0, 1.0]]]
+        });
+        let p = json!({"type": "Point", "coordinates": [0.5, 0.5]});
+        database.set("zones", "a", &a.to_string()).await.unwrap();
+        database.set("zones", "b", &b.to_string()).await.unwrap();
+        database.set("zones", "p", &p.to_string()).await.unwrap();
+
+        let cmd = GeomopCommand::new(Arc::clone(&database));
+        let result = cmd
+            .execute(&args(&["INTERSECTION", "zones", "a", "b"]))
+            .await
+            .unwrap();
+        let body = result.split("\r\n").nth(1).unwrap();
+        let geometry: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(geometry["type"], "Polygon");
+        assert_eq!(geometry["coordinates"][0].as_array().unwrap().len(), 5);
+
+        // 对象不存在返回 nil
+        let result = cmd
+            .execute(&args(&["UNION", "zones", "a", "missing"]))
+            .await
+            .unwrap();
+        assert_eq!(result, "$-1\r\n");
+
+        // collection 不存在返回 NOINDEX
+        let result = cmd
+            .execute(&args(&["UNION", "parcels", "a", "b"]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("-NOINDEX "));
+
+        // 非面状对象返回 WRONGTYPE 错误
+        let result = cmd
+            .execute(&args(&["UNION", "zones", "a", "p"]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("-WRONGTYPE geometry operation failed"));
+    }
+}
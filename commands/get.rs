@@ -1,8 +1,11 @@
-use crate::commands::args::ArgumentParser;
+use crate::commands::args::{ArgumentParser, GetOutput};
 use crate::commands::Command;
 use crate::protocol::{parser::RespValue, RespResponse};
-use crate::storage::GeoDatabase;
+use crate::rtree::StoredValue;
+use crate::storage::geometry_utils::{geojson_part, geojson_to_geometry, round_coordinates};
+use crate::storage::{geohash, rfc7946, GeoDatabase};
 use crate::Result;
+use geo::{BoundingRect, Centroid, Geometry};
 use std::sync::Arc;
 
 pub struct GetCommand {
@@ -40,23 +43,101 @@ impl Command for GetCommand {
 
             // 只有数据库操作需要异步
             match database
-                .get(&parsed_args.collection_id, &parsed_args.item_id)
+                .get_value(&parsed_args.collection_id, &parsed_args.item_id)
                 .await
             {
-                Ok(Some(item)) => {
-                    // 返回 GeoJSON 字符串
-                    Ok(RespResponse::bulk_string(Some(&item.geojson)))
+                // STRING 对象原样返回，没有几何体可以取部分或者计算坐标
+                Ok(Some(StoredValue::Text(text))) => {
+                    if parsed_args.part.is_some() || parsed_args.output != GetOutput::Object {
+                        return Ok(RespResponse::error(
+                            "ERR object is a string, it has no geometry",
+                        ));
+                    }
+                    Ok(RespResponse::bulk_string(Some(&text)))
+                }
+                Ok(Some(StoredValue::Geometry(item))) => {
+                    // 指定 PART 时只返回 GeometryCollection 中的一个部分
+                    let geojson = match parsed_args.part {
+                        Some(index) => match geojson_part(&item.geojson, index) {
+                            Ok(part) => part,
+                            Err(count) => {
+                                return Ok(RespResponse::error(&format!(
+                                    "ERR part {} out of range, object has {} part(s)",
+                                    index, count
+                                )))
+                            }
+                        },
+                        None => item.geojson,
+                    };
+                    let precision = parsed_args.precision.or(database.output_precision());
+
+                    if parsed_args.output != GetOutput::Object {
+                        let geometry = match parsed_args.part {
+                            Some(_) => geojson_to_geometry(&geojson)?,
+                            None => item.geometry,
+                        };
+                        return Ok(accessor_reply(&geometry, parsed_args.output, precision));
+                    }
+
+                    // 返回 GeoJSON 字符串，指定精度时四舍五入坐标
+                    let geojson = match precision {
+                        Some(decimals) => round_coordinates(&geojson, decimals),
+                        None => geojson,
+                    };
+                    // 严格模式的 collection 按 RFC 7946 规范化输出
+                    let geojson = if database.strict_geojson(&parsed_args.collection_id) {
+                        rfc7946::normalize(&geojson)
+                    } else {
+                        geojson
+                    };
+                    Ok(RespResponse::bulk_string(Some(&geojson)))
                 }
                 Ok(None) => Ok(RespResponse::bulk_string(None)),
-                Err(e) => Ok(RespResponse::error(&format!("ERR failed to get: {}", e))),
+                Err(e) => Ok(RespResponse::command_error("failed to get", e.as_ref())),
             }
         }
     }
 }
 
+/// POINT、BOUNDS 和 HASH 的回复，坐标与 Tile38 相同，纬度在前
+fn accessor_reply(geometry: &Geometry, output: GetOutput, precision: Option<u32>) -> String {
+    match accessor_value(geometry, output, precision) {
+        Some(value) => RespResponse::value_to_string(&value),
+        None => RespResponse::error("ERR object has no coordinates"),
+    }
+}
+
+/// POINT、BOUNDS 和 HASH 的值，INTERSECTS 和 NEARBY 的 POINTS、BOUNDS、HASHES 也使用它；
+/// 几何体没有坐标时返回 None
+pub fn accessor_value(
+    geometry: &Geometry,
+    output: GetOutput,
+    precision: Option<u32>,
+) -> Option<RespValue> {
+    let number = |value: f64| match precision {
+        Some(decimals) => RespValue::bulk(format!("{:.*}", decimals as usize, value)),
+        None => RespValue::bulk(value.to_string()),
+    };
+    let (center, rect) = (geometry.centroid()?, geometry.bounding_rect()?);
+    Some(match output {
+        GetOutput::Point => RespValue::Array(Some(vec![number(center.y()), number(center.x())])),
+        GetOutput::Bounds => {
+            let corner =
+                |lat: f64, lon: f64| RespValue::Array(Some(vec![number(lat), number(lon)]));
+            RespValue::Array(Some(vec![
+                corner(rect.min().y, rect.min().x),
+                corner(rect.max().y, rect.max().x),
+            ]))
+        }
+        GetOutput::Hash(length) => RespValue::bulk(geohash::encode(center.x(), center.y(), length)),
+        GetOutput::Object => unreachable!("OBJECT returns the stored GeoJSON"),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::commands::args;
     use serde_json::json;
 
     #[tokio::test]
@@ -75,10 +156,7 @@ mod tests {
 
         let cmd = GetCommand::new(Arc::clone(&database));
 
-        let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("truck1".to_string())),
-        ];
+        let args = vec![RespValue::bulk("fleet"), RespValue::bulk("truck1")];
 
         let result = cmd.execute(&args).await.unwrap();
         assert!(result.contains("Point"));
@@ -90,10 +168,7 @@ mod tests {
         let database = Arc::new(GeoDatabase::new());
         let cmd = GetCommand::new(database);
 
-        let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("nonexistent".to_string())),
-        ];
+        let args = vec![RespValue::bulk("fleet"), RespValue::bulk("nonexistent")];
 
         let result = cmd.execute(&args).await.unwrap();
         assert_eq!(result, RespResponse::bulk_string(None));
@@ -105,9 +180,198 @@ mod tests {
         let cmd = GetCommand::new(database);
 
         // 参数太少
-        let args = vec![RespValue::BulkString(Some("fleet".to_string()))];
+        let args = vec![RespValue::bulk("fleet")];
 
         let result = cmd.execute(&args).await.unwrap();
         assert!(result.contains("wrong number of arguments"));
     }
+
+    #[tokio::test]
+    async fn test_get_command_precision() {
+        let point_json = json!({"type": "Point", "coordinates": [-122.4194155, 37.7749295]});
+        let database = GeoDatabase::new().with_output_precision(4);
+        database
+            .set("fleet", "truck1", &point_json.to_string())
+            .await
+            .unwrap();
+        let cmd = GetCommand::new(Arc::new(database));
+
+        // 全局设置
+        let result = cmd.execute(&args(&["fleet", "truck1"])).await.unwrap();
+        assert!(result.contains("[-122.4194,37.7749]"));
+
+        // 请求中的 PRECISION 优先
+        let result = cmd
+            .execute(&args(&["fleet", "truck1", "PRECISION", "2"]))
+            .await
+            .unwrap();
+        assert!(result.contains("[-122.42,37.77]"));
+
+        let result = cmd
+            .execute(&args(&["fleet", "truck1", "PRECISION", "99"]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("-ERR invalid PRECISION value"));
+        let result = cmd
+            .execute(&args(&["fleet", "truck1", "ROUND", "2"]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("-ERR unknown option 'ROUND'"));
+    }
+
+    #[tokio::test]
+    async fn test_get_command_strict_geojson() {
+        // 开启严格模式之前写入的对象，输出时规范化
+        let clockwise = json!({
+            "type": "GeometryCollection",
+            "bbox": [10, 10, 20, 20],
+            "geometries": [
+                {"type": "Polygon", "coordinates": [[[0, 0], [0, 1], [1, 1], [1, 0], [0, 0]]]},
+                {"type": "GeometryCollection", "geometries": [
+                    {"type": "Point", "coordinates": [0.5, 0.5]}
+                ]}
+            ]
+        });
+        let database = GeoDatabase::new();
+        database
+            .set("export", "p1", &clockwise.to_string())
+            .await
+            .unwrap();
+        let database = Arc::new(database.with_strict_geojson(vec!["export".to_string()]));
+        let cmd = GetCommand::new(database);
+        let result = cmd
+            .execute(&[RespValue::bulk("export"), RespValue::bulk("p1")])
+            .await
+            .unwrap();
+        let geojson: serde_json::Value =
+            serde_json::from_str(result.lines().nth(1).unwrap()).unwrap();
+        assert_eq!(
+            geojson,
+            json!({
+                "type": "GeometryCollection",
+                "geometries": [
+                    {"type": "Polygon", "coordinates": [[[0, 0], [1, 0], [1, 1], [0, 1], [0, 0]]]},
+                    {"type": "Point", "coordinates": [0.5, 0.5]}
+                ]
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_command_part() {
+        let database = Arc::new(GeoDatabase::new());
+        let collection = json!({
+            "type": "Feature",
+            "properties": {"name": "campus"},
+            "geometry": {
+                "type": "GeometryCollection",
+                "geometries": [
+                    {"type": "Point", "coordinates": [1.0, 1.0]},
+                    {"type": "LineString", "coordinates": [[50.0, 50.0], [51.0, 51.0]]}
+                ]
+            }
+        });
+        database
+            .set("sites", "s1", &collection.to_string())
+            .await
+            .unwrap();
+        database
+            .set(
+                "sites",
+                "s2",
+                &json!({"type": "Point", "coordinates": [2.0, 2.0]}).to_string(),
+            )
+            .await
+            .unwrap();
+        let cmd = GetCommand::new(database);
+
+        let result = cmd
+            .execute(&args(&["sites", "s1", "PART", "1"]))
+            .await
+            .unwrap();
+        assert!(result.contains(r#""type":"LineString""#));
+        assert!(!result.contains("Point"));
+
+        // 普通几何体只有一个部分
+        let result = cmd
+            .execute(&args(&["sites", "s2", "part", "0"]))
+            .await
+            .unwrap();
+        assert!(result.contains(r#""type":"Point""#));
+
+        let result = cmd
+            .execute(&args(&["sites", "s1", "PART", "2"]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("-ERR part 2 out of range, object has 2 part(s)"));
+        let result = cmd
+            .execute(&args(&["sites", "s1", "PART", "0", "PART", "1"]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("-ERR duplicate PART keyword"));
+    }
+
+    #[tokio::test]
+    async fn test_get_command_accessors() {
+        let database = Arc::new(GeoDatabase::new());
+        let square = json!({
+            "type": "Polygon",
+            "coordinates": [[[10.0, 56.0], [11.0, 56.0], [11.0, 58.0], [10.0, 58.0], [10.0, 56.0]]]
+        });
+        database
+            .set("zones", "z1", &square.to_string())
+            .await
+            .unwrap();
+        database
+            .set(
+                "fleet",
+                "truck1",
+                &json!({"type": "Point", "coordinates": [10.40744, 57.64911]}).to_string(),
+            )
+            .await
+            .unwrap();
+        let cmd = GetCommand::new(database);
+
+        let result = cmd.execute(&args(&["zones", "z1", "POINT"])).await.unwrap();
+        assert_eq!(result, "*2\r\n$2\r\n57\r\n$4\r\n10.5\r\n");
+        let result = cmd
+            .execute(&args(&["zones", "z1", "bounds"]))
+            .await
+            .unwrap();
+        assert_eq!(
+            result,
+            "*2\r\n*2\r\n$2\r\n56\r\n$2\r\n10\r\n*2\r\n$2\r\n58\r\n$2\r\n11\r\n"
+        );
+        let result = cmd
+            .execute(&args(&["fleet", "truck1", "HASH", "11"]))
+            .await
+            .unwrap();
+        assert_eq!(result, RespResponse::bulk_string(Some("u4pruydqqvj")));
+        let result = cmd
+            .execute(&args(&["fleet", "truck1", "POINT", "PRECISION", "2"]))
+            .await
+            .unwrap();
+        assert_eq!(result, "*2\r\n$5\r\n57.65\r\n$5\r\n10.41\r\n");
+        let result = cmd
+            .execute(&args(&["fleet", "truck1", "OBJECT"]))
+            .await
+            .unwrap();
+        assert!(result.contains(r#""type":"Point""#));
+
+        let result = cmd
+            .execute(&args(&["fleet", "truck1", "HASH", "13"]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("-ERR invalid HASH precision"));
+        let result = cmd
+            .execute(&args(&["fleet", "truck1", "POINT", "BOUNDS"]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("-ERR only one of OBJECT, POINT, BOUNDS or HASH"));
+        let result = cmd
+            .execute(&args(&["fleet", "truck9", "POINT"]))
+            .await
+            .unwrap();
+        assert_eq!(result, RespResponse::bulk_string(None));
+    }
 }
@@ -0,0 +1,98 @@
+use crate::commands::args::ArgumentParser;
+use crate::commands::Command;
+use crate::protocol::{parser::RespValue, RespResponse};
+use crate::storage::geometry_utils::geometry_to_geojson;
+use crate::storage::GeoDatabase;
+use crate::Result;
+use std::sync::Arc;
+
+/// HULL 命令：返回 collection 或查询结果的凸包/凹包，用于快速绘制覆盖范围
+pub struct HullCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl HullCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+impl Command for HullCommand {
+    fn name(&self) -> &'static str {
+        "HULL"
+    }
+
+    fn execute(
+        &self,
+        args: &[RespValue],
+    ) -> impl std::future::Future<Output = Result<String>> + Send {
+        let database = Arc::clone(&self.database);
+
+        // 同步解析参数
+        let parse_result = ArgumentParser::new(args, "HULL").parse_hull_args();
+
+        async move {
+            // 检查参数解析结果
+            let parsed_args = match parse_result {
+                Ok(args) => args,
+                Err(err_msg) => {
+                    return Ok(RespResponse::error(&err_msg));
+                }
+            };
+
+            match database
+                .hull(
+                    &parsed_args.collection_id,
+                    parsed_args.area.as_ref(),
+                    parsed_args.kind,
+                )
+                .await
+            {
+                // 返回 GeoJSON 几何体，没有对象时返回 nil
+                Ok(Some(hull)) => Ok(RespResponse::bulk_string(Some(
+                    &geometry_to_geojson(&hull).to_string(),
+                ))),
+                Ok(None) => Ok(RespResponse::bulk_string(None)),
+                Err(e) => Ok(RespResponse::command_error("hull query failed", e.as_ref())),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_hull_command() {
+        let database = Arc::new(GeoDatabase::new());
+        for (id, lon, lat) in [
+            ("a", 0.0, 0.0),
+            ("b", 2.0, 0.0),
+            ("c", 2.0, 2.0),
+            ("d", 1.0, 0.5),
+        ] {
+            let point = json!({"type": "Point", "coordinates": [lon, lat]});
+            database.set("fleet", id, &point.to_string()).await.unwrap();
+        }
+
+        let cmd = HullCommand::new(Arc::clone(&database));
+        let args = vec![RespValue::bulk("fleet")];
+        let result = cmd.execute(&args).await.unwrap();
+
+        let body = result.split("\r\n").nth(1).unwrap();
+        let hull: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(hull["type"], "Polygon");
+        // 三角形外环（闭合）有 4 个坐标，内部点 d 不在外环上
+        assert_eq!(hull["coordinates"][0].as_array().unwrap().len(), 4);
+
+        // 不存在的 collection 返回 NOINDEX
+        let args = vec![RespValue::bulk("missing")];
+        assert!(cmd
+            .execute(&args)
+            .await
+            .unwrap()
+            .starts_with("-NOINDEX hull query failed"));
+    }
+}
@@ -0,0 +1,409 @@
+use crate::commands::Command;
+use crate::protocol::{parser::RespValue, RespResponse};
+use crate::server::alloc;
+use crate::storage::GeoDatabase;
+use crate::Result;
+use std::sync::Arc;
+
+/// INFO 命令：以 Redis 风格的 `key:value` 文本返回服务器状态
+///
+/// 语法: INFO [section]
+///
+/// 支持的 section: persistence, memory, keyspace（不指定时返回全部）
+pub struct InfoCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl InfoCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+
+    async fn persistence_section(database: &GeoDatabase) -> String {
+        let info = database.persistence_info().await;
+
+        let mut section = String::from("# Persistence\r\n");
+        section.push_str(&format!("loading:{}\r\n", info.loading as u8));
+        section.push_str(&format!("aof_enabled:{}\r\n", info.aof_enabled as u8));
+        section.push_str(&format!("ephemeral:{}\r\n", info.ephemeral as u8));
+        if info.aof_enabled {
+            section.push_str(&format!("aof_sync_policy:{}\r\n", info.sync_policy));
+            section.push_str(&format!(
+                "aof_effective_sync_policy:{}\r\n",
+                info.effective_sync_policy
+            ));
+            section.push_str(&format!(
+                "aof_write_stalled:{}\r\n",
+                info.write_stalled as u8
+            ));
+            if let Some(latency) = info.last_fsync_latency_ms {
+                section.push_str(&format!("aof_last_fsync_latency_ms:{:.3}\r\n", latency));
+            }
+            section.push_str(&format!("aof_bytes_written:{}\r\n", info.bytes_written));
+            section.push_str(&format!("aof_buffer_length:{}\r\n", info.buffered_bytes));
+            if let Some(time) = info.last_fsync_time {
+                section.push_str(&format!("aof_last_fsync_time:{}\r\n", time));
+            }
+            section.push_str(&format!("aof_current_size:{}\r\n", info.aof_current_size));
+            section.push_str(&format!("aof_base_size:{}\r\n", info.aof_base_size));
+            section.push_str(&format!(
+                "aof_rewrite_in_progress:{}\r\n",
+                info.aof_rewrite_in_progress as u8
+            ));
+            if let Some(time) = info.aof_last_rewrite_time {
+                section.push_str(&format!("aof_last_rewrite_time:{}\r\n", time));
+            }
+            section.push_str(&format!(
+                "aof_last_bgrewrite_status:{}\r\n",
+                if info.aof_last_rewrite_failed {
+                    "err"
+                } else {
+                    "ok"
+                }
+            ));
+        }
+        section.push_str(&format!(
+            "snapshot_enabled:{}\r\n",
+            info.snapshot_enabled as u8
+        ));
+        if info.snapshot_enabled {
+            section.push_str(&format!(
+                "snapshot_in_progress:{}\r\n",
+                info.snapshot_in_progress as u8
+            ));
+            if let Some(time) = info.snapshot_last_save_time {
+                section.push_str(&format!("snapshot_last_save_time:{}\r\n", time));
+            }
+            section.push_str(&format!(
+                "snapshot_last_status:{}\r\n",
+                if info.snapshot_last_failed {
+                    "err"
+                } else {
+                    "ok"
+                }
+            ));
+        }
+        if let Some(disk) = database.disk_status() {
+            section.push_str(&format!("disk_free_bytes:{}\r\n", disk.free_bytes));
+            section.push_str(&format!("disk_min_free_bytes:{}\r\n", disk.min_free_bytes));
+            section.push_str(&format!("disk_low:{}\r\n", disk.low as u8));
+        }
+        section
+    }
+
+    /// 分配器统计只在服务器安装了 TrackingAllocator 时可用；数据集的估算较慢，见 MEMORY STATS
+    pub(crate) fn memory_section() -> String {
+        let mut section = String::from("# Memory\r\n");
+        match alloc::allocator_stats() {
+            Some(stats) => {
+                section.push_str(&format!("allocator:{}\r\n", stats.allocator));
+                section.push_str(&format!("used_memory:{}\r\n", stats.allocated_bytes));
+                section.push_str(&format!("used_memory_peak:{}\r\n", stats.peak_bytes));
+                section.push_str(&format!("total_allocations:{}\r\n", stats.allocations));
+            }
+            None => section.push_str("allocator:untracked\r\n"),
+        }
+        if let Some(rss) = alloc::resident_memory_bytes() {
+            section.push_str(&format!("used_memory_rss:{}\r\n", rss));
+        }
+        section
+    }
+
+    async fn keyspace_section(database: &GeoDatabase) -> Result<String> {
+        let stats = database.stats().await?;
+
+        let mut section = String::from("# Keyspace\r\n");
+        section.push_str(&format!("collections:{}\r\n", stats.collections_count));
+        section.push_str(&format!("objects:{}\r\n", stats.total_items));
+        section.push_str(&format!(
+            "unloaded_collections:{}\r\n",
+            stats.unloaded_collections
+        ));
+        section.push_str(&format!("expiring_objects:{}\r\n", stats.expiring_items));
+        section.push_str(&format!(
+            "expired_objects:{}\r\n",
+            database.expired_objects()
+        ));
+        section.push_str(&format!(
+            "active_expire:{}\r\n",
+            database.active_expire_enabled() as u8
+        ));
+        Ok(section)
+    }
+}
+
+impl Command for InfoCommand {
+    fn name(&self) -> &'static str {
+        "INFO"
+    }
+
+    fn execute(
+        &self,
+        args: &[RespValue],
+    ) -> impl std::future::Future<Output = Result<String>> + Send {
+        let database = Arc::clone(&self.database);
+
+        // 同步解析参数
+        let section = match args {
+            [] => Ok(None),
+            [name @ RespValue::BulkString(Some(_))] => name
+                .as_str()
+                .map(|name| Some(name.to_lowercase()))
+                .ok_or_else(|| "ERR invalid section: expected UTF-8 string".to_string()),
+            [_] => Err("ERR invalid section: expected string".to_string()),
+            _ => Err("ERR wrong number of arguments for 'INFO' command".to_string()),
+        };
+
+        async move {
+            let section = match section {
+                Ok(section) => section,
+                Err(err_msg) => return Ok(RespResponse::error(&err_msg)),
+            };
+
+            let mut sections = Vec::new();
+            match section.as_deref() {
+                None | Some("all") => {
+                    sections.push(Self::persistence_section(&database).await);
+                    sections.push(Self::memory_section());
+                    sections.push(Self::keyspace_section(&database).await?);
+                }
+                Some("persistence") => {
+                    sections.push(Self::persistence_section(&database).await);
+                }
+                Some("memory") => {
+                    sections.push(Self::memory_section());
+                }
+                Some("keyspace") => {
+                    sections.push(Self::keyspace_section(&database).await?);
+                }
+                Some(other) => {
+                    return Ok(RespResponse::error(&format!(
+                        "ERR unknown INFO section '{}'",
+                        other
+                    )));
+                }
+            }
+
+            Ok(RespResponse::bulk_string(Some(&sections.join("\r\n"))))
+        }
+    }
+}
+
+/// SERVER 命令：一次返回服务器的概况，便于客户端做健康检查
+///
+/// 语法: SERVER
+///
+/// 返回运行时长、连接数、collection 和对象数、数据内存估算，以及开启 AOF 时的文件大小。
+/// 内存估算需要遍历对象，耗时与数据量成正比
+pub struct ServerCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl ServerCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+impl Command for ServerCommand {
+    fn name(&self) -> &'static str {
+        "SERVER"
+    }
+
+    fn execute(
+        &self,
+        args: &[RespValue],
+    ) -> impl std::future::Future<Output = Result<String>> + Send {
+        let database = Arc::clone(&self.database);
+        let arity_ok = args.is_empty();
+
+        async move {
+            if !arity_ok {
+                return Ok(RespResponse::error(
+                    "ERR wrong number of arguments for 'SERVER' command",
+                ));
+            }
+
+            let stats = database.stats().await?;
+            let dataset_memory: usize = database
+                .memory_usage()
+                .await
+                .iter()
+                .map(|(_, usage)| usage.total())
+                .sum();
+            let persistence = database.persistence_info().await;
+
+            let mut section = String::from("# Server\r\n");
+            section.push_str(&format!(
+                "uptime_in_seconds:{}\r\n",
+                database.uptime().as_secs()
+            ));
+            section.push_str(&format!(
+                "connected_clients:{}\r\n",
+                database.connected_clients()
+            ));
+            section.push_str(&format!("collections:{}\r\n", stats.collections_count));
+            section.push_str(&format!("objects:{}\r\n", stats.total_items));
+            section.push_str(&format!("dataset_memory:{}\r\n", dataset_memory));
+            if let Some(allocator) = alloc::allocator_stats() {
+                section.push_str(&format!("used_memory:{}\r\n", allocator.allocated_bytes));
+            }
+            section.push_str(&format!(
+                "aof_enabled:{}\r\n",
+                persistence.aof_enabled as u8
+            ));
+            if persistence.aof_enabled {
+                section.push_str(&format!(
+                    "aof_current_size:{}\r\n",
+                    persistence.aof_current_size
+                ));
+            }
+            Ok(RespResponse::bulk_string(Some(&section)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_info_command_all_sections() {
+        let database = Arc::new(GeoDatabase::new());
+        database
+            .set(
+                "fleet",
+                "truck1",
+                &json!({"type": "Point", "coordinates": [1.0, 2.0]}).to_string(),
+            )
+            .await
+            .unwrap();
+
+        let cmd = InfoCommand::new(Arc::clone(&database));
+        let result = cmd.execute(&[]).await.unwrap();
+
+        assert!(result.contains("# Persistence"));
+        assert!(result.contains("aof_enabled:0"));
+        assert!(result.contains("ephemeral:1"));
+        assert!(result.contains("snapshot_enabled:0"));
+        assert!(!result.contains("disk_low"));
+        assert!(result.contains("# Memory"));
+        assert!(result.contains("allocator:"));
+        assert!(result.contains("# Keyspace"));
+        assert!(result.contains("collections:1"));
+        assert!(result.contains("objects:1"));
+    }
+
+    #[tokio::test]
+    async fn test_info_command_persistence_with_aof() {
+        use crate::rtree::algorithms::aof::{AofConfig, AofSyncPolicy};
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let config = AofConfig::new(temp_dir.path().join("test.aof"))
+            .set_sync_policy(AofSyncPolicy::Always)
+            .with_stall_detection(std::time::Duration::from_secs(60), true);
+        let database = Arc::new(GeoDatabase::with_aof(config).unwrap());
+
+        let cmd = InfoCommand::new(Arc::clone(&database));
+        let args = vec![RespValue::bulk("persistence")];
+        let result = cmd.execute(&args).await.unwrap();
+
+        assert!(result.contains("aof_enabled:1"));
+        assert!(result.contains("ephemeral:0"));
+        assert!(result.contains("aof_sync_policy:always"));
+        assert!(result.contains("aof_effective_sync_policy:always"));
+        assert!(result.contains("aof_write_stalled:0"));
+        assert!(result.contains("aof_buffer_length:0"));
+        assert!(!result.contains("aof_last_fsync_time"));
+        assert!(!result.contains("# Keyspace"));
+
+        // always 策略下每次写入都会 fsync，缓冲区保持为空
+        database
+            .set(
+                "fleet",
+                "truck1",
+                &json!({"type": "Point", "coordinates": [1.0, 2.0]}).to_string(),
+            )
+            .await
+            .unwrap();
+        let result = cmd.execute(&args).await.unwrap();
+        assert!(result.contains("aof_buffer_length:0"));
+        assert!(result.contains("aof_last_fsync_time:"));
+        assert!(result.contains("aof_rewrite_in_progress:0"));
+        assert!(result.contains("aof_last_bgrewrite_status:ok"));
+        assert!(!result.contains("aof_current_size:0\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_info_command_persistence_with_snapshot() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let database =
+            Arc::new(GeoDatabase::new().with_snapshot_path(temp_dir.path().join("dump.spdb")));
+
+        let cmd = InfoCommand::new(Arc::clone(&database));
+        let args = vec![RespValue::bulk("persistence")];
+        let result = cmd.execute(&args).await.unwrap();
+        assert!(result.contains("ephemeral:0"));
+        assert!(result.contains("snapshot_enabled:1"));
+        assert!(result.contains("snapshot_in_progress:0"));
+        assert!(!result.contains("snapshot_last_save_time"));
+
+        database.save_snapshot().await.unwrap();
+        let result = cmd.execute(&args).await.unwrap();
+        assert!(result.contains("snapshot_last_save_time:"));
+        assert!(result.contains("snapshot_last_status:ok"));
+    }
+
+    #[tokio::test]
+    async fn test_info_command_memory_section() {
+        let cmd = InfoCommand::new(Arc::new(GeoDatabase::new()));
+        let args = vec![RespValue::bulk("memory")];
+        let result = cmd.execute(&args).await.unwrap();
+
+        assert!(result.contains("# Memory"));
+        assert!(!result.contains("# Persistence"));
+        #[cfg(target_os = "linux")]
+        assert!(result.contains("used_memory_rss:"));
+    }
+
+    #[tokio::test]
+    async fn test_info_command_unknown_section() {
+        let database = Arc::new(GeoDatabase::new());
+        let cmd = InfoCommand::new(database);
+
+        let args = vec![RespValue::bulk("nope")];
+        let result = cmd.execute(&args).await.unwrap();
+        assert!(result.starts_with("-ERR unknown INFO section"));
+    }
+
+    #[tokio::test]
+    async fn test_server_command() {
+        let database = Arc::new(GeoDatabase::new());
+        database
+            .set(
+                "fleet",
+                "truck1",
+                &json!({"type": "Point", "coordinates": [1.0, 2.0]}).to_string(),
+            )
+            .await
+            .unwrap();
+        database.client_connected();
+        let cmd = ServerCommand::new(Arc::clone(&database));
+
+        let result = cmd.execute(&[]).await.unwrap();
+        assert!(result.contains("# Server\r\nuptime_in_seconds:0\r\n"));
+        assert!(result.contains("connected_clients:1\r\n"));
+        assert!(result.contains("collections:1\r\nobjects:1\r\n"));
+        assert!(!result.contains("dataset_memory:0\r\n"));
+        assert!(result.contains("aof_enabled:0\r\n"));
+        assert!(!result.contains("aof_current_size"));
+
+        database.client_disconnected();
+        let result = cmd.execute(&[]).await.unwrap();
+        assert!(result.contains("connected_clients:0\r\n"));
+        let result = cmd.execute(&[RespValue::bulk("x")]).await.unwrap();
+        assert!(result.starts_with("-ERR wrong number of arguments"));
+    }
+}
@@ -1,7 +1,12 @@
+use crate::commands::args::{IntersectsArgs as ParsedIntersectsArgs, QueryOutput};
+use crate::commands::get::accessor_value;
 use crate::commands::{ArgumentParser, Command};
 use crate::protocol::parser::RespValue;
-use crate::protocol::RespResponse;
-use crate::storage::GeoDatabase;
+use crate::protocol::{ReplySink, RespResponse};
+use crate::rtree::GeoItem;
+use crate::storage::geo_utils::geometry_to_bbox;
+use crate::storage::geometry_utils::round_coordinates;
+use crate::storage::{rfc7946, GeoDatabase};
 use crate::Result;
 use serde_json;
 use std::sync::Arc;
@@ -31,46 +36,209 @@ impl Command for IntersectsCommand {
         let parse_result = ArgumentParser::new(args, "INTERSECTS").parse_intersects_args();
 
         async move {
-            // 检查参数解析结果
-            let parsed_args = match parse_result {
-                Ok(args) => args,
-                Err(err_msg) => {
-                    return Ok(RespResponse::error(&err_msg));
-                }
-            };
-
-            // 执行空间查询
-            match database
-                .intersects(
+            let mut sink = ReplySink::buffer();
+            write_reply(database, parse_result, &mut sink).await?;
+            Ok(sink.into_string())
+        }
+    }
+
+    /// 结果逐个编码写入 sink，不在内存中拼出整个回复
+    fn execute_into(
+        &self,
+        args: &[RespValue],
+        sink: &mut ReplySink,
+    ) -> impl std::future::Future<Output = Result<()>> + Send {
+        let database = Arc::clone(&self.database);
+        let parse_result = ArgumentParser::new(args, "INTERSECTS").parse_intersects_args();
+
+        async move {
+            write_reply(database, parse_result, sink).await?;
+            sink.flush().await
+        }
+    }
+}
+
+/// 每批从 collection 复制的结果数，复制期间持有锁，写入 sink 之前释放
+const REPLY_BATCH: usize = 256;
+
+/// 分块写出时按批加全局锁；收集完整回复时调用方已经持有全局锁（或 EXEC 的独占锁），不能再加
+async fn shared_lock(
+    database: &GeoDatabase,
+    streaming: bool,
+) -> Option<tokio::sync::RwLockReadGuard<'_, ()>> {
+    match streaming {
+        true => Some(database.shared().await),
+        false => None,
+    }
+}
+
+async fn write_reply(
+    database: Arc<GeoDatabase>,
+    parse_result: std::result::Result<ParsedIntersectsArgs, String>,
+    sink: &mut ReplySink,
+) -> Result<()> {
+    // 检查参数解析结果
+    let parsed_args = match parse_result {
+        Ok(args) => args,
+        Err(err_msg) => {
+            return sink.write(&RespResponse::error(&err_msg)).await;
+        }
+    };
+    // 围栏由连接注册（见 server::fence），到达这里说明连接不能推送事件
+    if parsed_args.fence {
+        return sink
+            .write(&RespResponse::error(
+                "ERR FENCE is only supported on RESP and WebSocket connections",
+            ))
+            .await;
+    }
+
+    // 分块写出时调用方没有持有全局锁（见 CommandRegistry::execute_into），查询和每批复制
+    // 各自加锁，等待连接取走数据时不持有任何锁，读得慢的客户端不会挡住 EXEC
+    let streaming = sink.is_channel();
+
+    // 先只取得匹配对象的 ID，带 WHERETAG 时先用标签索引缩小候选集；
+    // 带 CURSOR 时按 ID 分页，同时返回下一页的游标
+    let query_result = {
+        let _shared = shared_lock(&database, streaming).await;
+        if let Some(cursor) = &parsed_args.cursor {
+            database
+                .intersects_page_ids(
+                    &parsed_args.collection_id,
+                    &parsed_args.geometry,
+                    parsed_args.within,
+                    &parsed_args.tags,
+                    cursor,
+                    parsed_args.limit,
+                )
+                .await
+                .map(|page| (page.items, Some(page.next)))
+        } else {
+            database
+                .intersects_ids(
                     &parsed_args.collection_id,
                     &parsed_args.geometry,
                     parsed_args.limit,
                     parsed_args.within,
+                    parsed_args.order,
+                    &parsed_args.tags,
                 )
                 .await
-            {
-                Ok(results) => {
-                    if results.is_empty() {
-                        Ok(RespResponse::array(None))
-                    } else {
-                        // 优化：预分配容量，避免Vec动态扩容
-                        let mut resp_values = Vec::with_capacity(results.len());
-
-                        for item in results {
-                            // 优化：直接使用缓存的 GeoJSON 字符串，零序列化开销
-                            resp_values.push(RespValue::BulkString(Some(item.geojson)));
-                        }
-
-                        Ok(RespResponse::array(Some(&resp_values)))
-                    }
-                }
-                Err(e) => Ok(RespResponse::error(&format!(
-                    "ERR intersects query failed: {}",
-                    e
-                ))),
-            }
+                .map(|ids| (ids, None))
+        }
+    };
+    let (ids, next_cursor) = match query_result {
+        Ok(page) => page,
+        Err(e) => {
+            return sink
+                .write(&RespResponse::command_error(
+                    "intersects query failed",
+                    e.as_ref(),
+                ))
+                .await;
+        }
+    };
+    // COUNT：只返回匹配的数量
+    if parsed_args.output == QueryOutput::Count {
+        return sink.write(&RespResponse::integer(ids.len() as i64)).await;
+    }
+    if ids.is_empty() && next_cursor.is_none() {
+        return sink.write(&RespResponse::array(None)).await;
+    }
+
+    // 分页时回复 [下一页游标, [结果...]]，最后一页的游标为 "0"
+    if let Some(next) = next_cursor {
+        sink.write("*2\r\n").await?;
+        sink.write_value(&RespValue::bulk(
+            next.map_or_else(|| "0".to_string(), |c| c.to_string()),
+        ))
+        .await?;
+    }
+    sink.write(&format!("*{}\r\n", ids.len())).await?;
+
+    // IDS 时每个结果只有 id，不需要复制对象
+    if parsed_args.output == QueryOutput::Ids {
+        for id in &ids {
+            sink.write(&RespResponse::bulk_string(Some(id))).await?;
         }
+        return Ok(());
     }
+
+    let precision = parsed_args.precision.or(database.output_precision());
+    let strict = database.strict_geojson(&parsed_args.collection_id);
+    for batch in ids.chunks(REPLY_BATCH) {
+        let items = {
+            let _shared = shared_lock(&database, streaming).await;
+            database.items(&parsed_args.collection_id, batch).await?
+        };
+        for item in items {
+            write_item(&parsed_args, item, precision, strict, sink).await?;
+        }
+    }
+    Ok(())
+}
+
+/// 写出一个结果；查询之后被删除的对象回复 nil，数组长度与开头声明的保持一致
+async fn write_item(
+    parsed_args: &ParsedIntersectsArgs,
+    item: Option<GeoItem>,
+    precision: Option<u32>,
+    strict: bool,
+    sink: &mut ReplySink,
+) -> Result<()> {
+    let Some(mut item) = item else {
+        return sink.write(&RespResponse::bulk_string(None)).await;
+    };
+    // POINTS、BOUNDS 和 HASHES 时每个结果为 [id, 质心/外接矩形/geohash]
+    if let Some(accessor) = parsed_args.output.accessor() {
+        let value = accessor_value(&item.geometry, accessor, precision)
+            .unwrap_or(RespValue::BulkString(None));
+        sink.write_value(&RespValue::Array(Some(vec![
+            RespValue::bulk(item.id),
+            value,
+        ])))
+        .await?;
+        return Ok(());
+    }
+    // NOFIELDS/NOGEOM 时每个结果为 [id, "[minx,miny,maxx,maxy]"]，
+    // 不输出几何体和属性，由客户端按需再用 GET 取回
+    if parsed_args.bbox_only {
+        let bbox = geometry_to_bbox(&item.geometry).ok().map(|bbox| {
+            format!(
+                "[{},{},{},{}]",
+                bbox.min[0], bbox.min[1], bbox.max[0], bbox.max[1]
+            )
+        });
+        sink.write_value(&RespValue::Array(Some(vec![
+            RespValue::bulk(item.id),
+            RespValue::BulkString(bbox.map(String::into_bytes)),
+        ])))
+        .await?;
+        return Ok(());
+    }
+    if let Some(decimals) = precision {
+        item.geojson = round_coordinates(&item.geojson, decimals);
+    }
+    if strict {
+        item.geojson = rfc7946::normalize(&item.geojson);
+    }
+    match &parsed_args.fields {
+        // 指定 FIELDS 时每个结果为 [id, 所选字段的 JSON 对象]
+        Some(fields) => {
+            let projected = fields.project(&item);
+            sink.write_value(&RespValue::Array(Some(vec![
+                RespValue::bulk(item.id),
+                RespValue::bulk(projected),
+            ])))
+            .await?;
+        }
+        // 优化：直接使用缓存的 GeoJSON 字符串，零序列化开销
+        None => {
+            sink.write(&RespResponse::bulk_string(Some(&item.geojson)))
+                .await?
+        }
+    }
+    Ok(())
 }
 
 /// INTERSECTS 命令的参数结构
@@ -83,6 +251,7 @@ pub struct IntersectsArgs {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::commands::args;
     use serde_json::json;
 
     #[tokio::test]
@@ -131,8 +300,8 @@ mod tests {
         });
 
         let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some(query_bbox.to_string())),
+            RespValue::bulk("fleet"),
+            RespValue::bulk(query_bbox.to_string()),
         ];
 
         let result = cmd.execute(&args).await.unwrap();
@@ -162,8 +331,8 @@ mod tests {
         });
 
         let args = vec![
-            RespValue::BulkString(Some("empty_fleet".to_string())),
-            RespValue::BulkString(Some(query_bbox.to_string())),
+            RespValue::bulk("empty_fleet"),
+            RespValue::bulk(query_bbox.to_string()),
         ];
 
         let result = cmd.execute(&args).await.unwrap();
@@ -176,7 +345,7 @@ mod tests {
         let cmd = IntersectsCommand::new(database);
 
         // 参数太少
-        let args = vec![RespValue::BulkString(Some("fleet".to_string()))];
+        let args = vec![RespValue::bulk("fleet")];
 
         let result = cmd.execute(&args).await.unwrap();
         assert!(result.contains("wrong number of arguments"));
@@ -187,10 +356,7 @@ mod tests {
         let database = Arc::new(GeoDatabase::new());
         let cmd = IntersectsCommand::new(database);
 
-        let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("invalid json".to_string())),
-        ];
+        let args = vec![RespValue::bulk("fleet"), RespValue::bulk("invalid json")];
 
         let result = cmd.execute(&args).await.unwrap();
         assert!(result.contains("ERR invalid GeoJSON"));
@@ -246,10 +412,10 @@ mod tests {
 
         // 使用 WITHIN true 查询 - 只返回完全在内部的点
         let args_within = vec![
-            RespValue::BulkString(Some("test".to_string())),
-            RespValue::BulkString(Some(query_polygon.to_string())),
-            RespValue::BulkString(Some("WITHIN".to_string())),
-            RespValue::BulkString(Some("true".to_string())),
+            RespValue::bulk("test"),
+            RespValue::bulk(query_polygon.to_string()),
+            RespValue::bulk("WITHIN"),
+            RespValue::bulk("true"),
         ];
 
         let result_within = cmd.execute(&args_within).await.unwrap();
@@ -259,10 +425,10 @@ mod tests {
 
         // 使用 WITHIN false（或默认）查询 - 返回所有相交的点
         let args_intersects = vec![
-            RespValue::BulkString(Some("test".to_string())),
-            RespValue::BulkString(Some(query_polygon.to_string())),
-            RespValue::BulkString(Some("WITHIN".to_string())),
-            RespValue::BulkString(Some("false".to_string())),
+            RespValue::bulk("test"),
+            RespValue::bulk(query_polygon.to_string()),
+            RespValue::bulk("WITHIN"),
+            RespValue::bulk("false"),
         ];
 
         let result_intersects = cmd.execute(&args_intersects).await.unwrap();
@@ -303,12 +469,12 @@ mod tests {
 
         // 使用 WITHIN true 和 LIMIT 3
         let args = vec![
-            RespValue::BulkString(Some("test".to_string())),
-            RespValue::BulkString(Some(query_polygon.to_string())),
-            RespValue::BulkString(Some("WITHIN".to_string())),
-            RespValue::BulkString(Some("true".to_string())),
-            RespValue::BulkString(Some("LIMIT".to_string())),
-            RespValue::BulkString(Some("3".to_string())),
+            RespValue::bulk("test"),
+            RespValue::bulk(query_polygon.to_string()),
+            RespValue::bulk("WITHIN"),
+            RespValue::bulk("true"),
+            RespValue::bulk("LIMIT"),
+            RespValue::bulk("3"),
         ];
 
         let result = cmd.execute(&args).await.unwrap();
@@ -321,4 +487,252 @@ mod tests {
                 || result.starts_with("*1\r\n")
         );
     }
+
+    #[tokio::test]
+    async fn test_intersects_command_bbox_only() {
+        let database = Arc::new(GeoDatabase::new());
+        let line = json!({
+            "type": "Feature",
+            "properties": {"name": "road"},
+            "geometry": {"type": "LineString", "coordinates": [[1.0, 2.0], [3.5, 4.0]]}
+        });
+        database
+            .set("roads", "r1", &line.to_string())
+            .await
+            .unwrap();
+        let cmd = IntersectsCommand::new(Arc::clone(&database));
+
+        let query_polygon = json!({
+            "type": "Polygon",
+            "coordinates": [[[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0], [0.0, 0.0]]]
+        });
+        for keyword in ["NOFIELDS", "nogeom"] {
+            let args = vec![
+                RespValue::bulk("roads"),
+                RespValue::bulk(query_polygon.to_string()),
+                RespValue::bulk(keyword.to_string()),
+            ];
+            let result = cmd.execute(&args).await.unwrap();
+            let expected = RespResponse::array(Some(&[RespValue::Array(Some(vec![
+                RespValue::bulk("r1"),
+                RespValue::bulk("[1,2,3.5,4]"),
+            ]))]));
+            assert_eq!(result, expected);
+        }
+
+        // 与 FIELDS 冲突
+        let args = vec![
+            RespValue::bulk("roads"),
+            RespValue::bulk(query_polygon.to_string()),
+            RespValue::bulk("FIELDS"),
+            RespValue::bulk("name"),
+            RespValue::bulk("NOGEOM"),
+        ];
+        let result = cmd.execute(&args).await.unwrap();
+        assert!(result.starts_with("-ERR FIELDS cannot be combined"));
+    }
+
+    #[tokio::test]
+    async fn test_intersects_command_cursor() {
+        let database = Arc::new(GeoDatabase::new());
+        for i in 0..5 {
+            let point = json!({"type": "Point", "coordinates": [i as f64, 0.0]});
+            database
+                .set("fleet", &format!("v{}", i), &point.to_string())
+                .await
+                .unwrap();
+        }
+        let cmd = IntersectsCommand::new(Arc::clone(&database));
+        let query_polygon = json!({
+            "type": "Polygon",
+            "coordinates": [[[-1.0, -1.0], [10.0, -1.0], [10.0, 1.0], [-1.0, 1.0], [-1.0, -1.0]]]
+        })
+        .to_string();
+
+        let result = cmd
+            .execute(&args(&[
+                "fleet",
+                &query_polygon,
+                "CURSOR",
+                "0",
+                "LIMIT",
+                "3",
+                "NOGEOM",
+            ]))
+            .await
+            .unwrap();
+        let cursor = result.split("\r\n").nth(2).unwrap().to_string();
+        assert!(cursor.ends_with(":v2"));
+        assert!(result.contains("$2\r\nv0\r\n"));
+        assert!(result.contains("$2\r\nv2\r\n"));
+
+        // 两页之间删除游标指向的对象，已返回的 v0 被修改后重新返回，未修改的 v1 不重复
+        database.delete("fleet", "v2").await.unwrap();
+        database
+            .set(
+                "fleet",
+                "v0",
+                &json!({"type": "Point", "coordinates": [0.5, 0.0]}).to_string(),
+            )
+            .await
+            .unwrap();
+        let result = cmd
+            .execute(&args(&[
+                "fleet",
+                &query_polygon,
+                "CURSOR",
+                &cursor,
+                "LIMIT",
+                "3",
+                "NOGEOM",
+            ]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("*2\r\n$1\r\n0\r\n*3\r\n"));
+        for (id, present) in [("v0", true), ("v1", false), ("v3", true), ("v4", true)] {
+            assert_eq!(
+                result.contains(&format!("$2\r\n{}\r\n", id)),
+                present,
+                "{}",
+                id
+            );
+        }
+
+        let result = cmd
+            .execute(&args(&[
+                "fleet",
+                &query_polygon,
+                "CURSOR",
+                "0",
+                "ORDER",
+                "CENTER",
+            ]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("-ERR CURSOR cannot be combined with ORDER CENTER"));
+        let result = cmd
+            .execute(&args(&["fleet", &query_polygon, "CURSOR", "nope"]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("-ERR invalid cursor"));
+    }
+
+    #[tokio::test]
+    async fn test_intersects_query_shapes() {
+        let database = Arc::new(GeoDatabase::new());
+        // 沿经线每隔约 111 米一个点
+        for i in 0..10 {
+            let point = json!({"type": "Point", "coordinates": [116.4, 39.9 + i as f64 * 0.001]})
+                .to_string();
+            database
+                .set("fleet", &format!("v{}", i), &point)
+                .await
+                .unwrap();
+        }
+        let cmd = IntersectsCommand::new(Arc::clone(&database));
+        let count = |result: String| result.lines().next().unwrap().to_string();
+
+        // 500 米内的 v0..v4
+        let result = cmd
+            .execute(&args(&["fleet", "CIRCLE", "116.4", "39.9", "500"]))
+            .await
+            .unwrap();
+        assert_eq!(count(result), "*5");
+
+        // BOUNDS 纬度在前；之后照常接受其他选项
+        let result = cmd
+            .execute(&args(&[
+                "fleet", "BOUNDS", "39.9015", "116.3", "39.9065", "116.5", "LIMIT", "10",
+            ]))
+            .await
+            .unwrap();
+        assert_eq!(count(result), "*5");
+        let result = cmd
+            .execute(&args(&[
+                "fleet", "bounds", "39.9015", "116.3", "39.9065", "116.5", "3",
+            ]))
+            .await
+            .unwrap();
+        assert_eq!(count(result), "*3");
+
+        // 北京所在的 10 级瓦片 (843, 388) 和对应的 quadkey
+        let result = cmd
+            .execute(&args(&["fleet", "TILE", "843", "388", "10"]))
+            .await
+            .unwrap();
+        assert_eq!(count(result), "*10");
+        let result = cmd
+            .execute(&args(&["fleet", "QUADKEY", "1321001211", "WITHIN", "true"]))
+            .await
+            .unwrap();
+        assert_eq!(count(result), "*10");
+        let result = cmd
+            .execute(&args(&["fleet", "QUADKEY", "1321001210"]))
+            .await
+            .unwrap();
+        assert_eq!(result, RespResponse::array(None));
+
+        let result = cmd
+            .execute(&args(&["fleet", "CIRCLE", "116.4", "39.9"]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("-ERR CIRCLE requires 3 values"));
+        let result = cmd
+            .execute(&args(&["fleet", "TILE", "1", "0", "0"]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("-ERR invalid TILE"));
+    }
+
+    #[tokio::test]
+    async fn test_intersects_output_selectors() {
+        let database = Arc::new(GeoDatabase::new());
+        for i in 0..3 {
+            let point = json!({"type": "Point", "coordinates": [i as f64, 0.5]});
+            database
+                .set("fleet", &format!("v{}", i), &point.to_string())
+                .await
+                .unwrap();
+        }
+        let cmd = IntersectsCommand::new(Arc::clone(&database));
+        let query_polygon = json!({
+            "type": "Polygon",
+            "coordinates": [[[-1.0, -1.0], [10.0, -1.0], [10.0, 1.0], [-1.0, 1.0], [-1.0, -1.0]]]
+        })
+        .to_string();
+        let run = |options: &[&str]| {
+            let mut list = vec!["fleet", query_polygon.as_str(), "ORDER", "ID"];
+            list.extend_from_slice(options);
+            let values = args(&list);
+            let cmd = &cmd;
+            async move { cmd.execute(&values).await.unwrap() }
+        };
+
+        assert_eq!(run(&["COUNT"]).await, ":3\r\n");
+        assert_eq!(
+            run(&["IDS"]).await,
+            "*3\r\n$2\r\nv0\r\n$2\r\nv1\r\n$2\r\nv2\r\n"
+        );
+        assert!(run(&["POINTS", "LIMIT", "1"])
+            .await
+            .starts_with("*1\r\n*2\r\n$2\r\nv0\r\n*2\r\n$3\r\n0.5\r\n$1\r\n0\r\n"));
+        assert!(run(&["BOUNDS"]).await.contains(
+            "$2\r\nv2\r\n*2\r\n*2\r\n$3\r\n0.5\r\n$1\r\n2\r\n*2\r\n$3\r\n0.5\r\n$1\r\n2\r\n"
+        ));
+        assert_eq!(run(&["OBJECTS"]).await, run(&[]).await);
+
+        // 游标分页时 IDS 只改变每页结果的格式
+        let result = run(&["IDS", "CURSOR", "0", "LIMIT", "2"]).await;
+        assert!(result.ends_with("*2\r\n$2\r\nv0\r\n$2\r\nv1\r\n"));
+
+        assert!(run(&["COUNT", "CURSOR", "0"])
+            .await
+            .starts_with("-ERR COUNT cannot be combined with CURSOR"));
+        assert!(run(&["IDS", "NOFIELDS"])
+            .await
+            .starts_with("-ERR FIELDS, NOFIELDS and NOGEOM cannot be combined"));
+        assert!(run(&["IDS", "FENCE"])
+            .await
+            .starts_with("-ERR FENCE cannot be combined with IDS"));
+    }
 }
@@ -0,0 +1,465 @@
+//! 对象 JSON 文档的读写（JSET / JGET / JDEL）
+//!
+//! 路径相对于对象存储的 GeoJSON 文档，Feature 的元数据通常放在 `properties` 下：
+//! `JSET fleet truck1 properties.driver.name Tom`。修改后的文档必须仍是有效的 GeoJSON，
+//! 以完整的 SET 记录写入 AOF，重启后仍然存在
+
+use crate::commands::args::ArgumentParser;
+use crate::commands::Command;
+use crate::protocol::{parser::RespValue, RespResponse};
+use crate::storage::GeoDatabase;
+use crate::Result;
+use serde_json::{Map, Value};
+use std::sync::Arc;
+
+/// 点号分隔的 JSON 路径，例如 `properties.driver.name`、`properties.stops.0`
+///
+/// 数字段在数组中是下标，在对象中是普通的键；`\.` 表示键中的点号
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath {
+    segments: Vec<String>,
+}
+
+impl JsonPath {
+    pub fn parse(path: &str) -> std::result::Result<Self, String> {
+        let mut segments = Vec::new();
+        let mut segment = String::new();
+        let mut chars = path.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => segment.push(chars.next().unwrap_or('\\')),
+                '.' => segments.push(std::mem::take(&mut segment)),
+                c => segment.push(c),
+            }
+        }
+        segments.push(segment);
+
+        if segments.iter().any(String::is_empty) {
+            return Err(format!("ERR invalid JSON path '{}'", path));
+        }
+        Ok(Self { segments })
+    }
+
+    /// 路径指向的值，路径不存在时返回 None
+    pub fn get<'v>(&self, document: &'v Value) -> Option<&'v Value> {
+        self.segments
+            .iter()
+            .try_fold(document, |value, segment| child(value, segment))
+    }
+
+    /// 把路径处的值设为 `value`，返回文档是否发生了变化
+    ///
+    /// 不存在的中间对象会被创建（值为 null 时替换为对象）；数组下标等于长度时追加到末尾
+    pub fn set(&self, document: &mut Value, value: Value) -> std::result::Result<bool, String> {
+        let (last, parents) = self.segments.split_last().expect("path is never empty");
+        let mut current = document;
+        for segment in parents {
+            if current.is_null() {
+                *current = Value::Object(Map::new());
+            }
+            current = match current {
+                Value::Object(object) => object
+                    .entry(segment.as_str())
+                    .or_insert_with(|| Value::Object(Map::new())),
+                Value::Array(array) => {
+                    let index = self.index(segment, array.len())?;
+                    if index == array.len() {
+                        array.push(Value::Object(Map::new()));
+                    }
+                    &mut array[index]
+                }
+                _ => return Err(self.not_a_container(segment)),
+            };
+        }
+
+        if current.is_null() {
+            *current = Value::Object(Map::new());
+        }
+        match current {
+            Value::Object(object) => Ok(object.insert(last.clone(), value.clone()) != Some(value)),
+            Value::Array(array) => {
+                let index = self.index(last, array.len())?;
+                if index == array.len() {
+                    array.push(value);
+                    Ok(true)
+                } else {
+                    Ok(std::mem::replace(&mut array[index], value.clone()) != value)
+                }
+            }
+            _ => Err(self.not_a_container(last)),
+        }
+    }
+
+    /// 删除路径处的值，返回是否删除了
+    pub fn delete(&self, document: &mut Value) -> bool {
+        let (last, parents) = self.segments.split_last().expect("path is never empty");
+        let parent = parents
+            .iter()
+            .try_fold(document, |value, segment| child_mut(value, segment));
+        match parent {
+            Some(Value::Object(object)) => object.remove(last).is_some(),
+            Some(Value::Array(array)) => match last.parse::<usize>() {
+                Ok(index) if index < array.len() => {
+                    array.remove(index);
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// 数组下标，可以等于长度（追加）
+    fn index(&self, segment: &str, len: usize) -> std::result::Result<usize, String> {
+        match segment.parse::<usize>() {
+            Ok(index) if index <= len => Ok(index),
+            _ => Err(format!(
+                "ERR invalid JSON path '{}': array index '{}' out of range (length {})",
+                self, segment, len
+            )),
+        }
+    }
+
+    fn not_a_container(&self, segment: &str) -> String {
+        format!(
+            "ERR invalid JSON path '{}': cannot set '{}' inside a value that is not an object or array",
+            self, segment
+        )
+    }
+}
+
+impl std::fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let escaped: Vec<String> = self
+            .segments
+            .iter()
+            .map(|segment| segment.replace('\\', "\\\\").replace('.', "\\."))
+            .collect();
+        f.write_str(&escaped.join("."))
+    }
+}
+
+fn child<'v>(value: &'v Value, segment: &str) -> Option<&'v Value> {
+    match value {
+        Value::Object(object) => object.get(segment),
+        Value::Array(array) => array.get(segment.parse::<usize>().ok()?),
+        _ => None,
+    }
+}
+
+fn child_mut<'v>(value: &'v mut Value, segment: &str) -> Option<&'v mut Value> {
+    match value {
+        Value::Object(object) => object.get_mut(segment),
+        Value::Array(array) => array.get_mut(segment.parse::<usize>().ok()?),
+        _ => None,
+    }
+}
+
+/// JSET collection id path value [RAW|STR]：设置对象文档中路径处的值
+pub struct JsetCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl JsetCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+impl Command for JsetCommand {
+    fn name(&self) -> &'static str {
+        "JSET"
+    }
+
+    fn execute(
+        &self,
+        args: &[RespValue],
+    ) -> impl std::future::Future<Output = Result<String>> + Send {
+        let database = Arc::clone(&self.database);
+        let parse_result = ArgumentParser::new(args, "JSET").parse_jset_args();
+
+        async move {
+            let parsed_args = match parse_result {
+                Ok(args) => args,
+                Err(err_msg) => return Ok(RespResponse::error(&err_msg)),
+            };
+
+            let path = parsed_args.path;
+            let value = parsed_args.value;
+            match database
+                .update_geojson(
+                    &parsed_args.collection_id,
+                    &parsed_args.item_id,
+                    |document| path.set(document, value),
+                )
+                .await
+            {
+                Ok(Some(_)) => Ok(RespResponse::simple_string("OK")),
+                Ok(None) => Ok(RespResponse::error("ERR id not found")),
+                Err(e) => Ok(RespResponse::command_error("failed to set", e.as_ref())),
+            }
+        }
+    }
+}
+
+/// JGET collection id path [RAW]：读取对象文档中路径处的值，对象或路径不存在时返回 nil
+///
+/// 字符串默认不带引号返回，RAW 时所有值都按 JSON 编码返回
+pub struct JgetCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl JgetCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+impl Command for JgetCommand {
+    fn name(&self) -> &'static str {
+        "JGET"
+    }
+
+    fn execute(
+        &self,
+        args: &[RespValue],
+    ) -> impl std::future::Future<Output = Result<String>> + Send {
+        let database = Arc::clone(&self.database);
+        let parse_result = ArgumentParser::new(args, "JGET").parse_jget_args();
+
+        async move {
+            let parsed_args = match parse_result {
+                Ok(args) => args,
+                Err(err_msg) => return Ok(RespResponse::error(&err_msg)),
+            };
+
+            let item = match database
+                .get(&parsed_args.collection_id, &parsed_args.item_id)
+                .await
+            {
+                Ok(item) => item,
+                Err(e) => return Ok(RespResponse::command_error("failed to get", e.as_ref())),
+            };
+            let document = item.and_then(|item| serde_json::from_str::<Value>(&item.geojson).ok());
+            let reply = match document.as_ref().and_then(|doc| parsed_args.path.get(doc)) {
+                Some(Value::String(s)) if !parsed_args.raw => RespResponse::bulk_string(Some(s)),
+                Some(value) => RespResponse::bulk_string(Some(&value.to_string())),
+                None => RespResponse::bulk_string(None),
+            };
+            Ok(reply)
+        }
+    }
+}
+
+/// JDEL collection id path：删除对象文档中路径处的值，返回删除的数量（0 或 1）
+pub struct JdelCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl JdelCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+impl Command for JdelCommand {
+    fn name(&self) -> &'static str {
+        "JDEL"
+    }
+
+    fn execute(
+        &self,
+        args: &[RespValue],
+    ) -> impl std::future::Future<Output = Result<String>> + Send {
+        let database = Arc::clone(&self.database);
+        let parse_result = ArgumentParser::new(args, "JDEL").parse_jdel_args();
+
+        async move {
+            let parsed_args = match parse_result {
+                Ok(args) => args,
+                Err(err_msg) => return Ok(RespResponse::error(&err_msg)),
+            };
+
+            let path = parsed_args.path;
+            match database
+                .update_geojson(
+                    &parsed_args.collection_id,
+                    &parsed_args.item_id,
+                    |document| Ok(path.delete(document)),
+                )
+                .await
+            {
+                Ok(deleted) => Ok(RespResponse::integer(i64::from(deleted == Some(true)))),
+                Err(e) => Ok(RespResponse::command_error("failed to delete", e.as_ref())),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::args;
+    use crate::rtree::algorithms::search::SearchOrder;
+    use serde_json::json;
+
+    #[test]
+    fn test_json_path() {
+        let path = |p: &str| JsonPath::parse(p).unwrap();
+        let mut document = json!({"type": "Feature", "properties": {"stops": [1, 2]}});
+
+        assert!(path("properties.driver.name")
+            .set(&mut document, json!("Tom"))
+            .unwrap());
+        assert!(!path("properties.driver.name")
+            .set(&mut document, json!("Tom"))
+            .unwrap());
+        assert!(path("properties.stops.2")
+            .set(&mut document, json!(3))
+            .unwrap());
+        assert!(path(r"properties.a\.b")
+            .set(&mut document, json!(true))
+            .unwrap());
+        assert_eq!(
+            document["properties"],
+            json!({"driver": {"name": "Tom"}, "stops": [1, 2, 3], "a.b": true})
+        );
+        assert_eq!(path("properties.stops.1").get(&document), Some(&json!(2)));
+        assert_eq!(path("properties.missing.x").get(&document), None);
+
+        let err = path("properties.stops.5")
+            .set(&mut document, json!(0))
+            .unwrap_err();
+        assert!(err.contains("out of range"));
+        let err = path("type.x").set(&mut document, json!(0)).unwrap_err();
+        assert!(err.contains("not an object or array"));
+
+        assert!(path("properties.stops.0").delete(&mut document));
+        assert!(path("properties.driver").delete(&mut document));
+        assert!(!path("properties.driver").delete(&mut document));
+        assert_eq!(
+            document["properties"],
+            json!({"stops": [2, 3], "a.b": true})
+        );
+
+        assert!(JsonPath::parse("a..b").is_err());
+        assert!(JsonPath::parse("").is_err());
+        assert_eq!(path(r"a\.b.c").to_string(), r"a\.b.c");
+    }
+
+    #[tokio::test]
+    async fn test_jset_jget_jdel() {
+        let database = Arc::new(GeoDatabase::new());
+        let feature = json!({
+            "type": "Feature",
+            "geometry": {"type": "Point", "coordinates": [1.0, 2.0]},
+            "properties": {}
+        });
+        database
+            .set_with_tags(
+                "fleet",
+                "truck1",
+                &feature.to_string(),
+                &["hot".to_string()],
+            )
+            .await
+            .unwrap();
+        let jset = JsetCommand::new(Arc::clone(&database));
+        let jget = JgetCommand::new(Arc::clone(&database));
+        let jdel = JdelCommand::new(Arc::clone(&database));
+
+        let result = jset
+            .execute(&args(&["fleet", "truck1", "properties.driver", "Tom"]))
+            .await
+            .unwrap();
+        assert_eq!(result, "+OK\r\n");
+        // 数字等有效的 JSON 按 JSON 解析，STR 强制为字符串
+        jset.execute(&args(&["fleet", "truck1", "properties.speed", "42"]))
+            .await
+            .unwrap();
+        jset.execute(&args(&["fleet", "truck1", "properties.code", "7", "STR"]))
+            .await
+            .unwrap();
+
+        let result = jget
+            .execute(&args(&["fleet", "truck1", "properties.driver"]))
+            .await
+            .unwrap();
+        assert_eq!(result, "$3\r\nTom\r\n");
+        let result = jget
+            .execute(&args(&["fleet", "truck1", "properties.driver", "RAW"]))
+            .await
+            .unwrap();
+        assert_eq!(result, "$5\r\n\"Tom\"\r\n");
+        let result = jget
+            .execute(&args(&["fleet", "truck1", "properties"]))
+            .await
+            .unwrap();
+        assert_eq!(
+            result,
+            RespResponse::bulk_string(Some(r#"{"code":"7","driver":"Tom","speed":42}"#))
+        );
+
+        // 修改几何体后重新建立索引，标签保留
+        jset.execute(&args(&[
+            "fleet",
+            "truck1",
+            "geometry.coordinates",
+            "[5.0,6.0]",
+            "RAW",
+        ]))
+        .await
+        .unwrap();
+        let bbox = geo::Geometry::Rect(geo::Rect::new(
+            geo::coord! { x: 4.0, y: 5.0 },
+            geo::coord! { x: 7.0, y: 7.0 },
+        ));
+        let found = database
+            .intersects_tagged(
+                "fleet",
+                &bbox,
+                0,
+                false,
+                SearchOrder::Tree,
+                &["hot".to_string()],
+            )
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+
+        // 修改后不再是有效的 GeoJSON 时拒绝，对象保持不变
+        let result = jset
+            .execute(&args(&["fleet", "truck1", "geometry.type", "Nothing"]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("-ERR"));
+        assert!(database.get("fleet", "truck1").await.unwrap().is_some());
+
+        let result = jdel
+            .execute(&args(&["fleet", "truck1", "properties.driver"]))
+            .await
+            .unwrap();
+        assert_eq!(result, ":1\r\n");
+        let result = jdel
+            .execute(&args(&["fleet", "truck1", "properties.driver"]))
+            .await
+            .unwrap();
+        assert_eq!(result, ":0\r\n");
+        let result = jget
+            .execute(&args(&["fleet", "truck1", "properties.driver"]))
+            .await
+            .unwrap();
+        assert_eq!(result, "$-1\r\n");
+
+        let result = jset
+            .execute(&args(&["fleet", "missing", "properties.x", "1"]))
+            .await
+            .unwrap();
+        assert_eq!(result, "-ERR id not found\r\n");
+        let result = jset
+            .execute(&args(&["fleet", "truck1", "properties.x", "{", "RAW"]))
+            .await
+            .unwrap();
+        assert!(result.contains("invalid JSON value"));
+    }
+}
@@ -1,16 +1,28 @@
+use crate::commands::args::ArgumentParser;
 use crate::commands::Command;
 use crate::protocol::{parser::RespValue, RespResponse};
-use crate::storage::GeoDatabase;
+use crate::storage::{GeoDatabase, GlobPattern, Namespace};
 use crate::Result;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 pub struct KeysCommand {
     database: Arc<GeoDatabase>,
+    namespace: Namespace,
 }
 
 impl KeysCommand {
     pub fn new(database: Arc<GeoDatabase>) -> Self {
-        Self { database }
+        Self {
+            database,
+            namespace: Namespace::default(),
+        }
+    }
+
+    /// 只列出指定命名空间中的 collection
+    pub fn with_namespace(mut self, namespace: Namespace) -> Self {
+        self.namespace = namespace;
+        self
     }
 }
 
@@ -24,26 +36,46 @@ impl Command for KeysCommand {
         args: &[RespValue],
     ) -> impl std::future::Future<Output = Result<String>> + Send {
         let database = Arc::clone(&self.database);
+        let namespace = self.namespace;
+
+        // 同步解析参数
+        let parse_result = ArgumentParser::new(args, "KEYS").parse_keys_args();
 
         async move {
-            // KEYS 命令不接受参数
-            if !args.is_empty() {
-                return Ok(RespResponse::error(
-                    "ERR wrong number of arguments for 'KEYS' command",
-                ));
-            }
+            let parsed_args = match parse_result {
+                Ok(args) => args,
+                Err(err_msg) => {
+                    return Ok(RespResponse::error(&err_msg));
+                }
+            };
 
-            // 获取所有 collection 名称
-            let collection_names = database.collection_names().await;
+            // 获取当前命名空间中所有 collection 的名称和对象数量（按名称排序）
+            let pattern = parsed_args.pattern.as_deref().map(GlobPattern::new);
+            let counts: Vec<(String, usize)> = database
+                .collection_counts()
+                .await
+                .into_iter()
+                .filter_map(|(name, count)| {
+                    let name = namespace.unscope(&name)?;
+                    pattern
+                        .as_ref()
+                        .is_none_or(|pattern| pattern.matches(name))
+                        .then(|| (name.to_string(), count))
+                })
+                .collect();
 
-            if collection_names.is_empty() {
+            if let Some(separator) = &parsed_args.stats {
+                return Ok(namespace_stats_reply(&counts, separator));
+            }
+
+            if counts.is_empty() {
                 // 返回空数组
                 Ok(RespResponse::array(None))
             } else {
                 // 将 collection 名称转换为 RespValue
-                let resp_values: Vec<RespValue> = collection_names
+                let resp_values: Vec<RespValue> = counts
                     .into_iter()
-                    .map(|name| RespValue::BulkString(Some(name)))
+                    .map(|(name, _)| RespValue::bulk(name))
                     .collect();
 
                 Ok(RespResponse::array(Some(&resp_values)))
@@ -52,9 +84,35 @@ impl Command for KeysCommand {
     }
 }
 
+/// 按命名空间前缀（第一个分隔符之前的部分，没有分隔符时为完整名称）汇总
+///
+/// 每个命名空间为 [前缀, collection 数量, 对象总数]，按前缀排序
+fn namespace_stats_reply(counts: &[(String, usize)], separator: &str) -> String {
+    let mut namespaces: BTreeMap<&str, (i64, i64)> = BTreeMap::new();
+    for (name, items) in counts {
+        let prefix = name.split(separator).next().unwrap_or(name);
+        let entry = namespaces.entry(prefix).or_default();
+        entry.0 += 1;
+        entry.1 += *items as i64;
+    }
+
+    let values: Vec<RespValue> = namespaces
+        .into_iter()
+        .map(|(prefix, (collections, items))| {
+            RespValue::Array(Some(vec![
+                RespValue::bulk(prefix.to_string()),
+                RespValue::Integer(collections),
+                RespValue::Integer(items),
+            ]))
+        })
+        .collect();
+    RespResponse::array(Some(&values))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::commands::args;
     use serde_json::json;
 
     #[tokio::test]
@@ -109,11 +167,95 @@ mod tests {
         let database = Arc::new(GeoDatabase::new());
         let cmd = KeysCommand::new(database);
 
-        // KEYS 不应该接受参数
-        let args = vec![RespValue::BulkString(Some("invalid".to_string()))];
+        // 模式之后只接受 STATS 选项
+        let args = vec![RespValue::bulk("gps:*"), RespValue::bulk("invalid")];
 
         let result = cmd.execute(&args).await.unwrap();
-        assert!(result.contains("wrong number of arguments"));
+        assert!(result.contains("unknown option 'INVALID'"));
+    }
+
+    async fn daily_collections() -> Arc<GeoDatabase> {
+        let database = Arc::new(GeoDatabase::new());
+        let point = json!({"type": "Point", "coordinates": [1.0, 2.0]}).to_string();
+        for (collection, id) in [
+            ("gps:2024-01-01", "a"),
+            ("gps:2024-01-01", "b"),
+            ("gps:2024-01-02", "c"),
+            ("trips:2024-01-01", "d"),
+            ("fleet", "truck1"),
+        ] {
+            database.set(collection, id, &point).await.unwrap();
+        }
+        database
+    }
+
+    #[tokio::test]
+    async fn test_keys_command_pattern() {
+        let cmd = KeysCommand::new(daily_collections().await);
+
+        let result = cmd.execute(&args(&["gps:*"])).await.unwrap();
+        assert_eq!(
+            result,
+            "*2\r\n$14\r\ngps:2024-01-01\r\n$14\r\ngps:2024-01-02\r\n"
+        );
+
+        let result = cmd.execute(&args(&["*-01-01"])).await.unwrap();
+        assert!(result.starts_with("*2\r\n"));
+        assert!(result.contains("trips:2024-01-01"));
+    }
+
+    #[tokio::test]
+    async fn test_keys_command_match() {
+        let database = daily_collections().await;
+        let point = json!({"type": "Point", "coordinates": [1.0, 2.0]}).to_string();
+        for collection in ["sensor_1", "sensor_x", "车队:北京", "车队:上海", "fleet*"] {
+            database.set(collection, "a", &point).await.unwrap();
+        }
+        let cmd = KeysCommand::new(database);
+
+        let result = cmd
+            .execute(&args(&["MATCH", "sensor?[0-9]"]))
+            .await
+            .unwrap();
+        assert_eq!(result, "*1\r\n$8\r\nsensor_1\r\n");
+
+        // 按字符匹配，? 匹配一个汉字
+        let result = cmd.execute(&args(&["车队:??"])).await.unwrap();
+        assert_eq!(result, "*2\r\n$13\r\n车队:上海\r\n$13\r\n车队:北京\r\n");
+
+        // 转义的 * 只匹配字面的 *
+        let result = cmd.execute(&args(&["MATCH", r"fleet\*"])).await.unwrap();
+        assert_eq!(result, "*1\r\n$6\r\nfleet*\r\n");
+
+        let result = cmd
+            .execute(&args(&["MATCH", "gps:*", "STATS"]))
+            .await
+            .unwrap();
+        assert_eq!(result, "*1\r\n*3\r\n$3\r\ngps\r\n:2\r\n:3\r\n");
+
+        let result = cmd.execute(&args(&["gps:*", "MATCH", "*"])).await.unwrap();
+        assert!(result.contains("only one pattern"));
+        let result = cmd.execute(&args(&["MATCH"])).await.unwrap();
+        assert!(result.contains("requires a pattern"));
+    }
+
+    #[tokio::test]
+    async fn test_keys_command_namespace_stats() {
+        let cmd = KeysCommand::new(daily_collections().await);
+
+        // [前缀, collection 数量, 对象总数]
+        let result = cmd.execute(&args(&["STATS"])).await.unwrap();
+        assert_eq!(
+            result,
+            "*3\r\n*3\r\n$5\r\nfleet\r\n:1\r\n:1\r\n*3\r\n$3\r\ngps\r\n:2\r\n:3\r\n*3\r\n$5\r\ntrips\r\n:1\r\n:1\r\n"
+        );
+
+        // 模式过滤 + 自定义分隔符
+        let result = cmd
+            .execute(&args(&["gps:*", "STATS", "SEP", "-"]))
+            .await
+            .unwrap();
+        assert_eq!(result, "*1\r\n*3\r\n$8\r\ngps:2024\r\n:2\r\n:3\r\n");
     }
 
     #[tokio::test]
@@ -0,0 +1,181 @@
+use crate::commands::info::InfoCommand;
+use crate::commands::Command;
+use crate::protocol::{parser::RespValue, RespResponse};
+use crate::rtree::algorithms::memory::MemoryUsage;
+use crate::server::alloc;
+use crate::storage::GeoDatabase;
+use crate::Result;
+use std::sync::Arc;
+
+/// MEMORY 命令：定位内存增长来自哪些 collection、哪一部分数据
+///
+/// 语法:
+/// - MEMORY USAGE collection：collection 占用的字节数估算，不存在时返回 nil
+/// - MEMORY STATS：分配器统计，以及每个已加载 collection 按索引/几何体/GeoJSON/标签拆分的估算
+/// - MEMORY RESET-PEAK：把 used_memory_peak 重置为当前值，用于观察一段时间内的峰值
+///
+/// USAGE 和 STATS 需要遍历对象，耗时与数据量成正比
+pub struct MemoryCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl MemoryCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+enum MemorySubcommand {
+    Usage(String),
+    Stats,
+    ResetPeak,
+}
+
+fn parse_args(args: &[RespValue]) -> std::result::Result<MemorySubcommand, String> {
+    let strings: Vec<&str> = args
+        .iter()
+        .map(|arg| match arg {
+            RespValue::BulkString(Some(_)) => arg
+                .as_str()
+                .ok_or_else(|| "ERR invalid argument: expected UTF-8 string".to_string()),
+            _ => Err("ERR invalid argument: expected string".to_string()),
+        })
+        .collect::<std::result::Result<_, _>>()?;
+
+    let Some((subcommand, rest)) = strings.split_first() else {
+        return Err("ERR wrong number of arguments for 'MEMORY' command".to_string());
+    };
+    match (subcommand.to_uppercase().as_str(), rest) {
+        ("USAGE", [collection]) => Ok(MemorySubcommand::Usage(collection.to_string())),
+        ("STATS", []) => Ok(MemorySubcommand::Stats),
+        ("RESET-PEAK", []) => Ok(MemorySubcommand::ResetPeak),
+        ("USAGE" | "STATS" | "RESET-PEAK", _) => Err(format!(
+            "ERR wrong number of arguments for 'MEMORY {}' command",
+            subcommand.to_uppercase()
+        )),
+        _ => Err(format!(
+            "ERR unknown MEMORY subcommand '{}', expected USAGE, STATS or RESET-PEAK",
+            subcommand
+        )),
+    }
+}
+
+fn usage_fields(usage: &MemoryUsage) -> String {
+    format!(
+        "total={},index={},geometry={},geojson={},tags={}",
+        usage.total(),
+        usage.index_bytes,
+        usage.geometry_bytes,
+        usage.geojson_bytes,
+        usage.tag_bytes
+    )
+}
+
+impl Command for MemoryCommand {
+    fn name(&self) -> &'static str {
+        "MEMORY"
+    }
+
+    fn execute(
+        &self,
+        args: &[RespValue],
+    ) -> impl std::future::Future<Output = Result<String>> + Send {
+        let database = Arc::clone(&self.database);
+
+        // 同步解析参数
+        let parse_result = parse_args(args);
+
+        async move {
+            let subcommand = match parse_result {
+                Ok(subcommand) => subcommand,
+                Err(err_msg) => return Ok(RespResponse::error(&err_msg)),
+            };
+
+            match subcommand {
+                MemorySubcommand::Usage(collection) => {
+                    Ok(match database.collection_memory_usage(&collection).await {
+                        Some(usage) => RespResponse::integer(usage.total() as i64),
+                        None => RespResponse::bulk_string(None),
+                    })
+                }
+                MemorySubcommand::Stats => {
+                    let collections = database.memory_usage().await;
+                    let mut total = MemoryUsage::default();
+                    for (_, usage) in &collections {
+                        total += *usage;
+                    }
+
+                    let mut dataset = String::from("# Dataset\r\n");
+                    dataset.push_str(&format!("dataset:{}\r\n", usage_fields(&total)));
+                    for (name, usage) in &collections {
+                        dataset.push_str(&format!(
+                            "collection:{}:{}\r\n",
+                            name,
+                            usage_fields(usage)
+                        ));
+                    }
+
+                    let stats = [InfoCommand::memory_section(), dataset].join("\r\n");
+                    Ok(RespResponse::bulk_string(Some(&stats)))
+                }
+                MemorySubcommand::ResetPeak => {
+                    alloc::reset_peak();
+                    Ok(RespResponse::simple_string("OK"))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::args;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_memory_usage_and_stats() {
+        let database = Arc::new(GeoDatabase::new());
+        for i in 0..10 {
+            database
+                .set(
+                    "fleet",
+                    &format!("truck{}", i),
+                    &json!({"type": "Point", "coordinates": [i as f64, 2.0]}).to_string(),
+                )
+                .await
+                .unwrap();
+        }
+        let cmd = MemoryCommand::new(Arc::clone(&database));
+
+        let usage = cmd.execute(&args(&["USAGE", "fleet"])).await.unwrap();
+        let bytes: i64 = usage.trim_start_matches(':').trim_end().parse().unwrap();
+        assert!(bytes > 0);
+
+        let missing = cmd.execute(&args(&["usage", "nope"])).await.unwrap();
+        assert_eq!(missing, RespResponse::bulk_string(None));
+
+        let stats = cmd.execute(&args(&["STATS"])).await.unwrap();
+        assert!(stats.contains("# Memory"));
+        assert!(stats.contains("# Dataset"));
+        assert!(stats.contains(&format!("dataset:total={},", bytes)));
+        assert!(stats.contains(&format!("collection:fleet:total={},", bytes)));
+
+        let reset = cmd.execute(&args(&["RESET-PEAK"])).await.unwrap();
+        assert_eq!(reset, RespResponse::simple_string("OK"));
+    }
+
+    #[tokio::test]
+    async fn test_memory_invalid_arguments() {
+        let cmd = MemoryCommand::new(Arc::new(GeoDatabase::new()));
+
+        let result = cmd.execute(&[]).await.unwrap();
+        assert!(result.starts_with("-ERR wrong number of arguments"));
+
+        let result = cmd.execute(&args(&["USAGE"])).await.unwrap();
+        assert!(result.starts_with("-ERR wrong number of arguments for 'MEMORY USAGE'"));
+
+        let result = cmd.execute(&args(&["DOCTOR"])).await.unwrap();
+        assert!(result.starts_with("-ERR unknown MEMORY subcommand"));
+    }
+}
@@ -1,37 +1,96 @@
+pub mod agg;
+pub mod area;
 pub mod args;
 pub mod basic;
+pub mod cluster;
+pub mod debug;
 pub mod delete;
 pub mod drop;
+pub mod expire;
+pub mod features;
+pub mod fields;
+pub mod geomop;
 pub mod get;
+pub mod hull;
+pub mod info;
 pub mod intersects;
+pub mod json;
 pub mod keys;
+pub mod memory;
 pub mod nearby;
 pub mod registry;
+pub mod render;
+pub mod save;
+pub mod scan;
 pub mod set;
+pub mod snap;
+pub mod stats;
 
 use crate::protocol::parser::RespValue;
+use crate::protocol::ReplySink;
 use crate::Result;
 
+use agg::AggCommand;
 use basic::{HelloCommand, PingCommand, QuitCommand};
-use delete::DeleteCommand;
+use cluster::ClusterCommand;
+use debug::DebugCommand;
+use delete::{DeleteCommand, PdelCommand};
 use drop::DropCommand;
+use expire::{ExpireCommand, PersistCommand, TtlCommand};
+use features::FeaturesCommand;
+use geomop::GeomopCommand;
 use get::GetCommand;
+use hull::HullCommand;
+use info::{InfoCommand, ServerCommand};
 use intersects::IntersectsCommand;
+use json::{JdelCommand, JgetCommand, JsetCommand};
 use keys::KeysCommand;
+use memory::MemoryCommand;
 use nearby::NearbyCommand;
+use render::RenderCommand;
+use save::{
+    AofMd5Command, BgrewriteaofCommand, BgsaveCommand, ExportCommand, ImportCommand, SaveCommand,
+};
+use scan::ScanCommand;
 use set::SetCommand;
+use snap::SnapCommand;
+use stats::StatsCommand;
 
 // 重新导出常用的类型
-pub use args::{ArgumentParser, DeleteArgs, DropArgs, GetArgs, NearbyArgs, SetArgs};
+pub use args::{ArgumentParser, DeleteArgs, DropArgs, ExpireArgs, GetArgs, NearbyArgs, SetArgs};
 pub use intersects::IntersectsArgs;
 pub use registry::CommandRegistry;
 
+/// 测试用：把字符串参数转换为命令参数（RESP 批量字符串）
+#[cfg(test)]
+pub(crate) fn args(values: &[&str]) -> Vec<RespValue> {
+    values
+        .iter()
+        .map(|s| RespValue::bulk(s.to_string()))
+        .collect()
+}
+
 pub trait Command {
     fn name(&self) -> &'static str;
     fn execute(
         &self,
         args: &[RespValue],
     ) -> impl std::future::Future<Output = Result<String>> + Send;
+
+    /// 把回复写入 sink；默认先用 [`execute`](Command::execute) 生成完整回复，
+    /// 结果可能很大的命令覆盖它，逐个结果写入
+    fn execute_into(
+        &self,
+        args: &[RespValue],
+        sink: &mut ReplySink,
+    ) -> impl std::future::Future<Output = Result<()>> + Send {
+        let reply = self.execute(args);
+
+        async move {
+            sink.write(&reply.await?).await?;
+            sink.flush().await
+        }
+    }
 }
 
 pub enum CommandType {
@@ -41,10 +100,36 @@ pub enum CommandType {
     Set(SetCommand),
     Get(GetCommand),
     Delete(DeleteCommand),
+    Pdel(PdelCommand),
+    Expire(ExpireCommand),
+    Ttl(TtlCommand),
+    Persist(PersistCommand),
+    Jset(JsetCommand),
+    Jget(JgetCommand),
+    Jdel(JdelCommand),
     Intersects(IntersectsCommand),
     Nearby(NearbyCommand),
     Drop(DropCommand),
     Keys(KeysCommand),
+    Scan(ScanCommand),
+    Info(InfoCommand),
+    Server(ServerCommand),
+    Agg(AggCommand),
+    Hull(HullCommand),
+    Cluster(ClusterCommand),
+    Snap(SnapCommand),
+    Geomop(GeomopCommand),
+    Render(RenderCommand),
+    Features(FeaturesCommand),
+    Memory(MemoryCommand),
+    Stats(StatsCommand),
+    Save(SaveCommand),
+    Bgsave(BgsaveCommand),
+    Bgrewriteaof(BgrewriteaofCommand),
+    Aofmd5(AofMd5Command),
+    Export(ExportCommand),
+    Import(ImportCommand),
+    Debug(DebugCommand),
 }
 
 impl CommandType {
@@ -56,10 +141,83 @@ impl CommandType {
             CommandType::Set(cmd) => cmd.name(),
             CommandType::Get(cmd) => cmd.name(),
             CommandType::Delete(cmd) => cmd.name(),
+            CommandType::Pdel(cmd) => cmd.name(),
+            CommandType::Expire(cmd) => cmd.name(),
+            CommandType::Ttl(cmd) => cmd.name(),
+            CommandType::Persist(cmd) => cmd.name(),
+            CommandType::Jset(cmd) => cmd.name(),
+            CommandType::Jget(cmd) => cmd.name(),
+            CommandType::Jdel(cmd) => cmd.name(),
             CommandType::Intersects(cmd) => cmd.name(),
             CommandType::Nearby(cmd) => cmd.name(),
             CommandType::Drop(cmd) => cmd.name(),
             CommandType::Keys(cmd) => cmd.name(),
+            CommandType::Scan(cmd) => cmd.name(),
+            CommandType::Info(cmd) => cmd.name(),
+            CommandType::Server(cmd) => cmd.name(),
+            CommandType::Agg(cmd) => cmd.name(),
+            CommandType::Hull(cmd) => cmd.name(),
+            CommandType::Cluster(cmd) => cmd.name(),
+            CommandType::Snap(cmd) => cmd.name(),
+            CommandType::Geomop(cmd) => cmd.name(),
+            CommandType::Render(cmd) => cmd.name(),
+            CommandType::Features(cmd) => cmd.name(),
+            CommandType::Memory(cmd) => cmd.name(),
+            CommandType::Stats(cmd) => cmd.name(),
+            CommandType::Save(cmd) => cmd.name(),
+            CommandType::Bgsave(cmd) => cmd.name(),
+            CommandType::Bgrewriteaof(cmd) => cmd.name(),
+            CommandType::Aofmd5(cmd) => cmd.name(),
+            CommandType::Export(cmd) => cmd.name(),
+            CommandType::Import(cmd) => cmd.name(),
+            CommandType::Debug(cmd) => cmd.name(),
+        }
+    }
+
+    /// 是否会修改数据
+    ///
+    /// 写命令一旦开始就必须执行完成，客户端断开时不能取消
+    fn is_write(&self) -> bool {
+        matches!(
+            self,
+            CommandType::Set(_)
+                | CommandType::Delete(_)
+                | CommandType::Pdel(_)
+                | CommandType::Expire(_)
+                | CommandType::Persist(_)
+                | CommandType::Jset(_)
+                | CommandType::Jdel(_)
+                | CommandType::Drop(_)
+                | CommandType::Import(_)
+                | CommandType::Debug(_)
+        )
+    }
+
+    /// 启动加载期间是否照常执行：只有不读写数据的命令，其他命令返回 LOADING
+    fn serves_while_loading(&self) -> bool {
+        matches!(
+            self,
+            CommandType::Ping(_)
+                | CommandType::Hello(_)
+                | CommandType::Quit(_)
+                | CommandType::Info(_)
+                | CommandType::Server(_)
+                | CommandType::Features(_)
+        )
+    }
+
+    /// 回复是否逐个结果写出，连接对这些命令分块写到 socket
+    fn streams(&self) -> bool {
+        matches!(self, CommandType::Intersects(_))
+    }
+
+    async fn execute_into(&self, args: &[RespValue], sink: &mut ReplySink) -> Result<()> {
+        match self {
+            CommandType::Intersects(cmd) => cmd.execute_into(args, sink).await,
+            _ => {
+                sink.write(&self.execute(args).await?).await?;
+                sink.flush().await
+            }
         }
     }
 
@@ -71,10 +229,36 @@ impl CommandType {
             CommandType::Set(cmd) => cmd.execute(args).await,
             CommandType::Get(cmd) => cmd.execute(args).await,
             CommandType::Delete(cmd) => cmd.execute(args).await,
+            CommandType::Pdel(cmd) => cmd.execute(args).await,
+            CommandType::Expire(cmd) => cmd.execute(args).await,
+            CommandType::Ttl(cmd) => cmd.execute(args).await,
+            CommandType::Persist(cmd) => cmd.execute(args).await,
+            CommandType::Jset(cmd) => cmd.execute(args).await,
+            CommandType::Jget(cmd) => cmd.execute(args).await,
+            CommandType::Jdel(cmd) => cmd.execute(args).await,
             CommandType::Intersects(cmd) => cmd.execute(args).await,
             CommandType::Nearby(cmd) => cmd.execute(args).await,
             CommandType::Drop(cmd) => cmd.execute(args).await,
             CommandType::Keys(cmd) => cmd.execute(args).await,
+            CommandType::Scan(cmd) => cmd.execute(args).await,
+            CommandType::Info(cmd) => cmd.execute(args).await,
+            CommandType::Server(cmd) => cmd.execute(args).await,
+            CommandType::Agg(cmd) => cmd.execute(args).await,
+            CommandType::Hull(cmd) => cmd.execute(args).await,
+            CommandType::Cluster(cmd) => cmd.execute(args).await,
+            CommandType::Snap(cmd) => cmd.execute(args).await,
+            CommandType::Geomop(cmd) => cmd.execute(args).await,
+            CommandType::Render(cmd) => cmd.execute(args).await,
+            CommandType::Features(cmd) => cmd.execute(args).await,
+            CommandType::Memory(cmd) => cmd.execute(args).await,
+            CommandType::Stats(cmd) => cmd.execute(args).await,
+            CommandType::Save(cmd) => cmd.execute(args).await,
+            CommandType::Bgsave(cmd) => cmd.execute(args).await,
+            CommandType::Bgrewriteaof(cmd) => cmd.execute(args).await,
+            CommandType::Aofmd5(cmd) => cmd.execute(args).await,
+            CommandType::Export(cmd) => cmd.execute(args).await,
+            CommandType::Import(cmd) => cmd.execute(args).await,
+            CommandType::Debug(cmd) => cmd.execute(args).await,
         }
     }
 }
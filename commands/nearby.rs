@@ -1,7 +1,11 @@
+use crate::commands::args::QueryOutput;
+use crate::commands::get::accessor_value;
 use crate::commands::{ArgumentParser, Command};
 use crate::protocol::parser::RespValue;
 use crate::protocol::RespResponse;
-use crate::storage::GeoDatabase;
+use crate::rtree::algorithms::knn::DistanceMetric;
+use crate::storage::geometry_utils::round_coordinates;
+use crate::storage::{rfc7946, GeoDatabase};
 use crate::Result;
 use std::sync::Arc;
 
@@ -37,19 +41,77 @@ impl Command for NearbyCommand {
                     return Ok(RespResponse::error(&err_msg));
                 }
             };
+            // 围栏由连接注册（见 server::fence），到达这里说明连接不能推送事件
+            if parsed_args.fence {
+                return Ok(RespResponse::error(
+                    "ERR FENCE is only supported on RESP and WebSocket connections",
+                ));
+            }
 
             // 执行 KNN 查询
             let k = parsed_args.k.unwrap_or(0); // 0 表示不限制数量
-            match database
-                .nearby(
-                    &parsed_args.collection_id,
-                    parsed_args.query_lon,
-                    parsed_args.query_lat,
-                    k,
-                    parsed_args.max_radius,
-                )
-                .await
-            {
+            let filter = &parsed_args.filter;
+            let metric = parsed_args.metric.unwrap_or(database.distance_metric());
+            // 带 WHERETAG 时只在标签索引的交集中查找（精确距离，忽略 APPROX）
+            // 带 TYPE / WHERE 或者非默认的距离度量时在遍历中过滤、按该度量排序，一直展开直到找到 k 个匹配的对象
+            // 带 WITHIN 时跳过与区域边界框不相交的子树，只返回完全包含在区域内的对象
+            let query_result = if !parsed_args.tags.is_empty() {
+                database
+                    .nearby_tagged(
+                        &parsed_args.collection_id,
+                        parsed_args.query_lon,
+                        parsed_args.query_lat,
+                        k,
+                        parsed_args.max_radius,
+                        &parsed_args.tags,
+                        parsed_args.region.as_ref(),
+                        filter,
+                        metric,
+                    )
+                    .await
+            } else if !filter.is_empty() || metric != DistanceMetric::Haversine {
+                database
+                    .nearby_filtered(
+                        &parsed_args.collection_id,
+                        parsed_args.query_lon,
+                        parsed_args.query_lat,
+                        k,
+                        parsed_args.max_radius,
+                        parsed_args.epsilon,
+                        parsed_args.region.as_ref(),
+                        filter,
+                        metric,
+                    )
+                    .await
+            } else if let Some(region) = &parsed_args.region {
+                database
+                    .nearby_within(
+                        &parsed_args.collection_id,
+                        parsed_args.query_lon,
+                        parsed_args.query_lat,
+                        k,
+                        parsed_args.max_radius,
+                        parsed_args.epsilon,
+                        region,
+                    )
+                    .await
+            } else {
+                database
+                    .nearby_approx(
+                        &parsed_args.collection_id,
+                        parsed_args.query_lon,
+                        parsed_args.query_lat,
+                        k,
+                        parsed_args.max_radius,
+                        parsed_args.epsilon,
+                    )
+                    .await
+            };
+            match query_result {
+                // COUNT：只返回匹配的数量
+                Ok(results) if parsed_args.output == QueryOutput::Count => {
+                    Ok(RespResponse::integer(results.len() as i64))
+                }
                 Ok(results) => {
                     if results.is_empty() {
                         Ok(RespResponse::array(None))
@@ -57,23 +119,61 @@ impl Command for NearbyCommand {
                         // 构建返回结果，包含距离信息
                         // 格式: [["item_id", geojson, distance_in_meters], ...]
                         let mut resp_values = Vec::with_capacity(results.len());
-
-                        for (item, distance) in results {
+                        let precision = parsed_args.precision.or(database.output_precision());
+                        let strict = database.strict_geojson(&parsed_args.collection_id);
+
+                        for (mut item, distance) in results {
+                            // 米保留两位小数，平面坐标单位保留六位
+                            let distance = if metric.is_geodesic() {
+                                RespValue::bulk(format!("{:.2}", distance))
+                            } else {
+                                RespValue::bulk(format!("{:.6}", distance))
+                            };
+                            // IDS 时每个结果为 [id, distance]，
+                            // POINTS、BOUNDS 和 HASHES 时为 [id, 质心/外接矩形/geohash, distance]
+                            if parsed_args.output == QueryOutput::Ids {
+                                resp_values.push(RespValue::Array(Some(vec![
+                                    RespValue::bulk(item.id),
+                                    distance,
+                                ])));
+                                continue;
+                            }
+                            if let Some(accessor) = parsed_args.output.accessor() {
+                                let value = accessor_value(&item.geometry, accessor, precision)
+                                    .unwrap_or(RespValue::BulkString(None));
+                                resp_values.push(RespValue::Array(Some(vec![
+                                    RespValue::bulk(item.id),
+                                    value,
+                                    distance,
+                                ])));
+                                continue;
+                            }
+                            if let Some(decimals) = precision {
+                                item.geojson = round_coordinates(&item.geojson, decimals);
+                            }
+                            if strict {
+                                item.geojson = rfc7946::normalize(&item.geojson);
+                            }
                             // 每个结果是一个数组：[geojson, distance]
-                            let result_array = vec![
-                                RespValue::BulkString(Some(item.geojson)),
-                                RespValue::BulkString(Some(format!("{:.2}", distance))), // 距离保留两位小数
-                            ];
+                            // 指定 FIELDS 时为：[id, 所选字段的 JSON 对象, distance]
+                            let mut result_array = match &parsed_args.fields {
+                                Some(fields) => {
+                                    let projected = fields.project(&item);
+                                    vec![RespValue::bulk(item.id), RespValue::bulk(projected)]
+                                }
+                                None => vec![RespValue::bulk(item.geojson)],
+                            };
+                            result_array.push(distance);
                             resp_values.push(RespValue::Array(Some(result_array)));
                         }
 
                         Ok(RespResponse::array(Some(&resp_values)))
                     }
                 }
-                Err(e) => Ok(RespResponse::error(&format!(
-                    "ERR nearby query failed: {}",
-                    e
-                ))),
+                Err(e) => Ok(RespResponse::command_error(
+                    "nearby query failed",
+                    e.as_ref(),
+                )),
             }
         }
     }
@@ -82,6 +182,7 @@ impl Command for NearbyCommand {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::commands::args;
     use serde_json::json;
 
     #[tokio::test]
@@ -127,12 +228,12 @@ mod tests {
 
         // 查询北京市中心 (116.4, 39.9) 最近的 3 个点
         let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("POINT".to_string())),
-            RespValue::BulkString(Some("116.4".to_string())),
-            RespValue::BulkString(Some("39.9".to_string())),
-            RespValue::BulkString(Some("COUNT".to_string())),
-            RespValue::BulkString(Some("3".to_string())),
+            RespValue::bulk("fleet"),
+            RespValue::bulk("POINT"),
+            RespValue::bulk("116.4"),
+            RespValue::bulk("39.9"),
+            RespValue::bulk("COUNT"),
+            RespValue::bulk("3"),
         ];
 
         let result = cmd.execute(&args).await.unwrap();
@@ -152,12 +253,12 @@ mod tests {
 
         // 查询不存在的 collection
         let args = vec![
-            RespValue::BulkString(Some("nonexistent".to_string())),
-            RespValue::BulkString(Some("POINT".to_string())),
-            RespValue::BulkString(Some("116.4".to_string())),
-            RespValue::BulkString(Some("39.9".to_string())),
-            RespValue::BulkString(Some("COUNT".to_string())),
-            RespValue::BulkString(Some("10".to_string())),
+            RespValue::bulk("nonexistent"),
+            RespValue::bulk("POINT"),
+            RespValue::bulk("116.4"),
+            RespValue::bulk("39.9"),
+            RespValue::bulk("COUNT"),
+            RespValue::bulk("10"),
         ];
 
         let result = cmd.execute(&args).await.unwrap();
@@ -172,10 +273,7 @@ mod tests {
         let cmd = NearbyCommand::new(Arc::clone(&database));
 
         // 缺少参数
-        let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("POINT".to_string())),
-        ];
+        let args = vec![RespValue::bulk("fleet"), RespValue::bulk("POINT")];
 
         let result = cmd.execute(&args).await.unwrap();
 
@@ -191,12 +289,12 @@ mod tests {
 
         // 无效的经度
         let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("POINT".to_string())),
-            RespValue::BulkString(Some("200.0".to_string())), // 无效经度
-            RespValue::BulkString(Some("39.9".to_string())),
-            RespValue::BulkString(Some("COUNT".to_string())),
-            RespValue::BulkString(Some("10".to_string())),
+            RespValue::bulk("fleet"),
+            RespValue::bulk("POINT"),
+            RespValue::bulk("200.0"), // 无效经度
+            RespValue::bulk("39.9"),
+            RespValue::bulk("COUNT"),
+            RespValue::bulk("10"),
         ];
 
         let result = cmd.execute(&args).await.unwrap();
@@ -232,12 +330,12 @@ mod tests {
         let cmd = NearbyCommand::new(Arc::clone(&database));
 
         let args = vec![
-            RespValue::BulkString(Some("test".to_string())),
-            RespValue::BulkString(Some("POINT".to_string())),
-            RespValue::BulkString(Some("116.0".to_string())),
-            RespValue::BulkString(Some("39.0".to_string())),
-            RespValue::BulkString(Some("COUNT".to_string())),
-            RespValue::BulkString(Some("3".to_string())),
+            RespValue::bulk("test"),
+            RespValue::bulk("POINT"),
+            RespValue::bulk("116.0"),
+            RespValue::bulk("39.0"),
+            RespValue::bulk("COUNT"),
+            RespValue::bulk("3"),
         ];
 
         let result = cmd.execute(&args).await.unwrap();
@@ -280,12 +378,12 @@ mod tests {
 
         // 只使用 RADIUS，查询 1000 米内的所有点
         let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("POINT".to_string())),
-            RespValue::BulkString(Some("116.0".to_string())),
-            RespValue::BulkString(Some("39.0".to_string())),
-            RespValue::BulkString(Some("RADIUS".to_string())),
-            RespValue::BulkString(Some("1000".to_string())),
+            RespValue::bulk("fleet"),
+            RespValue::bulk("POINT"),
+            RespValue::bulk("116.0"),
+            RespValue::bulk("39.0"),
+            RespValue::bulk("RADIUS"),
+            RespValue::bulk("1000"),
         ];
 
         let result = cmd.execute(&args).await.unwrap();
@@ -314,14 +412,14 @@ mod tests {
 
         // COUNT 5 + RADIUS 500m，应该返回 500m 内最近的 5 个（如果有的话）
         let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("POINT".to_string())),
-            RespValue::BulkString(Some("116.0".to_string())),
-            RespValue::BulkString(Some("39.0".to_string())),
-            RespValue::BulkString(Some("COUNT".to_string())),
-            RespValue::BulkString(Some("5".to_string())),
-            RespValue::BulkString(Some("RADIUS".to_string())),
-            RespValue::BulkString(Some("500".to_string())),
+            RespValue::bulk("fleet"),
+            RespValue::bulk("POINT"),
+            RespValue::bulk("116.0"),
+            RespValue::bulk("39.0"),
+            RespValue::bulk("COUNT"),
+            RespValue::bulk("5"),
+            RespValue::bulk("RADIUS"),
+            RespValue::bulk("500"),
         ];
 
         let result = cmd.execute(&args).await.unwrap();
@@ -344,14 +442,14 @@ mod tests {
 
         // RADIUS 在 COUNT 之前（测试参数顺序不敏感）
         let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("POINT".to_string())),
-            RespValue::BulkString(Some("116.0".to_string())),
-            RespValue::BulkString(Some("39.0".to_string())),
-            RespValue::BulkString(Some("RADIUS".to_string())),
-            RespValue::BulkString(Some("1000".to_string())),
-            RespValue::BulkString(Some("COUNT".to_string())),
-            RespValue::BulkString(Some("10".to_string())),
+            RespValue::bulk("fleet"),
+            RespValue::bulk("POINT"),
+            RespValue::bulk("116.0"),
+            RespValue::bulk("39.0"),
+            RespValue::bulk("RADIUS"),
+            RespValue::bulk("1000"),
+            RespValue::bulk("COUNT"),
+            RespValue::bulk("10"),
         ];
 
         let result = cmd.execute(&args).await.unwrap();
@@ -359,4 +457,349 @@ mod tests {
         println!("Reverse order result: {}", result);
         assert!(result.starts_with("*"));
     }
+
+    #[tokio::test]
+    async fn test_nearby_command_fields() {
+        let database = Arc::new(GeoDatabase::new());
+        let truck = json!({
+            "type": "Feature",
+            "properties": {"speed": 42, "heading": 90, "driver": "alice"},
+            "geometry": {"type": "Point", "coordinates": [116.4, 39.9]}
+        });
+        database
+            .set("fleet", "truck1", &truck.to_string())
+            .await
+            .unwrap();
+
+        let cmd = NearbyCommand::new(Arc::clone(&database));
+        let args = vec![
+            RespValue::bulk("fleet"),
+            RespValue::bulk("POINT"),
+            RespValue::bulk("116.4"),
+            RespValue::bulk("39.9"),
+            RespValue::bulk("COUNT"),
+            RespValue::bulk("1"),
+            RespValue::bulk("FIELDS"),
+            RespValue::bulk("speed,heading"),
+        ];
+
+        let result = cmd.execute(&args).await.unwrap();
+
+        // [id, 所选字段, 距离]，不包含几何体和未选择的属性
+        assert!(result.starts_with("*1\r\n*3\r\n"));
+        assert!(result.contains("truck1"));
+        assert!(result.contains(r#"{"speed":42,"heading":90}"#));
+        assert!(result.contains("0.00"));
+        assert!(!result.contains("coordinates"));
+        assert!(!result.contains("alice"));
+    }
+
+    #[tokio::test]
+    async fn test_nearby_command_precision() {
+        let database = Arc::new(GeoDatabase::new());
+        let truck = json!({
+            "type": "Feature",
+            "properties": {"speed": 42.123456},
+            "geometry": {"type": "Point", "coordinates": [116.4123456, 39.9123456]}
+        });
+        database
+            .set("fleet", "truck1", &truck.to_string())
+            .await
+            .unwrap();
+
+        let cmd = NearbyCommand::new(Arc::clone(&database));
+        let values = args(&[
+            "fleet",
+            "POINT",
+            "116.4",
+            "39.9",
+            "COUNT",
+            "1",
+            "PRECISION",
+            "3",
+            "FIELDS",
+            "geometry,speed",
+        ]);
+
+        let result = cmd.execute(&values).await.unwrap();
+
+        // FIELDS 投影中的几何体同样四舍五入，属性保持原值
+        assert!(result.contains(r#""coordinates":[116.412,39.912]"#));
+        assert!(result.contains(r#""speed":42.123456"#));
+    }
+
+    #[tokio::test]
+    async fn test_nearby_command_wheretag() {
+        let database = Arc::new(GeoDatabase::new());
+        let set = crate::commands::set::SetCommand::new(Arc::clone(&database));
+        let vehicles = [
+            ("bus1", 116.400, "line42"),
+            ("bus2", 116.401, "line7"),
+            ("bus3", 116.402, "line42"),
+        ];
+        for (id, lon, line) in vehicles {
+            let point = json!({"type": "Point", "coordinates": [lon, 39.9]}).to_string();
+            let values = args(&["fleet", id, &point, "TAG", "bus", "TAG", line]);
+            assert_eq!(
+                set.execute(&values).await.unwrap(),
+                RespResponse::simple_string("OK")
+            );
+        }
+
+        let cmd = NearbyCommand::new(Arc::clone(&database));
+        let values = args(&[
+            "fleet", "POINT", "116.401", "39.9", "COUNT", "5", "WHERETAG", "bus", "WHERETAG",
+            "line42",
+        ]);
+        let result = cmd.execute(&values).await.unwrap();
+
+        // 只返回 42 路的 bus1 和 bus3（各约 85 米），查询点上的 bus2 被标签过滤掉
+        assert!(result.starts_with("*2\r\n"));
+        assert_eq!(result.matches("$5\r\n85.").count(), 2);
+        assert!(!result.contains("0.00\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_nearby_command_within_region() {
+        let database = Arc::new(GeoDatabase::new());
+        let stations = [
+            ("s1", 116.400, 39.900),
+            ("s2", 116.410, 39.900),
+            ("s3", 116.450, 39.900),
+            ("s4", 116.460, 39.900),
+        ];
+        for (id, lon, lat) in stations {
+            let point = json!({"type": "Point", "coordinates": [lon, lat]}).to_string();
+            database.set("stations", id, &point).await.unwrap();
+        }
+        // 区域只覆盖东边的 s3 和 s4
+        let district = json!({
+            "type": "Polygon",
+            "coordinates": [[[116.44, 39.89], [116.47, 39.89], [116.47, 39.91], [116.44, 39.91], [116.44, 39.89]]]
+        })
+        .to_string();
+
+        let cmd = NearbyCommand::new(Arc::clone(&database));
+        let values = args(&[
+            "stations", "POINT", "116.400", "39.900", "COUNT", "1", "FIELDS", "id", "WITHIN",
+            "GEOJSON", &district,
+        ]);
+        let result = cmd.execute(&values).await.unwrap();
+
+        // 查询点上的 s1 和更近的 s2 在区域外，区域内最近的是 s3
+        assert!(result.starts_with("*1\r\n"));
+        assert!(result.contains("s3"));
+        assert!(!result.contains("s1") && !result.contains("s2"));
+
+        // 与 WHERETAG 组合时同样只保留区域内的对象
+        let values = args(&[
+            "stations", "POINT", "116.400", "39.900", "COUNT", "5", "WITHIN", "GEOJSON", &district,
+            "WHERETAG", "missing",
+        ]);
+        let result = cmd.execute(&values).await.unwrap();
+        assert_eq!(result, RespResponse::array(None));
+    }
+
+    #[tokio::test]
+    async fn test_nearby_command_type_and_where() {
+        let database = Arc::new(GeoDatabase::new());
+        let objects = [
+            (
+                "depot",
+                json!({"type": "Polygon", "coordinates": [[[116.400, 39.900], [116.401, 39.900], [116.401, 39.901], [116.400, 39.900]]]}),
+            ),
+            (
+                "slow",
+                json!({"type": "Feature", "properties": {"speed": 5}, "geometry": {"type": "Point", "coordinates": [116.401, 39.9]}}),
+            ),
+            (
+                "bare",
+                json!({"type": "Point", "coordinates": [116.402, 39.9]}),
+            ),
+            (
+                "fast",
+                json!({"type": "Feature", "properties": {"speed": 60}, "geometry": {"type": "Point", "coordinates": [116.45, 39.9]}}),
+            ),
+        ];
+        for (id, geojson) in objects {
+            database
+                .set("fleet", id, &geojson.to_string())
+                .await
+                .unwrap();
+        }
+
+        let cmd = NearbyCommand::new(Arc::clone(&database));
+        let nearby = |extra: &[&str]| {
+            let mut list = vec!["fleet", "POINT", "116.400", "39.900", "LIMIT", "1", "IDS"];
+            list.extend_from_slice(extra);
+            args(&list)
+        };
+
+        // 最近的是面，TYPE point 时跳过它
+        let result = cmd.execute(&nearby(&[])).await.unwrap();
+        assert!(result.contains("depot"));
+        let result = cmd.execute(&nearby(&["TYPE", "point"])).await.unwrap();
+        assert!(result.starts_with("*1\r\n") && result.contains("slow"));
+
+        // 较近的慢车和没有 properties 的点都不满足 WHERE，继续查找直到找到 fast
+        let result = cmd
+            .execute(&nearby(&["TYPE", "point", "WHERE", "speed", "50", "+inf"]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("*1\r\n") && result.contains("fast"));
+
+        let result = cmd.execute(&nearby(&["TYPE", "linestring"])).await.unwrap();
+        assert_eq!(result, RespResponse::array(None));
+    }
+
+    #[tokio::test]
+    async fn test_nearby_command_distance_metric() {
+        // 平面图上的房间，坐标单位为米
+        let database = Arc::new(GeoDatabase::new());
+        for (id, x, y) in [
+            ("lobby", 10.0, 10.0),
+            ("office", 13.0, 14.0),
+            ("lab", 300.0, 10.0),
+        ] {
+            let point = json!({"type": "Point", "coordinates": [x, y]}).to_string();
+            database.set("floor", id, &point).await.unwrap();
+        }
+
+        let cmd = NearbyCommand::new(Arc::clone(&database));
+        let values = args(&[
+            "floor",
+            "POINT",
+            "10",
+            "10",
+            "RADIUS",
+            "5",
+            "IDS",
+            "DISTANCE",
+            "euclidean",
+        ]);
+        let result = cmd.execute(&values).await.unwrap();
+        // RADIUS 与距离都是坐标单位：office 恰好相距 5
+        assert!(result.starts_with("*2\r\n"));
+        assert!(result.contains("lobby") && result.contains("office"));
+        assert!(result.contains("5.000000"));
+
+        // 查询点可以超出经纬度范围
+        let values = args(&[
+            "floor",
+            "POINT",
+            "290",
+            "10",
+            "LIMIT",
+            "1",
+            "IDS",
+            "DISTANCE",
+            "euclidean",
+        ]);
+        let result = cmd.execute(&values).await.unwrap();
+        assert!(result.contains("lab") && result.contains("10.000000"));
+
+        // 全局默认的度量，请求中的 DISTANCE 优先
+        let database = Arc::new(GeoDatabase::new().with_distance_metric(DistanceMetric::Euclidean));
+        for (id, lon, lat) in [("east", 1.5, 60.0), ("north", 0.0, 61.2)] {
+            let point = json!({"type": "Point", "coordinates": [lon, lat]}).to_string();
+            database.set("fleet", id, &point).await.unwrap();
+        }
+        let cmd = NearbyCommand::new(Arc::clone(&database));
+        let nearest = |extra: &[&str]| {
+            let mut list = vec!["fleet", "POINT", "0", "60", "LIMIT", "1", "IDS"];
+            list.extend_from_slice(extra);
+            args(&list)
+        };
+        let result = cmd.execute(&nearest(&[])).await.unwrap();
+        assert!(result.contains("north"));
+        let result = cmd
+            .execute(&nearest(&["DISTANCE", "haversine"]))
+            .await
+            .unwrap();
+        assert!(result.contains("east"));
+    }
+
+    #[tokio::test]
+    async fn test_nearby_positional_radius_limit_and_count() {
+        let database = Arc::new(GeoDatabase::new());
+        // 每隔约 111 米一辆出租车
+        for i in 0..10 {
+            let point = json!({"type": "Point", "coordinates": [116.4, 39.9 + i as f64 * 0.001]})
+                .to_string();
+            database
+                .set("taxis", &format!("taxi{}", i), &point)
+                .await
+                .unwrap();
+        }
+
+        let cmd = NearbyCommand::new(Arc::clone(&database));
+        let run = |options: &[&str]| {
+            let mut list = vec!["taxis", "POINT", "116.4", "39.9"];
+            list.extend_from_slice(options);
+            let values = args(&list);
+            let cmd = &cmd;
+            async move { cmd.execute(&values).await.unwrap() }
+        };
+
+        // 500 米内的 taxi0..taxi4
+        assert!(run(&["500"]).await.starts_with("*5\r\n"));
+        assert_eq!(run(&["500", "COUNT"]).await, RespResponse::integer(5));
+        assert_eq!(
+            run(&["500", "LIMIT", "2"]).await,
+            run(&["RADIUS", "500", "COUNT", "2"]).await
+        );
+        assert_eq!(run(&["500", "LIMIT", "2", "COUNT"]).await, ":2\r\n");
+        assert_eq!(run(&["LIMIT", "3", "COUNT"]).await, ":3\r\n");
+        assert_eq!(run(&["50", "COUNT"]).await, ":1\r\n");
+
+        assert!(run(&["500", "RADIUS", "100"])
+            .await
+            .contains("duplicate RADIUS"));
+        assert!(run(&["COUNT"])
+            .await
+            .contains("at least one of LIMIT or RADIUS"));
+    }
+
+    #[tokio::test]
+    async fn test_nearby_output_selectors() {
+        let database = Arc::new(GeoDatabase::new());
+        for i in 0..3 {
+            let point = json!({"type": "Point", "coordinates": [116.4, 39.9 + i as f64 * 0.001]})
+                .to_string();
+            database
+                .set("taxis", &format!("taxi{}", i), &point)
+                .await
+                .unwrap();
+        }
+
+        let cmd = NearbyCommand::new(Arc::clone(&database));
+        let run = |options: &[&str]| {
+            let mut list = vec!["taxis", "POINT", "116.4", "39.9", "LIMIT", "2"];
+            list.extend_from_slice(options);
+            let values = args(&list);
+            let cmd = &cmd;
+            async move { cmd.execute(&values).await.unwrap() }
+        };
+
+        assert_eq!(
+            run(&["IDS"]).await,
+            "*2\r\n*2\r\n$5\r\ntaxi0\r\n$4\r\n0.00\r\n*2\r\n$5\r\ntaxi1\r\n$6\r\n111.19\r\n"
+        );
+        // POINTS 与 GET POINT 相同，纬度在前
+        assert!(run(&["POINTS", "PRECISION", "3"]).await.starts_with(
+            "*2\r\n*3\r\n$5\r\ntaxi0\r\n*2\r\n$6\r\n39.900\r\n$7\r\n116.400\r\n$4\r\n0.00\r\n"
+        ));
+        assert!(run(&["HASHES", "5"]).await.contains("$5\r\nwx4fb\r\n"));
+        assert_eq!(run(&["OBJECTS"]).await, run(&[]).await);
+
+        assert!(run(&["IDS", "POINTS"])
+            .await
+            .starts_with("-ERR only one of OBJECTS, IDS, COUNT, POINTS, BOUNDS or HASHES"));
+        assert!(run(&["IDS", "FIELDS", "name"])
+            .await
+            .starts_with("-ERR FIELDS cannot be combined"));
+        assert!(run(&["HASHES", "13"])
+            .await
+            .starts_with("-ERR invalid HASHES precision"));
+    }
 }
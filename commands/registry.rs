@@ -1,37 +1,71 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::sync::Arc;
 
 use crate::protocol::parser::RespValue;
-use crate::storage::GeoDatabase;
+use crate::protocol::{ReplySink, RespResponse};
+use crate::storage::namespace::{self, Namespace};
+use crate::storage::{GeoDatabase, StatEvent};
 use crate::Result;
 
 use super::{
+    agg::AggCommand,
     basic::{HelloCommand, PingCommand, QuitCommand},
-    delete::DeleteCommand,
+    cluster::ClusterCommand,
+    debug::DebugCommand,
+    delete::{DeleteCommand, PdelCommand},
     drop::DropCommand,
+    expire::{ExpireCommand, PersistCommand, TtlCommand},
+    features::FeaturesCommand,
+    geomop::GeomopCommand,
     get::GetCommand,
+    hull::HullCommand,
+    info::{InfoCommand, ServerCommand},
     intersects::IntersectsCommand,
+    json::{JdelCommand, JgetCommand, JsetCommand},
     keys::KeysCommand,
+    memory::MemoryCommand,
     nearby::NearbyCommand,
+    render::RenderCommand,
+    save::{
+        AofMd5Command, BgrewriteaofCommand, BgsaveCommand, ExportCommand, ImportCommand,
+        SaveCommand,
+    },
+    scan::ScanCommand,
     set::SetCommand,
+    snap::SnapCommand,
+    stats::StatsCommand,
     CommandType,
 };
 
+/// 命令别名及其对应的命令，兼容 Tile38 的命令名
+const ALIASES: &[(&str, &str)] = &[("DEL", "DELETE"), ("AOFSHRINK", "BGREWRITEAOF")];
+
 /// 命令注册表，管理所有可用的命令
 pub struct CommandRegistry {
     commands: HashMap<String, CommandType>,
+    database: Arc<GeoDatabase>,
+    /// 命令中的 collection 名称属于这个命名空间
+    namespace: Namespace,
 }
 
 impl CommandRegistry {
-    /// 创建新的命令注册表
+    /// 创建新的命令注册表，使用默认命名空间
     pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self::with_namespace(database, Namespace::default())
+    }
+
+    /// 创建在指定命名空间（SELECT n）中执行命令的注册表
+    pub fn with_namespace(database: Arc<GeoDatabase>, namespace: Namespace) -> Self {
         let mut registry = Self {
             commands: HashMap::new(),
+            database: Arc::clone(&database),
+            namespace,
         };
 
         // 注册基础命令
         registry.register(CommandType::Ping(PingCommand));
-        registry.register(CommandType::Hello(HelloCommand));
+        registry.register(CommandType::Hello(HelloCommand::new(Arc::clone(&database))));
         registry.register(CommandType::Quit(QuitCommand));
 
         // 注册存储命令
@@ -40,6 +74,17 @@ impl CommandRegistry {
         registry.register(CommandType::Delete(DeleteCommand::new(Arc::clone(
             &database,
         ))));
+        registry.register(CommandType::Pdel(PdelCommand::new(Arc::clone(&database))));
+        registry.register(CommandType::Expire(ExpireCommand::new(Arc::clone(
+            &database,
+        ))));
+        registry.register(CommandType::Ttl(TtlCommand::new(Arc::clone(&database))));
+        registry.register(CommandType::Persist(PersistCommand::new(Arc::clone(
+            &database,
+        ))));
+        registry.register(CommandType::Jset(JsetCommand::new(Arc::clone(&database))));
+        registry.register(CommandType::Jget(JgetCommand::new(Arc::clone(&database))));
+        registry.register(CommandType::Jdel(JdelCommand::new(Arc::clone(&database))));
 
         // 注册空间查询命令
         registry.register(CommandType::Intersects(IntersectsCommand::new(Arc::clone(
@@ -48,10 +93,55 @@ impl CommandRegistry {
         registry.register(CommandType::Nearby(NearbyCommand::new(Arc::clone(
             &database,
         ))));
+        registry.register(CommandType::Agg(AggCommand::new(Arc::clone(&database))));
+        registry.register(CommandType::Hull(HullCommand::new(Arc::clone(&database))));
+        registry.register(CommandType::Cluster(ClusterCommand::new(Arc::clone(
+            &database,
+        ))));
+        registry.register(CommandType::Snap(SnapCommand::new(Arc::clone(&database))));
+        registry.register(CommandType::Geomop(GeomopCommand::new(Arc::clone(
+            &database,
+        ))));
+        registry.register(CommandType::Render(RenderCommand::new(Arc::clone(
+            &database,
+        ))));
+        registry.register(CommandType::Features(FeaturesCommand::new(Arc::clone(
+            &database,
+        ))));
 
         // 注册管理命令
-        registry.register(CommandType::Drop(DropCommand::new(Arc::clone(&database))));
-        registry.register(CommandType::Keys(KeysCommand::new(Arc::clone(&database))));
+        registry.register(CommandType::Drop(
+            DropCommand::new(Arc::clone(&database)).with_namespace(namespace),
+        ));
+        registry.register(CommandType::Keys(
+            KeysCommand::new(Arc::clone(&database)).with_namespace(namespace),
+        ));
+        registry.register(CommandType::Scan(ScanCommand::new(Arc::clone(&database))));
+        registry.register(CommandType::Info(InfoCommand::new(Arc::clone(&database))));
+        registry.register(CommandType::Server(ServerCommand::new(Arc::clone(
+            &database,
+        ))));
+        registry.register(CommandType::Memory(MemoryCommand::new(Arc::clone(
+            &database,
+        ))));
+        registry.register(CommandType::Stats(StatsCommand::new(Arc::clone(&database))));
+        registry.register(CommandType::Save(SaveCommand::new(Arc::clone(&database))));
+        registry.register(CommandType::Bgsave(BgsaveCommand::new(Arc::clone(
+            &database,
+        ))));
+        registry.register(CommandType::Bgrewriteaof(BgrewriteaofCommand::new(
+            Arc::clone(&database),
+        )));
+        registry.register(CommandType::Aofmd5(AofMd5Command::new(Arc::clone(
+            &database,
+        ))));
+        registry.register(CommandType::Export(ExportCommand::new(Arc::clone(
+            &database,
+        ))));
+        registry.register(CommandType::Import(ImportCommand::new(Arc::clone(
+            &database,
+        ))));
+        registry.register(CommandType::Debug(DebugCommand::new(Arc::clone(&database))));
 
         registry
     }
@@ -62,30 +152,206 @@ impl CommandRegistry {
         self.commands.insert(name, command);
     }
 
+    pub fn namespace(&self) -> Namespace {
+        self.namespace
+    }
+
     /// 执行指定的命令
+    ///
+    /// 启动加载期间读写数据的命令直接返回 `-LOADING ...`；
+    /// 磁盘空间不足时写命令直接返回 `-OOM DISK ...`，读命令照常执行。
+    /// AOF 启用组提交时，写命令等待记录落盘后才返回回复
     pub async fn execute(&self, command_name: &str, args: &[RespValue]) -> Result<String> {
-        let name = command_name.to_uppercase();
-        match self.commands.get(&name) {
-            Some(command) => command.execute(args).await,
-            None => Ok(format!("-ERR unknown command '{}'\r\n", command_name)),
+        let _shared = self.database.shared().await;
+        let reply = self.run(command_name, args).await?;
+        if self.is_write(command_name) {
+            self.database.wait_durable().await?;
+        }
+        Ok(reply)
+    }
+
+    /// 依次执行 MULTI 之后排队的命令（EXEC），返回每个命令的回复
+    ///
+    /// 执行期间其他连接的命令等待，看不到事务执行到一半的数据。
+    /// 某个命令失败不会撤销之前的命令，它的错误回复和其他回复一起返回。
+    /// 组提交时所有命令执行完后等待一次落盘
+    pub async fn execute_transaction(
+        &self,
+        commands: &[(String, Vec<RespValue>)],
+    ) -> Result<Vec<String>> {
+        let _exclusive = self.database.exclusive().await;
+        let mut replies = Vec::with_capacity(commands.len());
+        for (command_name, args) in commands {
+            let reply = match self.run(command_name, args).await {
+                Ok(reply) => reply,
+                Err(e) => RespResponse::error(&format!("ERR {}", e)),
+            };
+            replies.push(reply);
+        }
+        if commands
+            .iter()
+            .any(|(command_name, _)| self.is_write(command_name))
+        {
+            self.database.wait_durable().await?;
+        }
+        Ok(replies)
+    }
+
+    /// 执行命令，回复写入 sink，结果较大的命令（见 [`streams`](Self::streams)）边查询边写出
+    ///
+    /// sink 是有界通道，客户端不读时写入会一直等待，所以等待 sink 时不能持有全局锁，
+    /// 否则会挡住 EXEC：分块写出的命令自己按批加锁，并在写入 sink 之前释放；
+    /// 其他命令与 [`execute`](Self::execute) 相同，生成完整回复、释放锁之后再写入
+    pub async fn execute_into(
+        &self,
+        command_name: &str,
+        args: &[RespValue],
+        sink: &mut ReplySink,
+    ) -> Result<()> {
+        if self.streams(command_name) && !self.is_write(command_name) {
+            return self.run_into(command_name, args, sink).await;
+        }
+        let reply = self.execute(command_name, args).await?;
+        sink.write(&reply).await?;
+        sink.flush().await
+    }
+
+    async fn run(&self, command_name: &str, args: &[RespValue]) -> Result<String> {
+        let mut sink = ReplySink::buffer();
+        self.run_into(command_name, args, &mut sink).await?;
+        Ok(sink.into_string())
+    }
+
+    async fn run_into(
+        &self,
+        command_name: &str,
+        args: &[RespValue],
+        sink: &mut ReplySink,
+    ) -> Result<()> {
+        let Some(command) = self.lookup(command_name) else {
+            return sink
+                .write(&format!("-ERR unknown command '{}'\r\n", command_name))
+                .await;
+        };
+        self.database.record_stat(StatEvent::Command);
+        if !command.serves_while_loading() {
+            if let Err(e) = self.database.check_loaded() {
+                return sink
+                    .write(&RespResponse::error(&format!("{} {}", e.code.prefix(), e)))
+                    .await;
+            }
+        }
+        if command.is_write() {
+            if let Err(e) = self.database.check_disk_space() {
+                return sink
+                    .write(&RespResponse::error(&format!("{} {}", e.code.prefix(), e)))
+                    .await;
+            }
         }
+        let args = match self.scope_args(command.name(), args) {
+            Ok(args) => args,
+            Err(message) => return sink.write(&RespResponse::error(&message)).await,
+        };
+        command.execute_into(&args, sink).await
+    }
+
+    /// 把参数中的 collection 名称换成当前命名空间中的存储名称
+    ///
+    /// 使用保留前缀（`@n:`）的名称在任何命名空间中都会被拒绝。
+    /// KEYS 和 DROP 的参数可以是模式，由命令自己按命名空间过滤
+    pub fn scope_args<'a>(
+        &self,
+        command_name: &str,
+        args: &'a [RespValue],
+    ) -> std::result::Result<Cow<'a, [RespValue]>, String> {
+        let positions = collection_positions(command_name, args);
+        for name in args[positions.clone()].iter().filter_map(RespValue::as_str) {
+            if namespace::is_reserved(name) {
+                return Err(format!(
+                    "ERR collection name '{}' is reserved, names starting with '@<n>:' belong to SELECT namespaces",
+                    name
+                ));
+            }
+        }
+        if self.namespace == Namespace::default() || positions.is_empty() {
+            return Ok(Cow::Borrowed(args));
+        }
+        let mut scoped = args.to_vec();
+        for arg in &mut scoped[positions] {
+            if let Some(name) = arg.as_str() {
+                *arg = RespValue::bulk(self.namespace.scope(name));
+            }
+        }
+        Ok(Cow::Owned(scoped))
     }
 
-    /// 获取所有注册的命令名称
+    /// 指定的命令是否会修改数据，未知命令返回 false
+    pub fn is_write(&self, command_name: &str) -> bool {
+        self.lookup(command_name)
+            .is_some_and(|command| command.is_write())
+    }
+
+    /// 回复是否逐个结果写出，这些命令的回复值得分块写到 socket
+    pub fn streams(&self, command_name: &str) -> bool {
+        self.lookup(command_name)
+            .is_some_and(|command| command.streams())
+    }
+
+    /// 获取所有注册的命令名称，包括别名
     pub fn command_names(&self) -> Vec<&str> {
-        self.commands.keys().map(|s| s.as_str()).collect()
+        self.commands
+            .keys()
+            .map(|s| s.as_str())
+            .chain(ALIASES.iter().map(|(alias, _)| *alias))
+            .collect()
     }
 
     /// 检查命令是否存在
     pub fn has_command(&self, command_name: &str) -> bool {
+        self.lookup(command_name).is_some()
+    }
+
+    /// 按名称（不区分大小写）查找命令，别名解析为对应的命令
+    fn lookup(&self, command_name: &str) -> Option<&CommandType> {
         let name = command_name.to_uppercase();
-        self.commands.contains_key(&name)
+        let name = ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == name)
+            .map_or(name.as_str(), |(_, command)| command);
+        self.commands.get(name)
     }
 }
 
+/// 命令参数中 collection 名称的位置
+fn collection_positions(command_name: &str, args: &[RespValue]) -> std::ops::Range<usize> {
+    let is = |i: usize, word: &str| {
+        args.get(i)
+            .and_then(RespValue::as_str)
+            .is_some_and(|s| s.eq_ignore_ascii_case(word))
+    };
+    let start = match command_name.to_uppercase().as_str() {
+        "SET" | "GET" | "DELETE" | "DEL" | "PDEL" | "JSET" | "JGET" | "JDEL" | "EXPIRE" | "TTL"
+        | "PERSIST" | "INTERSECTS" | "NEARBY" | "SCAN" | "HULL" | "AGG" | "SNAP" | "CLUSTER"
+        | "EXPORT" | "IMPORT" | "RENDER" => 0,
+        "GEOMOP" => 1,
+        "MEMORY" if is(0, "USAGE") => 1,
+        "DEBUG" if is(0, "VALIDATE") => 1,
+        // STATS collection [collection ...]
+        "STATS" if !is(0, "HISTORY") => return 0..args.len(),
+        // SETCHAN name [WEBHOOK url] NEARBY|INTERSECTS collection ...
+        "SETCHAN" => match (1..args.len()).find(|&i| is(i, "NEARBY") || is(i, "INTERSECTS")) {
+            Some(i) => i + 1,
+            None => return 0..0,
+        },
+        _ => return 0..0,
+    };
+    start..(start + 1).min(args.len().max(start))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::commands::args;
 
     #[tokio::test]
     async fn test_command_registry_basic() {
@@ -100,6 +366,15 @@ mod tests {
 
         // 测试不存在的命令
         assert!(!registry.has_command("UNKNOWN"));
+
+        // 写命令
+        assert!(registry.is_write("set"));
+        assert!(registry.is_write("DELETE"));
+        assert!(registry.is_write("del"));
+        assert!(registry.is_write("PDEL"));
+        assert!(registry.is_write("DROP"));
+        assert!(!registry.is_write("INTERSECTS"));
+        assert!(!registry.is_write("UNKNOWN"));
     }
 
     #[tokio::test]
@@ -114,6 +389,90 @@ mod tests {
         // 测试未知命令
         let result = registry.execute("UNKNOWN", &[]).await.unwrap();
         assert!(result.contains("unknown command"));
+
+        // DEL 是 DELETE 的别名
+        let point = r#"{"type":"Point","coordinates":[1.0,2.0]}"#;
+        let set_args = [
+            RespValue::bulk("fleet"),
+            RespValue::bulk("truck1"),
+            RespValue::bulk(point),
+        ];
+        registry.execute("SET", &set_args).await.unwrap();
+        let del_args = [RespValue::bulk("fleet"), RespValue::bulk("truck1")];
+        let result = registry.execute("del", &del_args).await.unwrap();
+        assert_eq!(result, RespResponse::integer(1));
+    }
+
+    #[tokio::test]
+    async fn test_low_disk_refuses_writes_but_serves_reads() {
+        use crate::storage::{DiskConfig, DiskMonitor};
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        let free = Arc::new(AtomicU64::new(1 << 30));
+        let probe_free = Arc::clone(&free);
+        let monitor = DiskMonitor::new(DiskConfig::new("data".into(), 1 << 20))
+            .with_probe(Arc::new(move |_| Ok(probe_free.load(Ordering::Relaxed))));
+        let database = Arc::new(GeoDatabase::new().with_disk_monitor(monitor));
+        database.refresh_disk_space().unwrap();
+        let registry = CommandRegistry::new(Arc::clone(&database));
+
+        let set_args = |id: &str| {
+            vec![
+                RespValue::bulk("fleet"),
+                RespValue::bulk(id.to_string()),
+                RespValue::bulk(r#"{"type":"Point","coordinates":[1.0,2.0]}"#.to_string()),
+            ]
+        };
+        assert_eq!(
+            registry.execute("SET", &set_args("v1")).await.unwrap(),
+            "+OK\r\n"
+        );
+
+        free.store(1024, Ordering::Relaxed);
+        database.refresh_disk_space().unwrap();
+
+        let result = registry.execute("SET", &set_args("v2")).await.unwrap();
+        assert!(result.starts_with("-OOM DISK "), "{}", result);
+        let drop_args = vec![RespValue::bulk("fleet")];
+        let result = registry.execute("DROP", &drop_args).await.unwrap();
+        assert!(result.starts_with("-OOM DISK "), "{}", result);
+
+        // 读命令不受影响
+        let get_args = vec![RespValue::bulk("fleet"), RespValue::bulk("v1")];
+        let result = registry.execute("GET", &get_args).await.unwrap();
+        assert!(result.contains("Point"));
+        assert!(database.get("fleet", "v2").await.unwrap().is_none());
+
+        // 空间恢复后重新接受写入
+        free.store(1 << 30, Ordering::Relaxed);
+        database.refresh_disk_space().unwrap();
+        assert_eq!(
+            registry.execute("SET", &set_args("v2")).await.unwrap(),
+            "+OK\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_loading_refuses_data_commands() {
+        let database = Arc::new(GeoDatabase::new());
+        let registry = CommandRegistry::new(Arc::clone(&database));
+        let get_args = vec![RespValue::bulk("fleet"), RespValue::bulk("truck1")];
+
+        database.begin_loading();
+        let result = registry.execute("GET", &get_args).await.unwrap();
+        assert_eq!(
+            result,
+            "-LOADING Spatio is loading the dataset in memory\r\n"
+        );
+        let result = registry.execute("INTERSECTS", &get_args).await.unwrap();
+        assert!(result.starts_with("-LOADING "), "{}", result);
+        // 不读写数据的命令照常执行
+        assert_eq!(registry.execute("PING", &[]).await.unwrap(), "+PONG\r\n");
+        let info = registry.execute("INFO", &[]).await.unwrap();
+        assert!(info.contains("loading:1\r\n"));
+
+        database.finish_loading();
+        assert_eq!(registry.execute("GET", &get_args).await.unwrap(), "$-1\r\n");
     }
 
     #[test]
@@ -126,6 +485,7 @@ mod tests {
         assert!(names.contains(&"SET"));
         assert!(names.contains(&"GET"));
         assert!(names.contains(&"NEARBY"));
+        assert!(names.contains(&"AOFSHRINK"));
         assert!(names.len() >= 6); // 至少有 6 个命令
     }
 
@@ -143,34 +503,34 @@ mod tests {
         let point3 = json!({"type": "Point", "coordinates": [116.3, 39.8]});
 
         let set_args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("v1".to_string())),
-            RespValue::BulkString(Some(point1.to_string())),
+            RespValue::bulk("fleet"),
+            RespValue::bulk("v1"),
+            RespValue::bulk(point1.to_string()),
         ];
         registry.execute("SET", &set_args).await.unwrap();
 
         let set_args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("v2".to_string())),
-            RespValue::BulkString(Some(point2.to_string())),
+            RespValue::bulk("fleet"),
+            RespValue::bulk("v2"),
+            RespValue::bulk(point2.to_string()),
         ];
         registry.execute("SET", &set_args).await.unwrap();
 
         let set_args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("v3".to_string())),
-            RespValue::BulkString(Some(point3.to_string())),
+            RespValue::bulk("fleet"),
+            RespValue::bulk("v3"),
+            RespValue::bulk(point3.to_string()),
         ];
         registry.execute("SET", &set_args).await.unwrap();
 
         // 2. 执行 NEARBY 查询
         let nearby_args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("POINT".to_string())),
-            RespValue::BulkString(Some("116.4".to_string())),
-            RespValue::BulkString(Some("39.9".to_string())),
-            RespValue::BulkString(Some("COUNT".to_string())),
-            RespValue::BulkString(Some("2".to_string())),
+            RespValue::bulk("fleet"),
+            RespValue::bulk("POINT"),
+            RespValue::bulk("116.4"),
+            RespValue::bulk("39.9"),
+            RespValue::bulk("COUNT"),
+            RespValue::bulk("2"),
         ];
 
         let result = registry.execute("NEARBY", &nearby_args).await.unwrap();
@@ -201,10 +561,7 @@ mod tests {
         assert!(registry.has_command("nearby")); // 大小写不敏感
 
         // 测试参数错误的情况
-        let invalid_args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("POINT".to_string())),
-        ];
+        let invalid_args = vec![RespValue::bulk("fleet"), RespValue::bulk("POINT")];
 
         let result = registry.execute("NEARBY", &invalid_args).await.unwrap();
         assert!(result.contains("ERR"));
@@ -225,34 +582,34 @@ mod tests {
         let point3 = json!({"type": "Point", "coordinates": [116.015, 39.0]}); // ~1665m
 
         let set_args1 = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("v1".to_string())),
-            RespValue::BulkString(Some(point1.to_string())),
+            RespValue::bulk("fleet"),
+            RespValue::bulk("v1"),
+            RespValue::bulk(point1.to_string()),
         ];
         registry.execute("SET", &set_args1).await.unwrap();
 
         let set_args2 = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("v2".to_string())),
-            RespValue::BulkString(Some(point2.to_string())),
+            RespValue::bulk("fleet"),
+            RespValue::bulk("v2"),
+            RespValue::bulk(point2.to_string()),
         ];
         registry.execute("SET", &set_args2).await.unwrap();
 
         let set_args3 = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("v3".to_string())),
-            RespValue::BulkString(Some(point3.to_string())),
+            RespValue::bulk("fleet"),
+            RespValue::bulk("v3"),
+            RespValue::bulk(point3.to_string()),
         ];
         registry.execute("SET", &set_args3).await.unwrap();
 
         // 测试只使用 RADIUS
         let nearby_args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("POINT".to_string())),
-            RespValue::BulkString(Some("116.0".to_string())),
-            RespValue::BulkString(Some("39.0".to_string())),
-            RespValue::BulkString(Some("RADIUS".to_string())),
-            RespValue::BulkString(Some("1000".to_string())),
+            RespValue::bulk("fleet"),
+            RespValue::bulk("POINT"),
+            RespValue::bulk("116.0"),
+            RespValue::bulk("39.0"),
+            RespValue::bulk("RADIUS"),
+            RespValue::bulk("1000"),
         ];
 
         let result = registry.execute("NEARBY", &nearby_args).await.unwrap();
@@ -263,14 +620,14 @@ mod tests {
 
         // 测试 COUNT + RADIUS 组合
         let nearby_args2 = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("POINT".to_string())),
-            RespValue::BulkString(Some("116.0".to_string())),
-            RespValue::BulkString(Some("39.0".to_string())),
-            RespValue::BulkString(Some("COUNT".to_string())),
-            RespValue::BulkString(Some("1".to_string())),
-            RespValue::BulkString(Some("RADIUS".to_string())),
-            RespValue::BulkString(Some("2000".to_string())),
+            RespValue::bulk("fleet"),
+            RespValue::bulk("POINT"),
+            RespValue::bulk("116.0"),
+            RespValue::bulk("39.0"),
+            RespValue::bulk("COUNT"),
+            RespValue::bulk("1"),
+            RespValue::bulk("RADIUS"),
+            RespValue::bulk("2000"),
         ];
 
         let result2 = registry.execute("NEARBY", &nearby_args2).await.unwrap();
@@ -279,4 +636,149 @@ mod tests {
         // 应该只返回 1 个结果（最近的 v1）
         assert!(result2.starts_with("*1"));
     }
+
+    #[tokio::test]
+    async fn test_namespaces_do_not_collide() {
+        let database = Arc::new(GeoDatabase::new());
+        let default = CommandRegistry::new(Arc::clone(&database));
+        let tenant = CommandRegistry::with_namespace(Arc::clone(&database), Namespace::new(2));
+        let point = r#"{"type":"Point","coordinates":[1.0,2.0]}"#;
+
+        default
+            .execute("SET", &args(&["fleet", "truck1", point]))
+            .await
+            .unwrap();
+        tenant
+            .execute("SET", &args(&["fleet", "truck2", point]))
+            .await
+            .unwrap();
+        tenant
+            .execute("SET", &args(&["gps", "p1", point]))
+            .await
+            .unwrap();
+
+        // 同名 collection 在两个命名空间中互不影响
+        let result = tenant
+            .execute("GET", &args(&["fleet", "truck1"]))
+            .await
+            .unwrap();
+        assert_eq!(result, "$-1\r\n");
+        assert!(database.get("@2:fleet", "truck2").await.unwrap().is_some());
+        assert_eq!(
+            default.execute("KEYS", &[]).await.unwrap(),
+            "*1\r\n$5\r\nfleet\r\n"
+        );
+        assert_eq!(
+            tenant.execute("KEYS", &args(&["*"])).await.unwrap(),
+            "*2\r\n$5\r\nfleet\r\n$3\r\ngps\r\n"
+        );
+
+        // 保留前缀不能直接访问其他命名空间
+        let result = default
+            .execute("GET", &args(&["@2:fleet", "truck2"]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("-ERR collection name '@2:fleet' is reserved"));
+        let result = default.execute("DROP", &args(&["@2:*"])).await.unwrap();
+        assert!(result.contains("is reserved"));
+
+        // DROP 模式只匹配当前命名空间
+        assert_eq!(
+            tenant.execute("DROP", &args(&["*"])).await.unwrap(),
+            "*2\r\n*2\r\n$5\r\nfleet\r\n:1\r\n*2\r\n$3\r\ngps\r\n:1\r\n"
+        );
+        assert_eq!(database.collection_names().await, vec!["fleet"]);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_excludes_other_commands() {
+        let database = Arc::new(GeoDatabase::new());
+        let registry = Arc::new(CommandRegistry::new(Arc::clone(&database)));
+        let point = r#"{"type":"Point","coordinates":[1.0,2.0]}"#;
+        let commands: Vec<(String, Vec<RespValue>)> = ["truck1", "truck2"]
+            .iter()
+            .map(|id| {
+                let args = vec![
+                    RespValue::bulk("fleet"),
+                    RespValue::bulk(id.to_string()),
+                    RespValue::bulk(point),
+                ];
+                ("SET".to_string(), args)
+            })
+            .chain([("NOPE".to_string(), Vec::new())])
+            .collect();
+        let replies = registry.execute_transaction(&commands).await.unwrap();
+        assert_eq!(replies[..2], ["+OK\r\n", "+OK\r\n"]);
+        assert!(replies[2].starts_with("-ERR unknown command"));
+
+        // 事务持有期间其他命令等待
+        let exclusive = database.exclusive().await;
+        let pending = tokio::spawn({
+            let registry = Arc::clone(&registry);
+            async move {
+                let args = [RespValue::bulk("fleet")];
+                registry.execute("DROP", &args).await
+            }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!pending.is_finished());
+        assert_eq!(database.collection_names().await, vec!["fleet"]);
+        drop(exclusive);
+        assert_eq!(pending.await.unwrap().unwrap(), ":2\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_stalled_stream_does_not_block_exec() {
+        let database = Arc::new(GeoDatabase::new());
+        let registry = Arc::new(CommandRegistry::new(Arc::clone(&database)));
+        // 回复远大于通道能缓存的块数
+        let padding = "x".repeat(1024);
+        for i in 0..2000 {
+            let feature = format!(
+                r#"{{"type":"Feature","properties":{{"pad":"{}"}},"geometry":{{"type":"Point","coordinates":[{},1.0]}}}}"#,
+                padding,
+                i as f64 * 0.001
+            );
+            database
+                .set("fleet", &format!("truck{}", i), &feature)
+                .await
+                .unwrap();
+        }
+
+        // 客户端不读取回复，INTERSECTS 停在写入 sink 处
+        let (mut sink, chunks) = ReplySink::channel();
+        let streaming = tokio::spawn({
+            let registry = Arc::clone(&registry);
+            async move {
+                let args: Vec<RespValue> = ["fleet", "BOUNDS", "-1", "0", "3", "2"]
+                    .into_iter()
+                    .map(RespValue::bulk)
+                    .collect();
+                registry.execute_into("INTERSECTS", &args, &mut sink).await
+            }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!streaming.is_finished());
+
+        let commands = vec![(
+            "SET".to_string(),
+            vec![
+                RespValue::bulk("fleet"),
+                RespValue::bulk("truck-new"),
+                RespValue::bulk(r#"{"type":"Point","coordinates":[1.0,1.0]}"#),
+            ],
+        )];
+        let replies = tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            registry.execute_transaction(&commands),
+        )
+        .await
+        .expect("EXEC waited for a stalled reader")
+        .unwrap();
+        assert_eq!(replies, ["+OK\r\n"]);
+
+        // 连接不再读取时命令出错结束
+        drop(chunks);
+        assert!(streaming.await.unwrap().is_err());
+    }
 }
@@ -0,0 +1,180 @@
+use crate::commands::args::ArgumentParser;
+use crate::commands::Command;
+use crate::protocol::{parser::RespValue, RespResponse};
+use crate::storage::geometry_utils::{
+    clip_to_rect, geojson_properties, geometry_to_geojson, round_coordinates,
+};
+use crate::storage::GeoDatabase;
+use crate::Result;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// RENDER 命令：返回与瓦片相交的对象，几何体裁剪到瓦片范围内，供 Leaflet/MapLibre 直接渲染
+///
+/// 语法: RENDER collection TILE x y z|QUADKEY key|BOUNDS minlat minlon maxlat maxlon
+///
+/// 回复是一个 GeoJSON FeatureCollection：每个对象一个 Feature，`id` 为对象 id，
+/// properties 取存储的 Feature 的 properties。只接触瓦片边界的线和面被丢弃
+pub struct RenderCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl RenderCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+impl Command for RenderCommand {
+    fn name(&self) -> &'static str {
+        "RENDER"
+    }
+
+    fn execute(
+        &self,
+        args: &[RespValue],
+    ) -> impl std::future::Future<Output = Result<String>> + Send {
+        let database = Arc::clone(&self.database);
+
+        // 同步解析参数
+        let parse_result = ArgumentParser::new(args, "RENDER").parse_render_args();
+
+        async move {
+            let parsed_args = match parse_result {
+                Ok(args) => args,
+                Err(err_msg) => {
+                    return Ok(RespResponse::error(&err_msg));
+                }
+            };
+
+            let items = match database
+                .intersects(&parsed_args.collection_id, &parsed_args.area, 0, false)
+                .await
+            {
+                Ok(items) => items,
+                Err(e) => return Ok(RespResponse::command_error("render failed", e.as_ref())),
+            };
+
+            let features: Vec<Value> = items
+                .into_iter()
+                .filter_map(|item| {
+                    let geometry = clip_to_rect(&item.geometry, &parsed_args.bounds)?;
+                    let properties = geojson_properties(&item.geojson).unwrap_or_default();
+                    Some(json!({
+                        "type": "Feature",
+                        "id": item.id,
+                        "properties": properties,
+                        "geometry": geometry_to_geojson(&geometry),
+                    }))
+                })
+                .collect();
+            let collection = json!({"type": "FeatureCollection", "features": features}).to_string();
+
+            // 与 GET 相同，按全局设置四舍五入坐标，减小瓦片的体积
+            let collection = match database.output_precision() {
+                Some(decimals) => round_coordinates(&collection, decimals),
+                None => collection,
+            };
+            Ok(RespResponse::bulk_string(Some(&collection)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::args;
+
+    /// 从回复中取出 FeatureCollection
+    fn collection(reply: &str) -> Value {
+        let body = reply.split_once("\r\n").unwrap().1.trim_end();
+        serde_json::from_str(body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_render_command() {
+        let database = Arc::new(GeoDatabase::new());
+        // 1/1/1 瓦片：经度 [0, 180]，纬度 [-85.05, 0]
+        let road = json!({
+            "type": "Feature",
+            "properties": {"name": "ring road"},
+            "geometry": {"type": "LineString", "coordinates": [[-10.0, -10.0], [10.0, -10.0]]}
+        });
+        let zone = json!({
+            "type": "Polygon",
+            "coordinates": [[[-10.0, -20.0], [10.0, -20.0], [10.0, 20.0], [-10.0, 20.0], [-10.0, -20.0]]]
+        });
+        let far = json!({"type": "Point", "coordinates": [-100.0, 40.0]});
+        for (id, geojson) in [("road", road), ("zone", zone), ("far", far)] {
+            database.set("map", id, &geojson.to_string()).await.unwrap();
+        }
+        let cmd = RenderCommand::new(Arc::clone(&database));
+
+        let reply = cmd
+            .execute(&args(&["map", "TILE", "1", "1", "1"]))
+            .await
+            .unwrap();
+        let tile = collection(&reply);
+        assert_eq!(tile["type"], "FeatureCollection");
+        let mut features = tile["features"].as_array().unwrap().clone();
+        features.sort_by_key(|feature| feature["id"].as_str().unwrap().to_string());
+        assert_eq!(features.len(), 2);
+
+        assert_eq!(features[0]["id"], "road");
+        assert_eq!(features[0]["properties"], json!({"name": "ring road"}));
+        let coordinates = features[0]["geometry"]["coordinates"].as_array().unwrap();
+        assert!(coordinates
+            .iter()
+            .all(|coord| coord[0].as_f64().unwrap() >= 0.0));
+
+        assert_eq!(features[1]["id"], "zone");
+        assert_eq!(features[1]["geometry"]["type"], "Polygon");
+        assert_eq!(features[1]["properties"], json!({}));
+        // 布尔运算的坐标精度约为 1e-7 度
+        let ring = features[1]["geometry"]["coordinates"][0]
+            .as_array()
+            .unwrap();
+        assert!(ring.iter().all(|coord| {
+            coord[0].as_f64().unwrap() >= 0.0 && coord[1].as_f64().unwrap() <= 1e-6
+        }));
+
+        // BOUNDS 与 TILE 一样裁剪
+        let reply = cmd
+            .execute(&args(&["map", "BOUNDS", "30", "-110", "50", "-90"]))
+            .await
+            .unwrap();
+        let features = collection(&reply)["features"].clone();
+        assert_eq!(features.as_array().unwrap().len(), 1);
+        assert_eq!(features[0]["id"], "far");
+
+        // 不存在的 collection 返回空的 FeatureCollection
+        let reply = cmd
+            .execute(&args(&["nothing", "QUADKEY", "0"]))
+            .await
+            .unwrap();
+        assert_eq!(collection(&reply)["features"], json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_render_command_errors() {
+        let cmd = RenderCommand::new(Arc::new(GeoDatabase::new()));
+
+        let reply = cmd.execute(&args(&["map", "TILE"])).await.unwrap();
+        assert!(reply.starts_with("-ERR wrong number of arguments"));
+        let reply = cmd
+            .execute(&args(&["map", "CIRCLE", "1", "2", "3"]))
+            .await
+            .unwrap();
+        assert!(reply.starts_with("-ERR unknown area 'CIRCLE'"));
+        let reply = cmd
+            .execute(&args(&["map", "TILE", "2", "0", "1"]))
+            .await
+            .unwrap();
+        assert!(reply.starts_with("-ERR invalid TILE"));
+        let reply = cmd
+            .execute(&args(&["map", "TILE", "0", "0", "1", "LIMIT"]))
+            .await
+            .unwrap();
+        assert!(reply.starts_with("-ERR unexpected argument 'LIMIT'"));
+    }
+}
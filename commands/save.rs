@@ -0,0 +1,554 @@
+use crate::commands::args::ArgumentParser;
+use crate::commands::Command;
+use crate::protocol::{parser::RespValue, RespResponse};
+use crate::storage::GeoDatabase;
+use crate::Result;
+use std::sync::Arc;
+
+/// SAVE 命令
+///
+/// 语法: SAVE
+///
+/// 将所有 collection 写入快照文件，完成后返回 OK。
+/// 复制每个 collection 时只持有它的读锁，写文件期间其它命令照常执行
+pub struct SaveCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl SaveCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+impl Command for SaveCommand {
+    fn name(&self) -> &'static str {
+        "SAVE"
+    }
+
+    async fn execute(&self, args: &[RespValue]) -> Result<String> {
+        if let Err(err_msg) = ArgumentParser::new(args, "SAVE").check_arg_count(0) {
+            return Ok(RespResponse::error(&err_msg));
+        }
+
+        match self.database.save_snapshot().await {
+            Ok(_) => Ok(RespResponse::simple_string("OK")),
+            Err(e) => Ok(RespResponse::command_error(
+                "failed to save snapshot",
+                e.as_ref(),
+            )),
+        }
+    }
+}
+
+/// BGSAVE 命令
+///
+/// 语法: BGSAVE
+///
+/// 在后台保存快照并立即返回，结果通过 `INFO persistence` 的 `snapshot_*` 字段查看
+pub struct BgsaveCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl BgsaveCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+impl Command for BgsaveCommand {
+    fn name(&self) -> &'static str {
+        "BGSAVE"
+    }
+
+    async fn execute(&self, args: &[RespValue]) -> Result<String> {
+        if let Err(err_msg) = ArgumentParser::new(args, "BGSAVE").check_arg_count(0) {
+            return Ok(RespResponse::error(&err_msg));
+        }
+
+        match self.database.background_save() {
+            Ok(()) => Ok(RespResponse::simple_string("Background saving started")),
+            Err(e) => Ok(RespResponse::command_error(
+                "failed to start background save",
+                e.as_ref(),
+            )),
+        }
+    }
+}
+
+/// BGREWRITEAOF 命令
+///
+/// 语法: BGREWRITEAOF
+///
+/// 在后台重写 AOF 并立即返回，结果通过 `INFO persistence` 的 `aof_rewrite_*` 字段查看
+pub struct BgrewriteaofCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl BgrewriteaofCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+impl Command for BgrewriteaofCommand {
+    fn name(&self) -> &'static str {
+        "BGREWRITEAOF"
+    }
+
+    async fn execute(&self, args: &[RespValue]) -> Result<String> {
+        if let Err(err_msg) = ArgumentParser::new(args, "BGREWRITEAOF").check_arg_count(0) {
+            return Ok(RespResponse::error(&err_msg));
+        }
+
+        match self.database.background_rewrite_aof() {
+            Ok(()) => Ok(RespResponse::simple_string(
+                "Background append only file rewriting started",
+            )),
+            Err(e) => Ok(RespResponse::command_error(
+                "failed to start AOF rewrite",
+                e.as_ref(),
+            )),
+        }
+    }
+}
+
+/// AOFMD5 命令
+///
+/// 语法: AOFMD5 pos size
+///
+/// 返回 AOF 中从 `pos` 开始的 `size` 个字节的 MD5（十六进制），复制时用来确认两个节点的
+/// AOF 片段一致。范围超出文件长度时返回错误
+pub struct AofMd5Command {
+    database: Arc<GeoDatabase>,
+}
+
+impl AofMd5Command {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+impl Command for AofMd5Command {
+    fn name(&self) -> &'static str {
+        "AOFMD5"
+    }
+
+    async fn execute(&self, args: &[RespValue]) -> Result<String> {
+        let parser = ArgumentParser::new(args, "AOFMD5");
+        let range = parser.check_arg_count(2).and_then(|()| {
+            Ok((
+                parser.get_integer(0, "pos")? as u64,
+                parser.get_integer(1, "size")? as u64,
+            ))
+        });
+        let (pos, size) = match range {
+            Ok(range) => range,
+            Err(err_msg) => return Ok(RespResponse::error(&err_msg)),
+        };
+
+        match self.database.aof_md5(pos, size).await {
+            Ok(digest) => Ok(RespResponse::bulk_string(Some(&digest))),
+            Err(e) => Ok(RespResponse::command_error(
+                "failed to compute AOF md5",
+                e.as_ref(),
+            )),
+        }
+    }
+}
+
+/// EXPORT 命令
+///
+/// 语法: EXPORT collection file
+///
+/// 把 collection 写成导出目录（`<data_dir>/export`）中的 GeoJSON FeatureCollection 文件，
+/// 返回 Feature 数量；collection 不存在时返回 nil。`file` 只能是文件名，不能包含目录
+pub struct ExportCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl ExportCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+impl Command for ExportCommand {
+    fn name(&self) -> &'static str {
+        "EXPORT"
+    }
+
+    async fn execute(&self, args: &[RespValue]) -> Result<String> {
+        let parser = ArgumentParser::new(args, "EXPORT");
+        let target = parser.check_arg_count(2).and_then(|()| {
+            Ok((
+                parser.get_string(0, "collection")?,
+                parser.get_string(1, "file")?,
+            ))
+        });
+        let (collection_id, file) = match target {
+            Ok(target) => target,
+            Err(err_msg) => return Ok(RespResponse::error(&err_msg)),
+        };
+
+        match self.database.export_collection(collection_id, file).await {
+            Ok(Some(count)) => Ok(RespResponse::integer(count as i64)),
+            Ok(None) => Ok(RespResponse::bulk_string(None)),
+            Err(e) => Ok(RespResponse::command_error(
+                "failed to export collection",
+                e.as_ref(),
+            )),
+        }
+    }
+}
+
+/// IMPORT 命令的数据来源
+enum ImportSource {
+    /// 导出目录中的文件
+    File(String),
+    /// 命令中直接给出的 FeatureCollection
+    Object(String),
+}
+
+/// 解析 IMPORT 的参数：collection FILE name|OBJECT geojson [IDFIELD field]
+fn parse_import_args(
+    args: &[RespValue],
+) -> std::result::Result<(String, ImportSource, Option<String>), String> {
+    let parser = ArgumentParser::new(args, "IMPORT");
+    if args.len() != 3 && args.len() != 5 {
+        return Err("ERR wrong number of arguments for 'IMPORT' command".to_string());
+    }
+    let collection_id = parser.get_string(0, "collection")?.to_string();
+    let value = parser.get_string(2, "source")?.to_string();
+    let source = match parser.get_string(1, "source")?.to_uppercase().as_str() {
+        "FILE" => ImportSource::File(value),
+        "OBJECT" => ImportSource::Object(value),
+        other => {
+            return Err(format!(
+                "ERR unknown IMPORT source '{}', expected FILE or OBJECT",
+                other
+            ))
+        }
+    };
+    let id_field = if args.len() == 5 {
+        let option = parser.get_string(3, "option")?;
+        if !option.eq_ignore_ascii_case("IDFIELD") {
+            return Err(format!(
+                "ERR unknown IMPORT option '{}', expected IDFIELD",
+                option
+            ));
+        }
+        Some(parser.get_string(4, "field")?.to_string())
+    } else {
+        None
+    };
+    Ok((collection_id, source, id_field))
+}
+
+/// IMPORT 命令
+///
+/// 语法: IMPORT collection FILE name|OBJECT geojson [IDFIELD field]
+///
+/// 把 GeoJSON FeatureCollection 中的每个 Feature 写成一个对象，返回导入的对象数量。
+/// FILE 从导出目录读取文件（EXPORT 写出的文件可以直接导入），OBJECT 直接给出 FeatureCollection。
+/// 对象 id 取 Feature 的 `id`，IDFIELD 指定改用 properties 中的字段；任何一个 Feature 无效时
+/// 不导入任何对象
+pub struct ImportCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl ImportCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+impl Command for ImportCommand {
+    fn name(&self) -> &'static str {
+        "IMPORT"
+    }
+
+    async fn execute(&self, args: &[RespValue]) -> Result<String> {
+        let (collection_id, source, id_field) = match parse_import_args(args) {
+            Ok(parsed) => parsed,
+            Err(err_msg) => return Ok(RespResponse::error(&err_msg)),
+        };
+
+        let result = match source {
+            ImportSource::File(file) => {
+                self.database
+                    .import_file(&collection_id, &file, id_field.as_deref())
+                    .await
+            }
+            ImportSource::Object(geojson) => {
+                self.database
+                    .import_geojson(&collection_id, geojson, id_field.as_deref())
+                    .await
+            }
+        };
+        match result {
+            Ok(count) => Ok(RespResponse::integer(count as i64)),
+            Err(e) => Ok(RespResponse::command_error(
+                "failed to import features",
+                e.as_ref(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_save_command() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("dump.spdb");
+        let database = Arc::new(GeoDatabase::new().with_snapshot_path(path.clone()));
+        let point = json!({"type": "Point", "coordinates": [1.0, 2.0]}).to_string();
+        database.set("fleet", "truck1", &point).await.unwrap();
+
+        let cmd = SaveCommand::new(Arc::clone(&database));
+        let result = cmd.execute(&[]).await.unwrap();
+        assert_eq!(result, "+OK\r\n");
+        assert!(path.exists());
+
+        let restored = GeoDatabase::new();
+        let summary = restored.load_snapshot(&path).await.unwrap().unwrap();
+        assert_eq!((summary.collections, summary.objects), (1, 1));
+        assert!(restored.get("fleet", "truck1").await.unwrap().is_some());
+
+        let result = cmd.execute(&[RespValue::bulk("now")]).await.unwrap();
+        assert!(result.contains("wrong number of arguments"));
+
+        // 上一次 BGSAVE 还在进行时返回 BUSY
+        let bgsave = BgsaveCommand::new(Arc::clone(&database));
+        assert!(!bgsave.execute(&[]).await.unwrap().starts_with('-'));
+        let result = bgsave.execute(&[]).await.unwrap();
+        assert_eq!(
+            result,
+            "-BUSY failed to start background save: a snapshot save is already in progress\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_save_commands_without_snapshot_path() {
+        let database = Arc::new(GeoDatabase::new());
+
+        let result = SaveCommand::new(Arc::clone(&database))
+            .execute(&[])
+            .await
+            .unwrap();
+        assert!(result.starts_with("-ERR failed to save snapshot"));
+
+        let result = BgsaveCommand::new(Arc::clone(&database))
+            .execute(&[])
+            .await
+            .unwrap();
+        assert!(result.contains("snapshot persistence is not enabled"));
+
+        let result = BgrewriteaofCommand::new(database)
+            .execute(&[])
+            .await
+            .unwrap();
+        assert!(result.contains("AOF is not enabled"));
+    }
+
+    #[tokio::test]
+    async fn test_bgrewriteaof_command() {
+        use crate::rtree::algorithms::aof::AofConfig;
+
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("test.aof");
+        let database = Arc::new(GeoDatabase::with_aof(AofConfig::new(aof_path.clone())).unwrap());
+        let point = json!({"type": "Point", "coordinates": [1.0, 2.0]}).to_string();
+        for _ in 0..3 {
+            database.set("fleet", "truck1", &point).await.unwrap();
+        }
+
+        let cmd = BgrewriteaofCommand::new(Arc::clone(&database));
+        let result = cmd.execute(&[]).await.unwrap();
+        assert_eq!(result, "+Background append only file rewriting started\r\n");
+        // 后台任务还没有机会运行，第二次重写返回 BUSY
+        let result = cmd.execute(&[]).await.unwrap();
+        assert_eq!(
+            result,
+            "-BUSY failed to start AOF rewrite: an AOF rewrite is already in progress\r\n"
+        );
+
+        for _ in 0..100 {
+            let info = database.persistence_info().await;
+            if info.aof_last_rewrite_time.is_some() {
+                assert!(!info.aof_last_rewrite_failed);
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let info = database.persistence_info().await;
+        assert!(info.aof_last_rewrite_time.is_some());
+        assert_eq!(info.aof_current_size, info.aof_base_size);
+
+        let result = cmd.execute(&[RespValue::bulk("now")]).await.unwrap();
+        assert!(result.contains("wrong number of arguments"));
+    }
+
+    #[tokio::test]
+    async fn test_aofmd5_command() {
+        use crate::rtree::algorithms::aof::AofConfig;
+        use crate::storage::md5::Md5;
+
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("test.aof");
+        let database = Arc::new(GeoDatabase::with_aof(AofConfig::new(aof_path.clone())).unwrap());
+        let point = json!({"type": "Point", "coordinates": [1.0, 2.0]}).to_string();
+        database.set("fleet", "truck1", &point).await.unwrap();
+        database.set("fleet", "truck2", &point).await.unwrap();
+
+        // AOFMD5 先把缓冲区写入文件
+        let cmd = AofMd5Command::new(Arc::clone(&database));
+        let result = cmd
+            .execute(&[RespValue::bulk("10"), RespValue::bulk("30")])
+            .await
+            .unwrap();
+        let bytes = std::fs::read(&aof_path).unwrap();
+        let mut md5 = Md5::new();
+        md5.update(&bytes[10..40]);
+        let expected = md5.hex_digest();
+        assert_eq!(result, RespResponse::bulk_string(Some(&expected)));
+
+        let end = bytes.len().to_string();
+        let result = cmd
+            .execute(&[RespValue::bulk(end.as_str()), RespValue::bulk("1")])
+            .await
+            .unwrap();
+        assert!(result.contains("beyond the end of the AOF"));
+
+        let result = cmd
+            .execute(&[RespValue::bulk("-1"), RespValue::bulk("1")])
+            .await
+            .unwrap();
+        assert!(result.contains("invalid pos"));
+
+        let result = AofMd5Command::new(Arc::new(GeoDatabase::new()))
+            .execute(&[RespValue::bulk("0"), RespValue::bulk("0")])
+            .await
+            .unwrap();
+        assert!(result.contains("AOF is not enabled"));
+    }
+
+    #[tokio::test]
+    async fn test_export_command() {
+        let temp_dir = TempDir::new().unwrap();
+        let export_dir = temp_dir.path().join("export");
+        let database = Arc::new(GeoDatabase::new().with_export_dir(export_dir.clone()));
+        let point = json!({"type": "Point", "coordinates": [1.0, 2.0]}).to_string();
+        database.set("fleet", "truck1", &point).await.unwrap();
+        database.set("fleet", "truck2", &point).await.unwrap();
+
+        let cmd = ExportCommand::new(Arc::clone(&database));
+        let result = cmd
+            .execute(&[RespValue::bulk("fleet"), RespValue::bulk("fleet.geojson")])
+            .await
+            .unwrap();
+        assert_eq!(result, ":2\r\n");
+        let exported: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(export_dir.join("fleet.geojson")).unwrap())
+                .unwrap();
+        assert_eq!(exported["features"].as_array().unwrap().len(), 2);
+
+        let result = cmd
+            .execute(&[RespValue::bulk("missing"), RespValue::bulk("x.geojson")])
+            .await
+            .unwrap();
+        assert_eq!(result, RespResponse::bulk_string(None));
+
+        let result = cmd
+            .execute(&[
+                RespValue::bulk("fleet"),
+                RespValue::bulk("../fleet.geojson"),
+            ])
+            .await
+            .unwrap();
+        assert!(result.contains("invalid export file name"));
+
+        let result = ExportCommand::new(Arc::new(GeoDatabase::new()))
+            .execute(&[RespValue::bulk("fleet"), RespValue::bulk("fleet.geojson")])
+            .await
+            .unwrap();
+        assert!(result.contains("EXPORT is not enabled"));
+    }
+
+    #[tokio::test]
+    async fn test_import_command() {
+        let temp_dir = TempDir::new().unwrap();
+        let export_dir = temp_dir.path().join("export");
+        let database = Arc::new(GeoDatabase::new().with_export_dir(export_dir.clone()));
+        let point = json!({"type": "Point", "coordinates": [1.0, 2.0]});
+        let features = json!({
+            "type": "FeatureCollection",
+            "features": [
+                {"type": "Feature", "id": "truck1", "properties": {"code": "a"}, "geometry": point},
+                {"type": "Feature", "id": "truck2", "properties": {"code": "b"}, "geometry": point}
+            ]
+        })
+        .to_string();
+
+        let cmd = ImportCommand::new(Arc::clone(&database));
+        let result = cmd
+            .execute(&[
+                RespValue::bulk("fleet"),
+                RespValue::bulk("OBJECT"),
+                RespValue::bulk(features.as_str()),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(result, ":2\r\n");
+        assert!(database.get("fleet", "truck1").await.unwrap().is_some());
+
+        // EXPORT 写出的文件可以再导入，IDFIELD 改用 properties 中的字段作为 id
+        ExportCommand::new(Arc::clone(&database))
+            .execute(&[RespValue::bulk("fleet"), RespValue::bulk("fleet.geojson")])
+            .await
+            .unwrap();
+        let result = cmd
+            .execute(&[
+                RespValue::bulk("codes"),
+                RespValue::bulk("file"),
+                RespValue::bulk("fleet.geojson"),
+                RespValue::bulk("IDFIELD"),
+                RespValue::bulk("code"),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(result, ":2\r\n");
+        assert!(database.get("codes", "b").await.unwrap().is_some());
+
+        let result = cmd
+            .execute(&[
+                RespValue::bulk("codes"),
+                RespValue::bulk("FILE"),
+                RespValue::bulk("../fleet.geojson"),
+            ])
+            .await
+            .unwrap();
+        assert!(result.contains("invalid export file name"));
+
+        let result = cmd
+            .execute(&[
+                RespValue::bulk("fleet"),
+                RespValue::bulk("URL"),
+                RespValue::bulk("x"),
+            ])
+            .await
+            .unwrap();
+        assert!(result.contains("expected FILE or OBJECT"));
+
+        let result = cmd
+            .execute(&[RespValue::bulk("fleet"), RespValue::bulk("OBJECT")])
+            .await
+            .unwrap();
+        assert!(result.contains("wrong number of arguments"));
+    }
+}
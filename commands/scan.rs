@@ -0,0 +1,126 @@
+use crate::commands::args::ArgumentParser;
+use crate::commands::Command;
+use crate::protocol::{parser::RespValue, RespResponse};
+use crate::storage::GeoDatabase;
+use crate::Result;
+use std::sync::Arc;
+
+pub struct ScanCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl ScanCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+impl Command for ScanCommand {
+    fn name(&self) -> &'static str {
+        "SCAN"
+    }
+
+    fn execute(
+        &self,
+        args: &[RespValue],
+    ) -> impl std::future::Future<Output = Result<String>> + Send {
+        let database = Arc::clone(&self.database);
+
+        // 同步解析参数
+        let parse_result = ArgumentParser::new(args, "SCAN").parse_scan_args();
+
+        async move {
+            let parsed_args = match parse_result {
+                Ok(args) => args,
+                Err(err_msg) => {
+                    return Ok(RespResponse::error(&err_msg));
+                }
+            };
+
+            match database
+                .scan(
+                    &parsed_args.collection_id,
+                    &parsed_args.cursor,
+                    parsed_args.count,
+                )
+                .await
+            {
+                // 返回 [下一页游标, [对象 ID...]]，最后一页的游标为 "0"
+                Ok(page) => {
+                    let next = page.next.map_or_else(|| "0".to_string(), |c| c.to_string());
+                    let ids: Vec<RespValue> = page.items.into_iter().map(RespValue::bulk).collect();
+                    Ok(RespResponse::array(Some(&[
+                        RespValue::bulk(next),
+                        RespValue::Array(Some(ids)),
+                    ])))
+                }
+                Err(e) => Ok(RespResponse::command_error("scan failed", e.as_ref())),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::args;
+
+    fn point(lon: f64, lat: f64) -> String {
+        format!(r#"{{"type":"Point","coordinates":[{},{}]}}"#, lon, lat)
+    }
+
+    /// 从 SCAN 的回复中取出下一页游标
+    fn next_cursor(reply: &str) -> String {
+        reply.split("\r\n").nth(2).unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_scan_command_pages() {
+        let database = Arc::new(GeoDatabase::new());
+        for i in 0..5 {
+            database
+                .set("fleet", &format!("truck{}", i), &point(i as f64, 0.0))
+                .await
+                .unwrap();
+        }
+        let cmd = ScanCommand::new(Arc::clone(&database));
+
+        let result = cmd
+            .execute(&args(&["fleet", "0", "COUNT", "3"]))
+            .await
+            .unwrap();
+        assert!(result.contains("*3\r\n$6\r\ntruck0\r\n$6\r\ntruck1\r\n$6\r\ntruck2\r\n"));
+        let cursor = next_cursor(&result);
+        assert!(cursor.ends_with(":truck2"));
+
+        // 两页之间删除游标指向的对象并插入新对象
+        database.delete("fleet", "truck2").await.unwrap();
+        database
+            .set("fleet", "truck9", &point(9.0, 0.0))
+            .await
+            .unwrap();
+
+        let result = cmd.execute(&args(&["fleet", &cursor])).await.unwrap();
+        assert!(result.starts_with("*2\r\n$1\r\n0\r\n"));
+        assert!(result.ends_with("*3\r\n$6\r\ntruck3\r\n$6\r\ntruck4\r\n$6\r\ntruck9\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_scan_command_errors() {
+        let cmd = ScanCommand::new(Arc::new(GeoDatabase::new()));
+
+        // 不存在的 collection 返回空页
+        let result = cmd.execute(&args(&["nothing", "0"])).await.unwrap();
+        assert_eq!(result, "*2\r\n$1\r\n0\r\n*0\r\n");
+
+        let result = cmd.execute(&args(&["fleet", "bogus"])).await.unwrap();
+        assert!(result.starts_with("-ERR invalid cursor 'bogus'"));
+        let result = cmd
+            .execute(&args(&["fleet", "0", "COUNT", "0"]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("-ERR COUNT must be positive"));
+        let result = cmd.execute(&args(&["fleet"])).await.unwrap();
+        assert!(result.contains("wrong number of arguments"));
+    }
+}
@@ -4,7 +4,16 @@ use crate::protocol::{parser::RespValue, RespResponse};
 use crate::storage::GeoDatabase;
 use crate::Result;
 use std::sync::Arc;
+use std::time::Duration;
 
+/// SET 命令
+///
+/// 语法: SET collection id geojson|HASH geohash|STRING value [TAG tag ...] [EX seconds]
+///
+/// `geojson` 可以是几何体或 Feature，Feature 的 properties 随对象保存；FeatureCollection 需要用
+/// IMPORT 拆成多个对象。覆盖对象时清除原有的过期时间；带 `EX` 时对象在 `seconds` 秒（可以带小数）之后过期。
+/// `HASH geohash` 存储 geohash 格子中心的 Point；`STRING value` 存储不参与空间查询的文本，
+/// GET、KEYS、SCAN 可以看到它
 pub struct SetCommand {
     database: Arc<GeoDatabase>,
 }
@@ -38,17 +47,36 @@ impl Command for SetCommand {
                 }
             };
 
+            // 超出 Duration 范围的存活时间按最大值处理
+            let ttl = parsed_args
+                .expire_seconds
+                .map(|seconds| Duration::try_from_secs_f64(seconds).unwrap_or(Duration::MAX));
+
             // 只有 I/O 操作需要异步
-            match database
-                .set(
-                    &parsed_args.collection_id,
-                    &parsed_args.item_id,
-                    &parsed_args.geojson,
-                )
-                .await
-            {
+            let result = if parsed_args.is_string {
+                database
+                    .set_string(
+                        &parsed_args.collection_id,
+                        &parsed_args.item_id,
+                        &parsed_args.geojson,
+                        &parsed_args.tags,
+                        ttl,
+                    )
+                    .await
+            } else {
+                database
+                    .set_with_ttl(
+                        &parsed_args.collection_id,
+                        &parsed_args.item_id,
+                        &parsed_args.geojson,
+                        &parsed_args.tags,
+                        ttl,
+                    )
+                    .await
+            };
+            match result {
                 Ok(_) => Ok(RespResponse::simple_string("OK")),
-                Err(e) => Ok(RespResponse::error(&format!("ERR failed to store: {}", e))),
+                Err(e) => Ok(RespResponse::command_error("failed to store", e.as_ref())),
             }
         }
     }
@@ -57,6 +85,7 @@ impl Command for SetCommand {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::commands::args;
     use serde_json::json;
 
     #[tokio::test]
@@ -70,9 +99,9 @@ mod tests {
         });
 
         let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("truck1".to_string())),
-            RespValue::BulkString(Some(point_json.to_string())),
+            RespValue::bulk("fleet"),
+            RespValue::bulk("truck1"),
+            RespValue::bulk(point_json.to_string()),
         ];
 
         let result = cmd.execute(&args).await.unwrap();
@@ -85,16 +114,47 @@ mod tests {
         assert!(matches!(item.geometry, geo::Geometry::Point(_)));
     }
 
+    #[tokio::test]
+    async fn test_set_command_with_ex() {
+        use crate::storage::Ttl;
+
+        let database = Arc::new(GeoDatabase::new());
+        let cmd = SetCommand::new(Arc::clone(&database));
+        let point = json!({"type": "Point", "coordinates": [1.0, 2.0]}).to_string();
+
+        let args = vec![
+            RespValue::bulk("fleet"),
+            RespValue::bulk("truck1"),
+            RespValue::bulk(point.clone()),
+            RespValue::bulk("EX"),
+            RespValue::bulk("100"),
+        ];
+        assert_eq!(cmd.execute(&args).await.unwrap(), "+OK\r\n");
+        let Ttl::Expires(remaining) = database.ttl("fleet", "truck1").await.unwrap() else {
+            panic!("expected a TTL");
+        };
+        assert!(remaining > Duration::from_secs(99));
+
+        // 不带 EX 覆盖时清除过期时间
+        let args = vec![
+            RespValue::bulk("fleet"),
+            RespValue::bulk("truck1"),
+            RespValue::bulk(point),
+        ];
+        assert_eq!(cmd.execute(&args).await.unwrap(), "+OK\r\n");
+        assert_eq!(
+            database.ttl("fleet", "truck1").await.unwrap(),
+            Ttl::Persistent
+        );
+    }
+
     #[tokio::test]
     async fn test_set_command_invalid_args() {
         let database = Arc::new(GeoDatabase::new());
         let cmd = SetCommand::new(database);
 
         // 参数太少
-        let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("truck1".to_string())),
-        ];
+        let args = vec![RespValue::bulk("fleet"), RespValue::bulk("truck1")];
 
         let result = cmd.execute(&args).await.unwrap();
         assert!(result.contains("wrong number of arguments"));
@@ -107,14 +167,129 @@ mod tests {
 
         // 使用一个有效的 GeoJSON 来测试成功案例
         let args = vec![
-            RespValue::BulkString(Some("fleet".to_string())),
-            RespValue::BulkString(Some("truck1".to_string())),
-            RespValue::BulkString(Some(
-                r#"{"type": "Point", "coordinates": [1.0, 2.0]}"#.to_string(),
-            )),
+            RespValue::bulk("fleet"),
+            RespValue::bulk("truck1"),
+            RespValue::bulk(r#"{"type": "Point", "coordinates": [1.0, 2.0]}"#.to_string()),
         ];
 
         let result = cmd.execute(&args).await.unwrap();
         assert_eq!(result, "+OK\r\n");
     }
+
+    #[tokio::test]
+    async fn test_set_command_hash() {
+        let database = Arc::new(GeoDatabase::new());
+        let cmd = SetCommand::new(Arc::clone(&database));
+
+        let result = cmd
+            .execute(&args(&[
+                "fleet",
+                "truck1",
+                "HASH",
+                "9q8yyk8ytpxr",
+                "TAG",
+                "bus",
+            ]))
+            .await
+            .unwrap();
+        assert_eq!(result, "+OK\r\n");
+        let item = database.get("fleet", "truck1").await.unwrap().unwrap();
+        let geo::Geometry::Point(point) = item.geometry else {
+            panic!("expected a point, got {:?}", item.geometry);
+        };
+        assert!((point.x() + 122.4194).abs() < 1e-4 && (point.y() - 37.7749).abs() < 1e-4);
+
+        let result = cmd
+            .execute(&args(&["fleet", "truck1", "HASH", "9q8!"]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("-ERR invalid geohash"));
+        let result = cmd
+            .execute(&args(&["fleet", "truck1", "HASH"]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("-ERR missing geohash parameter"));
+    }
+
+    #[tokio::test]
+    async fn test_set_command_feature() {
+        let database = Arc::new(GeoDatabase::new());
+        let cmd = SetCommand::new(Arc::clone(&database));
+        let point = json!({"type": "Point", "coordinates": [1.0, 2.0]});
+        let feature =
+            json!({"type": "Feature", "properties": {"driver": "alice"}, "geometry": point});
+
+        let args = vec![
+            RespValue::bulk("fleet"),
+            RespValue::bulk("truck1"),
+            RespValue::bulk(feature.to_string()),
+        ];
+        assert_eq!(cmd.execute(&args).await.unwrap(), "+OK\r\n");
+        let item = database.get("fleet", "truck1").await.unwrap().unwrap();
+        let stored: serde_json::Value = serde_json::from_str(&item.geojson).unwrap();
+        assert_eq!(stored["properties"]["driver"], "alice");
+
+        // FeatureCollection 和没有几何体的 Feature 返回明确的错误，原有对象保持不变
+        let collection = json!({"type": "FeatureCollection", "features": [feature]});
+        let empty = json!({"type": "Feature", "properties": {}, "geometry": null});
+        for (geojson, message) in [
+            (collection, "IMPORT collection OBJECT"),
+            (empty, "Feature 没有 geometry 字段"),
+        ] {
+            let args = vec![
+                RespValue::bulk("fleet"),
+                RespValue::bulk("truck1"),
+                RespValue::bulk(geojson.to_string()),
+            ];
+            let result = cmd.execute(&args).await.unwrap();
+            assert!(result.starts_with("-ERR failed to store: invalid GeoJSON"));
+            assert!(result.contains(message), "{}", result);
+        }
+        let item = database.get("fleet", "truck1").await.unwrap().unwrap();
+        assert!(item.geojson.contains("alice"));
+    }
+
+    #[tokio::test]
+    async fn test_set_command_string() {
+        use crate::commands::get::GetCommand;
+        use crate::storage::Ttl;
+
+        let database = Arc::new(GeoDatabase::new());
+        let cmd = SetCommand::new(Arc::clone(&database));
+        let values = args(&[
+            "fleet",
+            "note",
+            "string",
+            "hello world",
+            "TAG",
+            "memo",
+            "EX",
+            "100",
+        ]);
+        assert_eq!(cmd.execute(&values).await.unwrap(), "+OK\r\n");
+        assert_eq!(database.tags("fleet", "note").await.unwrap(), vec!["memo"]);
+        assert!(matches!(
+            database.ttl("fleet", "note").await.unwrap(),
+            Ttl::Expires(_)
+        ));
+
+        let get = GetCommand::new(Arc::clone(&database));
+        let result = get
+            .execute(&[RespValue::bulk("fleet"), RespValue::bulk("note")])
+            .await
+            .unwrap();
+        assert_eq!(result, RespResponse::bulk_string(Some("hello world")));
+        let result = get
+            .execute(&[
+                RespValue::bulk("fleet"),
+                RespValue::bulk("note"),
+                RespValue::bulk("POINT"),
+            ])
+            .await
+            .unwrap();
+        assert!(result.starts_with("-ERR object is a string"));
+
+        let values = args(&["fleet", "note", "STRING"]);
+        assert!(cmd.execute(&values).await.unwrap().starts_with("-ERR"));
+    }
 }
@@ -0,0 +1,102 @@
+use crate::commands::args::ArgumentParser;
+use crate::commands::Command;
+use crate::protocol::{parser::RespValue, RespResponse};
+use crate::storage::GeoDatabase;
+use crate::Result;
+use std::sync::Arc;
+
+/// SNAP 命令：将点吸附到 collection 中最近的线上，用于 GPS 轨迹纠偏
+pub struct SnapCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl SnapCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+impl Command for SnapCommand {
+    fn name(&self) -> &'static str {
+        "SNAP"
+    }
+
+    fn execute(
+        &self,
+        args: &[RespValue],
+    ) -> impl std::future::Future<Output = Result<String>> + Send {
+        let database = Arc::clone(&self.database);
+
+        // 同步解析参数
+        let parse_result = ArgumentParser::new(args, "SNAP").parse_snap_args();
+
+        async move {
+            // 检查参数解析结果
+            let parsed_args = match parse_result {
+                Ok(args) => args,
+                Err(err_msg) => {
+                    return Ok(RespResponse::error(&err_msg));
+                }
+            };
+
+            match database
+                .snap(&parsed_args.collection_id, parsed_args.lon, parsed_args.lat)
+                .await
+            {
+                // 返回 [id, lon, lat, distance]，没有线类对象时返回 nil
+                Ok(Some(result)) => {
+                    let resp_values = vec![
+                        RespValue::bulk(result.id),
+                        RespValue::bulk(result.point.x().to_string()),
+                        RespValue::bulk(result.point.y().to_string()),
+                        RespValue::bulk(result.distance.to_string()),
+                    ];
+                    Ok(RespResponse::array(Some(&resp_values)))
+                }
+                Ok(None) => Ok(RespResponse::array(None)),
+                Err(e) => Ok(RespResponse::command_error("snap query failed", e.as_ref())),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::args;
+
+    #[tokio::test]
+    async fn test_snap_command() {
+        let database = Arc::new(GeoDatabase::new());
+        database
+            .set(
+                "roads",
+                "main_st",
+                r#"{"type":"Feature","properties":{"name":"Main St"},"geometry":{"type":"LineString","coordinates":[[0.0,0.0],[0.0,1.0]]}}"#,
+            )
+            .await
+            .unwrap();
+        database
+            .set(
+                "roads",
+                "poi",
+                r#"{"type":"Point","coordinates":[0.001,0.5]}"#,
+            )
+            .await
+            .unwrap();
+
+        let cmd = SnapCommand::new(Arc::clone(&database));
+        let result = cmd
+            .execute(&args(&["roads", "0.001", "0.5"]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("*4\r\n$7\r\nmain_st\r\n$1\r\n0\r\n$3\r\n0.5\r\n"));
+
+        // 不存在的 collection
+        let result = cmd
+            .execute(&args(&["missing", "0.0", "0.0"]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("-NOINDEX snap query failed"));
+    }
+}
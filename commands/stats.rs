@@ -0,0 +1,256 @@
+use crate::commands::Command;
+use crate::protocol::{parser::RespValue, RespResponse};
+use crate::storage::{CollectionStats, GeoDatabase};
+use crate::Result;
+use std::sync::Arc;
+
+/// STATS HISTORY 未指定 MINUTES 时返回的分钟数
+pub const DEFAULT_HISTORY_MINUTES: u64 = 60;
+
+/// STATS 命令：不依赖外部监控查看最近的操作趋势
+///
+/// 语法:
+/// - STATS HISTORY [MINUTES n]：最近 n 分钟（默认 60，最多为保留时间）每分钟的命令数、
+///   GET 命中/未命中数和空闲 collection 卸载数，按时间递增，没有操作的分钟计数为 0
+/// - STATS collection [collection ...]：每个 collection 一项，为 `objects`、`bounds`（`[minx,miny,maxx,maxy]`，
+///   空或已卸载时为 nil）、`memory`（字节数估算）和 `unloaded` 的字段/值数组，不存在的 collection 为 nil。
+///   名为 `HISTORY` 的 collection 会被当作子命令
+pub struct StatsCommand {
+    database: Arc<GeoDatabase>,
+}
+
+impl StatsCommand {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+enum StatsSubcommand {
+    History { minutes: u64 },
+    Collections(Vec<String>),
+}
+
+fn parse_args(args: &[RespValue]) -> std::result::Result<StatsSubcommand, String> {
+    let strings: Vec<&str> = args
+        .iter()
+        .map(|arg| match arg {
+            RespValue::BulkString(Some(_)) => arg
+                .as_str()
+                .ok_or_else(|| "ERR invalid argument: expected UTF-8 string".to_string()),
+            _ => Err("ERR invalid argument: expected string".to_string()),
+        })
+        .collect::<std::result::Result<_, _>>()?;
+
+    let Some((subcommand, rest)) = strings.split_first() else {
+        return Err("ERR wrong number of arguments for 'STATS' command".to_string());
+    };
+    match (subcommand.to_uppercase().as_str(), rest) {
+        ("HISTORY", []) => Ok(StatsSubcommand::History {
+            minutes: DEFAULT_HISTORY_MINUTES,
+        }),
+        ("HISTORY", [option, value]) if option.eq_ignore_ascii_case("MINUTES") => {
+            match value.parse::<u64>() {
+                Ok(minutes) if minutes > 0 => Ok(StatsSubcommand::History { minutes }),
+                _ => Err(format!(
+                    "ERR invalid MINUTES value: expected a positive integer, got '{}'",
+                    value
+                )),
+            }
+        }
+        ("HISTORY", _) => Err(
+            "ERR wrong number of arguments for 'STATS HISTORY' command. Usage: STATS HISTORY [MINUTES n]"
+                .to_string(),
+        ),
+        _ => Ok(StatsSubcommand::Collections(
+            strings.iter().map(|name| name.to_string()).collect(),
+        )),
+    }
+}
+
+/// 一个 collection 的统计，为字段/值交替的数组
+fn collection_reply(stats: &CollectionStats) -> RespValue {
+    let bounds = stats
+        .bounds
+        .map(|rect| {
+            format!(
+                "[{},{},{},{}]",
+                rect.min[0], rect.min[1], rect.max[0], rect.max[1]
+            )
+        })
+        .map(String::into_bytes);
+    RespValue::Array(Some(vec![
+        RespValue::bulk("objects"),
+        RespValue::Integer(stats.objects as i64),
+        RespValue::bulk("bounds"),
+        RespValue::BulkString(bounds),
+        RespValue::bulk("memory"),
+        RespValue::Integer(stats.memory.total() as i64),
+        RespValue::bulk("unloaded"),
+        RespValue::Integer(stats.unloaded as i64),
+    ]))
+}
+
+impl Command for StatsCommand {
+    fn name(&self) -> &'static str {
+        "STATS"
+    }
+
+    fn execute(
+        &self,
+        args: &[RespValue],
+    ) -> impl std::future::Future<Output = Result<String>> + Send {
+        let database = Arc::clone(&self.database);
+
+        // 同步解析参数
+        let parse_result = parse_args(args);
+
+        async move {
+            let subcommand = match parse_result {
+                Ok(subcommand) => subcommand,
+                Err(err_msg) => return Ok(RespResponse::error(&err_msg)),
+            };
+
+            match subcommand {
+                StatsSubcommand::History { minutes } => {
+                    let mut history = String::from("# History\r\n");
+                    history.push_str(&format!(
+                        "retention_minutes:{}\r\n",
+                        database.stats_retention_minutes()
+                    ));
+                    for minute in database.stats_history(minutes) {
+                        history.push_str(&format!(
+                            "minute:{}:commands={},hits={},misses={},evictions={},fence_events={}\r\n",
+                            minute.minute,
+                            minute.commands,
+                            minute.hits,
+                            minute.misses,
+                            minute.evictions,
+                            minute.fence_events
+                        ));
+                    }
+                    Ok(RespResponse::bulk_string(Some(&history)))
+                }
+                StatsSubcommand::Collections(names) => {
+                    let mut items = Vec::with_capacity(names.len());
+                    for name in &names {
+                        items.push(match database.collection_stats(name).await {
+                            Some(stats) => collection_reply(&stats),
+                            None => RespValue::BulkString(None),
+                        });
+                    }
+                    Ok(RespResponse::array(Some(&items)))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::args;
+    use crate::commands::CommandRegistry;
+    use crate::storage::MockClock;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_stats_history_counts_per_minute() {
+        // 整分钟开始
+        let clock = Arc::new(MockClock::new(1_700_000_040 * 1_000_000_000));
+        let database = Arc::new(
+            GeoDatabase::new()
+                .with_clock(clock.clone())
+                .with_stats_retention(2),
+        );
+        let registry = CommandRegistry::new(Arc::clone(&database));
+
+        let point = r#"{"type":"Point","coordinates":[1.0,2.0]}"#;
+        registry
+            .execute("SET", &args(&["fleet", "truck1", point]))
+            .await
+            .unwrap();
+        registry
+            .execute("GET", &args(&["fleet", "truck1"]))
+            .await
+            .unwrap();
+        clock.advance(Duration::from_secs(120));
+        registry
+            .execute("GET", &args(&["fleet", "missing"]))
+            .await
+            .unwrap();
+        // 未知命令不计数
+        registry.execute("NOPE", &[]).await.unwrap();
+
+        let result = registry
+            .execute("STATS", &args(&["HISTORY", "MINUTES", "3"]))
+            .await
+            .unwrap();
+        assert!(result.contains("retention_minutes:120\r\n"));
+        assert!(result.contains(
+            "minute:1700000040:commands=2,hits=1,misses=0,evictions=0,fence_events=0\r\n"
+        ));
+        assert!(result.contains(
+            "minute:1700000100:commands=0,hits=0,misses=0,evictions=0,fence_events=0\r\n"
+        ));
+        // STATS 命令本身也计入当前分钟
+        assert!(result.contains(
+            "minute:1700000160:commands=2,hits=0,misses=1,evictions=0,fence_events=0\r\n"
+        ));
+
+        // 超过保留时间时截断
+        let result = registry
+            .execute("STATS", &args(&["HISTORY", "MINUTES", "100000"]))
+            .await
+            .unwrap();
+        assert_eq!(result.matches("minute:").count(), 120);
+    }
+
+    #[tokio::test]
+    async fn test_stats_command_errors() {
+        let cmd = StatsCommand::new(Arc::new(GeoDatabase::new()));
+
+        let result = cmd.execute(&args(&[])).await.unwrap();
+        assert!(result.contains("wrong number of arguments"));
+        let result = cmd
+            .execute(&args(&["HISTORY", "MINUTES", "0"]))
+            .await
+            .unwrap();
+        assert!(result.starts_with("-ERR invalid MINUTES value"));
+        let result = cmd
+            .execute(&args(&["HISTORY", "HOURS", "1"]))
+            .await
+            .unwrap();
+        assert!(result.contains("wrong number of arguments for 'STATS HISTORY'"));
+
+        // 默认返回最近 60 分钟
+        let result = cmd.execute(&args(&["history"])).await.unwrap();
+        assert_eq!(result.matches("minute:").count(), 60);
+    }
+
+    #[tokio::test]
+    async fn test_stats_collections() {
+        let database = Arc::new(GeoDatabase::new());
+        for (id, lon, lat) in [("truck1", 1.0, 2.0), ("truck2", 3.0, -4.0)] {
+            let point = serde_json::json!({"type": "Point", "coordinates": [lon, lat]});
+            database.set("fleet", id, &point.to_string()).await.unwrap();
+        }
+        let cmd = StatsCommand::new(Arc::clone(&database));
+
+        let result = cmd.execute(&args(&["fleet", "missing"])).await.unwrap();
+        let (reply, _) = crate::protocol::RespParser::decode(result.as_bytes())
+            .unwrap()
+            .unwrap();
+        let RespValue::Array(Some(items)) = reply else {
+            panic!("expected an array, got {:?}", reply);
+        };
+        assert_eq!(items[1], RespValue::BulkString(None));
+        let RespValue::Array(Some(fields)) = &items[0] else {
+            panic!("expected field/value pairs, got {:?}", items[0]);
+        };
+        assert_eq!(fields[0], RespValue::bulk("objects"));
+        assert_eq!(fields[1], RespValue::Integer(2));
+        assert_eq!(fields[3], RespValue::bulk("[1,-4,3,2]"));
+        assert!(matches!(fields[5], RespValue::Integer(bytes) if bytes > 0));
+        assert_eq!(fields[7], RespValue::Integer(0));
+    }
+}
@@ -1,6 +1,18 @@
+use crate::rtree::algorithms::knn::DistanceMetric;
+use crate::storage::geometry_utils::MAX_COORDINATE_PRECISION;
+use crate::storage::stats::{DEFAULT_STATS_RETENTION_HOURS, MAX_STATS_RETENTION_HOURS};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
+pub mod overrides;
+pub mod schema;
+
+pub use overrides::{split_overrides, ConfigOverride};
+pub use schema::UnknownKey;
+
+/// 配置文件中存放各个 profile 的表名：`[profile.production]`、`[profile.dev]`
+pub const PROFILE_TABLE: &str = "profile";
+
 /// Spatio 数据库配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SpatioConfig {
@@ -13,6 +25,9 @@ pub struct SpatioConfig {
     /// AOF 持久化配置
     pub aof: AofConfig,
 
+    /// 快照持久化配置
+    pub snapshot: SnapshotConfig,
+
     /// 日志配置
     pub logging: LoggingConfig,
 }
@@ -35,6 +50,40 @@ pub struct ServerConfig {
     /// 请求超时时间（秒）
     #[serde(default = "default_timeout")]
     pub timeout: u64,
+
+    /// PID 文件路径（可选），用于 systemd `PIDFile=` 等进程管理工具
+    #[serde(default)]
+    pub pidfile: Option<PathBuf>,
+
+    /// 输出坐标的小数位数（可选），GET/INTERSECTS/NEARBY 返回的 GeoJSON 按此四舍五入，
+    /// 请求中的 PRECISION 优先
+    #[serde(default)]
+    pub output_precision: Option<u32>,
+
+    /// NEARBY 默认的距离度量：haversine（默认）、euclidean 或 cosines，请求中的 DISTANCE 优先
+    #[serde(default = "default_distance_metric")]
+    pub distance_metric: String,
+
+    /// STATS HISTORY 按分钟统计的保留时间（小时）
+    #[serde(default = "default_stats_retention_hours")]
+    pub stats_retention_hours: u32,
+
+    /// 是否允许 DEBUG SET-ACTIVE-EXPIRE、DEBUG SLEEP 等测试命令，生产主节点上应保持关闭
+    #[serde(default)]
+    pub debug_testing: bool,
+
+    /// 连接密码（可选），设置后客户端必须先用 `AUTH password` 认证才能执行其他命令，
+    /// HTTP 请求使用 `Authorization: Bearer password` 头
+    #[serde(default)]
+    pub requirepass: Option<String>,
+
+    /// `SELECT n` 可以选择的命名空间数量，n 的范围为 0 到 databases - 1
+    #[serde(default = "default_databases")]
+    pub databases: u32,
+
+    /// gRPC 监听端口（可选，需要编译 `grpc` feature），与 RESP 共用 host
+    #[serde(default)]
+    pub grpc_port: Option<u16>,
 }
 
 /// 存储配置
@@ -47,6 +96,24 @@ pub struct StorageConfig {
     /// R-tree 最大子节点数
     #[serde(default = "default_max_children")]
     pub max_children: usize,
+
+    /// 空闲多少分钟后将 collection 卸载到磁盘（`<data_dir>/cold`），0 表示不卸载
+    #[serde(default = "default_unload_idle_minutes")]
+    pub unload_idle_minutes: u64,
+
+    /// data_dir 所在文件系统的可用空间低于多少 MB 时拒绝写入（返回 `-OOM DISK`），0 表示不检查
+    #[serde(default = "default_min_free_disk_mb")]
+    pub min_free_disk_mb: u64,
+
+    /// 纯内存模式：不创建也不锁定数据目录，不做任何持久化，重启后数据丢失。
+    /// 要求 `aof.enabled = false`，且不能启用空闲卸载和磁盘空间检查
+    #[serde(default)]
+    pub ephemeral: bool,
+
+    /// 开启 RFC 7946 严格模式的 collection，支持 glob 模式（如 `export:*`）：
+    /// SET 拒绝不符合 RFC 7946 的 GeoJSON，GET/INTERSECTS/NEARBY 返回规范化的 GeoJSON
+    #[serde(default)]
+    pub strict_geojson: Vec<String>,
 }
 
 /// AOF 持久化配置
@@ -64,6 +131,11 @@ pub struct AofConfig {
     #[serde(default = "default_sync_policy")]
     pub sync_policy: String,
 
+    /// 记录格式：json（JSON Lines）或 framed（每条记录带长度和 CRC32 校验），
+    /// 只影响新追加的记录，已有的记录在下一次重写时转换
+    #[serde(default = "default_aof_format")]
+    pub format: String,
+
     /// 是否启用 AOF 重写
     #[serde(default = "default_auto_rewrite")]
     pub auto_rewrite_enabled: bool,
@@ -75,6 +147,43 @@ pub struct AofConfig {
     /// AOF 重写触发的增长百分比
     #[serde(default = "default_auto_rewrite_percentage")]
     pub auto_rewrite_percentage: u64,
+
+    /// fsync 停顿判定阈值（毫秒），0 表示关闭停顿检测
+    #[serde(default = "default_stall_threshold_ms")]
+    pub stall_threshold_ms: u64,
+
+    /// 检测到停顿时是否自动从 always 降级为 everysec
+    #[serde(default = "default_stall_fallback")]
+    pub stall_fallback: bool,
+
+    /// 未刷新的数据达到该字节数时写入文件（everysec / no 策略），0 表示不按字节数刷新
+    #[serde(default = "default_flush_bytes")]
+    pub flush_bytes: usize,
+
+    /// 未刷新的记录达到该条数时写入文件（everysec / no 策略），0 表示不按条数刷新
+    #[serde(default = "default_flush_records")]
+    pub flush_records: usize,
+
+    /// always 策略的组提交窗口（毫秒）：并发写入的记录在窗口内一起 fsync 后再回复，0 表示每条记录单独 fsync
+    #[serde(default = "default_group_commit_ms")]
+    pub group_commit_ms: u64,
+
+    /// 启动时最后一条记录损坏（通常是断电时写了一半）是否自动截断并继续启动；
+    /// 为 false 时拒绝启动，需要手动修复
+    #[serde(default = "default_load_truncated")]
+    pub load_truncated: bool,
+}
+
+/// 快照持久化配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotConfig {
+    /// 快照文件路径，SAVE/BGSAVE 写入，启动时在重放 AOF 之前加载
+    #[serde(default = "default_snapshot_path")]
+    pub path: PathBuf,
+
+    /// 每隔多少秒自动在后台保存一次快照，0 表示只在 SAVE/BGSAVE 时保存
+    #[serde(default = "default_snapshot_interval")]
+    pub interval: u64,
 }
 
 /// 日志配置
@@ -112,6 +221,18 @@ fn default_timeout() -> u64 {
     30
 }
 
+fn default_databases() -> u32 {
+    16
+}
+
+fn default_distance_metric() -> String {
+    "haversine".to_string()
+}
+
+fn default_stats_retention_hours() -> u32 {
+    DEFAULT_STATS_RETENTION_HOURS
+}
+
 fn default_data_dir() -> PathBuf {
     PathBuf::from("./data")
 }
@@ -120,6 +241,14 @@ fn default_max_children() -> usize {
     10
 }
 
+fn default_unload_idle_minutes() -> u64 {
+    0
+}
+
+fn default_min_free_disk_mb() -> u64 {
+    0
+}
+
 fn default_aof_enabled() -> bool {
     true
 }
@@ -144,6 +273,42 @@ fn default_auto_rewrite_percentage() -> u64 {
     100
 }
 
+fn default_stall_threshold_ms() -> u64 {
+    500
+}
+
+fn default_stall_fallback() -> bool {
+    false
+}
+
+fn default_flush_bytes() -> usize {
+    crate::rtree::algorithms::aof::DEFAULT_FLUSH_BYTES
+}
+
+fn default_aof_format() -> String {
+    "json".to_string()
+}
+
+fn default_flush_records() -> usize {
+    0
+}
+
+fn default_group_commit_ms() -> u64 {
+    0
+}
+
+fn default_load_truncated() -> bool {
+    true
+}
+
+fn default_snapshot_path() -> PathBuf {
+    PathBuf::from("./data/dump.spdb")
+}
+
+fn default_snapshot_interval() -> u64 {
+    0
+}
+
 fn default_log_level() -> String {
     "info".to_string()
 }
@@ -164,18 +329,41 @@ impl Default for SpatioConfig {
                 port: default_port(),
                 max_connections: default_max_connections(),
                 timeout: default_timeout(),
+                pidfile: None,
+                output_precision: None,
+                distance_metric: default_distance_metric(),
+                stats_retention_hours: default_stats_retention_hours(),
+                debug_testing: false,
+                requirepass: None,
+                databases: default_databases(),
+                grpc_port: None,
             },
             storage: StorageConfig {
                 data_dir: default_data_dir(),
                 max_children: default_max_children(),
+                unload_idle_minutes: default_unload_idle_minutes(),
+                min_free_disk_mb: default_min_free_disk_mb(),
+                ephemeral: false,
+                strict_geojson: Vec::new(),
             },
             aof: AofConfig {
                 enabled: default_aof_enabled(),
                 filename: default_aof_filename(),
                 sync_policy: default_sync_policy(),
+                format: default_aof_format(),
                 auto_rewrite_enabled: default_auto_rewrite(),
                 auto_rewrite_min_size: default_auto_rewrite_min_size(),
                 auto_rewrite_percentage: default_auto_rewrite_percentage(),
+                stall_threshold_ms: default_stall_threshold_ms(),
+                stall_fallback: default_stall_fallback(),
+                flush_bytes: default_flush_bytes(),
+                flush_records: default_flush_records(),
+                group_commit_ms: default_group_commit_ms(),
+                load_truncated: default_load_truncated(),
+            },
+            snapshot: SnapshotConfig {
+                path: default_snapshot_path(),
+                interval: default_snapshot_interval(),
             },
             logging: LoggingConfig {
                 level: default_log_level(),
@@ -192,7 +380,9 @@ impl SpatioConfig {
     /// 配置加载顺序（优先级从低到高）：
     /// 1. 默认配置（内嵌的 default.toml）
     /// 2. 用户配置文件（可选）
-    /// 3. 环境变量（SPATIO__ 前缀，使用双下划线分隔嵌套）
+    /// 3. 配置文件中选中的 profile（`[profile.<name>]`，见 [`load_with_warnings`](Self::load_with_warnings)）
+    /// 4. 环境变量（SPATIO__ 前缀，使用双下划线分隔嵌套）
+    /// 5. 命令行覆盖
     ///
     /// # 示例
     ///
@@ -203,22 +393,95 @@ impl SpatioConfig {
     /// let config = SpatioConfig::from_file("spatio.toml").unwrap();
     /// ```
     pub fn from_file(path: &str) -> crate::Result<Self> {
-        let settings = config::Config::builder()
+        Self::from_file_with_overrides(path, &[])
+    }
+
+    /// 从文件加载配置，并应用命令行覆盖（优先级最高，高于环境变量）
+    ///
+    /// # 示例
+    ///
+    /// ```no_run
+    /// use spatio::config::{split_overrides, SpatioConfig};
+    ///
+    /// let (_args, overrides) = split_overrides(std::env::args()).unwrap();
+    /// let config = SpatioConfig::from_file_with_overrides("spatio.toml", &overrides).unwrap();
+    /// ```
+    pub fn from_file_with_overrides(
+        path: &str,
+        overrides: &[ConfigOverride],
+    ) -> crate::Result<Self> {
+        Ok(Self::load_with_warnings(path, None, overrides)?.0)
+    }
+
+    /// 与 [`from_file_with_overrides`](Self::from_file_with_overrides) 相同，
+    /// 同时返回被忽略的未知键（拼写错误的键不会导致加载失败，由调用方打印警告）
+    ///
+    /// `profile` 选择配置文件中的 `[profile.<name>]` 表，合并在基础配置之上，
+    /// 一个文件即可描述多个部署环境；选择了不存在的 profile 时返回错误
+    pub fn load_with_warnings(
+        path: &str,
+        profile: Option<&str>,
+        overrides: &[ConfigOverride],
+    ) -> crate::Result<(Self, Vec<UnknownKey>)> {
+        let mut builder = config::Config::builder()
             // 1. 加载默认配置（内嵌）
             .add_source(config::File::from_str(
                 include_str!("default.toml"),
                 config::FileFormat::Toml,
             ))
             // 2. 加载用户配置（可选，不存在不报错）
-            .add_source(config::File::with_name(path).required(false))
-            // 3. 加载环境变量（SPATIO__ 前缀，双下划线分隔嵌套）
-            .add_source(config::Environment::with_prefix("SPATIO").separator("__"))
+            .add_source(config::File::with_name(path).required(false));
+
+        // 3. 选中的 profile
+        if let Some(name) = profile {
+            builder = builder.add_source(Self::profile_source(path, name)?);
+        }
+
+        // 4. 加载环境变量（SPATIO__ 前缀，双下划线分隔嵌套）
+        builder = builder.add_source(config::Environment::with_prefix("SPATIO").separator("__"));
+
+        // 5. 命令行覆盖（--section.key value）
+        for o in overrides {
+            builder = builder
+                .set_override(o.key.as_str(), o.value.as_str())
+                .map_err(|e| format!("Invalid override --{}: {}", o.key, e))?;
+        }
+
+        let settings = builder
             .build()
             .map_err(|e| format!("Failed to load config: {}", e))?;
 
-        Ok(settings
+        let merged: serde_json::Value = settings
+            .clone()
+            .try_deserialize()
+            .map_err(|e| format!("Failed to parse config: {}", e))?;
+        let unknown = schema::unknown_keys(&merged);
+
+        let config = settings
             .try_deserialize()
-            .map_err(|e| format!("Failed to parse config: {}", e))?)
+            .map_err(|e| format!("Failed to parse config: {}", e))?;
+        Ok((config, unknown))
+    }
+
+    /// 配置文件中 `[profile.<name>]` 表的内容，作为一个独立的配置源
+    fn profile_source(path: &str, name: &str) -> crate::Result<config::Config> {
+        let file = config::Config::builder()
+            .add_source(config::File::with_name(path).required(false))
+            .build()
+            .map_err(|e| format!("Failed to load config: {}", e))?;
+        let table: config::Map<String, config::Value> = file
+            .get(&format!("{}.{}", PROFILE_TABLE, name))
+            .map_err(|_| format!("Profile '{}' is not defined in {}", name, path))?;
+
+        let mut builder = config::Config::builder();
+        for (key, value) in table {
+            builder = builder
+                .set_override(key.as_str(), value)
+                .map_err(|e| format!("Invalid profile '{}': {}", name, e))?;
+        }
+        Ok(builder
+            .build()
+            .map_err(|e| format!("Invalid profile '{}': {}", name, e))?)
     }
 
     /// 保存配置到文件
@@ -266,6 +529,68 @@ impl SpatioConfig {
             }
         }
 
+        // 验证 AOF 记录格式
+        match self.aof.format.as_str() {
+            "json" | "framed" => {}
+            _ => {
+                return Err(format!(
+                    "Invalid AOF format: '{}'. Must be one of: json, framed",
+                    self.aof.format
+                ))
+            }
+        }
+
+        // 验证输出精度
+        if let Some(precision) = self.server.output_precision {
+            if precision > MAX_COORDINATE_PRECISION {
+                return Err(format!(
+                    "Output precision {} is out of range (0-{})",
+                    precision, MAX_COORDINATE_PRECISION
+                ));
+            }
+        }
+
+        // 验证距离度量
+        if DistanceMetric::parse(&self.server.distance_metric).is_none() {
+            return Err(format!(
+                "Invalid distance metric: '{}'. Must be one of: haversine, euclidean, cosines",
+                self.server.distance_metric
+            ));
+        }
+
+        // 验证统计历史保留时间
+        if self.server.stats_retention_hours == 0
+            || self.server.stats_retention_hours > MAX_STATS_RETENTION_HOURS
+        {
+            return Err(format!(
+                "Stats retention {} hours is out of range (1-{})",
+                self.server.stats_retention_hours, MAX_STATS_RETENTION_HOURS
+            ));
+        }
+
+        // 验证命名空间数量
+        if self.server.databases == 0 {
+            return Err("Databases must be at least 1".to_string());
+        }
+        if self.server.requirepass.as_deref() == Some("") {
+            return Err(
+                "requirepass must not be empty, leave it unset to disable AUTH".to_string(),
+            );
+        }
+
+        // 验证 gRPC 端口
+        if let Some(grpc_port) = self.server.grpc_port {
+            if !cfg!(feature = "grpc") {
+                return Err("grpc_port requires a server built with the `grpc` feature".to_string());
+            }
+            if grpc_port < 1024 || grpc_port == self.server.port {
+                return Err(format!(
+                    "gRPC port {} must be at least 1024 and differ from the server port",
+                    grpc_port
+                ));
+            }
+        }
+
         // 验证日志级别
         match self.logging.level.as_str() {
             "trace" | "debug" | "info" | "warn" | "error" => {}
@@ -282,29 +607,157 @@ impl SpatioConfig {
             return Err("Log output is 'file' but log_file path is not specified".to_string());
         }
 
-        // 验证数据目录（尝试创建）
-        if !self.storage.data_dir.exists() {
-            std::fs::create_dir_all(&self.storage.data_dir).map_err(|e| {
-                format!(
-                    "Failed to create data directory '{}': {}",
-                    self.storage.data_dir.display(),
-                    e
-                )
-            })?;
+        // 纯内存模式不使用磁盘，与所有依赖数据目录的选项互斥
+        if self.storage.ephemeral {
+            if self.aof.enabled {
+                return Err("Ephemeral mode requires aof.enabled = false".to_string());
+            }
+            if self.storage.unload_idle_minutes > 0 {
+                return Err(
+                    "Ephemeral mode cannot unload idle collections (storage.unload_idle_minutes)"
+                        .to_string(),
+                );
+            }
+            if self.storage.min_free_disk_mb > 0 {
+                return Err(
+                    "Ephemeral mode does not monitor disk space (storage.min_free_disk_mb)"
+                        .to_string(),
+                );
+            }
+            if self.snapshot.interval > 0 {
+                return Err("Ephemeral mode cannot save snapshots (snapshot.interval)".to_string());
+            }
+            return Ok(());
+        }
+
+        // 验证路径（只检查，不创建；目录在 prepare_directories 中创建）
+        if self.storage.data_dir.as_os_str().is_empty() {
+            return Err("Data directory must not be empty".to_string());
+        }
+        if self.storage.data_dir.is_file() {
+            return Err(format!(
+                "Data directory '{}' is an existing file",
+                self.storage.data_dir.display()
+            ));
+        }
+        if self.aof.enabled {
+            if self.aof.filename.file_name().is_none() {
+                return Err(format!(
+                    "AOF filename '{}' does not name a file",
+                    self.aof.filename.display()
+                ));
+            }
+            if self.aof.filename.is_dir() {
+                return Err(format!(
+                    "AOF filename '{}' is an existing directory",
+                    self.aof.filename.display()
+                ));
+            }
+        }
+        if self.snapshot.path.file_name().is_none() {
+            return Err(format!(
+                "Snapshot path '{}' does not name a file",
+                self.snapshot.path.display()
+            ));
+        }
+        if self.snapshot.path.is_dir() {
+            return Err(format!(
+                "Snapshot path '{}' is an existing directory",
+                self.snapshot.path.display()
+            ));
         }
 
         Ok(())
     }
 
+    /// 切换为纯内存模式，关闭所有会写磁盘的选项（对应 `--ephemeral` 命令行参数）
+    pub fn set_ephemeral(&mut self) {
+        self.storage.ephemeral = true;
+        self.storage.unload_idle_minutes = 0;
+        self.storage.min_free_disk_mb = 0;
+        self.aof.enabled = false;
+        self.snapshot.interval = 0;
+    }
+
+    /// 创建数据目录、AOF 文件和快照文件所在的目录
+    ///
+    /// 与 [`validate`](Self::validate) 分开：验证配置（例如 `--verify-recovery`、测试）不会在磁盘上留下目录
+    pub fn prepare_directories(&self) -> Result<(), String> {
+        if self.storage.ephemeral {
+            return Ok(());
+        }
+
+        let mut dirs = vec![self.storage.data_dir.as_path()];
+        let mut files = vec![self.snapshot.path.as_path()];
+        if self.aof.enabled {
+            files.push(self.aof.filename.as_path());
+        }
+        // 只有文件名的相对路径（例如 `appendonly.aof`）的父目录为空，表示当前目录
+        dirs.extend(
+            files
+                .into_iter()
+                .filter_map(|file| file.parent())
+                .filter(|parent| !parent.as_os_str().is_empty()),
+        );
+
+        for dir in dirs {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create directory '{}': {}", dir.display(), e))?;
+        }
+        Ok(())
+    }
+
     /// 打印配置摘要
     pub fn print_summary(&self) {
         println!("📋 Spatio Configuration:");
         println!("   Server:      {}:{}", self.server.host, self.server.port);
         println!("   Max Connections: {}", self.server.max_connections);
         println!("   Timeout:     {} seconds", self.server.timeout);
+        if let Some(pidfile) = &self.server.pidfile {
+            println!("   PID File:    {}", pidfile.display());
+        }
+        if let Some(precision) = self.server.output_precision {
+            println!("   Precision:   {} decimals", precision);
+        }
+        if self.server.distance_metric != default_distance_metric() {
+            println!("   Distance:    {}", self.server.distance_metric);
+        }
+        println!(
+            "   Stats History: {} hours",
+            self.server.stats_retention_hours
+        );
+        if self.server.debug_testing {
+            println!("   Debug Testing: enabled (DEBUG SET-ACTIVE-EXPIRE, DEBUG SLEEP)");
+        }
+        if self.server.requirepass.is_some() {
+            println!("   Auth:        password required");
+        }
+        println!("   Databases:   {}", self.server.databases);
+        if let Some(grpc_port) = self.server.grpc_port {
+            println!("   gRPC Port:   {}", grpc_port);
+        }
         println!();
-        println!("   Data Dir:    {}", self.storage.data_dir.display());
+        if self.storage.ephemeral {
+            println!("   Mode:        ephemeral (in-memory only, nothing is persisted)");
+        } else {
+            println!("   Data Dir:    {}", self.storage.data_dir.display());
+        }
         println!("   Max Children: {}", self.storage.max_children);
+        if self.storage.unload_idle_minutes > 0 {
+            println!(
+                "   Idle Unload: after {} minutes",
+                self.storage.unload_idle_minutes
+            );
+        }
+        if self.storage.min_free_disk_mb > 0 {
+            println!("   Min Free Disk: {} MB", self.storage.min_free_disk_mb);
+        }
+        if !self.storage.strict_geojson.is_empty() {
+            println!(
+                "   RFC 7946 Strict: {}",
+                self.storage.strict_geojson.join(", ")
+            );
+        }
         println!();
         println!(
             "   AOF:         {}",
@@ -317,14 +770,38 @@ impl SpatioConfig {
         if self.aof.enabled {
             println!("   AOF File:    {}", self.aof.filename.display());
             println!("   Sync Policy: {}", self.aof.sync_policy);
-            println!(
-                "   Auto Rewrite: {}",
-                if self.aof.auto_rewrite_enabled {
-                    "enabled"
-                } else {
-                    "disabled"
-                }
-            );
+            println!("   Format:      {}", self.aof.format);
+            if self.aof.auto_rewrite_enabled {
+                println!(
+                    "   Auto Rewrite: enabled (>= {} MB, +{}%)",
+                    self.aof.auto_rewrite_min_size, self.aof.auto_rewrite_percentage
+                );
+            } else {
+                println!("   Auto Rewrite: disabled");
+            }
+            if self.aof.sync_policy == "always" && self.aof.group_commit_ms > 0 {
+                println!("   Group Commit: {} ms", self.aof.group_commit_ms);
+            }
+            if self.aof.stall_threshold_ms > 0 {
+                println!(
+                    "   Stall Detect: {} ms (fallback {})",
+                    self.aof.stall_threshold_ms,
+                    if self.aof.stall_fallback {
+                        "enabled"
+                    } else {
+                        "disabled"
+                    }
+                );
+            }
+        }
+        if !self.storage.ephemeral {
+            println!("   Snapshot:    {}", self.snapshot.path.display());
+            if self.snapshot.interval > 0 {
+                println!(
+                    "   Snapshot Interval: every {} seconds",
+                    self.snapshot.interval
+                );
+            }
         }
         println!();
         println!("   Log Level:   {}", self.logging.level);
@@ -347,6 +824,17 @@ mod tests {
         assert_eq!(config.server.port, 6379);
         assert!(config.aof.enabled);
         assert_eq!(config.aof.sync_policy, "everysec");
+        assert_eq!(config.aof.format, "json");
+        assert_eq!(config.aof.stall_threshold_ms, 500);
+        assert!(!config.aof.stall_fallback);
+        assert_eq!(config.aof.flush_bytes, 1024 * 1024);
+        assert_eq!(config.aof.flush_records, 0);
+        assert_eq!(config.aof.group_commit_ms, 0);
+        assert!(config.aof.load_truncated);
+        assert_eq!(config.storage.unload_idle_minutes, 0);
+        assert_eq!(config.storage.min_free_disk_mb, 0);
+        assert_eq!(config.snapshot.path, PathBuf::from("./data/dump.spdb"));
+        assert_eq!(config.snapshot.interval, 0);
     }
 
     #[test]
@@ -366,11 +854,273 @@ mod tests {
         assert!(config.validate().is_err());
         config.aof.sync_policy = "everysec".to_string();
 
+        config.aof.format = "binary".to_string();
+        assert!(config.validate().is_err());
+        config.aof.format = "framed".to_string();
+        assert!(config.validate().is_ok());
+
+        // 输出精度超出范围
+        config.server.output_precision = Some(6);
+        assert!(config.validate().is_ok());
+        config.server.output_precision = Some(16);
+        assert!(config.validate().is_err());
+        config.server.output_precision = None;
+
+        // 未知的距离度量
+        config.server.distance_metric = "manhattan".to_string();
+        assert!(config.validate().is_err());
+        config.server.distance_metric = "euclidean".to_string();
+        assert!(config.validate().is_ok());
+
+        // 统计历史保留时间超出范围
+        config.server.stats_retention_hours = 0;
+        assert!(config.validate().is_err());
+        config.server.stats_retention_hours = MAX_STATS_RETENTION_HOURS + 1;
+        assert!(config.validate().is_err());
+        config.server.stats_retention_hours = DEFAULT_STATS_RETENTION_HOURS;
+
+        // 命名空间数量和空密码
+        config.server.databases = 0;
+        assert!(config.validate().is_err());
+        config.server.databases = 16;
+        config.server.requirepass = Some(String::new());
+        assert!(config.validate().is_err());
+        config.server.requirepass = Some("secret".to_string());
+        assert!(config.validate().is_ok());
+        config.server.requirepass = None;
+
+        // gRPC 端口与 RESP 端口相同，或者没有编译 grpc feature
+        config.server.grpc_port = Some(config.server.port);
+        assert!(config.validate().is_err());
+        config.server.grpc_port = Some(50051);
+        assert_eq!(config.validate().is_ok(), cfg!(feature = "grpc"));
+        config.server.grpc_port = None;
+
         // 无效日志级别
         config.logging.level = "invalid".to_string();
         assert!(config.validate().is_err());
     }
 
+    #[test]
+    fn test_validate_does_not_create_directories() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = SpatioConfig::default();
+        config.storage.data_dir = temp_dir.path().join("data dir").join("数据");
+        config.aof.filename = temp_dir.path().join("aof").join("appendonly.aof");
+        config.snapshot.path = temp_dir.path().join("snapshots").join("dump.spdb");
+
+        assert!(config.validate().is_ok());
+        assert!(!config.storage.data_dir.exists());
+        assert!(!temp_dir.path().join("aof").exists());
+        assert!(!temp_dir.path().join("snapshots").exists());
+
+        config.prepare_directories().unwrap();
+        assert!(config.storage.data_dir.is_dir());
+        assert!(temp_dir.path().join("aof").is_dir());
+        assert!(temp_dir.path().join("snapshots").is_dir());
+
+        // 再次创建是幂等的
+        config.prepare_directories().unwrap();
+    }
+
+    #[test]
+    fn test_validate_rejects_invalid_paths() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let file = temp_dir.path().join("not-a-dir");
+        std::fs::write(&file, b"").unwrap();
+
+        let mut config = SpatioConfig::default();
+        config.storage.data_dir = file.clone();
+        assert!(config.validate().unwrap_err().contains("existing file"));
+
+        config.storage.data_dir = temp_dir.path().to_path_buf();
+        config.aof.filename = temp_dir.path().to_path_buf();
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .contains("existing directory"));
+
+        config.aof.filename = PathBuf::from("..");
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .contains("does not name a file"));
+
+        // AOF 关闭时不检查 AOF 路径；只有文件名的相对路径是合法的
+        config.aof.enabled = false;
+        assert!(config.validate().is_ok());
+        config.aof.enabled = true;
+        config.aof.filename = PathBuf::from("appendonly.aof");
+        assert!(config.validate().is_ok());
+
+        config.snapshot.path = temp_dir.path().to_path_buf();
+        assert!(config.validate().unwrap_err().contains("Snapshot path"));
+    }
+
+    #[test]
+    fn test_ephemeral_mode() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = SpatioConfig::default();
+        config.storage.data_dir = temp_dir.path().join("data");
+
+        // 与 AOF 和依赖数据目录的选项冲突
+        config.storage.ephemeral = true;
+        assert!(config.validate().unwrap_err().contains("aof.enabled"));
+        config.aof.enabled = false;
+        config.storage.unload_idle_minutes = 5;
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .contains("unload_idle_minutes"));
+
+        config.storage.unload_idle_minutes = 0;
+        config.snapshot.interval = 60;
+        assert!(config.validate().unwrap_err().contains("snapshot.interval"));
+
+        // set_ephemeral 关闭所有冲突的选项；数据目录不会被创建
+        config.storage.unload_idle_minutes = 5;
+        config.storage.min_free_disk_mb = 100;
+        config.aof.enabled = true;
+        config.set_ephemeral();
+        assert!(config.validate().is_ok());
+        config.prepare_directories().unwrap();
+        assert!(!config.storage.data_dir.exists());
+    }
+
+    #[test]
+    fn test_command_line_overrides() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("spatio.toml");
+        std::fs::write(
+            &path,
+            "[server]\nport = 6400\n[aof]\nsync_policy = \"no\"\n",
+        )
+        .unwrap();
+
+        let (_, overrides) = split_overrides(
+            [
+                "spatio-server",
+                "--server.port",
+                "7000",
+                "--aof.sync-policy=always",
+                "--aof.enabled=false",
+                "--storage.min-free-disk-mb",
+                "64",
+            ]
+            .map(String::from),
+        )
+        .unwrap();
+        let config =
+            SpatioConfig::from_file_with_overrides(path.to_str().unwrap(), &overrides).unwrap();
+
+        assert_eq!(config.server.port, 7000);
+        assert_eq!(config.aof.sync_policy, "always");
+        assert!(!config.aof.enabled);
+        assert_eq!(config.storage.min_free_disk_mb, 64);
+        // 未覆盖的值仍来自配置文件和默认值
+        assert_eq!(config.server.host, "127.0.0.1");
+
+        // 类型错误在反序列化时报告
+        let bad = vec![ConfigOverride {
+            key: "server.port".to_string(),
+            value: "seven".to_string(),
+        }];
+        assert!(SpatioConfig::from_file_with_overrides(path.to_str().unwrap(), &bad).is_err());
+    }
+
+    #[test]
+    fn test_load_reports_unknown_keys() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("spatio.toml");
+        std::fs::write(
+            &path,
+            "[server]\nprot = 7000\n[storage]\ndata_dir = \"./d\"\n[aof]\nsync_polcy = \"always\"\n",
+        )
+        .unwrap();
+
+        let overrides = vec![ConfigOverride {
+            key: "logging.levle".to_string(),
+            value: "debug".to_string(),
+        }];
+        let (config, unknown) =
+            SpatioConfig::load_with_warnings(path.to_str().unwrap(), None, &overrides).unwrap();
+
+        // 未知键被忽略，其他配置正常生效
+        assert_eq!(config.server.port, 6379);
+        assert_eq!(config.storage.data_dir, PathBuf::from("./d"));
+
+        let mut found: Vec<(String, Option<String>)> = unknown
+            .into_iter()
+            .map(|k| (k.path, k.suggestion))
+            .collect();
+        found.sort();
+        assert_eq!(
+            found,
+            vec![
+                ("aof.sync_polcy".into(), Some("aof.sync_policy".into())),
+                ("logging.levle".into(), Some("logging.level".into())),
+                ("server.prot".into(), Some("server.port".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_profiles() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("spatio.toml");
+        std::fs::write(
+            &path,
+            r#"
+[server]
+port = 6400
+
+[aof]
+sync_policy = "no"
+
+[profile.production.server]
+host = "0.0.0.0"
+
+[profile.production.aof]
+sync_policy = "always"
+
+[profile.dev.storage]
+ephemeral = true
+"#,
+        )
+        .unwrap();
+        let path = path.to_str().unwrap();
+
+        // 不选 profile：只有基础配置，profile 表本身不算未知键
+        let (base, unknown) = SpatioConfig::load_with_warnings(path, None, &[]).unwrap();
+        assert_eq!(base.server.host, "127.0.0.1");
+        assert_eq!(base.aof.sync_policy, "no");
+        assert!(unknown.is_empty(), "{:?}", unknown);
+
+        // profile 合并在基础配置之上，未设置的键保留基础配置的值
+        let (production, _) =
+            SpatioConfig::load_with_warnings(path, Some("production"), &[]).unwrap();
+        assert_eq!(production.server.host, "0.0.0.0");
+        assert_eq!(production.server.port, 6400);
+        assert_eq!(production.aof.sync_policy, "always");
+        assert!(!production.storage.ephemeral);
+
+        // 命令行覆盖的优先级高于 profile
+        let overrides = vec![ConfigOverride {
+            key: "aof.sync_policy".to_string(),
+            value: "everysec".to_string(),
+        }];
+        let (overridden, _) =
+            SpatioConfig::load_with_warnings(path, Some("production"), &overrides).unwrap();
+        assert_eq!(overridden.aof.sync_policy, "everysec");
+
+        let (dev, _) = SpatioConfig::load_with_warnings(path, Some("dev"), &[]).unwrap();
+        assert!(dev.storage.ephemeral);
+        assert_eq!(dev.aof.sync_policy, "no");
+
+        let err = SpatioConfig::load_with_warnings(path, Some("staging"), &[]).unwrap_err();
+        assert!(err.to_string().contains("Profile 'staging' is not defined"));
+    }
+
     #[test]
     fn test_save_and_load() {
         use tempfile::NamedTempFile;
@@ -0,0 +1,146 @@
+/// 命令行中的一条配置覆盖：`--server.port 7000` 或 `--aof.sync-policy=always`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigOverride {
+    /// 配置路径，`-` 已转换为 `_`，例如 `aof.sync_policy`
+    pub key: String,
+    /// 原始字符串值，类型转换在反序列化时进行（与环境变量相同）
+    pub value: String,
+}
+
+/// 从命令行参数中分离出配置覆盖，返回剩余参数（交给 clap）和覆盖列表
+///
+/// 名称中带 `.` 的长参数视为配置路径，其余参数原样保留；`--` 之后的参数不处理
+pub fn split_overrides<I>(args: I) -> Result<(Vec<String>, Vec<ConfigOverride>), String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut remaining = Vec::new();
+    let mut overrides = Vec::new();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            remaining.push(arg);
+            remaining.extend(args.by_ref());
+            break;
+        }
+
+        let Some(name) = arg.strip_prefix("--").filter(|name| is_config_path(name)) else {
+            remaining.push(arg);
+            continue;
+        };
+
+        let (path, value) = match name.split_once('=') {
+            Some((path, value)) => (path, value.to_string()),
+            None => match args.next() {
+                Some(value) => (name, value),
+                None => {
+                    return Err(format!(
+                        "Missing value for configuration override --{}",
+                        name
+                    ))
+                }
+            },
+        };
+
+        overrides.push(ConfigOverride {
+            key: path.replace('-', "_"),
+            value,
+        });
+    }
+
+    Ok((remaining, overrides))
+}
+
+/// `section.key`（允许 `=value` 后缀）：至少两段，每段只包含字母、数字、`_` 和 `-`
+fn is_config_path(name: &str) -> bool {
+    let path = name.split_once('=').map_or(name, |(path, _)| path);
+    path.contains('.')
+        && path.split('.').all(|segment| {
+            !segment.is_empty()
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_split_overrides() {
+        let (remaining, overrides) = split_overrides(args(&[
+            "spatio-server",
+            "--server.port",
+            "7000",
+            "-c",
+            "prod.toml",
+            "--aof.sync-policy=always",
+            "--log-level",
+            "debug",
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            remaining,
+            args(&["spatio-server", "-c", "prod.toml", "--log-level", "debug"])
+        );
+        assert_eq!(
+            overrides,
+            vec![
+                ConfigOverride {
+                    key: "server.port".to_string(),
+                    value: "7000".to_string()
+                },
+                ConfigOverride {
+                    key: "aof.sync_policy".to_string(),
+                    value: "always".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_overrides_edge_cases() {
+        // 值里的 `=` 和 `.` 保留；`--` 之后的参数原样传递
+        let (remaining, overrides) = split_overrides(args(&[
+            "spatio-server",
+            "--logging.log-file=./logs/a=b.log",
+            "--verify-recovery",
+            "./data/appendonly.aof",
+            "--",
+            "--server.port",
+            "1",
+        ]))
+        .unwrap();
+        assert_eq!(overrides.len(), 1);
+        assert_eq!(overrides[0].key, "logging.log_file");
+        assert_eq!(overrides[0].value, "./logs/a=b.log");
+        assert_eq!(
+            remaining,
+            args(&[
+                "spatio-server",
+                "--verify-recovery",
+                "./data/appendonly.aof",
+                "--",
+                "--server.port",
+                "1"
+            ])
+        );
+
+        // 不是配置路径的参数交给 clap 报错
+        let (remaining, overrides) =
+            split_overrides(args(&["spatio-server", "--.port", "--server."])).unwrap();
+        assert!(overrides.is_empty());
+        assert_eq!(remaining.len(), 3);
+
+        assert!(split_overrides(args(&["spatio-server", "--server.port"]))
+            .unwrap_err()
+            .contains("--server.port"));
+    }
+}
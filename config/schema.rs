@@ -0,0 +1,160 @@
+use super::{SpatioConfig, PROFILE_TABLE};
+use serde_json::{Map, Value};
+use std::fmt;
+
+/// 配置中无法识别的键（通常是拼写错误），加载时会被忽略
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownKey {
+    /// 完整路径，例如 `server.prot`
+    pub path: String,
+    /// 最相近的合法路径
+    pub suggestion: Option<String>,
+}
+
+impl fmt::Display for UnknownKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown config key '{}' is ignored", self.path)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (did you mean '{}'?)", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// 找出合并后的配置（文件、环境变量和命令行覆盖）中不属于 [`SpatioConfig`] 的键
+///
+/// 合法的键取自默认配置的序列化结果，新增字段不需要额外登记。
+/// `[profile.*]` 表不直接生效，选中的 profile 已经合并到顶层，因此跳过
+pub fn unknown_keys(merged: &Value) -> Vec<UnknownKey> {
+    let schema = serde_json::to_value(SpatioConfig::default()).unwrap_or_default();
+    let mut known = Vec::new();
+    collect_paths(&schema, "", &mut known);
+
+    let mut unknown = Vec::new();
+    if let (Value::Object(merged), Value::Object(schema)) = (merged, &schema) {
+        let mut merged = merged.clone();
+        merged.remove(PROFILE_TABLE);
+        find_unknown(&merged, schema, "", &known, &mut unknown);
+    }
+    unknown
+}
+
+fn find_unknown(
+    merged: &Map<String, Value>,
+    schema: &Map<String, Value>,
+    prefix: &str,
+    known: &[String],
+    unknown: &mut Vec<UnknownKey>,
+) {
+    for (key, value) in merged {
+        let path = join(prefix, key);
+        match (schema.get(key), value) {
+            (Some(Value::Object(schema)), Value::Object(merged)) => {
+                find_unknown(merged, schema, &path, known, unknown);
+            }
+            (Some(_), _) => {}
+            (None, _) => unknown.push(UnknownKey {
+                suggestion: suggest(&path, known),
+                path,
+            }),
+        }
+    }
+}
+
+/// 所有叶子和表的路径
+fn collect_paths(value: &Value, prefix: &str, paths: &mut Vec<String>) {
+    if let Value::Object(map) = value {
+        for (key, value) in map {
+            let path = join(prefix, key);
+            collect_paths(value, &path, paths);
+            paths.push(path);
+        }
+    }
+}
+
+fn join(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+/// 编辑距离足够小的最相近路径；`-` 写成 `_` 之外的拼写错误最多容忍三分之一的字符
+fn suggest(path: &str, known: &[String]) -> Option<String> {
+    let normalized = path.replace('-', "_");
+    let max_distance = (normalized.len() / 3).max(2);
+    known
+        .iter()
+        .map(|candidate| (edit_distance(&normalized, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.clone())
+}
+
+/// Levenshtein 距离
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("port", "port"), 0);
+        assert_eq!(edit_distance("prot", "port"), 2);
+        assert_eq!(edit_distance("sever", "server"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_unknown_keys_with_suggestions() {
+        let merged = json!({
+            "server": {"host": "0.0.0.0", "prot": 7000, "pidfile": "/run/spatio.pid"},
+            "sever": {"port": 1},
+            "aof": {"enabled": true, "sync-policy": "always"},
+            "logging": {"level": "info"},
+            "metrics": {"enabled": true}
+        });
+
+        let unknown = unknown_keys(&merged);
+        let find = |path: &str| unknown.iter().find(|k| k.path == path).cloned();
+
+        assert_eq!(unknown.len(), 4);
+        assert_eq!(
+            find("server.prot").unwrap().suggestion.as_deref(),
+            Some("server.port")
+        );
+        assert_eq!(find("sever").unwrap().suggestion.as_deref(), Some("server"));
+        assert_eq!(
+            find("aof.sync-policy").unwrap().suggestion.as_deref(),
+            Some("aof.sync_policy")
+        );
+        assert_eq!(find("metrics").unwrap().suggestion, None);
+        assert_eq!(
+            find("server.prot").unwrap().to_string(),
+            "unknown config key 'server.prot' is ignored (did you mean 'server.port'?)"
+        );
+    }
+
+    #[test]
+    fn test_default_config_has_no_unknown_keys() {
+        let default = serde_json::to_value(SpatioConfig::default()).unwrap();
+        assert!(unknown_keys(&default).is_empty());
+    }
+}
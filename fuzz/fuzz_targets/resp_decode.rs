@@ -0,0 +1,20 @@
+#![no_main]
+
+//! RESP 解码器的 fuzz target
+//!
+//! 运行：`cargo +nightly fuzz run resp_decode`（需要 `cargo install cargo-fuzz`）
+
+use libfuzzer_sys::fuzz_target;
+use spatio::protocol::RespParser;
+
+fuzz_target!(|data: &[u8]| {
+    // 任意输入都不能 panic；完整帧占用的字节数不超过输入长度，且只解码该帧时结果相同
+    // （按 Debug 输出比较，RESP3 的 `,nan` 与自身不相等）
+    if let Ok(Some((value, consumed))) = RespParser::decode(data) {
+        assert!(consumed > 0 && consumed <= data.len());
+        assert_eq!(
+            format!("{:?}", RespParser::decode(&data[..consumed]).unwrap()),
+            format!("{:?}", Some((value, consumed)))
+        );
+    }
+});
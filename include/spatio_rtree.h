@@ -0,0 +1,78 @@
+#ifndef SPATIO_RTREE_H
+#define SPATIO_RTREE_H
+
+/* Generated by cbindgen from rtree/ffi.rs, do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// 操作成功
+#define SPATIO_OK 0
+
+// 参数错误：空指针或非法边界框
+#define SPATIO_ERR_INVALID_ARGUMENT -1
+
+// 内部错误
+#define SPATIO_ERR_INTERNAL -2
+
+// 不透明句柄：R-tree 加上 ID -> 边界框，删除时需要边界框定位叶子节点
+typedef struct SpatioRTree SpatioRTree;
+
+// 轴对齐边界框，要求坐标有限且 `min <= max`
+typedef struct SpatioBBox {
+  double min_x;
+  double min_y;
+  double max_x;
+  double max_y;
+} SpatioBBox;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// 创建 R-tree，`max_entries` 为节点最大条目数（至少为 2），参数非法时返回 NULL
+struct SpatioRTree *spatio_rtree_new(uintptr_t max_entries);
+
+// 释放 R-tree，传入 NULL 时什么也不做
+//
+// # Safety
+// `tree` 必须是 [`spatio_rtree_new`] 返回的句柄，且只能释放一次
+void spatio_rtree_free(struct SpatioRTree *tree);
+
+// 插入对象，ID 已存在时替换其边界框
+//
+// # Safety
+// `tree` 必须是有效句柄或 NULL
+int32_t spatio_rtree_insert(struct SpatioRTree *tree, uint64_t id, struct SpatioBBox bbox);
+
+// 删除对象：返回 1 表示已删除，0 表示 ID 不存在，负数为错误码
+//
+// # Safety
+// `tree` 必须是有效句柄或 NULL
+int32_t spatio_rtree_delete(struct SpatioRTree *tree, uint64_t id);
+
+// 查询与 `query` 相交的对象，按 ID 升序写入 `out`（最多 `capacity` 个）
+//
+// 返回匹配总数（可能大于 `capacity`，调用方可据此扩容后重试），负数为错误码。
+// `capacity` 为 0 时 `out` 可以为 NULL，用于只获取数量
+//
+// # Safety
+// `tree` 必须是有效句柄或 NULL；`out` 指向至少 `capacity` 个 `uint64_t` 的可写内存
+int64_t spatio_rtree_search(const struct SpatioRTree *tree,
+                            struct SpatioBBox query,
+                            uint64_t *out,
+                            uintptr_t capacity);
+
+// 对象数量，`tree` 为 NULL 时返回 0
+//
+// # Safety
+// `tree` 必须是有效句柄或 NULL
+uintptr_t spatio_rtree_len(const struct SpatioRTree *tree);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* SPATIO_RTREE_H */
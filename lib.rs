@@ -9,7 +9,7 @@ pub mod storage;
 use std::error::Error;
 
 // 重新导出主要的公共接口
-pub use rtree::{Entry, GeoItem, Node, RTree, Rectangle};
+pub use rtree::{Entry, GeoItem, Node, RTree, Rectangle, StoredValue};
 
 // 重新导出常用类型，便于二进制文件使用
 pub use client::{CliArgs, ClientConnection, OutputFormatter};
@@ -0,0 +1,139 @@
+use std::error::Error;
+use std::fmt;
+
+/// 错误回复的前缀（RESP `-CODE message`）
+///
+/// 客户端根据前缀决定重试策略，而不是解析错误信息文本：
+/// - `ERR`：请求本身有问题（语法、参数），重试不会成功
+/// - `WRONGTYPE`：对象类型不支持该操作（例如对点做多边形运算），重试不会成功
+/// - `NOINDEX`：分析命令（AGG、CLUSTER、HULL、GEOMOP、SNAP）的目标 collection 不存在，需要先写入数据
+/// - `LOADING`：服务器正在启动加载快照或重放 AOF，稍后重试
+/// - `BUSY`：已有同类后台任务（BGSAVE、BGREWRITEAOF）在进行，退避后重试
+/// - `READONLY`：服务器当前不接受写入（例如 AOF 无法写入磁盘），读请求仍可用
+/// - `OOM`：内存或磁盘空间不足（`OOM DISK`），释放空间后重试
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    Err,
+    WrongType,
+    NoIndex,
+    Loading,
+    Busy,
+    ReadOnly,
+    Oom,
+}
+
+impl ErrorCode {
+    const ALL: [ErrorCode; 7] = [
+        ErrorCode::Err,
+        ErrorCode::WrongType,
+        ErrorCode::NoIndex,
+        ErrorCode::Loading,
+        ErrorCode::Busy,
+        ErrorCode::ReadOnly,
+        ErrorCode::Oom,
+    ];
+
+    /// 错误回复中使用的前缀
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            ErrorCode::Err => "ERR",
+            ErrorCode::WrongType => "WRONGTYPE",
+            ErrorCode::NoIndex => "NOINDEX",
+            ErrorCode::Loading => "LOADING",
+            ErrorCode::Busy => "BUSY",
+            ErrorCode::ReadOnly => "READONLY",
+            ErrorCode::Oom => "OOM",
+        }
+    }
+
+    /// 是否为暂时性错误：同样的请求在退避后重试可能成功
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ErrorCode::Loading | ErrorCode::Busy | ErrorCode::ReadOnly | ErrorCode::Oom
+        )
+    }
+
+    /// 从错误回复文本中解析前缀，未知前缀视为 `ERR`
+    pub fn from_message(message: &str) -> Self {
+        let prefix = message.split_whitespace().next().unwrap_or_default();
+        Self::ALL
+            .into_iter()
+            .find(|code| code.prefix() == prefix)
+            .unwrap_or(ErrorCode::Err)
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.prefix())
+    }
+}
+
+/// 带错误码的错误
+///
+/// 存储层返回该错误时，命令层会使用对应的前缀回复客户端；
+/// 其他错误统一使用 `ERR`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl CommandError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// 从任意错误中取出错误码，不是 `CommandError` 时返回 `ERR`
+    pub fn code_of(error: &(dyn Error + 'static)) -> ErrorCode {
+        error
+            .downcast_ref::<CommandError>()
+            .map_or(ErrorCode::Err, |e| e.code)
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for CommandError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_round_trip() {
+        for code in ErrorCode::ALL {
+            let message = format!("{} something went wrong", code.prefix());
+            assert_eq!(ErrorCode::from_message(&message), code);
+        }
+        assert_eq!(ErrorCode::from_message("MOVED 1 elsewhere"), ErrorCode::Err);
+        assert_eq!(ErrorCode::from_message(""), ErrorCode::Err);
+    }
+
+    #[test]
+    fn test_retryable() {
+        assert!(ErrorCode::Loading.is_retryable());
+        assert!(ErrorCode::ReadOnly.is_retryable());
+        assert!(!ErrorCode::Err.is_retryable());
+        assert!(!ErrorCode::WrongType.is_retryable());
+        assert!(!ErrorCode::NoIndex.is_retryable());
+    }
+
+    #[test]
+    fn test_code_of() {
+        let typed: Box<dyn Error + Send + Sync> =
+            Box::new(CommandError::new(ErrorCode::Busy, "try again"));
+        assert_eq!(CommandError::code_of(typed.as_ref()), ErrorCode::Busy);
+
+        let plain: Box<dyn Error + Send + Sync> = "boom".into();
+        assert_eq!(CommandError::code_of(plain.as_ref()), ErrorCode::Err);
+    }
+}
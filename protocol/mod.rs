@@ -1,5 +1,9 @@
+pub mod error_code;
 pub mod parser;
 pub mod response;
+pub mod stream;
 
-pub use parser::RespParser;
+pub use error_code::{CommandError, ErrorCode};
+pub use parser::{ProtocolError, RespDecoder, RespParser};
 pub use response::RespResponse;
+pub use stream::ReplySink;
@@ -1,13 +1,242 @@
 use crate::Result;
-use std::io::{BufRead, BufReader, Cursor};
+use std::io::BufRead;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum RespValue {
     SimpleString(String),
     Error(String),
     Integer(i64),
-    BulkString(Option<String>),
+    /// 批量字符串是二进制安全的，内容只在需要文本的地方（命令名、GeoJSON 等）才按 UTF-8 解释
+    BulkString(Option<Vec<u8>>),
     Array(Option<Vec<RespValue>>),
+    /// RESP3 null（`_`）
+    Null,
+    /// RESP3 布尔值（`#t` / `#f`）
+    Boolean(bool),
+    /// RESP3 浮点数（`,`），包括 `inf`、`-inf` 和 `nan`
+    Double(f64),
+    /// RESP3 大整数（`(`），以十进制文本保存，不限制位数
+    BigNumber(String),
+    /// RESP3 映射（`%`），按收到的顺序保存键值对
+    Map(Vec<(RespValue, RespValue)>),
+    /// RESP3 集合（`~`）
+    Set(Vec<RespValue>),
+    /// RESP3 推送（`>`）：服务器主动发送的带外消息，例如围栏事件
+    Push(Vec<RespValue>),
+}
+
+impl RespValue {
+    /// 由文本或字节创建批量字符串
+    pub fn bulk(content: impl Into<Vec<u8>>) -> Self {
+        Self::BulkString(Some(content.into()))
+    }
+
+    /// 批量字符串按 UTF-8 解释的内容；不是批量字符串或者不是合法的 UTF-8 时返回 None
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::BulkString(Some(bytes)) => std::str::from_utf8(bytes).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// RESP 值的首行
+///
+/// 简单类型由首行即可确定，批量字符串和数组还需要继续读取后续内容。
+/// 同步解析器和异步客户端共用这一步。
+#[derive(Debug, Clone, PartialEq)]
+pub enum RespHeader {
+    /// 首行即完整值（包括 null 批量字符串和 null 数组）
+    Value(RespValue),
+    /// 批量字符串，后续还有 n 字节内容和结尾的 \r\n
+    Bulk(usize),
+    /// 数组，后续还有 n 个元素
+    Array(usize),
+    /// RESP3 映射，后续还有 n 个键值对（2n 个元素）
+    Map(usize),
+    /// RESP3 集合，后续还有 n 个元素
+    Set(usize),
+    /// RESP3 推送，后续还有 n 个元素
+    Push(usize),
+}
+
+impl RespHeader {
+    /// 聚合类型（数组、映射、集合、推送）后续的元素数量，映射的每个键值对算两个元素
+    pub fn element_count(&self) -> Option<usize> {
+        match self {
+            Self::Array(len) | Self::Set(len) | Self::Push(len) => Some(*len),
+            Self::Map(len) => Some(len * 2),
+            Self::Value(_) | Self::Bulk(_) => None,
+        }
+    }
+
+    /// 由读取到的元素构造聚合值；元素数量应等于 [`element_count`](Self::element_count)
+    pub fn into_aggregate(self, items: Vec<RespValue>) -> RespValue {
+        match self {
+            Self::Map(_) => {
+                let mut pairs = Vec::with_capacity(items.len() / 2);
+                let mut items = items.into_iter();
+                while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                    pairs.push((key, value));
+                }
+                RespValue::Map(pairs)
+            }
+            Self::Set(_) => RespValue::Set(items),
+            Self::Push(_) => RespValue::Push(items),
+            Self::Array(_) | Self::Value(_) | Self::Bulk(_) => RespValue::Array(Some(items)),
+        }
+    }
+}
+
+/// 单个批量字符串的最大长度（与 Redis 的 proto-max-bulk-len 默认值相同）
+pub const MAX_BULK_LEN: usize = 512 * 1024 * 1024;
+/// 单个数组的最大元素数量
+pub const MAX_ARRAY_LEN: usize = 1024 * 1024;
+/// 数组的最大嵌套深度，命令本身只有一层
+pub const MAX_NESTING_DEPTH: usize = 32;
+/// 首行（类型 + 长度或简单字符串）的最大长度
+pub const MAX_LINE_LEN: usize = 64 * 1024;
+
+/// 畸形帧：帧边界无法确定，连接不能继续同步
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Protocol error: {0}")]
+pub struct ProtocolError(pub String);
+
+impl ProtocolError {
+    fn new(message: impl Into<String>) -> Self {
+        Self(message.into())
+    }
+}
+
+/// 增量 RESP 解码器：在多次调用之间保存当前帧的解析进度
+///
+/// 帧不完整时记下已经解析的位置、等待内容的批量字符串长度和未完成的聚合值，
+/// 读取到更多数据后从上次停下的地方继续，不重复解析（和复制）已经收到的部分，
+/// 逐字节收到一个大帧的总开销仍与帧长成正比。
+///
+/// 调用方每次把新数据追加到同一个缓冲区后调用 [`decode`](Self::decode)；帧完整之前不能修改
+/// 缓冲区中已有的字节，帧完整后移除前 `consumed` 字节，解码器从头开始解析下一帧
+#[derive(Debug, Default)]
+pub struct RespDecoder {
+    /// 当前帧已经解析到的位置
+    pos: usize,
+    /// 从 `pos` 开始已经确认不含换行符的字节数
+    scanned: usize,
+    /// 已经读取首行、等待内容的批量字符串长度
+    pending_bulk: Option<usize>,
+    /// 未完成的聚合值：首行、还缺少的元素数量和已经解析的元素
+    stack: Vec<(RespHeader, usize, Vec<RespValue>)>,
+}
+
+impl RespDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 继续解码缓冲区开头的帧，返回值与 [`RespParser::decode`] 相同；出错后解码器回到初始状态
+    pub fn decode(
+        &mut self,
+        buf: &[u8],
+    ) -> std::result::Result<Option<(RespValue, usize)>, ProtocolError> {
+        let result = self.decode_frame(buf);
+        if !matches!(result, Ok(None)) {
+            *self = Self::default();
+        }
+        result
+    }
+
+    fn decode_frame(
+        &mut self,
+        buf: &[u8],
+    ) -> std::result::Result<Option<(RespValue, usize)>, ProtocolError> {
+        loop {
+            let mut value = match self.step(buf)? {
+                Step::Value(value) => value,
+                Step::Header => continue,
+                Step::Incomplete => return Ok(None),
+            };
+            // 把值加入所在的聚合值，聚合值完整后再加入上一层
+            loop {
+                let Some((_, remaining, items)) = self.stack.last_mut() else {
+                    return Ok(Some((value, self.pos)));
+                };
+                items.push(value);
+                *remaining -= 1;
+                if *remaining > 0 {
+                    break;
+                }
+                let (header, _, items) = self.stack.pop().unwrap();
+                value = header.into_aggregate(items);
+            }
+        }
+    }
+
+    /// 从 `pos` 开始解析一个首行，或者等待中的批量字符串内容
+    fn step(&mut self, buf: &[u8]) -> std::result::Result<Step, ProtocolError> {
+        if let Some(len) = self.pending_bulk {
+            let end = self.pos + len;
+            if buf.len() < end + 2 {
+                return Ok(Step::Incomplete);
+            }
+            if &buf[end..end + 2] != b"\r\n" {
+                return Err(ProtocolError::new("bulk string is not terminated by CRLF"));
+            }
+            let bytes = buf[self.pos..end].to_vec();
+            self.pending_bulk = None;
+            self.pos = end + 2;
+            return Ok(Step::Value(RespValue::BulkString(Some(bytes))));
+        }
+
+        let rest = &buf[self.pos..];
+        let Some(newline) = rest[self.scanned..].iter().position(|&b| b == b'\n') else {
+            if rest.len() > MAX_LINE_LEN {
+                return Err(ProtocolError::new("line too long"));
+            }
+            self.scanned = rest.len();
+            return Ok(Step::Incomplete);
+        };
+        let newline = self.scanned + newline;
+        self.scanned = 0;
+        if newline > MAX_LINE_LEN {
+            return Err(ProtocolError::new("line too long"));
+        }
+        let line = std::str::from_utf8(&rest[..newline])
+            .map_err(|_| ProtocolError::new("line is not valid UTF-8"))?;
+        let header =
+            RespParser::parse_header(line).map_err(|e| ProtocolError::new(e.to_string()))?;
+        self.pos += newline + 1;
+
+        match header {
+            RespHeader::Value(value) => Ok(Step::Value(value)),
+            RespHeader::Bulk(len) => {
+                self.pending_bulk = Some(len);
+                Ok(Step::Header)
+            }
+            header => {
+                if self.stack.len() >= MAX_NESTING_DEPTH {
+                    return Err(ProtocolError::new("arrays nested too deeply"));
+                }
+                let len = header.element_count().unwrap_or(0);
+                if len == 0 {
+                    return Ok(Step::Value(header.into_aggregate(Vec::new())));
+                }
+                // 长度来自客户端，预分配的容量不能直接使用
+                self.stack
+                    .push((header, len, Vec::with_capacity(len.min(64))));
+                Ok(Step::Header)
+            }
+        }
+    }
+}
+
+/// [`RespDecoder::step`] 的结果
+enum Step {
+    /// 解析出一个完整的值
+    Value(RespValue),
+    /// 读完了批量字符串或聚合值的首行，继续解析后续内容
+    Header,
+    /// 需要更多数据
+    Incomplete,
 }
 
 pub struct RespParser;
@@ -24,69 +253,138 @@ impl RespParser {
     }
 
     pub fn parse(&self, input: &[u8]) -> Result<RespValue> {
-        let mut cursor = Cursor::new(input);
-        let mut reader = BufReader::new(&mut cursor);
-        self.parse_value(&mut reader)
+        match Self::decode(input)? {
+            Some((value, _)) => Ok(value),
+            None => Err("Unexpected EOF".into()),
+        }
     }
 
-    fn parse_value<R: BufRead>(&self, reader: &mut R) -> Result<RespValue> {
-        let mut line = String::new();
-        let bytes_read = reader.read_line(&mut line)?;
+    /// 从缓冲区开头解码一个 RESP 值
+    ///
+    /// - `Ok(Some((value, consumed)))`：完整的帧，占用缓冲区的前 `consumed` 字节
+    /// - `Ok(None)`：帧还不完整，需要读取更多数据
+    /// - `Err(..)`：畸形帧，之后的字节无法再按帧切分
+    ///
+    /// 长度和嵌套深度受 `MAX_*` 限制，任意输入都不会导致 panic 或超大内存分配。
+    /// 每次调用都从头解析；从网络分多次读取同一帧时使用 [`RespDecoder`]
+    pub fn decode(buf: &[u8]) -> std::result::Result<Option<(RespValue, usize)>, ProtocolError> {
+        RespDecoder::new().decode(buf)
+    }
 
-        if bytes_read == 0 {
-            return Err("Unexpected EOF".into());
-        }
+    /// 从任意 BufRead 中读取并解析一个完整的 RESP 值
+    ///
+    /// 对网络流会一直读取到值完整为止，不依赖单次 read 的边界
+    pub fn parse_from<R: BufRead>(&self, reader: &mut R) -> Result<RespValue> {
+        self.parse_value(reader)
+    }
 
+    /// 解析 RESP 值的首行（不含结尾的 \r\n）
+    pub fn parse_header(line: &str) -> Result<RespHeader> {
         let line = line.trim_end_matches('\n').trim_end_matches('\r');
         if line.is_empty() {
             return Err("Empty line".into());
         }
 
         let first_char = line.chars().next().unwrap();
-        let content = &line[1..];
+        let content = &line[first_char.len_utf8()..];
 
         match first_char {
-            '+' => Ok(RespValue::SimpleString(content.to_string())),
-            '-' => Ok(RespValue::Error(content.to_string())),
+            '+' => Ok(RespHeader::Value(RespValue::SimpleString(
+                content.to_string(),
+            ))),
+            '-' => Ok(RespHeader::Value(RespValue::Error(content.to_string()))),
             ':' => {
                 let num = content.parse::<i64>()?;
-                Ok(RespValue::Integer(num))
+                Ok(RespHeader::Value(RespValue::Integer(num)))
             }
             '$' => {
                 let len = content.parse::<i64>()?;
                 if len == -1 {
-                    Ok(RespValue::BulkString(None))
-                } else if len == 0 {
-                    // 读取空字符串的 \r\n
-                    let mut end = String::new();
-                    reader.read_line(&mut end)?;
-                    Ok(RespValue::BulkString(Some(String::new())))
+                    Ok(RespHeader::Value(RespValue::BulkString(None)))
                 } else {
-                    let mut buf = vec![0; len as usize];
-                    reader.read_exact(&mut buf)?;
-                    // 读取结尾的 \r\n
-                    let mut end = String::new();
-                    reader.read_line(&mut end)?;
-                    let s = String::from_utf8(buf)?;
-                    Ok(RespValue::BulkString(Some(s)))
+                    let len = usize::try_from(len)?;
+                    if len > MAX_BULK_LEN {
+                        return Err(format!("Bulk string length {} exceeds limit", len).into());
+                    }
+                    Ok(RespHeader::Bulk(len))
                 }
             }
             '*' => {
                 let len = content.parse::<i64>()?;
                 if len == -1 {
-                    Ok(RespValue::Array(None))
+                    Ok(RespHeader::Value(RespValue::Array(None)))
                 } else {
-                    let mut arr = Vec::with_capacity(len as usize);
-                    for _ in 0..len {
-                        let value = self.parse_value(reader)?;
-                        arr.push(value);
-                    }
-                    Ok(RespValue::Array(Some(arr)))
+                    Ok(RespHeader::Array(Self::aggregate_len(content)?))
+                }
+            }
+            '_' => match content {
+                "" => Ok(RespHeader::Value(RespValue::Null)),
+                _ => Err(format!("Invalid null: {}", content).into()),
+            },
+            '#' => match content {
+                "t" => Ok(RespHeader::Value(RespValue::Boolean(true))),
+                "f" => Ok(RespHeader::Value(RespValue::Boolean(false))),
+                _ => Err(format!("Invalid boolean: {}", content).into()),
+            },
+            ',' => Ok(RespHeader::Value(RespValue::Double(content.parse()?))),
+            '(' => {
+                let digits = content.strip_prefix('-').unwrap_or(content);
+                if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(format!("Invalid big number: {}", content).into());
                 }
+                Ok(RespHeader::Value(RespValue::BigNumber(content.to_string())))
             }
+            '%' => Ok(RespHeader::Map(Self::aggregate_len(content)?)),
+            '~' => Ok(RespHeader::Set(Self::aggregate_len(content)?)),
+            '>' => Ok(RespHeader::Push(Self::aggregate_len(content)?)),
             _ => Err(format!("Unknown RESP type: {}", first_char).into()),
         }
     }
+
+    /// 聚合类型的长度，映射按键值对计数，上限与数组相同
+    fn aggregate_len(content: &str) -> Result<usize> {
+        let len = usize::try_from(content.parse::<i64>()?)?;
+        if len > MAX_ARRAY_LEN {
+            return Err(format!("Array length {} exceeds limit", len).into());
+        }
+        Ok(len)
+    }
+
+    fn parse_value<R: BufRead>(&self, reader: &mut R) -> Result<RespValue> {
+        let mut line = String::new();
+        let bytes_read = reader.read_line(&mut line)?;
+
+        if bytes_read == 0 {
+            return Err("Unexpected EOF".into());
+        }
+
+        match Self::parse_header(&line)? {
+            RespHeader::Value(value) => Ok(value),
+            RespHeader::Bulk(0) => {
+                // 读取空字符串的 \r\n
+                let mut end = String::new();
+                reader.read_line(&mut end)?;
+                Ok(RespValue::BulkString(Some(Vec::new())))
+            }
+            RespHeader::Bulk(len) => {
+                let mut buf = vec![0; len];
+                reader.read_exact(&mut buf)?;
+                // 读取结尾的 \r\n
+                let mut end = String::new();
+                reader.read_line(&mut end)?;
+                Ok(RespValue::BulkString(Some(buf)))
+            }
+            header => {
+                let len = header.element_count().unwrap_or(0);
+                let mut arr = Vec::with_capacity(len.min(64));
+                for _ in 0..len {
+                    let value = self.parse_value(reader)?;
+                    arr.push(value);
+                }
+                Ok(header.into_aggregate(arr))
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -118,7 +416,7 @@ mod tests {
     fn test_bulk_string() {
         let parser = RespParser::new();
         let result = parser.parse(b"$6\r\nfoobar\r\n").unwrap();
-        assert_eq!(result, RespValue::BulkString(Some("foobar".to_string())));
+        assert_eq!(result, RespValue::bulk("foobar"));
     }
 
     #[test]
@@ -132,10 +430,278 @@ mod tests {
     fn test_array() {
         let parser = RespParser::new();
         let result = parser.parse(b"*2\r\n$3\r\nfoo\r\n$3\r\nbar\r\n").unwrap();
-        let expected = RespValue::Array(Some(vec![
-            RespValue::BulkString(Some("foo".to_string())),
-            RespValue::BulkString(Some("bar".to_string())),
-        ]));
+        let expected = RespValue::Array(Some(vec![RespValue::bulk("foo"), RespValue::bulk("bar")]));
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn test_resp3_scalars() {
+        let parser = RespParser::new();
+        assert_eq!(parser.parse(b"_\r\n").unwrap(), RespValue::Null);
+        assert_eq!(parser.parse(b"#t\r\n").unwrap(), RespValue::Boolean(true));
+        assert_eq!(parser.parse(b"#f\r\n").unwrap(), RespValue::Boolean(false));
+        assert_eq!(parser.parse(b",3.25\r\n").unwrap(), RespValue::Double(3.25));
+        assert_eq!(
+            parser.parse(b",-inf\r\n").unwrap(),
+            RespValue::Double(f64::NEG_INFINITY)
+        );
+        assert!(matches!(
+            parser.parse(b",nan\r\n").unwrap(),
+            RespValue::Double(n) if n.is_nan()
+        ));
+        assert_eq!(
+            parser
+                .parse(b"(-3492890328409238509324850943850943825024385\r\n")
+                .unwrap(),
+            RespValue::BigNumber("-3492890328409238509324850943850943825024385".to_string())
+        );
+
+        for input in [
+            &b"_x\r\n"[..],
+            b"#x\r\n",
+            b",abc\r\n",
+            b"(\r\n",
+            b"(12a\r\n",
+        ] {
+            assert!(RespParser::decode(input).is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_resp3_aggregates() {
+        let frame =
+            b"%2\r\n$5\r\nproto\r\n:3\r\n$4\r\nmode\r\n~1\r\n#t\r\n>2\r\n$5\r\nfence\r\n_\r\n";
+        let map_len = frame.len() - b">2\r\n$5\r\nfence\r\n_\r\n".len();
+        for end in 0..map_len {
+            assert_eq!(RespParser::decode(&frame[..end]).unwrap(), None, "{}", end);
+        }
+
+        let (map, consumed) = RespParser::decode(frame).unwrap().unwrap();
+        assert_eq!(consumed, map_len);
+        assert_eq!(
+            map,
+            RespValue::Map(vec![
+                (RespValue::bulk("proto"), RespValue::Integer(3)),
+                (
+                    RespValue::bulk("mode"),
+                    RespValue::Set(vec![RespValue::Boolean(true)])
+                ),
+            ])
+        );
+        let (push, _) = RespParser::decode(&frame[consumed..]).unwrap().unwrap();
+        assert_eq!(
+            push,
+            RespValue::Push(vec![RespValue::bulk("fence"), RespValue::Null])
+        );
+
+        // 阻塞读取与增量解码结果一致
+        let mut reader = &frame[..];
+        let parser = RespParser::new();
+        assert_eq!(parser.parse_from(&mut reader).unwrap(), map);
+        assert_eq!(parser.parse_from(&mut reader).unwrap(), push);
+
+        assert_eq!(
+            RespParser::parse_header("%2\r\n").unwrap().element_count(),
+            Some(4)
+        );
+        let huge_map = format!("%{}\r\n", MAX_ARRAY_LEN + 1);
+        assert!(RespParser::decode(huge_map.as_bytes()).is_err());
+        assert!(RespParser::decode(b">-1\r\n").is_err());
+        let deep = ">1\r\n".repeat(MAX_NESTING_DEPTH + 1);
+        assert!(RespParser::decode(deep.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_parse_header() {
+        assert_eq!(
+            RespParser::parse_header("*3\r\n").unwrap(),
+            RespHeader::Array(3)
+        );
+        assert_eq!(
+            RespParser::parse_header("$-1\r\n").unwrap(),
+            RespHeader::Value(RespValue::BulkString(None))
+        );
+        assert_eq!(
+            RespParser::parse_header("-ERR boom").unwrap(),
+            RespHeader::Value(RespValue::Error("ERR boom".to_string()))
+        );
+        assert!(RespParser::parse_header("$-5\r\n").is_err());
+        assert!(RespParser::parse_header("?oops\r\n").is_err());
+    }
+
+    #[test]
+    fn test_decode_incomplete_frames() {
+        let frame = b"*3\r\n$3\r\nSET\r\n$0\r\n\r\n:42\r\n";
+        for end in 0..frame.len() {
+            assert_eq!(RespParser::decode(&frame[..end]).unwrap(), None, "{}", end);
+        }
+
+        let (value, consumed) = RespParser::decode(frame).unwrap().unwrap();
+        assert_eq!(consumed, frame.len());
+        assert_eq!(
+            value,
+            RespValue::Array(Some(vec![
+                RespValue::bulk("SET"),
+                RespValue::bulk(String::new()),
+                RespValue::Integer(42),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_decode_pipelined_frames() {
+        let buf = b"+OK\r\n$4\r\nPING\r\n*1";
+        let (first, consumed) = RespParser::decode(buf).unwrap().unwrap();
+        assert_eq!(first, RespValue::SimpleString("OK".to_string()));
+        let (second, next) = RespParser::decode(&buf[consumed..]).unwrap().unwrap();
+        assert_eq!(second, RespValue::bulk("PING"));
+        assert_eq!(RespParser::decode(&buf[consumed + next..]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_decoder_large_bulk_one_byte_at_a_time() {
+        // 4 MB 的批量字符串后面还有一个元素；逐字节收到时已经解析的部分不再重复解析或复制
+        let content = vec![b'x'; 4 * 1024 * 1024];
+        let header = format!("*3\r\n$3\r\nSET\r\n${}\r\n", content.len());
+        let mut frame = header.clone().into_bytes();
+        frame.extend_from_slice(&content);
+        frame.extend_from_slice(b"\r\n$5\r\nfleet\r\n");
+        let bulk_end = header.len() + content.len() + 2;
+
+        let mut decoder = RespDecoder::new();
+        let mut buf = Vec::with_capacity(frame.len());
+        for &byte in &frame[..frame.len() - 1] {
+            buf.push(byte);
+            assert_eq!(decoder.decode(&buf).unwrap(), None);
+            if buf.len() == header.len() + content.len() / 2 {
+                // 内容收到一半：首行已经解析，只等待剩余的内容
+                assert_eq!(decoder.pos, header.len());
+                assert_eq!(decoder.pending_bulk, Some(content.len()));
+                assert_eq!(decoder.stack[0].2, [RespValue::bulk("SET")]);
+            }
+            if buf.len() == bulk_end + 3 {
+                assert_eq!(decoder.pos, bulk_end);
+                assert_eq!(decoder.scanned, 3);
+                assert_eq!(decoder.stack[0].1, 1);
+            }
+        }
+        buf.push(frame[frame.len() - 1]);
+        let (value, consumed) = decoder.decode(&buf).unwrap().unwrap();
+        assert_eq!(consumed, frame.len());
+        assert_eq!(
+            value,
+            RespValue::Array(Some(vec![
+                RespValue::bulk("SET"),
+                RespValue::bulk(content),
+                RespValue::bulk("fleet"),
+            ]))
+        );
+
+        // 帧完整后从头开始解析下一帧
+        assert_eq!(decoder.pos, 0);
+        assert!(decoder.stack.is_empty());
+        let next = b"+OK\r\n";
+        for end in 1..next.len() {
+            assert_eq!(decoder.decode(&next[..end]).unwrap(), None);
+        }
+        assert_eq!(
+            decoder.decode(next).unwrap(),
+            Some((RespValue::SimpleString("OK".to_string()), next.len()))
+        );
+    }
+
+    #[test]
+    fn test_binary_bulk_string() {
+        // 批量字符串按长度切分，任意字节（包括 \r\n 和非 UTF-8）都原样保留
+        let buf = b"*2\r\n$3\r\nSET\r\n$4\r\n\xff\r\n\x00\r\n";
+        let (value, consumed) = RespParser::decode(buf).unwrap().unwrap();
+        assert_eq!(consumed, buf.len());
+        let RespValue::Array(Some(items)) = value else {
+            panic!("expected array");
+        };
+        assert_eq!(items[0].as_str(), Some("SET"));
+        assert_eq!(
+            items[1],
+            RespValue::BulkString(Some(b"\xff\r\n\x00".to_vec()))
+        );
+        assert_eq!(items[1].as_str(), None);
+
+        let parsed = RespParser::new().parse(b"$2\r\n\xff\xfe\r\n").unwrap();
+        assert_eq!(parsed, RespValue::bulk(&b"\xff\xfe"[..]));
+    }
+
+    #[test]
+    fn test_decode_malformed_frames() {
+        let malformed: [&[u8]; 7] = [
+            b"?oops\r\n",
+            b"*abc\r\n",
+            b"$-2\r\n",
+            b":12x\r\n",
+            b"$3\r\nfooXY",
+            b"*2\r\n$3\r\nfoo\r\n!\r\n",
+            b"\r\n",
+        ];
+        for input in malformed {
+            assert!(RespParser::decode(input).is_err(), "{:?}", input);
+        }
+        assert!(RespParser::new().parse(b"$3\r\nfo").is_err());
+    }
+
+    #[test]
+    fn test_decode_limits() {
+        // 声明的长度超过限制时立即报错，不等待数据也不预分配
+        let huge_bulk = format!("${}\r\n", MAX_BULK_LEN + 1);
+        assert!(RespParser::decode(huge_bulk.as_bytes()).is_err());
+        let huge_array = format!("*{}\r\n", MAX_ARRAY_LEN + 1);
+        assert!(RespParser::decode(huge_array.as_bytes()).is_err());
+        assert!(RespParser::decode(b"$99999999999999999999\r\n").is_err());
+
+        let deep = "*1\r\n".repeat(MAX_NESTING_DEPTH + 1);
+        assert!(RespParser::decode(deep.as_bytes()).is_err());
+        let nested = format!("{}:1\r\n", "*1\r\n".repeat(MAX_NESTING_DEPTH));
+        assert!(RespParser::decode(nested.as_bytes()).unwrap().is_some());
+
+        let long_line = vec![b'+'; MAX_LINE_LEN + 2];
+        assert!(RespParser::decode(&long_line).is_err());
+    }
+
+    #[test]
+    fn test_decode_random_mutations_never_panic() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        // 与 fuzz/fuzz_targets/resp_decode.rs 相同的不变量，固定种子在普通测试中运行
+        let seeds: [&[u8]; 5] = [
+            b"*3\r\n$3\r\nSET\r\n$5\r\nfleet\r\n$2\r\n{}\r\n",
+            b"*2\r\n*1\r\n:1\r\n$-1\r\n",
+            b"+OK\r\n-ERR boom\r\n",
+            b"*-1\r\n$0\r\n\r\n",
+            b"%1\r\n+a\r\n,1.5\r\n>2\r\n#t\r\n(12\r\n_\r\n",
+        ];
+        let mut rng = StdRng::seed_from_u64(4476);
+        for _ in 0..20_000 {
+            let mut input = seeds[rng.gen_range(0..seeds.len())].to_vec();
+            for _ in 0..rng.gen_range(1..4) {
+                let at = rng.gen_range(0..=input.len());
+                match rng.gen_range(0..3) {
+                    0 => input.insert(at, rng.gen()),
+                    1 if at < input.len() => input[at] = rng.gen(),
+                    _ => input.truncate(at),
+                }
+            }
+            check_decode_invariants(&input);
+        }
+    }
+
+    /// 任意输入：不 panic；完整帧占用的字节数不超过输入长度，且再次解码结果相同
+    ///
+    /// 按 Debug 输出比较，`nan` 与自身不相等
+    fn check_decode_invariants(input: &[u8]) {
+        if let Ok(Some((value, consumed))) = RespParser::decode(input) {
+            assert!(consumed > 0 && consumed <= input.len());
+            assert_eq!(
+                format!("{:?}", RespParser::decode(&input[..consumed]).unwrap()),
+                format!("{:?}", Some((value, consumed)))
+            );
+        }
+    }
 }
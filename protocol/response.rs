@@ -1,4 +1,6 @@
+use crate::protocol::error_code::CommandError;
 use crate::protocol::parser::RespValue;
+use std::error::Error;
 
 pub struct RespResponse;
 
@@ -11,6 +13,12 @@ impl RespResponse {
         format!("-{}\r\n", msg)
     }
 
+    /// 将命令执行中的错误编码为错误回复，前缀由错误码决定（参见 [`CommandError`]）
+    pub fn command_error(context: &str, error: &(dyn Error + 'static)) -> String {
+        let code = CommandError::code_of(error);
+        Self::error(&format!("{} {}: {}", code.prefix(), context, error))
+    }
+
     pub fn integer(n: i64) -> String {
         format!(":{}\r\n", n)
     }
@@ -24,24 +32,61 @@ impl RespResponse {
 
     pub fn array(items: Option<&[RespValue]>) -> String {
         match items {
-            Some(items) => {
-                let mut result = format!("*{}\r\n", items.len());
-                for item in items {
-                    result.push_str(&Self::value_to_string(item));
-                }
-                result
-            }
+            Some(items) => Self::aggregate('*', items),
             None => "*-1\r\n".to_string(),
         }
     }
 
-    fn value_to_string(value: &RespValue) -> String {
+    /// RESP3 映射，用于 `HELLO 3` 等协商了 RESP3 的回复
+    pub fn map(pairs: &[(RespValue, RespValue)]) -> String {
+        let mut result = format!("%{}\r\n", pairs.len());
+        for (key, value) in pairs {
+            result.push_str(&Self::value_to_string(key));
+            result.push_str(&Self::value_to_string(value));
+        }
+        result
+    }
+
+    /// RESP3 浮点数，非有限值编码为 `inf`、`-inf` 和 `nan`
+    pub fn double(n: f64) -> String {
+        if n.is_nan() {
+            ",nan\r\n".to_string()
+        } else {
+            format!(",{}\r\n", n)
+        }
+    }
+
+    /// RESP3 推送帧：服务器主动发送的消息，客户端不会把它当作某个命令的回复
+    pub fn push(items: &[RespValue]) -> String {
+        Self::aggregate('>', items)
+    }
+
+    fn aggregate(prefix: char, items: &[RespValue]) -> String {
+        let mut result = format!("{}{}\r\n", prefix, items.len());
+        for item in items {
+            result.push_str(&Self::value_to_string(item));
+        }
+        result
+    }
+
+    /// 编码任意一个值
+    pub fn value_to_string(value: &RespValue) -> String {
         match value {
             RespValue::SimpleString(s) => Self::simple_string(s),
             RespValue::Error(s) => Self::error(s),
             RespValue::Integer(n) => Self::integer(*n),
-            RespValue::BulkString(s) => Self::bulk_string(s.as_deref()),
+            // 回复以文本构建，命令产生的批量字符串都是 UTF-8；其他字节替换为 U+FFFD，长度按替换后计算
+            RespValue::BulkString(s) => {
+                Self::bulk_string(s.as_deref().map(String::from_utf8_lossy).as_deref())
+            }
             RespValue::Array(arr) => Self::array(arr.as_deref()),
+            RespValue::Null => "_\r\n".to_string(),
+            RespValue::Boolean(b) => format!("#{}\r\n", if *b { 't' } else { 'f' }),
+            RespValue::Double(n) => Self::double(*n),
+            RespValue::BigNumber(n) => format!("({}\r\n", n),
+            RespValue::Map(pairs) => Self::map(pairs),
+            RespValue::Set(items) => Self::aggregate('~', items),
+            RespValue::Push(items) => Self::push(items),
         }
     }
 }
@@ -76,4 +121,45 @@ mod tests {
         );
         assert_eq!(RespResponse::bulk_string(None), "$-1\r\n");
     }
+
+    #[test]
+    fn test_resp3_values() {
+        assert_eq!(RespResponse::double(1.5), ",1.5\r\n");
+        assert_eq!(RespResponse::double(f64::NEG_INFINITY), ",-inf\r\n");
+        assert_eq!(RespResponse::double(f64::NAN), ",nan\r\n");
+        assert_eq!(
+            RespResponse::map(&[(RespValue::bulk("proto"), RespValue::Integer(3))]),
+            "%1\r\n$5\r\nproto\r\n:3\r\n"
+        );
+        assert_eq!(
+            RespResponse::push(&[RespValue::bulk("fence"), RespValue::Null]),
+            ">2\r\n$5\r\nfence\r\n_\r\n"
+        );
+        assert_eq!(
+            RespResponse::array(Some(&[
+                RespValue::Boolean(true),
+                RespValue::BigNumber("-12345678901234567890".to_string()),
+                RespValue::Set(vec![RespValue::Integer(1)]),
+            ])),
+            "*3\r\n#t\r\n(-12345678901234567890\r\n~1\r\n:1\r\n"
+        );
+    }
+
+    #[test]
+    fn test_command_error() {
+        use crate::protocol::{CommandError, ErrorCode};
+
+        let plain: Box<dyn Error + Send + Sync> = "disk full".into();
+        assert_eq!(
+            RespResponse::command_error("failed to store", plain.as_ref()),
+            "-ERR failed to store: disk full\r\n"
+        );
+
+        let typed: Box<dyn Error + Send + Sync> =
+            Box::new(CommandError::new(ErrorCode::ReadOnly, "AOF append failed"));
+        assert_eq!(
+            RespResponse::command_error("failed to store", typed.as_ref()),
+            "-READONLY failed to store: AOF append failed\r\n"
+        );
+    }
 }
@@ -0,0 +1,135 @@
+//! 分块写出的回复
+//!
+//! INTERSECTS 在密集区域的结果可能有几 MB。命令把编码好的片段写入 [`ReplySink`]，累积到
+//! [`STREAM_CHUNK_BYTES`] 就作为一块交给连接写到 socket，不必先把整个回复拼成一个字符串。
+//! 通道是有界的，客户端读得慢时命令在写入处等待，内存中最多缓存 [`STREAM_CHANNEL_CAPACITY`] 块。
+//! HTTP、WebSocket、`OUTPUT json` 和事务需要完整的回复，使用 [`ReplySink::buffer`] 收集
+
+use tokio::sync::mpsc;
+
+use crate::protocol::parser::RespValue;
+use crate::protocol::RespResponse;
+use crate::Result;
+
+/// 缓冲区达到这个大小时发送一块
+pub const STREAM_CHUNK_BYTES: usize = 64 * 1024;
+
+/// 连接还没有写出的块数上限
+pub const STREAM_CHANNEL_CAPACITY: usize = 4;
+
+/// 命令回复的写入端
+pub struct ReplySink {
+    buffer: String,
+    /// None 时收集完整的回复
+    sender: Option<mpsc::Sender<String>>,
+}
+
+impl ReplySink {
+    /// 收集完整回复，用 [`into_string`](Self::into_string) 取出
+    pub fn buffer() -> Self {
+        Self {
+            buffer: String::new(),
+            sender: None,
+        }
+    }
+
+    /// 分块发送回复，接收端按顺序写出每一块；写入端丢弃时接收端结束
+    pub fn channel() -> (Self, mpsc::Receiver<String>) {
+        let (sender, receiver) = mpsc::channel(STREAM_CHANNEL_CAPACITY);
+        let sink = Self {
+            buffer: String::with_capacity(STREAM_CHUNK_BYTES),
+            sender: Some(sender),
+        };
+        (sink, receiver)
+    }
+
+    /// 是否分块发送；分块模式的 sink 只由连接创建，经
+    /// [`CommandRegistry::execute_into`](crate::commands::registry::CommandRegistry::execute_into)
+    /// 执行的命令不持有全局锁，需要时自己加锁
+    pub fn is_channel(&self) -> bool {
+        self.sender.is_some()
+    }
+
+    /// 追加已经编码的 RESP 片段，分块模式下缓冲区满时等待连接取走
+    pub async fn write(&mut self, frame: &str) -> Result<()> {
+        self.buffer.push_str(frame);
+        if self.sender.is_some() && self.buffer.len() >= STREAM_CHUNK_BYTES {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// 编码并追加一个值
+    pub async fn write_value(&mut self, value: &RespValue) -> Result<()> {
+        self.write(&RespResponse::value_to_string(value)).await
+    }
+
+    /// 发送缓冲区中剩余的内容，分块模式下命令写完回复后调用
+    pub async fn flush(&mut self) -> Result<()> {
+        let Some(sender) = &self.sender else {
+            return Ok(());
+        };
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::replace(&mut self.buffer, String::with_capacity(STREAM_CHUNK_BYTES));
+        sender
+            .send(chunk)
+            .await
+            .map_err(|_| "connection stopped reading the reply".into())
+    }
+
+    /// 收集到的完整回复；分块模式下为还没有发送的部分
+    pub fn into_string(self) -> String {
+        self.buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reply_sink_chunks() {
+        let (mut sink, mut receiver) = ReplySink::channel();
+        let item = "x".repeat(STREAM_CHUNK_BYTES / 2);
+        let writer = tokio::spawn(async move {
+            sink.write("*3\r\n").await.unwrap();
+            for _ in 0..3 {
+                sink.write_value(&RespValue::bulk(item.as_str()))
+                    .await
+                    .unwrap();
+            }
+            sink.flush().await.unwrap();
+        });
+
+        let mut chunks = Vec::new();
+        while let Some(chunk) = receiver.recv().await {
+            chunks.push(chunk);
+        }
+        writer.await.unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks[0].len() >= STREAM_CHUNK_BYTES);
+
+        let reply = chunks.concat();
+        let (value, consumed) = crate::protocol::RespParser::decode(reply.as_bytes())
+            .unwrap()
+            .unwrap();
+        assert_eq!(consumed, reply.len());
+        assert!(matches!(value, RespValue::Array(Some(items)) if items.len() == 3));
+
+        // 收集模式不分块
+        let mut sink = ReplySink::buffer();
+        sink.write("+OK\r\n").await.unwrap();
+        sink.flush().await.unwrap();
+        assert_eq!(sink.into_string(), "+OK\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_reply_sink_reports_closed_connection() {
+        let (mut sink, receiver) = ReplySink::channel();
+        drop(receiver);
+        sink.write("+OK\r\n").await.unwrap();
+        assert!(sink.flush().await.is_err());
+    }
+}
@@ -0,0 +1,208 @@
+use super::super::rectangle::Rectangle;
+use super::super::rtree::RTree;
+use crate::storage::geometry_utils::geojson_properties;
+use geo::Centroid;
+use std::collections::HashMap;
+
+const SQRT_3: f64 = 1.732_050_807_568_877_2;
+
+/// 分箱方式
+///
+/// 网格以经纬度原点为锚点，同样的 size 在不同查询范围下得到的格子一致，便于拼接热力图
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Binning {
+    /// 正方形网格，size 为格子边长（度）
+    Grid { size: f64 },
+    /// 尖顶六边形网格，size 为六边形外接圆半径（度）
+    Hex { size: f64 },
+}
+
+impl Binning {
+    /// 返回点所在格子的编号
+    fn cell_of(&self, x: f64, y: f64) -> (i64, i64) {
+        match *self {
+            Binning::Grid { size } => ((x / size).floor() as i64, (y / size).floor() as i64),
+            Binning::Hex { size } => {
+                // 像素坐标转轴向坐标，再按立方坐标取整
+                let q = (SQRT_3 / 3.0 * x - y / 3.0) / size;
+                let r = (2.0 / 3.0 * y) / size;
+                hex_round(q, r)
+            }
+        }
+    }
+
+    /// 返回格子的中心点
+    fn center_of(&self, cell: (i64, i64)) -> [f64; 2] {
+        let (a, b) = (cell.0 as f64, cell.1 as f64);
+        match *self {
+            Binning::Grid { size } => [(a + 0.5) * size, (b + 0.5) * size],
+            Binning::Hex { size } => [size * (SQRT_3 * a + SQRT_3 / 2.0 * b), size * 1.5 * b],
+        }
+    }
+}
+
+fn hex_round(q: f64, r: f64) -> (i64, i64) {
+    let s = -q - r;
+    let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+    let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+
+    if dq > dr && dq > ds {
+        rq = -rr - rs;
+    } else if dr > ds {
+        rr = -rq - rs;
+    }
+    (rq as i64, rr as i64)
+}
+
+/// 单个格子的统计结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinStat {
+    /// 格子中心 [lon, lat]
+    pub center: [f64; 2],
+    /// 对象数量
+    pub count: usize,
+    /// 数值字段之和（未指定字段时为 0）
+    pub sum: f64,
+    /// 数值字段有效的对象数量
+    pub field_count: usize,
+}
+
+impl BinStat {
+    /// 数值字段平均值，没有有效值时返回 None
+    pub fn avg(&self) -> Option<f64> {
+        (self.field_count > 0).then(|| self.sum / self.field_count as f64)
+    }
+}
+
+/// 空间聚合相关算法
+impl RTree {
+    /// 统计范围内对象在各格子中的数量，以及可选数值字段的和与平均值
+    ///
+    /// 通过 R-tree 找出边界框与范围相交的对象，以几何体质心决定所属格子，
+    /// 质心不在范围内的对象不计入。结果只包含有对象的格子，按纬度、经度升序排列
+    pub fn aggregate(
+        &self,
+        bounds: &Rectangle,
+        binning: Binning,
+        field: Option<&str>,
+    ) -> Vec<BinStat> {
+        let mut bins: HashMap<(i64, i64), BinStat> = HashMap::new();
+
+        for id in self.search_bbox(bounds) {
+            let Some(point) = self.geometry_map.get(&id).and_then(|g| g.centroid()) else {
+                continue;
+            };
+            if !bounds.contains_point(point.x(), point.y()) {
+                continue;
+            }
+
+            let cell = binning.cell_of(point.x(), point.y());
+            let stat = bins.entry(cell).or_insert_with(|| BinStat {
+                center: binning.center_of(cell),
+                count: 0,
+                sum: 0.0,
+                field_count: 0,
+            });
+            stat.count += 1;
+
+            // 只有指定字段时才解析 properties
+            let value = field.and_then(|name| {
+                let geojson = self.geojson_map.get(&id)?;
+                geojson_properties(geojson)?.get(name)?.as_f64()
+            });
+            if let Some(value) = value {
+                stat.sum += value;
+                stat.field_count += 1;
+            }
+        }
+
+        let mut stats: Vec<BinStat> = bins.into_values().collect();
+        stats.sort_by(|a, b| {
+            a.center[1]
+                .total_cmp(&b.center[1])
+                .then(a.center[0].total_cmp(&b.center[0]))
+        });
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn insert_point(tree: &mut RTree, id: &str, x: f64, y: f64, speed: Option<f64>) {
+        let geojson = match speed {
+            Some(speed) => json!({
+                "type": "Feature",
+                "properties": {"speed": speed},
+                "geometry": {"type": "Point", "coordinates": [x, y]}
+            }),
+            None => json!({"type": "Point", "coordinates": [x, y]}),
+        };
+        assert!(tree.insert_geojson(id.to_string(), &geojson.to_string()));
+    }
+
+    #[test]
+    fn test_aggregate_grid() {
+        let mut tree = RTree::new(4);
+        insert_point(&mut tree, "a", 0.1, 0.1, Some(10.0));
+        insert_point(&mut tree, "b", 0.2, 0.3, Some(20.0));
+        insert_point(&mut tree, "c", 0.9, 0.1, None);
+        insert_point(&mut tree, "d", 1.5, 1.5, Some(5.0));
+        // 范围外
+        insert_point(&mut tree, "e", 5.0, 5.0, Some(1.0));
+
+        let bounds = Rectangle::new(0.0, 0.0, 2.0, 2.0);
+        let stats = tree.aggregate(&bounds, Binning::Grid { size: 0.5 }, Some("speed"));
+
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats[0].center, [0.25, 0.25]);
+        assert_eq!(stats[0].count, 2);
+        assert_eq!(stats[0].sum, 30.0);
+        assert_eq!(stats[0].avg(), Some(15.0));
+
+        assert_eq!(stats[1].center, [0.75, 0.25]);
+        assert_eq!(stats[1].count, 1);
+        assert_eq!(stats[1].avg(), None);
+
+        assert_eq!(stats[2].center, [1.75, 1.75]);
+        assert_eq!(stats.iter().map(|s| s.count).sum::<usize>(), 4);
+    }
+
+    #[test]
+    fn test_aggregate_hex() {
+        let mut tree = RTree::new(4);
+        let size = 1.0;
+        let binning = Binning::Hex { size };
+
+        // 每个格子中心附近放两个点
+        let centers = [
+            binning.center_of((0, 0)),
+            binning.center_of((1, 0)),
+            binning.center_of((0, 1)),
+        ];
+        for (i, [x, y]) in centers.iter().enumerate() {
+            insert_point(&mut tree, &format!("{}a", i), x + 0.1, y - 0.1, None);
+            insert_point(&mut tree, &format!("{}b", i), x - 0.2, y + 0.2, None);
+        }
+
+        let bounds = Rectangle::new(-5.0, -5.0, 5.0, 5.0);
+        let stats = tree.aggregate(&bounds, binning, None);
+
+        assert_eq!(stats.len(), 3);
+        assert!(stats.iter().all(|s| s.count == 2));
+        for center in centers {
+            assert!(stats.iter().any(|s| s.center == center));
+        }
+    }
+
+    #[test]
+    fn test_hex_round_trip() {
+        let binning = Binning::Hex { size: 0.01 };
+        for cell in [(0, 0), (3, -2), (-7, 5), (12, 12)] {
+            let [x, y] = binning.center_of(cell);
+            assert_eq!(binning.cell_of(x, y), cell);
+        }
+    }
+}
@@ -1,15 +1,18 @@
 //! AOF (Append-Only File) 持久化模块
 //!
 //! 实现基于 JSON Lines 格式的追加式日志持久化系统，支持：
-//! - 写入命令到 AOF 文件
+//! - 写入命令到 AOF 文件，可选带长度和 CRC32 校验的分帧记录（见 [`AofFormat`]）
 //! - 从 AOF 文件恢复数据
 //! - 三种同步策略（Always、EverySecond、No）
+//! - fsync 停顿检测与同步策略自动降级
+//! - AOF 重写：用当前数据的最小记录集合替换文件（见 `storage::rewrite`）
 //! - 容错恢复机制
 
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
+use tracing::{info, warn};
 
 // ============================================================================
 // 错误类型
@@ -72,6 +75,245 @@ pub enum AofSyncPolicy {
     No,
 }
 
+impl AofSyncPolicy {
+    /// 返回与配置文件一致的策略名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Always => "always",
+            Self::EverySecond => "everysec",
+            Self::No => "no",
+        }
+    }
+}
+
+// ============================================================================
+// 记录格式
+// ============================================================================
+
+/// AOF 记录格式
+///
+/// 两种格式都是一行一条记录，读取时逐行识别，同一个文件中可以混合出现：
+/// 切换格式后新的记录使用新格式，之前的记录保持原样，下一次重写时整个文件转换为新格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AofFormat {
+    /// 每行一个 JSON 对象，没有完整性校验
+    #[default]
+    JsonLines,
+
+    /// `#<版本> <长度> <CRC32> <JSON>`，长度和 CRC32 为 8 位十六进制，都针对 JSON 部分
+    ///
+    /// 写了一半的记录长度对不上，磁盘上的位翻转校验和对不上，
+    /// 不会因为残缺的 JSON 碰巧能够解析而被当作完整的记录
+    Framed,
+}
+
+impl AofFormat {
+    /// 返回与配置文件一致的格式名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::JsonLines => "json",
+            Self::Framed => "framed",
+        }
+    }
+
+    /// 把序列化后的命令编码为一条记录（不含换行符）
+    pub fn encode(&self, json: String) -> String {
+        match self {
+            Self::JsonLines => json,
+            Self::Framed => format!(
+                "{}{} {:08x} {:08x} {}",
+                FRAME_MARKER as char,
+                FRAME_VERSION,
+                json.len(),
+                crc32(json.as_bytes()),
+                json
+            ),
+        }
+    }
+}
+
+/// 分帧记录的起始字符，JSON Lines 记录以 `{` 开头
+const FRAME_MARKER: u8 = b'#';
+
+/// 当前的分帧记录版本，写在起始字符之后
+pub const FRAME_VERSION: u8 = 1;
+
+/// 分帧记录头部的长度，例如 `#1 0000002a 9ad1c2f3 `
+const FRAME_HEADER_LEN: usize = 21;
+
+/// 解析一条记录（不含换行符），自动识别格式
+pub fn decode_record(record: &[u8]) -> Result<AofCommand, String> {
+    let json = if is_frame(record) {
+        decode_frame(record)?
+    } else {
+        record
+    };
+    serde_json::from_slice(json).map_err(|e| e.to_string())
+}
+
+/// 记录是否为分帧格式
+fn is_frame(record: &[u8]) -> bool {
+    record.first() == Some(&FRAME_MARKER)
+}
+
+/// 校验分帧记录的头部，返回其中的 JSON
+fn decode_frame(record: &[u8]) -> Result<&[u8], String> {
+    let header = record
+        .get(..FRAME_HEADER_LEN)
+        .ok_or("truncated record header")?;
+    if header[1] != b'0' + FRAME_VERSION {
+        return Err(format!(
+            "unsupported record version '{}'",
+            header[1].escape_ascii()
+        ));
+    }
+    if header[2] != b' ' || header[11] != b' ' || header[20] != b' ' {
+        return Err("malformed record header".to_string());
+    }
+    let length = parse_hex(&header[3..11])? as usize;
+    let checksum = parse_hex(&header[12..20])?;
+
+    let json = &record[FRAME_HEADER_LEN..];
+    if json.len() != length {
+        return Err(format!(
+            "record is {} bytes, header says {}",
+            json.len(),
+            length
+        ));
+    }
+    if crc32(json) != checksum {
+        return Err("record checksum mismatch".to_string());
+    }
+    Ok(json)
+}
+
+fn parse_hex(digits: &[u8]) -> Result<u32, String> {
+    std::str::from_utf8(digits)
+        .ok()
+        .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+        .ok_or_else(|| "malformed record header".to_string())
+}
+
+/// CRC-32（IEEE 802.3 多项式，与 zlib 和 gzip 相同）
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc = CRC32_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+// ============================================================================
+// 停顿检测
+// ============================================================================
+
+/// 连续多少次 fsync 越过阈值才判定状态切换（避免单次抖动导致来回切换）
+pub const STALL_TRIGGER_COUNT: u32 = 3;
+
+/// 默认的缓冲区刷新阈值：累计 1MB 未刷新的数据
+pub const DEFAULT_FLUSH_BYTES: usize = 1024 * 1024;
+
+/// 停顿状态切换
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StallTransition {
+    /// 进入停顿状态
+    Entered,
+    /// 从停顿状态恢复
+    Recovered,
+}
+
+/// fsync 写入停顿检测器
+///
+/// 连续 `trigger_count` 次 fsync 耗时超过阈值即判定为停顿；
+/// 停顿期间连续 `trigger_count` 次 fsync 耗时低于阈值则判定为恢复
+#[derive(Debug, Clone)]
+pub struct StallDetector {
+    threshold: Duration,
+    trigger_count: u32,
+    slow_streak: u32,
+    fast_streak: u32,
+    stalled: bool,
+    last_latency: Duration,
+}
+
+impl StallDetector {
+    /// 创建新的停顿检测器
+    ///
+    /// # 参数
+    /// * `threshold` - fsync 耗时阈值
+    /// * `trigger_count` - 触发状态切换所需的连续次数（至少为 1）
+    pub fn new(threshold: Duration, trigger_count: u32) -> Self {
+        Self {
+            threshold,
+            trigger_count: trigger_count.max(1),
+            slow_streak: 0,
+            fast_streak: 0,
+            stalled: false,
+            last_latency: Duration::ZERO,
+        }
+    }
+
+    /// 记录一次 fsync 耗时，如果发生状态切换则返回切换类型
+    pub fn record(&mut self, latency: Duration) -> Option<StallTransition> {
+        self.last_latency = latency;
+
+        if latency > self.threshold {
+            self.slow_streak += 1;
+            self.fast_streak = 0;
+            if !self.stalled && self.slow_streak >= self.trigger_count {
+                self.stalled = true;
+                return Some(StallTransition::Entered);
+            }
+        } else {
+            self.fast_streak += 1;
+            self.slow_streak = 0;
+            if self.stalled && self.fast_streak >= self.trigger_count {
+                self.stalled = false;
+                return Some(StallTransition::Recovered);
+            }
+        }
+
+        None
+    }
+
+    /// 当前是否处于停顿状态
+    pub fn is_stalled(&self) -> bool {
+        self.stalled
+    }
+
+    /// 最近一次 fsync 的耗时
+    pub fn last_latency(&self) -> Duration {
+        self.last_latency
+    }
+
+    /// 停顿判定阈值
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+}
+
 // ============================================================================
 // AOF 配置
 // ============================================================================
@@ -85,8 +327,32 @@ pub struct AofConfig {
     /// 同步策略
     pub sync_policy: AofSyncPolicy,
 
+    /// 新追加的记录和重写后文件的格式
+    pub format: AofFormat,
+
     /// 是否启用 AOF（可以临时关闭）
     pub enabled: bool,
+
+    /// fsync 停顿判定阈值（None 表示关闭停顿检测）
+    pub stall_threshold: Option<Duration>,
+
+    /// 检测到停顿时是否自动从 Always 降级为 EverySecond（磁盘恢复后自动还原）
+    pub stall_fallback: bool,
+
+    /// 缓冲区中未刷新的数据达到该字节数时写入文件（0 表示不按字节数刷新），同时作为缓冲区容量
+    pub flush_bytes: usize,
+
+    /// 缓冲区中未刷新的记录达到该条数时写入文件（0 表示不按条数刷新）
+    pub flush_records: usize,
+
+    /// 自动重写的最小文件大小（字节），小于该值时不重写
+    pub auto_rewrite_min_size: u64,
+
+    /// 文件比上次重写后（或启动时）增长超过该百分比时自动重写，0 表示关闭自动重写
+    pub auto_rewrite_percentage: u64,
+
+    /// Always 策略的组提交窗口（None 表示每条记录追加后立即 fsync）
+    pub group_commit: Option<Duration>,
 }
 
 impl Default for AofConfig {
@@ -94,7 +360,15 @@ impl Default for AofConfig {
         Self {
             file_path: PathBuf::from("data/appendonly.aof"),
             sync_policy: AofSyncPolicy::EverySecond,
+            format: AofFormat::JsonLines,
             enabled: true,
+            stall_threshold: None,
+            stall_fallback: false,
+            flush_bytes: DEFAULT_FLUSH_BYTES,
+            flush_records: 0,
+            auto_rewrite_min_size: 0,
+            auto_rewrite_percentage: 0,
+            group_commit: None,
         }
     }
 }
@@ -114,11 +388,59 @@ impl AofConfig {
         self
     }
 
+    /// 设置记录格式
+    pub fn with_format(mut self, format: AofFormat) -> Self {
+        self.format = format;
+        self
+    }
+
     /// 设置是否启用
     pub fn with_enabled(mut self, enabled: bool) -> Self {
         self.enabled = enabled;
         self
     }
+
+    /// 启用 fsync 停顿检测
+    ///
+    /// # 参数
+    /// * `threshold` - fsync 耗时超过该值视为慢速写入
+    /// * `fallback` - 停顿时是否自动降级为 EverySecond
+    pub fn with_stall_detection(mut self, threshold: Duration, fallback: bool) -> Self {
+        self.stall_threshold = Some(threshold);
+        self.stall_fallback = fallback;
+        self
+    }
+
+    /// 设置缓冲区刷新阈值，对 EverySecond 和 No 策略生效
+    ///
+    /// 任一阈值达到时把缓冲区写入文件（不 fsync）：No 策略的数据按固定节奏进入页缓存，
+    /// EverySecond 策略每秒的 fsync 不需要一次写出整秒的数据，延迟更平稳
+    ///
+    /// # 参数
+    /// * `bytes` - 未刷新的字节数阈值，0 表示不按字节数刷新
+    /// * `records` - 未刷新的记录条数阈值，0 表示不按条数刷新
+    pub fn with_flush_thresholds(mut self, bytes: usize, records: usize) -> Self {
+        self.flush_bytes = bytes;
+        self.flush_records = records;
+        self
+    }
+
+    /// 启用自动重写：文件不小于 `min_size` 字节，且比上次重写后增长了 `percentage`% 时触发
+    pub fn with_auto_rewrite(mut self, min_size: u64, percentage: u64) -> Self {
+        self.auto_rewrite_min_size = min_size;
+        self.auto_rewrite_percentage = percentage;
+        self
+    }
+
+    /// 启用组提交，对 Always 策略生效
+    ///
+    /// 追加记录时只写入缓冲区，由 [`GeoDatabase`](crate::storage::GeoDatabase) 的组提交任务在
+    /// `window` 内收集并发写入的记录，一次 write + fsync 后再回复这些命令。
+    /// 回复客户端时记录仍然已经落盘，并发写入越多，每次 fsync 分摊的命令越多
+    pub fn with_group_commit(mut self, window: Duration) -> Self {
+        self.group_commit = Some(window);
+        self
+    }
 }
 
 // ============================================================================
@@ -135,31 +457,108 @@ pub enum AofCommand {
     Insert {
         /// 时间戳（纳秒）
         ts: u64,
+        /// 序列号（单调递增，0 表示未编号的旧记录）
+        #[serde(default, skip_serializing_if = "is_unsequenced")]
+        seq: u64,
         /// 集合名称
         collection: String,
         /// 对象 key
         key: String,
         /// GeoJSON 数据
         geojson: String,
+        /// 对象标签（没有标签时不写入）
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<String>,
+    },
+
+    /// 写入 STRING 对象（不进入空间索引的文本值）
+    String {
+        /// 时间戳（纳秒）
+        ts: u64,
+        /// 序列号（单调递增，0 表示未编号的旧记录）
+        #[serde(default, skip_serializing_if = "is_unsequenced")]
+        seq: u64,
+        /// 集合名称
+        collection: String,
+        /// 对象 key
+        key: String,
+        /// 文本
+        value: String,
+        /// 对象标签（没有标签时不写入）
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<String>,
     },
 
     /// 删除命令
     Delete {
         /// 时间戳（纳秒）
         ts: u64,
+        /// 序列号（单调递增，0 表示未编号的旧记录）
+        #[serde(default, skip_serializing_if = "is_unsequenced")]
+        seq: u64,
         /// 集合名称
         collection: String,
         /// 对象 key
         key: String,
     },
 
+    /// 设置或清除对象的过期时间
+    Expire {
+        /// 时间戳（纳秒）
+        ts: u64,
+        /// 序列号（单调递增，0 表示未编号的旧记录）
+        #[serde(default, skip_serializing_if = "is_unsequenced")]
+        seq: u64,
+        /// 集合名称
+        collection: String,
+        /// 对象 key
+        key: String,
+        /// 过期时刻（Unix 纳秒），None 表示清除过期时间（PERSIST）
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        at: Option<u64>,
+    },
+
     /// 删除集合命令
     Drop {
         /// 时间戳（纳秒）
         ts: u64,
+        /// 序列号（单调递增，0 表示未编号的旧记录）
+        #[serde(default, skip_serializing_if = "is_unsequenced")]
+        seq: u64,
         /// 集合名称
         collection: String,
     },
+
+    /// 注册或替换围栏频道（SETCHAN），重放时按名称覆盖，重复重放结果相同
+    SetChan {
+        /// 时间戳（纳秒）
+        ts: u64,
+        /// 序列号（单调递增，0 表示未编号的旧记录）
+        #[serde(default, skip_serializing_if = "is_unsequenced")]
+        seq: u64,
+        /// 频道名称
+        name: String,
+        /// 频道名称之后的 SETCHAN 参数（collection 已加上命名空间前缀）
+        args: Vec<String>,
+    },
+
+    /// 删除围栏频道（DELCHAN）
+    DelChan {
+        /// 时间戳（纳秒）
+        ts: u64,
+        /// 序列号（单调递增，0 表示未编号的旧记录）
+        #[serde(default, skip_serializing_if = "is_unsequenced")]
+        seq: u64,
+        /// 频道名称
+        name: String,
+    },
+
+    /// 重写标记：重写后的 AOF 的第一条记录，之后是重写时的数据和重写开始之后追加的记录，
+    /// 从空数据库重放即可得到全部数据
+    Rewrite {
+        /// 重写开始的时间戳（纳秒）
+        ts: u64,
+    },
 }
 
 impl AofCommand {
@@ -167,17 +566,69 @@ impl AofCommand {
     pub fn timestamp(&self) -> u64 {
         match self {
             Self::Insert { ts, .. } => *ts,
+            Self::String { ts, .. } => *ts,
             Self::Delete { ts, .. } => *ts,
+            Self::Expire { ts, .. } => *ts,
             Self::Drop { ts, .. } => *ts,
+            Self::SetChan { ts, .. } => *ts,
+            Self::DelChan { ts, .. } => *ts,
+            Self::Rewrite { ts } => *ts,
+        }
+    }
+
+    /// 获取命令的序列号，0 表示未编号
+    pub fn seq(&self) -> u64 {
+        match self {
+            Self::Insert { seq, .. } => *seq,
+            Self::String { seq, .. } => *seq,
+            Self::Delete { seq, .. } => *seq,
+            Self::Expire { seq, .. } => *seq,
+            Self::Drop { seq, .. } => *seq,
+            Self::SetChan { seq, .. } => *seq,
+            Self::DelChan { seq, .. } => *seq,
+            Self::Rewrite { .. } => 0,
         }
     }
 
-    /// 获取命令关联的集合名称
+    /// 设置命令的序列号，重写标记没有序列号
+    pub fn with_seq(mut self, value: u64) -> Self {
+        match &mut self {
+            Self::Insert { seq, .. } => *seq = value,
+            Self::String { seq, .. } => *seq = value,
+            Self::Delete { seq, .. } => *seq = value,
+            Self::Expire { seq, .. } => *seq = value,
+            Self::Drop { seq, .. } => *seq = value,
+            Self::SetChan { seq, .. } => *seq = value,
+            Self::DelChan { seq, .. } => *seq = value,
+            Self::Rewrite { .. } => {}
+        }
+        self
+    }
+
+    /// 设置命令的时间戳（纳秒），用于由外部时钟决定记录时间
+    pub fn with_timestamp(mut self, value: u64) -> Self {
+        match &mut self {
+            Self::Insert { ts, .. } => *ts = value,
+            Self::String { ts, .. } => *ts = value,
+            Self::Delete { ts, .. } => *ts = value,
+            Self::Expire { ts, .. } => *ts = value,
+            Self::Drop { ts, .. } => *ts = value,
+            Self::SetChan { ts, .. } => *ts = value,
+            Self::DelChan { ts, .. } => *ts = value,
+            Self::Rewrite { ts } => *ts = value,
+        }
+        self
+    }
+
+    /// 获取命令关联的集合名称，围栏频道和重写标记返回空字符串
     pub fn collection(&self) -> &str {
         match self {
             Self::Insert { collection, .. } => collection,
+            Self::String { collection, .. } => collection,
             Self::Delete { collection, .. } => collection,
+            Self::Expire { collection, .. } => collection,
             Self::Drop { collection, .. } => collection,
+            Self::SetChan { .. } | Self::DelChan { .. } | Self::Rewrite { .. } => "",
         }
     }
 
@@ -198,9 +649,36 @@ impl AofCommand {
     pub fn insert(collection: String, key: String, geojson: String) -> Self {
         Self::Insert {
             ts: Self::now(),
+            seq: 0,
             collection,
             key,
             geojson,
+            tags: Vec::new(),
+        }
+    }
+
+    /// 设置 INSERT 和 STRING 命令的对象标签，其他命令不受影响
+    pub fn with_tags(mut self, value: Vec<String>) -> Self {
+        if let Self::Insert { tags, .. } | Self::String { tags, .. } = &mut self {
+            *tags = value;
+        }
+        self
+    }
+
+    /// 创建 STRING 命令
+    ///
+    /// # 参数
+    /// * `collection` - 集合名称
+    /// * `key` - 对象 key
+    /// * `value` - 文本
+    pub fn string(collection: String, key: String, value: String) -> Self {
+        Self::String {
+            ts: Self::now(),
+            seq: 0,
+            collection,
+            key,
+            value,
+            tags: Vec::new(),
         }
     }
 
@@ -212,11 +690,28 @@ impl AofCommand {
     pub fn delete(collection: String, key: String) -> Self {
         Self::Delete {
             ts: Self::now(),
+            seq: 0,
             collection,
             key,
         }
     }
 
+    /// 创建 EXPIRE 命令
+    ///
+    /// # 参数
+    /// * `collection` - 集合名称
+    /// * `key` - 对象 key
+    /// * `at` - 过期时刻（Unix 纳秒），None 表示清除过期时间
+    pub fn expire(collection: String, key: String, at: Option<u64>) -> Self {
+        Self::Expire {
+            ts: Self::now(),
+            seq: 0,
+            collection,
+            key,
+            at,
+        }
+    }
+
     /// 创建 DROP 命令
     ///
     /// # 参数
@@ -224,9 +719,45 @@ impl AofCommand {
     pub fn drop(collection: String) -> Self {
         Self::Drop {
             ts: Self::now(),
+            seq: 0,
             collection,
         }
     }
+
+    /// 创建 SETCHAN 命令
+    ///
+    /// # 参数
+    /// * `name` - 频道名称
+    /// * `args` - 频道名称之后的 SETCHAN 参数
+    pub fn set_chan(name: String, args: Vec<String>) -> Self {
+        Self::SetChan {
+            ts: Self::now(),
+            seq: 0,
+            name,
+            args,
+        }
+    }
+
+    /// 创建 DELCHAN 命令
+    ///
+    /// # 参数
+    /// * `name` - 频道名称
+    pub fn del_chan(name: String) -> Self {
+        Self::DelChan {
+            ts: Self::now(),
+            seq: 0,
+            name,
+        }
+    }
+
+    /// 创建重写标记
+    pub fn rewrite(ts: u64) -> Self {
+        Self::Rewrite { ts }
+    }
+}
+
+fn is_unsequenced(seq: &u64) -> bool {
+    *seq == 0
 }
 
 // ============================================================================
@@ -244,7 +775,23 @@ pub struct AofWriter {
     writer: BufWriter<File>,
     config: AofConfig,
     last_sync: Instant,
+    /// 最近一次成功 fsync 的时间
+    last_fsync_at: Option<SystemTime>,
     bytes_written: u64,
+    /// 上次刷新缓冲区以来写入的字节数和记录数
+    pending_bytes: usize,
+    pending_records: usize,
+    /// 最近一次写入的序列号
+    last_seq: u64,
+    /// 已经 fsync 到磁盘的最大序列号
+    synced_seq: u64,
+    /// 文件当前大小（包括缓冲区中的数据）
+    file_size: u64,
+    /// 上次重写后（或启动时）的文件大小，自动重写按它计算增长比例
+    rewrite_base_size: u64,
+    stall_detector: Option<StallDetector>,
+    /// 是否因停顿从 Always 降级为 EverySecond
+    degraded: bool,
 }
 
 impl AofWriter {
@@ -275,23 +822,55 @@ impl AofWriter {
             std::fs::create_dir_all(parent)?;
         }
 
-        // 打开文件（追加模式）
+        // 从已有文件的末尾接着编号
+        let last_seq = read_last_seq(&config.file_path)?;
+
+        // 打开文件（追加模式），新建的文件需要把目录项也刷到磁盘
+        let created = !config.file_path.exists();
         let file = OpenOptions::new()
             .create(true)
             .append(true)
             .open(&config.file_path)?;
+        if created {
+            super::persistence::sync_parent_dir(&config.file_path)?;
+        }
+        let file_size = file.metadata()?.len();
+
+        let stall_detector = config
+            .stall_threshold
+            .map(|threshold| StallDetector::new(threshold, STALL_TRIGGER_COUNT));
+
+        let writer = Self::buffered(file, &config);
 
         Ok(Self {
-            writer: BufWriter::new(file),
+            writer,
             config,
             last_sync: Instant::now(),
+            last_fsync_at: None,
             bytes_written: 0,
+            pending_bytes: 0,
+            pending_records: 0,
+            last_seq,
+            synced_seq: last_seq,
+            file_size,
+            rewrite_base_size: file_size,
+            stall_detector,
+            degraded: false,
         })
     }
 
+    /// 缓冲区至少能放下一个刷新周期的数据，否则 BufWriter 会在阈值之前自行写出
+    fn buffered(file: File, config: &AofConfig) -> BufWriter<File> {
+        match config.flush_bytes {
+            0 => BufWriter::new(file),
+            capacity => BufWriter::with_capacity(capacity, file),
+        }
+    }
+
     /// 追加命令到 AOF
     ///
-    /// 将命令序列化为 JSON Lines 格式并写入文件，根据同步策略决定是否立即同步到磁盘
+    /// 将命令序列化为 JSON，按配置的格式写入一行，根据同步策略决定是否立即同步到磁盘。
+    /// 返回这条记录的序列号
     ///
     /// # 参数
     /// * `cmd` - 要追加的命令
@@ -318,51 +897,196 @@ impl AofWriter {
     ///
     /// writer.append(&cmd).unwrap();
     /// ```
-    pub fn append(&mut self, cmd: &AofCommand) -> Result<(), AofError> {
-        // 序列化为 JSON（单行，不换行）
-        let json = serde_json::to_string(cmd)?;
+    pub fn append(&mut self, cmd: &AofCommand) -> Result<u64, AofError> {
+        let seq = self.write_record(cmd)?;
 
-        // 写入一行（JSON + \n）
-        writeln!(self.writer, "{}", json)?;
+        // 根据同步策略决定是否 fsync
+        self.sync_if_needed()?;
 
-        self.bytes_written += (json.len() + 1) as u64;
+        Ok(seq)
+    }
 
-        // 根据同步策略决定是否 fsync
+    /// 连续追加一批命令，之后按同步策略只同步一次，返回最后一条记录的序列号
+    ///
+    /// 用于 IMPORT 等批量写入：`Always` 策略下整批只 fsync 一次。空的批次不写入任何记录，
+    /// 返回 [`last_seq`](Self::last_seq)
+    pub fn append_batch(&mut self, cmds: &[AofCommand]) -> Result<u64, AofError> {
+        if cmds.is_empty() {
+            return Ok(self.last_seq);
+        }
+        let mut seq = self.last_seq;
+        for cmd in cmds {
+            seq = self.write_record(cmd)?;
+        }
         self.sync_if_needed()?;
+        Ok(seq)
+    }
 
-        Ok(())
+    /// 分配序列号并把一条记录写入缓冲区（不同步）
+    fn write_record(&mut self, cmd: &AofCommand) -> Result<u64, AofError> {
+        // 分配序列号：未编号的命令使用下一个序列号，已编号的命令（例如复制重试）保留原序列号
+        let seq = match cmd.seq() {
+            0 => self.last_seq + 1,
+            seq => seq,
+        };
+        self.last_seq = self.last_seq.max(seq);
+
+        // 序列化为 JSON（单行，不换行），按配置的格式编码
+        let json = if cmd.seq() == seq {
+            serde_json::to_string(cmd)?
+        } else {
+            serde_json::to_string(&cmd.clone().with_seq(seq))?
+        };
+        let record = self.config.format.encode(json);
+
+        // 写入一行（记录 + \n）
+        writeln!(self.writer, "{}", record)?;
+
+        self.bytes_written += (record.len() + 1) as u64;
+        self.file_size += (record.len() + 1) as u64;
+        self.pending_bytes += record.len() + 1;
+        self.pending_records += 1;
+
+        Ok(seq)
+    }
+
+    /// 最近一次写入的序列号
+    pub fn last_seq(&self) -> u64 {
+        self.last_seq
+    }
+
+    /// 确保之后分配的序列号大于 `seq`（恢复时遇到更大的序列号时调用）
+    pub fn resume_after(&mut self, seq: u64) {
+        self.last_seq = self.last_seq.max(seq);
     }
 
     /// 根据策略执行同步
     ///
-    /// - `Always`: 立即 flush 并 fsync
-    /// - `EverySecond`: 每秒 flush 并 fsync
-    /// - `No`: 每 1MB flush（不 fsync）
+    /// - `Always`: 立即 flush 并 fsync；启用组提交时由组提交任务 fsync，其间达到刷新阈值时只 flush
+    /// - `EverySecond`: 每秒 flush 并 fsync，其间达到刷新阈值时只 flush
+    /// - `No`: 达到刷新阈值时 flush（不 fsync）
     fn sync_if_needed(&mut self) -> Result<(), AofError> {
-        match self.config.sync_policy {
+        match self.effective_sync_policy() {
+            AofSyncPolicy::Always if self.config.group_commit.is_some() => {
+                // 由组提交任务批量 fsync，见 prepare_group_sync
+                self.flush_if_due()?;
+            }
             AofSyncPolicy::Always => {
                 // 立即刷新并同步到磁盘
-                self.writer.flush()?;
-                self.writer.get_ref().sync_data()?;
+                self.sync_data()?;
             }
             AofSyncPolicy::EverySecond => {
                 // 每秒同步一次
                 if self.last_sync.elapsed().as_secs() >= 1 {
-                    self.writer.flush()?;
-                    self.writer.get_ref().sync_data()?;
+                    self.sync_data()?;
                     self.last_sync = Instant::now();
+                } else {
+                    self.flush_if_due()?;
                 }
             }
             AofSyncPolicy::No => {
-                // 每 1MB 刷新一次缓冲区（但不 fsync）
-                if self.bytes_written % (1024 * 1024) == 0 {
-                    self.writer.flush()?;
-                }
+                // 达到阈值时刷新缓冲区（但不 fsync）
+                self.flush_if_due()?;
             }
         }
         Ok(())
     }
 
+    /// 未刷新的数据达到任一阈值时把缓冲区写入文件
+    fn flush_if_due(&mut self) -> Result<(), AofError> {
+        let bytes_due =
+            self.config.flush_bytes > 0 && self.pending_bytes >= self.config.flush_bytes;
+        let records_due =
+            self.config.flush_records > 0 && self.pending_records >= self.config.flush_records;
+        if bytes_due || records_due {
+            self.flush_buffer()?;
+        }
+        Ok(())
+    }
+
+    /// 把缓冲区写入文件（不 fsync）
+    fn flush_buffer(&mut self) -> Result<(), AofError> {
+        self.writer.flush()?;
+        self.pending_bytes = 0;
+        self.pending_records = 0;
+        Ok(())
+    }
+
+    /// 刷新缓冲区并 fsync，同时记录耗时用于停顿检测
+    fn sync_data(&mut self) -> Result<(), AofError> {
+        let start = Instant::now();
+        self.flush_buffer()?;
+        self.writer.get_ref().sync_data()?;
+        self.synced_seq = self.last_seq;
+        self.record_fsync(start.elapsed());
+        Ok(())
+    }
+
+    /// 记录一次成功的 fsync 及其耗时，停顿检测据此降级或还原同步策略
+    fn record_fsync(&mut self, latency: Duration) {
+        self.last_fsync_at = Some(SystemTime::now());
+
+        let transition = match self.stall_detector.as_mut() {
+            Some(detector) => detector.record(latency),
+            None => None,
+        };
+
+        match transition {
+            Some(StallTransition::Entered) => {
+                warn!(
+                    "⚠️  AOF write stall detected: fsync took {:?} (threshold {:?})",
+                    latency,
+                    self.config.stall_threshold.unwrap_or_default()
+                );
+                if self.config.stall_fallback && self.config.sync_policy == AofSyncPolicy::Always {
+                    self.degraded = true;
+                    self.last_sync = Instant::now();
+                    warn!("⚠️  AOF sync policy degraded: always -> everysec until disk recovers");
+                }
+            }
+            Some(StallTransition::Recovered) => {
+                info!("✅ AOF fsync latency recovered: {:?}", latency);
+                if self.degraded {
+                    self.degraded = false;
+                    info!("✅ AOF sync policy restored: everysec -> always");
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// 是否有记录等待组提交 fsync：只在 Always 策略启用组提交时可能为 true
+    pub fn sync_pending(&self) -> bool {
+        self.config.group_commit.is_some()
+            && self.effective_sync_policy() == AofSyncPolicy::Always
+            && self.synced_seq < self.last_seq
+    }
+
+    /// 已经 fsync 到磁盘的最大序列号
+    pub fn synced_seq(&self) -> u64 {
+        self.synced_seq
+    }
+
+    /// 组提交的第一步：把缓冲区写入文件，返回文件句柄和已经写入文件的最大序列号，
+    /// 没有需要 fsync 的记录时返回 None
+    ///
+    /// 调用方释放写入器后在句柄上 fsync，再调用 [`complete_group_sync`](Self::complete_group_sync)，
+    /// fsync 期间其他命令可以继续追加。重写替换文件时会 fsync 新文件，旧句柄上的 fsync 仍然有效
+    pub fn prepare_group_sync(&mut self) -> Result<Option<(File, u64)>, AofError> {
+        if self.synced_seq >= self.last_seq {
+            return Ok(None);
+        }
+        self.flush_buffer()?;
+        let file = self.writer.get_ref().try_clone()?;
+        Ok(Some((file, self.last_seq)))
+    }
+
+    /// 组提交的第二步：记录 `seq` 及之前的记录已经落盘，`latency` 是 fsync 的耗时
+    pub fn complete_group_sync(&mut self, seq: u64, latency: Duration) {
+        self.synced_seq = self.synced_seq.max(seq);
+        self.record_fsync(latency);
+    }
+
     /// 手动刷新缓冲区并同步到磁盘
     ///
     /// 用于确保所有数据都写入磁盘，通常在关闭前调用
@@ -378,20 +1102,121 @@ impl AofWriter {
     /// writer.flush().unwrap();
     /// ```
     pub fn flush(&mut self) -> Result<(), AofError> {
-        self.writer.flush()?;
+        self.flush_buffer()?;
         self.writer.get_ref().sync_all()?;
+        self.synced_seq = self.last_seq;
+        self.last_fsync_at = Some(SystemTime::now());
         Ok(())
     }
 
-    /// 获取已写入的字节数
-    pub fn bytes_written(&self) -> u64 {
-        self.bytes_written
+    /// 获取已写入的字节数
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// 文件当前大小（字节），包括缓冲区中尚未写入文件的数据
+    pub fn file_size(&self) -> u64 {
+        self.file_size
+    }
+
+    /// 上次重写后（或启动时）的文件大小（字节）
+    pub fn rewrite_base_size(&self) -> u64 {
+        self.rewrite_base_size
+    }
+
+    /// 是否达到配置的自动重写阈值
+    ///
+    /// 与 Redis 相同：文件不小于最小大小，且比上次重写后增长了指定的百分比
+    pub fn rewrite_due(&self) -> bool {
+        let percentage = self.config.auto_rewrite_percentage;
+        if percentage == 0 || self.file_size < self.config.auto_rewrite_min_size {
+            return false;
+        }
+        let base = self.rewrite_base_size.max(1);
+        self.file_size.saturating_sub(base) * 100 / base >= percentage
+    }
+
+    /// 开始重写：把缓冲区写入文件，返回当前文件长度
+    ///
+    /// 之后追加的记录从这个偏移开始，由 [`finish_rewrite`](Self::finish_rewrite) 复制到新文件末尾
+    pub fn begin_rewrite(&mut self) -> Result<u64, AofError> {
+        self.flush_to_file()
+    }
+
+    /// 把缓冲区写入文件（不 fsync），返回文件长度：此后文件中这个长度之内的内容都是完整的记录
+    pub fn flush_to_file(&mut self) -> Result<u64, AofError> {
+        self.flush_buffer()?;
+        Ok(self.file_size)
+    }
+
+    /// 完成重写：把 `offset` 之后追加的记录复制到 `rewritten` 末尾，fsync 后原子地替换 AOF 文件，
+    /// 之后的记录追加到新文件。返回新文件的大小
+    ///
+    /// 调用方在复制期间持有写入器，新的记录不会落在两个文件之间。
+    /// 替换之前失败时原文件保持不变，由调用方删除 `rewritten`
+    pub fn finish_rewrite(
+        &mut self,
+        rewritten: &std::path::Path,
+        offset: u64,
+    ) -> Result<u64, AofError> {
+        use std::io::{Seek, SeekFrom};
+
+        self.flush_buffer()?;
+        let mut tail = File::open(&self.config.file_path)?;
+        tail.seek(SeekFrom::Start(offset))?;
+        let mut file = OpenOptions::new().append(true).open(rewritten)?;
+        std::io::copy(&mut tail, &mut file)?;
+        file.sync_all()?;
+
+        std::fs::rename(rewritten, &self.config.file_path)?;
+        super::persistence::sync_parent_dir(&self.config.file_path)?;
+
+        let size = file.metadata()?.len();
+        self.writer = Self::buffered(file, &self.config);
+        self.file_size = size;
+        self.rewrite_base_size = size;
+        self.synced_seq = self.last_seq;
+        self.last_fsync_at = Some(SystemTime::now());
+        Ok(size)
+    }
+
+    /// 缓冲区中尚未写入文件的字节数，进程崩溃时会丢失
+    pub fn buffered_bytes(&self) -> usize {
+        self.writer.buffer().len()
+    }
+
+    /// 最近一次成功 fsync 的时间，还没有 fsync 过时返回 None
+    pub fn last_fsync_at(&self) -> Option<SystemTime> {
+        self.last_fsync_at
     }
 
     /// 获取配置的引用
     pub fn config(&self) -> &AofConfig {
         &self.config
     }
+
+    /// 当前实际生效的同步策略（停顿降级时与配置不同）
+    pub fn effective_sync_policy(&self) -> AofSyncPolicy {
+        if self.degraded {
+            AofSyncPolicy::EverySecond
+        } else {
+            self.config.sync_policy
+        }
+    }
+
+    /// 是否检测到 fsync 停顿
+    pub fn is_stalled(&self) -> bool {
+        self.stall_detector
+            .as_ref()
+            .is_some_and(|detector| detector.is_stalled())
+    }
+
+    /// 最近一次 fsync 的耗时（未启用停顿检测时返回 None）
+    pub fn last_fsync_latency(&self) -> Option<Duration> {
+        self.stall_detector
+            .as_ref()
+            .map(|detector| detector.last_latency())
+    }
 }
 
 impl Drop for AofWriter {
@@ -401,16 +1226,181 @@ impl Drop for AofWriter {
     }
 }
 
+/// 从 AOF 文件末尾读取最后一条记录的序列号，文件不存在或没有编号的记录时返回 0
+///
+/// 从末尾 64KB 开始向前查找，遇到超长的 GeoJSON 记录时逐步扩大读取范围
+fn read_last_seq(path: &std::path::Path) -> Result<u64, AofError> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+    let len = file.metadata()?.len();
+
+    let mut window = 64 * 1024u64;
+    loop {
+        let start = len.saturating_sub(window);
+        file.seek(SeekFrom::Start(start))?;
+        let mut buf = Vec::with_capacity((len - start) as usize);
+        file.read_to_end(&mut buf)?;
+
+        let text = String::from_utf8_lossy(&buf);
+        let mut lines: Vec<&str> = text.lines().collect();
+        // 窗口起点可能落在某一行的中间
+        if start > 0 && !lines.is_empty() {
+            lines.remove(0);
+        }
+
+        // 最后一条可以解析的记录决定序列号（末尾可能有写了一半的记录）
+        for line in lines.iter().rev() {
+            if let Ok(cmd) = decode_record(line.trim().as_bytes()) {
+                return Ok(cmd.seq());
+            }
+        }
+
+        if start == 0 {
+            return Ok(0);
+        }
+        window *= 4;
+    }
+}
+
+/// AOF 最近一次重写开始的时间戳（纳秒），文件不存在或没有重写过时返回 None
+///
+/// 只读取第一行：重写后的文件以重写标记开头
+pub fn rewritten_at(path: &std::path::Path) -> Result<Option<u64>, AofError> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut line = String::new();
+    BufReader::new(file).read_line(&mut line)?;
+    match decode_record(line.trim().as_bytes()) {
+        Ok(AofCommand::Rewrite { ts }) => Ok(Some(ts)),
+        _ => Ok(None),
+    }
+}
+
+/// AOF 末尾无法解析或校验失败的记录（通常是断电时写了一半）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CorruptedTail {
+    /// 损坏记录的起始字节偏移，也是截断后的文件长度
+    pub offset: u64,
+    /// 损坏记录占用的字节数
+    pub discarded_bytes: u64,
+}
+
+/// 文件末尾的状态
+enum TailState {
+    /// 最后一条记录完整，或文件为空
+    Clean,
+    /// 最后一条记录完整但缺少换行符，直接追加会和下一条记录粘在一起
+    MissingNewline,
+    Corrupted(CorruptedTail),
+}
+
+/// 检查 AOF 文件最后一条记录是否损坏，不修改文件
+///
+/// 只检查最后一条非空记录；文件中间的损坏行在恢复时逐行跳过并报告
+pub fn find_corrupted_tail(path: &std::path::Path) -> Result<Option<CorruptedTail>, AofError> {
+    let mut file = File::open(path)?;
+    match inspect_tail(&mut file)? {
+        TailState::Corrupted(tail) => Ok(Some(tail)),
+        TailState::Clean | TailState::MissingNewline => Ok(None),
+    }
+}
+
+/// 将 AOF 文件截断到最后一条完整记录之后，返回被截掉的损坏记录
+///
+/// 最后一条记录完整但缺少换行符时补上换行符，不丢弃数据。修改后立即 fsync
+pub fn truncate_corrupted_tail(path: &std::path::Path) -> Result<Option<CorruptedTail>, AofError> {
+    use std::io::Seek;
+
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    match inspect_tail(&mut file)? {
+        TailState::Clean => Ok(None),
+        TailState::MissingNewline => {
+            file.seek(std::io::SeekFrom::End(0))?;
+            file.write_all(b"\n")?;
+            file.sync_all()?;
+            Ok(None)
+        }
+        TailState::Corrupted(tail) => {
+            file.set_len(tail.offset)?;
+            file.sync_all()?;
+            Ok(Some(tail))
+        }
+    }
+}
+
+/// 找到最后一条非空记录并检查能否解析，读取范围与 [`read_last_seq`] 相同
+fn inspect_tail(file: &mut File) -> Result<TailState, AofError> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let len = file.metadata()?.len();
+
+    let mut window = 64 * 1024u64;
+    loop {
+        let start = len.saturating_sub(window);
+        file.seek(SeekFrom::Start(start))?;
+        let mut buf = Vec::with_capacity((len - start) as usize);
+        file.read_to_end(&mut buf)?;
+
+        // 跳过末尾的空白，找到最后一条记录的起止位置
+        let Some(end) = buf
+            .iter()
+            .rposition(|b| !b.is_ascii_whitespace())
+            .map(|i| i + 1)
+        else {
+            if start == 0 {
+                return Ok(TailState::Clean);
+            }
+            window *= 4;
+            continue;
+        };
+        let line_start = match buf[..end].iter().rposition(|&b| b == b'\n') {
+            Some(newline) => newline + 1,
+            None if start == 0 => 0,
+            // 窗口起点落在最后一条记录的中间
+            None => {
+                window *= 4;
+                continue;
+            }
+        };
+
+        if decode_record(&buf[line_start..end]).is_ok() {
+            return Ok(if buf.last() == Some(&b'\n') {
+                TailState::Clean
+            } else {
+                TailState::MissingNewline
+            });
+        }
+
+        let offset = start + line_start as u64;
+        return Ok(TailState::Corrupted(CorruptedTail {
+            offset,
+            discarded_bytes: len - offset,
+        }));
+    }
+}
+
 // ============================================================================
 // AOF Reader
 // ============================================================================
 
 /// AOF 读取器
 ///
-/// 负责从 AOF 文件中读取命令，支持容错恢复
+/// 负责从 AOF 文件中读取命令，支持容错恢复。逐条识别记录格式，两种格式可以混合出现
 pub struct AofReader {
     reader: BufReader<File>,
     line_count: usize,
+    /// 已经读取的字节数
+    offset: u64,
+    /// 文件末尾写了一半的分帧记录
+    corrupted_tail: Option<CorruptedTail>,
 }
 
 impl AofReader {
@@ -440,14 +1430,20 @@ impl AofReader {
         Ok(Self {
             reader: BufReader::new(file),
             line_count: 0,
+            offset: 0,
+            corrupted_tail: None,
         })
     }
 
     /// 读取下一条命令
     ///
-    /// 逐行读取 AOF 文件，解析 JSON Lines 格式的命令。
+    /// 逐行读取 AOF 文件，解析 JSON Lines 或分帧格式的命令。
     /// 自动跳过空行。
     ///
+    /// 文件最后一条记录是校验失败的分帧记录时，视为断电时写了一半：不返回错误，
+    /// 读取到此结束，可以从 [`corrupted_tail`](Self::corrupted_tail) 取得它的位置。
+    /// 文件中间的损坏记录仍然返回错误，调用方可以跳过它继续读取
+    ///
     /// # 返回
     /// - `Ok(Some(command))` - 成功读取到命令
     /// - `Ok(None)` - 到达文件末尾
@@ -465,35 +1461,54 @@ impl AofReader {
     /// }
     /// ```
     pub fn read_next(&mut self) -> Result<Option<AofCommand>, AofError> {
-        let mut line = String::new();
+        if self.corrupted_tail.is_some() {
+            return Ok(None);
+        }
+        let mut line = Vec::new();
 
         loop {
             line.clear();
-            let bytes_read = self.reader.read_line(&mut line)?;
+            let bytes_read = self.reader.read_until(b'\n', &mut line)?;
 
             if bytes_read == 0 {
                 return Ok(None); // EOF
             }
 
+            let start = self.offset;
+            self.offset += bytes_read as u64;
             self.line_count += 1;
-            let line = line.trim();
+            let record = line.trim_ascii();
 
-            if line.is_empty() {
+            if record.is_empty() {
                 continue; // 跳过空行
             }
 
-            match serde_json::from_str::<AofCommand>(line) {
+            match decode_record(record) {
                 Ok(cmd) => return Ok(Some(cmd)),
-                Err(e) => {
+                Err(_) if is_frame(record) && self.reader.fill_buf()?.is_empty() => {
+                    self.corrupted_tail = Some(CorruptedTail {
+                        offset: start,
+                        discarded_bytes: self.offset - start,
+                    });
+                    return Ok(None);
+                }
+                Err(reason) => {
                     return Err(AofError::InvalidCommand {
                         line: self.line_count,
-                        reason: e.to_string(),
+                        reason,
                     });
                 }
             }
         }
     }
 
+    /// 文件末尾写了一半的分帧记录，读到文件末尾之后才确定
+    ///
+    /// 启动时由 [`truncate_corrupted_tail`] 截掉；这里只报告，不修改文件
+    pub fn corrupted_tail(&self) -> Option<CorruptedTail> {
+        self.corrupted_tail
+    }
+
     /// 恢复所有命令（容错模式）
     ///
     /// 读取整个 AOF 文件，尽可能恢复所有有效命令。
@@ -607,6 +1622,19 @@ mod tests {
         assert!(cmd.timestamp() > 0);
     }
 
+    #[test]
+    fn test_aof_command_expire_serialization() {
+        let cmd = AofCommand::expire("cities".to_string(), "beijing".to_string(), Some(42));
+        let json = serde_json::to_string(&cmd).unwrap();
+        assert!(json.contains(r#""cmd":"EXPIRE""#));
+        assert!(json.contains(r#""at":42"#));
+
+        // PERSIST 不写 at 字段
+        let cmd = AofCommand::expire("cities".to_string(), "beijing".to_string(), None);
+        let json = serde_json::to_string(&cmd).unwrap();
+        assert!(!json.contains(r#""at""#));
+    }
+
     #[test]
     fn test_aof_command_drop_creation() {
         let cmd = AofCommand::drop("cities".to_string());
@@ -644,6 +1672,8 @@ mod tests {
         assert!(matches!(cmd, AofCommand::Insert { .. }));
         assert_eq!(cmd.collection(), "cities");
         assert_eq!(cmd.timestamp(), 1698234567890123456);
+        // 没有序列号的旧记录
+        assert_eq!(cmd.seq(), 0);
 
         if let AofCommand::Insert { key, geojson, .. } = cmd {
             assert_eq!(key, "beijing");
@@ -655,7 +1685,11 @@ mod tests {
     fn test_aof_command_all_types_serialization() {
         let commands = vec![
             AofCommand::insert("test".to_string(), "key1".to_string(), "{}".to_string()),
+            AofCommand::string("test".to_string(), "note".to_string(), "hi".to_string())
+                .with_tags(vec!["memo".to_string()]),
             AofCommand::delete("test".to_string(), "key1".to_string()),
+            AofCommand::expire("test".to_string(), "key1".to_string(), Some(42)),
+            AofCommand::expire("test".to_string(), "key1".to_string(), None),
             AofCommand::drop("test".to_string()),
         ];
 
@@ -691,6 +1725,79 @@ mod tests {
         assert!(!config.enabled);
     }
 
+    #[test]
+    fn test_stall_detector_enter_and_recover() {
+        let mut detector = StallDetector::new(Duration::from_millis(100), 3);
+        let slow = Duration::from_millis(250);
+        let fast = Duration::from_millis(5);
+
+        // 单次抖动不会触发停顿
+        assert_eq!(detector.record(slow), None);
+        assert_eq!(detector.record(fast), None);
+        assert!(!detector.is_stalled());
+
+        // 连续 3 次慢速 fsync 进入停顿
+        assert_eq!(detector.record(slow), None);
+        assert_eq!(detector.record(slow), None);
+        assert_eq!(detector.record(slow), Some(StallTransition::Entered));
+        assert!(detector.is_stalled());
+        assert_eq!(detector.last_latency(), slow);
+
+        // 停顿期间继续慢速不会重复触发
+        assert_eq!(detector.record(slow), None);
+
+        // 连续 3 次快速 fsync 恢复
+        assert_eq!(detector.record(fast), None);
+        assert_eq!(detector.record(fast), None);
+        assert_eq!(detector.record(fast), Some(StallTransition::Recovered));
+        assert!(!detector.is_stalled());
+    }
+
+    #[test]
+    fn test_aof_writer_stall_fallback() {
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("test_stall.aof");
+
+        // 阈值为 0：任何 fsync 都视为慢速，用于模拟慢磁盘
+        let config = AofConfig::new(aof_path)
+            .set_sync_policy(AofSyncPolicy::Always)
+            .with_stall_detection(Duration::ZERO, true);
+        let mut writer = AofWriter::new(config).unwrap();
+
+        assert_eq!(writer.effective_sync_policy(), AofSyncPolicy::Always);
+        assert!(!writer.is_stalled());
+
+        let cmd = AofCommand::insert("test".to_string(), "key1".to_string(), "{}".to_string());
+        for _ in 0..STALL_TRIGGER_COUNT {
+            writer.append(&cmd).unwrap();
+        }
+
+        assert!(writer.is_stalled());
+        assert_eq!(writer.effective_sync_policy(), AofSyncPolicy::EverySecond);
+        assert_eq!(writer.config().sync_policy, AofSyncPolicy::Always);
+        assert!(writer.last_fsync_latency().is_some());
+    }
+
+    #[test]
+    fn test_aof_writer_stall_without_fallback() {
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("test_stall_no_fallback.aof");
+
+        let config = AofConfig::new(aof_path)
+            .set_sync_policy(AofSyncPolicy::Always)
+            .with_stall_detection(Duration::ZERO, false);
+        let mut writer = AofWriter::new(config).unwrap();
+
+        let cmd = AofCommand::insert("test".to_string(), "key1".to_string(), "{}".to_string());
+        for _ in 0..STALL_TRIGGER_COUNT {
+            writer.append(&cmd).unwrap();
+        }
+
+        // 只报告停顿，不降级
+        assert!(writer.is_stalled());
+        assert_eq!(writer.effective_sync_policy(), AofSyncPolicy::Always);
+    }
+
     #[test]
     fn test_aof_error_display() {
         let error = AofError::InvalidCommand {
@@ -708,6 +1815,86 @@ mod tests {
 
     use tempfile::TempDir;
 
+    #[test]
+    fn test_aof_writer_rewrite_due() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = AofConfig::new(temp_dir.path().join("test.aof")).with_auto_rewrite(200, 100);
+        let mut writer = AofWriter::new(config).unwrap();
+        assert!(!writer.rewrite_due());
+
+        let cmd = AofCommand::insert(
+            "cities".to_string(),
+            "beijing".to_string(),
+            r#"{"type":"Point","coordinates":[116.4,39.9]}"#.to_string(),
+        );
+        while writer.file_size() < 200 {
+            writer.append(&cmd).unwrap();
+        }
+        // 新文件的基准大小为 0，达到最小大小即触发
+        assert!(writer.rewrite_due());
+        drop(writer);
+
+        // 重新打开后以当前大小为基准，需要再增长一倍
+        let config = AofConfig::new(temp_dir.path().join("test.aof")).with_auto_rewrite(200, 100);
+        let mut writer = AofWriter::new(config).unwrap();
+        let base = writer.rewrite_base_size();
+        assert_eq!(writer.file_size(), base);
+        assert!(!writer.rewrite_due());
+        while writer.file_size() < base * 2 {
+            writer.append(&cmd).unwrap();
+        }
+        assert!(writer.rewrite_due());
+
+        // 百分比为 0 时关闭自动重写
+        let config = AofConfig::new(temp_dir.path().join("test.aof"));
+        assert!(!AofWriter::new(config).unwrap().rewrite_due());
+    }
+
+    #[test]
+    fn test_aof_writer_finish_rewrite() {
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("test.aof");
+        let rewritten = temp_dir.path().join("test.aof.tmp");
+        let insert = |key: &str| {
+            AofCommand::insert(
+                "cities".to_string(),
+                key.to_string(),
+                r#"{"type":"Point","coordinates":[116.4,39.9]}"#.to_string(),
+            )
+        };
+
+        let mut writer = AofWriter::new(AofConfig::new(aof_path.clone())).unwrap();
+        writer.append(&insert("a")).unwrap();
+        writer.append(&insert("b")).unwrap();
+
+        // 重写开始之后追加的记录被复制到新文件末尾
+        let offset = writer.begin_rewrite().unwrap();
+        writer.append(&insert("c")).unwrap();
+        assert_eq!(rewritten_at(&aof_path).unwrap(), None);
+        let base = [AofCommand::rewrite(7), AofCommand::drop("x".to_string())]
+            .map(|cmd| serde_json::to_string(&cmd).unwrap() + "\n")
+            .concat();
+        std::fs::write(&rewritten, base).unwrap();
+        let size = writer.finish_rewrite(&rewritten, offset).unwrap();
+        assert_eq!(rewritten_at(&aof_path).unwrap(), Some(7));
+        assert!(!rewritten.exists());
+        assert_eq!(writer.file_size(), size);
+        assert_eq!(writer.rewrite_base_size(), size);
+
+        // 之后的记录追加到新文件，序列号继续递增
+        assert_eq!(writer.append(&insert("d")).unwrap(), 4);
+        drop(writer);
+
+        let mut reader = AofReader::open(aof_path).unwrap();
+        let keys: Vec<String> = std::iter::from_fn(|| reader.read_next().unwrap())
+            .map(|cmd| match cmd {
+                AofCommand::Insert { key, .. } => key,
+                other => other.collection().to_string(),
+            })
+            .collect();
+        assert_eq!(keys, vec!["", "x", "c", "d"]);
+    }
+
     #[test]
     fn test_aof_writer_basic() {
         let temp_dir = TempDir::new().unwrap();
@@ -740,6 +1927,45 @@ mod tests {
         assert!(content.contains(r#""cmd":"DELETE""#));
     }
 
+    #[test]
+    fn test_aof_writer_sequence_numbers() {
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("test.aof");
+        let config = AofConfig::new(aof_path.clone()).set_sync_policy(AofSyncPolicy::Always);
+
+        {
+            let mut writer = AofWriter::new(config.clone()).unwrap();
+            assert_eq!(writer.last_seq(), 0);
+
+            let insert =
+                AofCommand::insert("cities".to_string(), "a".to_string(), "{}".to_string());
+            assert_eq!(writer.append(&insert).unwrap(), 1);
+            assert_eq!(writer.append(&insert).unwrap(), 2);
+
+            // 已编号的命令保留原序列号
+            let delete = AofCommand::delete("cities".to_string(), "a".to_string()).with_seq(7);
+            assert_eq!(writer.append(&delete).unwrap(), 7);
+            assert_eq!(writer.last_seq(), 7);
+        }
+
+        let content = std::fs::read_to_string(&aof_path).unwrap();
+        assert!(content.contains(r#""seq":1"#));
+        assert!(content.contains(r#""seq":7"#));
+
+        // 末尾写了一半的记录被忽略，重新打开后接着编号
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&aof_path)
+            .unwrap()
+            .write_all(br#"{"cmd":"DROP","ts":1,"seq":99,"colle"#)
+            .unwrap();
+        let mut writer = AofWriter::new(config).unwrap();
+        assert_eq!(writer.last_seq(), 7);
+
+        let drop_cmd = AofCommand::drop("cities".to_string());
+        assert_eq!(writer.append(&drop_cmd).unwrap(), 8);
+    }
+
     #[test]
     fn test_aof_writer_json_lines_format() {
         let temp_dir = TempDir::new().unwrap();
@@ -789,6 +2015,75 @@ mod tests {
         assert!(content.contains(r#""cmd":"INSERT""#));
     }
 
+    #[test]
+    fn test_aof_writer_append_batch() {
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("test_batch.aof");
+
+        let config = AofConfig::new(aof_path.clone()).set_sync_policy(AofSyncPolicy::Always);
+        let mut writer = AofWriter::new(config).unwrap();
+
+        let cmds: Vec<_> = (0..3)
+            .map(|i| AofCommand::insert("test".to_string(), format!("key{}", i), "{}".to_string()))
+            .collect();
+        assert_eq!(writer.append_batch(&cmds).unwrap(), 3);
+        assert_eq!(writer.synced_seq(), 3);
+        assert_eq!(writer.buffered_bytes(), 0);
+
+        // 空的批次不写入记录
+        assert_eq!(writer.append_batch(&[]).unwrap(), 3);
+        let content = std::fs::read_to_string(&aof_path).unwrap();
+        let seqs: Vec<u64> = content
+            .lines()
+            .map(|line| serde_json::from_str::<AofCommand>(line).unwrap().seq())
+            .collect();
+        assert_eq!(seqs, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_aof_writer_group_sync() {
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("test_group.aof");
+
+        let config = AofConfig::new(aof_path.clone())
+            .set_sync_policy(AofSyncPolicy::Always)
+            .with_group_commit(Duration::from_millis(1))
+            .with_stall_detection(Duration::from_secs(10), false);
+        let mut writer = AofWriter::new(config).unwrap();
+
+        // 追加时只写入缓冲区，等待组提交
+        for i in 0..3 {
+            let cmd = AofCommand::insert("test".to_string(), format!("key{}", i), "{}".to_string());
+            writer.append(&cmd).unwrap();
+        }
+        assert!(writer.sync_pending());
+        assert!(writer.last_fsync_at().is_none());
+        assert!(writer.buffered_bytes() > 0);
+
+        let (file, seq) = writer.prepare_group_sync().unwrap().unwrap();
+        assert_eq!(seq, 3);
+        assert_eq!(writer.buffered_bytes(), 0);
+        assert_eq!(
+            std::fs::read_to_string(&aof_path).unwrap().lines().count(),
+            3
+        );
+
+        // fsync 期间继续追加的记录留给下一组
+        let cmd = AofCommand::insert("test".to_string(), "key3".to_string(), "{}".to_string());
+        writer.append(&cmd).unwrap();
+        file.sync_data().unwrap();
+        writer.complete_group_sync(seq, Duration::from_millis(2));
+        assert_eq!(writer.synced_seq(), 3);
+        assert!(writer.sync_pending());
+        assert!(writer.last_fsync_at().is_some());
+        assert_eq!(writer.last_fsync_latency(), Some(Duration::from_millis(2)));
+
+        writer.flush().unwrap();
+        assert_eq!(writer.synced_seq(), 4);
+        assert!(!writer.sync_pending());
+        assert!(writer.prepare_group_sync().unwrap().is_none());
+    }
+
     #[test]
     fn test_aof_writer_sync_policy_everysecond() {
         let temp_dir = TempDir::new().unwrap();
@@ -832,6 +2127,51 @@ mod tests {
         assert!(content.contains(r#""cmd":"INSERT""#));
     }
 
+    #[test]
+    fn test_aof_writer_flush_thresholds() {
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("test_flush.aof");
+        let line_count =
+            |path: &std::path::Path| std::fs::read_to_string(path).unwrap().lines().count();
+
+        // 按条数：每 3 条写入文件一次
+        let config = AofConfig::new(aof_path.clone())
+            .set_sync_policy(AofSyncPolicy::No)
+            .with_flush_thresholds(0, 3);
+        let mut writer = AofWriter::new(config).unwrap();
+        let cmd = AofCommand::insert("test".to_string(), "key1".to_string(), "{}".to_string());
+        for _ in 0..2 {
+            writer.append(&cmd).unwrap();
+        }
+        assert_eq!(line_count(&aof_path), 0);
+        assert_eq!(writer.buffered_bytes() as u64, writer.bytes_written());
+        writer.append(&cmd).unwrap();
+        assert_eq!(line_count(&aof_path), 3);
+        assert_eq!(writer.buffered_bytes(), 0);
+        assert!(writer.last_fsync_at().is_none());
+        for _ in 0..2 {
+            writer.append(&cmd).unwrap();
+        }
+        assert_eq!(line_count(&aof_path), 3);
+        drop(writer);
+        assert_eq!(line_count(&aof_path), 5);
+
+        // 按字节数：不要求正好落在阈值的整数倍上
+        let aof_path = temp_dir.path().join("test_flush_bytes.aof");
+        let config = AofConfig::new(aof_path.clone())
+            .set_sync_policy(AofSyncPolicy::No)
+            .with_flush_thresholds(200, 0);
+        let mut writer = AofWriter::new(config).unwrap();
+        while writer.bytes_written() < 200 {
+            assert_eq!(line_count(&aof_path), 0);
+            writer.append(&cmd).unwrap();
+        }
+        assert_eq!(
+            std::fs::metadata(&aof_path).unwrap().len(),
+            writer.bytes_written()
+        );
+    }
+
     #[test]
     fn test_aof_writer_bytes_written() {
         let temp_dir = TempDir::new().unwrap();
@@ -1034,6 +2374,194 @@ mod tests {
         assert_eq!(result.total_lines, 3);
     }
 
+    #[test]
+    fn test_truncate_corrupted_tail() {
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("torn.aof");
+
+        // 两条完整记录 + 一条断电时写了一半的记录
+        let valid_len = {
+            let mut writer = AofWriter::new(
+                AofConfig::new(aof_path.clone()).set_sync_policy(AofSyncPolicy::Always),
+            )
+            .unwrap();
+            for key in ["key1", "key2"] {
+                let cmd = AofCommand::insert("test".to_string(), key.to_string(), "{}".to_string());
+                writer.append(&cmd).unwrap();
+            }
+            writer.flush().unwrap();
+            let len = std::fs::metadata(&aof_path).unwrap().len();
+
+            let mut file = OpenOptions::new().append(true).open(&aof_path).unwrap();
+            write!(file, "{{\"cmd\":\"INSERT\",\"collection\":\"te").unwrap();
+            len
+        };
+
+        let tail = find_corrupted_tail(&aof_path).unwrap().unwrap();
+        assert_eq!(tail.offset, valid_len);
+        // 只检查不修改
+        assert!(std::fs::metadata(&aof_path).unwrap().len() > valid_len);
+
+        assert_eq!(truncate_corrupted_tail(&aof_path).unwrap(), Some(tail));
+        assert_eq!(std::fs::metadata(&aof_path).unwrap().len(), valid_len);
+        assert_eq!(find_corrupted_tail(&aof_path).unwrap(), None);
+
+        // 截断后继续追加，恢复时没有任何错误
+        {
+            let mut writer = AofWriter::new(AofConfig::new(aof_path.clone())).unwrap();
+            let cmd = AofCommand::insert("test".to_string(), "key3".to_string(), "{}".to_string());
+            assert_eq!(writer.append(&cmd).unwrap(), 3);
+        }
+        let result = AofReader::open(aof_path).unwrap().recover_all().unwrap();
+        assert!(result.is_complete());
+        assert_eq!(result.commands.len(), 3);
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(
+            crc32(b"The quick brown fox jumps over the lazy dog"),
+            0x414F_A339
+        );
+    }
+
+    #[test]
+    fn test_framed_record_decoding() {
+        let cmd = AofCommand::insert("test".to_string(), "key1".to_string(), "{}".to_string());
+        let json = serde_json::to_string(&cmd).unwrap();
+        let record = AofFormat::Framed.encode(json.clone());
+        assert!(record.starts_with(&format!("#1 {:08x} ", json.len())));
+        assert_eq!(decode_record(record.as_bytes()).unwrap(), cmd);
+        assert_eq!(AofFormat::JsonLines.encode(json.clone()), json);
+        assert_eq!(decode_record(json.as_bytes()).unwrap(), cmd);
+
+        // 写了一半、被改动的字节和未知的版本都被识别出来
+        let torn = &record.as_bytes()[..record.len() - 5];
+        assert!(decode_record(torn).unwrap_err().contains("header says"));
+        assert!(decode_record(&record.as_bytes()[..10])
+            .unwrap_err()
+            .contains("truncated"));
+        let flipped = record.replace("key1", "key2");
+        assert_eq!(
+            decode_record(flipped.as_bytes()).unwrap_err(),
+            "record checksum mismatch"
+        );
+        let future = record.replacen("#1", "#2", 1);
+        assert!(decode_record(future.as_bytes())
+            .unwrap_err()
+            .contains("unsupported record version '2'"));
+    }
+
+    #[test]
+    fn test_aof_reader_framed_torn_tail() {
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("framed.aof");
+        let config = AofConfig::new(aof_path.clone())
+            .set_sync_policy(AofSyncPolicy::Always)
+            .with_format(AofFormat::Framed);
+
+        // 三条分帧记录，中间一条被改动；最后是一条写了一半的记录
+        let valid_len = {
+            let mut writer = AofWriter::new(config.clone()).unwrap();
+            for key in ["key1", "key2", "key3"] {
+                let cmd = AofCommand::insert("test".to_string(), key.to_string(), "{}".to_string());
+                writer.append(&cmd).unwrap();
+            }
+            writer.flush().unwrap();
+            std::fs::metadata(&aof_path).unwrap().len()
+        };
+        let content = std::fs::read_to_string(&aof_path).unwrap();
+        let torn = content.lines().next().unwrap().replace("key1", "key4");
+        let content = content.replacen("key2", "kez2", 1) + &torn[..torn.len() - 8];
+        std::fs::write(&aof_path, &content).unwrap();
+
+        // 中间的损坏记录报告为错误，末尾写了一半的记录不算错误
+        let mut reader = AofReader::open(aof_path.clone()).unwrap();
+        let result = reader.recover_all().unwrap();
+        assert_eq!(result.commands.len(), 2);
+        assert_eq!(result.errors.len(), 1);
+        assert!(result.errors[0].to_string().contains("checksum mismatch"));
+        let tail = reader.corrupted_tail().unwrap();
+        assert_eq!(tail.offset, valid_len);
+        assert_eq!(tail.discarded_bytes, (torn.len() - 8) as u64);
+
+        // 启动时截断末尾，新写入的序列号接在最后一条完整记录之后
+        assert_eq!(truncate_corrupted_tail(&aof_path).unwrap(), Some(tail));
+        let mut writer = AofWriter::new(config).unwrap();
+        assert_eq!(writer.last_seq(), 3);
+        let cmd = AofCommand::insert("test".to_string(), "key5".to_string(), "{}".to_string());
+        assert_eq!(writer.append(&cmd).unwrap(), 4);
+        drop(writer);
+        let mut reader = AofReader::open(aof_path).unwrap();
+        assert_eq!(reader.recover_all().unwrap().commands.len(), 3);
+        assert!(reader.corrupted_tail().is_none());
+    }
+
+    #[test]
+    fn test_truncate_corrupted_tail_detects_checksum_mismatch() {
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("flipped.aof");
+        let config = AofConfig::new(aof_path.clone()).with_format(AofFormat::Framed);
+        {
+            let mut writer = AofWriter::new(config).unwrap();
+            for key in ["key1", "key2"] {
+                let cmd = AofCommand::insert("test".to_string(), key.to_string(), "{}".to_string());
+                writer.append(&cmd).unwrap();
+            }
+        }
+
+        // 改动后的 JSON 仍然可以解析，只有校验和能发现
+        let content = std::fs::read_to_string(&aof_path).unwrap();
+        let first_len = content.lines().next().unwrap().len() as u64 + 1;
+        let flipped = content.replace("key2", "kez2");
+        std::fs::write(&aof_path, flipped).unwrap();
+
+        let tail = find_corrupted_tail(&aof_path).unwrap().unwrap();
+        assert_eq!(tail.offset, first_len);
+    }
+
+    #[test]
+    fn test_truncate_corrupted_tail_edge_cases() {
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("edge.aof");
+        let record =
+            serde_json::to_string(&AofCommand::insert("test".into(), "k".into(), "{}".into()))
+                .unwrap();
+
+        // 空文件和完整的文件不做修改
+        std::fs::write(&aof_path, "").unwrap();
+        assert_eq!(truncate_corrupted_tail(&aof_path).unwrap(), None);
+        std::fs::write(&aof_path, format!("{}\n\n", record)).unwrap();
+        assert_eq!(truncate_corrupted_tail(&aof_path).unwrap(), None);
+        assert_eq!(
+            std::fs::read_to_string(&aof_path).unwrap(),
+            format!("{}\n\n", record)
+        );
+
+        // 完整记录缺少换行符：补上换行符而不是丢弃
+        std::fs::write(&aof_path, &record).unwrap();
+        assert_eq!(truncate_corrupted_tail(&aof_path).unwrap(), None);
+        assert_eq!(
+            std::fs::read_to_string(&aof_path).unwrap(),
+            format!("{}\n", record)
+        );
+
+        // 断电后文件系统留下的零字节
+        std::fs::write(&aof_path, format!("{}\n\0\0\0\0", record)).unwrap();
+        let tail = truncate_corrupted_tail(&aof_path).unwrap().unwrap();
+        assert_eq!(tail.discarded_bytes, 4);
+        assert_eq!(
+            std::fs::read_to_string(&aof_path).unwrap(),
+            format!("{}\n", record)
+        );
+
+        // 中间的损坏行不属于末尾，不截断
+        std::fs::write(&aof_path, format!("{{broken\n{}\n", record)).unwrap();
+        assert_eq!(truncate_corrupted_tail(&aof_path).unwrap(), None);
+    }
+
     #[test]
     fn test_aof_reader_skip_empty_lines() {
         let temp_dir = TempDir::new().unwrap();
@@ -0,0 +1,235 @@
+use super::super::node::{Entry, Node, NodeType};
+use super::super::rectangle::Rectangle;
+use super::super::rtree::RTree;
+use super::utils::index_bboxes;
+
+/// 批量装载 - Sort-Tile-Recursive (STR) 打包
+///
+/// 自底向上建树：把 n 个条目按中心点 x 坐标排序后切成 S = ⌈√(n/M)⌉ 个竖条，
+/// 每个竖条内按 y 坐标排序，再依次装满节点；得到的节点作为上一层的条目重复这一过程，
+/// 直到只剩一个根节点。每个条目只参与 O(log n) 次排序，不做逐条插入时的
+/// ChooseLeaf 和节点分裂，装载大量对象时快得多，得到的节点重叠也更少。
+///
+/// 条目在竖条和节点之间平均分配，除根节点外每个节点至少有 m 个条目，
+/// 之后的插入和删除照常进行
+impl RTree {
+    /// 批量插入索引条目：与树中已有的条目一起按 STR 重新打包整棵树
+    ///
+    /// 与 [`insert`](Self::insert) 一样只修改空间索引，几何体和 GeoJSON 由调用方维护
+    pub fn bulk_load(&mut self, items: Vec<(Rectangle, String)>) {
+        let mut entries = Vec::new();
+        if let Some(root) = self.root_mut().take() {
+            collect_data_entries(*root, &mut entries);
+        }
+        entries.extend(
+            items
+                .into_iter()
+                .map(|(mbr, data)| Entry::Data { mbr, data }),
+        );
+        if entries.is_empty() {
+            return;
+        }
+
+        let max_entries = self.max_entries_internal();
+        let mut nodes = pack_level(entries, max_entries, NodeType::Leaf, 0);
+        let mut level = 0;
+        while nodes.len() > 1 {
+            level += 1;
+            let entries = nodes
+                .into_iter()
+                .map(|node| Entry::Node {
+                    mbr: node.mbr,
+                    node: Box::new(node),
+                })
+                .collect();
+            nodes = pack_level(entries, max_entries, NodeType::Index, level);
+        }
+        *self.root_mut() = nodes.pop().map(Box::new);
+    }
+
+    /// 暂停维护空间索引：之后的插入和删除只修改几何体等数据，
+    /// 由 [`rebuild_index`](Self::rebuild_index) 一次性建立索引
+    ///
+    /// 用于恢复（快照加载、AOF 重放）：暂停期间空间查询看不到任何对象
+    pub(crate) fn defer_indexing(&mut self) {
+        *self.root_mut() = None;
+        self.index_deferred = true;
+    }
+
+    /// 按当前所有对象批量装载空间索引，结束 [`defer_indexing`](Self::defer_indexing)
+    pub(crate) fn rebuild_index(&mut self) {
+        let mut items = Vec::with_capacity(self.geometry_map.len());
+        for (id, geometry) in &self.geometry_map {
+            // 插入时已经算过边界框，这里不会失败
+            if let Ok(rects) = index_bboxes(geometry) {
+                items.extend(rects.into_iter().map(|rect| (rect, id.clone())));
+            }
+        }
+        *self.root_mut() = None;
+        self.index_deferred = false;
+        self.bulk_load(items);
+    }
+}
+
+/// 收集子树中所有的数据条目
+fn collect_data_entries(node: Node, entries: &mut Vec<Entry>) {
+    for entry in node.entries {
+        match entry {
+            Entry::Data { .. } => entries.push(entry),
+            Entry::Node { node, .. } => collect_data_entries(*node, entries),
+        }
+    }
+}
+
+/// 把一层条目打包成节点
+fn pack_level(
+    mut entries: Vec<Entry>,
+    max_entries: usize,
+    node_type: NodeType,
+    level: usize,
+) -> Vec<Node> {
+    let node_count = entries.len().div_ceil(max_entries);
+    let slice_count = (node_count as f64).sqrt().ceil() as usize;
+
+    sort_by_center(&mut entries, 0);
+    let mut nodes = Vec::with_capacity(node_count);
+    for mut slice in split_evenly(entries, slice_count) {
+        sort_by_center(&mut slice, 1);
+        let count = slice.len().div_ceil(max_entries);
+        for group in split_evenly(slice, count) {
+            let mut node = Node::new(node_type.clone(), level);
+            node.entries = group;
+            node.update_mbr();
+            nodes.push(node);
+        }
+    }
+    nodes
+}
+
+/// 按 MBR 中心点的某一维排序
+fn sort_by_center(entries: &mut [Entry], axis: usize) {
+    entries.sort_by(|a, b| a.mbr().center()[axis].total_cmp(&b.mbr().center()[axis]));
+}
+
+/// 按顺序平均分成 `parts` 份，各份的长度最多相差 1
+fn split_evenly(entries: Vec<Entry>, parts: usize) -> Vec<Vec<Entry>> {
+    let parts = parts.clamp(1, entries.len().max(1));
+    let (size, extra) = (entries.len() / parts, entries.len() % parts);
+    let mut rest = entries.into_iter();
+    (0..parts)
+        .map(|i| rest.by_ref().take(size + usize::from(i < extra)).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn grid(n: usize) -> Vec<(Rectangle, String)> {
+        (0..n)
+            .map(|i| {
+                let (x, y) = ((i % 100) as f64, (i / 100) as f64);
+                (Rectangle::new(x, y, x + 0.5, y + 0.5), i.to_string())
+            })
+            .collect()
+    }
+
+    /// 检查每个节点的条目数、层级和 MBR
+    fn check_node(node: &Node, rtree: &RTree, is_root: bool) -> usize {
+        assert!(node.entries.len() <= rtree.max_entries());
+        if !is_root {
+            assert!(node.entries.len() >= rtree.min_entries());
+        }
+        let mut expected = node.clone();
+        expected.update_mbr();
+        assert_eq!(node.mbr, expected.mbr);
+
+        node.entries
+            .iter()
+            .map(|entry| match entry {
+                Entry::Data { .. } => {
+                    assert!(node.is_leaf_node());
+                    1
+                }
+                Entry::Node { mbr, node: child } => {
+                    assert_eq!(child.level + 1, node.level);
+                    assert_eq!(*mbr, child.mbr);
+                    check_node(child, rtree, false)
+                }
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_bulk_load_structure() {
+        for n in [1, 4, 5, 17, 100, 1000, 2345] {
+            let mut rtree = RTree::new(8);
+            rtree.bulk_load(grid(n));
+            assert_eq!(rtree.len(), n);
+            assert_eq!(check_node(rtree.get_root().unwrap(), &rtree, true), n);
+        }
+
+        let mut empty = RTree::new(8);
+        empty.bulk_load(Vec::new());
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_bulk_load_search_matches_insert() {
+        let items = grid(1000);
+        let mut packed = RTree::new(6);
+        packed.bulk_load(items.clone());
+        let mut inserted = RTree::new(6);
+        for (rect, id) in items {
+            inserted.insert(rect, id);
+        }
+
+        let query = Rectangle::new(10.2, 3.0, 20.0, 7.7);
+        let mut expected = inserted.search_bbox(&query);
+        let mut found = packed.search_bbox(&query);
+        expected.sort();
+        found.sort();
+        assert_eq!(found, expected);
+        assert!(!found.is_empty());
+    }
+
+    #[test]
+    fn test_bulk_load_merges_existing_entries() {
+        let mut rtree = RTree::new(4);
+        for (rect, id) in grid(10) {
+            rtree.insert(rect, id);
+        }
+        rtree.bulk_load(grid(30).split_off(10));
+        assert_eq!(rtree.len(), 30);
+        assert_eq!(check_node(rtree.get_root().unwrap(), &rtree, true), 30);
+
+        // 打包后的树照常插入和删除
+        rtree.insert(
+            Rectangle::new(500.0, 500.0, 501.0, 501.0),
+            "new".to_string(),
+        );
+        assert!(rtree.delete_in_rtree(&Rectangle::new(0.0, 0.0, 0.5, 0.5), "0"));
+        assert_eq!(rtree.len(), 30);
+    }
+
+    #[test]
+    fn test_deferred_indexing() {
+        let mut rtree = RTree::new(4);
+        rtree.defer_indexing();
+        for i in 0..50 {
+            let point = json!({"type": "Point", "coordinates": [i as f64, 0.0]}).to_string();
+            assert!(rtree.insert_geojson(i.to_string(), &point));
+        }
+        assert!(rtree.delete("7"));
+        assert!(rtree.is_empty());
+        assert!(rtree.get("7").is_none());
+
+        rtree.rebuild_index();
+        assert_eq!(rtree.len(), 49);
+        let found = rtree.search_bbox(&Rectangle::new(5.0, -1.0, 8.0, 1.0));
+        assert_eq!(found.len(), 3);
+        assert!(rtree.delete("8"));
+        assert_eq!(rtree.len(), 48);
+    }
+}
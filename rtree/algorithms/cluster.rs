@@ -0,0 +1,229 @@
+use super::super::rectangle::Rectangle;
+use super::super::rtree::RTree;
+use super::knn::haversine_distance;
+use geo::Centroid;
+use std::collections::{HashMap, VecDeque};
+
+/// 每度纬度对应的米数（地球平均半径）
+const METERS_PER_DEGREE: f64 = 6_371_000.0 * std::f64::consts::PI / 180.0;
+
+/// 单个聚类的结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cluster {
+    /// 聚类编号，从 0 开始，按发现顺序分配
+    pub id: usize,
+    /// 成员质心的平均位置 [lon, lat]
+    pub centroid: [f64; 2],
+    /// 成员对象 ID（按 ID 排序）
+    pub members: Vec<String>,
+}
+
+/// 点集中的一个点：对象 ID 及其质心
+struct Site {
+    id: String,
+    lon: f64,
+    lat: f64,
+}
+
+/// 密度聚类相关算法
+impl RTree {
+    /// DBSCAN 密度聚类
+    ///
+    /// 以对象质心作为点，`eps` 为邻域半径（米），`min_points` 为核心点所需的邻居数量（包含自身）。
+    /// 邻域查询先用 R-tree 按扩展后的边界框筛选，再用 Haversine 距离精确判断。
+    /// `bounds` 不为 None 时只对质心落在范围内的对象聚类。噪声点不出现在结果中
+    pub fn cluster(&self, eps: f64, min_points: usize, bounds: Option<&Rectangle>) -> Vec<Cluster> {
+        // 收集参与聚类的点，按 ID 排序保证结果稳定
+        let mut ids: Vec<String> = match bounds {
+            Some(bounds) => self.search_bbox(bounds),
+            None => self.geometry_map.keys().cloned().collect(),
+        };
+        ids.sort();
+
+        let sites: Vec<Site> = ids
+            .into_iter()
+            .filter_map(|id| {
+                let point = self.geometry_map.get(&id)?.centroid()?;
+                if bounds.is_some_and(|b| !b.contains_point(point.x(), point.y())) {
+                    return None;
+                }
+                Some(Site {
+                    id,
+                    lon: point.x(),
+                    lat: point.y(),
+                })
+            })
+            .collect();
+        let index: HashMap<&str, usize> = sites
+            .iter()
+            .enumerate()
+            .map(|(i, site)| (site.id.as_str(), i))
+            .collect();
+
+        let neighbors = |i: usize| -> Vec<usize> {
+            let site = &sites[i];
+            let mut found: Vec<usize> = self
+                .search_bbox(&eps_rectangle(site.lon, site.lat, eps))
+                .iter()
+                .filter_map(|id| index.get(id.as_str()).copied())
+                .filter(|&j| {
+                    haversine_distance(site.lon, site.lat, sites[j].lon, sites[j].lat) <= eps
+                })
+                .collect();
+            found.sort_unstable();
+            found
+        };
+
+        // None: 未访问；Some(None): 噪声；Some(Some(c)): 属于聚类 c
+        let mut labels: Vec<Option<Option<usize>>> = vec![None; sites.len()];
+        let mut clusters: Vec<Vec<usize>> = Vec::new();
+
+        for i in 0..sites.len() {
+            if labels[i].is_some() {
+                continue;
+            }
+
+            let seeds = neighbors(i);
+            if seeds.len() < min_points {
+                labels[i] = Some(None);
+                continue;
+            }
+
+            let cluster_id = clusters.len();
+            let mut members = vec![i];
+            labels[i] = Some(Some(cluster_id));
+
+            let mut queue: VecDeque<usize> = seeds.into_iter().collect();
+            while let Some(j) = queue.pop_front() {
+                match labels[j] {
+                    // 之前标记为噪声的点成为边界点
+                    Some(None) => {
+                        labels[j] = Some(Some(cluster_id));
+                        members.push(j);
+                    }
+                    None => {
+                        labels[j] = Some(Some(cluster_id));
+                        members.push(j);
+
+                        // 核心点继续扩展
+                        let expansion = neighbors(j);
+                        if expansion.len() >= min_points {
+                            queue.extend(expansion);
+                        }
+                    }
+                    Some(Some(_)) => {}
+                }
+            }
+
+            clusters.push(members);
+        }
+
+        clusters
+            .into_iter()
+            .enumerate()
+            .map(|(id, members)| {
+                let n = members.len() as f64;
+                let lon = members.iter().map(|&m| sites[m].lon).sum::<f64>() / n;
+                let lat = members.iter().map(|&m| sites[m].lat).sum::<f64>() / n;
+
+                let mut member_ids: Vec<String> =
+                    members.iter().map(|&m| sites[m].id.clone()).collect();
+                member_ids.sort();
+
+                Cluster {
+                    id,
+                    centroid: [lon, lat],
+                    members: member_ids,
+                }
+            })
+            .collect()
+    }
+}
+
+/// 返回覆盖点周围 `eps` 米范围的边界框（度）
+fn eps_rectangle(lon: f64, lat: f64, eps: f64) -> Rectangle {
+    let dlat = eps / METERS_PER_DEGREE;
+    // 高纬度时经度方向的度数会急剧增大，靠近极点时直接覆盖所有经度
+    let cos_lat = (lat.abs() + dlat).min(90.0).to_radians().cos();
+    let dlon = if cos_lat > 1e-6 {
+        (dlat / cos_lat).min(180.0)
+    } else {
+        180.0
+    };
+    Rectangle::new(lon - dlon, lat - dlat, lon + dlon, lat + dlat)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn insert_point(tree: &mut RTree, id: &str, x: f64, y: f64) {
+        let geojson = json!({"type": "Point", "coordinates": [x, y]});
+        assert!(tree.insert_geojson(id.to_string(), &geojson.to_string()));
+    }
+
+    #[test]
+    fn test_cluster_two_groups_and_noise() {
+        let mut tree = RTree::new(4);
+        // 两组相距约 1km 内的点，0.001 度约为 111 米
+        for i in 0..5 {
+            let offset = i as f64 * 0.001;
+            insert_point(&mut tree, &format!("a{}", i), 116.4 + offset, 39.9);
+            insert_point(&mut tree, &format!("b{}", i), 121.4, 31.2 + offset);
+        }
+        // 孤立点
+        insert_point(&mut tree, "noise", 100.0, 10.0);
+
+        let clusters = tree.cluster(200.0, 3, None);
+        assert_eq!(clusters.len(), 2);
+
+        let a = clusters.iter().find(|c| c.members[0] == "a0").unwrap();
+        assert_eq!(a.members, vec!["a0", "a1", "a2", "a3", "a4"]);
+        assert!((a.centroid[0] - 116.402).abs() < 1e-9);
+        assert!((a.centroid[1] - 39.9).abs() < 1e-9);
+
+        let b = clusters.iter().find(|c| c.members[0] == "b0").unwrap();
+        assert_eq!(b.members.len(), 5);
+        assert!(clusters
+            .iter()
+            .all(|c| !c.members.contains(&"noise".to_string())));
+    }
+
+    #[test]
+    fn test_cluster_border_points_and_min_points() {
+        let mut tree = RTree::new(4);
+        // 三个紧密的核心点，外加一个只在其中一个核心点邻域内的边界点
+        insert_point(&mut tree, "c1", 0.0, 0.0);
+        insert_point(&mut tree, "c2", 0.0005, 0.0);
+        insert_point(&mut tree, "c3", 0.001, 0.0);
+        insert_point(&mut tree, "edge", 0.0025, 0.0);
+
+        // eps 约 180 米：edge 只和 c3 相邻
+        let clusters = tree.cluster(180.0, 3, None);
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].members, vec!["c1", "c2", "c3", "edge"]);
+
+        // min_points 过大时全部为噪声
+        assert!(tree.cluster(180.0, 10, None).is_empty());
+    }
+
+    #[test]
+    fn test_cluster_within_bounds() {
+        let mut tree = RTree::new(4);
+        for i in 0..4 {
+            insert_point(&mut tree, &format!("in{}", i), 1.0 + i as f64 * 0.0005, 1.0);
+            insert_point(
+                &mut tree,
+                &format!("out{}", i),
+                5.0 + i as f64 * 0.0005,
+                5.0,
+            );
+        }
+
+        let bounds = Rectangle::new(0.0, 0.0, 2.0, 2.0);
+        let clusters = tree.cluster(100.0, 2, Some(&bounds));
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].members, vec!["in0", "in1", "in2", "in3"]);
+    }
+}
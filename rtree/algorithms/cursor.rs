@@ -0,0 +1,391 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::LazyLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use geo::Geometry;
+
+use super::super::rtree::{GeoItem, RTree};
+use super::search::matches_geometry;
+use super::utils::geometry_to_bbox;
+
+/// 全局版本时钟：每次修改对象时取下一个值作为该对象的版本号
+///
+/// 以启动时的纳秒时间戳为起点，重启后的版本号仍大于重启前发出的游标中的版本号
+static VERSION_CLOCK: LazyLock<AtomicU64> = LazyLock::new(|| {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64);
+    AtomicU64::new(now)
+});
+
+/// 取一个新的版本号（严格递增）
+pub fn next_version() -> u64 {
+    VERSION_CLOCK.fetch_add(1, Ordering::SeqCst) + 1
+}
+
+/// 当前版本号：之后发生的修改的版本号都大于它
+pub fn current_version() -> u64 {
+    VERSION_CLOCK.load(Ordering::SeqCst)
+}
+
+/// 分页游标：上一页最后一个对象的 ID 加上发出游标时的版本号
+///
+/// 编码为 `版本号:ID`，起始游标为 `0`。分页按 ID 升序进行，游标只记录位置而不是下标，
+/// 两页之间的插入、删除（包括删除游标指向的对象本身）都不会使后续页错位。
+///
+/// 语义为至少一次（at-least-once）：
+/// - 整个分页过程中未被修改的对象恰好返回一次
+/// - 在游标位置之后被修改的对象在到达时返回最新值
+/// - 在游标位置之前（已返回）被修改或新插入的对象会在下一页中重新返回
+/// - 分页过程中被删除的对象不再返回
+/// - collection 被重建或从磁盘重新加载（冷存储、重启）后，已返回的对象可能全部重新返回
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanCursor {
+    /// 发出游标时的版本号，版本号更大的对象在游标之后被修改过
+    pub version: u64,
+    /// 上一页最后一个对象的 ID，None 表示从头开始
+    pub last_id: Option<String>,
+}
+
+impl ScanCursor {
+    /// 解析客户端传回的游标，格式错误时返回 None
+    pub fn parse(token: &str) -> Option<Self> {
+        if token == "0" {
+            return Some(Self::default());
+        }
+        let (version, last_id) = token.split_once(':')?;
+        Some(Self {
+            version: version.parse().ok()?,
+            last_id: Some(last_id.to_string()),
+        })
+    }
+}
+
+impl fmt::Display for ScanCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.last_id {
+            Some(last_id) => write!(f, "{}:{}", self.version, last_id),
+            None => write!(f, "0"),
+        }
+    }
+}
+
+/// 一页结果
+#[derive(Debug, Clone)]
+pub struct Page<T = GeoItem> {
+    pub items: Vec<T>,
+    /// 下一页的游标，None 表示已经返回到最后
+    pub next: Option<ScanCursor>,
+}
+
+impl<T> Default for Page<T> {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            next: None,
+        }
+    }
+}
+
+/// 游标分页
+impl RTree {
+    /// 记录对象被修改，插入、替换和修改标签时调用
+    pub(crate) fn touch(&mut self, data_id: &str) {
+        self.versions.insert(data_id.to_string(), next_version());
+    }
+
+    /// 对象最近一次修改的版本号；创建或加载树之后没有修改过的对象取树的基准版本号
+    fn item_version(&self, data_id: &str) -> u64 {
+        self.versions
+            .get(data_id)
+            .copied()
+            .unwrap_or(self.base_version)
+    }
+
+    /// 按 ID 分页遍历所有对象
+    ///
+    /// `count` 为每页返回的新对象数量（0 表示不限制），重新返回的已修改对象不计入
+    pub fn scan_page(&self, cursor: &ScanCursor, count: usize) -> Page {
+        self.page(
+            self.geometry_map.keys(),
+            cursor,
+            count,
+            |_| true,
+            |id| self.get(id),
+        )
+    }
+
+    /// 按 ID 分页遍历所有对象的 ID，包括 STRING 对象，语义与 [`scan_page`](Self::scan_page) 相同
+    pub fn scan_ids(&self, cursor: &ScanCursor, count: usize) -> Page<String> {
+        let candidates = self.geometry_map.keys().chain(self.strings.keys());
+        self.page(
+            candidates,
+            cursor,
+            count,
+            |_| true,
+            |id| Some(id.to_string()),
+        )
+    }
+
+    /// 按 ID 分页的空间查询，`tags` 不为空时只返回同时带有所有标签的对象
+    pub fn search_page(
+        &self,
+        geometry: &Geometry,
+        within: bool,
+        tags: &[String],
+        cursor: &ScanCursor,
+        count: usize,
+    ) -> Page {
+        self.search_page_with(geometry, within, tags, cursor, count, |id| self.get(id))
+    }
+
+    /// 与 [`search_page`](Self::search_page) 相同，但页中只有对象 ID
+    pub fn search_page_ids(
+        &self,
+        geometry: &Geometry,
+        within: bool,
+        tags: &[String],
+        cursor: &ScanCursor,
+        count: usize,
+    ) -> Page<String> {
+        self.search_page_with(geometry, within, tags, cursor, count, |id| {
+            Some(id.to_string())
+        })
+    }
+
+    fn search_page_with<T>(
+        &self,
+        geometry: &Geometry,
+        within: bool,
+        tags: &[String],
+        cursor: &ScanCursor,
+        count: usize,
+        load: impl Fn(&str) -> Option<T>,
+    ) -> Page<T> {
+        let matches = |id: &str| {
+            self.geometry_map
+                .get(id)
+                .is_some_and(|entry_geometry| matches_geometry(entry_geometry, geometry, within))
+        };
+        if !tags.is_empty() {
+            return self.page(
+                self.ids_with_tags(tags).iter(),
+                cursor,
+                count,
+                matches,
+                load,
+            );
+        }
+        let Ok(bbox) = geometry_to_bbox(geometry) else {
+            return Page::default();
+        };
+        self.page(self.search_bbox(&bbox).iter(), cursor, count, matches, load)
+    }
+
+    /// 从候选 ID 中取出一页：先是游标位置之前被修改过的对象，再是游标之后的前 `count` 个匹配
+    ///
+    /// `load` 取出对象在页中的表示，返回 None 的对象跳过
+    fn page<'a, T, I, F, L>(
+        &self,
+        candidates: I,
+        cursor: &ScanCursor,
+        count: usize,
+        matches: F,
+        load: L,
+    ) -> Page<T>
+    where
+        I: Iterator<Item = &'a String>,
+        F: Fn(&str) -> bool,
+        L: Fn(&str) -> Option<T>,
+    {
+        let mut redelivered = Vec::new();
+        let mut remaining = Vec::new();
+        for id in candidates {
+            match &cursor.last_id {
+                Some(last_id) if id <= last_id => {
+                    if self.item_version(id) > cursor.version {
+                        redelivered.push(id.as_str());
+                    }
+                }
+                _ => remaining.push(id.as_str()),
+            }
+        }
+        redelivered.sort_unstable();
+        remaining.sort_unstable();
+
+        let mut items: Vec<T> = redelivered
+            .into_iter()
+            .filter(|id| matches(id))
+            .filter_map(&load)
+            .collect();
+
+        let limit = if count == 0 { usize::MAX } else { count };
+        let mut taken = 0;
+        let mut last_id = None;
+        let mut more = false;
+        for id in remaining {
+            if !matches(id) {
+                continue;
+            }
+            if taken == limit {
+                more = true;
+                break;
+            }
+            if let Some(item) = load(id) {
+                items.push(item);
+            }
+            taken += 1;
+            last_id = Some(id);
+        }
+
+        // 持有读锁期间没有修改，之后的修改版本号都大于当前版本号
+        let next = more.then(|| ScanCursor {
+            version: current_version(),
+            last_id: last_id.map(str::to_string),
+        });
+        Page { items, next }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{Coord, Polygon};
+
+    fn point(lon: f64, lat: f64) -> String {
+        format!(r#"{{"type":"Point","coordinates":[{},{}]}}"#, lon, lat)
+    }
+
+    fn ids(page: &Page) -> Vec<&str> {
+        page.items.iter().map(|item| item.id.as_str()).collect()
+    }
+
+    fn fleet(n: usize) -> RTree {
+        let mut rtree = RTree::new(4);
+        for i in 0..n {
+            rtree.insert_geojson(format!("v{:02}", i), &point(i as f64, 0.0));
+        }
+        rtree
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        assert_eq!(ScanCursor::parse("0"), Some(ScanCursor::default()));
+        assert_eq!(ScanCursor::default().to_string(), "0");
+
+        // ID 中可以包含冒号
+        let cursor = ScanCursor {
+            version: 42,
+            last_id: Some("gps:truck1".to_string()),
+        };
+        assert_eq!(cursor.to_string(), "42:gps:truck1");
+        assert_eq!(ScanCursor::parse("42:gps:truck1"), Some(cursor));
+
+        assert_eq!(ScanCursor::parse("abc"), None);
+        assert_eq!(ScanCursor::parse("x:truck1"), None);
+    }
+
+    #[test]
+    fn test_scan_pages_cover_all_items_once() {
+        let rtree = fleet(10);
+        let mut cursor = ScanCursor::default();
+        let mut seen = Vec::new();
+        loop {
+            let page = rtree.scan_page(&cursor, 3);
+            assert!(page.items.len() <= 3);
+            seen.extend(page.items.into_iter().map(|item| item.id));
+            match page.next {
+                Some(next) => cursor = next,
+                None => break,
+            }
+        }
+        let expected: Vec<String> = (0..10).map(|i| format!("v{:02}", i)).collect();
+        assert_eq!(seen, expected);
+
+        // 恰好取完时不再返回游标
+        assert!(rtree.scan_page(&ScanCursor::default(), 10).next.is_none());
+    }
+
+    #[test]
+    fn test_scan_tolerates_writes_between_pages() {
+        let mut rtree = fleet(6);
+        let page = rtree.scan_page(&ScanCursor::default(), 3);
+        assert_eq!(ids(&page), vec!["v00", "v01", "v02"]);
+        let cursor = page.next.unwrap();
+
+        // 删除游标指向的对象，修改已返回和未返回的对象，并在两侧插入新对象
+        rtree.delete("v02");
+        rtree.insert_geojson("v01".to_string(), &point(1.5, 0.0));
+        rtree.insert_geojson("v04".to_string(), &point(4.5, 0.0));
+        rtree.insert_geojson("v00a".to_string(), &point(0.5, 0.0));
+        rtree.insert_geojson("v99".to_string(), &point(9.0, 0.0));
+        rtree.delete("v05");
+
+        let page = rtree.scan_page(&cursor, 10);
+        // 已返回的对象中只有修改过的和新插入的重新返回，未修改的 v00 不重复
+        assert_eq!(ids(&page), vec!["v00a", "v01", "v03", "v04", "v99"]);
+        assert!(page.items[1].geojson.contains("1.5"));
+        assert!(page.items[3].geojson.contains("4.5"));
+        assert!(page.next.is_none());
+    }
+
+    #[test]
+    fn test_search_page_with_stale_cursor() {
+        let mut rtree = fleet(8);
+        let query = Geometry::Polygon(Polygon::new(
+            vec![
+                Coord { x: -0.5, y: -1.0 },
+                Coord { x: 5.5, y: -1.0 },
+                Coord { x: 5.5, y: 1.0 },
+                Coord { x: -0.5, y: 1.0 },
+                Coord { x: -0.5, y: -1.0 },
+            ]
+            .into(),
+            vec![],
+        ));
+
+        let page = rtree.search_page(&query, false, &[], &ScanCursor::default(), 2);
+        assert_eq!(ids(&page), vec!["v00", "v01"]);
+        let cursor = page.next.unwrap();
+
+        // v00 移出查询范围后不再返回，v01 未修改也不重复
+        rtree.insert_geojson("v00".to_string(), &point(50.0, 50.0));
+        let page = rtree.search_page(&query, false, &[], &cursor, 2);
+        assert_eq!(ids(&page), vec!["v02", "v03"]);
+        let page = rtree.search_page(&query, false, &[], &page.next.unwrap(), 2);
+        assert_eq!(ids(&page), vec!["v04", "v05"]);
+        assert!(page.next.is_none());
+
+        // 重新加载后无法区分哪些对象被修改过，已返回的对象全部重新返回
+        let reloaded: RTree =
+            serde_json::from_str(&serde_json::to_string(&rtree).unwrap()).unwrap();
+        let page = reloaded.search_page(&query, false, &[], &cursor, 2);
+        assert_eq!(ids(&page), vec!["v01", "v02", "v03"]);
+    }
+
+    #[test]
+    fn test_search_page_with_tags() {
+        let mut rtree = fleet(6);
+        for id in ["v01", "v03", "v05"] {
+            rtree.set_tags(id, ["odd".to_string()]);
+        }
+        let query = Geometry::Polygon(Polygon::new(
+            vec![
+                Coord { x: -1.0, y: -1.0 },
+                Coord { x: 10.0, y: -1.0 },
+                Coord { x: 10.0, y: 1.0 },
+                Coord { x: -1.0, y: 1.0 },
+                Coord { x: -1.0, y: -1.0 },
+            ]
+            .into(),
+            vec![],
+        ));
+        let tags = vec!["odd".to_string()];
+        let page = rtree.search_page(&query, false, &tags, &ScanCursor::default(), 2);
+        assert_eq!(ids(&page), vec!["v01", "v03"]);
+        let page = rtree.search_page(&query, false, &tags, &page.next.unwrap(), 2);
+        assert_eq!(ids(&page), vec!["v05"]);
+        assert!(page.next.is_none());
+    }
+}
@@ -1,370 +1,130 @@
-use super::super::node::{Entry, Node};
 use super::super::rectangle::Rectangle;
 use super::super::rtree::RTree;
-use super::utils::geometry_to_bbox;
+use super::utils::index_bboxes;
 
 /// R-tree删除算法实现
 impl RTree {
     /// 删除指定的数据条目 - 遵循论文Algorithm Delete
     /// 返回 true 表示操作成功（幂等：不存在也视为成功）
     pub fn delete(&mut self, data: &str) -> bool {
+        // STRING 对象不在空间索引中
+        if self.strings.remove(data).is_some() {
+            self.remove_tags(data);
+            self.expires.remove(data);
+            self.versions.remove(data);
+            return true;
+        }
+
         // 直接在 if let 中获取几何体，如果不存在就直接返回
         let Some(geometry) = self.geometry_map.get(data) else {
             return true; // 幂等：不存在视为已删除
         };
 
-        let Ok(rect) = geometry_to_bbox(geometry) else {
+        let Ok(rects) = index_bboxes(geometry) else {
             eprintln!("Error calculating bounding box for data={}", data);
             return false;
         };
 
-        if self.delete_in_rtree(&rect, data) {
+        // 逐个删除该对象的所有索引条目（GeometryCollection 每个部分一个），
+        // 暂停维护索引时树中没有条目
+        let mut deleted = true;
+        if !self.index_deferred {
+            for rect in &rects {
+                deleted &= self.delete_in_rtree(rect, data);
+            }
+        }
+        if deleted {
             self.geometry_map.remove(data);
+            if let Some(ids) = self.ordered_ids.get_mut() {
+                ids.remove(data);
+            }
             self.geojson_map.remove(data);
+            self.remove_tags(data);
+            self.expires.remove(data);
+            self.versions.remove(data);
             true
         } else {
             false
         }
     }
 
-    /// 删除指定的数据条目 - 使用简化的下溢处理策略
+    /// 删除指定的数据条目 - 使用简化的下溢处理策略，见 [`RTreeIndex::delete`](spatio_rtree::RTreeIndex::delete)
     pub fn delete_in_rtree(&mut self, rect: &Rectangle, data: &str) -> bool {
-        // D1: 找到包含目标条目的叶子节点
-        if let Some(leaf_path) = self.find_leaf_path(rect, data) {
-            // D2: 从叶子节点删除条目
-            let (deleted, leaf_entries_count) = {
-                let leaf_node = match self.get_last_node_mut(&leaf_path) {
-                    Some(node) => node,
-                    None => {
-                        println!("Warning: Failed to get leaf node during deletion");
-                        return false;
-                    }
-                };
-                let initial_count = leaf_node.entries.len();
-
-                // 删除匹配的条目
-                leaf_node.entries.retain(|entry| {
-                    if let Entry::Data {
-                        mbr,
-                        data: entry_data,
-                    } = entry
-                    {
-                        !(mbr == rect && entry_data == data)
-                    } else {
-                        true
-                    }
-                });
-
-                // 检查是否真的删除了条目
-                if leaf_node.entries.len() == initial_count {
-                    return false; // 没有找到要删除的条目
-                }
-
-                // 更新叶子节点的MBR
-                leaf_node.update_mbr();
-
-                (true, leaf_node.entries.len())
-            };
-
-            if deleted {
-                // D3: 检查叶子节点是否下溢
-                let min_entries = self.min_entries_internal();
-
-                if leaf_entries_count < min_entries && !leaf_path.is_empty() {
-                    // 叶子节点下溢且不是根节点 - 使用简化的处理方案
-                    self.handle_leaf_underflow(leaf_path.clone());
-                } else {
-                    // 只需要向上调整MBR
-                    self.adjust_tree_upward(leaf_path);
-                }
-
-                // D4: 如果根节点只有一个条目且为索引节点，则缩短树
-                self.shorten_tree();
-
-                true
-            } else {
-                false
-            }
-        } else {
-            false // 没有找到要删除的条目
-        }
-    }
-
-    /// 查找包含指定数据条目的叶子节点路径
-    ///
-    /// 返回从根节点到包含目标条目的叶子节点的路径
-    pub(crate) fn find_leaf_path(&self, rect: &Rectangle, data: &str) -> Option<Vec<usize>> {
-        if let Some(root) = self.root_ref() {
-            let mut path = Vec::new();
-            if self.find_leaf_recursive(root, rect, data, &mut path) {
-                Some(path)
-            } else {
-                None
-            }
-        } else {
-            None
-        }
-    }
-
-    /// 递归查找包含指定数据条目的叶子节点
-    fn find_leaf_recursive(
-        &self,
-        node: &Node,
-        rect: &Rectangle,
-        data: &str,
-        path: &mut Vec<usize>,
-    ) -> bool {
-        if node.is_leaf_node() {
-            // 在叶子节点中查找目标条目
-            for entry in node.entries.iter() {
-                if let Entry::Data {
-                    mbr,
-                    data: entry_data,
-                } = entry
-                {
-                    if mbr == rect && *entry_data == data {
-                        return true; // 找到了目标条目
-                    }
-                }
-            }
-            false
-        } else {
-            // 在索引节点中递归搜索
-            for (i, entry) in node.entries.iter().enumerate() {
-                if let Entry::Node {
-                    mbr,
-                    node: child_node,
-                } = entry
-                {
-                    // 只在MBR包含目标矩形的子树中搜索
-                    if mbr.contains(rect) {
-                        path.push(i);
-                        if self.find_leaf_recursive(child_node, rect, data, path) {
-                            return true;
-                        }
-                        path.pop();
-                    }
-                }
-            }
-            false
-        }
+        self.index.delete(rect, data)
     }
+}
 
-    /// 处理叶子节点下溢 - 简化方案
-    ///
-    /// 1. 收集下溢叶子节点中的所有数据条目
-    /// 2. 将这些条目重新插入到树中
-    /// 3. 从父节点中移除下溢的叶子节点
-    /// 4. 向上调整MBR
-    pub(crate) fn handle_leaf_underflow(&mut self, leaf_path: Vec<usize>) {
-        // 1. 收集下溢叶子节点中的所有数据条目
-        let entries_to_reinsert = {
-            let leaf_node = match self.get_last_node_mut(&leaf_path) {
-                Some(node) => node,
-                None => {
-                    println!("Warning: Failed to get leaf node in handle_leaf_underflow");
-                    return;
-                }
-            };
-            let mut entries = Vec::new();
-            for entry in &leaf_node.entries {
-                if let Entry::Data { mbr, data } = entry {
-                    entries.push((*mbr, data.clone()));
-                }
-            }
-            entries
-        };
+#[cfg(test)]
+#[allow(clippy::unnecessary_to_owned)]
+mod tests {
+    use crate::storage::geometry_utils::geometry_to_geojson;
 
-        // 2. 从父节点中移除下溢的叶子节点
-        let parent_path = &leaf_path[..leaf_path.len() - 1];
-        let leaf_index = leaf_path[leaf_path.len() - 1];
+    use super::*;
+    use crate::rtree::node::{Entry, Node};
+    use geo::{Coord, Point, Polygon};
 
-        if parent_path.is_empty() {
-            // 父节点是根节点
-            let root = self.root_mut().as_mut().unwrap();
-            if leaf_index < root.entries.len() {
-                root.entries.remove(leaf_index);
-                root.update_mbr();
-            }
-        } else {
-            // 父节点是中间节点
-            let parent = match self.get_last_node_mut(parent_path) {
-                Some(node) => node,
-                None => {
-                    println!("Warning: Failed to get parent node in handle_leaf_underflow");
-                    // 仍然尝试重新插入条目
-                    for (rect, data) in entries_to_reinsert {
-                        self.insert(rect, data);
-                    }
-                    return;
+    // 新的 delete 函数测试（直接通过 data ID 删除）
+    #[test]
+    fn test_delete_keeps_tree_balanced() {
+        // 返回子树高度，同时检查父节点中的 MBR 与子节点一致、所有叶子节点在同一层
+        fn check(node: &Node) -> usize {
+            let mut heights = node.entries.iter().filter_map(|entry| match entry {
+                Entry::Node { mbr, node } => {
+                    assert_eq!(*mbr, node.mbr);
+                    Some(check(node))
                 }
-            };
-            if leaf_index < parent.entries.len() {
-                parent.entries.remove(leaf_index);
-                parent.update_mbr();
-            }
+                Entry::Data { .. } => None,
+            });
+            let height = heights.next().unwrap_or(0);
+            assert!(heights.all(|h| h == height));
+            height + 1
         }
 
-        // 2.5 如果父节点变空了，递归删除空的非叶子节点
-        if !parent_path.is_empty() {
-            let parent = match self.get_last_node_mut(parent_path) {
-                Some(node) => node,
-                None => {
-                    println!("Warning: Failed to get parent node for empty check");
-                    // 仍然尝试重新插入条目
-                    for (rect, data) in entries_to_reinsert {
-                        self.insert(rect, data);
-                    }
-                    return;
-                }
-            };
-            if parent.entries.is_empty() && parent.is_index_node() {
-                // 父节点也变空了，递归处理父节点
-                self.remove_empty_nodes(parent_path.to_vec());
-            }
+        let mut rtree = RTree::new(4);
+        for i in 0..200 {
+            let x = (i % 20) as f64;
+            let y = (i / 20) as f64;
+            rtree.insert(Rectangle::new(x, y, x + 0.5, y + 0.5), i.to_string());
         }
+        check(rtree.root_ref().as_ref().unwrap());
 
-        // 3. 向上调整MBR（仅调整MBR，不做其他下溢检查）
-        self.adjust_tree_upward(parent_path.to_vec());
-
-        // 4. 重新插入收集到的数据条目
-        for (mbr, data) in entries_to_reinsert {
-            self.insert(mbr, data);
+        for i in (0..200).filter(|i| i % 3 != 0) {
+            let x = (i % 20) as f64;
+            let y = (i / 20) as f64;
+            assert!(rtree.delete_in_rtree(&Rectangle::new(x, y, x + 0.5, y + 0.5), &i.to_string()));
+            check(rtree.root_ref().as_ref().unwrap());
         }
+        assert_eq!(rtree.len(), 67);
+        assert!(!rtree.delete_in_rtree(&Rectangle::new(1.0, 0.0, 1.5, 0.5), "1"));
     }
 
-    /// 删除空的非叶子节点 - 从指定路径的节点开始，递归删除空的父节点
-    ///
-    /// 这个函数检查path指定的节点，如果它是空的非叶子节点，则删除它。
-    /// 删除后，检查其父节点是否也变成空的，如果是则继续向上删除。
-    ///
-    /// # 参数
-    /// - `node_path`: 从根节点到目标节点的路径索引
-    ///
-    /// # 说明
-    /// - 只删除空的非叶子节点（索引节点）
-    /// - 叶子节点即使为空也不会被删除
-    /// - 只有当删除节点后其父节点变空时，才继续向上处理
-    /// - 如果根节点变空，会清空整个树
-    /// - 删除节点后会向上调整MBR
-    pub(crate) fn remove_empty_nodes(&mut self, node_path: Vec<usize>) {
-        if node_path.is_empty() {
-            return;
-        }
-
-        // 检查指定路径的节点是否为空的非叶子节点
-        let should_remove = {
-            let node = match self.get_last_node_mut(&node_path) {
-                Some(node) => node,
-                None => {
-                    println!("Warning: Failed to get node in remove_empty_nodes");
-                    return;
-                }
-            };
-            node.is_index_node() && node.entries.is_empty()
-        };
-
-        if !should_remove {
-            // 当前节点不是空的非叶子节点，不需要删除
-            return;
-        }
-
-        // 构造父节点路径
-        let mut parent_path = node_path.clone();
-        let node_index = parent_path.pop().unwrap();
-
-        if parent_path.is_empty() {
-            // 要删除的是根节点的直接子节点
-            let root = self.root_mut().as_mut().unwrap();
-
-            if node_index < root.entries.len() {
-                root.entries.remove(node_index);
-
-                // 检查根节点是否变空
-                if root.entries.is_empty() {
-                    // 清空整个树
-                    *self.root_mut() = None;
-                } else {
-                    // 更新根节点的MBR
-                    root.update_mbr();
-
-                    // 根节点不为空，停止递归
-                }
-            }
-        } else {
-            // 要删除的是中间节点
-            let parent = match self.get_last_node_mut(&parent_path) {
-                Some(node) => node,
-                None => {
-                    println!("Warning: Failed to get parent node in remove_empty_nodes");
-                    return;
-                }
-            };
-
-            if node_index < parent.entries.len() {
-                parent.entries.remove(node_index);
-
-                // 更新父节点的MBR
-                parent.update_mbr();
-
-                // 检查父节点是否也变空了
-                if parent.entries.is_empty() && parent.is_index_node() {
-                    // 父节点也变空了，递归处理父节点
-                    self.remove_empty_nodes(parent_path);
-                } else {
-                    // 父节点不为空，向上调整MBR
-                    self.adjust_tree_upward(parent_path);
-                }
-            }
+    #[test]
+    fn test_many_nearly_identical_rectangles() {
+        // 大量几乎相同的矩形会让每个节点的 MBR 都互相重叠，搜索和删除要遍历几乎整棵树，
+        // 搜索使用显式栈，插入和删除的递归深度只等于树高
+        let count = 1_000_000;
+        let mut rtree = RTree::new(4);
+        for i in 0..count {
+            let offset = i as f64 * 1e-9;
+            rtree.insert(
+                Rectangle::new(offset, offset, 1.0 + offset, 1.0 + offset),
+                i.to_string(),
+            );
         }
-    }
+        assert_eq!(rtree.len(), count);
+        assert!(rtree.depth() < 32);
 
-    /// 缩短树 - 如果根节点只有一个条目且为索引节点，则将其子节点作为新的根节点
-    pub(crate) fn shorten_tree(&mut self) {
-        loop {
-            let should_shorten = {
-                if let Some(root) = self.root_ref() {
-                    root.is_index_node() && root.entries.len() == 1
-                } else {
-                    false
-                }
-            };
-
-            if should_shorten {
-                // 将唯一的子节点提升为新的根节点
-                let old_root = self.root_mut().take().unwrap();
-                let mut entries = old_root.entries;
-                if let Some(Entry::Node { node, .. }) = entries.pop() {
-                    *self.root_mut() = Some(node);
-                } else {
-                    // 恢复根节点，防止出错
-                    let restored_root = Node::new(old_root.node_type, old_root.level);
-                    *self.root_mut() = Some(Box::new(restored_root));
-                    break;
-                }
-            } else {
-                break;
-            }
-        }
+        let all = rtree.search_bbox(&Rectangle::new(0.5, 0.5, 0.5, 0.5));
+        assert_eq!(all.len(), count);
 
-        // 如果根节点为空（所有条目都被删除），则清空树
-        if let Some(root) = self.root_ref() {
-            if root.entries.is_empty() {
-                *self.root_mut() = None;
-            }
+        for i in (0..count).step_by(100_000) {
+            let offset = i as f64 * 1e-9;
+            let rect = Rectangle::new(offset, offset, 1.0 + offset, 1.0 + offset);
+            assert!(rtree.delete_in_rtree(&rect, &i.to_string()));
         }
+        assert_eq!(rtree.len(), count - 10);
     }
-}
-
-#[cfg(test)]
-#[allow(clippy::unnecessary_to_owned)]
-mod tests {
-    use crate::storage::geometry_utils::geometry_to_geojson;
-
-    use super::*;
-    use geo::{Coord, Point, Polygon};
 
-    // 新的 delete 函数测试（直接通过 data ID 删除）
     #[test]
     fn test_delete_by_id_basic() {
         let mut rtree = RTree::new(4);
@@ -0,0 +1,93 @@
+use super::super::rtree::RTree;
+
+/// 对象过期时间（TTL）
+///
+/// 过期时刻使用 Unix 纪元以来的纳秒，与 AOF 时间戳相同，重启或卸载后重新加载仍然有效。
+/// 树本身不会删除过期对象：由数据库在读取时（惰性过期）和后台定期清理时（主动过期）删除，
+/// 删除同时写入 AOF
+impl RTree {
+    /// 设置对象的过期时刻，对象不存在时返回 false
+    pub fn set_expire(&mut self, data_id: &str, at: u64) -> bool {
+        if !self.contains(data_id) {
+            return false;
+        }
+        self.expires.insert(data_id.to_string(), at);
+        true
+    }
+
+    /// 清除对象的过期时刻，对象原本有 TTL 时返回 true
+    pub fn persist(&mut self, data_id: &str) -> bool {
+        self.expires.remove(data_id).is_some()
+    }
+
+    /// 对象的过期时刻，没有 TTL 时返回 None
+    pub fn expires_at(&self, data_id: &str) -> Option<u64> {
+        self.expires.get(data_id).copied()
+    }
+
+    /// 对象在 `now` 时是否已经过期
+    pub fn is_expired(&self, data_id: &str, now: u64) -> bool {
+        self.expires.get(data_id).is_some_and(|&at| at <= now)
+    }
+
+    /// 带 TTL 的对象数量
+    pub fn expiring_count(&self) -> usize {
+        self.expires.len()
+    }
+
+    /// 在 `now` 时已经过期的对象 ID（按 ID 升序），最多返回 `limit` 个，0 表示不限制
+    pub fn expired_ids(&self, now: u64, limit: usize) -> Vec<String> {
+        let mut ids: Vec<String> = self
+            .expires
+            .iter()
+            .filter(|(_, &at)| at <= now)
+            .map(|(id, _)| id.clone())
+            .collect();
+        ids.sort_unstable();
+        if limit > 0 {
+            ids.truncate(limit);
+        }
+        ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POINT: &str = r#"{"type":"Point","coordinates":[1.0,2.0]}"#;
+
+    #[test]
+    fn test_expire_lifecycle() {
+        let mut tree = RTree::new(4);
+        assert!(!tree.set_expire("missing", 100));
+
+        for id in ["a", "b", "c"] {
+            tree.insert_geojson(id.to_string(), POINT);
+        }
+        assert!(tree.set_expire("a", 100));
+        assert!(tree.set_expire("b", 200));
+        assert_eq!(tree.expires_at("a"), Some(100));
+        assert_eq!(tree.expires_at("c"), None);
+        assert_eq!(tree.expiring_count(), 2);
+
+        assert!(!tree.is_expired("a", 99));
+        assert!(tree.is_expired("a", 100));
+        assert!(!tree.is_expired("c", u64::MAX));
+        assert_eq!(tree.expired_ids(150, 0), vec!["a"]);
+        assert_eq!(tree.expired_ids(200, 0), vec!["a", "b"]);
+        assert_eq!(tree.expired_ids(200, 1), vec!["a"]);
+
+        assert!(tree.persist("b"));
+        assert!(!tree.persist("b"));
+        assert_eq!(tree.expired_ids(200, 0), vec!["a"]);
+
+        // 删除或覆盖对象都会清除 TTL
+        tree.delete("a");
+        assert_eq!(tree.expires_at("a"), None);
+        tree.set_expire("c", 100);
+        tree.insert_geojson("c".to_string(), POINT);
+        assert_eq!(tree.expires_at("c"), None);
+        assert_eq!(tree.expiring_count(), 0);
+    }
+}
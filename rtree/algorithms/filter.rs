@@ -0,0 +1,242 @@
+use super::super::rtree::{GeoItem, RTree};
+use super::knn::DistanceMetric;
+use crate::storage::geometry_utils::geojson_properties;
+use geo::Geometry;
+
+/// 几何类型（NEARBY TYPE），Multi* 与对应的单个几何归为同一类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeometryKind {
+    Point,
+    LineString,
+    Polygon,
+}
+
+impl GeometryKind {
+    /// 按名称解析，不区分大小写：point、linestring（或 line）、polygon
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "point" => Some(Self::Point),
+            "linestring" | "line" => Some(Self::LineString),
+            "polygon" => Some(Self::Polygon),
+            _ => None,
+        }
+    }
+
+    /// GeometryCollection 不属于任何类型
+    pub fn matches(&self, geometry: &Geometry) -> bool {
+        match self {
+            Self::Point => matches!(geometry, Geometry::Point(_) | Geometry::MultiPoint(_)),
+            Self::LineString => matches!(
+                geometry,
+                Geometry::Line(_) | Geometry::LineString(_) | Geometry::MultiLineString(_)
+            ),
+            Self::Polygon => matches!(
+                geometry,
+                Geometry::Polygon(_)
+                    | Geometry::MultiPolygon(_)
+                    | Geometry::Rect(_)
+                    | Geometry::Triangle(_)
+            ),
+        }
+    }
+}
+
+/// WHERE field min max：Feature 的数值属性落在闭区间 [min, max] 内
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldRange {
+    pub field: String,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// KNN 查询的对象过滤条件
+///
+/// `kinds` 不为空时几何类型须是其中之一；`fields` 中的每个区间都要满足，
+/// 裸几何体或缺少该字段（或不是数字）的对象不匹配
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjectFilter {
+    pub kinds: Vec<GeometryKind>,
+    pub fields: Vec<FieldRange>,
+}
+
+impl ObjectFilter {
+    /// 没有任何条件
+    pub fn is_empty(&self) -> bool {
+        self.kinds.is_empty() && self.fields.is_empty()
+    }
+
+    /// 先判断几何类型，只有存在 WHERE 条件时才解析 properties
+    pub fn matches(&self, geometry: &Geometry, geojson: Option<&str>) -> bool {
+        if !self.kinds.is_empty() && !self.kinds.iter().any(|kind| kind.matches(geometry)) {
+            return false;
+        }
+        if self.fields.is_empty() {
+            return true;
+        }
+        let Some(properties) = geojson.and_then(geojson_properties) else {
+            return false;
+        };
+        self.fields.iter().all(|range| {
+            properties
+                .get(&range.field)
+                .and_then(|value| value.as_f64())
+                .is_some_and(|value| range.min <= value && value <= range.max)
+        })
+    }
+}
+
+impl RTree {
+    /// 带过滤条件的 KNN 查询：返回满足 `filter` 的最近 k 个对象
+    ///
+    /// 过滤在遍历时进行，被拒绝的对象不占用结果名额，会继续展开堆直到找到 k 个匹配对象，
+    /// 参见 [`knn_search_filtered`](super::knn::knn_search_filtered)。
+    /// `region` 不为 None 时只在完全包含于其中的对象中查找；距离和 `max_radius` 按 `metric` 计算
+    #[allow(clippy::too_many_arguments)]
+    pub fn nearby_filtered(
+        &self,
+        query_lon: f64,
+        query_lat: f64,
+        k: usize,
+        max_radius: Option<f64>,
+        epsilon: f64,
+        region: Option<&Geometry>,
+        filter: &ObjectFilter,
+        metric: DistanceMetric,
+    ) -> Vec<(GeoItem, f64)> {
+        use super::knn::knn_search_filtered;
+
+        let accept = |id: &str, geometry: &Geometry| {
+            filter.matches(geometry, self.geojson_map.get(id).map(String::as_str))
+        };
+        knn_search_filtered(
+            self.get_root(),
+            query_lon,
+            query_lat,
+            k,
+            &self.geometry_map,
+            &self.geojson_map,
+            max_radius,
+            epsilon,
+            region,
+            &accept,
+            metric,
+        )
+        .into_iter()
+        .map(|result| (result.item, result.distance))
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn feature(geometry: serde_json::Value, speed: Option<f64>) -> String {
+        let properties = match speed {
+            Some(speed) => json!({"speed": speed}),
+            None => json!({}),
+        };
+        json!({"type": "Feature", "properties": properties, "geometry": geometry}).to_string()
+    }
+
+    fn point(lon: f64, lat: f64) -> serde_json::Value {
+        json!({"type": "Point", "coordinates": [lon, lat]})
+    }
+
+    #[test]
+    fn test_object_filter() {
+        let kinds = |names: &[&str]| -> Vec<GeometryKind> {
+            names
+                .iter()
+                .map(|name| GeometryKind::parse(name).unwrap())
+                .collect()
+        };
+        assert_eq!(GeometryKind::parse("LINE"), Some(GeometryKind::LineString));
+        assert_eq!(GeometryKind::parse("circle"), None);
+
+        let multipoint: Geometry = geo::MultiPoint::from(vec![geo::Point::new(0.0, 0.0)]).into();
+        let filter = ObjectFilter {
+            kinds: kinds(&["point"]),
+            fields: Vec::new(),
+        };
+        assert!(filter.matches(&multipoint, None));
+        let filter = ObjectFilter {
+            kinds: kinds(&["polygon", "linestring"]),
+            fields: Vec::new(),
+        };
+        assert!(!filter.matches(&multipoint, None));
+
+        let filter = ObjectFilter {
+            kinds: Vec::new(),
+            fields: vec![FieldRange {
+                field: "speed".to_string(),
+                min: 10.0,
+                max: f64::INFINITY,
+            }],
+        };
+        let point_geometry: Geometry = geo::Point::new(0.0, 0.0).into();
+        assert!(filter.matches(&point_geometry, Some(&feature(point(0.0, 0.0), Some(10.0)))));
+        assert!(!filter.matches(&point_geometry, Some(&feature(point(0.0, 0.0), Some(9.0)))));
+        assert!(!filter.matches(&point_geometry, Some(&feature(point(0.0, 0.0), None))));
+        assert!(!filter.matches(&point_geometry, Some(&point(0.0, 0.0).to_string())));
+    }
+
+    #[test]
+    fn test_nearby_filtered() {
+        let mut tree = RTree::new(4);
+        // 离查询点最近的都是慢车和面，只有较远的点满足条件
+        for i in 0..20 {
+            let lon = i as f64 * 0.001;
+            tree.insert_geojson(format!("slow{}", i), &feature(point(lon, 0.0), Some(5.0)));
+        }
+        let zone = json!({
+            "type": "Polygon",
+            "coordinates": [[[0.0, 0.0], [0.001, 0.0], [0.001, 0.001], [0.0, 0.0]]]
+        });
+        tree.insert_geojson("zone".to_string(), &feature(zone, Some(50.0)));
+        tree.insert_geojson("fast1".to_string(), &feature(point(0.05, 0.0), Some(60.0)));
+        tree.insert_geojson("fast2".to_string(), &feature(point(0.08, 0.0), Some(80.0)));
+        tree.insert_geojson("fast3".to_string(), &feature(point(0.09, 0.0), Some(90.0)));
+
+        let filter = ObjectFilter {
+            kinds: vec![GeometryKind::Point],
+            fields: vec![FieldRange {
+                field: "speed".to_string(),
+                min: 40.0,
+                max: 85.0,
+            }],
+        };
+        let results = tree.nearby_filtered(
+            0.0,
+            0.0,
+            2,
+            None,
+            0.0,
+            None,
+            &filter,
+            DistanceMetric::Haversine,
+        );
+        let ids: Vec<&str> = results.iter().map(|(item, _)| item.id.as_str()).collect();
+        assert_eq!(ids, vec!["fast1", "fast2"]);
+        assert!(results[0].1 < results[1].1);
+
+        // 只按类型过滤
+        let filter = ObjectFilter {
+            kinds: vec![GeometryKind::Polygon],
+            fields: Vec::new(),
+        };
+        let results = tree.nearby_filtered(
+            0.05,
+            0.0,
+            5,
+            None,
+            0.0,
+            None,
+            &filter,
+            DistanceMetric::Haversine,
+        );
+        let ids: Vec<&str> = results.iter().map(|(item, _)| item.id.as_str()).collect();
+        assert_eq!(ids, vec!["zone"]);
+    }
+}
@@ -0,0 +1,138 @@
+use super::super::rtree::RTree;
+use geo::{ConcaveHull, ConvexHull, Coord, CoordsIter, Geometry, LineString, MultiPoint, Point};
+
+/// 外包几何类型
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum HullKind {
+    /// 凸包
+    #[default]
+    Convex,
+    /// 凹包，concavity 越小越贴合点集（geo 的 concavity 参数，常用值为 2.0）
+    Concave { concavity: f64 },
+}
+
+/// 外包几何相关算法
+impl RTree {
+    /// 计算对象集合的外包几何
+    ///
+    /// `area` 为 None 时使用整个 collection，否则只使用与 `area` 相交的对象。
+    /// 没有对象时返回 None；所有顶点重合时返回 Point，共线时返回 LineString，其他情况返回 Polygon
+    pub fn hull(&self, area: Option<&Geometry>, kind: HullKind) -> Option<Geometry> {
+        let mut coords: Vec<Coord> = match area {
+            Some(area) => self
+                .search(area, 0, false)
+                .iter()
+                .flat_map(|item| item.geometry.coords_iter())
+                .collect(),
+            None => self
+                .geometry_map
+                .values()
+                .flat_map(|geometry| geometry.coords_iter())
+                .collect(),
+        };
+
+        coords.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+        coords.dedup();
+
+        match coords.len() {
+            0 => return None,
+            1 => return Some(Geometry::Point(Point(coords[0]))),
+            2 => return Some(Geometry::LineString(LineString(coords))),
+            _ => {}
+        }
+
+        let points = MultiPoint::from(coords);
+        let hull = match kind {
+            HullKind::Convex => points.convex_hull(),
+            HullKind::Concave { concavity } => points.concave_hull(concavity),
+        };
+
+        // 共线的点集得到的是退化的多边形，改用两端点之间的线段表示
+        if hull.exterior().0.len() < 4 {
+            let ends = [points.0[0].0, points.0[points.0.len() - 1].0];
+            return Some(Geometry::LineString(LineString(ends.to_vec())));
+        }
+        Some(Geometry::Polygon(hull))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{Area, Contains};
+    use serde_json::json;
+
+    fn tree_with(points: &[(f64, f64)]) -> RTree {
+        let mut tree = RTree::new(4);
+        for (i, (x, y)) in points.iter().enumerate() {
+            let geojson = json!({"type": "Point", "coordinates": [x, y]});
+            assert!(tree.insert_geojson(format!("p{}", i), &geojson.to_string()));
+        }
+        tree
+    }
+
+    #[test]
+    fn test_convex_hull() {
+        let tree = tree_with(&[(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0), (2.0, 2.0)]);
+
+        let Some(Geometry::Polygon(hull)) = tree.hull(None, HullKind::Convex) else {
+            panic!("expected a polygon");
+        };
+        assert_eq!(hull.unsigned_area(), 16.0);
+        // 内部点不在外环上
+        assert_eq!(hull.exterior().0.len(), 5);
+        assert!(hull.contains(&Point::new(2.0, 2.0)));
+    }
+
+    #[test]
+    fn test_concave_hull_is_tighter() {
+        // L 形点集
+        let mut points = Vec::new();
+        for i in 0..=20 {
+            let t = i as f64 * 0.5;
+            points.push((t, 0.0));
+            points.push((0.0, t));
+            points.push((t, 1.0));
+            points.push((1.0, t));
+        }
+        let tree = tree_with(&points);
+
+        let Some(Geometry::Polygon(convex)) = tree.hull(None, HullKind::Convex) else {
+            panic!("expected a polygon");
+        };
+        let Some(Geometry::Polygon(concave)) =
+            tree.hull(None, HullKind::Concave { concavity: 0.5 })
+        else {
+            panic!("expected a polygon");
+        };
+        // L 形本身面积为 19，凸包会把缺口一起包进去
+        assert!(concave.unsigned_area() < 20.0);
+        assert!(convex.unsigned_area() > 50.0);
+    }
+
+    #[test]
+    fn test_hull_of_query_area_and_degenerate_cases() {
+        let tree = tree_with(&[(0.0, 0.0), (1.0, 1.0), (2.0, 2.0), (10.0, 10.0)]);
+
+        // 只有查询范围内的共线点
+        let area: Geometry = geo::Rect::new((-1.0, -1.0), (3.0, 3.0)).to_polygon().into();
+        assert_eq!(
+            tree.hull(Some(&area), HullKind::Convex),
+            Some(Geometry::LineString(LineString::from(vec![
+                (0.0, 0.0),
+                (2.0, 2.0)
+            ])))
+        );
+
+        let area: Geometry = geo::Rect::new((9.0, 9.0), (11.0, 11.0)).to_polygon().into();
+        assert_eq!(
+            tree.hull(Some(&area), HullKind::Convex),
+            Some(Geometry::Point(Point::new(10.0, 10.0)))
+        );
+
+        let area: Geometry = geo::Rect::new((50.0, 50.0), (51.0, 51.0))
+            .to_polygon()
+            .into();
+        assert_eq!(tree.hull(Some(&area), HullKind::Convex), None);
+    }
+}
@@ -1,9 +1,8 @@
 use crate::storage::geometry_utils::geojson_to_geometry;
 
-use super::super::node::{Entry, Node};
 use super::super::rectangle::Rectangle;
 use super::super::rtree::RTree;
-use super::utils::geometry_to_bbox;
+use super::utils::index_bboxes;
 // use geojson::Value;
 
 /// 插入操作相关算法
@@ -14,46 +13,48 @@ impl RTree {
     /// - `true` - 插入成功
     /// - `false` - 插入失败（GeoJSON 无效或 bbox 计算失败）
     pub fn insert_geojson(&mut self, data: String, geojson_str: &str) -> bool {
-        println!(
-            "🔍 insert_geojson called with data: {}, geojson_str: {}",
-            data, geojson_str
-        );
-
-        // 如果 key 已存在，先删除
-        if self.geometry_map.contains_key(&data) || self.geojson_map.contains_key(&data) {
-            self.delete(&data);
+        match self.try_insert_geojson(data, geojson_str) {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("❌ Failed to insert GeoJSON: {}", e);
+                false
+            }
         }
+    }
 
+    /// 与 [`insert_geojson`](Self::insert_geojson) 相同，失败时返回原因
+    ///
+    /// 先解析 GeoJSON 并计算边界框，都成功后才替换同名对象，失败时原有对象保持不变
+    pub fn try_insert_geojson(&mut self, data: String, geojson_str: &str) -> crate::Result<()> {
         // 解析 GeoJSON（可能失败）
-        let geometry = match geojson_to_geometry(geojson_str) {
-            Ok(g) => g,
-            Err(e) => {
-                eprintln!("❌ Failed to parse GeoJSON: {}", e);
-                return false;
-            }
-        };
+        let geometry = geojson_to_geometry(geojson_str)?;
 
-        // 计算边界框（可能失败）
-        let rect = match geometry_to_bbox(&geometry) {
-            Ok(bbox) => bbox,
-            Err(e) => {
-                eprintln!("❌ Failed to calculate bounding box: {}", e);
-                return false;
-            }
-        };
+        // 计算边界框（可能失败），GeometryCollection 的每个部分各有一个
+        let rects = index_bboxes(&geometry)
+            .map_err(|e| format!("failed to calculate bounding box: {}", e))?;
 
-        // 插入到 R-tree
-        self.insert(rect, data.clone());
-        self.geometry_map.insert(data.clone(), geometry);
-        self.geojson_map
-            .insert(data.clone(), geojson_str.to_string());
+        // 如果 key 已存在（包括同名的 STRING 对象），先删除
+        if self.geometry_map.contains_key(&data)
+            || self.geojson_map.contains_key(&data)
+            || self.strings.contains_key(&data)
+        {
+            self.delete(&data);
+        }
 
-        println!(
-            "🔍 Stored in geojson_map: {}",
-            self.geojson_map.get(&data).unwrap()
-        );
+        // 插入到 R-tree（暂停维护索引时由 rebuild_index 统一建立）
+        if !self.index_deferred {
+            for rect in rects {
+                self.insert(rect, data.clone());
+            }
+        }
+        self.geometry_map.insert(data.clone(), geometry);
+        if let Some(ids) = self.ordered_ids.get_mut() {
+            ids.insert(data.clone());
+        }
+        self.touch(&data);
+        self.geojson_map.insert(data, geojson_str.to_string());
 
-        true
+        Ok(())
     }
 
     // /// 插入新的数据条目 - 遵循论文Algorithm Insert
@@ -78,81 +79,9 @@ impl RTree {
     //     self.geojson_map.insert(data.clone(), geojson_value.to_string());
 
     // }
-    /// 插入新的数据条目 - 遵循论文Algorithm Insert
+    /// 插入新的数据条目 - 遵循论文Algorithm Insert，见 [`RTreeIndex::insert`](spatio_rtree::RTreeIndex::insert)
     pub fn insert(&mut self, rect: Rectangle, data: String) {
-        // I1: 如果根节点不存在，创建根节点
-        if self.root_ref().is_none() {
-            let mut root = Node::new_leaf_node();
-            root.add_entry(Entry::Data { mbr: rect, data });
-            *self.root_mut() = Some(Box::new(root));
-            return;
-        }
-
-        // I2: 选择叶子节点
-        let leaf_path = self.choose_leaf_path(&rect);
-
-        // I3: 添加记录到叶子节点
-        let max_entries = self.max_entries_internal();
-        let leaf_node = match self.get_last_node_mut(&leaf_path) {
-            Some(node) => node,
-            None => {
-                // 如果无法获取叶子节点，说明路径有问题，这是一个严重的错误
-                panic!("Failed to get leaf node during insertion");
-            }
-        };
-        leaf_node.add_entry(Entry::Data { mbr: rect, data });
-
-        // I4: 检查是否需要分裂并调整树
-        if leaf_node.entries.len() > max_entries {
-            self.handle_overflow(leaf_path);
-        } else {
-            // 只需要更新MBR
-            self.adjust_tree_upward(leaf_path);
-        }
-    }
-
-    /// 选择叶子节点路径 - 遵循论文ChooseLeaf算法
-    fn choose_leaf_path(&self, rect: &Rectangle) -> Vec<usize> {
-        let mut path = Vec::new();
-        let mut current = self.root_ref().as_ref().unwrap();
-
-        // CL1: 初始化，从根节点开始
-        // CL2: 叶子检查
-        while !current.is_leaf_node() {
-            // CL3: 选择子树 - 选择扩大面积最小的条目
-            let best_index = self.choose_subtree(&current.entries, rect);
-            path.push(best_index);
-
-            // CL4: 下降到子节点
-            if let Some(Entry::Node { node, .. }) = current.entries.get(best_index) {
-                current = node;
-            }
-        }
-
-        path
-    }
-
-    /// 选择子树 - 计算扩大面积最小的条目
-    fn choose_subtree(&self, entries: &[Entry], rect: &Rectangle) -> usize {
-        let mut best_index = 0;
-        let mut min_enlargement = f64::INFINITY;
-        let mut min_area = f64::INFINITY;
-
-        for (i, entry) in entries.iter().enumerate() {
-            let mbr = entry.mbr();
-            let enlargement = mbr.enlargement(rect);
-            let area = mbr.area();
-
-            // 选择扩大面积最小的，如果相同则选择面积最小的
-            if enlargement < min_enlargement || (enlargement == min_enlargement && area < min_area)
-            {
-                min_enlargement = enlargement;
-                min_area = area;
-                best_index = i;
-            }
-        }
-
-        best_index
+        self.index.insert(rect, data);
     }
 }
 
@@ -348,54 +277,6 @@ mod tests {
         assert!(partial_results.contains(&data_id));
     }
 
-    #[test]
-    fn test_choose_leaf_path() {
-        let mut rtree = RTree::new(3); // 小的max_entries以便测试分裂
-
-        // 插入足够多的数据以创建多层树结构
-        for i in 0..6 {
-            let x = (i as f64) * 2.0;
-            rtree.insert(Rectangle::new(x, 0.0, x + 1.0, 1.0), i.to_string());
-        }
-
-        // 测试选择叶子路径
-        let rect = Rectangle::new(0.5, 0.5, 1.5, 1.5);
-        if let Some(root) = rtree.root_ref() {
-            if !root.is_leaf_node() {
-                let path = rtree.choose_leaf_path(&rect);
-                assert!(!path.is_empty());
-            }
-        }
-    }
-
-    #[test]
-    fn test_choose_subtree() {
-        let rtree = RTree::new(4);
-
-        // 创建一些测试条目
-        let entries = vec![
-            Entry::Data {
-                mbr: Rectangle::new(0.0, 0.0, 5.0, 5.0),
-                data: "1".to_string(),
-            },
-            Entry::Data {
-                mbr: Rectangle::new(10.0, 10.0, 15.0, 15.0),
-                data: "2".to_string(),
-            },
-            Entry::Data {
-                mbr: Rectangle::new(20.0, 20.0, 25.0, 25.0),
-                data: "3".to_string(),
-            },
-        ];
-
-        // 测试选择最合适的子树
-        let test_rect = Rectangle::new(2.0, 2.0, 3.0, 3.0);
-        let best_index = rtree.choose_subtree(&entries, &test_rect);
-
-        // 应该选择第一个条目，因为它与测试矩形重叠
-        assert_eq!(best_index, 0);
-    }
-
     #[test]
     fn test_insert_same_id_overwrites() {
         let mut rtree = RTree::new(4);
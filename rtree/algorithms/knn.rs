@@ -18,14 +18,45 @@
 //!
 //! - Time Complexity: O(K log N) for small K values
 //! - Space Complexity: O(log N) for the heap
+//! - Nodes are borrowed from the tree while traversing; a `GeoItem` is only cloned
+//!   out of the maps once its entry is accepted as a result
 //! - Much more efficient than brute-force scan for large datasets
+//!
+//! ## Approximate Search
+//!
+//! [`knn_search_approx`] accepts an `epsilon` that trades exactness for latency.
+//! Candidates are tracked as soon as they are discovered, and the search stops once
+//! the best remaining MBR distance multiplied by `1 + epsilon` exceeds the current
+//! K-th candidate distance. Every returned distance is then within a factor of
+//! `1 + epsilon` of the true K-th nearest distance. `epsilon = 0` is an exact search.
+//!
+//! ## Region-Constrained Search
+//!
+//! [`knn_search_within`] only returns items inside a region (e.g. the K nearest
+//! stations inside a district). The traversal skips every subtree whose MBR misses
+//! the region's bounding box, so candidates outside it are never queued.
+//!
+//! ## Filtered Search
+//!
+//! [`knn_search_filtered`] additionally takes an item predicate (e.g. geometry type
+//! or property ranges). The predicate runs before an item is queued, so rejected
+//! items never occupy a result slot and the traversal keeps expanding the heap until
+//! `k` accepted items are found.
+//!
+//! ## Distance Metrics
+//!
+//! Distances default to Haversine meters. [`DistanceMetric`] also offers the
+//! spherical law of cosines (same great-circle distance, a different formula) and
+//! planar Euclidean distance in coordinate units for non-geographic data such as
+//! game maps or floor plans, where Haversine would rank items in the wrong order.
 
 use super::super::node::{Entry, Node};
 use super::super::rectangle::Rectangle;
 use super::super::rtree::GeoItem;
-use geo::Geometry;
+use super::utils::is_multipart;
+use geo::{Geometry, Within};
 use std::cmp::Ordering;
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, HashSet};
 
 /// Result of a KNN search: an item and its distance to the query point
 #[derive(Debug, Clone)]
@@ -40,16 +71,20 @@ pub struct KnnResult {
 ///
 /// This can represent either:
 /// - A leaf entry (actual data item)
-/// - An internal node with its children
+/// - An internal node with its children (borrowed from the tree, never cloned)
 #[derive(Debug)]
-enum QueueEntry {
-    /// A leaf entry containing actual data
-    LeafEntry { min_distance: f64, item: GeoItem },
+enum QueueEntry<'a> {
+    /// A leaf entry; the GeoItem is only built once it makes it into the results
+    LeafEntry {
+        min_distance: f64,
+        id: &'a String,
+        geometry: &'a Geometry,
+    },
     /// An internal node to be explored
-    InternalNode { min_distance: f64, node: Node },
+    InternalNode { min_distance: f64, node: &'a Node },
 }
 
-impl QueueEntry {
+impl QueueEntry<'_> {
     fn min_distance(&self) -> f64 {
         match self {
             QueueEntry::LeafEntry { min_distance, .. } => *min_distance,
@@ -60,21 +95,21 @@ impl QueueEntry {
 
 // Implement Ord for BinaryHeap (min-heap behavior)
 // Note: BinaryHeap is a max-heap by default, so we reverse the ordering
-impl PartialEq for QueueEntry {
+impl PartialEq for QueueEntry<'_> {
     fn eq(&self, other: &Self) -> bool {
         self.min_distance() == other.min_distance()
     }
 }
 
-impl Eq for QueueEntry {}
+impl Eq for QueueEntry<'_> {}
 
-impl PartialOrd for QueueEntry {
+impl PartialOrd for QueueEntry<'_> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl Ord for QueueEntry {
+impl Ord for QueueEntry<'_> {
     fn cmp(&self, other: &Self) -> Ordering {
         // Reverse ordering for min-heap behavior
         other
@@ -117,6 +152,98 @@ pub fn haversine_distance(lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> f64 {
     EARTH_RADIUS_METERS * c
 }
 
+/// Calculate great-circle distance with the spherical law of cosines
+///
+/// Mathematically equal to [`haversine_distance`], but loses precision for points
+/// less than about a meter apart.
+///
+/// # Returns
+///
+/// Distance in meters
+pub fn spherical_law_of_cosines_distance(lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> f64 {
+    const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+
+    let lat1_rad = lat1.to_radians();
+    let lat2_rad = lat2.to_radians();
+    let delta_lon = (lon2 - lon1).to_radians();
+
+    let cos_c = lat1_rad.sin() * lat2_rad.sin() + lat1_rad.cos() * lat2_rad.cos() * delta_lon.cos();
+
+    EARTH_RADIUS_METERS * cos_c.clamp(-1.0, 1.0).acos()
+}
+
+/// Distance metric used to rank KNN results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DistanceMetric {
+    /// Great-circle distance in meters (default)
+    #[default]
+    Haversine,
+    /// Planar distance in coordinate units, for non-geographic data
+    Euclidean,
+    /// Great-circle distance in meters computed with the spherical law of cosines
+    SphericalLawOfCosines,
+}
+
+impl DistanceMetric {
+    /// Parse a metric name (case-insensitive): `haversine`, `euclidean` or `cosines`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "haversine" => Some(Self::Haversine),
+            "euclidean" => Some(Self::Euclidean),
+            "cosines" => Some(Self::SphericalLawOfCosines),
+            _ => None,
+        }
+    }
+
+    /// The name accepted by [`parse`](Self::parse)
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Haversine => "haversine",
+            Self::Euclidean => "euclidean",
+            Self::SphericalLawOfCosines => "cosines",
+        }
+    }
+
+    /// Whether distances are great-circle meters (as opposed to coordinate units)
+    pub fn is_geodesic(&self) -> bool {
+        !matches!(self, Self::Euclidean)
+    }
+
+    /// Distance between two points
+    pub fn point_distance(&self, lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> f64 {
+        match self {
+            Self::Haversine => haversine_distance(lon1, lat1, lon2, lat2),
+            Self::Euclidean => (lon2 - lon1).hypot(lat2 - lat1),
+            Self::SphericalLawOfCosines => {
+                spherical_law_of_cosines_distance(lon1, lat1, lon2, lat2)
+            }
+        }
+    }
+
+    /// Minimum distance from a point to a rectangle, a lower bound for everything inside it
+    pub fn point_to_rectangle(&self, point_lon: f64, point_lat: f64, rect: &Rectangle) -> f64 {
+        match self {
+            Self::Euclidean => {
+                let dx = lon_gap(point_lon, rect.min[0], rect.max[0]);
+                let dy = lon_gap(point_lat, rect.min[1], rect.max[1]);
+                dx.hypot(dy)
+            }
+            _ => spherical_point_to_rectangle(point_lon, point_lat, rect, *self),
+        }
+    }
+
+    /// Distance from a point to the closest point of a geometry, 0 inside polygons
+    pub fn point_to_geometry(&self, point_lon: f64, point_lat: f64, geometry: &Geometry) -> f64 {
+        match self {
+            Self::Euclidean => {
+                use geo::{Distance, Euclidean};
+                Euclidean.distance(&geo::Point::new(point_lon, point_lat), geometry)
+            }
+            _ => spherical_point_to_geometry(point_lon, point_lat, geometry, *self),
+        }
+    }
+}
+
 /// Calculate minimum distance from a point to a rectangle (MBR)
 ///
 /// This returns the distance to the closest point on the rectangle's boundary
@@ -130,13 +257,58 @@ pub fn haversine_distance(lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> f64 {
 /// # Returns
 ///
 /// Minimum distance in meters
+///
+/// # Notes
+///
+/// The longitude gap is measured the short way around, so a query at 179.9°
+/// is close to a rectangle starting at -180°. Outside the rectangle's longitude
+/// range the nearest point lies on its edge meridian, but not at the query's
+/// latitude: great circles bend poleward, so the foot of the perpendicular is
+/// used. This keeps the result a true lower bound near the antimeridian and at
+/// high latitudes, which the KNN pruning relies on.
 pub fn point_to_rectangle_distance(point_lon: f64, point_lat: f64, rect: &Rectangle) -> f64 {
-    // Find the closest point on the rectangle to the query point
-    let closest_lon = point_lon.clamp(rect.min[0], rect.max[0]);
-    let closest_lat = point_lat.clamp(rect.min[1], rect.max[1]);
+    spherical_point_to_rectangle(point_lon, point_lat, rect, DistanceMetric::Haversine)
+}
+
+/// [`point_to_rectangle_distance`] with the point distance taken from a geodesic `metric`
+fn spherical_point_to_rectangle(
+    point_lon: f64,
+    point_lat: f64,
+    rect: &Rectangle,
+    metric: DistanceMetric,
+) -> f64 {
+    let lon = wrapped_lon(point_lon, rect).unwrap_or(point_lon);
+    let edge_lon = lon.clamp(rect.min[0], rect.max[0]);
+    let delta_lon = (lon - edge_lon).to_radians();
+
+    // Inside the longitude range: the closest point is straight north or south.
+    // Within 90° of the edge meridian, the distance along it is smallest at
+    // latitude atan2(sin φ, cos φ cos Δλ), clamped into the rectangle
+    if delta_lon.cos() >= 0.0 {
+        let lat = point_lat.to_radians();
+        let foot_lat = lat.sin().atan2(lat.cos() * delta_lon.cos()).to_degrees();
+        let closest_lat = foot_lat.clamp(rect.min[1], rect.max[1]);
+        return metric.point_distance(point_lon, point_lat, edge_lon, closest_lat);
+    }
+
+    // More than 90° away the foot lies beyond the pole, so one of the corners is closest
+    let to_bottom = metric.point_distance(point_lon, point_lat, edge_lon, rect.min[1]);
+    let to_top = metric.point_distance(point_lon, point_lat, edge_lon, rect.max[1]);
+    to_bottom.min(to_top)
+}
 
-    // Calculate distance to the closest point
-    haversine_distance(point_lon, point_lat, closest_lon, closest_lat)
+/// Longitude gap (degrees) from `lon` to the range `[min, max]`, 0 if inside
+fn lon_gap(lon: f64, min: f64, max: f64) -> f64 {
+    (min - lon).max(lon - max).max(0.0)
+}
+
+/// The query longitude shifted by ±360° if that image is closer to the
+/// rectangle's longitude range, i.e. the short way crosses the antimeridian
+fn wrapped_lon(lon: f64, rect: &Rectangle) -> Option<f64> {
+    let gap = lon_gap(lon, rect.min[0], rect.max[0]);
+    [lon + 360.0, lon - 360.0]
+        .into_iter()
+        .find(|&shifted| lon_gap(shifted, rect.min[0], rect.max[0]) < gap)
 }
 
 /// Calculate distance from a point to a geometry
@@ -162,7 +334,44 @@ pub fn point_to_rectangle_distance(point_lon: f64, point_lat: f64, rect: &Rectan
 /// - For points inside polygons, returns 0.0
 /// - Uses planar approximation for finding closest points, then Haversine for distance
 /// - Suitable for local-scale queries; large-scale queries may have minor geodetic errors
+/// - Lines and polygons on the other side of the antimeridian are measured from the
+///   query shifted by ±360°, so a query in Fiji finds a coastline drawn at -179.9°
 pub fn point_to_geometry_distance(point_lon: f64, point_lat: f64, geometry: &Geometry) -> f64 {
+    spherical_point_to_geometry(point_lon, point_lat, geometry, DistanceMetric::Haversine)
+}
+
+/// [`point_to_geometry_distance`] with the point distance taken from a geodesic `metric`
+fn spherical_point_to_geometry(
+    point_lon: f64,
+    point_lat: f64,
+    geometry: &Geometry,
+    metric: DistanceMetric,
+) -> f64 {
+    let distance = planar_closest_distance(point_lon, point_lat, geometry, metric);
+
+    // Point distances are great-circle distances, which already wrap; collections
+    // handle the wrap per member
+    if matches!(
+        geometry,
+        Geometry::Point(_) | Geometry::MultiPoint(_) | Geometry::GeometryCollection(_)
+    ) {
+        return distance;
+    }
+    match geometry_to_rectangle(geometry).and_then(|rect| wrapped_lon(point_lon, &rect)) {
+        Some(shifted) => distance.min(planar_closest_distance(
+            shifted, point_lat, geometry, metric,
+        )),
+        None => distance,
+    }
+}
+
+/// Distance to the closest point found in planar coordinates, see [`point_to_geometry_distance`]
+fn planar_closest_distance(
+    point_lon: f64,
+    point_lat: f64,
+    geometry: &Geometry,
+    metric: DistanceMetric,
+) -> f64 {
     use geo::algorithm::closest_point::ClosestPoint;
 
     let query_point = geo::Point::new(point_lon, point_lat);
@@ -170,27 +379,22 @@ pub fn point_to_geometry_distance(point_lon: f64, point_lat: f64, geometry: &Geo
     match geometry {
         Geometry::Point(p) => {
             // Point to point: direct distance
-            haversine_distance(point_lon, point_lat, p.x(), p.y())
+            metric.point_distance(point_lon, point_lat, p.x(), p.y())
         }
         Geometry::Line(line) => {
             // Point to line segment: may be perpendicular distance or distance to endpoint
             match line.closest_point(&query_point) {
                 geo::Closest::Intersection(_) => 0.0, // Point is on the line
                 geo::Closest::SinglePoint(closest) => {
-                    haversine_distance(point_lon, point_lat, closest.x(), closest.y())
+                    metric.point_distance(point_lon, point_lat, closest.x(), closest.y())
                 }
                 geo::Closest::Indeterminate => f64::INFINITY,
             }
         }
-        Geometry::LineString(ls) => {
-            // Point to polyline: finds closest point on any segment
-            match ls.closest_point(&query_point) {
-                geo::Closest::Intersection(_) => 0.0,
-                geo::Closest::SinglePoint(closest) => {
-                    haversine_distance(point_lon, point_lat, closest.x(), closest.y())
-                }
-                geo::Closest::Indeterminate => f64::INFINITY,
-            }
+        Geometry::LineString(_) | Geometry::MultiLineString(_) => {
+            // Point to polyline(s): finds closest point on any segment
+            closest_point_on_line_by(point_lon, point_lat, geometry, metric)
+                .map_or(f64::INFINITY, |(_, distance)| distance)
         }
         Geometry::Polygon(poly) => {
             // Point to polygon: 0 if inside, otherwise distance to boundary
@@ -202,7 +406,7 @@ pub fn point_to_geometry_distance(point_lon: f64, point_lat: f64, geometry: &Geo
             // Find closest point on exterior ring
             let mut min_distance = match poly.exterior().closest_point(&query_point) {
                 geo::Closest::SinglePoint(closest) => {
-                    haversine_distance(point_lon, point_lat, closest.x(), closest.y())
+                    metric.point_distance(point_lon, point_lat, closest.x(), closest.y())
                 }
                 geo::Closest::Intersection(_) => 0.0,
                 geo::Closest::Indeterminate => f64::INFINITY,
@@ -212,7 +416,7 @@ pub fn point_to_geometry_distance(point_lon: f64, point_lat: f64, geometry: &Geo
             for interior in poly.interiors() {
                 let dist = match interior.closest_point(&query_point) {
                     geo::Closest::SinglePoint(closest) => {
-                        haversine_distance(point_lon, point_lat, closest.x(), closest.y())
+                        metric.point_distance(point_lon, point_lat, closest.x(), closest.y())
                     }
                     geo::Closest::Intersection(_) => 0.0,
                     geo::Closest::Indeterminate => f64::INFINITY,
@@ -225,19 +429,7 @@ pub fn point_to_geometry_distance(point_lon: f64, point_lat: f64, geometry: &Geo
         Geometry::MultiPoint(mp) => {
             // Find nearest point in the collection
             mp.iter()
-                .map(|p| haversine_distance(point_lon, point_lat, p.x(), p.y()))
-                .fold(f64::INFINITY, f64::min)
-        }
-        Geometry::MultiLineString(mls) => {
-            // Find nearest line in the collection
-            mls.iter()
-                .map(|ls| match ls.closest_point(&query_point) {
-                    geo::Closest::Intersection(_) => 0.0,
-                    geo::Closest::SinglePoint(closest) => {
-                        haversine_distance(point_lon, point_lat, closest.x(), closest.y())
-                    }
-                    geo::Closest::Indeterminate => f64::INFINITY,
-                })
+                .map(|p| metric.point_distance(point_lon, point_lat, p.x(), p.y()))
                 .fold(f64::INFINITY, f64::min)
         }
         Geometry::MultiPolygon(mp) => {
@@ -253,7 +445,7 @@ pub fn point_to_geometry_distance(point_lon: f64, point_lat: f64, geometry: &Geo
                     // Find minimum distance to this polygon's boundary
                     let mut min_dist = match poly.exterior().closest_point(&query_point) {
                         geo::Closest::SinglePoint(closest) => {
-                            haversine_distance(point_lon, point_lat, closest.x(), closest.y())
+                            metric.point_distance(point_lon, point_lat, closest.x(), closest.y())
                         }
                         geo::Closest::Intersection(_) => 0.0,
                         geo::Closest::Indeterminate => f64::INFINITY,
@@ -261,9 +453,12 @@ pub fn point_to_geometry_distance(point_lon: f64, point_lat: f64, geometry: &Geo
 
                     for interior in poly.interiors() {
                         let dist = match interior.closest_point(&query_point) {
-                            geo::Closest::SinglePoint(closest) => {
-                                haversine_distance(point_lon, point_lat, closest.x(), closest.y())
-                            }
+                            geo::Closest::SinglePoint(closest) => metric.point_distance(
+                                point_lon,
+                                point_lat,
+                                closest.x(),
+                                closest.y(),
+                            ),
                             geo::Closest::Intersection(_) => 0.0,
                             geo::Closest::Indeterminate => f64::INFINITY,
                         };
@@ -277,13 +472,13 @@ pub fn point_to_geometry_distance(point_lon: f64, point_lat: f64, geometry: &Geo
         Geometry::GeometryCollection(gc) => {
             // Recursively find nearest geometry in the collection
             gc.iter()
-                .map(|geom| point_to_geometry_distance(point_lon, point_lat, geom))
+                .map(|geom| spherical_point_to_geometry(point_lon, point_lat, geom, metric))
                 .fold(f64::INFINITY, f64::min)
         }
         _ => {
             // For other types (Rect, Triangle), use bounding rectangle as fallback
             if let Some(rect) = geometry_to_rectangle(geometry) {
-                point_to_rectangle_distance(point_lon, point_lat, &rect)
+                spherical_point_to_rectangle(point_lon, point_lat, &rect, metric)
             } else {
                 f64::INFINITY
             }
@@ -291,6 +486,135 @@ pub fn point_to_geometry_distance(point_lon: f64, point_lat: f64, geometry: &Geo
     }
 }
 
+/// Find the closest point on a line-like geometry
+///
+/// Supports `Line`, `LineString` and `MultiLineString`; any other geometry type
+/// returns `None`. For a `MultiLineString` the nearest of its lines is used.
+///
+/// # Returns
+///
+/// The projected point on the line and its Haversine distance (meters) to the query
+/// point, or `None` if the geometry is not line-like or is empty
+pub fn closest_point_on_line(
+    point_lon: f64,
+    point_lat: f64,
+    geometry: &Geometry,
+) -> Option<(geo::Point, f64)> {
+    closest_point_on_line_by(point_lon, point_lat, geometry, DistanceMetric::Haversine)
+}
+
+/// [`closest_point_on_line`] with the distance measured by `metric`
+fn closest_point_on_line_by(
+    point_lon: f64,
+    point_lat: f64,
+    geometry: &Geometry,
+    metric: DistanceMetric,
+) -> Option<(geo::Point, f64)> {
+    use geo::algorithm::closest_point::ClosestPoint;
+
+    let query_point = geo::Point::new(point_lon, point_lat);
+    let closest = match geometry {
+        Geometry::Line(line) => line.closest_point(&query_point),
+        Geometry::LineString(ls) => ls.closest_point(&query_point),
+        Geometry::MultiLineString(mls) => mls.closest_point(&query_point),
+        _ => return None,
+    };
+
+    match closest {
+        geo::Closest::Intersection(p) => Some((p, 0.0)),
+        geo::Closest::SinglePoint(p) => {
+            Some((p, metric.point_distance(point_lon, point_lat, p.x(), p.y())))
+        }
+        geo::Closest::Indeterminate => None,
+    }
+}
+
+/// Result of snapping a query point onto the nearest line
+#[derive(Debug, Clone)]
+pub struct SnapResult {
+    /// ID of the nearest line-like item
+    pub id: String,
+    /// Projected point on the line
+    pub point: geo::Point,
+    /// Offset distance from the query point to the projected point (meters)
+    pub distance: f64,
+}
+
+/// Snap a point onto the nearest line-like item in an R-tree
+///
+/// Uses the same best-first traversal as [`knn_search`], but only line-like leaves
+/// (see [`closest_point_on_line`]) are queued, so the first leaf popped from the
+/// heap is the nearest line. Points and polygons never stop the search.
+///
+/// # Returns
+///
+/// `None` if the tree contains no line-like items
+pub fn snap_search(
+    root: Option<&Node>,
+    query_lon: f64,
+    query_lat: f64,
+    geometry_map: &std::collections::HashMap<String, Geometry>,
+) -> Option<SnapResult> {
+    let root_node = root?;
+    if root_node.entries.is_empty() {
+        return None;
+    }
+
+    // Projected points of queued leaves, so each line is only projected once
+    let mut projections: std::collections::HashMap<&String, geo::Point> =
+        std::collections::HashMap::new();
+    let mut heap: BinaryHeap<QueueEntry> = BinaryHeap::new();
+    heap.push(QueueEntry::InternalNode {
+        min_distance: point_to_rectangle_distance(query_lon, query_lat, &root_node.mbr),
+        node: root_node,
+    });
+
+    while let Some(entry) = heap.pop() {
+        match entry {
+            QueueEntry::LeafEntry {
+                min_distance, id, ..
+            } => {
+                return Some(SnapResult {
+                    id: id.clone(),
+                    point: projections[id],
+                    distance: min_distance,
+                });
+            }
+            QueueEntry::InternalNode { node, .. } => {
+                for entry in &node.entries {
+                    match entry {
+                        Entry::Data { mbr: _, data } => {
+                            let Some(geometry) = geometry_map.get(data) else {
+                                continue;
+                            };
+                            if let Some((point, distance)) =
+                                closest_point_on_line(query_lon, query_lat, geometry)
+                            {
+                                projections.insert(data, point);
+                                heap.push(QueueEntry::LeafEntry {
+                                    min_distance: distance,
+                                    id: data,
+                                    geometry,
+                                });
+                            }
+                        }
+                        Entry::Node { mbr, node } => {
+                            heap.push(QueueEntry::InternalNode {
+                                min_distance: point_to_rectangle_distance(
+                                    query_lon, query_lat, mbr,
+                                ),
+                                node,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    None
+}
+
 /// Convert a geometry to its bounding rectangle
 fn geometry_to_rectangle(geometry: &Geometry) -> Option<Rectangle> {
     use geo::algorithm::bounding_rect::BoundingRect;
@@ -335,6 +659,184 @@ pub fn knn_search(
     geometry_map: &std::collections::HashMap<String, Geometry>,
     geojson_map: &std::collections::HashMap<String, String>,
     max_radius: Option<f64>,
+) -> Vec<KnnResult> {
+    knn_search_approx(
+        root,
+        query_lon,
+        query_lat,
+        k,
+        geometry_map,
+        geojson_map,
+        max_radius,
+        0.0,
+    )
+}
+
+/// Perform (1 + epsilon)-approximate KNN search on an R-tree
+///
+/// Same as [`knn_search`], but allows the search to terminate early once the best
+/// remaining MBR distance, scaled by `1 + epsilon`, exceeds the K-th nearest candidate
+/// discovered so far. Larger values visit fewer nodes on huge trees at the cost of
+/// possibly missing some true nearest neighbors.
+///
+/// # Arguments
+///
+/// * `epsilon` - Approximation factor (>= 0). `0.0` gives exact results. Only applies
+///   when `k > 0`; radius-only queries are always exact.
+///
+/// # Returns
+///
+/// Vector of KnnResult, sorted by ascending distance (nearest first)
+#[allow(clippy::too_many_arguments)]
+pub fn knn_search_approx(
+    root: Option<&Node>,
+    query_lon: f64,
+    query_lat: f64,
+    k: usize,
+    geometry_map: &std::collections::HashMap<String, Geometry>,
+    geojson_map: &std::collections::HashMap<String, String>,
+    max_radius: Option<f64>,
+    epsilon: f64,
+) -> Vec<KnnResult> {
+    knn_traverse(
+        root,
+        query_lon,
+        query_lat,
+        k,
+        geometry_map,
+        geojson_map,
+        max_radius,
+        epsilon,
+        None,
+        None,
+        DistanceMetric::Haversine,
+    )
+}
+
+/// Perform KNN search among the items that lie within `region`
+///
+/// Subtrees whose MBR does not intersect the region's bounding box are never
+/// visited, and each remaining item is checked precisely with `is_within`
+/// before it can become a candidate. Items on the region's boundary are not
+/// within it, matching `INTERSECTS ... WITHIN`.
+///
+/// # Returns
+///
+/// Vector of KnnResult, sorted by ascending distance (nearest first). Empty if
+/// the region has no bounding box (e.g. an empty geometry collection).
+#[allow(clippy::too_many_arguments)]
+pub fn knn_search_within(
+    root: Option<&Node>,
+    query_lon: f64,
+    query_lat: f64,
+    k: usize,
+    geometry_map: &std::collections::HashMap<String, Geometry>,
+    geojson_map: &std::collections::HashMap<String, String>,
+    max_radius: Option<f64>,
+    epsilon: f64,
+    region: &Geometry,
+) -> Vec<KnnResult> {
+    let Some(bbox) = geometry_to_rectangle(region) else {
+        return Vec::new();
+    };
+    knn_traverse(
+        root,
+        query_lon,
+        query_lat,
+        k,
+        geometry_map,
+        geojson_map,
+        max_radius,
+        epsilon,
+        Some(Region {
+            bbox,
+            geometry: region,
+        }),
+        None,
+        DistanceMetric::Haversine,
+    )
+}
+
+/// Perform KNN search among the items accepted by `accept`
+///
+/// `accept` receives the item ID and geometry and is evaluated while traversing,
+/// before the item becomes a candidate, so the search returns the K nearest
+/// *matching* items rather than filtering a fixed list of K candidates. With a
+/// `region`, items must also lie within it as in [`knn_search_within`].
+/// Distances (and `max_radius`) are measured with `metric`.
+///
+/// # Returns
+///
+/// Vector of KnnResult, sorted by ascending distance (nearest first)
+#[allow(clippy::too_many_arguments)]
+pub fn knn_search_filtered(
+    root: Option<&Node>,
+    query_lon: f64,
+    query_lat: f64,
+    k: usize,
+    geometry_map: &std::collections::HashMap<String, Geometry>,
+    geojson_map: &std::collections::HashMap<String, String>,
+    max_radius: Option<f64>,
+    epsilon: f64,
+    region: Option<&Geometry>,
+    accept: ItemFilter<'_>,
+    metric: DistanceMetric,
+) -> Vec<KnnResult> {
+    let region = match region {
+        Some(geometry) => match geometry_to_rectangle(geometry) {
+            Some(bbox) => Some(Region { bbox, geometry }),
+            None => return Vec::new(),
+        },
+        None => None,
+    };
+    knn_traverse(
+        root,
+        query_lon,
+        query_lat,
+        k,
+        geometry_map,
+        geojson_map,
+        max_radius,
+        epsilon,
+        region,
+        Some(accept),
+        metric,
+    )
+}
+
+/// Item predicate for [`knn_search_filtered`]: receives the item ID and geometry
+pub type ItemFilter<'a> = &'a dyn Fn(&str, &Geometry) -> bool;
+
+/// Region constraint for [`knn_search_within`]
+struct Region<'a> {
+    bbox: Rectangle,
+    geometry: &'a Geometry,
+}
+
+impl Region<'_> {
+    /// Cheap MBR test used to prune subtrees and leaf entries
+    fn may_contain(&self, mbr: &Rectangle) -> bool {
+        self.bbox.intersects(mbr)
+    }
+
+    fn contains(&self, geometry: &Geometry) -> bool {
+        geometry.is_within(self.geometry)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn knn_traverse(
+    root: Option<&Node>,
+    query_lon: f64,
+    query_lat: f64,
+    k: usize,
+    geometry_map: &std::collections::HashMap<String, Geometry>,
+    geojson_map: &std::collections::HashMap<String, String>,
+    max_radius: Option<f64>,
+    epsilon: f64,
+    region: Option<Region<'_>>,
+    accept: Option<ItemFilter<'_>>,
+    metric: DistanceMetric,
 ) -> Vec<KnnResult> {
     // Early return if tree is empty or (k is 0 and no radius limit)
     if root.is_none() || (k == 0 && max_radius.is_none()) {
@@ -344,6 +846,13 @@ pub fn knn_search(
     let mut results: Vec<KnnResult> = Vec::with_capacity(k);
     let mut heap: BinaryHeap<QueueEntry> = BinaryHeap::new();
 
+    // Build GeoItem on demand
+    let build_item = |id: &String, geometry: &Geometry| GeoItem {
+        id: id.clone(),
+        geometry: geometry.clone(),
+        geojson: geojson_map.get(id).cloned().unwrap_or_default(),
+    };
+
     // Start with the root node
     let root_node = root.unwrap();
     let root_distance = if root_node.entries.is_empty() {
@@ -351,14 +860,23 @@ pub fn knn_search(
     } else {
         // Calculate minimum distance to root's MBR
         let root_mbr = &root_node.mbr;
-        point_to_rectangle_distance(query_lon, query_lat, root_mbr)
+        metric.point_to_rectangle(query_lon, query_lat, root_mbr)
     };
 
     heap.push(QueueEntry::InternalNode {
         min_distance: root_distance,
-        node: root_node.clone(),
+        node: root_node,
     });
 
+    // Approximate mode: distances of the K best candidates discovered so far
+    // (sorted ascending), including leaves still waiting in the heap
+    let approximate = k > 0 && epsilon > 0.0;
+    let mut candidates: Vec<f64> = Vec::with_capacity(k + 1);
+
+    // GeometryCollections have one index entry per part; the distance is measured to the
+    // whole geometry, so only the first part reached needs to be queued
+    let mut queued_multipart: HashSet<&String> = HashSet::new();
+
     // Process the heap until we have K results or heap is empty
     while let Some(entry) = heap.pop() {
         // Early termination based on radius: if min distance exceeds radius, skip
@@ -368,6 +886,44 @@ pub fn knn_search(
             }
         }
 
+        // Approximate early termination: nothing left in the heap can improve the
+        // K-th candidate by more than a factor of (1 + epsilon)
+        if approximate
+            && candidates.len() >= k
+            && entry.min_distance() * (1.0 + epsilon) > candidates[k - 1]
+        {
+            // Collect the discovered leaves that are still in the heap
+            let needed = k.saturating_sub(results.len());
+            let mut pending_leaves = Vec::new();
+            for pending in std::iter::once(entry).chain(heap.drain()) {
+                if let QueueEntry::LeafEntry {
+                    min_distance,
+                    id,
+                    geometry,
+                } = pending
+                {
+                    if max_radius.is_none_or(|radius| min_distance <= radius) {
+                        pending_leaves.push((min_distance, id, geometry));
+                    }
+                }
+            }
+            pending_leaves.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+            // Leaves popped earlier are never farther than anything still pending
+            for (distance, id, geometry) in pending_leaves.into_iter().take(needed) {
+                results.push(KnnResult {
+                    item: build_item(id, geometry),
+                    distance,
+                });
+            }
+            results.sort_by(|a, b| {
+                a.distance
+                    .partial_cmp(&b.distance)
+                    .unwrap_or(Ordering::Equal)
+            });
+            results.truncate(k);
+            break;
+        }
+
         // Early termination: if we have K results and the next entry's
         // minimum distance is greater than our furthest result, we're done
         if k > 0 && results.len() >= k {
@@ -378,7 +934,11 @@ pub fn knn_search(
         }
 
         match entry {
-            QueueEntry::LeafEntry { min_distance, item } => {
+            QueueEntry::LeafEntry {
+                min_distance,
+                id,
+                geometry,
+            } => {
                 // This is an actual data item
                 // Skip if outside radius
                 if let Some(radius) = max_radius {
@@ -387,17 +947,15 @@ pub fn knn_search(
                     }
                 }
 
-                results.push(KnnResult {
-                    item,
-                    distance: min_distance,
-                });
-
-                // Keep results sorted by distance
-                results.sort_by(|a, b| {
-                    a.distance
-                        .partial_cmp(&b.distance)
-                        .unwrap_or(Ordering::Equal)
-                });
+                // Keep results sorted by distance (binary insertion, no full re-sort)
+                let pos = results.partition_point(|r| r.distance <= min_distance);
+                results.insert(
+                    pos,
+                    KnnResult {
+                        item: build_item(id, geometry),
+                        distance: min_distance,
+                    },
+                );
 
                 // Keep only K nearest (if k > 0)
                 if k > 0 && results.len() > k {
@@ -408,32 +966,57 @@ pub fn knn_search(
                 // Process all entries in this node
                 for entry in &node.entries {
                     match entry {
-                        Entry::Data { mbr: _, data } => {
-                            // This is a leaf entry - retrieve geometry and build GeoItem on demand
+                        Entry::Data { mbr, data } => {
+                            if region
+                                .as_ref()
+                                .is_some_and(|region| !region.may_contain(mbr))
+                            {
+                                continue;
+                            }
+                            // This is a leaf entry - retrieve geometry, GeoItem is built lazily
                             if let Some(geometry) = geometry_map.get(data) {
+                                if region
+                                    .as_ref()
+                                    .is_some_and(|region| !region.contains(geometry))
+                                {
+                                    continue;
+                                }
+                                if accept.is_some_and(|accept| !accept(data, geometry)) {
+                                    continue;
+                                }
+                                if is_multipart(geometry) && !queued_multipart.insert(data) {
+                                    continue;
+                                }
                                 let distance =
-                                    point_to_geometry_distance(query_lon, query_lat, geometry);
+                                    metric.point_to_geometry(query_lon, query_lat, geometry);
 
-                                // Build GeoItem on demand
-                                let item = GeoItem {
-                                    id: data.clone(),
-                                    geometry: geometry.clone(),
-                                    geojson: geojson_map.get(data).cloned().unwrap_or_default(),
-                                };
+                                if approximate && max_radius.is_none_or(|radius| distance <= radius)
+                                {
+                                    let pos = candidates.partition_point(|d| *d <= distance);
+                                    candidates.insert(pos, distance);
+                                    candidates.truncate(k);
+                                }
 
                                 heap.push(QueueEntry::LeafEntry {
                                     min_distance: distance,
-                                    item,
+                                    id: data,
+                                    geometry,
                                 });
                             }
                         }
                         Entry::Node { mbr, node } => {
+                            if region
+                                .as_ref()
+                                .is_some_and(|region| !region.may_contain(mbr))
+                            {
+                                continue;
+                            }
                             // This is an internal node - calculate distance to its MBR
-                            let distance = point_to_rectangle_distance(query_lon, query_lat, mbr);
+                            let distance = metric.point_to_rectangle(query_lon, query_lat, mbr);
 
                             heap.push(QueueEntry::InternalNode {
                                 min_distance: distance,
-                                node: (**node).clone(),
+                                node,
                             });
                         }
                     }
@@ -502,6 +1085,88 @@ mod tests {
         assert_eq!(distance, 0.0);
     }
 
+    #[test]
+    fn test_point_to_rectangle_distance_antimeridian() {
+        // Fiji: Levuka is ~10 km from islands east of the antimeridian, not ~360° away
+        let lau = Rectangle::new(-180.0, -18.0, -179.8, -16.0);
+        let distance = point_to_rectangle_distance(179.9, -17.0, &lau);
+        let expected = haversine_distance(179.9, -17.0, -180.0, -17.0);
+        assert!((distance - expected).abs() < 1.0);
+        assert!(distance < 11_000.0);
+
+        // Chukotka: west of the antimeridian looking back at Anadyr's side
+        let anadyr = Rectangle::new(176.0, 64.0, 179.0, 68.0);
+        let distance = point_to_rectangle_distance(-179.5, 66.0, &anadyr);
+        assert!(distance < 70_000.0, "distance = {}", distance);
+    }
+
+    #[test]
+    fn test_point_to_rectangle_distance_is_lower_bound() {
+        // Near the poles the closest point of a far-away box is poleward of the query
+        // (or over the pole entirely); clamping the latitude would overestimate it
+        let cases = [
+            (0.0, 80.0, Rectangle::new(90.0, 70.0, 100.0, 85.0)),
+            (0.0, 85.0, Rectangle::new(170.0, 80.0, 180.0, 89.0)),
+            (179.5, -75.0, Rectangle::new(-120.0, -89.0, -100.0, -80.0)),
+            (-179.9, 66.0, Rectangle::new(160.0, 50.0, 179.0, 60.0)),
+            (15.0, 15.0, Rectangle::new(0.0, 0.0, 10.0, 10.0)),
+        ];
+        for (lon, lat, rect) in cases {
+            let bound = point_to_rectangle_distance(lon, lat, &rect);
+            let mut nearest = f64::INFINITY;
+            for i in 0..=200 {
+                for j in 0..=200 {
+                    let x = rect.min[0] + (rect.max[0] - rect.min[0]) * i as f64 / 200.0;
+                    let y = rect.min[1] + (rect.max[1] - rect.min[1]) * j as f64 / 200.0;
+                    nearest = nearest.min(haversine_distance(lon, lat, x, y));
+                }
+            }
+            assert!(
+                bound <= nearest + 1e-6,
+                "({}, {}): {} > {}",
+                lon,
+                lat,
+                bound,
+                nearest
+            );
+            // The bound is tight, not just small
+            assert!(
+                bound >= nearest - 5_000.0,
+                "({}, {}): {} << {}",
+                lon,
+                lat,
+                bound,
+                nearest
+            );
+        }
+    }
+
+    #[test]
+    fn test_point_to_geometry_distance_antimeridian() {
+        // A coastline drawn just east of the antimeridian, queried from the west side
+        let coast = Geometry::LineString(geo::LineString::from(vec![
+            (-179.9, -17.0),
+            (-179.9, -16.0),
+        ]));
+        let distance = point_to_geometry_distance(179.9, -16.5, &coast);
+        let expected = haversine_distance(179.9, -16.5, -179.9, -16.5);
+        assert!((distance - expected).abs() < 1.0);
+
+        let island = Geometry::Polygon(geo::Polygon::new(
+            geo::LineString::from(vec![
+                (179.0, -17.0),
+                (179.95, -17.0),
+                (179.95, -16.0),
+                (179.0, -16.0),
+                (179.0, -17.0),
+            ]),
+            vec![],
+        ));
+        let distance = point_to_geometry_distance(-179.95, -16.5, &island);
+        let expected = haversine_distance(-179.95, -16.5, 179.95, -16.5);
+        assert!((distance - expected).abs() < 1.0);
+    }
+
     #[test]
     fn test_point_to_geometry_distance_point() {
         let geometry = Geometry::Point(geo::Point::new(116.4, 39.9));
@@ -941,4 +1606,473 @@ mod tests {
             "Should return empty when no items within radius"
         );
     }
+
+    #[test]
+    fn test_knn_search_approx() {
+        use crate::rtree::RTree;
+
+        let mut tree = RTree::new(4);
+        let mut all_items = Vec::new();
+
+        for x in 0..30 {
+            for y in 0..30 {
+                let id = format!("grid_{}_{}", x, y);
+                let lon = 116.0 + x as f64 * 0.01;
+                let lat = 39.0 + y as f64 * 0.01;
+                let geojson = format!(r#"{{"type":"Point","coordinates":[{},{}]}}"#, lon, lat);
+
+                tree.insert_geojson(id, &geojson);
+                all_items.push((lon, lat));
+            }
+        }
+
+        let query_lon = 116.123;
+        let query_lat = 39.157;
+        let k = 8;
+
+        let mut brute_force: Vec<f64> = all_items
+            .iter()
+            .map(|(lon, lat)| haversine_distance(query_lon, query_lat, *lon, *lat))
+            .collect();
+        brute_force.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        // epsilon = 0 is exact
+        let exact = knn_search_approx(
+            tree.get_root(),
+            query_lon,
+            query_lat,
+            k,
+            &tree.geometry_map,
+            &tree.geojson_map,
+            None,
+            0.0,
+        );
+        assert_eq!(exact.len(), k);
+        for i in 0..k {
+            assert!((exact[i].distance - brute_force[i]).abs() < 1e-6);
+        }
+
+        // Approximate results stay within the (1 + epsilon) bound
+        for epsilon in [0.1, 0.5, 2.0] {
+            let results = knn_search_approx(
+                tree.get_root(),
+                query_lon,
+                query_lat,
+                k,
+                &tree.geometry_map,
+                &tree.geojson_map,
+                None,
+                epsilon,
+            );
+
+            assert_eq!(results.len(), k);
+            for i in 0..k {
+                assert!(
+                    results[i].distance <= brute_force[i] * (1.0 + epsilon) + 1e-6,
+                    "epsilon={}: result {} at {}m exceeds bound of {}m",
+                    epsilon,
+                    i,
+                    results[i].distance,
+                    brute_force[i] * (1.0 + epsilon)
+                );
+            }
+            for i in 0..k - 1 {
+                assert!(results[i].distance <= results[i + 1].distance);
+            }
+        }
+
+        // Radius is still respected in approximate mode
+        let results = knn_search_approx(
+            tree.get_root(),
+            query_lon,
+            query_lat,
+            k,
+            &tree.geometry_map,
+            &tree.geojson_map,
+            Some(1000.0),
+            1.0,
+        );
+        assert!(results.iter().all(|r| r.distance <= 1000.0));
+    }
+
+    #[test]
+    fn test_knn_search_within() {
+        use crate::rtree::RTree;
+        use geo::{Coord, LineString, Polygon};
+
+        let mut tree = RTree::new(4);
+        for x in 0..30 {
+            for y in 0..30 {
+                let id = format!("grid_{}_{}", x, y);
+                let lon = 116.0 + x as f64 * 0.01;
+                let lat = 39.0 + y as f64 * 0.01;
+                let geojson = format!(r#"{{"type":"Point","coordinates":[{},{}]}}"#, lon, lat);
+                tree.insert_geojson(id, &geojson);
+            }
+        }
+
+        // Triangle away from the query point: the unconstrained nearest items all lie outside it
+        let region: Geometry = Polygon::new(
+            LineString::from(vec![
+                Coord {
+                    x: 116.155,
+                    y: 39.055,
+                },
+                Coord {
+                    x: 116.255,
+                    y: 39.055,
+                },
+                Coord {
+                    x: 116.155,
+                    y: 39.155,
+                },
+                Coord {
+                    x: 116.155,
+                    y: 39.055,
+                },
+            ]),
+            vec![],
+        )
+        .into();
+        let query_lon = 116.05;
+        let query_lat = 39.05;
+        let k = 5;
+
+        // Brute force: filter every item by the region, then sort by distance
+        let mut expected: Vec<(f64, String)> = tree
+            .geometry_map
+            .iter()
+            .filter(|&(_, geometry)| geometry.is_within(&region))
+            .map(|(id, geometry)| {
+                (
+                    point_to_geometry_distance(query_lon, query_lat, geometry),
+                    id.clone(),
+                )
+            })
+            .collect();
+        expected.sort_by(|a, b| a.0.total_cmp(&b.0));
+        assert!(expected.len() > k);
+
+        let results = knn_search_within(
+            tree.get_root(),
+            query_lon,
+            query_lat,
+            k,
+            &tree.geometry_map,
+            &tree.geojson_map,
+            None,
+            0.0,
+            &region,
+        );
+        assert_eq!(results.len(), k);
+        for (result, (distance, _)) in results.iter().zip(&expected) {
+            assert!(result.item.geometry.is_within(&region));
+            assert!((result.distance - distance).abs() < 1e-6);
+        }
+
+        // k = 0 with a radius returns everything inside both the region and the radius
+        let radius = expected[k].0;
+        let results = knn_search_within(
+            tree.get_root(),
+            query_lon,
+            query_lat,
+            0,
+            &tree.geometry_map,
+            &tree.geojson_map,
+            Some(radius),
+            0.0,
+            &region,
+        );
+        let inside_radius = expected.iter().filter(|(d, _)| *d <= radius).count();
+        assert_eq!(results.len(), inside_radius);
+
+        // A region that contains no items returns nothing
+        let empty_region: Geometry = Polygon::new(
+            LineString::from(vec![
+                Coord { x: 120.0, y: 10.0 },
+                Coord { x: 121.0, y: 10.0 },
+                Coord { x: 121.0, y: 11.0 },
+                Coord { x: 120.0, y: 10.0 },
+            ]),
+            vec![],
+        )
+        .into();
+        assert!(knn_search_within(
+            tree.get_root(),
+            query_lon,
+            query_lat,
+            k,
+            &tree.geometry_map,
+            &tree.geojson_map,
+            None,
+            0.0,
+            &empty_region,
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_knn_search_filtered() {
+        use crate::rtree::RTree;
+
+        let mut tree = RTree::new(4);
+        for i in 0..200 {
+            let id = format!("item_{}", i);
+            let geojson = format!(
+                r#"{{"type":"Point","coordinates":[{},{}]}}"#,
+                116.0 + i as f64 * 0.001,
+                39.0
+            );
+            tree.insert_geojson(id, &geojson);
+        }
+
+        // Only every 50th item is accepted: all of the unfiltered nearest items are rejected,
+        // so the search must keep expanding instead of post-filtering the first K
+        let accept = |id: &str, _: &Geometry| {
+            id.trim_start_matches("item_").parse::<usize>().unwrap() % 50 == 0
+        };
+        let results = knn_search_filtered(
+            tree.get_root(),
+            116.0,
+            39.0,
+            3,
+            &tree.geometry_map,
+            &tree.geojson_map,
+            None,
+            0.0,
+            None,
+            &accept,
+            DistanceMetric::Haversine,
+        );
+        let ids: Vec<&str> = results.iter().map(|r| r.item.id.as_str()).collect();
+        assert_eq!(ids, vec!["item_0", "item_50", "item_100"]);
+
+        // Approximate search only counts accepted items as candidates
+        let results = knn_search_filtered(
+            tree.get_root(),
+            116.0,
+            39.0,
+            3,
+            &tree.geometry_map,
+            &tree.geojson_map,
+            None,
+            0.5,
+            None,
+            &accept,
+            DistanceMetric::Haversine,
+        );
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|r| accept(&r.item.id, &r.item.geometry)));
+
+        // The radius still applies
+        let results = knn_search_filtered(
+            tree.get_root(),
+            116.0,
+            39.0,
+            0,
+            &tree.geometry_map,
+            &tree.geojson_map,
+            Some(2000.0),
+            0.0,
+            None,
+            &accept,
+            DistanceMetric::Haversine,
+        );
+        let ids: Vec<&str> = results.iter().map(|r| r.item.id.as_str()).collect();
+        assert_eq!(ids, vec!["item_0"]);
+    }
+
+    #[test]
+    fn test_distance_metrics() {
+        use crate::rtree::RTree;
+
+        assert_eq!(
+            DistanceMetric::parse("COSINES"),
+            Some(DistanceMetric::SphericalLawOfCosines)
+        );
+        assert_eq!(DistanceMetric::parse("manhattan"), None);
+        assert_eq!(DistanceMetric::default().name(), "haversine");
+
+        // The law of cosines agrees with Haversine
+        let haversine = haversine_distance(116.4074, 39.9042, 121.4737, 31.2304);
+        let cosines = spherical_law_of_cosines_distance(116.4074, 39.9042, 121.4737, 31.2304);
+        assert!((haversine - cosines).abs() < 1e-3);
+
+        // Euclidean distances are in coordinate units, and the MBR distance is a lower bound
+        let metric = DistanceMetric::Euclidean;
+        assert_eq!(metric.point_distance(0.0, 0.0, 3.0, 4.0), 5.0);
+        let rect = Rectangle::new(2.0, 2.0, 4.0, 4.0);
+        assert_eq!(metric.point_to_rectangle(3.0, 3.0, &rect), 0.0);
+        assert_eq!(metric.point_to_rectangle(0.0, 3.0, &rect), 2.0);
+        let square = geo::Rect::new(geo::Coord { x: 2.0, y: 2.0 }, geo::Coord { x: 4.0, y: 4.0 })
+            .to_polygon()
+            .into();
+        assert_eq!(metric.point_to_geometry(0.0, 3.0, &square), 2.0);
+        assert_eq!(metric.point_to_geometry(3.0, 3.0, &square), 0.0);
+
+        // At 60°N a degree of longitude is half as long as a degree of latitude, so the
+        // planar and great-circle nearest neighbors differ
+        let mut tree = RTree::new(4);
+        tree.insert_geojson(
+            "east".to_string(),
+            r#"{"type":"Point","coordinates":[1.5,60.0]}"#,
+        );
+        tree.insert_geojson(
+            "north".to_string(),
+            r#"{"type":"Point","coordinates":[0.0,61.2]}"#,
+        );
+        let nearest = |metric: DistanceMetric| {
+            let results = knn_search_filtered(
+                tree.get_root(),
+                0.0,
+                60.0,
+                1,
+                &tree.geometry_map,
+                &tree.geojson_map,
+                None,
+                0.0,
+                None,
+                &|_, _| true,
+                metric,
+            );
+            (results[0].item.id.clone(), results[0].distance)
+        };
+        assert_eq!(nearest(DistanceMetric::Haversine).0, "east");
+        assert_eq!(nearest(DistanceMetric::SphericalLawOfCosines).0, "east");
+        let (id, distance) = nearest(DistanceMetric::Euclidean);
+        assert_eq!(id, "north");
+        assert!((distance - 1.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_knn_search_antimeridian_and_poles() {
+        use crate::rtree::RTree;
+
+        let mut tree = RTree::new(4);
+        let mut points = Vec::new();
+        // Background grid so the tree has several levels
+        for i in 0..48 {
+            for j in 0..20 {
+                points.push((
+                    format!("grid_{}_{}", i, j),
+                    -180.0 + 7.5 * i as f64 + 3.0,
+                    -85.0 + 8.5 * j as f64 + 2.0,
+                ));
+            }
+        }
+        let landmarks = [
+            ("suva", 178.44, -18.14),
+            ("levuka", 178.83, -17.68),
+            ("lakeba", -178.80, -18.22),
+            ("vanua_balavu", -178.95, -17.23),
+            ("anadyr", 177.50, 64.73),
+            ("egvekinot", 179.12, 66.32),
+            ("lavrentiya", -171.00, 65.58),
+            ("uelen", -169.80, 66.16),
+            ("north_a", 180.0, 89.9),
+            ("north_b", 0.0, 89.5),
+            ("north_c", -90.0, 89.7),
+            ("south_a", 120.0, -89.8),
+            ("south_b", -60.0, -89.6),
+        ];
+        for (id, lon, lat) in landmarks {
+            points.push((id.to_string(), lon, lat));
+        }
+        for (id, lon, lat) in &points {
+            let geojson = format!(r#"{{"type":"Point","coordinates":[{},{}]}}"#, lon, lat);
+            assert!(tree.insert_geojson(id.clone(), &geojson));
+        }
+
+        let queries = [
+            (179.99, -17.5),
+            (-179.99, -17.5),
+            (180.0, -18.0),
+            (-180.0, 65.0),
+            (-179.5, 66.0),
+            (179.5, 66.0),
+            (0.0, 89.9),
+            (45.0, 89.95),
+            (-150.0, -89.9),
+        ];
+        for (lon, lat) in queries {
+            let mut expected: Vec<(f64, &String)> = points
+                .iter()
+                .map(|(id, x, y)| (haversine_distance(lon, lat, *x, *y), id))
+                .collect();
+            expected.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            let results = tree.nearby(lon, lat, 4, None);
+            let got: Vec<&String> = results.iter().map(|(item, _)| &item.id).collect();
+            let want: Vec<&String> = expected.iter().take(4).map(|(_, id)| *id).collect();
+            assert_eq!(got, want, "query ({}, {})", lon, lat);
+            for ((_, distance), (expected, _)) in results.iter().zip(&expected) {
+                assert!((distance - expected).abs() < 1e-6);
+            }
+
+            // A radius just past the 4th neighbour returns the same items
+            let radius = expected[3].0 + 1.0;
+            let within = tree.nearby(lon, lat, 0, Some(radius));
+            assert_eq!(within.len(), 4, "query ({}, {})", lon, lat);
+        }
+
+        // Fiji: the nearest islands are across the antimeridian
+        let results = tree.nearby(179.99, -17.5, 2, None);
+        assert_eq!(results[0].0.id, "vanua_balavu");
+        // Near the north pole, a point "on the other side" at 180° is the closest
+        let results = tree.nearby(0.0, 89.9, 1, None);
+        assert_eq!(results[0].0.id, "north_a");
+    }
+
+    #[test]
+    fn test_closest_point_on_line() {
+        let line = Geometry::LineString(geo::LineString::from(vec![(0.0, 0.0), (10.0, 0.0)]));
+        let (point, distance) = closest_point_on_line(5.0, 3.0, &line).unwrap();
+        assert_eq!(point, geo::Point::new(5.0, 0.0));
+        assert!((distance - haversine_distance(5.0, 3.0, 5.0, 0.0)).abs() < 1e-6);
+
+        let (point, distance) = closest_point_on_line(2.0, 0.0, &line).unwrap();
+        assert_eq!(point, geo::Point::new(2.0, 0.0));
+        assert_eq!(distance, 0.0);
+
+        let point = Geometry::Point(geo::Point::new(5.0, 3.0));
+        assert!(closest_point_on_line(5.0, 3.0, &point).is_none());
+    }
+
+    #[test]
+    fn test_snap_search_skips_non_lines() {
+        use crate::rtree::RTree;
+
+        let mut tree = RTree::new(4);
+        let features = [
+            // A point right on top of the query must not win
+            ("poi", r#"{"type":"Point","coordinates":[5.0,1.0]}"#),
+            (
+                "far_road",
+                r#"{"type":"LineString","coordinates":[[0.0,5.0],[10.0,5.0]]}"#,
+            ),
+            (
+                "near_road",
+                r#"{"type":"LineString","coordinates":[[0.0,0.0],[10.0,0.0]]}"#,
+            ),
+            (
+                "ring",
+                r#"{"type":"MultiLineString","coordinates":[[[20.0,0.0],[30.0,0.0]]]}"#,
+            ),
+        ];
+        for (id, geojson) in features {
+            assert!(tree.insert_geojson(id.to_string(), geojson));
+        }
+
+        let result = snap_search(tree.get_root(), 5.0, 1.0, &tree.geometry_map).unwrap();
+        assert_eq!(result.id, "near_road");
+        assert_eq!(result.point, geo::Point::new(5.0, 0.0));
+        assert!((result.distance - haversine_distance(5.0, 1.0, 5.0, 0.0)).abs() < 1e-6);
+
+        let result = snap_search(tree.get_root(), 26.0, 2.0, &tree.geometry_map).unwrap();
+        assert_eq!(result.id, "ring");
+        assert_eq!(result.point, geo::Point::new(26.0, 0.0));
+
+        let empty = RTree::new(4);
+        assert!(snap_search(empty.get_root(), 0.0, 0.0, &empty.geometry_map).is_none());
+    }
 }
@@ -0,0 +1,159 @@
+use std::collections::{BTreeSet, HashMap};
+use std::mem::size_of;
+
+use geo::{CoordsIter, Geometry};
+
+use super::super::node::{Entry, Node};
+use super::super::rtree::RTree;
+
+/// R-tree 的堆内存占用估算（字节），按组成部分拆分
+///
+/// 按容器容量和元素大小计算，不包含分配器自身的元数据和碎片，
+/// 用于定位哪一部分在增长，而不是精确计量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// 树节点、条目和条目中的对象 ID，以及游标分页用的对象版本号
+    pub index_bytes: usize,
+    /// geometry_map：解析后的几何体坐标
+    pub geometry_bytes: usize,
+    /// geojson_map：原始 GeoJSON 字符串，以及 STRING 对象的文本
+    pub geojson_bytes: usize,
+    /// 标签和标签倒排索引
+    pub tag_bytes: usize,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> usize {
+        self.index_bytes + self.geometry_bytes + self.geojson_bytes + self.tag_bytes
+    }
+}
+
+impl std::ops::AddAssign for MemoryUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.index_bytes += other.index_bytes;
+        self.geometry_bytes += other.geometry_bytes;
+        self.geojson_bytes += other.geojson_bytes;
+        self.tag_bytes += other.tag_bytes;
+    }
+}
+
+/// 内存占用估算
+impl RTree {
+    /// 估算树占用的堆内存，耗时与对象数量成正比
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let index_bytes = self
+            .get_root()
+            .map_or(0, |root| size_of::<Node>() + node_bytes(root))
+            + map_bytes(&self.versions)
+            + self.versions.keys().map(String::capacity).sum::<usize>();
+
+        let geometry_bytes = map_bytes(&self.geometry_map)
+            + self
+                .geometry_map
+                .iter()
+                .map(|(id, geometry)| id.capacity() + geometry_bytes(geometry))
+                .sum::<usize>();
+
+        let geojson_bytes = map_bytes(&self.geojson_map)
+            + map_bytes(&self.strings)
+            + self
+                .geojson_map
+                .iter()
+                .chain(&self.strings)
+                .map(|(id, text)| id.capacity() + text.capacity())
+                .sum::<usize>();
+
+        let tag_bytes = tag_map_bytes(&self.tags) + tag_map_bytes(&self.tag_index);
+
+        MemoryUsage {
+            index_bytes,
+            geometry_bytes,
+            geojson_bytes,
+            tag_bytes,
+        }
+    }
+}
+
+/// 节点的条目数组及其子树（不含节点自身，节点自身算在父条目或根的 Box 中）
+fn node_bytes(node: &Node) -> usize {
+    let entries = node.entries.capacity() * size_of::<Entry>();
+    entries
+        + node
+            .entries
+            .iter()
+            .map(|entry| match entry {
+                Entry::Data { data, .. } => data.capacity(),
+                Entry::Node { node, .. } => size_of::<Node>() + node_bytes(node),
+            })
+            .sum::<usize>()
+}
+
+/// 坐标数组；多环、多部件几何体每个部件的 Vec 头部忽略不计
+fn geometry_bytes(geometry: &Geometry) -> usize {
+    geometry.coords_count() * size_of::<geo::Coord>()
+}
+
+/// HashMap 的槽位数组（每个槽位一个控制字节）
+fn map_bytes<K, V>(map: &HashMap<K, V>) -> usize {
+    map.capacity() * (size_of::<K>() + size_of::<V>() + 1)
+}
+
+/// BTreeSet 按元素计算，节点内的空闲槽位忽略不计
+fn tag_map_bytes(map: &HashMap<String, BTreeSet<String>>) -> usize {
+    map_bytes(map)
+        + map
+            .iter()
+            .map(|(key, set)| {
+                key.capacity()
+                    + set
+                        .iter()
+                        .map(|value| size_of::<String>() + value.capacity())
+                        .sum::<usize>()
+            })
+            .sum::<usize>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_usage_grows_with_data() {
+        let mut tree = RTree::new(4);
+        assert_eq!(tree.memory_usage().total(), 0);
+
+        for i in 0..50 {
+            tree.insert_geojson(
+                format!("point_{}", i),
+                &format!(r#"{{"type":"Point","coordinates":[{},{}]}}"#, i, i),
+            );
+        }
+        let points = tree.memory_usage();
+        assert!(points.index_bytes > 0);
+        assert!(points.geometry_bytes >= 50 * size_of::<geo::Coord>());
+        assert!(points.geojson_bytes >= 50 * r#"{"type":"Point","coordinates":[0,0]}"#.len());
+        assert_eq!(points.tag_bytes, 0);
+
+        // 多顶点多边形的坐标计入 geometry_bytes
+        let ring: Vec<String> = (0..=100)
+            .map(|i| {
+                let angle = std::f64::consts::TAU * (i % 100) as f64 / 100.0;
+                format!("[{},{}]", angle.cos(), angle.sin())
+            })
+            .collect();
+        tree.insert_geojson(
+            "polygon".to_string(),
+            &format!(
+                r#"{{"type":"Polygon","coordinates":[[{}]]}}"#,
+                ring.join(",")
+            ),
+        );
+        tree.set_tags("polygon", ["zone".to_string()]);
+        let with_polygon = tree.memory_usage();
+        assert!(
+            with_polygon.geometry_bytes >= points.geometry_bytes + 101 * size_of::<geo::Coord>()
+        );
+        assert!(with_polygon.tag_bytes > 0);
+        assert!(with_polygon.total() > points.total());
+    }
+}
@@ -1,24 +1,47 @@
 // R-tree算法模块
 //
-// 这个模块包含R-tree的所有核心算法实现，按功能分解为不同的子模块：
+// 这个模块包含R-tree基于几何体的算法实现，按功能分解为不同的子模块；
+// 只按边界框的插入、搜索、删除和节点分裂在 spatio-rtree 的 RTreeIndex 中：
 // - search: 搜索和查询算法
 // - insert: 插入和树构建算法
-// - split: 节点分裂算法
+// - bulk_load: STR 批量装载
 // - delete: 删除和树维护算法
 // - knn: K-最近邻搜索算法
+// - filter: KNN 查询的对象过滤条件（几何类型、属性范围）
+// - aggregate: 网格/六边形分箱聚合
+// - hull: 凸包/凹包等外包几何
+// - cluster: DBSCAN 密度聚类
+// - overlay: 多边形叠加运算（交集/并集/差集）
+// - tags: 对象标签与标签倒排索引
+// - expire: 对象过期时间（TTL）
+// - strings: STRING 对象（不进入空间索引的文本值）
+// - cursor: 按 ID 的游标分页（SCAN / INTERSECTS CURSOR）
+// - memory: 内存占用估算
 // - utils: 共用的工具函数
 // - debug: 调试和可视化工具
+// - validate: 结构自检（DEBUG VALIDATE）
 // - persistence: 持久化和序列化功能（RDB 快照）
 // - aof: AOF (Append-Only File) 持久化功能
 // - concurrent: 并发安全的R-tree实现（使用 std::sync）
 // - async_concurrent: 异步并发安全的R-tree实现（使用 tokio::sync）
 
+pub mod aggregate;
 pub mod aof;
+pub mod bulk_load;
+pub mod cluster;
+pub mod cursor;
 pub mod debug;
 pub mod delete;
+pub mod expire;
+pub mod filter;
+pub mod hull;
 pub mod insert;
 pub mod knn;
+pub mod memory;
+pub mod overlay;
 pub mod persistence;
 pub mod search;
-pub mod split;
+pub mod strings;
+pub mod tags;
 pub mod utils;
+pub mod validate;
@@ -0,0 +1,152 @@
+use super::super::rtree::RTree;
+use geo::{BooleanOps, Geometry, MultiPolygon};
+
+/// 多边形叠加运算类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlayOp {
+    /// 交集
+    Intersection,
+    /// 并集
+    Union,
+    /// 差集（第一个对象减去第二个对象）
+    Difference,
+    /// 对称差
+    Xor,
+}
+
+impl OverlayOp {
+    /// 从命令参数解析运算类型（不区分大小写）
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_uppercase().as_str() {
+            "INTERSECTION" => Some(OverlayOp::Intersection),
+            "UNION" => Some(OverlayOp::Union),
+            "DIFFERENCE" => Some(OverlayOp::Difference),
+            "XOR" => Some(OverlayOp::Xor),
+            _ => None,
+        }
+    }
+
+    /// 对两个多边形集合执行运算
+    pub fn apply(&self, a: &MultiPolygon, b: &MultiPolygon) -> MultiPolygon {
+        match self {
+            OverlayOp::Intersection => a.intersection(b),
+            OverlayOp::Union => a.union(b),
+            OverlayOp::Difference => a.difference(b),
+            OverlayOp::Xor => a.xor(b),
+        }
+    }
+}
+
+/// 将面状几何体转换为 MultiPolygon，非面状几何体返回 None
+fn to_multi_polygon(geometry: &Geometry) -> Option<MultiPolygon> {
+    match geometry {
+        Geometry::Polygon(poly) => Some(MultiPolygon::new(vec![poly.clone()])),
+        Geometry::MultiPolygon(mp) => Some(mp.clone()),
+        Geometry::Rect(rect) => Some(MultiPolygon::new(vec![rect.to_polygon()])),
+        Geometry::Triangle(triangle) => Some(MultiPolygon::new(vec![triangle.to_polygon()])),
+        _ => None,
+    }
+}
+
+/// 多边形叠加运算
+impl RTree {
+    /// 计算两个已存储对象之间的交集/并集/差集/对称差
+    ///
+    /// 任一对象不存在时返回 `Ok(None)`；对象不是面状几何体时返回错误。
+    /// 结果只有一个多边形时返回 Polygon，否则返回 MultiPolygon（可能为空）
+    pub fn overlay(
+        &self,
+        first_id: &str,
+        second_id: &str,
+        op: OverlayOp,
+    ) -> std::result::Result<Option<Geometry>, String> {
+        let (Some(first), Some(second)) =
+            (self.get_geometry(first_id), self.get_geometry(second_id))
+        else {
+            return Ok(None);
+        };
+
+        let polygons = |id: &str, geometry: &Geometry| {
+            to_multi_polygon(geometry)
+                .ok_or_else(|| format!("object '{}' is not a Polygon or MultiPolygon", id))
+        };
+        let first = polygons(first_id, first)?;
+        let second = polygons(second_id, second)?;
+
+        let mut result = op.apply(&first, &second);
+        if result.0.len() == 1 {
+            return Ok(Some(Geometry::Polygon(result.0.remove(0))));
+        }
+        Ok(Some(Geometry::MultiPolygon(result)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::Area;
+    use serde_json::json;
+
+    fn square(x: f64, y: f64, size: f64) -> String {
+        json!({
+            "type": "Polygon",
+            "coordinates": [[
+                [x, y], [x + size, y], [x + size, y + size], [x, y + size], [x, y]
+            ]]
+        })
+        .to_string()
+    }
+
+    fn area(geometry: Option<Geometry>) -> f64 {
+        geometry.unwrap().unsigned_area()
+    }
+
+    #[test]
+    fn test_overlay_ops() {
+        let mut tree = RTree::new(4);
+        assert!(tree.insert_geojson("a".to_string(), &square(0.0, 0.0, 2.0)));
+        assert!(tree.insert_geojson("b".to_string(), &square(1.0, 1.0, 2.0)));
+
+        let intersection = tree.overlay("a", "b", OverlayOp::Intersection).unwrap();
+        assert!(matches!(intersection, Some(Geometry::Polygon(_))));
+        assert!((area(intersection) - 1.0).abs() < 1e-9);
+
+        let union = tree.overlay("a", "b", OverlayOp::Union).unwrap();
+        assert!((area(union) - 7.0).abs() < 1e-9);
+
+        let difference = tree.overlay("a", "b", OverlayOp::Difference).unwrap();
+        assert!((area(difference) - 3.0).abs() < 1e-9);
+
+        let xor = tree.overlay("a", "b", OverlayOp::Xor).unwrap();
+        assert!((area(xor) - 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_overlay_disjoint_missing_and_invalid() {
+        let mut tree = RTree::new(4);
+        assert!(tree.insert_geojson("a".to_string(), &square(0.0, 0.0, 1.0)));
+        assert!(tree.insert_geojson("far".to_string(), &square(5.0, 5.0, 1.0)));
+        let point = json!({"type": "Point", "coordinates": [0.5, 0.5]}).to_string();
+        assert!(tree.insert_geojson("p".to_string(), &point));
+
+        // 不相交时交集为空的 MultiPolygon，并集保留两个多边形
+        assert_eq!(
+            tree.overlay("a", "far", OverlayOp::Intersection).unwrap(),
+            Some(Geometry::MultiPolygon(MultiPolygon::new(vec![])))
+        );
+        let Some(Geometry::MultiPolygon(union)) =
+            tree.overlay("a", "far", OverlayOp::Union).unwrap()
+        else {
+            panic!("expected a multipolygon");
+        };
+        assert_eq!(union.0.len(), 2);
+
+        assert_eq!(tree.overlay("a", "missing", OverlayOp::Union), Ok(None));
+        assert!(tree
+            .overlay("a", "p", OverlayOp::Union)
+            .unwrap_err()
+            .contains("'p'"));
+        assert_eq!(OverlayOp::parse("difference"), Some(OverlayOp::Difference));
+        assert_eq!(OverlayOp::parse("buffer"), None);
+    }
+}
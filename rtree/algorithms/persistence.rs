@@ -1,6 +1,8 @@
 use crate::rtree::RTree;
+use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
 /// 持久化错误类型
 #[derive(Debug, thiserror::Error)]
@@ -15,6 +17,153 @@ pub enum PersistenceError {
     InvalidFormat,
 }
 
+/// 分块快照的文件头标识
+const CHUNKED_MAGIC: &[u8; 4] = b"SPCK";
+
+/// 分块快照格式版本（2：对象记录增加过期时间；3：增加 STRING 对象）
+pub(crate) const CHUNKED_VERSION: u32 = 3;
+
+/// 分块快照默认每块的对象数量
+pub const DEFAULT_CHUNK_SIZE: usize = 1024;
+
+/// 分块快照的文件头
+#[derive(Debug, Serialize, Deserialize)]
+struct ChunkedHeader {
+    version: u32,
+    max_entries: usize,
+    applied_seq: u64,
+    /// 对象总数，加载时用于校验文件是否完整
+    count: usize,
+}
+
+/// 分块快照中的一个对象，数据库快照（`storage::snapshot`）和 AOF 重写使用同样的记录
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ChunkedRecord {
+    pub(crate) id: String,
+    /// 几何体的 GeoJSON，STRING 对象为文本
+    pub(crate) geojson: String,
+    #[serde(default)]
+    pub(crate) tags: Vec<String>,
+    /// 过期时刻（Unix 纳秒），没有 TTL 时为 None
+    pub(crate) expires_at: Option<u64>,
+    /// 是否为 STRING 对象
+    pub(crate) text: bool,
+}
+
+/// 版本 2 快照中的对象，没有 STRING 对象
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ChunkedRecordV2 {
+    id: String,
+    geojson: String,
+    tags: Vec<String>,
+    expires_at: Option<u64>,
+}
+
+impl From<ChunkedRecordV2> for ChunkedRecord {
+    fn from(record: ChunkedRecordV2) -> Self {
+        ChunkedRecord {
+            id: record.id,
+            geojson: record.geojson,
+            tags: record.tags,
+            expires_at: record.expires_at,
+            text: false,
+        }
+    }
+}
+
+/// 版本 1 快照中的对象，没有过期时间
+#[derive(Debug, Serialize, Deserialize)]
+struct ChunkedRecordV1 {
+    id: String,
+    geojson: String,
+    tags: Vec<String>,
+}
+
+/// 按格式版本解码一块对象记录，`version` 为 [`CHUNKED_VERSION`] 或更早的版本
+pub(crate) fn decode_records(
+    version: u32,
+    data: &[u8],
+) -> Result<Vec<ChunkedRecord>, PersistenceError> {
+    Ok(match version {
+        1 => {
+            let chunk: Vec<ChunkedRecordV1> = bincode::deserialize(data)?;
+            chunk
+                .into_iter()
+                .map(|record| ChunkedRecord {
+                    id: record.id,
+                    geojson: record.geojson,
+                    tags: record.tags,
+                    expires_at: None,
+                    text: false,
+                })
+                .collect()
+        }
+        2 => {
+            let chunk: Vec<ChunkedRecordV2> = bincode::deserialize(data)?;
+            chunk.into_iter().map(ChunkedRecord::from).collect()
+        }
+        _ => bincode::deserialize(data)?,
+    })
+}
+
+/// 与 `path` 同目录的临时文件路径，写完后重命名，保证原子性
+///
+/// `fleet.json` -> `fleet.json.tmp`，没有扩展名时 `fleet` -> `fleet.tmp`
+pub(crate) fn temp_path(path: &Path) -> PathBuf {
+    match path.extension() {
+        Some(ext) => path.with_extension(format!("{}.tmp", ext.to_string_lossy())),
+        None => path.with_extension("tmp"),
+    }
+}
+
+/// 将文件所在目录的元数据（新建、重命名）刷到磁盘，用于原子重命名之后
+///
+/// Unix 上重命名只有在目录 fsync 之后才能在断电后保留；Windows 无法以文件方式打开目录，
+/// NTFS 的元数据由文件系统日志保证，因此是空操作。文件本身的 `sync_all` 在 Windows 上
+/// 对应 `FlushFileBuffers`，不需要额外处理
+pub fn sync_parent_dir(path: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        fs::File::open(parent)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// 写入一帧：4 字节小端长度 + 数据
+pub(crate) async fn write_frame<W: AsyncWriteExt + Unpin>(
+    writer: &mut W,
+    data: &[u8],
+) -> Result<(), PersistenceError> {
+    let len = u32::try_from(data.len()).map_err(|_| PersistenceError::InvalidFormat)?;
+    writer.write_all(&len.to_le_bytes()).await?;
+    writer.write_all(data).await?;
+    Ok(())
+}
+
+/// 读取一帧，文件正好结束时返回 None
+pub(crate) async fn read_frame<R: AsyncReadExt + Unpin>(
+    reader: &mut R,
+) -> Result<Option<Vec<u8>>, PersistenceError> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let mut data = vec![0u8; u32::from_le_bytes(len) as usize];
+    reader
+        .read_exact(&mut data)
+        .await
+        .map_err(|_| PersistenceError::InvalidFormat)?;
+    Ok(Some(data))
+}
+
 /// 序列化格式枚举
 #[derive(Debug, Clone, Copy)]
 pub enum SerializationFormat {
@@ -67,10 +216,7 @@ impl RTree {
         let path = path.as_ref();
 
         // 创建临时文件路径，确保原子性写入
-        let temp_path = path.with_extension(format!(
-            "{}.tmp",
-            path.extension().unwrap_or_default().to_string_lossy()
-        ));
+        let temp_path = temp_path(path);
 
         // 序列化数据
         let data = match format {
@@ -78,11 +224,15 @@ impl RTree {
             SerializationFormat::Binary => bincode::serialize(self)?,
         };
 
-        // 写入临时文件
-        fs::write(&temp_path, data)?;
+        // 写入临时文件并刷盘，重命名之后不会出现内容不完整的文件
+        let mut file = fs::File::create(&temp_path)?;
+        std::io::Write::write_all(&mut file, &data)?;
+        file.sync_all()?;
+        drop(file);
 
-        // 原子性重命名
+        // 原子性重命名（Windows 上同样会替换已存在的目标文件）
         fs::rename(temp_path, path)?;
+        sync_parent_dir(path)?;
 
         Ok(())
     }
@@ -113,6 +263,184 @@ impl RTree {
     }
 }
 
+/// 异步持久化：文件读写使用 tokio::fs，不阻塞运行时的工作线程
+impl RTree {
+    /// 异步导出到文件，格式规则与 [`dump_to_file`](Self::dump_to_file) 相同
+    ///
+    /// 序列化仍在当前任务中一次完成，大树请使用 [`dump_chunked`](Self::dump_chunked)
+    pub async fn dump_to_file_async<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Result<(), PersistenceError> {
+        let path = path.as_ref();
+        let data = match SerializationFormat::from_extension(path) {
+            SerializationFormat::Json => serde_json::to_vec_pretty(self)?,
+            SerializationFormat::Binary => bincode::serialize(self)?,
+        };
+
+        let temp_path = temp_path(path);
+        let mut file = tokio::fs::File::create(&temp_path).await?;
+        file.write_all(&data).await?;
+        file.sync_all().await?;
+        drop(file);
+
+        tokio::fs::rename(temp_path, path).await?;
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || sync_parent_dir(&path))
+            .await
+            .map_err(std::io::Error::other)??;
+        Ok(())
+    }
+
+    /// 异步从文件加载，反序列化在阻塞线程池中进行
+    pub async fn load_from_file_async<P: AsRef<Path>>(path: P) -> Result<RTree, PersistenceError> {
+        let format = SerializationFormat::from_extension(&path);
+        let data = tokio::fs::read(path).await?;
+
+        tokio::task::spawn_blocking(move || -> Result<RTree, PersistenceError> {
+            Ok(match format {
+                SerializationFormat::Json => serde_json::from_slice(&data)?,
+                SerializationFormat::Binary => bincode::deserialize(&data)?,
+            })
+        })
+        .await
+        .map_err(std::io::Error::other)?
+    }
+
+    /// 分块导出快照，返回写入的对象数量
+    ///
+    /// 文件由文件头和若干块组成，每块包含最多 `chunk_size` 个对象（ID、GeoJSON、标签），
+    /// 每写完一块让出一次执行权，大树快照期间运行时上的其他任务仍能继续执行。
+    /// 只保存对象数据，加载时重建树结构
+    pub async fn dump_chunked<P: AsRef<Path>>(
+        &self,
+        path: P,
+        chunk_size: usize,
+    ) -> Result<usize, PersistenceError> {
+        let path = path.as_ref();
+        let chunk_size = chunk_size.max(1);
+        let temp_path = temp_path(path);
+        let mut writer = tokio::io::BufWriter::new(tokio::fs::File::create(&temp_path).await?);
+
+        let header = ChunkedHeader {
+            version: CHUNKED_VERSION,
+            max_entries: self.max_entries(),
+            applied_seq: self.applied_seq(),
+            count: self.geojson_map.len() + self.strings.len(),
+        };
+        writer.write_all(CHUNKED_MAGIC).await?;
+        write_frame(&mut writer, &bincode::serialize(&header)?).await?;
+
+        let mut chunk = Vec::with_capacity(chunk_size.min(header.count));
+        let objects = self
+            .geojson_map
+            .iter()
+            .map(|(id, geojson)| (id, geojson, false))
+            .chain(self.strings.iter().map(|(id, text)| (id, text, true)));
+        for (id, value, text) in objects {
+            chunk.push(self.chunked_record(id, value, text));
+            if chunk.len() == chunk_size {
+                write_frame(&mut writer, &bincode::serialize(&chunk)?).await?;
+                chunk.clear();
+                tokio::task::yield_now().await;
+            }
+        }
+        if !chunk.is_empty() {
+            write_frame(&mut writer, &bincode::serialize(&chunk)?).await?;
+        }
+
+        writer.flush().await?;
+        writer.into_inner().sync_all().await?;
+        tokio::fs::rename(temp_path, path).await?;
+        let dir_path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || sync_parent_dir(&dir_path))
+            .await
+            .map_err(std::io::Error::other)??;
+        Ok(header.count)
+    }
+
+    /// 加载 [`dump_chunked`](Self::dump_chunked) 写入的快照，逐块读入对象，每块之后让出一次执行权，
+    /// 最后用 [`bulk_load`](Self::bulk_load) 建立索引
+    ///
+    /// 文件不完整（对象数量与文件头不符）或格式不正确时返回 `InvalidFormat`
+    pub async fn load_chunked<P: AsRef<Path>>(path: P) -> Result<RTree, PersistenceError> {
+        let file = tokio::fs::File::open(path).await?;
+        let mut reader = tokio::io::BufReader::new(file);
+
+        let mut magic = [0u8; 4];
+        reader
+            .read_exact(&mut magic)
+            .await
+            .map_err(|_| PersistenceError::InvalidFormat)?;
+        if &magic != CHUNKED_MAGIC {
+            return Err(PersistenceError::InvalidFormat);
+        }
+        let header: ChunkedHeader = match read_frame(&mut reader).await? {
+            Some(data) => bincode::deserialize(&data)?,
+            None => return Err(PersistenceError::InvalidFormat),
+        };
+        if !(1..=CHUNKED_VERSION).contains(&header.version) || header.max_entries < 2 {
+            return Err(PersistenceError::InvalidFormat);
+        }
+
+        // 先读入所有对象，最后一次性批量装载索引
+        let mut rtree = RTree::new(header.max_entries);
+        rtree.defer_indexing();
+        let mut loaded = 0;
+        while let Some(data) = read_frame(&mut reader).await? {
+            for record in decode_records(header.version, &data)? {
+                rtree.insert_record(record)?;
+                loaded += 1;
+            }
+            tokio::task::yield_now().await;
+        }
+
+        if loaded != header.count {
+            return Err(PersistenceError::InvalidFormat);
+        }
+        rtree.rebuild_index();
+        rtree.mark_applied(header.applied_seq);
+        Ok(rtree)
+    }
+
+    /// 所有对象的快照记录，调用方持有读锁期间复制，之后可以在锁外序列化
+    pub(crate) fn chunked_records(&self) -> Vec<ChunkedRecord> {
+        self.geojson_map
+            .iter()
+            .map(|(id, geojson)| self.chunked_record(id, geojson, false))
+            .chain(
+                self.strings
+                    .iter()
+                    .map(|(id, text)| self.chunked_record(id, text, true)),
+            )
+            .collect()
+    }
+
+    fn chunked_record(&self, id: &str, value: &str, text: bool) -> ChunkedRecord {
+        ChunkedRecord {
+            id: id.to_string(),
+            geojson: value.to_string(),
+            tags: self.get_tags(id),
+            expires_at: self.expires_at(id),
+            text,
+        }
+    }
+
+    /// 插入一条快照记录（几何体或文本、标签和过期时间），GeoJSON 无效时返回 `InvalidFormat`
+    pub(crate) fn insert_record(&mut self, record: ChunkedRecord) -> Result<(), PersistenceError> {
+        if record.text {
+            self.set_string(record.id.clone(), record.geojson);
+        } else if !self.insert_geojson(record.id.clone(), &record.geojson) {
+            return Err(PersistenceError::InvalidFormat);
+        }
+        self.set_tags(&record.id, record.tags);
+        if let Some(at) = record.expires_at {
+            self.set_expire(&record.id, at);
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -255,4 +583,217 @@ mod tests {
         assert!(json_size > 0);
         assert!(bin_size > 0);
     }
+
+    fn tagged_tree(size: usize) -> RTree {
+        let mut rtree = RTree::new(4);
+        for i in 0..size {
+            let point = serde_json::json!({
+                "type": "Point",
+                "coordinates": [i as f64 * 0.01, 1.0]
+            });
+            rtree.insert_geojson(format!("p{}", i), &point.to_string());
+        }
+        rtree.set_tags("p3", vec!["bus".to_string()]);
+        rtree.set_expire("p5", 1_000);
+        rtree.mark_applied(42);
+        rtree
+    }
+
+    #[tokio::test]
+    async fn test_async_persistence_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let original = tagged_tree(10);
+
+        for name in ["async.json", "async.bin"] {
+            let path = temp_dir.path().join(name);
+            original.dump_to_file_async(&path).await.unwrap();
+            let loaded = RTree::load_from_file_async(&path).await.unwrap();
+            assert_eq!(loaded.count(), 10);
+            assert_eq!(loaded.applied_seq(), 42);
+            assert_eq!(loaded.get_tags("p3"), vec!["bus"]);
+            assert_eq!(loaded.expires_at("p5"), Some(1_000));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chunked_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("chunked.snap");
+        let original = tagged_tree(25);
+
+        // 25 个对象分成 4 块
+        assert_eq!(original.dump_chunked(&path, 8).await.unwrap(), 25);
+        assert!(!temp_dir.path().join("chunked.snap.tmp").exists());
+
+        let loaded = RTree::load_chunked(&path).await.unwrap();
+        assert_eq!(loaded.count(), 25);
+        assert_eq!(loaded.max_entries(), 4);
+        assert_eq!(loaded.applied_seq(), 42);
+        assert_eq!(loaded.get_tags("p3"), vec!["bus"]);
+        assert_eq!(loaded.expires_at("p5"), Some(1_000));
+        assert_eq!(loaded.get_geojson("p7"), original.get_geojson("p7"));
+
+        let search_rect = Rectangle::new(-1.0, 0.0, 0.1, 2.0);
+        let mut expected = original.search_bbox(&search_rect);
+        let mut found = loaded.search_bbox(&search_rect);
+        expected.sort();
+        found.sort();
+        assert_eq!(found, expected);
+    }
+
+    #[tokio::test]
+    async fn test_string_objects_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut original = tagged_tree(3);
+        original.set_string("note".to_string(), "hello".to_string());
+        original.set_tags("note", vec!["memo".to_string()]);
+        original.set_expire("note", 2_000);
+
+        let path = temp_dir.path().join("strings.snap");
+        assert_eq!(original.dump_chunked(&path, 2).await.unwrap(), 4);
+        let bin_path = temp_dir.path().join("strings.bin");
+        original.dump_to_file_async(&bin_path).await.unwrap();
+        for loaded in [
+            RTree::load_chunked(&path).await.unwrap(),
+            RTree::load_from_file_async(&bin_path).await.unwrap(),
+        ] {
+            assert_eq!(loaded.count(), 4);
+            assert_eq!(loaded.get_string("note").map(String::as_str), Some("hello"));
+            assert_eq!(loaded.get_tags("note"), vec!["memo"]);
+            assert_eq!(loaded.expires_at("note"), Some(2_000));
+            assert_eq!(loaded.len(), 3);
+        }
+
+        // 版本 2 的记录读成几何体
+        let v2 = vec![ChunkedRecordV2 {
+            id: "p0".to_string(),
+            geojson: r#"{"type":"Point","coordinates":[1.0,2.0]}"#.to_string(),
+            tags: Vec::new(),
+            expires_at: Some(5),
+        }];
+        let records = decode_records(2, &bincode::serialize(&v2).unwrap()).unwrap();
+        assert!(!records[0].text);
+        assert_eq!(records[0].expires_at, Some(5));
+    }
+
+    #[tokio::test]
+    async fn test_chunked_rejects_truncated_and_foreign_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("chunked.snap");
+        tagged_tree(25).dump_chunked(&path, 8).await.unwrap();
+
+        // 去掉最后一块：对象数量与文件头不符
+        let data = fs::read(&path).unwrap();
+        let last_chunk_len = {
+            let mut offset = 4;
+            let mut last = 0;
+            while offset < data.len() {
+                let len = u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+                last = 4 + len;
+                offset += last;
+            }
+            last
+        };
+        let truncated = temp_dir.path().join("truncated.snap");
+        fs::write(&truncated, &data[..data.len() - last_chunk_len]).unwrap();
+        assert!(matches!(
+            RTree::load_chunked(&truncated).await,
+            Err(PersistenceError::InvalidFormat)
+        ));
+
+        let bin_path = temp_dir.path().join("plain.bin");
+        tagged_tree(3).dump_to_file(&bin_path).unwrap();
+        assert!(matches!(
+            RTree::load_chunked(&bin_path).await,
+            Err(PersistenceError::InvalidFormat)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_chunked_loads_version_1() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("v1.snap");
+        let header = ChunkedHeader {
+            version: 1,
+            max_entries: 4,
+            applied_seq: 7,
+            count: 1,
+        };
+        let records = vec![ChunkedRecordV1 {
+            id: "p0".to_string(),
+            geojson: r#"{"type":"Point","coordinates":[1.0,2.0]}"#.to_string(),
+            tags: vec!["bus".to_string()],
+        }];
+
+        let mut file = tokio::fs::File::create(&path).await.unwrap();
+        file.write_all(CHUNKED_MAGIC).await.unwrap();
+        write_frame(&mut file, &bincode::serialize(&header).unwrap())
+            .await
+            .unwrap();
+        write_frame(&mut file, &bincode::serialize(&records).unwrap())
+            .await
+            .unwrap();
+        file.flush().await.unwrap();
+
+        let loaded = RTree::load_chunked(&path).await.unwrap();
+        assert_eq!(loaded.count(), 1);
+        assert_eq!(loaded.applied_seq(), 7);
+        assert_eq!(loaded.get_tags("p0"), vec!["bus"]);
+        assert_eq!(loaded.expires_at("p0"), None);
+    }
+
+    #[tokio::test]
+    async fn test_chunked_empty_tree() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("empty.snap");
+
+        assert_eq!(RTree::new(6).dump_chunked(&path, 0).await.unwrap(), 0);
+        let loaded = RTree::load_chunked(&path).await.unwrap();
+        assert!(loaded.is_empty());
+        assert_eq!(loaded.max_entries(), 6);
+    }
+
+    #[test]
+    fn test_temp_path_naming() {
+        let dir = Path::new("snapshots");
+        assert_eq!(
+            temp_path(&dir.join("fleet.json")),
+            dir.join("fleet.json.tmp")
+        );
+        assert_eq!(temp_path(&dir.join("fleet")), dir.join("fleet.tmp"));
+        assert_eq!(
+            temp_path(Path::new("fleet.rtree")),
+            Path::new("fleet.rtree.tmp")
+        );
+    }
+
+    #[test]
+    fn test_dump_into_nested_directory_and_replace() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().join("with space").join("数据");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("snapshot");
+
+        let mut rtree = RTree::new(4);
+        rtree.insert(Rectangle::new(0.0, 0.0, 1.0, 1.0), "1".to_string());
+        rtree.dump_to_file(&path).unwrap();
+
+        // 覆盖已有文件，不留下临时文件
+        rtree.insert(Rectangle::new(2.0, 2.0, 3.0, 3.0), "2".to_string());
+        rtree.dump_to_file(&path).unwrap();
+        assert_eq!(RTree::load_from_file(&path).unwrap().len(), 2);
+        let names: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(names, vec![std::ffi::OsString::from("snapshot")]);
+    }
+
+    #[test]
+    fn test_sync_parent_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        sync_parent_dir(&temp_dir.path().join("file.bin")).unwrap();
+        // 只有文件名的相对路径使用当前目录
+        sync_parent_dir(Path::new("file.bin")).unwrap();
+    }
 }
@@ -2,115 +2,245 @@ use super::super::node::{Entry, Node};
 use super::super::rectangle::Rectangle;
 use super::super::rtree::GeoItem;
 use super::super::rtree::RTree;
-use super::utils::geometry_to_bbox;
+use super::knn::{snap_search, SnapResult};
+use super::utils::{geometry_to_bbox, is_multipart};
 use geo::{Geometry, Intersects, Within};
+use std::collections::{BTreeSet, HashSet};
 
 #[cfg(test)]
 use crate::storage::geometry_utils::geometry_to_geojson;
 
+/// 候选至少占集合的 1/ORDERED_WALK_RATIO 时，按 ID 顺序的搜索沿有序 ID 索引遍历
+const ORDERED_WALK_RATIO: usize = 8;
+
+/// 搜索遍历顺序提示
+///
+/// 只在设置了 limit 时有意义：决定达到 limit 提前终止时保留哪些结果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SearchOrder {
+    /// 按树内存储顺序遍历（默认，开销最小）
+    #[default]
+    Tree,
+    /// 优先遍历距离查询范围中心更近的子树，截断后的结果更集中于视口中心
+    Center,
+    /// 按对象 ID 升序返回，结果与插入顺序和树结构无关，跨运行保持一致
+    Id,
+}
+
+/// 一次搜索的查询参数，在遍历过程中共享
+struct SearchQuery<'a> {
+    bbox: &'a Rectangle,
+    center: [f64; 2],
+    geometry: &'a Geometry,
+    limit: usize,
+    /// true = 完全包含在 geometry 内部, false = 与 geometry 相交
+    within: bool,
+    order: SearchOrder,
+}
+
+impl SearchQuery<'_> {
+    fn limit_reached(&self, results: &[String]) -> bool {
+        self.limit > 0 && results.len() >= self.limit
+    }
+
+    /// 按遍历顺序把节点的子条目压栈，先访问的条目最后入栈
+    fn push_entries<'n>(&self, node: &'n Node, stack: &mut Vec<&'n Entry>) {
+        let start = stack.len();
+        stack.extend(node.entries.iter().rev());
+        if self.order == SearchOrder::Center {
+            // 距离中心越近越先访问，即越靠近栈顶
+            let [cx, cy] = self.center;
+            stack[start..].sort_by(|a, b| {
+                b.mbr()
+                    .distance_squared_to_point(cx, cy)
+                    .total_cmp(&a.mbr().distance_squared_to_point(cx, cy))
+            });
+        }
+    }
+}
+
+/// 根据 Geometry 进行精确比较
+/// within: true = entry_geometry 完全包含在 geometry 内部, false = 与 geometry 相交
+pub(super) fn matches_geometry(
+    entry_geometry: &Geometry,
+    geometry: &Geometry,
+    within: bool,
+) -> bool {
+    if within {
+        entry_geometry.is_within(geometry)
+    } else {
+        entry_geometry.intersects(geometry)
+    }
+}
+
 /// 搜索操作相关算法
 impl RTree {
     /// 搜索与查询几何体相交或完全包含在其中的所有条目
     /// within: true = 完全包含在 geometry 内部, false = 与 geometry 相交
     pub fn search(&self, geometry: &Geometry, limit: usize, within: bool) -> Vec<GeoItem> {
-        let bbox = geometry_to_bbox(geometry);
-        let mut results = Vec::new();
-
-        if let Some(root) = self.root_ref() {
-            self.search_recursive(root, &bbox.unwrap(), geometry, &mut results, limit, within);
-        }
+        self.search_ordered(geometry, limit, within, SearchOrder::Tree)
+    }
 
-        results
+    /// 带遍历顺序提示的搜索，limit 在遍历过程中生效，达到后立即停止
+    pub fn search_ordered(
+        &self,
+        geometry: &Geometry,
+        limit: usize,
+        within: bool,
+        order: SearchOrder,
+    ) -> Vec<GeoItem> {
+        self.search_ids(geometry, limit, within, order)
+            .into_iter()
+            .filter_map(|id| self.load_item(id))
+            .collect()
     }
 
-    /// 仅使用边界框进行搜索（用于测试和简单查询）
-    pub fn search_bbox(&self, query: &Rectangle) -> Vec<String> {
+    /// 与 [`search_ordered`](Self::search_ordered) 相同，但只返回 ID，不复制几何体和 GeoJSON
+    pub fn search_ids(
+        &self,
+        geometry: &Geometry,
+        limit: usize,
+        within: bool,
+        order: SearchOrder,
+    ) -> Vec<String> {
+        let bbox = geometry_to_bbox(geometry);
         let mut results = Vec::new();
 
-        if let Some(root) = self.root_ref() {
-            self.search_recursive_bbox_only(root, query, &mut results);
+        if order == SearchOrder::Id {
+            return match bbox {
+                Ok(bbox) => self.search_by_id(&bbox, geometry, limit, within),
+                Err(_) => results,
+            };
+        }
+
+        if let (Some(root), Ok(bbox)) = (self.root_ref(), bbox) {
+            // 无 limit 时所有匹配都会返回，排序没有意义
+            let order = if limit == 0 { SearchOrder::Tree } else { order };
+            let query = SearchQuery {
+                bbox: &bbox,
+                center: bbox.center(),
+                geometry,
+                limit,
+                within,
+                order,
+            };
+            self.search_tree(root, &query, &mut results);
         }
 
         results
     }
 
-    /// 递归搜索 - 遵循论文Search算法
-    /// within: true = 完全包含在 geometry 内部, false = 与 geometry 相交
-    fn search_recursive(
+    /// 按 ID 升序搜索
+    ///
+    /// 先只用边界框收集候选 ID，再按 ID 顺序做精确比较，达到 limit 后立即停止，
+    /// 超出 limit 的候选不会做精确比较。
+    /// 候选超过 limit 且占集合的比例不低于 1/[`ORDERED_WALK_RATIO`] 时沿有序 ID 索引遍历，
+    /// 平均只需经过约 limit × ORDERED_WALK_RATIO 个 ID，不必对所有候选排序；
+    /// 候选较少时直接排序候选，开销只与候选数有关
+    fn search_by_id(
         &self,
-        node: &Node,
-        query: &Rectangle,
+        bbox: &Rectangle,
         geometry: &Geometry,
-        results: &mut Vec<GeoItem>,
         limit: usize,
         within: bool,
-    ) {
-        // limit == 0 表示无限制，其他值表示有限制
-        if limit > 0 && results.len() >= limit {
-            return;
+    ) -> Vec<String> {
+        let mut candidates = self.search_bbox(bbox);
+        let limit = if limit == 0 { usize::MAX } else { limit };
+        let matches = |id: &str| {
+            self.geometry_map
+                .get(id)
+                .is_some_and(|entry_geometry| matches_geometry(entry_geometry, geometry, within))
+        };
+
+        if limit < candidates.len()
+            && candidates.len().saturating_mul(ORDERED_WALK_RATIO) >= self.geometry_map.len()
+        {
+            let candidates: HashSet<&str> = candidates.iter().map(String::as_str).collect();
+            return self
+                .ordered_ids()
+                .iter()
+                .filter(|id| candidates.contains(id.as_str()) && matches(id))
+                .take(limit)
+                .cloned()
+                .collect();
         }
 
-        // S1: 搜索子树
-        for entry in &node.entries {
-            if entry.mbr().intersects(query) {
-                match entry {
-                    Entry::Data { data, .. } => {
-                        // 根据 Geometry 进行精确比较
-                        if let Some(entry_geometry) = self.geometry_map.get(data) {
-                            let matches = if within {
-                                // Within 查询：entry_geometry 必须完全包含在 geometry 内部
-                                entry_geometry.is_within(geometry)
-                            } else {
-                                // Intersects 查询：entry_geometry 与 geometry 相交
-                                entry_geometry.intersects(geometry)
-                            };
-
-                            if matches {
-                                // S2: 添加数据到结果
-                                results.push(GeoItem {
-                                    id: data.clone(),
-                                    geometry: entry_geometry.clone(),
-                                    geojson: self
-                                        .geojson_map
-                                        .get(data)
-                                        .cloned()
-                                        .unwrap_or_default(),
-                                });
-                                if limit > 0 && results.len() >= limit {
-                                    return;
-                                }
-                            }
-                        }
-                    }
-                    Entry::Node { node, .. } => {
-                        // 递归搜索子节点
-                        self.search_recursive(node, query, geometry, results, limit, within);
-                        if limit > 0 && results.len() >= limit {
-                            return;
-                        }
-                    }
-                }
-            }
+        candidates.sort_unstable();
+        candidates
+            .into_iter()
+            .filter(|id| matches(id))
+            .take(limit)
+            .collect()
+    }
+
+    /// 按 ID 排序的所有对象 ID，第一次调用时从 geometry_map 建立
+    fn ordered_ids(&self) -> &BTreeSet<String> {
+        self.ordered_ids
+            .get_or_init(|| self.geometry_map.keys().cloned().collect())
+    }
+
+    /// 复制查询结果中的一个对象，缺少 GeoJSON 时为空字符串
+    pub(super) fn load_item(&self, id: String) -> Option<GeoItem> {
+        let geometry = self.geometry_map.get(&id)?.clone();
+        let geojson = self.geojson_map.get(&id).cloned().unwrap_or_default();
+        Some(GeoItem {
+            id,
+            geometry,
+            geojson,
+        })
+    }
+
+    /// 仅使用边界框进行搜索（用于测试和简单查询）
+    ///
+    /// 每个 ID 只返回一次，即使对象有多个部分与查询范围相交
+    pub fn search_bbox(&self, query: &Rectangle) -> Vec<String> {
+        let mut results = self.index.search_bbox(query);
+        if results
+            .iter()
+            .any(|id| self.geometry_map.get(id).is_some_and(is_multipart))
+        {
+            let mut seen = std::collections::HashSet::new();
+            results.retain(|id| seen.insert(id.clone()));
         }
+
+        results
     }
 
-    /// 递归搜索 - 仅边界框过滤（用于测试）
-    fn search_recursive_bbox_only(
-        &self,
-        node: &Node,
-        query: &Rectangle,
-        results: &mut Vec<String>,
-    ) {
-        for entry in &node.entries {
-            if entry.mbr().intersects(query) {
-                match entry {
-                    Entry::Data { data, .. } => {
-                        results.push(data.clone());
+    /// 搜索 - 遵循论文Search算法
+    ///
+    /// 用显式栈做深度优先遍历，退化的深树不会耗尽调用栈；
+    /// 子条目逆序入栈，出栈顺序与递归遍历相同
+    fn search_tree(&self, root: &Node, query: &SearchQuery, results: &mut Vec<String>) {
+        let mut stack = Vec::new();
+        query.push_entries(root, &mut stack);
+
+        while let Some(entry) = stack.pop() {
+            // limit == 0 表示无限制，其他值表示有限制
+            if query.limit_reached(results) {
+                return;
+            }
+
+            // S1: 搜索子树
+            if !entry.mbr().intersects(query.bbox) {
+                continue;
+            }
+
+            match entry {
+                Entry::Data { data, .. } => {
+                    // 根据 Geometry 进行精确比较
+                    let Some(entry_geometry) = self.geometry_map.get(data) else {
+                        continue;
+                    };
+                    // 多部分对象的其他部分可能已经命中过
+                    if is_multipart(entry_geometry) && results.contains(data) {
+                        continue;
                     }
-                    Entry::Node { node, .. } => {
-                        self.search_recursive_bbox_only(node, query, results);
+                    if matches_geometry(entry_geometry, query.geometry, query.within) {
+                        // S2: 添加数据到结果
+                        results.push(data.clone());
                     }
                 }
+                Entry::Node { node, .. } => query.push_entries(node, &mut stack),
             }
         }
     }
@@ -149,10 +279,25 @@ impl RTree {
         k: usize,
         max_radius: Option<f64>,
     ) -> Vec<(GeoItem, f64)> {
-        use super::knn::knn_search;
+        self.nearby_approx(query_lon, query_lat, k, max_radius, 0.0)
+    }
+
+    /// KNN 近似查询：允许提前终止以降低大树上的查询延迟
+    ///
+    /// 当剩余节点的最小 MBR 距离乘以 `1 + epsilon` 超过当前第 k 个候选距离时即返回，
+    /// 每个结果的距离不超过真实第 k 近距离的 `1 + epsilon` 倍。`epsilon = 0` 等价于 [`nearby`](Self::nearby)。
+    pub fn nearby_approx(
+        &self,
+        query_lon: f64,
+        query_lat: f64,
+        k: usize,
+        max_radius: Option<f64>,
+        epsilon: f64,
+    ) -> Vec<(GeoItem, f64)> {
+        use super::knn::knn_search_approx;
 
         // 直接传递 geometry_map 和 geojson_map 的引用，避免复制整个数据集
-        let knn_results = knn_search(
+        let knn_results = knn_search_approx(
             self.get_root(),
             query_lon,
             query_lat,
@@ -160,6 +305,7 @@ impl RTree {
             &self.geometry_map,
             &self.geojson_map,
             max_radius,
+            epsilon,
         );
 
         // 转换结果为 (GeoItem, distance) 元组
@@ -168,6 +314,45 @@ impl RTree {
             .map(|result| (result.item, result.distance))
             .collect()
     }
+
+    /// 区域内的 KNN 查询：只在完全包含于 `region` 内的对象中查找最近的 k 个
+    ///
+    /// 遍历时跳过与 `region` 边界框不相交的子树，再对候选做精确的包含判断，
+    /// 参见 [`knn_search_within`](super::knn::knn_search_within)
+    pub fn nearby_within(
+        &self,
+        query_lon: f64,
+        query_lat: f64,
+        k: usize,
+        max_radius: Option<f64>,
+        epsilon: f64,
+        region: &Geometry,
+    ) -> Vec<(GeoItem, f64)> {
+        use super::knn::knn_search_within;
+
+        knn_search_within(
+            self.get_root(),
+            query_lon,
+            query_lat,
+            k,
+            &self.geometry_map,
+            &self.geojson_map,
+            max_radius,
+            epsilon,
+            region,
+        )
+        .into_iter()
+        .map(|result| (result.item, result.distance))
+        .collect()
+    }
+
+    /// 将点吸附到最近的线（LineString/MultiLineString）上
+    ///
+    /// 返回最近线的 ID、线上的投影点以及偏移距离（米），没有线类对象时返回 None。
+    /// 点和多边形会被忽略，常用于 GPS 轨迹纠偏
+    pub fn snap(&self, query_lon: f64, query_lat: f64) -> Option<SnapResult> {
+        snap_search(self.get_root(), query_lon, query_lat, &self.geometry_map)
+    }
 }
 
 #[cfg(test)]
@@ -358,4 +543,201 @@ mod tests {
         assert!(!results.iter().any(|item| item.id == "3"));
         assert_eq!(results.len(), 2);
     }
+
+    #[test]
+    fn test_search_order_center_with_limit() {
+        let mut rtree = RTree::new(4);
+
+        // 沿 x 轴分布的 20 个点，查询范围覆盖全部点
+        for i in 0..20 {
+            let point = Geometry::Point(Point::new(i as f64, 0.0));
+            rtree.insert_geojson(format!("p{}", i), &geometry_to_geojson(&point).to_string());
+        }
+
+        let query_polygon = Geometry::Polygon(Polygon::new(
+            vec![
+                Coord { x: -0.5, y: -1.0 },
+                Coord { x: 19.5, y: -1.0 },
+                Coord { x: 19.5, y: 1.0 },
+                Coord { x: -0.5, y: 1.0 },
+                Coord { x: -0.5, y: -1.0 },
+            ]
+            .into(),
+            vec![],
+        ));
+
+        // 中心在 x = 9.5，CENTER 顺序下截断结果应比树内顺序更靠近中心
+        let mean_offset = |items: &[GeoItem]| {
+            items
+                .iter()
+                .map(|item| match &item.geometry {
+                    Geometry::Point(p) => (p.x() - 9.5).abs(),
+                    _ => unreachable!(),
+                })
+                .sum::<f64>()
+                / items.len() as f64
+        };
+        let center_results = rtree.search_ordered(&query_polygon, 4, false, SearchOrder::Center);
+        let tree_results = rtree.search_ordered(&query_polygon, 4, false, SearchOrder::Tree);
+        assert_eq!(center_results.len(), 4);
+        assert_eq!(tree_results.len(), 4);
+        assert!(mean_offset(&center_results) < mean_offset(&tree_results));
+
+        // 无 limit 时两种顺序返回相同的结果集
+        let mut all_tree: Vec<String> = rtree
+            .search_ordered(&query_polygon, 0, false, SearchOrder::Tree)
+            .into_iter()
+            .map(|item| item.id)
+            .collect();
+        let mut all_center: Vec<String> = rtree
+            .search_ordered(&query_polygon, 0, false, SearchOrder::Center)
+            .into_iter()
+            .map(|item| item.id)
+            .collect();
+        all_tree.sort();
+        all_center.sort();
+        assert_eq!(all_tree.len(), 20);
+        assert_eq!(all_tree, all_center);
+    }
+
+    #[test]
+    fn test_search_order_id_is_deterministic() {
+        let query_polygon = Geometry::Polygon(Polygon::new(
+            vec![
+                Coord { x: -0.5, y: -1.0 },
+                Coord { x: 9.5, y: -1.0 },
+                Coord { x: 9.5, y: 1.0 },
+                Coord { x: -0.5, y: 1.0 },
+                Coord { x: -0.5, y: -1.0 },
+            ]
+            .into(),
+            vec![],
+        ));
+
+        // 同样的数据以不同顺序插入，树结构不同，但 ID 顺序的结果一致
+        let build = |order: &[usize]| {
+            let mut rtree = RTree::new(4);
+            for &i in order {
+                let point = Geometry::Point(Point::new(i as f64, 0.0));
+                rtree.insert_geojson(
+                    format!("p{:02}", i),
+                    &geometry_to_geojson(&point).to_string(),
+                );
+            }
+            // 范围外的对象
+            let outside = Geometry::Point(Point::new(50.0, 50.0));
+            rtree.insert_geojson(
+                "a_outside".to_string(),
+                &geometry_to_geojson(&outside).to_string(),
+            );
+            rtree
+        };
+        let forward: Vec<usize> = (0..10).collect();
+        let shuffled = [7, 2, 9, 0, 5, 3, 8, 1, 6, 4];
+
+        let ids = |rtree: &RTree, limit: usize| -> Vec<String> {
+            rtree
+                .search_ordered(&query_polygon, limit, false, SearchOrder::Id)
+                .into_iter()
+                .map(|item| item.id)
+                .collect()
+        };
+        let expected: Vec<String> = (0..10).map(|i| format!("p{:02}", i)).collect();
+        assert_eq!(ids(&build(&forward), 0), expected);
+        assert_eq!(ids(&build(&shuffled), 0), expected);
+
+        // limit 取 ID 最小的前几个匹配
+        assert_eq!(ids(&build(&shuffled), 3), expected[..3].to_vec());
+    }
+
+    #[test]
+    fn test_search_order_id_walks_ordered_index() {
+        let point = |x: f64, y: f64| geometry_to_geojson(&Geometry::Point(Point::new(x, y)));
+        let mut rtree = RTree::new(4);
+        for i in (0..100).rev() {
+            rtree.insert_geojson(format!("p{:03}", i), &point(i as f64, 0.0).to_string());
+        }
+        let ids = |rtree: &RTree, max_x: f64, limit: usize| -> Vec<String> {
+            let area = Geometry::Rect(geo::Rect::new(
+                Coord { x: -0.5, y: -1.0 },
+                Coord { x: max_x, y: 1.0 },
+            ));
+            rtree.search_ids(&area, limit, false, SearchOrder::Id)
+        };
+
+        // 候选较少时排序候选，不建立有序索引
+        assert_eq!(ids(&rtree, 4.5, 2), vec!["p000", "p001"]);
+        assert!(rtree.ordered_ids.get().is_none());
+
+        // 候选覆盖大部分集合时沿有序索引遍历
+        assert_eq!(ids(&rtree, 99.5, 3), vec!["p000", "p001", "p002"]);
+        assert_eq!(rtree.ordered_ids.get().map(BTreeSet::len), Some(100));
+
+        // 建立之后随插入和删除更新
+        rtree.delete("p001");
+        rtree.insert_geojson("p0005".to_string(), &point(0.5, 0.0).to_string());
+        assert_eq!(ids(&rtree, 99.5, 3), vec!["p000", "p0005", "p002"]);
+        let expected: Vec<String> = rtree
+            .geometry_map
+            .keys()
+            .cloned()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        assert_eq!(ids(&rtree, 99.5, 0), expected);
+    }
+
+    #[test]
+    fn test_multipart_object_indexed_per_part() {
+        let mut rtree = RTree::new(4);
+        let collection = r#"{"type":"GeometryCollection","geometries":[
+            {"type":"Point","coordinates":[1.0,1.0]},
+            {"type":"LineString","coordinates":[[50.0,50.0],[51.0,51.0]]}
+        ]}"#;
+        assert!(rtree.insert_geojson("campus".to_string(), collection));
+
+        // 每个部分一个索引条目，但只算一个对象
+        assert_eq!(rtree.len(), 2);
+        assert_eq!(rtree.count(), 1);
+
+        // 两个部分之间的空白区域不再命中
+        let gap = Geometry::Polygon(Polygon::new(
+            vec![
+                Coord { x: 20.0, y: 20.0 },
+                Coord { x: 30.0, y: 20.0 },
+                Coord { x: 30.0, y: 30.0 },
+                Coord { x: 20.0, y: 30.0 },
+                Coord { x: 20.0, y: 20.0 },
+            ]
+            .into(),
+            vec![],
+        ));
+        assert!(rtree.search(&gap, 0, false).is_empty());
+
+        // 同时覆盖两个部分的查询只返回一次
+        let all = Geometry::Polygon(Polygon::new(
+            vec![
+                Coord { x: 0.0, y: 0.0 },
+                Coord { x: 60.0, y: 0.0 },
+                Coord { x: 60.0, y: 60.0 },
+                Coord { x: 0.0, y: 60.0 },
+                Coord { x: 0.0, y: 0.0 },
+            ]
+            .into(),
+            vec![],
+        ));
+        assert_eq!(rtree.search(&all, 0, false).len(), 1);
+        assert_eq!(
+            rtree.search_bbox(&Rectangle::new(0.0, 0.0, 60.0, 60.0)),
+            vec!["campus".to_string()]
+        );
+        assert_eq!(rtree.nearby(50.5, 50.5, 10, None).len(), 1);
+
+        // 删除时移除所有部分
+        assert!(rtree.delete("campus"));
+        assert_eq!(rtree.len(), 0);
+        assert!(rtree
+            .search_bbox(&Rectangle::new(0.0, 0.0, 60.0, 60.0))
+            .is_empty());
+    }
 }
@@ -0,0 +1,94 @@
+use super::super::rtree::{GeoItem, RTree};
+
+/// 对象的值：几何体或者 STRING 文本
+#[derive(Debug, Clone)]
+pub enum StoredValue {
+    Geometry(GeoItem),
+    Text(String),
+}
+
+/// STRING 对象（`SET key id STRING value`）
+///
+/// 文本值与几何体共用同一个 ID 空间，可以带标签和 TTL，KEYS、SCAN、GET 都能看到它们；
+/// 它们没有边界框，不进入 R-tree，空间查询（WITHIN、INTERSECTS、NEARBY 等）不会返回它们
+impl RTree {
+    /// 写入 STRING 对象，覆盖同 ID 的几何体或文本（与覆盖几何体一样清除原有的标签和 TTL）
+    pub fn set_string(&mut self, data: String, value: String) {
+        self.delete(&data);
+        self.touch(&data);
+        self.strings.insert(data, value);
+    }
+
+    /// STRING 对象的文本，对象不存在或者是几何体时返回 None
+    pub fn get_string(&self, data_id: &str) -> Option<&String> {
+        self.strings.get(data_id)
+    }
+
+    /// 对象的值，几何体和 STRING 对象都会返回
+    pub fn get_value(&self, data_id: &str) -> Option<StoredValue> {
+        match self.strings.get(data_id) {
+            Some(text) => Some(StoredValue::Text(text.clone())),
+            None => self.get(data_id).map(StoredValue::Geometry),
+        }
+    }
+
+    /// 对象是否存在（几何体或 STRING 对象）
+    pub fn contains(&self, data_id: &str) -> bool {
+        self.geometry_map.contains_key(data_id) || self.strings.contains_key(data_id)
+    }
+
+    /// STRING 对象数量
+    pub fn string_count(&self) -> usize {
+        self.strings.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POINT: &str = r#"{"type":"Point","coordinates":[1.0,2.0]}"#;
+
+    #[test]
+    fn test_string_objects() {
+        let mut tree = RTree::new(4);
+        tree.insert_geojson("truck1".to_string(), POINT);
+        tree.set_string("note".to_string(), "hello".to_string());
+        assert_eq!(tree.count(), 2);
+        assert_eq!(tree.string_count(), 1);
+        assert!(tree.contains("note"));
+        assert_eq!(tree.get_string("note").map(String::as_str), Some("hello"));
+        assert!(matches!(
+            tree.get_value("note"),
+            Some(StoredValue::Text(text)) if text == "hello"
+        ));
+        assert!(matches!(
+            tree.get_value("truck1"),
+            Some(StoredValue::Geometry(item)) if item.id == "truck1"
+        ));
+        assert_eq!(tree.ids_matching("*"), vec!["note", "truck1"]);
+        assert_eq!(tree.ids_matching("note"), vec!["note"]);
+
+        // 不进入空间索引
+        let everything = crate::rtree::Rectangle::new(-180.0, -90.0, 180.0, 90.0);
+        assert_eq!(tree.search_bbox(&everything).len(), 1);
+
+        // 标签和 TTL 可用，覆盖时清除
+        tree.set_expire("note", 100);
+        assert_eq!(tree.expires_at("note"), Some(100));
+        tree.set_string("note".to_string(), "again".to_string());
+        assert_eq!(tree.expires_at("note"), None);
+
+        // 几何体与文本互相覆盖
+        tree.set_string("truck1".to_string(), "parked".to_string());
+        assert!(tree.get("truck1").is_none());
+        assert_eq!(tree.search_bbox(&everything).len(), 0);
+        tree.insert_geojson("note".to_string(), POINT);
+        assert_eq!(tree.get_string("note"), None);
+        assert_eq!(tree.count(), 2);
+
+        assert!(tree.delete("truck1"));
+        assert!(!tree.contains("truck1"));
+        assert_eq!(tree.count(), 1);
+    }
+}
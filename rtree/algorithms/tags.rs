@@ -0,0 +1,341 @@
+use super::super::rtree::{GeoItem, RTree};
+use super::filter::ObjectFilter;
+use super::knn::DistanceMetric;
+use super::search::{matches_geometry, SearchOrder};
+use super::utils::geometry_to_bbox;
+use geo::Geometry;
+use std::collections::BTreeSet;
+
+/// 对象标签与标签过滤查询
+///
+/// 标签保存在两张表中：对象 -> 标签集合，以及标签 -> 对象集合的倒排索引。
+/// 带 WHERETAG 的查询先对倒排索引求交集得到候选对象，再只对候选对象做精确几何判断，
+/// 标签足够有选择性时（例如“42 路公交”）不需要遍历 R-tree
+impl RTree {
+    /// 设置对象的标签，替换原有标签；传入空集合即清除标签
+    pub fn set_tags<I>(&mut self, data_id: &str, tags: I)
+    where
+        I: IntoIterator<Item = String>,
+    {
+        self.remove_tags(data_id);
+        if self.contains(data_id) {
+            self.touch(data_id);
+        }
+
+        let tags: BTreeSet<String> = tags.into_iter().collect();
+        if tags.is_empty() {
+            return;
+        }
+        for tag in &tags {
+            self.tag_index
+                .entry(tag.clone())
+                .or_default()
+                .insert(data_id.to_string());
+        }
+        self.tags.insert(data_id.to_string(), tags);
+    }
+
+    /// 清除对象的标签，并从倒排索引中移除
+    pub fn remove_tags(&mut self, data_id: &str) {
+        let Some(tags) = self.tags.remove(data_id) else {
+            return;
+        };
+        for tag in tags {
+            if let Some(ids) = self.tag_index.get_mut(&tag) {
+                ids.remove(data_id);
+                if ids.is_empty() {
+                    self.tag_index.remove(&tag);
+                }
+            }
+        }
+    }
+
+    /// 获取对象的标签（按字典序），没有标签时返回空列表
+    pub fn get_tags(&self, data_id: &str) -> Vec<String> {
+        self.tags
+            .get(data_id)
+            .map(|tags| tags.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 同时带有所有指定标签的对象 ID（按 ID 升序）
+    ///
+    /// 从最小的标签集合开始求交集；任一标签不存在时结果为空
+    pub fn ids_with_tags(&self, tags: &[String]) -> BTreeSet<String> {
+        let mut sets = Vec::with_capacity(tags.len());
+        for tag in tags {
+            match self.tag_index.get(tag) {
+                Some(ids) => sets.push(ids),
+                None => return BTreeSet::new(),
+            }
+        }
+        sets.sort_by_key(|ids| ids.len());
+
+        let Some((smallest, rest)) = sets.split_first() else {
+            return BTreeSet::new();
+        };
+        smallest
+            .iter()
+            .filter(|id| rest.iter().all(|ids| ids.contains(*id)))
+            .cloned()
+            .collect()
+    }
+
+    /// 带标签过滤的空间查询
+    ///
+    /// 只对同时带有所有 `tags` 的对象做精确几何判断。`SearchOrder::Center` 按对象边界框中心
+    /// 到查询范围中心的距离排序，其余顺序按 ID 升序
+    pub fn search_tagged(
+        &self,
+        geometry: &Geometry,
+        limit: usize,
+        within: bool,
+        order: SearchOrder,
+        tags: &[String],
+    ) -> Vec<GeoItem> {
+        self.search_tagged_ids(geometry, limit, within, order, tags)
+            .into_iter()
+            .filter_map(|id| self.load_item(id))
+            .collect()
+    }
+
+    /// 与 [`search_tagged`](Self::search_tagged) 相同，但只返回 ID
+    pub fn search_tagged_ids(
+        &self,
+        geometry: &Geometry,
+        limit: usize,
+        within: bool,
+        order: SearchOrder,
+        tags: &[String],
+    ) -> Vec<String> {
+        let Ok(bbox) = geometry_to_bbox(geometry) else {
+            return Vec::new();
+        };
+        let limit = if limit == 0 { usize::MAX } else { limit };
+
+        let matches = self.ids_with_tags(tags).into_iter().filter_map(|id| {
+            let entry_geometry = self.geometry_map.get(&id)?;
+            matches_geometry(entry_geometry, geometry, within).then_some((id, entry_geometry))
+        });
+
+        let selected: Vec<(String, &Geometry)> = if order == SearchOrder::Center {
+            let center = bbox.center();
+            let mut matches: Vec<_> = matches
+                .map(|(id, entry_geometry)| {
+                    let distance = geometry_to_bbox(entry_geometry).map_or(f64::INFINITY, |b| {
+                        let c = b.center();
+                        (c[0] - center[0]).powi(2) + (c[1] - center[1]).powi(2)
+                    });
+                    (distance, id, entry_geometry)
+                })
+                .collect();
+            matches.sort_by(|a, b| a.0.total_cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+            matches
+                .into_iter()
+                .take(limit)
+                .map(|(_, id, entry_geometry)| (id, entry_geometry))
+                .collect()
+        } else {
+            matches.take(limit).collect()
+        };
+
+        selected.into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// 带标签过滤的 KNN 查询
+    ///
+    /// 对同时带有所有 `tags` 的对象逐个计算距离（米），按距离升序返回；
+    /// `k` 为 0 表示不限制数量，`max_radius` 为 None 表示不限制半径，
+    /// `region` 不为 None 时只保留完全包含在其中的对象，同时还要满足 `filter`；
+    /// 距离和 `max_radius` 按 `metric` 计算
+    #[allow(clippy::too_many_arguments)]
+    pub fn nearby_tagged(
+        &self,
+        query_lon: f64,
+        query_lat: f64,
+        k: usize,
+        max_radius: Option<f64>,
+        tags: &[String],
+        region: Option<&Geometry>,
+        filter: &ObjectFilter,
+        metric: DistanceMetric,
+    ) -> Vec<(GeoItem, f64)> {
+        let mut candidates: Vec<(String, f64)> = self
+            .ids_with_tags(tags)
+            .into_iter()
+            .filter_map(|id| {
+                let geometry = self.geometry_map.get(&id)?;
+                if region.is_some_and(|region| !matches_geometry(geometry, region, true)) {
+                    return None;
+                }
+                if !filter.matches(geometry, self.geojson_map.get(&id).map(String::as_str)) {
+                    return None;
+                }
+                let distance = metric.point_to_geometry(query_lon, query_lat, geometry);
+                match max_radius {
+                    Some(radius) if distance > radius => None,
+                    _ => Some((id, distance)),
+                }
+            })
+            .collect();
+        candidates.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        if k > 0 {
+            candidates.truncate(k);
+        }
+
+        candidates
+            .into_iter()
+            .filter_map(|(id, distance)| Some((self.get(&id)?, distance)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{Coord, Polygon, Rect};
+    use serde_json::json;
+
+    fn point(lon: f64, lat: f64) -> String {
+        json!({"type": "Point", "coordinates": [lon, lat]}).to_string()
+    }
+
+    fn tags(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn fleet() -> RTree {
+        let mut tree = RTree::new(4);
+        let vehicles = [
+            ("bus1", 0.0, 0.0, &["bus", "line42"][..]),
+            ("bus2", 0.01, 0.0, &["bus", "line7"][..]),
+            ("bus3", 0.02, 0.0, &["bus", "line42"][..]),
+            ("taxi1", 0.005, 0.0, &["taxi"][..]),
+            ("bus4", 5.0, 5.0, &["bus", "line42"][..]),
+        ];
+        for (id, lon, lat, names) in vehicles {
+            assert!(tree.insert_geojson(id.to_string(), &point(lon, lat)));
+            tree.set_tags(id, tags(names));
+        }
+        tree
+    }
+
+    fn ids(items: &[GeoItem]) -> Vec<&str> {
+        items.iter().map(|item| item.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_tag_index_intersection() {
+        let tree = fleet();
+        let line42: Vec<String> = tree
+            .ids_with_tags(&tags(&["line42", "bus"]))
+            .into_iter()
+            .collect();
+        assert_eq!(line42, vec!["bus1", "bus3", "bus4"]);
+        assert!(tree.ids_with_tags(&tags(&["bus", "tram"])).is_empty());
+        assert_eq!(tree.get_tags("bus2"), tags(&["bus", "line7"]));
+        assert!(tree.get_tags("missing").is_empty());
+    }
+
+    #[test]
+    fn test_tags_follow_object_lifecycle() {
+        let mut tree = fleet();
+
+        // 重新 SET 会替换几何体并清除旧标签
+        assert!(tree.insert_geojson("bus1".to_string(), &point(0.0, 0.0)));
+        assert!(tree.get_tags("bus1").is_empty());
+        assert!(!tree.ids_with_tags(&tags(&["line42"])).contains("bus1"));
+
+        tree.delete("bus3");
+        tree.delete("bus4");
+        assert!(tree.ids_with_tags(&tags(&["line42"])).is_empty());
+        assert!(!tree.tag_index.contains_key("line42"));
+
+        tree.set_tags("bus2", Vec::new());
+        assert!(tree.get_tags("bus2").is_empty());
+        assert!(!tree.tags.contains_key("bus2"));
+    }
+
+    #[test]
+    fn test_search_tagged() {
+        let tree = fleet();
+        let area = Geometry::Rect(Rect::new(
+            Coord { x: -1.0, y: -1.0 },
+            Coord { x: 1.0, y: 1.0 },
+        ));
+
+        let results = tree.search_tagged(&area, 0, false, SearchOrder::Tree, &tags(&["line42"]));
+        assert_eq!(ids(&results), vec!["bus1", "bus3"]);
+
+        let results = tree.search_tagged(&area, 0, false, SearchOrder::Tree, &tags(&["bus"]));
+        assert_eq!(ids(&results), vec!["bus1", "bus2", "bus3"]);
+
+        // 以 (0.02, 0) 为中心的查询范围，最近的公交排在前面
+        let shifted = Geometry::Polygon(Polygon::new(
+            vec![
+                Coord { x: -0.98, y: -1.0 },
+                Coord { x: 1.02, y: -1.0 },
+                Coord { x: 1.02, y: 1.0 },
+                Coord { x: -0.98, y: 1.0 },
+                Coord { x: -0.98, y: -1.0 },
+            ]
+            .into(),
+            vec![],
+        ));
+        let results = tree.search_tagged(&shifted, 2, false, SearchOrder::Center, &tags(&["bus"]));
+        assert_eq!(ids(&results), vec!["bus3", "bus2"]);
+    }
+
+    #[test]
+    fn test_nearby_tagged() {
+        let tree = fleet();
+
+        let results = tree.nearby_tagged(
+            0.004,
+            0.0,
+            2,
+            None,
+            &tags(&["bus", "line42"]),
+            None,
+            &ObjectFilter::default(),
+            DistanceMetric::Haversine,
+        );
+        let found: Vec<&str> = results.iter().map(|(item, _)| item.id.as_str()).collect();
+        assert_eq!(found, vec!["bus1", "bus3"]);
+        assert!(results[0].1 < results[1].1);
+
+        // 半径过滤掉远处的 bus4，taxi1 虽然最近但没有公交标签
+        let results = tree.nearby_tagged(
+            0.004,
+            0.0,
+            0,
+            Some(10_000.0),
+            &tags(&["bus"]),
+            None,
+            &ObjectFilter::default(),
+            DistanceMetric::Haversine,
+        );
+        let found: Vec<&str> = results.iter().map(|(item, _)| item.id.as_str()).collect();
+        assert_eq!(found, vec!["bus1", "bus2", "bus3"]);
+
+        // 区域只覆盖 bus3 和 bus4，最近的 bus1 被排除
+        let region: Geometry = geo::Rect::new(
+            geo::Coord { x: 0.015, y: -1.0 },
+            geo::Coord { x: 6.0, y: 6.0 },
+        )
+        .to_polygon()
+        .into();
+        let results = tree.nearby_tagged(
+            0.004,
+            0.0,
+            1,
+            None,
+            &tags(&["bus", "line42"]),
+            Some(&region),
+            &ObjectFilter::default(),
+            DistanceMetric::Haversine,
+        );
+        let found: Vec<&str> = results.iter().map(|(item, _)| item.id.as_str()).collect();
+        assert_eq!(found, vec!["bus3"]);
+    }
+}
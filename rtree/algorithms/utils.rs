@@ -1,4 +1,3 @@
-use super::super::node::{Entry, Node};
 use super::super::rectangle::Rectangle;
 use crate::rtree::RTree;
 // use std::result::Result;
@@ -29,76 +28,33 @@ pub fn geometry_to_bbox(geometry: &geo::Geometry) -> Result<Rectangle> {
     }
 }
 
-/// R-tree工具函数实现
-impl RTree {
-    /// 向上调整树 - 更新MBR
-    ///
-    /// 从给定路径的叶子节点向上更新每一层的MBR，确保树的MBR信息正确
-    pub(crate) fn adjust_tree_upward(&mut self, mut path: Vec<usize>) {
-        // 从叶子节点向上更新每一层的MBR
-        while !path.is_empty() {
-            // 获取当前节点,如果当前节点为空，则跳过当前节点
-            let node = match self.get_last_node_mut(&path) {
-                Some(node) => node,
-                None => {
-                    path.pop().unwrap();
-                    break;
-                }
-            };
-
-            // 更新当前节点的MBR
-            node.update_mbr();
-
-            // 将当前节点从 path 中移除
-            let current_node_index = path[path.len() - 1];
-            path.pop().unwrap();
-
-            let current_mbr = node.mbr;
-
-            // 更新父节点中指向当前节点的条目的MBR
-            if path.is_empty() {
-                // 当前节点是根节点的直接子节点，更新根节点中的条目
-                if let Some(root) = self.root_mut() {
-                    if let Some(Entry::Node { mbr, .. }) = root.entries.get_mut(current_node_index)
-                    {
-                        *mbr = current_mbr;
-                    }
-                }
-            } else {
-                // 更新中间层的父节点
-                let parent = match self.get_last_node_mut(&path) {
-                    Some(node) => node,
-                    None => {
-                        println!(
-                            "Warning: Failed to get parent node (Empty Node) during MBR update"
-                        );
-                        break;
-                    }
-                };
-                if let Some(Entry::Node { mbr, .. }) = parent.entries.get_mut(current_node_index) {
-                    *mbr = current_mbr;
-                }
+/// 计算对象在 R-tree 中的索引边界框
+///
+/// 包含多个部分的 GeometryCollection 为每个部分单独建立一个条目（同一个对象 ID），
+/// 分散的部分不会合并成一个覆盖大片空白区域的边界框；其他几何体只有一个条目。
+/// 空的部分没有边界框，不建立条目
+pub fn index_bboxes(geometry: &geo::Geometry) -> Result<Vec<Rectangle>> {
+    if is_multipart(geometry) {
+        if let geo::Geometry::GeometryCollection(collection) = geometry {
+            let parts: Vec<Rectangle> = collection
+                .iter()
+                .filter_map(|part| geometry_to_bbox(part).ok())
+                .collect();
+            if !parts.is_empty() {
+                return Ok(parts);
             }
         }
     }
+    geometry_to_bbox(geometry).map(|bbox| vec![bbox])
+}
 
-    /// 获取路径中最后一个节点的可变引用
-    ///
-    /// 根据给定的路径从根节点开始遍历，返回路径末端节点的可变引用
-    pub(crate) fn get_last_node_mut(&mut self, path: &[usize]) -> Option<&mut Node> {
-        let mut current = self.root_mut().as_mut()?;
-
-        for &index in path {
-            if let Some(Entry::Node { node, .. }) = current.entries.get_mut(index) {
-                current = node;
-            } else {
-                return None;
-            }
-        }
-
-        Some(current)
-    }
+/// 对象是否按部分建立了多个索引条目，遍历时需要对这类对象去重
+pub fn is_multipart(geometry: &geo::Geometry) -> bool {
+    matches!(geometry, geo::Geometry::GeometryCollection(collection) if collection.len() > 1)
+}
 
+/// R-tree工具函数实现
+impl RTree {
     /// 计算扩大成本
     ///
     /// 计算将一个矩形添加到另一个矩形时所需的面积扩大量
@@ -121,57 +77,4 @@ mod tests {
         let cost = rtree.enlargement_cost(&mbr1, &rect1);
         assert_eq!(cost, 125.0); // 225 - 100 = 125
     }
-
-    #[test]
-    fn test_get_last_node_mut() {
-        let mut rtree = RTree::new(4);
-
-        // 插入一些数据以创建多层结构
-        rtree.insert(Rectangle::new(0.0, 0.0, 1.0, 1.0), "1".to_string());
-        rtree.insert(Rectangle::new(2.0, 2.0, 3.0, 3.0), "2".to_string());
-        rtree.insert(Rectangle::new(4.0, 4.0, 5.0, 5.0), "3".to_string());
-        rtree.insert(Rectangle::new(6.0, 6.0, 7.0, 7.0), "4".to_string());
-        rtree.insert(Rectangle::new(8.0, 8.0, 9.0, 9.0), "5".to_string());
-
-        // 测试空路径 - 应该返回根节点
-        assert!(rtree.get_last_node_mut(&[]).is_some());
-
-        // 测试有效路径
-        if let Some(root) = rtree.root_ref() {
-            if !root.entries.is_empty() {
-                let path = vec![0];
-                let node = rtree.get_last_node_mut(&path);
-                assert!(node.is_some());
-            }
-        }
-    }
-
-    #[test]
-    fn test_adjust_tree_upward() {
-        let mut rtree = RTree::new(3);
-
-        // 插入数据
-        rtree.insert(Rectangle::new(0.0, 0.0, 1.0, 1.0), "1".to_string());
-        rtree.insert(Rectangle::new(2.0, 2.0, 3.0, 3.0), "2".to_string());
-        rtree.insert(Rectangle::new(4.0, 4.0, 5.0, 5.0), "3".to_string());
-        rtree.insert(Rectangle::new(6.0, 6.0, 7.0, 7.0), "4".to_string());
-
-        // 获取根节点的MBR作为参考
-        let _original_mbr = if let Some(root) = rtree.root_ref() {
-            root.mbr
-        } else {
-            return;
-        };
-
-        // 调用adjust_tree_upward应该不会改变正确的MBR
-        // 这里我们传入一个空路径，应该不会造成任何变化
-        rtree.adjust_tree_upward(vec![]);
-
-        // 验证根节点MBR没有被错误修改
-        if let Some(root) = rtree.root_ref() {
-            // MBR应该仍然包含所有数据点
-            assert!(root.mbr.contains(&Rectangle::new(0.0, 0.0, 1.0, 1.0)));
-            assert!(root.mbr.contains(&Rectangle::new(6.0, 6.0, 7.0, 7.0)));
-        }
-    }
 }
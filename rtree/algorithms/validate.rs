@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+
+use super::super::node::{Entry, Node};
+use super::super::rtree::RTree;
+use super::utils::index_bboxes;
+
+/// [`RTree::validate`] 的结果：树的统计信息和发现的问题
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TreeReport {
+    /// 树高，空树为 0
+    pub depth: usize,
+    /// 叶子节点数量
+    pub leaf_nodes: usize,
+    /// 索引节点数量
+    pub index_nodes: usize,
+    /// 数据条目数量，GeometryCollection 的每个部分各算一个
+    pub entries: usize,
+    /// 节点的平均填充率（条目数 / 最大条目数）
+    pub fill: f64,
+    /// 违反的不变式，每条以节点路径开头，例如 `root[2][0]`
+    pub violations: Vec<String>,
+}
+
+impl TreeReport {
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// R-tree 结构自检
+impl RTree {
+    /// 检查树的不变式，返回统计信息和所有违反的不变式
+    ///
+    /// 检查的内容：
+    /// - 条目数：每个节点不超过 M；非根叶子节点不少于 m，非根索引节点不为空
+    ///   （删除只在叶子节点下溢时重新插入，索引节点变空才移除）；根索引节点至少两个条目
+    /// - MBR：节点的 MBR 包含每个条目的 MBR，父节点中的子节点 MBR 包含子节点自身的 MBR
+    /// - 层级：叶子节点层级为 0 且只有数据条目，索引节点只有子节点条目，子节点层级比父节点小 1
+    /// - 索引：每个存储的对象在树中的条目数等于它的边界框数（暂停维护索引时跳过）
+    ///
+    /// 遍历整棵树，耗时与对象数成正比
+    pub fn validate(&self) -> TreeReport {
+        let mut report = TreeReport {
+            depth: self.depth(),
+            ..TreeReport::default()
+        };
+        let mut indexed: HashMap<&str, usize> = HashMap::new();
+        let mut slots = 0;
+
+        if let Some(root) = self.get_root() {
+            if root.is_index_node() && root.entries.len() < 2 {
+                report.violations.push(format!(
+                    "root: index root has {} entries, expected at least 2",
+                    root.entries.len()
+                ));
+            }
+            self.validate_node(
+                root,
+                "root".to_string(),
+                true,
+                &mut report,
+                &mut indexed,
+                &mut slots,
+            );
+        }
+        let nodes = report.leaf_nodes + report.index_nodes;
+        if nodes > 0 {
+            report.fill = slots as f64 / (nodes * self.max_entries()) as f64;
+        }
+
+        if !self.index_deferred {
+            let mut missing: Vec<String> = self
+                .geometry_map
+                .iter()
+                .filter_map(|(id, geometry)| {
+                    let expected = index_bboxes(geometry).map_or(0, |rects| rects.len());
+                    let found = indexed.get(id.as_str()).copied().unwrap_or(0);
+                    (found != expected).then(|| {
+                        format!(
+                            "index: object {} has {} entries, expected {}",
+                            id, found, expected
+                        )
+                    })
+                })
+                .collect();
+            missing.sort();
+            report.violations.extend(missing);
+        }
+        report
+    }
+
+    fn validate_node<'a>(
+        &self,
+        node: &'a Node,
+        path: String,
+        is_root: bool,
+        report: &mut TreeReport,
+        indexed: &mut HashMap<&'a str, usize>,
+        slots: &mut usize,
+    ) {
+        let count = node.entries.len();
+        *slots += count;
+        if count > self.max_entries() {
+            report.violations.push(format!(
+                "{}: {} entries, more than the maximum {}",
+                path,
+                count,
+                self.max_entries()
+            ));
+        }
+
+        if node.is_leaf_node() {
+            report.leaf_nodes += 1;
+            if node.level != 0 {
+                report
+                    .violations
+                    .push(format!("{}: leaf at level {}", path, node.level));
+            }
+            if !is_root && count < self.min_entries() {
+                report.violations.push(format!(
+                    "{}: leaf has {} entries, fewer than the minimum {}",
+                    path,
+                    count,
+                    self.min_entries()
+                ));
+            }
+        } else {
+            report.index_nodes += 1;
+            if count == 0 {
+                report
+                    .violations
+                    .push(format!("{}: empty index node", path));
+            }
+        }
+
+        for (i, entry) in node.entries.iter().enumerate() {
+            let entry_path = format!("{}[{}]", path, i);
+            if !node.mbr.contains(entry.mbr()) {
+                report.violations.push(format!(
+                    "{}: entry MBR is not inside the node MBR",
+                    entry_path
+                ));
+            }
+            match entry {
+                Entry::Data { data, .. } => {
+                    report.entries += 1;
+                    *indexed.entry(data.as_str()).or_default() += 1;
+                    if node.is_index_node() {
+                        report
+                            .violations
+                            .push(format!("{}: data entry in an index node", entry_path));
+                    }
+                }
+                Entry::Node { mbr, node: child } => {
+                    if node.is_leaf_node() {
+                        report
+                            .violations
+                            .push(format!("{}: child node in a leaf", entry_path));
+                    }
+                    if child.level + 1 != node.level {
+                        report.violations.push(format!(
+                            "{}: child at level {} under a node at level {}",
+                            entry_path, child.level, node.level
+                        ));
+                    }
+                    if !mbr.contains(&child.mbr) {
+                        report.violations.push(format!(
+                            "{}: parent entry MBR does not contain the child MBR",
+                            entry_path
+                        ));
+                    }
+                    self.validate_node(child, entry_path, false, report, indexed, slots);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::rectangle::Rectangle;
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_after_inserts_and_deletes() {
+        let mut rtree = RTree::new(4);
+        assert!(rtree.validate().is_valid());
+
+        for i in 0..300 {
+            let point = json!({"type": "Point", "coordinates": [(i % 20) as f64, (i / 20) as f64]});
+            assert!(rtree.insert_geojson(i.to_string(), &point.to_string()));
+        }
+        let report = rtree.validate();
+        assert!(report.is_valid(), "{:?}", report.violations);
+        assert_eq!(report.entries, 300);
+        assert_eq!(report.depth, rtree.depth());
+        assert!(report.fill > 0.5 && report.fill <= 1.0);
+
+        // 删除的顺序打乱到多个叶子节点，触发下溢和重新插入
+        for i in (0..300).filter(|i| i % 3 != 0) {
+            assert!(rtree.delete(&((i * 7) % 300).to_string()));
+            let report = rtree.validate();
+            assert!(report.is_valid(), "after {}: {:?}", i, report.violations);
+        }
+    }
+
+    #[test]
+    fn test_validate_reports_violations() {
+        let mut rtree = RTree::new(4);
+        for i in 0..40 {
+            let point = json!({"type": "Point", "coordinates": [i as f64, 0.0]});
+            rtree.insert_geojson(i.to_string(), &point.to_string());
+        }
+
+        // 从索引中移除一个对象的条目，并把根节点第一个子节点的 MBR 移到别处
+        let rect = index_bboxes(rtree.get_geometry("39").unwrap()).unwrap()[0];
+        assert!(rtree.delete_in_rtree(&rect, "39"));
+        let root = rtree.root_mut().as_mut().unwrap();
+        let Entry::Node { mbr, .. } = &mut root.entries[0] else {
+            panic!("root should be an index node");
+        };
+        *mbr = Rectangle::new(1000.0, 1000.0, 1001.0, 1001.0);
+
+        let report = rtree.validate();
+        assert!(!report.is_valid());
+        assert!(report
+            .violations
+            .iter()
+            .any(|v| v.starts_with("root[0]: parent entry MBR does not contain the child MBR")));
+        assert!(report
+            .violations
+            .contains(&"index: object 39 has 0 entries, expected 1".to_string()));
+    }
+}
@@ -0,0 +1,260 @@
+//! R-tree 的 C ABI
+//!
+//! 面向 C/C++ GIS 工具的最小接口：不透明句柄 + 边界框结构体，对象 ID 为 `uint64_t`。
+//! 头文件为 `include/spatio_rtree.h`，修改本文件后使用 `make ffi-header`（cbindgen）重新生成。
+//!
+//! 约定：
+//! - 返回 `int` 的函数：`0` 表示成功，负数表示参数错误（空指针、非法边界框）或内部错误
+//! - 内部 panic 不会跨越 FFI 边界，而是转换为错误码
+//! - 句柄不是线程安全的，多线程使用时需要调用方加锁
+
+use super::rectangle::Rectangle;
+use super::rtree::RTree;
+use std::collections::HashMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// 操作成功
+pub const SPATIO_OK: i32 = 0;
+/// 参数错误：空指针或非法边界框
+pub const SPATIO_ERR_INVALID_ARGUMENT: i32 = -1;
+/// 内部错误
+pub const SPATIO_ERR_INTERNAL: i32 = -2;
+
+/// 轴对齐边界框，要求坐标有限且 `min <= max`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpatioBBox {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+impl SpatioBBox {
+    fn to_rectangle(self) -> Option<Rectangle> {
+        let coords = [self.min_x, self.min_y, self.max_x, self.max_y];
+        if coords.iter().all(|c| c.is_finite())
+            && self.min_x <= self.max_x
+            && self.min_y <= self.max_y
+        {
+            Some(Rectangle::new(
+                self.min_x, self.min_y, self.max_x, self.max_y,
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+/// 不透明句柄：R-tree 加上 ID -> 边界框，删除时需要边界框定位叶子节点
+pub struct SpatioRTree {
+    tree: RTree,
+    boxes: HashMap<u64, Rectangle>,
+}
+
+impl SpatioRTree {
+    fn insert(&mut self, id: u64, rect: Rectangle) {
+        self.delete(id);
+        self.tree.insert(rect, id.to_string());
+        self.boxes.insert(id, rect);
+    }
+
+    fn delete(&mut self, id: u64) -> bool {
+        match self.boxes.remove(&id) {
+            Some(rect) => self.tree.delete_in_rtree(&rect, &id.to_string()),
+            None => false,
+        }
+    }
+
+    fn search(&self, rect: &Rectangle) -> Vec<u64> {
+        let mut ids: Vec<u64> = self
+            .tree
+            .search_bbox(rect)
+            .iter()
+            .filter_map(|data| data.parse().ok())
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+}
+
+/// 创建 R-tree，`max_entries` 为节点最大条目数（至少为 2），参数非法时返回 NULL
+#[no_mangle]
+pub extern "C" fn spatio_rtree_new(max_entries: usize) -> *mut SpatioRTree {
+    if max_entries < 2 {
+        return std::ptr::null_mut();
+    }
+    Box::into_raw(Box::new(SpatioRTree {
+        tree: RTree::new(max_entries),
+        boxes: HashMap::new(),
+    }))
+}
+
+/// 释放 R-tree，传入 NULL 时什么也不做
+///
+/// # Safety
+/// `tree` 必须是 [`spatio_rtree_new`] 返回的句柄，且只能释放一次
+#[no_mangle]
+pub unsafe extern "C" fn spatio_rtree_free(tree: *mut SpatioRTree) {
+    if !tree.is_null() {
+        drop(Box::from_raw(tree));
+    }
+}
+
+/// 插入对象，ID 已存在时替换其边界框
+///
+/// # Safety
+/// `tree` 必须是有效句柄或 NULL
+#[no_mangle]
+pub unsafe extern "C" fn spatio_rtree_insert(
+    tree: *mut SpatioRTree,
+    id: u64,
+    bbox: SpatioBBox,
+) -> i32 {
+    let (Some(tree), Some(rect)) = (tree.as_mut(), bbox.to_rectangle()) else {
+        return SPATIO_ERR_INVALID_ARGUMENT;
+    };
+    match catch_unwind(AssertUnwindSafe(|| tree.insert(id, rect))) {
+        Ok(()) => SPATIO_OK,
+        Err(_) => SPATIO_ERR_INTERNAL,
+    }
+}
+
+/// 删除对象：返回 1 表示已删除，0 表示 ID 不存在，负数为错误码
+///
+/// # Safety
+/// `tree` 必须是有效句柄或 NULL
+#[no_mangle]
+pub unsafe extern "C" fn spatio_rtree_delete(tree: *mut SpatioRTree, id: u64) -> i32 {
+    let Some(tree) = tree.as_mut() else {
+        return SPATIO_ERR_INVALID_ARGUMENT;
+    };
+    match catch_unwind(AssertUnwindSafe(|| tree.delete(id))) {
+        Ok(deleted) => i32::from(deleted),
+        Err(_) => SPATIO_ERR_INTERNAL,
+    }
+}
+
+/// 查询与 `query` 相交的对象，按 ID 升序写入 `out`（最多 `capacity` 个）
+///
+/// 返回匹配总数（可能大于 `capacity`，调用方可据此扩容后重试），负数为错误码。
+/// `capacity` 为 0 时 `out` 可以为 NULL，用于只获取数量
+///
+/// # Safety
+/// `tree` 必须是有效句柄或 NULL；`out` 指向至少 `capacity` 个 `uint64_t` 的可写内存
+#[no_mangle]
+pub unsafe extern "C" fn spatio_rtree_search(
+    tree: *const SpatioRTree,
+    query: SpatioBBox,
+    out: *mut u64,
+    capacity: usize,
+) -> i64 {
+    let (Some(tree), Some(rect)) = (tree.as_ref(), query.to_rectangle()) else {
+        return SPATIO_ERR_INVALID_ARGUMENT as i64;
+    };
+    if out.is_null() && capacity > 0 {
+        return SPATIO_ERR_INVALID_ARGUMENT as i64;
+    }
+    let Ok(ids) = catch_unwind(AssertUnwindSafe(|| tree.search(&rect))) else {
+        return SPATIO_ERR_INTERNAL as i64;
+    };
+
+    let written = ids.len().min(capacity);
+    if written > 0 {
+        std::ptr::copy_nonoverlapping(ids.as_ptr(), out, written);
+    }
+    ids.len() as i64
+}
+
+/// 对象数量，`tree` 为 NULL 时返回 0
+///
+/// # Safety
+/// `tree` 必须是有效句柄或 NULL
+#[no_mangle]
+pub unsafe extern "C" fn spatio_rtree_len(tree: *const SpatioRTree) -> usize {
+    tree.as_ref().map_or(0, |tree| tree.boxes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bbox(min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> SpatioBBox {
+        SpatioBBox {
+            min_x,
+            min_y,
+            max_x,
+            max_y,
+        }
+    }
+
+    #[test]
+    fn test_ffi_lifecycle() {
+        unsafe {
+            let tree = spatio_rtree_new(4);
+            assert!(!tree.is_null());
+
+            for id in 0..20u64 {
+                let x = id as f64;
+                assert_eq!(
+                    spatio_rtree_insert(tree, id, bbox(x, x, x + 0.5, x + 0.5)),
+                    SPATIO_OK
+                );
+            }
+            // 重复插入替换边界框
+            assert_eq!(
+                spatio_rtree_insert(tree, 3, bbox(100.0, 100.0, 101.0, 101.0)),
+                SPATIO_OK
+            );
+            assert_eq!(spatio_rtree_len(tree), 20);
+
+            let mut out = [0u64; 4];
+            let query = bbox(0.0, 0.0, 5.0, 5.0);
+            let total = spatio_rtree_search(tree, query, out.as_mut_ptr(), out.len());
+            assert_eq!(total, 5); // 0,1,2,4,5
+            assert_eq!(out, [0, 1, 2, 4]);
+            assert_eq!(spatio_rtree_search(tree, query, std::ptr::null_mut(), 0), 5);
+
+            assert_eq!(spatio_rtree_delete(tree, 4), 1);
+            assert_eq!(spatio_rtree_delete(tree, 4), 0);
+            let total = spatio_rtree_search(tree, query, out.as_mut_ptr(), out.len());
+            assert_eq!(total, 4);
+            assert_eq!(out, [0, 1, 2, 5]);
+            assert_eq!(spatio_rtree_len(tree), 19);
+
+            spatio_rtree_free(tree);
+        }
+    }
+
+    #[test]
+    fn test_ffi_invalid_arguments() {
+        unsafe {
+            assert!(spatio_rtree_new(1).is_null());
+
+            let tree = spatio_rtree_new(8);
+            assert_eq!(
+                spatio_rtree_insert(tree, 1, bbox(1.0, 0.0, 0.0, 1.0)),
+                SPATIO_ERR_INVALID_ARGUMENT
+            );
+            assert_eq!(
+                spatio_rtree_insert(tree, 1, bbox(f64::NAN, 0.0, 1.0, 1.0)),
+                SPATIO_ERR_INVALID_ARGUMENT
+            );
+            assert_eq!(
+                spatio_rtree_search(tree, bbox(0.0, 0.0, 1.0, 1.0), std::ptr::null_mut(), 1),
+                SPATIO_ERR_INVALID_ARGUMENT as i64
+            );
+
+            let null = std::ptr::null_mut();
+            assert_eq!(
+                spatio_rtree_insert(null, 1, bbox(0.0, 0.0, 1.0, 1.0)),
+                SPATIO_ERR_INVALID_ARGUMENT
+            );
+            assert_eq!(spatio_rtree_delete(null, 1), SPATIO_ERR_INVALID_ARGUMENT);
+            assert_eq!(spatio_rtree_len(null), 0);
+            spatio_rtree_free(null);
+
+            spatio_rtree_free(tree);
+        }
+    }
+}
@@ -1,10 +1,14 @@
 pub mod algorithms;
-pub mod node;
-pub mod rectangle;
+pub mod ffi;
 #[allow(clippy::module_inception)]
 pub mod rtree;
 
+// 矩形、节点和只按边界框索引的核心算法在 spatio-rtree 中，不依赖 std
+pub use spatio_rtree::{node, rectangle, RTreeIndex};
+
 // 重新导出主要类型
+pub use algorithms::filter::{FieldRange, GeometryKind, ObjectFilter};
+pub use algorithms::strings::StoredValue;
 pub use node::{Entry, Node};
 pub use rectangle::Rectangle;
-pub use rtree::{GeoItem, RTree};
+pub use rtree::{GeoItem, RTree, SCHEMA_VERSION};
@@ -1,12 +1,16 @@
+use super::algorithms::cursor::next_version;
 use super::node::{Entry, Node, NodeType};
 use super::rectangle::Rectangle;
 use derive_more::Display;
 use geo::Geometry;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use spatio_rtree::RTreeIndex;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::OnceLock;
 
 #[cfg(test)]
 use crate::storage::geometry_utils::geometry_to_geojson;
+use crate::storage::{is_glob_pattern, GlobPattern};
 
 #[derive(Debug, Display, Clone, Serialize, Deserialize)]
 #[display(
@@ -60,34 +64,122 @@ pub struct DataEntry {
     pub data: String,
 }
 
+/// 序列化格式（schema）版本
+///
+/// RTree 可以直接用 serde（bincode、serde_json 等）持久化，序列化结果的第一个字段是该版本号。
+/// 修改 RTree/Node/Rectangle 的序列化结构时需要递增，旧版本程序读取新版本数据时会报错，
+/// 而不是得到错乱的树（3：增加 STRING 对象）
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// 序列化时写入 [`SCHEMA_VERSION`]，反序列化时拒绝比当前程序更新的版本
+///
+/// 不保存任何状态：内存中的树总是当前版本
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SchemaVersion;
+
+impl Serialize for SchemaVersion {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(SCHEMA_VERSION)
+    }
+}
+
+impl<'de> Deserialize<'de> for SchemaVersion {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let version = u32::deserialize(deserializer)?;
+        if version > SCHEMA_VERSION {
+            return Err(serde::de::Error::custom(format!(
+                "unsupported R-tree schema version {} (this build supports up to {})",
+                version, SCHEMA_VERSION
+            )));
+        }
+        Ok(SchemaVersion)
+    }
+}
+
 /// R-tree主结构
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RTree {
-    /// 根节点
-    root: Option<Box<Node>>,
-    /// 最大条目数M
-    max_entries: usize,
-    /// 最小条目数m（通常为M/2）
-    min_entries: usize,
+    /// 序列化格式版本，必须是第一个字段，读取方可以先检查版本
+    #[serde(default)]
+    schema_version: SchemaVersion,
+    /// 空间索引（根节点、最大和最小条目数），bincode 中与之前直接保存这三个字段的格式相同
+    pub(crate) index: RTreeIndex,
     pub(crate) geometry_map: HashMap<String, Geometry>,
+    /// 按 ID 排序的对象 ID，第一次按 ID 顺序查询时建立，之后随插入和删除更新，
+    /// 见 [`search_ids`](Self::search_ids)
+    #[serde(skip)]
+    pub(crate) ordered_ids: OnceLock<BTreeSet<String>>,
     pub(crate) geojson_map: HashMap<String, String>,
+    /// 对象标签：对象 ID -> 标签集合
+    #[serde(default)]
+    pub(crate) tags: HashMap<String, BTreeSet<String>>,
+    /// 标签倒排索引：标签 -> 对象 ID 集合
+    #[serde(default)]
+    pub(crate) tag_index: HashMap<String, BTreeSet<String>>,
+    /// 对象过期时间：对象 ID -> 过期时刻（Unix 纪元以来的纳秒），没有 TTL 的对象不在表中
+    #[serde(default)]
+    pub(crate) expires: HashMap<String, u64>,
+    /// 已应用的最大 AOF 序列号，随快照一起保存，重放时跳过已应用的记录
+    #[serde(default)]
+    applied_seq: u64,
+    /// STRING 对象：对象 ID -> 文本，不进入空间索引，见 [`set_string`](Self::set_string)
+    #[serde(default)]
+    pub(crate) strings: HashMap<String, String>,
+    /// 已从数据库中移除（DROP 或卸载）：持有旧引用的写操作必须重新查找 collection，
+    /// 否则写入会落在游离的树上，而 AOF 中却有记录
+    #[serde(skip)]
+    detached: bool,
+    /// 恢复期间暂停维护空间索引，见 [`defer_indexing`](Self::defer_indexing)
+    #[serde(skip)]
+    pub(crate) index_deferred: bool,
+    /// 对象最近一次修改的版本号，用于游标分页识别两页之间被修改的对象
+    #[serde(skip)]
+    pub(crate) versions: HashMap<String, u64>,
+    /// 树创建或加载时的版本号，versions 中没有记录的对象视为在此时修改
+    #[serde(skip, default = "next_version")]
+    pub(crate) base_version: u64,
 }
 
 impl RTree {
     /// 创建新的R-tree
     pub fn new(max_entries: usize) -> Self {
-        assert!(max_entries >= 2, "Max entries must be at least 2");
-        let min_entries = max_entries / 2;
-
         RTree {
-            root: None,
-            max_entries,
-            min_entries,
+            schema_version: SchemaVersion,
+            index: RTreeIndex::new(max_entries),
             geometry_map: HashMap::new(),
+            ordered_ids: OnceLock::new(),
             geojson_map: HashMap::new(),
+            tags: HashMap::new(),
+            tag_index: HashMap::new(),
+            expires: HashMap::new(),
+            applied_seq: 0,
+            strings: HashMap::new(),
+            detached: false,
+            index_deferred: false,
+            versions: HashMap::new(),
+            base_version: next_version(),
         }
     }
 
+    /// 已应用的最大 AOF 序列号
+    pub fn applied_seq(&self) -> u64 {
+        self.applied_seq
+    }
+
+    /// 记录已应用的 AOF 序列号（只增不减）
+    pub fn mark_applied(&mut self, seq: u64) {
+        self.applied_seq = self.applied_seq.max(seq);
+    }
+
+    /// 标记为已从数据库中移除，必须在持有写锁时调用
+    pub(crate) fn detach(&mut self) {
+        self.detached = true;
+    }
+
+    pub(crate) fn is_detached(&self) -> bool {
+        self.detached
+    }
+
     /// 使用默认参数创建R-tree（M=10, m=5）
     pub fn with_default_capacity() -> Self {
         Self::new(10)
@@ -95,74 +187,52 @@ impl RTree {
 
     /// 检查R-tree是否为空
     pub fn is_empty(&self) -> bool {
-        self.root.is_none()
+        self.index.is_empty()
     }
 
     /// 获取R-tree的根节点MBR
     pub fn root_mbr(&self) -> Option<&Rectangle> {
-        self.root.as_ref().map(|node| &node.mbr)
+        self.index.root().map(|node| &node.mbr)
     }
 
     /// 获取最大条目数
     pub fn max_entries(&self) -> usize {
-        self.max_entries
+        self.index.max_entries()
     }
 
     /// 获取最小条目数
     pub fn min_entries(&self) -> usize {
-        self.min_entries
+        self.index.min_entries()
     }
 
     /// 获取树的深度
     pub fn depth(&self) -> usize {
-        self.root.as_ref().map_or(0, |node| node.level + 1)
+        self.index.depth()
     }
 
     /// 获取总的条目数量
     pub fn len(&self) -> usize {
-        self.root
-            .as_ref()
-            .map_or(0, |node| self.count_entries(node))
-    }
-
-    /// 统计节点中的条目数量
-    fn count_entries(&self, node: &Node) -> usize {
-        if node.is_leaf_node() {
-            node.entries.len()
-        } else {
-            node.entries
-                .iter()
-                .map(|entry| match entry {
-                    Entry::Node { node, .. } => self.count_entries(node),
-                    Entry::Data { .. } => 1,
-                })
-                .sum()
-        }
+        self.index.len()
     }
 
     /// 内部方法：获取根节点的可变引用
     pub(crate) fn root_mut(&mut self) -> &mut Option<Box<Node>> {
-        &mut self.root
+        self.index.root_mut()
     }
 
     /// 内部方法：获取根节点的引用
     pub(crate) fn root_ref(&self) -> &Option<Box<Node>> {
-        &self.root
+        self.index.root_ref()
     }
 
     /// 获取根节点（用于算法访问，如 KNN 搜索）
     pub fn get_root(&self) -> Option<&Node> {
-        self.root.as_ref().map(|b| b.as_ref())
+        self.index.root()
     }
 
     /// 内部方法：获取最大条目数
     pub(crate) fn max_entries_internal(&self) -> usize {
-        self.max_entries
-    }
-
-    /// 内部方法：获取最小条目数
-    pub(crate) fn min_entries_internal(&self) -> usize {
-        self.min_entries
+        self.index.max_entries()
     }
 
     pub fn get_geometry(&self, data_id: &str) -> Option<&Geometry> {
@@ -183,8 +253,31 @@ impl RTree {
         })
     }
 
+    /// 对象数量，包括 STRING 对象
     pub fn count(&self) -> usize {
-        self.geometry_map.len()
+        self.geometry_map.len() + self.strings.len()
+    }
+
+    /// ID 匹配 glob 模式（`*`、`?`）的对象（包括 STRING 对象），按 ID 升序；
+    /// 模式不含通配符时只查找这一个 ID
+    pub fn ids_matching(&self, pattern: &str) -> Vec<String> {
+        if !is_glob_pattern(pattern) {
+            return self
+                .contains(pattern)
+                .then(|| pattern.to_string())
+                .into_iter()
+                .collect();
+        }
+        let pattern = GlobPattern::new(pattern);
+        let mut ids: Vec<String> = self
+            .geometry_map
+            .keys()
+            .chain(self.strings.keys())
+            .filter(|id| pattern.matches(id))
+            .cloned()
+            .collect();
+        ids.sort_unstable();
+        ids
     }
 
     /// 导出树结构为JSON格式
@@ -199,12 +292,11 @@ impl RTree {
     fn create_tree_visualization(&self) -> TreeVisualization {
         TreeVisualization {
             root: self
-                .root
-                .as_ref()
+                .get_root()
                 .map(|node| self.create_node_visualization(node)),
             config: TreeConfig {
-                max_entries: self.max_entries,
-                min_entries: self.min_entries,
+                max_entries: self.max_entries(),
+                min_entries: self.min_entries(),
             },
         }
     }
@@ -407,4 +499,64 @@ mod tests {
         assert!(json.contains("\"max_entries\": 3"));
         assert!(json.contains("\"min_entries\": 1"));
     }
+
+    fn sample_tree() -> RTree {
+        let mut rtree = RTree::new(4);
+        for i in 0..10 {
+            let point = Geometry::Point(geo::Point::new(i as f64, i as f64));
+            rtree.insert_geojson(format!("p{}", i), &geometry_to_geojson(&point).to_string());
+        }
+        rtree
+    }
+
+    #[test]
+    fn test_serde_round_trip_with_schema_version() {
+        let rtree = sample_tree();
+
+        // serde_json：版本号是第一个字段
+        let json = serde_json::to_string(&rtree).unwrap();
+        assert!(json.starts_with(&format!(r#"{{"schema_version":{}"#, SCHEMA_VERSION)));
+        let loaded: RTree = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.count(), 10);
+        assert_eq!(loaded.len(), 10);
+
+        // bincode：前 4 个字节是版本号
+        let bytes = bincode::serialize(&rtree).unwrap();
+        assert_eq!(bytes[..4], SCHEMA_VERSION.to_le_bytes());
+        // 接着是空间索引的根节点、最大和最小条目数，与拆分出 spatio-rtree 之前的格式相同
+        let index = (rtree.get_root(), rtree.max_entries(), rtree.min_entries());
+        let index = bincode::serialize(&index).unwrap();
+        assert_eq!(bytes[4..4 + index.len()], index[..]);
+        let loaded: RTree = bincode::deserialize(&bytes).unwrap();
+        let query = Rectangle::new(0.0, 0.0, 3.5, 3.5);
+        let mut found = loaded.search_bbox(&query);
+        found.sort();
+        assert_eq!(found, vec!["p0", "p1", "p2", "p3"]);
+
+        // 节点和矩形也可以单独序列化
+        let root = loaded.get_root().unwrap();
+        let node: Node = bincode::deserialize(&bincode::serialize(root).unwrap()).unwrap();
+        assert_eq!(node.mbr, root.mbr);
+        let rect: Rectangle =
+            serde_json::from_str(&serde_json::to_string(&query).unwrap()).unwrap();
+        assert_eq!(rect, query);
+    }
+
+    #[test]
+    fn test_serde_rejects_newer_schema_version() {
+        let mut value = serde_json::to_value(sample_tree()).unwrap();
+        value["schema_version"] = serde_json::json!(SCHEMA_VERSION + 1);
+        let err = serde_json::from_value::<RTree>(value.clone()).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("unsupported R-tree schema version"));
+
+        let mut bytes = bincode::serialize(&sample_tree()).unwrap();
+        bytes[..4].copy_from_slice(&(SCHEMA_VERSION + 1).to_le_bytes());
+        assert!(bincode::deserialize::<RTree>(&bytes).is_err());
+
+        // 没有版本号字段的旧 JSON 数据按旧版本读取
+        value.as_object_mut().unwrap().remove("schema_version");
+        assert_eq!(serde_json::from_value::<RTree>(value).unwrap().count(), 10);
+    }
 }
@@ -0,0 +1,181 @@
+//! 内存分配统计：包装全局分配器，统计当前/峰值分配字节数，供 INFO memory 和 MEMORY 命令使用
+//!
+//! 服务器二进制把 [`TrackingAllocator`] 安装为 `#[global_allocator]`；
+//! 作为库使用时不安装，[`allocator_stats`] 返回 `None`。
+//! 每次分配只多两次 relaxed 原子操作（与 Redis 的 zmalloc used_memory 相同的做法）
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, AtomicUsize, Ordering};
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+// 被包装的分配器名称，分配路径中不能分配内存，因此拆成指针和长度保存
+static NAME_PTR: AtomicPtr<u8> = AtomicPtr::new(std::ptr::null_mut());
+static NAME_LEN: AtomicUsize = AtomicUsize::new(0);
+
+/// 统计分配字节数的全局分配器包装
+///
+/// 计数是进程级的：同一进程中只应安装一个实例
+pub struct TrackingAllocator<A = System> {
+    inner: A,
+    name: &'static str,
+}
+
+impl TrackingAllocator<System> {
+    /// 包装系统分配器
+    pub const fn system() -> Self {
+        Self::new(System, "system")
+    }
+}
+
+impl<A> TrackingAllocator<A> {
+    /// 包装任意分配器，`name` 显示在 INFO 的 `allocator` 字段
+    pub const fn new(inner: A, name: &'static str) -> Self {
+        Self { inner, name }
+    }
+
+    #[inline]
+    fn record_alloc(&self, size: usize) {
+        if !INSTALLED.load(Ordering::Relaxed) {
+            NAME_PTR.store(self.name.as_ptr() as *mut u8, Ordering::Relaxed);
+            NAME_LEN.store(self.name.len(), Ordering::Relaxed);
+            INSTALLED.store(true, Ordering::Release);
+        }
+        let allocated = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK.fetch_max(allocated, Ordering::Relaxed);
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn record_dealloc(&self, size: usize) {
+        ALLOCATED.fetch_sub(size, Ordering::Relaxed);
+    }
+}
+
+// SAFETY: 所有分配和释放都原样转发给内部分配器，只额外更新计数
+unsafe impl<A: GlobalAlloc> GlobalAlloc for TrackingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        if !ptr.is_null() {
+            self.record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            self.record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout);
+        self.record_dealloc(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.inner.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            self.record_dealloc(layout.size());
+            self.record_alloc(new_size);
+        }
+        new_ptr
+    }
+}
+
+/// 分配器统计快照
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocatorStats {
+    /// 被包装的分配器名称
+    pub allocator: &'static str,
+    /// 当前已分配、尚未释放的字节数
+    pub allocated_bytes: usize,
+    /// 启动（或上次 [`reset_peak`]）以来的峰值
+    pub peak_bytes: usize,
+    /// 累计分配次数（realloc 计一次）
+    pub allocations: u64,
+}
+
+/// 当前的分配器统计；没有安装 [`TrackingAllocator`] 时返回 `None`
+pub fn allocator_stats() -> Option<AllocatorStats> {
+    if !INSTALLED.load(Ordering::Acquire) {
+        return None;
+    }
+    let ptr = NAME_PTR.load(Ordering::Relaxed);
+    let len = NAME_LEN.load(Ordering::Relaxed);
+    // SAFETY: 指针和长度来自同一个 &'static str，在 INSTALLED 之前写入
+    let allocator = unsafe { std::str::from_utf8_unchecked(std::slice::from_raw_parts(ptr, len)) };
+
+    Some(AllocatorStats {
+        allocator,
+        allocated_bytes: ALLOCATED.load(Ordering::Relaxed),
+        peak_bytes: PEAK.load(Ordering::Relaxed),
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+    })
+}
+
+/// 把峰值重置为当前值，用于观察某一段时间（例如一次批量导入）内的内存增长
+pub fn reset_peak() {
+    PEAK.store(ALLOCATED.load(Ordering::Relaxed), Ordering::Relaxed);
+}
+
+/// 进程常驻内存（RSS），目前只支持 Linux
+#[cfg(target_os = "linux")]
+pub fn resident_memory_bytes() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    // SAFETY: sysconf 没有前置条件
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    u64::try_from(page_size)
+        .ok()
+        .map(|page_size| pages * page_size)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn resident_memory_bytes() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracking_allocator_counts() {
+        // 测试进程没有安装全局分配器，直接调用实例方法；计数是全局的，这里只检查增量
+        let allocator = TrackingAllocator::system();
+        let layout = Layout::from_size_align(4096, 8).unwrap();
+
+        unsafe {
+            let before = ALLOCATED.load(Ordering::Relaxed);
+            let ptr = allocator.alloc(layout);
+            assert!(!ptr.is_null());
+            let stats = allocator_stats().unwrap();
+            assert_eq!(stats.allocator, "system");
+            assert!(stats.allocated_bytes >= before + 4096);
+            assert!(stats.peak_bytes >= stats.allocated_bytes);
+            assert!(stats.allocations >= 1);
+
+            let ptr = allocator.realloc(ptr, layout, 8192);
+            assert!(!ptr.is_null());
+            assert!(ALLOCATED.load(Ordering::Relaxed) >= before + 8192);
+
+            allocator.dealloc(ptr, Layout::from_size_align(8192, 8).unwrap());
+            assert_eq!(ALLOCATED.load(Ordering::Relaxed), before);
+        }
+
+        reset_peak();
+        let stats = allocator_stats().unwrap();
+        assert_eq!(stats.peak_bytes, stats.allocated_bytes);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_resident_memory() {
+        assert!(resident_memory_bytes().unwrap() > 0);
+    }
+}
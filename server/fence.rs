@@ -0,0 +1,1510 @@
+//! 地理围栏
+//!
+//! `NEARBY ... RADIUS r FENCE` 或 `INTERSECTS ... FENCE` 在连接上注册一个围栏，之后该 collection
+//! 上的 SET/DELETE 使对象进入、离开或穿过围栏时，向注册围栏的连接推送一条 JSON 事件：
+//!
+//! ```json
+//! {"fence":1,"command":"set","detect":"enter","collection":"fleet","id":"truck1","object":{...},"ts":1700000000000000000}
+//! ```
+//!
+//! RESP2 连接上事件是一个批量字符串；`HELLO 3` 协商 RESP3 后是推送帧 `>2 fence <事件>`，
+//! WebSocket 上是文本帧。
+//!
+//! `FENCE DWELL seconds` 额外检测停留：对象进入围栏后连续停留 `seconds` 秒（期间没有离开或被删除）时
+//! 产生一次 `"detect":"dwell"` 事件，`object` 为对象当前的 GeoJSON，`ts` 为达到停留时间的时刻。
+//! 只有注册之后进入围栏的对象才会计时。
+//!
+//! 围栏随连接存在，连接关闭时自动注销。`SETCHAN name [WEBHOOK url] NEARBY|INTERSECTS ... [FENCE]` 注册不属于任何连接的
+//! 围栏频道：事件中用 `"channel":"name"` 代替 `"fence":id`，发布到同名的 pub/sub 频道（见 [`crate::server::pubsub`]），
+//! 配置了 webhook 时同时 POST 到该地址（见 [`crate::server::webhook`]）。频道一直存在，直到 `DELCHAN name`；
+//! 频道的定义写入 AOF 和快照（[`GeoDatabase::set_channel`](crate::storage::GeoDatabase::set_channel)），
+//! 服务器启动时重新注册。连接注册的围栏随连接消失，不会持久化。`FENCES LIST` 列出所有围栏，
+//! `FENCES CONTAINING lon lat` 返回区域包含该点的围栏 ID（按围栏区域建立的 R-tree 查找）。
+//!
+//! [`FenceManager::run`] 从数据库的事件总线
+//! （[`GeoDatabase::subscribe_events`](crate::storage::GeoDatabase::subscribe_events)）读取修改，
+//! 同一个 collection 的事件顺序与写入顺序一致，并定期检查到期的停留计时。DROP 不产生围栏事件，
+//! 只清除该 collection 上的停留计时
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use geo::{Centroid, Geometry, Intersects, LineString, Point, Rect, Within};
+use tokio::sync::mpsc;
+use tokio::time::MissedTickBehavior;
+
+use crate::commands::ArgumentParser;
+use crate::protocol::parser::RespValue;
+use crate::rtree::algorithms::knn::{point_to_geometry_distance, DistanceMetric};
+use crate::rtree::rectangle::Rectangle;
+use crate::rtree::RTree;
+use crate::server::pubsub::PubSub;
+use crate::server::webhook::{Webhook, WebhookUrl};
+use crate::storage::geometry_utils::geometry_to_geojson;
+use crate::storage::namespace;
+use crate::storage::{
+    DatabaseEvent, EventReceiver, GeoDatabase, SharedClock, StatEvent, SystemClock,
+};
+
+/// 检查停留计时的间隔，dwell 事件最多比 DWELL 秒数晚这么久
+const DWELL_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 地球平均半径（米），与 Haversine 距离相同
+const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+
+/// 围栏的区域
+#[derive(Debug, Clone)]
+pub enum FenceArea {
+    /// NEARBY：以 (lon, lat) 为圆心、半径 `radius` 米的圆
+    Circle { lon: f64, lat: f64, radius: f64 },
+    /// INTERSECTS：与几何体相交，`within` 为 true 时要求完全包含在几何体内
+    Geometry { geometry: Geometry, within: bool },
+}
+
+impl FenceArea {
+    /// 对象是否在围栏内
+    pub fn contains(&self, object: &Geometry) -> bool {
+        match self {
+            FenceArea::Circle { lon, lat, radius } => {
+                point_to_geometry_distance(*lon, *lat, object) <= *radius
+            }
+            FenceArea::Geometry { geometry, within } => {
+                if *within {
+                    object.is_within(geometry)
+                } else {
+                    object.intersects(geometry)
+                }
+            }
+        }
+    }
+
+    /// 建立索引用的几何体：圆使用外接的经纬度矩形，其他使用几何体本身
+    fn index_geometry(&self) -> Geometry {
+        match self {
+            FenceArea::Circle { lon, lat, radius } => {
+                // 略微放大，避免浮点误差漏掉圆周上的点，精确判断由 contains 完成
+                let dlat = (radius / EARTH_RADIUS_METERS).to_degrees() * 1.01;
+                let (min_lat, max_lat) = ((lat - dlat).max(-90.0), (lat + dlat).min(90.0));
+                let widest = min_lat.abs().max(max_lat.abs());
+                let dlon = if widest >= 90.0 {
+                    180.0
+                } else {
+                    dlat / widest.to_radians().cos()
+                };
+                // 跨过极点或 180 度经线时使用整个经度范围
+                let (min_lon, max_lon) = if lon - dlon < -180.0 || lon + dlon > 180.0 {
+                    (-180.0, 180.0)
+                } else {
+                    (lon - dlon, lon + dlon)
+                };
+                Geometry::Polygon(
+                    Rect::new(
+                        geo::coord! { x: min_lon, y: min_lat },
+                        geo::coord! { x: max_lon, y: max_lat },
+                    )
+                    .to_polygon(),
+                )
+            }
+            FenceArea::Geometry { geometry, .. } => geometry.clone(),
+        }
+    }
+
+    /// 路径是否经过围栏
+    fn touches(&self, path: &Geometry) -> bool {
+        match self {
+            FenceArea::Circle { lon, lat, radius } => {
+                point_to_geometry_distance(*lon, *lat, path) <= *radius
+            }
+            FenceArea::Geometry { geometry, .. } => path.intersects(geometry),
+        }
+    }
+}
+
+/// 对象相对围栏的变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Detect {
+    /// 从围栏外（或不存在）进入围栏
+    Enter,
+    /// 从围栏内离开，或在围栏内被删除
+    Exit,
+    /// 修改前后都在围栏外，但从旧位置到新位置的直线经过围栏
+    Cross,
+    /// 进入围栏后停留达到 DWELL 秒数，由计时产生，[`Detect::of`] 不会返回
+    Dwell,
+}
+
+impl Detect {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Detect::Enter => "enter",
+            Detect::Exit => "exit",
+            Detect::Cross => "cross",
+            Detect::Dwell => "dwell",
+        }
+    }
+
+    /// 判断一次修改对围栏的影响，没有变化（一直在内或一直在外）时返回 None
+    ///
+    /// `previous` 为修改前的几何体（新对象为 None），`current` 为修改后的几何体（删除时为 None）
+    pub fn of(
+        area: &FenceArea,
+        previous: Option<&Geometry>,
+        current: Option<&Geometry>,
+    ) -> Option<Self> {
+        let was_inside = previous.is_some_and(|g| area.contains(g));
+        let is_inside = current.is_some_and(|g| area.contains(g));
+        match (was_inside, is_inside) {
+            (false, true) => Some(Detect::Enter),
+            (true, false) => Some(Detect::Exit),
+            (true, true) => None,
+            (false, false) => {
+                // 用中心点连线近似对象的移动路径
+                let from = previous?.centroid()?;
+                let to = current?.centroid()?;
+                let path = Geometry::LineString(LineString::from(vec![from, to]));
+                area.touches(&path).then_some(Detect::Cross)
+            }
+        }
+    }
+}
+
+/// 从 FENCE 命令解析出的围栏
+#[derive(Debug, Clone)]
+pub struct FenceSpec {
+    pub collection: String,
+    pub area: FenceArea,
+    /// DWELL 秒数，None 表示不检测停留
+    pub dwell: Option<u64>,
+}
+
+impl FenceSpec {
+    /// 解析 `NEARBY ... FENCE` 或 `INTERSECTS ... FENCE`，不带 FENCE 时返回 None
+    ///
+    /// 围栏只使用查询的区域和 DWELL，WHERETAG 以及 NEARBY 的 WITHIN、TYPE、WHERE 不能与 FENCE 同时使用。
+    /// `default_metric` 是服务器的默认距离度量（`distance_metric` 配置），NEARBY 不带 DISTANCE 时使用
+    pub fn parse(
+        command: &str,
+        args: &[RespValue],
+        default_metric: DistanceMetric,
+    ) -> Result<Option<Self>, String> {
+        match command.to_uppercase().as_str() {
+            "NEARBY" => {
+                let args = ArgumentParser::new(args, "NEARBY").parse_nearby_args()?;
+                if !args.fence {
+                    return Ok(None);
+                }
+                if !args.tags.is_empty() || args.region.is_some() {
+                    return Err("ERR FENCE cannot be combined with WHERETAG or WITHIN".to_string());
+                }
+                if !args.filter.is_empty() {
+                    return Err("ERR FENCE cannot be combined with TYPE or WHERE".to_string());
+                }
+                // 围栏按 Haversine 米判断进出，查询实际使用的度量（显式 DISTANCE 或服务器默认）必须一致
+                if args.metric.unwrap_or(default_metric) != DistanceMetric::Haversine {
+                    return Err("ERR FENCE only supports DISTANCE HAVERSINE".to_string());
+                }
+                Ok(Some(Self {
+                    collection: args.collection_id,
+                    area: FenceArea::Circle {
+                        lon: args.query_lon,
+                        lat: args.query_lat,
+                        radius: args.max_radius.unwrap_or_default(),
+                    },
+                    dwell: args.dwell,
+                }))
+            }
+            "INTERSECTS" => {
+                let args = ArgumentParser::new(args, "INTERSECTS").parse_intersects_args()?;
+                if !args.fence {
+                    return Ok(None);
+                }
+                if !args.tags.is_empty() {
+                    return Err("ERR FENCE cannot be combined with WHERETAG".to_string());
+                }
+                Ok(Some(Self {
+                    collection: args.collection_id,
+                    area: FenceArea::Geometry {
+                        geometry: args.geometry,
+                        within: args.within,
+                    },
+                    dwell: args.dwell,
+                }))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// 从 `SETCHAN` 命令解析出的围栏频道
+#[derive(Debug, Clone)]
+pub struct ChannelSpec {
+    pub name: String,
+    pub webhook: Option<WebhookUrl>,
+    pub fence: FenceSpec,
+}
+
+impl ChannelSpec {
+    /// 解析 `SETCHAN name [WEBHOOK url] NEARBY|INTERSECTS ... [FENCE]`，围栏命令末尾的 FENCE 可以省略，
+    /// `default_metric` 见 [`FenceSpec::parse`]
+    pub fn parse(args: &[RespValue], default_metric: DistanceMetric) -> Result<Self, String> {
+        let text = |i: usize| -> Result<&str, String> {
+            args.get(i)
+                .and_then(RespValue::as_str)
+                .ok_or_else(|| "ERR wrong number of arguments for 'SETCHAN' command".to_string())
+        };
+        let name = text(0)?.to_string();
+        let (webhook, command_at) = if text(1)?.eq_ignore_ascii_case("WEBHOOK") {
+            (Some(WebhookUrl::parse(text(2)?)?), 3)
+        } else {
+            (None, 1)
+        };
+        let command = text(command_at)?;
+        if !matches!(command.to_uppercase().as_str(), "NEARBY" | "INTERSECTS") {
+            return Err(format!(
+                "ERR SETCHAN expects NEARBY or INTERSECTS, got '{}'",
+                command
+            ));
+        }
+
+        let mut fence_args = args[command_at + 1..].to_vec();
+        let has_fence = fence_args.iter().any(|arg| {
+            arg.as_str()
+                .is_some_and(|s| s.eq_ignore_ascii_case("FENCE"))
+        });
+        if !has_fence {
+            fence_args.push(RespValue::bulk("FENCE"));
+        }
+        let fence = FenceSpec::parse(command, &fence_args, default_metric)?
+            .ok_or_else(|| "ERR syntax error near FENCE".to_string())?;
+        Ok(Self {
+            name,
+            webhook,
+            fence,
+        })
+    }
+}
+
+/// FENCES LIST 列出的一个围栏
+#[derive(Debug, Clone)]
+pub struct FenceInfo {
+    pub id: u64,
+    pub collection: String,
+    /// 围栏频道的名称，连接注册的围栏为 None
+    pub channel: Option<String>,
+    /// 围栏频道的 webhook 地址
+    pub webhook: Option<String>,
+    pub area: FenceArea,
+    /// DWELL 秒数
+    pub dwell: Option<u64>,
+}
+
+impl FenceInfo {
+    /// 键值对交替排列的数组：id、collection、channel 和 webhook（围栏频道）、area（nearby、intersects
+    /// 或 within）、geometry（GeoJSON，nearby 为圆心）、radius（nearby）、dwell（设置了 DWELL 时）
+    pub fn to_resp(&self) -> RespValue {
+        let mut items = vec![
+            RespValue::bulk("id"),
+            RespValue::Integer(self.id as i64),
+            RespValue::bulk("collection"),
+            RespValue::bulk(namespace::unscoped(&self.collection)),
+        ];
+        if let Some(channel) = &self.channel {
+            items.extend([
+                RespValue::bulk("channel"),
+                RespValue::bulk(channel.as_str()),
+            ]);
+        }
+        if let Some(webhook) = &self.webhook {
+            items.extend([
+                RespValue::bulk("webhook"),
+                RespValue::bulk(webhook.as_str()),
+            ]);
+        }
+        let (area, geometry, radius) = match &self.area {
+            FenceArea::Circle { lon, lat, radius } => (
+                "nearby",
+                Geometry::Point(geo::Point::new(*lon, *lat)),
+                Some(*radius),
+            ),
+            FenceArea::Geometry { geometry, within } => (
+                if *within { "within" } else { "intersects" },
+                geometry.clone(),
+                None,
+            ),
+        };
+        items.extend([
+            RespValue::bulk("area"),
+            RespValue::bulk(area),
+            RespValue::bulk("geometry"),
+            RespValue::bulk(geometry_to_geojson(&geometry).to_string()),
+        ]);
+        if let Some(radius) = radius {
+            items.extend([
+                RespValue::bulk("radius"),
+                RespValue::bulk(radius.to_string()),
+            ]);
+        }
+        if let Some(dwell) = self.dwell {
+            items.extend([RespValue::bulk("dwell"), RespValue::Integer(dwell as i64)]);
+        }
+        RespValue::Array(Some(items))
+    }
+}
+
+/// 连接接收围栏事件的一端，丢弃时不会注销围栏，需要调用 [`FenceManager::unsubscribe`]
+pub struct FenceSubscriber {
+    id: u64,
+    sender: mpsc::UnboundedSender<String>,
+    pub events: mpsc::UnboundedReceiver<String>,
+}
+
+impl FenceSubscriber {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+/// 围栏事件的去向
+enum Target {
+    /// FENCE：推送给注册围栏的连接
+    Connection {
+        subscriber: u64,
+        sender: mpsc::UnboundedSender<String>,
+    },
+    /// SETCHAN：发布到同名频道，配置了 webhook 时同时发送
+    Channel {
+        name: String,
+        webhook: Option<Webhook>,
+    },
+}
+
+/// 停留检测的状态
+struct Dwell {
+    /// 停留时间（纳秒）
+    duration: u64,
+    /// 注册之后进入围栏、还没有离开的对象
+    inside: HashMap<String, Stay>,
+}
+
+/// 对象在围栏内的一次停留
+struct Stay {
+    /// 对象最新的 GeoJSON
+    geojson: Arc<str>,
+    /// 这次停留是否已经产生过 dwell 事件
+    fired: bool,
+}
+
+struct Fence {
+    id: u64,
+    area: FenceArea,
+    target: Target,
+    dwell: Option<Dwell>,
+}
+
+impl Fence {
+    fn new(id: u64, area: FenceArea, target: Target, dwell: Option<u64>) -> Self {
+        Self {
+            id,
+            area,
+            target,
+            dwell: dwell.map(|seconds| Dwell {
+                duration: seconds.saturating_mul(1_000_000_000),
+                inside: HashMap::new(),
+            }),
+        }
+    }
+
+    fn channel(&self) -> Option<&str> {
+        match &self.target {
+            Target::Channel { name, .. } => Some(name),
+            Target::Connection { .. } => None,
+        }
+    }
+
+    /// 推送一条事件，连接的接收端已经关闭时返回 false
+    #[allow(clippy::too_many_arguments)]
+    fn deliver(
+        &self,
+        pubsub: &PubSub,
+        command: &str,
+        detect: Detect,
+        collection: &str,
+        id: &str,
+        geojson: &str,
+        timestamp: u64,
+    ) -> bool {
+        let source = match &self.target {
+            Target::Connection { .. } => format!(r#""fence":{}"#, self.id),
+            Target::Channel { name, .. } => {
+                format!(r#""channel":{}"#, serde_json::Value::from(name.as_str()))
+            }
+        };
+        let message = format!(
+            r#"{{{},"command":"{}","detect":"{}","collection":{},"id":{},"object":{},"ts":{}}}"#,
+            source,
+            command,
+            detect.as_str(),
+            serde_json::Value::from(namespace::unscoped(collection)),
+            serde_json::Value::from(id),
+            geojson,
+            timestamp
+        );
+        match &self.target {
+            Target::Connection { sender, .. } => sender.send(message).is_ok(),
+            Target::Channel { name, webhook } => {
+                if let Some(webhook) = webhook {
+                    webhook.send(message.clone());
+                }
+                pubsub.publish(name, &message);
+                true
+            }
+        }
+    }
+
+    fn is_alive(&self) -> bool {
+        match &self.target {
+            Target::Connection { sender, .. } => !sender.is_closed(),
+            Target::Channel { .. } => true,
+        }
+    }
+}
+
+/// 停留计时，每个 (围栏, 对象) 最多一个；对象离开或围栏注销时取消
+#[derive(Default)]
+struct DwellTimers {
+    /// 按到期时间排序：(到期时间, 围栏 ID, 对象 ID) -> collection
+    queue: BTreeMap<(u64, u64, String), String>,
+    /// 围栏 ID -> 对象 ID -> 到期时间，用于按键取消
+    keys: HashMap<u64, HashMap<String, u64>>,
+}
+
+impl DwellTimers {
+    /// 开始计时，替换该对象在这个围栏上的旧计时
+    fn schedule(&mut self, deadline: u64, collection: &str, fence_id: u64, id: &str) {
+        self.cancel(fence_id, id);
+        self.keys
+            .entry(fence_id)
+            .or_default()
+            .insert(id.to_string(), deadline);
+        self.queue
+            .insert((deadline, fence_id, id.to_string()), collection.to_string());
+    }
+
+    /// 取消对象在围栏上的计时
+    fn cancel(&mut self, fence_id: u64, id: &str) {
+        let Some(objects) = self.keys.get_mut(&fence_id) else {
+            return;
+        };
+        if let Some(deadline) = objects.remove(id) {
+            self.queue.remove(&(deadline, fence_id, id.to_string()));
+        }
+        if objects.is_empty() {
+            self.keys.remove(&fence_id);
+        }
+    }
+
+    /// 取消围栏上的所有计时
+    fn cancel_fence(&mut self, fence_id: u64) {
+        for (id, deadline) in self.keys.remove(&fence_id).into_iter().flatten() {
+            self.queue.remove(&(deadline, fence_id, id));
+        }
+    }
+
+    /// 取出一个在 `now` 之前到期的计时：(到期时间, collection, 围栏 ID, 对象 ID)
+    fn pop_due(&mut self, now: u64) -> Option<(u64, String, u64, String)> {
+        let entry = self.queue.first_entry()?;
+        if entry.key().0 > now {
+            return None;
+        }
+        let ((deadline, fence_id, id), collection) = entry.remove_entry();
+        if let Some(objects) = self.keys.get_mut(&fence_id) {
+            objects.remove(&id);
+            if objects.is_empty() {
+                self.keys.remove(&fence_id);
+            }
+        }
+        Some((deadline, collection, fence_id, id))
+    }
+
+    fn len(&self) -> usize {
+        self.queue.len()
+    }
+}
+
+/// 所有围栏区域的空间索引（FENCES CONTAINING），键为围栏 ID
+///
+/// R-tree 中保存 [`FenceArea::index_geometry`]，先按边界框找出候选，再用 [`FenceArea::contains`] 精确判断
+struct FenceIndex {
+    tree: RTree,
+    areas: HashMap<u64, FenceArea>,
+}
+
+impl Default for FenceIndex {
+    fn default() -> Self {
+        Self {
+            tree: RTree::new(16),
+            areas: HashMap::new(),
+        }
+    }
+}
+
+impl FenceIndex {
+    fn insert(&mut self, id: u64, area: &FenceArea) {
+        let geojson = geometry_to_geojson(&area.index_geometry()).to_string();
+        if self.tree.insert_geojson(id.to_string(), &geojson) {
+            self.areas.insert(id, area.clone());
+        }
+    }
+
+    fn remove(&mut self, id: u64) {
+        if self.areas.remove(&id).is_some() {
+            self.tree.delete(&id.to_string());
+        }
+    }
+
+    /// 区域包含该点的围栏 ID，按 ID 排序
+    fn containing(&self, lon: f64, lat: f64) -> Vec<u64> {
+        let point = Geometry::Point(Point::new(lon, lat));
+        let mut ids: Vec<u64> = self
+            .tree
+            .search_bbox(&Rectangle::from_point(lon, lat))
+            .iter()
+            .filter_map(|id| id.parse().ok())
+            .filter(|id| self.areas.get(id).is_some_and(|area| area.contains(&point)))
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+}
+
+/// 注册的围栏、停留计时和区域索引，由同一把锁保护
+#[derive(Default)]
+struct FenceTable {
+    /// 按 collection 分组的围栏
+    by_collection: HashMap<String, Vec<Fence>>,
+    /// 停留计时，对象离开、围栏注销或 collection 被删除时取消
+    timers: DwellTimers,
+    index: FenceIndex,
+}
+
+/// 管理所有连接注册的围栏和围栏频道，按 collection 分组
+pub struct FenceManager {
+    next_id: AtomicU64,
+    table: Mutex<FenceTable>,
+    pubsub: Arc<PubSub>,
+    clock: SharedClock,
+    /// 推送的事件计入该数据库的统计历史（STATS HISTORY 的 fence_events）
+    stats: Option<Arc<GeoDatabase>>,
+}
+
+impl Default for FenceManager {
+    fn default() -> Self {
+        Self {
+            next_id: AtomicU64::new(0),
+            table: Mutex::new(FenceTable::default()),
+            pubsub: Arc::default(),
+            clock: SystemClock::shared(),
+            stats: None,
+        }
+    }
+}
+
+impl FenceManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 替换检查停留计时使用的时钟（默认使用系统时钟），应与数据库的时钟一致
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 把推送的事件计入数据库的每分钟统计
+    pub fn with_stats(mut self, database: Arc<GeoDatabase>) -> Self {
+        self.stats = Some(database);
+        self
+    }
+
+    fn record_events(&self, count: usize) {
+        if let Some(database) = &self.stats {
+            for _ in 0..count {
+                database.record_stat(StatEvent::FenceEvent);
+            }
+        }
+    }
+
+    /// 围栏频道发布事件使用的 pub/sub，连接的 SUBSCRIBE 和 PUBLISH 也使用它
+    pub fn pubsub(&self) -> &Arc<PubSub> {
+        &self.pubsub
+    }
+
+    /// 消费数据库事件直到总线关闭，同时定期检查停留计时；服务器启动时在后台运行
+    pub async fn run(self: Arc<Self>, mut events: EventReceiver) {
+        let mut ticker = tokio::time::interval(DWELL_CHECK_INTERVAL);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Some(event) => self.notify(&event),
+                    None => break,
+                },
+                _ = ticker.tick() => self.check_dwell(),
+            }
+        }
+    }
+
+    /// 为连接创建事件通道
+    pub fn subscribe(&self) -> FenceSubscriber {
+        let (sender, events) = mpsc::unbounded_channel();
+        FenceSubscriber {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed) + 1,
+            sender,
+            events,
+        }
+    }
+
+    /// 注册围栏，返回围栏 ID（事件中的 `fence` 字段）
+    pub fn register(&self, subscriber: &FenceSubscriber, spec: FenceSpec) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let target = Target::Connection {
+            subscriber: subscriber.id,
+            sender: subscriber.sender.clone(),
+        };
+        self.insert(
+            spec.collection,
+            Fence::new(id, spec.area, target, spec.dwell),
+        );
+        id
+    }
+
+    /// 注销连接的所有围栏
+    pub fn unsubscribe(&self, subscriber: u64) {
+        self.remove_where(|fence| {
+            matches!(fence.target, Target::Connection { subscriber: id, .. } if id == subscriber)
+        });
+    }
+
+    /// 注册围栏频道，替换同名的频道时返回 true；配置了 webhook 时启动发送任务，须在 tokio 运行时中调用
+    pub fn set_channel(&self, spec: ChannelSpec) -> bool {
+        let replaced = self.delete_channel(&spec.name);
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let target = Target::Channel {
+            name: spec.name,
+            webhook: spec.webhook.map(Webhook::spawn),
+        };
+        self.insert(
+            spec.fence.collection,
+            Fence::new(id, spec.fence.area, target, spec.fence.dwell),
+        );
+        replaced
+    }
+
+    /// 删除围栏频道，频道不存在时返回 false；已经订阅该频道的连接不受影响，只是不再收到事件
+    pub fn delete_channel(&self, name: &str) -> bool {
+        self.remove_where(|fence| fence.channel() == Some(name)) > 0
+    }
+
+    fn insert(&self, collection: String, fence: Fence) {
+        let mut table = self.table.lock().unwrap();
+        table.index.insert(fence.id, &fence.area);
+        table
+            .by_collection
+            .entry(collection)
+            .or_default()
+            .push(fence);
+    }
+
+    /// 移除满足条件的围栏，返回移除的数量
+    fn remove_where(&self, predicate: impl Fn(&Fence) -> bool) -> usize {
+        let mut removed = 0;
+        let mut table = self.table.lock().unwrap();
+        let FenceTable {
+            by_collection,
+            timers,
+            index,
+        } = &mut *table;
+        by_collection.retain(|_, list| {
+            list.retain(|fence| {
+                let remove = predicate(fence);
+                if remove {
+                    index.remove(fence.id);
+                    timers.cancel_fence(fence.id);
+                    removed += 1;
+                }
+                !remove
+            });
+            !list.is_empty()
+        });
+        removed
+    }
+
+    /// 当前注册的围栏数量
+    pub fn len(&self) -> usize {
+        self.table
+            .lock()
+            .unwrap()
+            .by_collection
+            .values()
+            .map(Vec::len)
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 尚未到期的停留计时数量
+    pub fn pending_timers(&self) -> usize {
+        self.table.lock().unwrap().timers.len()
+    }
+
+    /// 区域包含点 (lon, lat) 的所有围栏的 ID（FENCES CONTAINING），按 ID 排序
+    pub fn containing(&self, lon: f64, lat: f64) -> Vec<u64> {
+        self.table.lock().unwrap().index.containing(lon, lat)
+    }
+
+    /// 所有注册的围栏（FENCES LIST），按 ID 排序
+    pub fn list(&self) -> Vec<FenceInfo> {
+        let table = self.table.lock().unwrap();
+        let mut list: Vec<FenceInfo> = table
+            .by_collection
+            .iter()
+            .flat_map(|(collection, fences)| {
+                fences.iter().map(move |fence| FenceInfo {
+                    id: fence.id,
+                    collection: collection.clone(),
+                    channel: fence.channel().map(str::to_string),
+                    webhook: match &fence.target {
+                        Target::Channel {
+                            webhook: Some(webhook),
+                            ..
+                        } => Some(webhook.url().to_string()),
+                        _ => None,
+                    },
+                    area: fence.area.clone(),
+                    dwell: fence
+                        .dwell
+                        .as_ref()
+                        .map(|dwell| dwell.duration / 1_000_000_000),
+                })
+            })
+            .collect();
+        list.sort_by_key(|info| info.id);
+        list
+    }
+
+    /// 检查对象修改，向受影响的围栏推送事件并更新停留状态；接收端已经关闭的围栏顺便移除
+    pub fn notify(&self, event: &DatabaseEvent) {
+        let (command, id, previous, current, geojson, timestamp) = match event {
+            DatabaseEvent::ObjectSet {
+                id,
+                previous,
+                geometry,
+                geojson,
+                timestamp,
+                ..
+            } => (
+                "set",
+                id,
+                previous.as_ref(),
+                Some(geometry),
+                geojson,
+                *timestamp,
+            ),
+            DatabaseEvent::ObjectDeleted {
+                id,
+                geometry,
+                geojson,
+                timestamp,
+                ..
+            } => ("del", id, Some(geometry), None, geojson, *timestamp),
+            DatabaseEvent::CollectionDropped { collection, .. } => {
+                let mut table = self.table.lock().unwrap();
+                let FenceTable {
+                    by_collection,
+                    timers,
+                    ..
+                } = &mut *table;
+                for fence in by_collection.get_mut(collection).into_iter().flatten() {
+                    if let Some(dwell) = &mut fence.dwell {
+                        dwell.inside.clear();
+                        timers.cancel_fence(fence.id);
+                    }
+                }
+                return;
+            }
+        };
+
+        let collection = event.collection();
+        let mut table = self.table.lock().unwrap();
+        let FenceTable {
+            by_collection,
+            timers,
+            index,
+        } = &mut *table;
+        let Some(list) = by_collection.get_mut(collection) else {
+            return;
+        };
+        let mut delivered = 0;
+        list.retain_mut(|fence| {
+            let detect = Detect::of(&fence.area, previous, current);
+            if let Some(dwell) = &mut fence.dwell {
+                match detect {
+                    Some(Detect::Enter) => {
+                        dwell.inside.insert(
+                            id.clone(),
+                            Stay {
+                                geojson: Arc::from(geojson.as_str()),
+                                fired: false,
+                            },
+                        );
+                        let deadline = timestamp.saturating_add(dwell.duration);
+                        timers.schedule(deadline, collection, fence.id, id);
+                    }
+                    Some(Detect::Exit) => {
+                        dwell.inside.remove(id.as_str());
+                        timers.cancel(fence.id, id);
+                    }
+                    // 一直在围栏内：记下最新的位置
+                    None => {
+                        if let Some(stay) = dwell.inside.get_mut(id.as_str()) {
+                            stay.geojson = Arc::from(geojson.as_str());
+                        }
+                    }
+                    Some(Detect::Cross | Detect::Dwell) => {}
+                }
+            }
+            let alive = match detect {
+                None => fence.is_alive(),
+                Some(detect) => {
+                    let sent = fence.deliver(
+                        &self.pubsub,
+                        command,
+                        detect,
+                        collection,
+                        id,
+                        geojson,
+                        timestamp,
+                    );
+                    delivered += sent as usize;
+                    sent
+                }
+            };
+            if !alive {
+                index.remove(fence.id);
+                timers.cancel_fence(fence.id);
+            }
+            alive
+        });
+        if list.is_empty() {
+            by_collection.remove(collection);
+        }
+        drop(table);
+        self.record_events(delivered);
+    }
+
+    /// 对到期的停留计时产生 dwell 事件，[`run`](Self::run) 每隔 100 毫秒调用一次
+    pub fn check_dwell(&self) {
+        let now = self.clock.unix_nanos();
+        let mut table = self.table.lock().unwrap();
+        let FenceTable {
+            by_collection,
+            timers,
+            ..
+        } = &mut *table;
+        let mut delivered = 0;
+        while let Some((deadline, collection, fence_id, id)) = timers.pop_due(now) {
+            let Some(fence) = by_collection
+                .get_mut(&collection)
+                .and_then(|list| list.iter_mut().find(|fence| fence.id == fence_id))
+            else {
+                continue;
+            };
+            let Some(dwell) = &mut fence.dwell else {
+                continue;
+            };
+            let Some(stay) = dwell.inside.get_mut(&id).filter(|stay| !stay.fired) else {
+                continue;
+            };
+            stay.fired = true;
+            let geojson = Arc::clone(&stay.geojson);
+            delivered += fence.deliver(
+                &self.pubsub,
+                "set",
+                Detect::Dwell,
+                &collection,
+                &id,
+                &geojson,
+                deadline,
+            ) as usize;
+        }
+        drop(table);
+        self.record_events(delivered);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::args;
+    use crate::storage::{GeoDatabase, MockClock};
+    use serde_json::{json, Value};
+
+    fn point(lon: f64, lat: f64) -> String {
+        json!({"type": "Point", "coordinates": [lon, lat]}).to_string()
+    }
+
+    /// 把数据库事件交给围栏管理器处理，再取出推送给连接的下一条事件
+    fn next_event(
+        manager: &FenceManager,
+        events: &mut EventReceiver,
+        subscriber: &mut FenceSubscriber,
+    ) -> Option<Value> {
+        while let Ok(event) = events.try_recv() {
+            manager.notify(&event);
+        }
+        subscriber
+            .events
+            .try_recv()
+            .ok()
+            .map(|event| serde_json::from_str(&event).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_enter_exit_cross_events() {
+        let manager = Arc::new(FenceManager::new());
+        let database = GeoDatabase::new();
+        let mut events = database.subscribe_events();
+        let mut subscriber = manager.subscribe();
+        let square = json!({
+            "type": "Polygon",
+            "coordinates": [[[0, 0], [1, 0], [1, 1], [0, 1], [0, 0]]]
+        })
+        .to_string();
+        let spec = FenceSpec::parse(
+            "intersects",
+            &args(&["fleet", &square, "FENCE"]),
+            DistanceMetric::Haversine,
+        )
+        .unwrap()
+        .unwrap();
+        let fence = manager.register(&subscriber, spec);
+
+        database
+            .set("fleet", "truck1", &point(-1.0, 0.5))
+            .await
+            .unwrap();
+        assert!(next_event(&manager, &mut events, &mut subscriber).is_none());
+
+        database
+            .set("fleet", "truck1", &point(0.5, 0.5))
+            .await
+            .unwrap();
+        let event = next_event(&manager, &mut events, &mut subscriber).unwrap();
+        assert_eq!(event["fence"], fence);
+        assert_eq!(event["command"], "set");
+        assert_eq!(event["detect"], "enter");
+        assert_eq!(event["collection"], "fleet");
+        assert_eq!(event["id"], "truck1");
+        assert_eq!(event["object"]["coordinates"], json!([0.5, 0.5]));
+
+        // 在围栏内移动没有事件
+        database
+            .set("fleet", "truck1", &point(0.6, 0.6))
+            .await
+            .unwrap();
+        assert!(next_event(&manager, &mut events, &mut subscriber).is_none());
+
+        database
+            .set("fleet", "truck1", &point(2.0, 0.5))
+            .await
+            .unwrap();
+        assert_eq!(
+            next_event(&manager, &mut events, &mut subscriber).unwrap()["detect"],
+            "exit"
+        );
+
+        // 一步跨过围栏
+        database
+            .set("fleet", "truck1", &point(-1.0, 0.5))
+            .await
+            .unwrap();
+        assert_eq!(
+            next_event(&manager, &mut events, &mut subscriber).unwrap()["detect"],
+            "cross"
+        );
+        database
+            .set("fleet", "truck1", &point(-1.0, 5.0))
+            .await
+            .unwrap();
+        assert!(next_event(&manager, &mut events, &mut subscriber).is_none());
+
+        // 在围栏内被删除
+        database
+            .set("fleet", "truck2", &point(0.2, 0.2))
+            .await
+            .unwrap();
+        assert_eq!(
+            next_event(&manager, &mut events, &mut subscriber).unwrap()["detect"],
+            "enter"
+        );
+        database.delete("fleet", "truck2").await.unwrap();
+        let event = next_event(&manager, &mut events, &mut subscriber).unwrap();
+        assert_eq!(event["command"], "del");
+        assert_eq!(event["detect"], "exit");
+        assert_eq!(event["object"]["coordinates"], json!([0.2, 0.2]));
+
+        // 其他 collection 不触发
+        database
+            .set("other", "truck1", &point(0.5, 0.5))
+            .await
+            .unwrap();
+        assert!(next_event(&manager, &mut events, &mut subscriber).is_none());
+
+        manager.unsubscribe(subscriber.id());
+        assert!(manager.is_empty());
+        database
+            .set("fleet", "truck3", &point(0.5, 0.5))
+            .await
+            .unwrap();
+        assert!(next_event(&manager, &mut events, &mut subscriber).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_nearby_fence_and_closed_subscribers() {
+        let manager = Arc::new(FenceManager::new());
+        let database = GeoDatabase::new();
+        let mut events = database.subscribe_events();
+        let mut near = manager.subscribe();
+        let far = manager.subscribe();
+        let spec = FenceSpec::parse(
+            "NEARBY",
+            &args(&["fleet", "POINT", "116.4", "39.9", "RADIUS", "1000", "FENCE"]),
+            DistanceMetric::Haversine,
+        )
+        .unwrap()
+        .unwrap();
+        manager.register(&near, spec.clone());
+        manager.register(&far, spec);
+        assert_eq!(manager.len(), 2);
+
+        // 接收端关闭的围栏在下一次修改时移除
+        drop(far);
+        database
+            .set("fleet", "bike1", &point(116.401, 39.901))
+            .await
+            .unwrap();
+        assert_eq!(
+            next_event(&manager, &mut events, &mut near).unwrap()["detect"],
+            "enter"
+        );
+        assert_eq!(manager.len(), 1);
+
+        database
+            .set("fleet", "bike1", &point(116.5, 39.9))
+            .await
+            .unwrap();
+        assert_eq!(
+            next_event(&manager, &mut events, &mut near).unwrap()["detect"],
+            "exit"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_channel_fences_publish_events() {
+        let manager = Arc::new(FenceManager::new());
+        let database = GeoDatabase::new();
+        let mut events = database.subscribe_events();
+        let pubsub = Arc::clone(manager.pubsub());
+        let mut listener = pubsub.subscriber();
+        pubsub.psubscribe(&mut listener, "warehouse*");
+
+        let spec = ChannelSpec::parse(
+            &args(&[
+                "warehouse",
+                "NEARBY",
+                "fleet",
+                "POINT",
+                "116.4",
+                "39.9",
+                "RADIUS",
+                "1000",
+            ]),
+            DistanceMetric::Haversine,
+        )
+        .unwrap();
+        assert!(spec.webhook.is_none());
+        assert!(!manager.set_channel(spec.clone()));
+        // 同名频道被替换，而不是重复注册
+        assert!(manager.set_channel(spec));
+        assert_eq!(manager.len(), 1);
+
+        database
+            .set("fleet", "bike1", &point(116.401, 39.901))
+            .await
+            .unwrap();
+        while let Ok(event) = events.try_recv() {
+            manager.notify(&event);
+        }
+        let message = listener.messages.try_recv().unwrap();
+        assert_eq!(message.channel, "warehouse");
+        let event: Value = serde_json::from_str(&message.payload).unwrap();
+        assert_eq!(event["channel"], "warehouse");
+        assert_eq!(event["detect"], "enter");
+        assert!(event.get("fence").is_none());
+        assert!(listener.messages.try_recv().is_err());
+
+        // 连接的退订不影响频道，DELCHAN 之后不再发布
+        manager.unsubscribe(12345);
+        assert_eq!(manager.len(), 1);
+        assert!(manager.delete_channel("warehouse"));
+        assert!(!manager.delete_channel("warehouse"));
+        assert!(manager.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fences_containing() {
+        let manager = FenceManager::new();
+        let subscriber = manager.subscribe();
+        let circle = FenceSpec::parse(
+            "NEARBY",
+            &args(&["fleet", "POINT", "116.4", "39.9", "RADIUS", "1000", "FENCE"]),
+            DistanceMetric::Haversine,
+        )
+        .unwrap()
+        .unwrap();
+        let circle = manager.register(&subscriber, circle);
+        let square = json!({
+            "type": "Polygon",
+            "coordinates": [[[116.39, 39.89], [116.5, 39.89], [116.5, 40.0], [116.39, 40.0], [116.39, 39.89]]]
+        })
+        .to_string();
+        let square = FenceSpec::parse(
+            "INTERSECTS",
+            &args(&["zones", &square, "FENCE"]),
+            DistanceMetric::Haversine,
+        )
+        .unwrap()
+        .unwrap();
+        let square = manager.register(&subscriber, square);
+        // 跨过 180 度经线的圆
+        let dateline = FenceSpec::parse(
+            "NEARBY",
+            &args(&["fleet", "POINT", "179.999", "0", "RADIUS", "1000", "FENCE"]),
+            DistanceMetric::Haversine,
+        )
+        .unwrap()
+        .unwrap();
+        let dateline = manager.register(&subscriber, dateline);
+
+        assert_eq!(manager.containing(116.4, 39.9), vec![circle, square]);
+        // 在圆的外接矩形内但不在圆内
+        assert_eq!(manager.containing(116.41, 39.908), vec![square]);
+        assert_eq!(manager.containing(116.45, 39.95), vec![square]);
+        assert_eq!(manager.containing(-179.9999, 0.0), vec![dateline]);
+        assert!(manager.containing(0.0, 0.0).is_empty());
+
+        // 注销的围栏和接收端关闭后移除的围栏都不再出现
+        assert!(manager.containing(116.4, 39.9).contains(&circle));
+        manager.unsubscribe(subscriber.id());
+        assert!(manager.containing(116.4, 39.9).is_empty());
+        assert!(manager.containing(179.9999, 0.0).is_empty());
+
+        let spec = ChannelSpec::parse(
+            &args(&[
+                "warehouse",
+                "NEARBY",
+                "fleet",
+                "POINT",
+                "116.4",
+                "39.9",
+                "RADIUS",
+                "1000",
+            ]),
+            DistanceMetric::Haversine,
+        )
+        .unwrap();
+        manager.set_channel(spec.clone());
+        let [id] = manager.containing(116.4, 39.9)[..] else {
+            panic!("expected one fence");
+        };
+        // 替换频道时旧的区域从索引中移除
+        manager.set_channel(spec);
+        let [replaced] = manager.containing(116.4, 39.9)[..] else {
+            panic!("expected one fence");
+        };
+        assert_ne!(id, replaced);
+        assert!(manager.delete_channel("warehouse"));
+        assert!(manager.containing(116.4, 39.9).is_empty());
+    }
+
+    #[test]
+    fn test_parse_channel() {
+        let spec = ChannelSpec::parse(
+            &args(&[
+                "zone",
+                "webhook",
+                "http://127.0.0.1:9000/events",
+                "INTERSECTS",
+                "fleet",
+                "BOUNDS",
+                "39.8",
+                "116.3",
+                "40.0",
+                "116.5",
+                "FENCE",
+            ]),
+            DistanceMetric::Haversine,
+        )
+        .unwrap();
+        assert_eq!(spec.name, "zone");
+        assert_eq!(spec.webhook.unwrap().port, 9000);
+        assert_eq!(spec.fence.collection, "fleet");
+
+        assert!(ChannelSpec::parse(
+            &args(&["zone", "GET", "fleet", "a"]),
+            DistanceMetric::Haversine
+        )
+        .unwrap_err()
+        .contains("expects NEARBY or INTERSECTS"));
+        assert!(ChannelSpec::parse(
+            &args(&["zone", "WEBHOOK", "https://x", "NEARBY"]),
+            DistanceMetric::Haversine
+        )
+        .unwrap_err()
+        .contains("only http://"));
+        assert!(
+            ChannelSpec::parse(&args(&["zone"]), DistanceMetric::Haversine)
+                .unwrap_err()
+                .contains("wrong number of arguments")
+        );
+    }
+
+    #[test]
+    fn test_parse_fence() {
+        let nearby = |extra: &[&str]| {
+            let mut values = vec!["fleet", "POINT", "116.4", "39.9"];
+            values.extend_from_slice(extra);
+            FenceSpec::parse("NEARBY", &args(&values), DistanceMetric::Haversine)
+        };
+        assert!(nearby(&["RADIUS", "100"]).unwrap().is_none());
+        assert!(nearby(&["RADIUS", "100", "fence"]).unwrap().is_some());
+        assert_eq!(
+            nearby(&["COUNT", "3", "FENCE"]).unwrap_err(),
+            "ERR FENCE requires RADIUS and cannot be combined with LIMIT or COUNT"
+        );
+        assert_eq!(
+            nearby(&["RADIUS", "100", "WHERETAG", "bus", "FENCE"]).unwrap_err(),
+            "ERR FENCE cannot be combined with WHERETAG or WITHIN"
+        );
+        assert_eq!(
+            nearby(&["RADIUS", "100", "TYPE", "point", "FENCE"]).unwrap_err(),
+            "ERR FENCE cannot be combined with TYPE or WHERE"
+        );
+        assert_eq!(
+            nearby(&["RADIUS", "100", "DISTANCE", "euclidean", "FENCE"]).unwrap_err(),
+            "ERR FENCE only supports DISTANCE HAVERSINE"
+        );
+        assert!(nearby(&["RADIUS", "100", "DISTANCE", "haversine", "FENCE"])
+            .unwrap()
+            .is_some());
+
+        // 不带 DISTANCE 时按服务器的默认度量判断
+        let with_default = |extra: &[&str], metric| {
+            let mut values = vec!["fleet", "POINT", "116.4", "39.9", "RADIUS", "100"];
+            values.extend_from_slice(extra);
+            FenceSpec::parse("NEARBY", &args(&values), metric)
+        };
+        assert_eq!(
+            with_default(&["FENCE"], DistanceMetric::Euclidean).unwrap_err(),
+            "ERR FENCE only supports DISTANCE HAVERSINE"
+        );
+        assert!(with_default(
+            &["DISTANCE", "haversine", "FENCE"],
+            DistanceMetric::Euclidean
+        )
+        .unwrap()
+        .is_some());
+        assert_eq!(
+            ChannelSpec::parse(
+                &args(&["zone", "NEARBY", "fleet", "POINT", "1", "2", "RADIUS", "100"]),
+                DistanceMetric::SphericalLawOfCosines
+            )
+            .unwrap_err(),
+            "ERR FENCE only supports DISTANCE HAVERSINE"
+        );
+        assert!(
+            FenceSpec::parse("GET", &args(&["fleet", "FENCE"]), DistanceMetric::Haversine)
+                .unwrap()
+                .is_none()
+        );
+
+        let spec = nearby(&["RADIUS", "100", "FENCE", "DWELL", "30"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(spec.dwell, Some(30));
+        assert_eq!(
+            nearby(&["RADIUS", "100", "DWELL", "30"]).unwrap_err(),
+            "ERR DWELL requires FENCE"
+        );
+        assert!(nearby(&["RADIUS", "100", "FENCE", "DWELL", "0"])
+            .unwrap_err()
+            .contains("must be greater than 0"));
+    }
+
+    #[tokio::test]
+    async fn test_dwell_events() {
+        let clock = Arc::new(MockClock::new(1_700_000_000_000_000_000));
+        let database = Arc::new(GeoDatabase::new().with_clock(clock.clone()));
+        let manager = Arc::new(
+            FenceManager::new()
+                .with_clock(clock.clone())
+                .with_stats(Arc::clone(&database)),
+        );
+        let mut events = database.subscribe_events();
+        let mut subscriber = manager.subscribe();
+        let spec = FenceSpec::parse(
+            "NEARBY",
+            &args(&[
+                "fleet", "POINT", "116.4", "39.9", "RADIUS", "1000", "FENCE", "DWELL", "60",
+            ]),
+            DistanceMetric::Haversine,
+        )
+        .unwrap()
+        .unwrap();
+        let fence = manager.register(&subscriber, spec);
+
+        database
+            .set("fleet", "bike1", &point(116.401, 39.901))
+            .await
+            .unwrap();
+        let entered = next_event(&manager, &mut events, &mut subscriber).unwrap();
+        assert_eq!(entered["detect"], "enter");
+
+        // 未到停留时间，期间在围栏内移动
+        clock.advance(Duration::from_secs(30));
+        database
+            .set("fleet", "bike1", &point(116.402, 39.902))
+            .await
+            .unwrap();
+        manager.check_dwell();
+        assert!(next_event(&manager, &mut events, &mut subscriber).is_none());
+
+        clock.advance(Duration::from_secs(30));
+        manager.check_dwell();
+        let dwell = next_event(&manager, &mut events, &mut subscriber).unwrap();
+        assert_eq!(dwell["fence"], fence);
+        assert_eq!(dwell["detect"], "dwell");
+        assert_eq!(dwell["id"], "bike1");
+        assert_eq!(dwell["object"]["coordinates"], json!([116.402, 39.902]));
+        assert_eq!(
+            dwell["ts"].as_u64().unwrap(),
+            entered["ts"].as_u64().unwrap() + 60_000_000_000
+        );
+
+        // 每次停留只产生一次
+        clock.advance(Duration::from_secs(120));
+        manager.check_dwell();
+        assert!(next_event(&manager, &mut events, &mut subscriber).is_none());
+
+        // 离开后重新计时，计时期间离开的停留不产生事件
+        database
+            .set("fleet", "bike1", &point(116.5, 39.9))
+            .await
+            .unwrap();
+        assert_eq!(
+            next_event(&manager, &mut events, &mut subscriber).unwrap()["detect"],
+            "exit"
+        );
+        database
+            .set("fleet", "bike1", &point(116.401, 39.901))
+            .await
+            .unwrap();
+        assert_eq!(
+            next_event(&manager, &mut events, &mut subscriber).unwrap()["detect"],
+            "enter"
+        );
+        clock.advance(Duration::from_secs(10));
+        database.delete("fleet", "bike1").await.unwrap();
+        assert_eq!(
+            next_event(&manager, &mut events, &mut subscriber).unwrap()["detect"],
+            "exit"
+        );
+        clock.advance(Duration::from_secs(60));
+        manager.check_dwell();
+        assert!(next_event(&manager, &mut events, &mut subscriber).is_none());
+
+        assert_eq!(manager.pending_timers(), 0);
+
+        // enter、dwell、exit、enter、exit 都计入统计历史
+        let fence_events: u64 = database
+            .stats_history(10)
+            .iter()
+            .map(|minute| minute.fence_events)
+            .sum();
+        assert_eq!(fence_events, 5);
+    }
+
+    #[tokio::test]
+    async fn test_dwell_timers_cancelled_on_exit_and_unregister() {
+        let clock = Arc::new(MockClock::new(1_700_000_000_000_000_000));
+        let database = Arc::new(GeoDatabase::new().with_clock(clock.clone()));
+        let manager = FenceManager::new().with_clock(clock.clone());
+        let mut events = database.subscribe_events();
+        let subscriber = manager.subscribe();
+        let spec = || {
+            FenceSpec::parse(
+                "NEARBY",
+                &args(&[
+                    "fleet", "POINT", "116.4", "39.9", "RADIUS", "1000", "FENCE", "DWELL", "60",
+                ]),
+                DistanceMetric::Haversine,
+            )
+            .unwrap()
+            .unwrap()
+        };
+        manager.register(&subscriber, spec());
+        manager.register(&subscriber, spec());
+        let mut apply = || {
+            while let Ok(event) = events.try_recv() {
+                manager.notify(&event);
+            }
+        };
+
+        for id in ["bike1", "bike2"] {
+            database
+                .set("fleet", id, &point(116.401, 39.901))
+                .await
+                .unwrap();
+        }
+        apply();
+        assert_eq!(manager.pending_timers(), 4);
+
+        // 离开围栏时取消两个围栏上的计时，反复进出不会累积
+        for _ in 0..3 {
+            database
+                .set("fleet", "bike1", &point(116.5, 39.9))
+                .await
+                .unwrap();
+            apply();
+            assert_eq!(manager.pending_timers(), 2);
+            database
+                .set("fleet", "bike1", &point(116.401, 39.901))
+                .await
+                .unwrap();
+            apply();
+            assert_eq!(manager.pending_timers(), 4);
+        }
+        database.delete("fleet", "bike1").await.unwrap();
+        apply();
+        assert_eq!(manager.pending_timers(), 2);
+
+        // 连接注销围栏时取消剩余的计时
+        manager.unsubscribe(subscriber.id);
+        assert_eq!(manager.pending_timers(), 0);
+    }
+}
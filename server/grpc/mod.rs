@@ -0,0 +1,669 @@
+//! gRPC 接入（`grpc` feature，`server.grpc_port`），接口契约见 `proto/spatio.proto`
+//!
+//! 与 RESP 命令使用同一个 [`GeoDatabase`] 和同样的执行规则：每个请求持有全局共享锁（MULTI/EXEC
+//! 执行期间等待），写请求在磁盘空间不足时被拒绝，组提交时等待记录落盘后才返回。
+//! 只访问命名空间 0；设置了 `requirepass` 时请求须带 `authorization: Bearer <password>` 元数据。
+//!
+//! 流式回复在锁内取出结果，释放锁之后再逐个发送，读得慢的客户端不会阻塞写入。
+//! 返回的几何体使用结构化编码，Feature 的 properties 不包含在内（用 RESP 的 GET 获取）
+
+// Status 是 tonic 接口规定的错误类型，无法装箱
+#![allow(clippy::result_large_err)]
+
+pub mod pb;
+
+use std::pin::Pin;
+use std::sync::Arc;
+
+use futures_util::{stream, Stream};
+use geo::{Coord, Geometry};
+use tokio::net::TcpListener;
+use tonic::transport::server::TcpIncoming;
+use tonic::{Request, Response, Status};
+
+use crate::rtree::algorithms::knn::DistanceMetric;
+use crate::rtree::algorithms::search::SearchOrder;
+use crate::rtree::GeoItem;
+use crate::storage::geometry_utils::{
+    geojson_to_geometry, geometries_intersect, geometry_to_geojson,
+};
+use crate::storage::namespace;
+use crate::storage::{DatabaseEvent, GeoDatabase, StatEvent};
+use pb::geometry::Kind;
+use pb::spatio_server::{Spatio, SpatioServer};
+use pb::subscribe_event;
+
+type ReplyStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+/// 实现 `spatio.v1.Spatio` 服务
+pub struct GrpcService {
+    database: Arc<GeoDatabase>,
+}
+
+impl GrpcService {
+    pub fn new(database: Arc<GeoDatabase>) -> Self {
+        Self { database }
+    }
+
+    /// 在 `listener` 上提供服务，直到监听出错
+    pub async fn serve(
+        self,
+        listener: TcpListener,
+        password: Option<Arc<str>>,
+    ) -> crate::Result<()> {
+        let incoming = TcpIncoming::from_listener(listener, true, None)?;
+        let service = SpatioServer::with_interceptor(self, move |request| {
+            authorize(password.as_deref(), request)
+        });
+        tonic::transport::Server::builder()
+            .add_service(service)
+            .serve_with_incoming(incoming)
+            .await?;
+        Ok(())
+    }
+
+    /// 与 RESP 命令相同：计入命令统计，写请求先检查磁盘空间
+    fn begin(&self, write: bool) -> Result<(), Status> {
+        self.database.record_stat(StatEvent::Command);
+        if write {
+            self.database
+                .check_disk_space()
+                .map_err(|e| Status::resource_exhausted(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// 写请求在组提交时等待落盘
+    async fn finish_write(&self) -> Result<(), Status> {
+        self.database.wait_durable().await.map_err(internal)
+    }
+}
+
+#[tonic::async_trait]
+impl Spatio for GrpcService {
+    async fn set(
+        &self,
+        request: Request<pb::SetRequest>,
+    ) -> Result<Response<pb::SetResponse>, Status> {
+        let request = request.into_inner();
+        check_name("collection", &request.collection)?;
+        check_name("id", &request.id)?;
+        let geojson = match request.geometry.and_then(|geometry| geometry.kind) {
+            Some(Kind::Geojson(text)) => text,
+            Some(kind) => geometry_to_geojson(&from_proto(kind)?).to_string(),
+            None => return Err(Status::invalid_argument("geometry is required")),
+        };
+
+        let _shared = self.database.shared().await;
+        self.begin(true)?;
+        self.database
+            .set(&request.collection, &request.id, &geojson)
+            .await
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        self.finish_write().await?;
+        Ok(Response::new(pb::SetResponse {}))
+    }
+
+    async fn get(
+        &self,
+        request: Request<pb::GetRequest>,
+    ) -> Result<Response<pb::GetResponse>, Status> {
+        let request = request.into_inner();
+        check_name("collection", &request.collection)?;
+
+        let _shared = self.database.shared().await;
+        self.begin(false)?;
+        let item = self
+            .database
+            .get(&request.collection, &request.id)
+            .await
+            .map_err(internal)?;
+        Ok(Response::new(pb::GetResponse {
+            object: item.as_ref().map(to_object),
+        }))
+    }
+
+    async fn delete(
+        &self,
+        request: Request<pb::DeleteRequest>,
+    ) -> Result<Response<pb::DeleteResponse>, Status> {
+        let request = request.into_inner();
+        check_name("collection", &request.collection)?;
+
+        let _shared = self.database.shared().await;
+        self.begin(true)?;
+        let deleted = self
+            .database
+            .delete(&request.collection, &request.id)
+            .await
+            .map_err(internal)?;
+        self.finish_write().await?;
+        Ok(Response::new(pb::DeleteResponse { deleted }))
+    }
+
+    type IntersectsStream = ReplyStream<pb::GeoObject>;
+
+    async fn intersects(
+        &self,
+        request: Request<pb::IntersectsRequest>,
+    ) -> Result<Response<Self::IntersectsStream>, Status> {
+        let request = request.into_inner();
+        check_name("collection", &request.collection)?;
+        let order = match request.order() {
+            pb::SearchOrder::Tree => SearchOrder::Tree,
+            pb::SearchOrder::Center => SearchOrder::Center,
+        };
+        let geometry = required_geometry(request.geometry)?;
+
+        let items = {
+            let _shared = self.database.shared().await;
+            self.begin(false)?;
+            self.database
+                .intersects_ordered(
+                    &request.collection,
+                    &geometry,
+                    usize::try_from(request.limit).unwrap_or(usize::MAX),
+                    request.within,
+                    order,
+                )
+                .await
+                .map_err(internal)?
+        };
+        let objects = items.into_iter().map(|item| Ok(to_object(&item)));
+        Ok(Response::new(Box::pin(stream::iter(objects))))
+    }
+
+    type NearbyStream = ReplyStream<pb::NearbyResult>;
+
+    async fn nearby(
+        &self,
+        request: Request<pb::NearbyRequest>,
+    ) -> Result<Response<Self::NearbyStream>, Status> {
+        let request = request.into_inner();
+        check_name("collection", &request.collection)?;
+        let point = request
+            .point
+            .ok_or_else(|| Status::invalid_argument("point is required"))?;
+        if request.count == 0 && request.radius.is_none() {
+            return Err(Status::invalid_argument(
+                "at least one of count or radius must be set",
+            ));
+        }
+        if request.epsilon.is_nan() || request.epsilon < 0.0 {
+            return Err(Status::invalid_argument("epsilon must not be negative"));
+        }
+        let k = usize::try_from(request.count).unwrap_or(usize::MAX);
+
+        let results = {
+            let _shared = self.database.shared().await;
+            self.begin(false)?;
+            // 与 NEARBY 相同：按 server.distance_metric 计算距离
+            let metric = self.database.distance_metric();
+            let results = if metric == DistanceMetric::Haversine {
+                self.database
+                    .nearby_approx(
+                        &request.collection,
+                        point.lon,
+                        point.lat,
+                        k,
+                        request.radius,
+                        request.epsilon,
+                    )
+                    .await
+            } else {
+                self.database
+                    .nearby_filtered(
+                        &request.collection,
+                        point.lon,
+                        point.lat,
+                        k,
+                        request.radius,
+                        request.epsilon,
+                        None,
+                        &Default::default(),
+                        metric,
+                    )
+                    .await
+            };
+            results.map_err(internal)?
+        };
+        let results = results.into_iter().map(|(item, distance)| {
+            Ok(pb::NearbyResult {
+                object: Some(to_object(&item)),
+                distance,
+            })
+        });
+        Ok(Response::new(Box::pin(stream::iter(results))))
+    }
+
+    type SubscribeStream = ReplyStream<pb::SubscribeEvent>;
+
+    async fn subscribe(
+        &self,
+        request: Request<pb::SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let request = request.into_inner();
+        check_name("collection", &request.collection)?;
+        let area = request
+            .area
+            .map(|area| required_geometry(Some(area)))
+            .transpose()?;
+        self.begin(false)?;
+
+        // 客户端断开时 tonic 丢弃流，接收端随之取消订阅
+        let receiver = self.database.subscribe_events();
+        let state = (receiver, request.collection, area);
+        let events = stream::unfold(state, |(mut receiver, collection, area)| async move {
+            loop {
+                let event = receiver.recv().await?;
+                if let Some(event) = to_event(&event, &collection, area.as_ref()) {
+                    return Some((Ok(event), (receiver, collection, area)));
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(events)))
+    }
+}
+
+/// 校验 `authorization: Bearer <password>` 元数据，没有设置密码时不检查
+fn authorize(password: Option<&str>, request: Request<()>) -> Result<Request<()>, Status> {
+    let Some(expected) = password else {
+        return Ok(request);
+    };
+    let token = request
+        .metadata()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match token {
+        Some(token) if super::server_connection::password_matches(expected, token) => Ok(request),
+        _ => Err(Status::unauthenticated("NOAUTH Authentication required.")),
+    }
+}
+
+/// 名称不能为空，collection 名称不能使用 SELECT 命名空间的保留前缀
+fn check_name(field: &str, name: &str) -> Result<(), Status> {
+    if name.is_empty() {
+        return Err(Status::invalid_argument(format!("{} is required", field)));
+    }
+    if field == "collection" && namespace::is_reserved(name) {
+        return Err(Status::invalid_argument(format!(
+            "collection name '{}' is reserved, names starting with '@<n>:' belong to SELECT namespaces",
+            name
+        )));
+    }
+    Ok(())
+}
+
+fn internal(error: Box<dyn std::error::Error + Send + Sync>) -> Status {
+    Status::internal(error.to_string())
+}
+
+fn required_geometry(geometry: Option<pb::Geometry>) -> Result<Geometry, Status> {
+    match geometry.and_then(|geometry| geometry.kind) {
+        Some(kind) => from_proto(kind),
+        None => Err(Status::invalid_argument("geometry is required")),
+    }
+}
+
+/// 只发送指定 collection 中、与 `area`（如果有）相关的事件：SET 时修改前后任一几何体与区域相交
+fn to_event(
+    event: &DatabaseEvent,
+    collection: &str,
+    area: Option<&Geometry>,
+) -> Option<pb::SubscribeEvent> {
+    if event.collection() != collection {
+        return None;
+    }
+    let in_area =
+        |geometry: &Geometry| area.is_none_or(|area| geometries_intersect(area, geometry));
+    let (kind, id, geometry) = match event {
+        DatabaseEvent::ObjectSet {
+            id,
+            previous,
+            geometry,
+            ..
+        } => {
+            if !in_area(geometry) && !previous.as_ref().is_some_and(in_area) {
+                return None;
+            }
+            (
+                subscribe_event::Kind::Set,
+                id.clone(),
+                Some(to_proto(geometry)),
+            )
+        }
+        DatabaseEvent::ObjectDeleted { id, geometry, .. } => {
+            if !in_area(geometry) {
+                return None;
+            }
+            (subscribe_event::Kind::Delete, id.clone(), None)
+        }
+        DatabaseEvent::CollectionDropped { .. } => {
+            (subscribe_event::Kind::Drop, String::new(), None)
+        }
+    };
+    Some(pb::SubscribeEvent {
+        kind: kind as i32,
+        collection: collection.to_string(),
+        id,
+        geometry,
+    })
+}
+
+fn to_object(item: &GeoItem) -> pb::GeoObject {
+    pb::GeoObject {
+        id: item.id.clone(),
+        geometry: Some(to_proto(&item.geometry)),
+    }
+}
+
+fn coordinate(coord: Coord) -> pb::Coordinate {
+    pb::Coordinate {
+        lon: coord.x,
+        lat: coord.y,
+    }
+}
+
+fn line_string(line: &geo::LineString) -> pb::LineString {
+    pb::LineString {
+        coordinates: line.coords().copied().map(coordinate).collect(),
+    }
+}
+
+fn polygon(polygon: &geo::Polygon) -> pb::Polygon {
+    pb::Polygon {
+        rings: std::iter::once(polygon.exterior())
+            .chain(polygon.interiors())
+            .map(line_string)
+            .collect(),
+    }
+}
+
+/// geo 几何体转换为结构化编码，Line 按 LineString、Rect 和 Triangle 按 Polygon 编码
+pub fn to_proto(geometry: &Geometry) -> pb::Geometry {
+    let kind = match geometry {
+        Geometry::Point(point) => Kind::Point(coordinate(point.0)),
+        Geometry::Line(line) => Kind::LineString(pb::LineString {
+            coordinates: vec![coordinate(line.start), coordinate(line.end)],
+        }),
+        Geometry::LineString(line) => Kind::LineString(line_string(line)),
+        Geometry::Polygon(p) => Kind::Polygon(polygon(p)),
+        Geometry::MultiPoint(points) => Kind::MultiPoint(pb::MultiPoint {
+            points: points.iter().map(|point| coordinate(point.0)).collect(),
+        }),
+        Geometry::MultiLineString(lines) => Kind::MultiLineString(pb::MultiLineString {
+            lines: lines.iter().map(line_string).collect(),
+        }),
+        Geometry::MultiPolygon(polygons) => Kind::MultiPolygon(pb::MultiPolygon {
+            polygons: polygons.iter().map(polygon).collect(),
+        }),
+        Geometry::GeometryCollection(collection) => {
+            Kind::GeometryCollection(pb::GeometryCollection {
+                geometries: collection.iter().map(to_proto).collect(),
+            })
+        }
+        Geometry::Rect(rect) => Kind::Polygon(polygon(&rect.to_polygon())),
+        Geometry::Triangle(triangle) => Kind::Polygon(polygon(&triangle.to_polygon())),
+    };
+    pb::Geometry { kind: Some(kind) }
+}
+
+fn to_coord(coordinate: &pb::Coordinate) -> Coord {
+    Coord {
+        x: coordinate.lon,
+        y: coordinate.lat,
+    }
+}
+
+fn to_line_string(line: &pb::LineString) -> geo::LineString {
+    line.coordinates.iter().map(to_coord).collect()
+}
+
+fn to_polygon(polygon: &pb::Polygon) -> Result<geo::Polygon, Status> {
+    let mut rings = polygon.rings.iter().map(to_line_string);
+    let exterior = rings
+        .next()
+        .ok_or_else(|| Status::invalid_argument("polygon requires an exterior ring"))?;
+    Ok(geo::Polygon::new(exterior, rings.collect()))
+}
+
+/// 结构化编码或 GeoJSON 文本转换为 geo 几何体
+pub fn from_proto(kind: Kind) -> Result<Geometry, Status> {
+    let geometry = match kind {
+        Kind::Point(point) => Geometry::Point(to_coord(&point).into()),
+        Kind::LineString(line) => Geometry::LineString(to_line_string(&line)),
+        Kind::Polygon(polygon) => Geometry::Polygon(to_polygon(&polygon)?),
+        Kind::MultiPoint(points) => Geometry::MultiPoint(
+            points
+                .points
+                .iter()
+                .map(|point| geo::Point::from(to_coord(point)))
+                .collect(),
+        ),
+        Kind::MultiLineString(lines) => {
+            Geometry::MultiLineString(lines.lines.iter().map(to_line_string).collect())
+        }
+        Kind::MultiPolygon(polygons) => Geometry::MultiPolygon(
+            polygons
+                .polygons
+                .iter()
+                .map(to_polygon)
+                .collect::<Result<Vec<_>, _>>()?
+                .into(),
+        ),
+        Kind::GeometryCollection(collection) => Geometry::GeometryCollection(
+            collection
+                .geometries
+                .into_iter()
+                .map(|geometry| required_geometry(Some(geometry)))
+                .collect::<Result<Vec<_>, _>>()?
+                .into(),
+        ),
+        Kind::Geojson(text) => {
+            geojson_to_geometry(&text).map_err(|e| Status::invalid_argument(e.to_string()))?
+        }
+    };
+    Ok(geometry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::pb::spatio_client::SpatioClient;
+    use super::*;
+    use futures_util::StreamExt;
+    use serde_json::json;
+    use tonic::transport::Channel;
+
+    async fn start(password: Option<&str>) -> (Arc<GeoDatabase>, SpatioClient<Channel>) {
+        let database = Arc::new(GeoDatabase::new());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let password = password.map(Arc::from);
+        tokio::spawn(GrpcService::new(Arc::clone(&database)).serve(listener, password));
+        let client = SpatioClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap();
+        (database, client)
+    }
+
+    fn point(lon: f64, lat: f64) -> pb::Geometry {
+        pb::Geometry {
+            kind: Some(Kind::Point(pb::Coordinate { lon, lat })),
+        }
+    }
+
+    fn set(collection: &str, id: &str, geometry: pb::Geometry) -> pb::SetRequest {
+        pb::SetRequest {
+            collection: collection.to_string(),
+            id: id.to_string(),
+            geometry: Some(geometry),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_grpc_round_trip() {
+        let (database, mut client) = start(None).await;
+
+        client
+            .set(set("fleet", "truck1", point(116.40, 39.90)))
+            .await
+            .unwrap();
+        let feature = json!({
+            "type": "Feature",
+            "properties": {"speed": 42},
+            "geometry": {"type": "Point", "coordinates": [116.41, 39.90]}
+        });
+        let geojson = pb::Geometry {
+            kind: Some(Kind::Geojson(feature.to_string())),
+        };
+        client.set(set("fleet", "truck2", geojson)).await.unwrap();
+        // 与 RESP 写入的是同一个数据库
+        assert!(database.get("fleet", "truck2").await.unwrap().is_some());
+
+        let reply = client
+            .get(pb::GetRequest {
+                collection: "fleet".to_string(),
+                id: "truck1".to_string(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(reply.object.unwrap().geometry, Some(point(116.40, 39.90)));
+
+        let area = pb::Geometry {
+            kind: Some(Kind::Polygon(pb::Polygon {
+                rings: vec![pb::LineString {
+                    coordinates: [
+                        (116.0, 39.0),
+                        (117.0, 39.0),
+                        (117.0, 40.0),
+                        (116.0, 40.0),
+                        (116.0, 39.0),
+                    ]
+                    .iter()
+                    .map(|&(lon, lat)| pb::Coordinate { lon, lat })
+                    .collect(),
+                }],
+            })),
+        };
+        let mut ids: Vec<String> = client
+            .intersects(pb::IntersectsRequest {
+                collection: "fleet".to_string(),
+                geometry: Some(area),
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .into_inner()
+            .map(|object| object.unwrap().id)
+            .collect()
+            .await;
+        ids.sort();
+        assert_eq!(ids, vec!["truck1", "truck2"]);
+
+        let results: Vec<pb::NearbyResult> = client
+            .nearby(pb::NearbyRequest {
+                collection: "fleet".to_string(),
+                point: Some(pb::Coordinate {
+                    lon: 116.411,
+                    lat: 39.90,
+                }),
+                count: 1,
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .into_inner()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].object.as_ref().unwrap().id, "truck2");
+        assert!(results[0].distance > 50.0 && results[0].distance < 150.0);
+
+        let reply = client
+            .delete(pb::DeleteRequest {
+                collection: "fleet".to_string(),
+                id: "truck1".to_string(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(reply.deleted);
+        assert!(database.get("fleet", "truck1").await.unwrap().is_none());
+
+        let status = client
+            .set(set("@1:fleet", "truck1", point(0.0, 0.0)))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_grpc_subscribe_filters_by_area() {
+        let (database, mut client) = start(None).await;
+        let area = json!({
+            "type": "Polygon",
+            "coordinates": [[[116.0, 39.0], [117.0, 39.0], [117.0, 40.0], [116.0, 40.0], [116.0, 39.0]]]
+        });
+        let mut events = client
+            .subscribe(pb::SubscribeRequest {
+                collection: "fleet".to_string(),
+                area: Some(pb::Geometry {
+                    kind: Some(Kind::Geojson(area.to_string())),
+                }),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+
+        // 收到回复头时服务端已经完成订阅
+        let point_json =
+            |lon: f64| json!({"type": "Point", "coordinates": [lon, 39.5]}).to_string();
+        database
+            .set("other", "p1", &point_json(116.5))
+            .await
+            .unwrap();
+        database
+            .set("fleet", "far", &point_json(0.0))
+            .await
+            .unwrap();
+        database
+            .set("fleet", "near", &point_json(116.5))
+            .await
+            .unwrap();
+        database.delete("fleet", "near").await.unwrap();
+
+        let event = events.next().await.unwrap().unwrap();
+        assert_eq!(event.kind(), subscribe_event::Kind::Set);
+        assert_eq!(event.id, "near");
+        assert_eq!(event.geometry, Some(point(116.5, 39.5)));
+        let event = events.next().await.unwrap().unwrap();
+        assert_eq!(event.kind(), subscribe_event::Kind::Delete);
+        assert_eq!(event.id, "near");
+    }
+
+    #[tokio::test]
+    async fn test_grpc_requires_password() {
+        let (_database, mut client) = start(Some("secret")).await;
+        let request = || pb::GetRequest {
+            collection: "fleet".to_string(),
+            id: "truck1".to_string(),
+        };
+        let status = client.get(request()).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+        let mut request = Request::new(request());
+        request
+            .metadata_mut()
+            .insert("authorization", "Bearer secret".parse().unwrap());
+        assert!(client
+            .get(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .object
+            .is_none());
+    }
+}
@@ -0,0 +1,211 @@
+//! `proto/spatio.proto` 中的消息（package `spatio.v1`）和 tonic 生成的服务代码
+//!
+//! 修改 proto 文件时同步修改这里的字段编号和类型
+
+/// 坐标顺序与 GeoJSON 一致：经度在前，纬度在后
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct Coordinate {
+    #[prost(double, tag = "1")]
+    pub lon: f64,
+    #[prost(double, tag = "2")]
+    pub lat: f64,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LineString {
+    #[prost(message, repeated, tag = "1")]
+    pub coordinates: Vec<Coordinate>,
+}
+
+/// 第一个环为外环，其余为内环（洞）
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Polygon {
+    #[prost(message, repeated, tag = "1")]
+    pub rings: Vec<LineString>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MultiPoint {
+    #[prost(message, repeated, tag = "1")]
+    pub points: Vec<Coordinate>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MultiLineString {
+    #[prost(message, repeated, tag = "1")]
+    pub lines: Vec<LineString>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MultiPolygon {
+    #[prost(message, repeated, tag = "1")]
+    pub polygons: Vec<Polygon>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GeometryCollection {
+    #[prost(message, repeated, tag = "1")]
+    pub geometries: Vec<Geometry>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Geometry {
+    #[prost(oneof = "geometry::Kind", tags = "1, 2, 3, 4, 5, 6, 7, 15")]
+    pub kind: Option<geometry::Kind>,
+}
+
+pub mod geometry {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Kind {
+        #[prost(message, tag = "1")]
+        Point(super::Coordinate),
+        #[prost(message, tag = "2")]
+        LineString(super::LineString),
+        #[prost(message, tag = "3")]
+        Polygon(super::Polygon),
+        #[prost(message, tag = "4")]
+        MultiPoint(super::MultiPoint),
+        #[prost(message, tag = "5")]
+        MultiLineString(super::MultiLineString),
+        #[prost(message, tag = "6")]
+        MultiPolygon(super::MultiPolygon),
+        #[prost(message, tag = "7")]
+        GeometryCollection(super::GeometryCollection),
+        /// 原样传递 GeoJSON 文本（包括 Feature），与 RESP 的 SET 参数相同
+        #[prost(string, tag = "15")]
+        Geojson(String),
+    }
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GeoObject {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(message, optional, tag = "2")]
+    pub geometry: Option<Geometry>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SetRequest {
+    #[prost(string, tag = "1")]
+    pub collection: String,
+    #[prost(string, tag = "2")]
+    pub id: String,
+    #[prost(message, optional, tag = "3")]
+    pub geometry: Option<Geometry>,
+}
+
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct SetResponse {}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetRequest {
+    #[prost(string, tag = "1")]
+    pub collection: String,
+    #[prost(string, tag = "2")]
+    pub id: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetResponse {
+    /// 对象不存在时为空
+    #[prost(message, optional, tag = "1")]
+    pub object: Option<GeoObject>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DeleteRequest {
+    #[prost(string, tag = "1")]
+    pub collection: String,
+    #[prost(string, tag = "2")]
+    pub id: String,
+}
+
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct DeleteResponse {
+    #[prost(bool, tag = "1")]
+    pub deleted: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum SearchOrder {
+    Tree = 0,
+    Center = 1,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct IntersectsRequest {
+    #[prost(string, tag = "1")]
+    pub collection: String,
+    #[prost(message, optional, tag = "2")]
+    pub geometry: Option<Geometry>,
+    /// 0 表示不限制
+    #[prost(uint64, tag = "3")]
+    pub limit: u64,
+    /// true: 完全包含在 geometry 内，false: 相交
+    #[prost(bool, tag = "4")]
+    pub within: bool,
+    #[prost(enumeration = "SearchOrder", tag = "5")]
+    pub order: i32,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct NearbyRequest {
+    #[prost(string, tag = "1")]
+    pub collection: String,
+    #[prost(message, optional, tag = "2")]
+    pub point: Option<Coordinate>,
+    /// 0 表示不限制数量（需要设置 radius）
+    #[prost(uint64, tag = "3")]
+    pub count: u64,
+    /// 米，未设置表示不限制半径
+    #[prost(double, optional, tag = "4")]
+    pub radius: Option<f64>,
+    /// 近似因子，0 表示精确查询
+    #[prost(double, tag = "5")]
+    pub epsilon: f64,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct NearbyResult {
+    #[prost(message, optional, tag = "1")]
+    pub object: Option<GeoObject>,
+    /// 米
+    #[prost(double, tag = "2")]
+    pub distance: f64,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeRequest {
+    #[prost(string, tag = "1")]
+    pub collection: String,
+    /// 只关注与该区域相关的变更，未设置表示整个 collection
+    #[prost(message, optional, tag = "2")]
+    pub area: Option<Geometry>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeEvent {
+    #[prost(enumeration = "subscribe_event::Kind", tag = "1")]
+    pub kind: i32,
+    #[prost(string, tag = "2")]
+    pub collection: String,
+    #[prost(string, tag = "3")]
+    pub id: String,
+    /// KIND_SET 时为新的几何体
+    #[prost(message, optional, tag = "4")]
+    pub geometry: Option<Geometry>,
+}
+
+pub mod subscribe_event {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+    #[repr(i32)]
+    pub enum Kind {
+        Set = 0,
+        Delete = 1,
+        Drop = 2,
+    }
+}
+
+include!(concat!(env!("OUT_DIR"), "/spatio.v1.Spatio.rs"));
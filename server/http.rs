@@ -0,0 +1,348 @@
+//! 同一端口上的 HTTP 接入：解析请求、从请求中取出命令、把 RESP 回复转换为 JSON
+//!
+//! 每个连接只处理一个请求，回复后关闭（`Connection: close`），不支持分块传输。
+//! 命令有两种写法：
+//! - `GET /SET+fleet+truck1+...`：路径按 `+` 分割，每段再做百分号解码
+//! - `POST /`：请求体是 JSON 字符串数组，例如 `["GET","fleet","truck1"]`（WebSocket 消息使用同样的格式）
+
+use serde_json::{json, Value};
+
+use crate::protocol::parser::RespValue;
+use crate::protocol::RespParser;
+
+/// 请求行和请求头的最大长度
+pub const MAX_HEAD_LEN: usize = 64 * 1024;
+/// 请求体的最大长度，与 WebSocket 单帧上限一致
+pub const MAX_BODY_LEN: usize = super::websocket::MAX_FRAME_PAYLOAD;
+
+/// 识别连接协议时认可的 HTTP 方法（包括末尾的空格）
+pub const METHODS: [&[u8]; 7] = [
+    b"GET ",
+    b"POST ",
+    b"PUT ",
+    b"DELETE ",
+    b"HEAD ",
+    b"OPTIONS ",
+    b"PATCH ",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    /// 按名称查找请求头（不区分大小写）
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// 是否为 WebSocket 升级请求
+    pub fn is_websocket_upgrade(&self) -> bool {
+        let upgrade = self
+            .header("Upgrade")
+            .is_some_and(|v| v.eq_ignore_ascii_case("websocket"));
+        let connection = self.header("Connection").is_some_and(|v| {
+            v.split(',')
+                .any(|token| token.trim().eq_ignore_ascii_case("upgrade"))
+        });
+        self.method == "GET" && upgrade && connection
+    }
+
+    /// 从请求中取出命令名和参数
+    pub fn command(&self) -> Result<Vec<String>, String> {
+        match self.method.as_str() {
+            "GET" => {
+                // 查询字符串不属于命令
+                let path = self.path.split('?').next().unwrap_or("");
+                let args = path
+                    .trim_start_matches('/')
+                    .split('+')
+                    .filter(|part| !part.is_empty())
+                    .map(percent_decode)
+                    .collect::<Result<Vec<_>, _>>()?;
+                if args.is_empty() {
+                    return Err("missing command in request path".to_string());
+                }
+                Ok(args)
+            }
+            "POST" => parse_json_command(&self.body),
+            method => Err(format!("method {} is not supported", method)),
+        }
+    }
+}
+
+/// 从缓冲区开头解析一个 HTTP 请求
+///
+/// - `Ok(Some((request, consumed)))`：完整的请求（含请求体），占用缓冲区的前 `consumed` 字节
+/// - `Ok(None)`：请求还不完整，需要读取更多数据
+/// - `Err(..)`：格式错误或超过长度限制，应回复 400 并关闭连接
+pub fn parse_request(buf: &[u8]) -> Result<Option<(HttpRequest, usize)>, String> {
+    let Some(head_end) = buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+        if buf.len() > MAX_HEAD_LEN {
+            return Err("request head too long".to_string());
+        }
+        return Ok(None);
+    };
+    if head_end > MAX_HEAD_LEN {
+        return Err("request head too long".to_string());
+    }
+    let head = std::str::from_utf8(&buf[..head_end])
+        .map_err(|_| "request head is not valid UTF-8".to_string())?;
+    let mut lines = head.split("\r\n");
+
+    let request_line = lines.next().unwrap_or("");
+    let mut parts = request_line.split(' ');
+    let (Some(method), Some(path), Some(version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(format!("malformed request line: {}", request_line));
+    };
+    if !version.starts_with("HTTP/1.") {
+        return Err(format!("unsupported HTTP version: {}", version));
+    }
+
+    let mut headers = Vec::new();
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(format!("malformed header: {}", line));
+        };
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+
+    let mut request = HttpRequest {
+        method: method.to_string(),
+        path: path.to_string(),
+        headers,
+        body: Vec::new(),
+    };
+    if request.header("Transfer-Encoding").is_some() {
+        return Err("chunked request bodies are not supported".to_string());
+    }
+    let body_len = match request.header("Content-Length") {
+        Some(len) => len
+            .parse::<usize>()
+            .map_err(|_| format!("invalid Content-Length: {}", len))?,
+        None => 0,
+    };
+    if body_len > MAX_BODY_LEN {
+        return Err(format!("request body of {} bytes exceeds limit", body_len));
+    }
+
+    let body_start = head_end + 4;
+    if buf.len() < body_start + body_len {
+        return Ok(None);
+    }
+    request.body = buf[body_start..body_start + body_len].to_vec();
+    Ok(Some((request, body_start + body_len)))
+}
+
+/// 解析 JSON 字符串数组形式的命令（POST 请求体和 WebSocket 消息）
+pub fn parse_json_command(body: &[u8]) -> Result<Vec<String>, String> {
+    let args: Vec<String> = serde_json::from_slice(body)
+        .map_err(|e| format!("command must be a JSON array of strings: {}", e))?;
+    if args.is_empty() {
+        return Err("command must not be empty".to_string());
+    }
+    Ok(args)
+}
+
+/// 把命令的 RESP 回复转换为 JSON：`{"ok":true,"result":..}` 或 `{"ok":false,"err":".."}`
+///
+/// 内容是 JSON 对象或数组的字符串（GeoJSON、边界框等）直接嵌入，不再作为字符串转义
+pub fn reply_to_json(reply: &str) -> Value {
+    match RespParser::decode(reply.as_bytes()) {
+        Ok(Some((RespValue::Error(message), _))) => error_json(&message),
+        Ok(Some((value, _))) => json!({"ok": true, "result": resp_to_json(value)}),
+        _ => error_json("ERR malformed reply"),
+    }
+}
+
+pub fn error_json(message: &str) -> Value {
+    json!({"ok": false, "err": message})
+}
+
+fn resp_to_json(value: RespValue) -> Value {
+    match value {
+        RespValue::SimpleString(s) => Value::String(s),
+        RespValue::BulkString(Some(bytes)) => {
+            if bytes.starts_with(b"{") || bytes.starts_with(b"[") {
+                if let Ok(parsed) = serde_json::from_slice(&bytes) {
+                    return parsed;
+                }
+            }
+            Value::String(String::from_utf8_lossy(&bytes).into_owned())
+        }
+        RespValue::Error(s) => json!({"err": s}),
+        RespValue::Integer(n) => Value::from(n),
+        RespValue::BulkString(None) | RespValue::Array(None) | RespValue::Null => Value::Null,
+        RespValue::Array(Some(items)) | RespValue::Set(items) | RespValue::Push(items) => {
+            Value::Array(items.into_iter().map(resp_to_json).collect())
+        }
+        RespValue::Boolean(b) => Value::Bool(b),
+        RespValue::Double(n) => {
+            serde_json::Number::from_f64(n).map_or_else(|| json!(n.to_string()), Value::Number)
+        }
+        RespValue::BigNumber(n) => Value::String(n),
+        RespValue::Map(pairs) => Value::Array(
+            pairs
+                .into_iter()
+                .map(|(key, value)| json!([resp_to_json(key), resp_to_json(value)]))
+                .collect(),
+        ),
+    }
+}
+
+/// 编码一个完整的 HTTP 响应，回复后连接关闭
+pub fn response(status: u16, content_type: &str, body: &[u8]) -> Vec<u8> {
+    let mut response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        reason_phrase(status),
+        content_type,
+        body.len()
+    )
+    .into_bytes();
+    response.extend_from_slice(body);
+    response
+}
+
+/// 编码 JSON 响应
+pub fn json_response(status: u16, body: &Value) -> Vec<u8> {
+    response(status, "application/json", body.to_string().as_bytes())
+}
+
+/// WebSocket 握手成功的 101 响应
+pub fn upgrade_response(client_key: &str) -> Vec<u8> {
+    format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        super::websocket::accept_key(client_key)
+    )
+    .into_bytes()
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        426 => "Upgrade Required",
+        _ => "Internal Server Error",
+    }
+}
+
+/// 百分号解码；`+` 在路径中是参数分隔符，字面的 `+` 需要写成 `%2B`
+fn percent_decode(input: &str) -> Result<String, String> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes
+                .get(i + 1..i + 3)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| format!("invalid percent-encoding in {}", input))?;
+            decoded.push(hex);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| format!("argument is not valid UTF-8: {}", input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_get_request() {
+        let raw = b"GET /GET+fleet+truck%201 HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        for end in 0..raw.len() {
+            assert_eq!(parse_request(&raw[..end]), Ok(None));
+        }
+        let (request, consumed) = parse_request(raw).unwrap().unwrap();
+        assert_eq!(consumed, raw.len());
+        assert_eq!(request.header("host"), Some("localhost"));
+        assert_eq!(request.command().unwrap(), vec!["GET", "fleet", "truck 1"]);
+    }
+
+    #[test]
+    fn test_parse_post_request() {
+        let body = r#"["SET","fleet","truck1","{\"type\":\"Point\",\"coordinates\":[1,2]}"]"#;
+        let raw = format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        assert_eq!(parse_request(&raw.as_bytes()[..raw.len() - 1]), Ok(None));
+        let (request, _) = parse_request(raw.as_bytes()).unwrap().unwrap();
+        let args = request.command().unwrap();
+        assert_eq!(args[3], r#"{"type":"Point","coordinates":[1,2]}"#);
+    }
+
+    #[test]
+    fn test_parse_request_errors() {
+        assert!(parse_request(b"GET /\r\n\r\n").is_err());
+        assert!(parse_request(b"GET / HTTP/2\r\n\r\n").is_err());
+        assert!(parse_request(b"POST / HTTP/1.1\r\nContent-Length: x\r\n\r\n").is_err());
+        assert!(parse_request(b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n").is_err());
+        assert!(parse_request(&vec![b'a'; MAX_HEAD_LEN + 1]).is_err());
+
+        let (request, _) = parse_request(b"GET /%zz HTTP/1.1\r\n\r\n")
+            .unwrap()
+            .unwrap();
+        assert!(request.command().is_err());
+        let (request, _) = parse_request(b"GET / HTTP/1.1\r\n\r\n").unwrap().unwrap();
+        assert!(request.command().is_err());
+        assert!(parse_json_command(b"[]").is_err());
+        assert!(parse_json_command(b"[1]").is_err());
+    }
+
+    #[test]
+    fn test_websocket_upgrade_headers() {
+        let raw =
+            b"GET / HTTP/1.1\r\nUpgrade: WebSocket\r\nConnection: keep-alive, Upgrade\r\n\r\n";
+        let (request, _) = parse_request(raw).unwrap().unwrap();
+        assert!(request.is_websocket_upgrade());
+
+        let (request, _) = parse_request(b"GET /PING HTTP/1.1\r\n\r\n")
+            .unwrap()
+            .unwrap();
+        assert!(!request.is_websocket_upgrade());
+    }
+
+    #[test]
+    fn test_reply_to_json() {
+        assert_eq!(
+            reply_to_json("+PONG\r\n"),
+            json!({"ok": true, "result": "PONG"})
+        );
+        assert_eq!(
+            reply_to_json("-ERR unknown command\r\n"),
+            json!({"ok": false, "err": "ERR unknown command"})
+        );
+        assert_eq!(
+            reply_to_json("$-1\r\n"),
+            json!({"ok": true, "result": null})
+        );
+        // JSON 内容直接嵌入，其他字符串保持原样
+        assert_eq!(
+            reply_to_json("*3\r\n$2\r\nid\r\n$7\r\n{\"a\":1}\r\n:5\r\n"),
+            json!({"ok": true, "result": ["id", {"a": 1}, 5]})
+        );
+        assert_eq!(
+            reply_to_json("$4\r\n[1,x\r\n"),
+            json!({"ok": true, "result": "[1,x"})
+        );
+    }
+}
@@ -1,5 +1,19 @@
+pub mod alloc;
+pub mod fence;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod http;
+pub mod pubsub;
 pub mod server_connection;
+pub mod session;
+pub mod systemd;
 pub mod tcp_server;
+pub mod webhook;
+pub mod websocket;
 
+pub use alloc::TrackingAllocator;
+pub use fence::FenceManager;
+pub use pubsub::PubSub;
 pub use server_connection::ServerConnection;
+pub use systemd::PidFile;
 pub use tcp_server::TcpServer;
@@ -0,0 +1,277 @@
+//! 发布/订阅
+//!
+//! `SUBSCRIBE channel ...` 和 `PSUBSCRIBE pattern ...` 让连接进入订阅状态，之后 `PUBLISH channel message`
+//! 发布的消息推送给所有订阅了该频道或匹配模式的连接。推送的格式与 Redis 相同：RESP2 上是数组
+//! `["message", channel, payload]` 或 `["pmessage", pattern, channel, payload]`，RESP3 上是同样内容的推送帧，
+//! WebSocket 上是 JSON 数组的文本帧。
+//!
+//! `SETCHAN` 注册的围栏（见 [`FenceManager::set_channel`](crate::server::FenceManager::set_channel)）
+//! 把事件发布到以围栏命名的频道，任何连接都可以订阅。订阅随连接存在，连接关闭时自动退订
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use tokio::sync::mpsc;
+
+use crate::protocol::parser::RespValue;
+use crate::protocol::RespResponse;
+use crate::storage::glob_match;
+
+/// 推送给订阅者的一条消息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    /// 通过 PSUBSCRIBE 匹配到时为匹配的模式
+    pub pattern: Option<String>,
+    pub channel: String,
+    pub payload: String,
+}
+
+impl Message {
+    fn items(&self) -> Vec<RespValue> {
+        match &self.pattern {
+            Some(pattern) => vec![
+                RespValue::bulk("pmessage"),
+                RespValue::bulk(pattern.as_str()),
+                RespValue::bulk(self.channel.as_str()),
+                RespValue::bulk(self.payload.as_str()),
+            ],
+            None => vec![
+                RespValue::bulk("message"),
+                RespValue::bulk(self.channel.as_str()),
+                RespValue::bulk(self.payload.as_str()),
+            ],
+        }
+    }
+
+    /// 编码为 RESP：协商了 RESP3 时为推送帧，否则为数组
+    pub fn to_resp(&self, resp3: bool) -> String {
+        if resp3 {
+            RespResponse::push(&self.items())
+        } else {
+            RespResponse::array(Some(&self.items()))
+        }
+    }
+
+    /// WebSocket 文本帧的内容：JSON 字符串数组
+    pub fn to_json(&self) -> String {
+        let mut items = vec![if self.pattern.is_some() {
+            "pmessage"
+        } else {
+            "message"
+        }];
+        items.extend(self.pattern.as_deref());
+        items.extend([self.channel.as_str(), self.payload.as_str()]);
+        serde_json::Value::from(items).to_string()
+    }
+}
+
+/// 连接订阅的一端，记录自己订阅的频道和模式；丢弃时不会退订，需要调用 [`PubSub::unsubscribe_all`]
+pub struct PubSubSubscriber {
+    id: u64,
+    sender: mpsc::UnboundedSender<Message>,
+    pub messages: mpsc::UnboundedReceiver<Message>,
+    channels: BTreeSet<String>,
+    patterns: BTreeSet<String>,
+}
+
+impl PubSubSubscriber {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// 订阅的频道和模式总数，决定连接是否仍处于订阅状态
+    pub fn count(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+
+    pub fn channels(&self) -> Vec<String> {
+        self.channels.iter().cloned().collect()
+    }
+
+    pub fn patterns(&self) -> Vec<String> {
+        self.patterns.iter().cloned().collect()
+    }
+}
+
+type Subscribers = HashMap<String, HashMap<u64, mpsc::UnboundedSender<Message>>>;
+
+/// 所有连接的订阅，按频道和模式索引
+#[derive(Default)]
+pub struct PubSub {
+    next_id: AtomicU64,
+    channels: Mutex<Subscribers>,
+    patterns: Mutex<Subscribers>,
+}
+
+impl PubSub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 为连接创建消息通道
+    pub fn subscriber(&self) -> PubSubSubscriber {
+        let (sender, messages) = mpsc::unbounded_channel();
+        PubSubSubscriber {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed) + 1,
+            sender,
+            messages,
+            channels: BTreeSet::new(),
+            patterns: BTreeSet::new(),
+        }
+    }
+
+    /// 订阅频道，返回订阅后的总数
+    pub fn subscribe(&self, subscriber: &mut PubSubSubscriber, channel: &str) -> usize {
+        if subscriber.channels.insert(channel.to_string()) {
+            add(&self.channels, channel, subscriber);
+        }
+        subscriber.count()
+    }
+
+    /// 订阅匹配 glob 模式（`*`、`?`）的频道，返回订阅后的总数
+    pub fn psubscribe(&self, subscriber: &mut PubSubSubscriber, pattern: &str) -> usize {
+        if subscriber.patterns.insert(pattern.to_string()) {
+            add(&self.patterns, pattern, subscriber);
+        }
+        subscriber.count()
+    }
+
+    /// 退订频道，返回退订后的总数
+    pub fn unsubscribe(&self, subscriber: &mut PubSubSubscriber, channel: &str) -> usize {
+        if subscriber.channels.remove(channel) {
+            remove(&self.channels, channel, subscriber.id);
+        }
+        subscriber.count()
+    }
+
+    /// 退订模式，返回退订后的总数
+    pub fn punsubscribe(&self, subscriber: &mut PubSubSubscriber, pattern: &str) -> usize {
+        if subscriber.patterns.remove(pattern) {
+            remove(&self.patterns, pattern, subscriber.id);
+        }
+        subscriber.count()
+    }
+
+    /// 退订连接的所有频道和模式
+    pub fn unsubscribe_all(&self, subscriber: &mut PubSubSubscriber) {
+        for channel in std::mem::take(&mut subscriber.channels) {
+            remove(&self.channels, &channel, subscriber.id);
+        }
+        for pattern in std::mem::take(&mut subscriber.patterns) {
+            remove(&self.patterns, &pattern, subscriber.id);
+        }
+    }
+
+    /// 发布消息，返回收到消息的订阅数（同一连接通过多个模式匹配时分别计数）
+    pub fn publish(&self, channel: &str, payload: &str) -> usize {
+        let mut received = 0;
+        if let Some(subscribers) = self.channels.lock().unwrap().get(channel) {
+            for sender in subscribers.values() {
+                let message = Message {
+                    pattern: None,
+                    channel: channel.to_string(),
+                    payload: payload.to_string(),
+                };
+                received += usize::from(sender.send(message).is_ok());
+            }
+        }
+        for (pattern, subscribers) in self.patterns.lock().unwrap().iter() {
+            if !glob_match(pattern, channel) {
+                continue;
+            }
+            for sender in subscribers.values() {
+                let message = Message {
+                    pattern: Some(pattern.clone()),
+                    channel: channel.to_string(),
+                    payload: payload.to_string(),
+                };
+                received += usize::from(sender.send(message).is_ok());
+            }
+        }
+        received
+    }
+
+    /// 当前有订阅者的频道数和模式数
+    pub fn counts(&self) -> (usize, usize) {
+        (
+            self.channels.lock().unwrap().len(),
+            self.patterns.lock().unwrap().len(),
+        )
+    }
+}
+
+fn add(index: &Mutex<Subscribers>, key: &str, subscriber: &PubSubSubscriber) {
+    index
+        .lock()
+        .unwrap()
+        .entry(key.to_string())
+        .or_default()
+        .insert(subscriber.id, subscriber.sender.clone());
+}
+
+fn remove(index: &Mutex<Subscribers>, key: &str, id: u64) {
+    let mut index = index.lock().unwrap();
+    if let Some(subscribers) = index.get_mut(key) {
+        subscribers.remove(&id);
+        if subscribers.is_empty() {
+            index.remove(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_publish_to_channels_and_patterns() {
+        let pubsub = PubSub::new();
+        let mut exact = pubsub.subscriber();
+        let mut pattern = pubsub.subscriber();
+        assert_eq!(pubsub.subscribe(&mut exact, "fleet"), 1);
+        assert_eq!(pubsub.subscribe(&mut exact, "fleet"), 1);
+        assert_eq!(pubsub.psubscribe(&mut pattern, "fl*"), 1);
+        assert_eq!(pubsub.psubscribe(&mut pattern, "*t"), 2);
+
+        assert_eq!(pubsub.publish("fleet", "hi"), 3);
+        assert_eq!(
+            exact.messages.try_recv().unwrap(),
+            Message {
+                pattern: None,
+                channel: "fleet".to_string(),
+                payload: "hi".to_string(),
+            }
+        );
+        let mut patterns = vec![
+            pattern.messages.try_recv().unwrap().pattern.unwrap(),
+            pattern.messages.try_recv().unwrap().pattern.unwrap(),
+        ];
+        patterns.sort();
+        assert_eq!(patterns, vec!["*t", "fl*"]);
+
+        assert_eq!(pubsub.publish("other", "hi"), 0);
+        assert_eq!(pubsub.unsubscribe(&mut exact, "fleet"), 0);
+        assert_eq!(pubsub.punsubscribe(&mut pattern, "*t"), 1);
+        assert_eq!(pubsub.publish("fleet", "again"), 1);
+
+        pubsub.unsubscribe_all(&mut pattern);
+        assert_eq!(pattern.count(), 0);
+        assert_eq!(pubsub.counts(), (0, 0));
+    }
+
+    #[test]
+    fn test_message_encoding() {
+        let message = Message {
+            pattern: Some("f*".to_string()),
+            channel: "fleet".to_string(),
+            payload: "{}".to_string(),
+        };
+        assert_eq!(
+            message.to_resp(false),
+            "*4\r\n$8\r\npmessage\r\n$2\r\nf*\r\n$5\r\nfleet\r\n$2\r\n{}\r\n"
+        );
+        assert!(message.to_resp(true).starts_with(">4\r\n"));
+        assert_eq!(message.to_json(), r#"["pmessage","f*","fleet","{}"]"#);
+    }
+}
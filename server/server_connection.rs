@@ -1,67 +1,959 @@
+use std::future::Future;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
-use tracing::{debug, error, info};
+use tokio::sync::mpsc;
+use tracing::{debug, error, info, warn};
 
+use crate::client::OutputFormatter;
 use crate::commands::registry::CommandRegistry;
 use crate::protocol::parser::RespValue;
-use crate::protocol::{RespParser, RespResponse};
-use crate::storage::GeoDatabase;
+use crate::protocol::{ReplySink, RespDecoder, RespParser, RespResponse};
+use crate::server::fence::{ChannelSpec, FenceInfo, FenceManager, FenceSpec, FenceSubscriber};
+use crate::server::http::{self, HttpRequest};
+use crate::server::pubsub::{Message, PubSubSubscriber};
+use crate::server::session::{Session, Transition};
+use crate::server::websocket;
+use crate::storage::{GeoDatabase, Namespace};
 use crate::Result;
 
+/// WebSocket 关闭帧的状态码（RFC 6455 第 7.4.1 节）
+const CLOSE_NORMAL: u16 = 1000;
+const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+const CLOSE_TOO_LARGE: u16 = 1009;
+
+/// 连接使用的协议，由客户端发送的第一批字节决定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Protocol {
+    Resp,
+    /// 普通 HTTP 请求，或者随后升级为 WebSocket 的握手请求
+    Http,
+}
+
+impl Protocol {
+    /// 以 HTTP 方法开头的连接按 HTTP 处理，其余按 RESP 处理；前缀还无法区分时返回 None
+    fn detect(buf: &[u8]) -> Option<Self> {
+        if buf.is_empty() {
+            return None;
+        }
+        let mut undecided = false;
+        for method in http::METHODS {
+            if buf.starts_with(method) {
+                return Some(Self::Http);
+            }
+            undecided |= method.starts_with(buf);
+        }
+        (!undecided).then_some(Self::Resp)
+    }
+}
+
+/// 等待输入时收到的内容
+enum Input {
+    /// 从 socket 读到的字节数，0 表示对端关闭
+    Read(usize),
+    /// 需要推送给客户端的围栏事件
+    Event(String),
+    /// 订阅的频道收到的消息
+    Message(Message),
+}
+
+/// MULTI 之后排队的命令
+#[derive(Default)]
+struct Transaction {
+    commands: Vec<(String, Vec<RespValue>)>,
+    /// 有命令排队失败（例如未知命令），EXEC 时放弃整个事务
+    aborted: bool,
+}
+
 pub struct ServerConnection {
     stream: TcpStream,
-    registry: CommandRegistry,
+    /// 连接数计入数据库的统计，连接关闭时减去
+    database: Arc<GeoDatabase>,
+    registry: Arc<CommandRegistry>,
+    session: Session,
     buffer: Vec<u8>,
+    /// 保存 buffer 中未完成的 RESP 帧的解析进度
+    decoder: RespDecoder,
+    fences: Option<Arc<FenceManager>>,
+    /// 第一次注册围栏时创建
+    subscriber: Option<FenceSubscriber>,
+    /// 第一次 SUBSCRIBE/PSUBSCRIBE 时创建
+    channels: Option<PubSubSubscriber>,
+    /// RESP 和 WebSocket 连接可以推送事件，HTTP 请求不能注册围栏
+    streaming: bool,
+    /// RESP 连接把较大的回复分块写出，HTTP 和 WebSocket 需要完整的回复作为响应体或消息
+    chunked: bool,
+    /// `OUTPUT json` 之后回复为 Tile38 格式的 JSON
+    json_output: bool,
+    /// 配置了 `requirepass` 时，AUTH 需要提供的密码
+    password: Option<Arc<str>>,
+    /// SELECT 可以选择的命名空间数量
+    databases: u32,
+    /// MULTI 到 EXEC/DISCARD 之间排队的命令
+    transaction: Option<Transaction>,
 }
 
 impl ServerConnection {
     pub fn new(stream: TcpStream, database: Arc<GeoDatabase>) -> Self {
-        let registry = CommandRegistry::new(database);
+        database.client_connected();
+        let registry = Arc::new(CommandRegistry::new(Arc::clone(&database)));
         Self {
             stream,
+            database,
             registry,
+            session: Session::new(),
             buffer: Vec::with_capacity(4096),
+            decoder: RespDecoder::new(),
+            fences: None,
+            subscriber: None,
+            channels: None,
+            streaming: false,
+            chunked: false,
+            json_output: false,
+            password: None,
+            databases: 1,
+            transaction: None,
+        }
+    }
+
+    /// 要求连接先用 AUTH 提供密码才能执行其他命令，None 表示不需要认证
+    pub fn with_password(mut self, password: Option<Arc<str>>) -> Self {
+        if password.is_some() {
+            self.session = Session::unauthenticated();
         }
+        self.password = password;
+        self
+    }
+
+    /// 允许 `SELECT n` 选择 0 到 databases - 1 的命名空间
+    pub fn with_databases(mut self, databases: u32) -> Self {
+        self.databases = databases;
+        self
+    }
+
+    /// 允许连接用 FENCE 注册地理围栏，并使用 pub/sub 和围栏频道的命令
+    pub fn with_fences(mut self, fences: Arc<FenceManager>) -> Self {
+        self.fences = Some(fences);
+        self
     }
 
     pub async fn handle(&mut self) -> Result<()> {
         let peer_addr = self.stream.peer_addr()?;
         info!("New connection from {}", peer_addr);
 
-        loop {
-            // 读取数据
-            self.buffer.clear();
+        // 根据客户端发送的第一批字节决定协议，同一端口同时服务 CLI、curl 和浏览器
+        let protocol = loop {
+            if let Some(protocol) = Protocol::detect(&self.buffer) {
+                break Some(protocol);
+            }
             match self.read_command().await {
-                Ok(0) => {
-                    info!("Connection closed by {}", peer_addr);
-                    break;
+                Ok(0) => break None,
+                Ok(_) => {}
+                Err(e) => {
+                    error!("Failed to read from socket: {}", e);
+                    break None;
                 }
-                Ok(_) => {
-                    if let Err(e) = self.process_command().await {
-                        error!("Error processing command: {}", e);
-                        let error_response = RespResponse::error(&format!("ERR {}", e));
-                        if let Err(write_err) =
-                            self.stream.write_all(error_response.as_bytes()).await
-                        {
-                            error!("Failed to write error response: {}", write_err);
+            }
+        };
+
+        match protocol {
+            Some(Protocol::Resp) => self.handle_resp(peer_addr).await,
+            Some(Protocol::Http) => self.handle_http(peer_addr).await,
+            None => info!("Connection closed by {}", peer_addr),
+        }
+
+        info!("Connection with {} closed", peer_addr);
+        Ok(())
+    }
+
+    async fn handle_resp(&mut self, peer_addr: SocketAddr) {
+        self.streaming = true;
+        self.chunked = true;
+        loop {
+            // 先处理缓冲区中所有完整的帧（pipeline），帧不完整时再读取更多数据
+            let command = match self.decoder.decode(&self.buffer) {
+                Ok(Some((command, consumed))) => {
+                    self.buffer.drain(..consumed);
+                    command
+                }
+                Ok(None) => match self.read_input().await {
+                    Ok(Input::Read(0)) => {
+                        info!("Connection closed by {}", peer_addr);
+                        break;
+                    }
+                    Ok(Input::Read(_)) => continue,
+                    Ok(Input::Event(event)) => {
+                        // RESP3 客户端能区分推送帧和命令回复，RESP2 客户端只能收到批量字符串
+                        let push = if self.session.protocol_version() == Some(3) {
+                            RespResponse::push(&[RespValue::bulk("fence"), RespValue::bulk(event)])
+                        } else {
+                            RespResponse::bulk_string(Some(&event))
+                        };
+                        if let Err(e) = self.stream.write_all(push.as_bytes()).await {
+                            error!("Failed to write fence event: {}", e);
                             break;
                         }
+                        continue;
                     }
-                }
+                    Ok(Input::Message(message)) => {
+                        let resp3 = self.session.protocol_version() == Some(3);
+                        let push = message.to_resp(resp3);
+                        if let Err(e) = self.stream.write_all(push.as_bytes()).await {
+                            error!("Failed to write pub/sub message: {}", e);
+                            break;
+                        }
+                        continue;
+                    }
+                    Err(e) => {
+                        error!("Failed to read from socket: {}", e);
+                        break;
+                    }
+                },
                 Err(e) => {
-                    error!("Failed to read from socket: {}", e);
+                    // 帧边界已经无法确定：回复协议错误并关闭连接，而不是猜测下一帧的位置
+                    warn!("Closing connection with {}: {}", peer_addr, e);
+                    let response = RespResponse::error(&format!("ERR {}", e));
+                    self.write_and_close(response.as_bytes()).await;
                     break;
                 }
+            };
+
+            let Some(response) = self.reply_to(command, peer_addr).await else {
+                break;
+            };
+            if self.session.is_closing() {
+                self.write_and_close(response.as_bytes()).await;
+                break;
+            }
+            if let Err(e) = self.stream.write_all(response.as_bytes()).await {
+                error!("Failed to write response: {}", e);
+                break;
+            }
+            debug!("Sent response: {}", response.trim_end());
+        }
+    }
+
+    /// 处理一个 HTTP 请求，回复后关闭连接；WebSocket 升级请求转交给 [`Self::handle_websocket`]
+    async fn handle_http(&mut self, peer_addr: SocketAddr) {
+        let request = loop {
+            match http::parse_request(&self.buffer) {
+                Ok(Some((request, consumed))) => {
+                    self.buffer.drain(..consumed);
+                    break request;
+                }
+                Ok(None) => match self.read_command().await {
+                    Ok(0) => return,
+                    Ok(_) => continue,
+                    Err(e) => {
+                        error!("Failed to read from socket: {}", e);
+                        return;
+                    }
+                },
+                Err(e) => {
+                    warn!("Bad HTTP request from {}: {}", peer_addr, e);
+                    let body = http::error_json(&format!("ERR {}", e));
+                    self.write_and_close(&http::json_response(400, &body)).await;
+                    return;
+                }
+            }
+        };
+
+        // HTTP 请求各自使用一个连接，用 Authorization 头代替 AUTH 命令
+        let bearer = request
+            .header("Authorization")
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if bearer.is_some_and(|token| self.password_matches(token)) {
+            self.session.authenticate();
+        }
+
+        if request.is_websocket_upgrade() {
+            return self.handle_websocket(peer_addr, request).await;
+        }
+
+        let response = if request.method != "GET" && request.method != "POST" {
+            let message = format!("ERR method {} is not supported", request.method);
+            http::json_response(405, &http::error_json(&message))
+        } else {
+            match request.command() {
+                Ok(args) => {
+                    let Some(reply) = self.reply_to(command_from_args(args), peer_addr).await
+                    else {
+                        return;
+                    };
+                    let body = self.reply_json(&reply);
+                    let status = if body["ok"] == true { 200 } else { 400 };
+                    http::json_response(status, &body)
+                }
+                Err(e) => http::json_response(400, &http::error_json(&format!("ERR {}", e))),
             }
+        };
+        self.write_and_close(&response).await;
+    }
+
+    /// 完成 WebSocket 握手，之后每条消息是一个 JSON 字符串数组形式的命令，回复为 JSON 文本帧
+    async fn handle_websocket(&mut self, peer_addr: SocketAddr, request: HttpRequest) {
+        let Some(key) = request.header("Sec-WebSocket-Key") else {
+            let body = http::error_json("ERR missing Sec-WebSocket-Key header");
+            self.write_and_close(&http::json_response(400, &body)).await;
+            return;
+        };
+        if request.header("Sec-WebSocket-Version") != Some("13") {
+            let body = http::error_json("ERR only WebSocket version 13 is supported");
+            self.write_and_close(&http::json_response(426, &body)).await;
+            return;
+        }
+        if let Err(e) = self.stream.write_all(&http::upgrade_response(key)).await {
+            error!("Failed to write response: {}", e);
+            return;
         }
+        info!("Upgraded connection from {} to WebSocket", peer_addr);
+        self.streaming = true;
 
-        info!("Connection with {} closed", peer_addr);
-        Ok(())
+        // 分片消息中已经收到的部分
+        let mut partial: Option<Vec<u8>> = None;
+        loop {
+            let frame = match websocket::decode_frame(&self.buffer) {
+                Ok(Some((frame, consumed))) => {
+                    self.buffer.drain(..consumed);
+                    frame
+                }
+                Ok(None) => match self.read_input().await {
+                    Ok(Input::Read(0)) => {
+                        info!("Connection closed by {}", peer_addr);
+                        return;
+                    }
+                    Ok(Input::Read(_)) => continue,
+                    Ok(Input::Event(event)) => {
+                        let frame =
+                            websocket::encode_frame(websocket::OPCODE_TEXT, event.as_bytes());
+                        if let Err(e) = self.stream.write_all(&frame).await {
+                            error!("Failed to write fence event: {}", e);
+                            return;
+                        }
+                        continue;
+                    }
+                    Ok(Input::Message(message)) => {
+                        let frame = websocket::encode_frame(
+                            websocket::OPCODE_TEXT,
+                            message.to_json().as_bytes(),
+                        );
+                        if let Err(e) = self.stream.write_all(&frame).await {
+                            error!("Failed to write pub/sub message: {}", e);
+                            return;
+                        }
+                        continue;
+                    }
+                    Err(e) => {
+                        error!("Failed to read from socket: {}", e);
+                        return;
+                    }
+                },
+                Err(e) => {
+                    warn!("Closing WebSocket with {}: {}", peer_addr, e);
+                    return self.close_websocket(CLOSE_PROTOCOL_ERROR).await;
+                }
+            };
+
+            match (frame.opcode, &mut partial) {
+                (websocket::OPCODE_PING, _) => {
+                    let pong = websocket::encode_frame(websocket::OPCODE_PONG, &frame.payload);
+                    if let Err(e) = self.stream.write_all(&pong).await {
+                        error!("Failed to write response: {}", e);
+                        return;
+                    }
+                    continue;
+                }
+                (websocket::OPCODE_PONG, _) => continue,
+                (websocket::OPCODE_CLOSE, _) => {
+                    return self.close_websocket(CLOSE_NORMAL).await;
+                }
+                (websocket::OPCODE_TEXT | websocket::OPCODE_BINARY, None) => {
+                    partial = Some(frame.payload);
+                }
+                (websocket::OPCODE_CONTINUATION, Some(message)) => {
+                    if message.len() + frame.payload.len() > websocket::MAX_FRAME_PAYLOAD {
+                        warn!("Closing WebSocket with {}: message too large", peer_addr);
+                        return self.close_websocket(CLOSE_TOO_LARGE).await;
+                    }
+                    message.extend_from_slice(&frame.payload);
+                }
+                (opcode, _) => {
+                    warn!(
+                        "Closing WebSocket with {}: unexpected opcode {:#x}",
+                        peer_addr, opcode
+                    );
+                    return self.close_websocket(CLOSE_PROTOCOL_ERROR).await;
+                }
+            }
+            if !frame.fin {
+                continue;
+            }
+            let Some(message) = partial.take() else {
+                continue;
+            };
+
+            let reply = match http::parse_json_command(&message) {
+                Ok(args) => match self.reply_to(command_from_args(args), peer_addr).await {
+                    Some(reply) => self.reply_json(&reply),
+                    None => return,
+                },
+                Err(e) => http::error_json(&format!("ERR {}", e)),
+            };
+            let frame =
+                websocket::encode_frame(websocket::OPCODE_TEXT, reply.to_string().as_bytes());
+            if let Err(e) = self.stream.write_all(&frame).await {
+                error!("Failed to write response: {}", e);
+                return;
+            }
+            if self.session.is_closing() {
+                return self.close_websocket(CLOSE_NORMAL).await;
+            }
+        }
+    }
+
+    /// 发送带状态码的关闭帧并关闭连接
+    async fn close_websocket(&mut self, code: u16) {
+        let frame = websocket::encode_frame(websocket::OPCODE_CLOSE, &code.to_be_bytes());
+        self.write_and_close(&frame).await;
+    }
+
+    /// 尽力写出最后的回复并关闭连接，连接已经断开时忽略错误
+    async fn write_and_close(&mut self, bytes: &[u8]) {
+        let _ = self.stream.write_all(bytes).await;
+        let _ = self.stream.shutdown().await;
+    }
+
+    /// 执行命令并编码回复，客户端在执行期间断开时返回 None
+    ///
+    /// 会话状态在这里统一检查和切换：当前状态不允许的命令直接回复错误，不会到达命令注册表
+    async fn reply_to(&mut self, command: RespValue, peer_addr: SocketAddr) -> Option<String> {
+        let (cmd_name, args) = match command {
+            RespValue::Array(Some(mut arr)) if !arr.is_empty() => {
+                // 第一个元素是命令名
+                match arr.remove(0) {
+                    RespValue::BulkString(Some(cmd_name)) => (cmd_name, arr),
+                    _ => return Some(RespResponse::error("ERR invalid command format")),
+                }
+            }
+            // 简单命令（如直接输入 PING）
+            RespValue::BulkString(Some(cmd_name)) => (cmd_name, Vec::new()),
+            _ => return Some(RespResponse::error("ERR invalid command format")),
+        };
+        // 参数可以是任意字节，命令名必须是文本
+        let Ok(cmd_name) = String::from_utf8(cmd_name) else {
+            return Some(RespResponse::error("ERR invalid command format"));
+        };
+        let transition = match self.session.admit(&cmd_name, &args) {
+            Ok(transition) => transition,
+            Err(message) => return Some(RespResponse::error(&message)),
+        };
+
+        let started = Instant::now();
+        let name = cmd_name.clone();
+        let arguments = if self.json_output {
+            args.clone()
+        } else {
+            Vec::new()
+        };
+        let response = self.respond(transition, cmd_name, args, peer_addr).await?;
+        self.session.complete(transition, &response);
+
+        // 订阅的确认和之后推送的消息保持 RESP 格式
+        if !self.json_output || transition == Transition::Subscription {
+            return Some(response);
+        }
+        let Ok(Some((reply, _))) = RespParser::decode(response.as_bytes()) else {
+            return Some(response);
+        };
+        let body = OutputFormatter::tile38_json(&name, &arguments, &reply, started.elapsed());
+        Some(RespResponse::bulk_string(Some(&body.to_string())))
+    }
+
+    /// 执行会话已经放行的命令，连接级的命令在到达命令注册表之前处理
+    async fn respond(
+        &mut self,
+        transition: Transition,
+        cmd_name: String,
+        args: Vec<RespValue>,
+        peer_addr: SocketAddr,
+    ) -> Option<String> {
+        if cmd_name.eq_ignore_ascii_case("OUTPUT") {
+            return Some(self.set_output(&args));
+        }
+        match transition {
+            Transition::Auth => return Some(self.auth(&args)),
+            Transition::Multi => {
+                self.transaction = Some(Transaction::default());
+                return Some(RespResponse::simple_string("OK"));
+            }
+            Transition::Queue => return Some(self.queue(cmd_name, args)),
+            Transition::EndTransaction => return self.end_transaction(&cmd_name, peer_addr).await,
+            _ => {}
+        }
+        if cmd_name.eq_ignore_ascii_case("SELECT") {
+            return Some(self.select(&args));
+        }
+        // 启动加载完成后才重新注册恢复的频道，加载期间不能修改频道或注册围栏
+        if transition == Transition::Fence
+            || ["SETCHAN", "DELCHAN"]
+                .iter()
+                .any(|name| cmd_name.eq_ignore_ascii_case(name))
+        {
+            if let Err(e) = self.database.check_loaded() {
+                return Some(RespResponse::error(&format!("{} {}", e.code.prefix(), e)));
+            }
+        }
+        if let Some(fences) = self.fences.clone() {
+            if let Some(response) = self.pubsub_command(&fences, &cmd_name, &args).await {
+                return Some(response);
+            }
+        }
+        if transition == Transition::Fence && self.streaming {
+            if let Some(fences) = self.fences.clone() {
+                return Some(self.register_fence(&fences, &cmd_name, &args));
+            }
+        }
+
+        // JSON 输出需要完整的回复才能转换
+        if self.chunked && !self.json_output && self.registry.streams(&cmd_name) {
+            return self.stream_command(&cmd_name, &args, peer_addr).await;
+        }
+
+        match self.execute_command(cmd_name, args).await {
+            Ok(Some(response)) => Some(response),
+            Ok(None) => {
+                info!("{} disconnected while a command was in flight", peer_addr);
+                None
+            }
+            Err(e) => {
+                error!("Error processing command: {}", e);
+                Some(RespResponse::error(&format!("ERR {}", e)))
+            }
+        }
+    }
+
+    /// 执行命令，回复边生成边分块写到 socket，写完后返回空字符串
+    ///
+    /// 客户端读得慢时命令在写入处等待（见 [`ReplySink`]），断开时命令被取消并返回 None。
+    /// 已经写出一部分回复后出错无法再回复错误，同样返回 None 关闭连接
+    async fn stream_command(
+        &mut self,
+        cmd_name: &str,
+        args: &[RespValue],
+        peer_addr: SocketAddr,
+    ) -> Option<String> {
+        let (mut sink, mut chunks) = ReplySink::channel();
+        let registry = Arc::clone(&self.registry);
+        // 命令完成时 sink 随之丢弃，chunks 在取完剩余的块后结束
+        let command = async move { registry.execute_into(cmd_name, args, &mut sink).await };
+        tokio::pin!(command);
+
+        let (mut reader, mut writer) = self.stream.split();
+        let mut probe = [0u8; 1];
+        // 客户端已经发送了后续命令（pipeline）时无法再探测断开，见 run_until_disconnect
+        let mut probing = true;
+        let mut written = false;
+        let mut result = None;
+        loop {
+            tokio::select! {
+                biased;
+                chunk = chunks.recv() => {
+                    let Some(chunk) = chunk else { break };
+                    if let Err(e) = writer.write_all(chunk.as_bytes()).await {
+                        error!("Failed to write response: {}", e);
+                        return None;
+                    }
+                    written = true;
+                }
+                output = &mut command, if result.is_none() => result = Some(output),
+                peeked = reader.peek(&mut probe), if probing => {
+                    if peeked.is_err() {
+                        info!("{} disconnected while a command was in flight", peer_addr);
+                        return None;
+                    }
+                    probing = false;
+                }
+            }
+        }
+
+        // chunks 只在命令完成、sink 丢弃之后结束，这里 result 总是已经取得
+        let result = match result {
+            Some(result) => result,
+            None => command.await,
+        };
+        match result {
+            Ok(()) => Some(String::new()),
+            Err(e) if !written => {
+                error!("Error processing command: {}", e);
+                Some(RespResponse::error(&format!("ERR {}", e)))
+            }
+            Err(e) => {
+                error!(
+                    "Closing connection with {} after a partial reply: {}",
+                    peer_addr, e
+                );
+                None
+            }
+        }
+    }
+
+    /// `OUTPUT [json|resp]`：不带参数时返回当前的输出格式
+    fn set_output(&mut self, args: &[RespValue]) -> String {
+        match args {
+            [] => RespResponse::bulk_string(Some(if self.json_output { "json" } else { "resp" })),
+            [format] => match format.as_str().map(str::to_lowercase).as_deref() {
+                Some("json") => {
+                    self.json_output = true;
+                    RespResponse::simple_string("OK")
+                }
+                Some("resp") => {
+                    self.json_output = false;
+                    RespResponse::simple_string("OK")
+                }
+                _ => RespResponse::error("ERR invalid output format, expected json or resp"),
+            },
+            _ => RespResponse::error("ERR wrong number of arguments for 'OUTPUT' command"),
+        }
+    }
+
+    /// `AUTH [default] password`：与 `requirepass` 比较，成功后会话可以执行所有命令
+    fn auth(&self, args: &[RespValue]) -> String {
+        let password = match args {
+            [password] => password,
+            [user, password] if user.as_str() == Some("default") => password,
+            [_, _] => {
+                return RespResponse::error(
+                    "WRONGPASS invalid username-password pair or user is disabled.",
+                )
+            }
+            _ => return RespResponse::error("ERR wrong number of arguments for 'AUTH' command"),
+        };
+        if self.password.is_none() {
+            return RespResponse::error(
+                "ERR AUTH called without any password configured, set server.requirepass to enable it",
+            );
+        }
+        if password.as_str().is_some_and(|p| self.password_matches(p)) {
+            RespResponse::simple_string("OK")
+        } else {
+            RespResponse::error("WRONGPASS invalid username-password pair or user is disabled.")
+        }
+    }
+
+    fn password_matches(&self, given: &str) -> bool {
+        self.password
+            .as_deref()
+            .is_some_and(|expected| password_matches(expected, given))
+    }
+
+    /// 事务中的命令排队，EXEC 时再执行；未知命令使整个事务在 EXEC 时被放弃
+    fn queue(&mut self, cmd_name: String, args: Vec<RespValue>) -> String {
+        let transaction = self.transaction.get_or_insert_with(Transaction::default);
+        if !self.registry.has_command(&cmd_name) {
+            transaction.aborted = true;
+            return RespResponse::error(&format!("ERR unknown command '{}'", cmd_name));
+        }
+        transaction.commands.push((cmd_name, args));
+        RespResponse::simple_string("QUEUED")
+    }
+
+    /// EXEC 依次执行排队的命令并返回所有回复组成的数组，DISCARD 丢弃排队的命令；
+    /// 客户端在 EXEC 执行期间断开时返回 None
+    async fn end_transaction(&mut self, cmd_name: &str, peer_addr: SocketAddr) -> Option<String> {
+        let transaction = self.transaction.take().unwrap_or_default();
+        if cmd_name.eq_ignore_ascii_case("DISCARD") {
+            return Some(RespResponse::simple_string("OK"));
+        }
+        if transaction.aborted {
+            return Some(RespResponse::error(
+                "EXECABORT Transaction discarded because of previous errors.",
+            ));
+        }
+
+        let commands = transaction.commands;
+        let has_write = commands
+            .iter()
+            .any(|(name, _)| self.registry.is_write(name));
+        let replies = if has_write {
+            // 与单个写命令一样在独立任务中执行，客户端断开时事务仍然完整执行
+            let registry = Arc::clone(&self.registry);
+            let task = tokio::spawn(async move { registry.execute_transaction(&commands).await });
+            match run_until_disconnect(&self.stream, task).await {
+                Some(Ok(replies)) => replies,
+                Some(Err(e)) => Err(format!("command task failed: {}", e).into()),
+                None => {
+                    info!(
+                        "{} disconnected while a transaction was in flight",
+                        peer_addr
+                    );
+                    return None;
+                }
+            }
+        } else {
+            let execution = self.registry.execute_transaction(&commands);
+            match run_until_disconnect(&self.stream, execution).await {
+                Some(replies) => replies,
+                None => {
+                    info!(
+                        "{} disconnected while a transaction was in flight",
+                        peer_addr
+                    );
+                    return None;
+                }
+            }
+        };
+        match replies {
+            Ok(replies) => Some(format!("*{}\r\n{}", replies.len(), replies.concat())),
+            Err(e) => {
+                error!("Error processing transaction: {}", e);
+                Some(RespResponse::error(&format!("ERR {}", e)))
+            }
+        }
+    }
+
+    /// `SELECT n`：之后的命令使用命名空间 n 中的 collection
+    fn select(&mut self, args: &[RespValue]) -> String {
+        let [index] = args else {
+            return RespResponse::error("ERR wrong number of arguments for 'SELECT' command");
+        };
+        let Some(index) = index.as_str().and_then(|s| s.parse::<u32>().ok()) else {
+            return RespResponse::error("ERR value is not an integer or out of range");
+        };
+        if index >= self.databases {
+            return RespResponse::error(&format!(
+                "ERR DB index is out of range, expected 0 to {}",
+                self.databases - 1
+            ));
+        }
+        let namespace = Namespace::new(index);
+        if namespace != self.registry.namespace() {
+            self.registry = Arc::new(CommandRegistry::with_namespace(
+                Arc::clone(&self.database),
+                namespace,
+            ));
+        }
+        RespResponse::simple_string("OK")
+    }
+
+    /// HTTP 和 WebSocket 的 JSON 回复；`OUTPUT json` 模式下回复本身已经是 JSON，原样返回
+    fn reply_json(&self, reply: &str) -> serde_json::Value {
+        if self.json_output {
+            if let Ok(Some((RespValue::BulkString(Some(bytes)), _))) =
+                RespParser::decode(reply.as_bytes())
+            {
+                if let Ok(body) = serde_json::from_slice(&bytes) {
+                    return body;
+                }
+            }
+        }
+        http::reply_to_json(reply)
+    }
+
+    /// 注册 NEARBY/INTERSECTS ... FENCE 描述的围栏，之后的事件在等待输入时推送
+    fn register_fence(
+        &mut self,
+        fences: &FenceManager,
+        cmd_name: &str,
+        args: &[RespValue],
+    ) -> String {
+        let args = match self.registry.scope_args(cmd_name, args) {
+            Ok(args) => args,
+            Err(message) => return RespResponse::error(&message),
+        };
+        let spec = match FenceSpec::parse(cmd_name, &args, self.database.distance_metric()) {
+            Ok(Some(spec)) => spec,
+            // FENCE 出现在参数值中（例如标签名），按普通命令的错误处理
+            Ok(None) => return RespResponse::error("ERR syntax error near FENCE"),
+            Err(message) => return RespResponse::error(&message),
+        };
+        let subscriber = self.subscriber.get_or_insert_with(|| fences.subscribe());
+        let id = fences.register(subscriber, spec);
+        debug!("Registered fence {}", id);
+        RespResponse::simple_string("OK")
+    }
+
+    /// 处理 pub/sub、围栏频道和 FENCES 命令，其他命令返回 None
+    async fn pubsub_command(
+        &mut self,
+        fences: &FenceManager,
+        cmd_name: &str,
+        args: &[RespValue],
+    ) -> Option<String> {
+        let name = cmd_name.to_uppercase();
+        let text = |arg: &RespValue| match arg {
+            RespValue::BulkString(Some(bytes)) => String::from_utf8_lossy(bytes).into_owned(),
+            _ => String::new(),
+        };
+        let wrong_arity = || {
+            RespResponse::error(&format!(
+                "ERR wrong number of arguments for '{}' command",
+                cmd_name
+            ))
+        };
+        let response = match name.as_str() {
+            "SUBSCRIBE" | "PSUBSCRIBE" | "UNSUBSCRIBE" | "PUNSUBSCRIBE" => {
+                if !self.streaming {
+                    return Some(RespResponse::error(&format!(
+                        "ERR {} is only supported on RESP and WebSocket connections",
+                        name
+                    )));
+                }
+                if args.is_empty() && !name.contains("UNSUBSCRIBE") {
+                    return Some(wrong_arity());
+                }
+                let pubsub = fences.pubsub();
+                let subscriber = self.channels.get_or_insert_with(|| pubsub.subscriber());
+                let mut targets: Vec<String> = args.iter().map(text).collect();
+                if targets.is_empty() {
+                    // 不带参数的退订：退订所有频道（或模式）
+                    targets = if name == "UNSUBSCRIBE" {
+                        subscriber.channels()
+                    } else {
+                        subscriber.patterns()
+                    };
+                }
+                let kind = name.to_lowercase();
+                let resp3 = self.session.protocol_version() == Some(3);
+                let confirm = |target: Option<&str>, count: usize| {
+                    let items = [
+                        RespValue::bulk(kind.as_str()),
+                        target.map_or(RespValue::BulkString(None), RespValue::bulk),
+                        RespValue::Integer(count as i64),
+                    ];
+                    if resp3 {
+                        RespResponse::push(&items)
+                    } else {
+                        RespResponse::array(Some(&items))
+                    }
+                };
+                if targets.is_empty() {
+                    return Some(confirm(None, subscriber.count()));
+                }
+                let mut response = String::new();
+                for target in &targets {
+                    let count = match name.as_str() {
+                        "SUBSCRIBE" => pubsub.subscribe(subscriber, target),
+                        "PSUBSCRIBE" => pubsub.psubscribe(subscriber, target),
+                        "UNSUBSCRIBE" => pubsub.unsubscribe(subscriber, target),
+                        _ => pubsub.punsubscribe(subscriber, target),
+                    };
+                    response.push_str(&confirm(Some(target), count));
+                }
+                response
+            }
+            "PUBLISH" => match args {
+                [channel, message] => {
+                    let received = fences.pubsub().publish(&text(channel), &text(message));
+                    RespResponse::integer(received as i64)
+                }
+                _ => wrong_arity(),
+            },
+            "SETCHAN" => {
+                let scoped = match self.registry.scope_args(cmd_name, args) {
+                    Ok(scoped) => scoped,
+                    Err(message) => return Some(RespResponse::error(&message)),
+                };
+                let spec = match ChannelSpec::parse(&scoped, self.database.distance_metric()) {
+                    Ok(spec) => spec,
+                    Err(message) => return Some(RespResponse::error(&message)),
+                };
+                // 先持久化定义，重启后由服务器重新注册
+                let definition = scoped[1..].iter().map(text).collect();
+                if let Err(e) = self.persist_channel(&spec.name, Some(definition)).await {
+                    return Some(RespResponse::error(&format!("ERR {}", e)));
+                }
+                let name = spec.name.clone();
+                let replaced = fences.set_channel(spec);
+                debug!("Set fence channel {} (replaced: {})", name, replaced);
+                RespResponse::simple_string("OK")
+            }
+            "DELCHAN" => match args {
+                [channel] => {
+                    let name = text(channel);
+                    if let Err(e) = self.persist_channel(&name, None).await {
+                        return Some(RespResponse::error(&format!("ERR {}", e)));
+                    }
+                    RespResponse::integer(i64::from(fences.delete_channel(&name)))
+                }
+                _ => wrong_arity(),
+            },
+            "FENCES" => {
+                let is = |i: usize, word: &str| {
+                    args.get(i)
+                        .and_then(RespValue::as_str)
+                        .is_some_and(|s| s.eq_ignore_ascii_case(word))
+                };
+                match args.len() {
+                    1 if is(0, "LIST") => {
+                        let items: Vec<RespValue> =
+                            fences.list().iter().map(FenceInfo::to_resp).collect();
+                        RespResponse::array(Some(&items))
+                    }
+                    3 if is(0, "CONTAINING") => {
+                        let coordinate = |i: usize, limit: f64| {
+                            text(&args[i])
+                                .parse::<f64>()
+                                .ok()
+                                .filter(|v| (-limit..=limit).contains(v))
+                        };
+                        match (coordinate(1, 180.0), coordinate(2, 90.0)) {
+                            (Some(lon), Some(lat)) => {
+                                let items: Vec<RespValue> = fences
+                                    .containing(lon, lat)
+                                    .into_iter()
+                                    .map(|id| RespValue::Integer(id as i64))
+                                    .collect();
+                                RespResponse::array(Some(&items))
+                            }
+                            _ => RespResponse::error(
+                                "ERR invalid coordinates: expected lon in [-180, 180] and lat in [-90, 90]",
+                            ),
+                        }
+                    }
+                    _ => RespResponse::error(
+                        "ERR syntax error, expected FENCES LIST or FENCES CONTAINING lon lat",
+                    ),
+                }
+            }
+            _ => return None,
+        };
+        Some(response)
+    }
+
+    /// 保存（`definition` 为 Some）或删除围栏频道的定义，等待记录落盘
+    async fn persist_channel(&self, name: &str, definition: Option<Vec<String>>) -> Result<()> {
+        match definition {
+            Some(args) => self.database.set_channel(name, args).await?,
+            None => {
+                self.database.delete_channel(name).await?;
+            }
+        }
+        self.database.wait_durable().await
+    }
+
+    /// 读取更多数据；注册了围栏或订阅了频道时同时等待推送的事件和消息
+    async fn read_input(&mut self) -> Result<Input> {
+        if self.subscriber.is_none() && self.channels.is_none() {
+            return Ok(Input::Read(self.read_command().await?));
+        }
+        let events = self.subscriber.as_mut().map(|s| &mut s.events);
+        let messages = self.channels.as_mut().map(|s| &mut s.messages);
+        let mut temp_buffer = [0; 4096];
+        tokio::select! {
+            read = self.stream.read(&mut temp_buffer) => {
+                let bytes_read = read?;
+                self.buffer.extend_from_slice(&temp_buffer[..bytes_read]);
+                Ok(Input::Read(bytes_read))
+            }
+            Some(event) = recv_from(events) => Ok(Input::Event(event)),
+            Some(message) = recv_from(messages) => Ok(Input::Message(message)),
+        }
     }
 
     async fn read_command(&mut self) -> Result<usize> {
-        let mut temp_buffer = [0; 1024];
+        let mut temp_buffer = [0; 4096];
         let bytes_read = self.stream.read(&mut temp_buffer).await?;
 
         if bytes_read > 0 {
@@ -69,70 +961,997 @@ impl ServerConnection {
             debug!(
                 "Read {} bytes: {:?}",
                 bytes_read,
-                String::from_utf8_lossy(&self.buffer)
+                String::from_utf8_lossy(&temp_buffer[..bytes_read])
             );
         }
 
         Ok(bytes_read)
     }
 
-    async fn process_command(&mut self) -> Result<()> {
-        if let Some(command_bytes) = self.extract_complete_command() {
-            let command_str = String::from_utf8_lossy(&command_bytes);
-            debug!("Processing command: {}", command_str.trim());
+    /// 执行命令，客户端在执行期间断开时返回 None
+    ///
+    /// 读命令随连接一起取消，释放持有的读锁并放弃响应的构建；
+    /// 写命令在独立任务中执行，即使客户端断开也会完整地修改内存并写入 AOF
+    async fn execute_command(
+        &self,
+        cmd_name: String,
+        args: Vec<RespValue>,
+    ) -> Result<Option<String>> {
+        if self.registry.is_write(&cmd_name) {
+            let registry = Arc::clone(&self.registry);
+            // 丢弃 JoinHandle 不会取消任务
+            let task = tokio::spawn(async move { registry.execute(&cmd_name, &args).await });
+            match run_until_disconnect(&self.stream, task).await {
+                Some(joined) => joined
+                    .map_err(|e| format!("command task failed: {}", e))?
+                    .map(Some),
+                None => Ok(None),
+            }
+        } else {
+            run_until_disconnect(&self.stream, self.registry.execute(&cmd_name, &args))
+                .await
+                .transpose()
+        }
+    }
+}
+
+impl Drop for ServerConnection {
+    /// 连接关闭时注销它注册的所有围栏，退订所有频道
+    fn drop(&mut self) {
+        self.database.client_disconnected();
+        if let (Some(fences), Some(subscriber)) = (&self.fences, &self.subscriber) {
+            fences.unsubscribe(subscriber.id());
+        }
+        if let (Some(fences), Some(channels)) = (&self.fences, &mut self.channels) {
+            fences.pubsub().unsubscribe_all(channels);
+        }
+    }
+}
 
-            // 处理命令
-            let response = self.process_command_str(&command_str).await?;
+/// 从可能不存在的通道接收，通道不存在时永远等待
+async fn recv_from<T>(receiver: Option<&mut mpsc::UnboundedReceiver<T>>) -> Option<T> {
+    match receiver {
+        Some(receiver) => receiver.recv().await,
+        None => std::future::pending().await,
+    }
+}
 
-            // 发送响应
-            self.stream.write_all(response.as_bytes()).await?;
-            debug!("Sent response: {}", response.trim_end());
+/// 把 HTTP 和 WebSocket 中的参数列表转换为与 RESP 客户端相同的命令数组
+fn command_from_args(args: Vec<String>) -> RespValue {
+    RespValue::Array(Some(args.into_iter().map(RespValue::bulk).collect()))
+}
+
+/// 执行 future，直到完成或检测到客户端断开（此时 future 被丢弃并返回 None）
+///
+/// 通过 peek 探测连接状态，不会消费数据。只有连接出错（例如被重置）才算断开：
+/// peek 读到 EOF 只说明客户端关闭了写方向（半关闭，例如 `nc -N`），它仍然在等待回复。
+/// 如果客户端已经发送了后续命令（pipeline）或者已经半关闭，则无法再探测断开，
+/// 只等待 future 完成，之后写回复失败时再关闭连接。
+async fn run_until_disconnect<F: Future>(stream: &TcpStream, future: F) -> Option<F::Output> {
+    tokio::pin!(future);
+    let mut probe = [0u8; 1];
+
+    tokio::select! {
+        biased;
+        output = &mut future => return Some(output),
+        peeked = stream.peek(&mut probe) => {
+            if peeked.is_err() {
+                return None;
+            }
         }
+    }
 
-        Ok(())
+    Some(future.await)
+}
+
+/// 逐字节比较全部内容，耗时不随第一个不同字节的位置变化
+pub(crate) fn password_matches(expected: &str, given: &str) -> bool {
+    let (expected, given) = (expected.as_bytes(), given.as_bytes());
+    expected.len() == given.len()
+        && expected
+            .iter()
+            .zip(given)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtree::algorithms::knn::DistanceMetric;
+    use serde_json::json;
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+    use tokio::sync::RwLock;
+
+    async fn socket_pair() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = TcpStream::connect(addr).await.unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+        (server, client)
     }
 
-    async fn process_command_str(&self, data: &str) -> Result<String> {
-        // 解析 RESP 协议
-        let parser = RespParser::new();
-        match parser.parse(data.as_bytes()) {
-            Ok(command) => {
-                let response = self.execute_command(command).await?;
-                Ok(response)
+    #[tokio::test]
+    async fn test_disconnect_cancels_read_and_releases_lock() {
+        let (server, client) = socket_pair().await;
+        let lock = Arc::new(RwLock::new(()));
+
+        // 模拟一个持有读锁、迟迟不返回的查询
+        let query = {
+            let lock = Arc::clone(&lock);
+            async move {
+                let _guard = lock.read().await;
+                std::future::pending::<String>().await
             }
-            Err(e) => {
-                eprintln!("Parse error: {:?}", e);
-                Ok(RespResponse::error("ERR parse error"))
+        };
+
+        // 连接被重置（RST）才算断开，正常关闭见 test_half_close_still_gets_reply
+        client.set_zero_linger().unwrap();
+        drop(client);
+        let result =
+            tokio::time::timeout(Duration::from_secs(5), run_until_disconnect(&server, query))
+                .await
+                .unwrap();
+
+        assert!(result.is_none());
+        assert!(lock.try_write().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_half_close_still_gets_reply() {
+        let database = Arc::new(GeoDatabase::new());
+        let point = json!({"type": "Point", "coordinates": [116.4, 39.9]}).to_string();
+        database.set("fleet", "truck1", &point).await.unwrap();
+        let (server, mut client) = socket_pair().await;
+        let mut connection = ServerConnection::new(server, Arc::clone(&database));
+        let handle = tokio::spawn(async move { connection.handle().await });
+
+        // 发送命令后关闭写方向（nc -N、shutdown(SHUT_WR)），然后读取回复
+        client
+            .write_all(b"*3\r\n$3\r\nGET\r\n$5\r\nfleet\r\n$6\r\ntruck1\r\n")
+            .await
+            .unwrap();
+        client.shutdown().await.unwrap();
+
+        let mut reply = String::new();
+        tokio::time::timeout(Duration::from_secs(5), client.read_to_string(&mut reply))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(reply.contains("116.4"), "unexpected reply: {:?}", reply);
+        handle.await.unwrap().unwrap();
+
+        // 半关闭时正在执行的命令同样完成并回复
+        let (server, mut client) = socket_pair().await;
+        client.shutdown().await.unwrap();
+        let query = async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            "done"
+        };
+        assert_eq!(run_until_disconnect(&server, query).await, Some("done"));
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_data_is_not_a_disconnect() {
+        let (server, mut client) = socket_pair().await;
+        client.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+
+        let query = async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            "done"
+        };
+        let result = run_until_disconnect(&server, query).await;
+        assert_eq!(result, Some("done"));
+
+        // peek 不会消费后续命令
+        let mut buf = [0u8; 64];
+        let n = server.try_read(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"*1\r\n$4\r\nPING\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_write_completes_after_disconnect() {
+        let database = Arc::new(GeoDatabase::new());
+        let (server, mut client) = socket_pair().await;
+        let mut connection = ServerConnection::new(server, Arc::clone(&database));
+
+        // 发送 SET 后立即断开，不等待响应
+        let point = json!({"type": "Point", "coordinates": [116.4, 39.9]}).to_string();
+        let command = format!(
+            "*4\r\n$3\r\nSET\r\n$5\r\nfleet\r\n$6\r\ntruck1\r\n${}\r\n{}\r\n",
+            point.len(),
+            point
+        );
+        client.write_all(command.as_bytes()).await.unwrap();
+        drop(client);
+
+        tokio::time::timeout(Duration::from_secs(5), connection.handle())
+            .await
+            .unwrap()
+            .unwrap();
+
+        // 写命令完整执行
+        let mut stored = false;
+        for _ in 0..50 {
+            if database.get("fleet", "truck1").await.unwrap().is_some() {
+                stored = true;
+                break;
             }
+            tokio::time::sleep(Duration::from_millis(10)).await;
         }
+        assert!(stored);
     }
 
-    fn extract_complete_command(&mut self) -> Option<Vec<u8>> {
-        // 简单实现：假设每次接收到的数据都是完整的命令
-        if !self.buffer.is_empty() {
-            let command = self.buffer.clone();
-            self.buffer.clear();
-            return Some(command);
+    #[tokio::test]
+    async fn test_disconnect_mid_request() {
+        let database = Arc::new(GeoDatabase::new());
+        let (server, mut client) = socket_pair().await;
+        let mut connection = ServerConnection::new(server, Arc::clone(&database));
+
+        // 只发送了命令的一部分就断开
+        client
+            .write_all(b"*4\r\n$3\r\nSET\r\n$5\r\nfle")
+            .await
+            .unwrap();
+        drop(client);
+
+        tokio::time::timeout(Duration::from_secs(5), connection.handle())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(database.collection_names().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_split_and_pipelined_frames() {
+        let database = Arc::new(GeoDatabase::new());
+        let (server, mut client) = socket_pair().await;
+        let mut connection = ServerConnection::new(server, database);
+        let handle = tokio::spawn(async move { connection.handle().await });
+
+        // 一帧分两次发送，第二次同时带上一条完整的命令
+        client.write_all(b"*1\r\n$4\r\nPI").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        client
+            .write_all(b"NG\r\n*1\r\n$4\r\nPING\r\n")
+            .await
+            .unwrap();
+
+        let mut received = Vec::new();
+        let mut buf = [0u8; 64];
+        while received.len() < 14 {
+            let n = tokio::time::timeout(Duration::from_secs(5), client.read(&mut buf))
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(n > 0);
+            received.extend_from_slice(&buf[..n]);
         }
-        None
+        assert_eq!(received, b"+PONG\r\n+PONG\r\n");
+
+        drop(client);
+        handle.await.unwrap().unwrap();
     }
 
-    async fn execute_command(&self, command: RespValue) -> Result<String> {
-        match command {
-            RespValue::Array(Some(arr)) if !arr.is_empty() => {
-                // 第一个元素是命令名
-                if let RespValue::BulkString(Some(cmd_name)) = &arr[0] {
-                    let args = &arr[1..];
-                    self.registry.execute(cmd_name, args).await
-                } else {
-                    Ok(RespResponse::error("ERR invalid command format"))
-                }
+    #[tokio::test]
+    async fn test_malformed_frame_replies_and_closes() {
+        let database = Arc::new(GeoDatabase::new());
+        let (server, mut client) = socket_pair().await;
+        let mut connection = ServerConnection::new(server, database);
+
+        // 第一条命令正常执行，之后的畸形帧导致连接关闭
+        client
+            .write_all(b"*1\r\n$4\r\nPING\r\n$3\r\nfooXY*1\r\n$4\r\nPING\r\n")
+            .await
+            .unwrap();
+
+        tokio::time::timeout(Duration::from_secs(5), connection.handle())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let mut received = String::new();
+        client.read_to_string(&mut received).await.unwrap();
+        assert!(received.starts_with("+PONG\r\n-ERR Protocol error: "));
+        assert_eq!(received.matches("PONG").count(), 1);
+    }
+
+    #[test]
+    fn test_protocol_detection() {
+        assert_eq!(Protocol::detect(b""), None);
+        assert_eq!(Protocol::detect(b"*1\r\n"), Some(Protocol::Resp));
+        // 在方法名完整之前无法区分
+        assert_eq!(Protocol::detect(b"PO"), None);
+        assert_eq!(Protocol::detect(b"POST / HTTP/1.1"), Some(Protocol::Http));
+        assert_eq!(Protocol::detect(b"GETX"), Some(Protocol::Resp));
+        assert_eq!(Protocol::detect(b"PING\r\n"), Some(Protocol::Resp));
+    }
+
+    async fn http_roundtrip(database: Arc<GeoDatabase>, request: &[u8]) -> String {
+        let (server, mut client) = socket_pair().await;
+        let mut connection = ServerConnection::new(server, database);
+        let handle = tokio::spawn(async move { connection.handle().await });
+
+        client.write_all(request).await.unwrap();
+        let mut received = String::new();
+        tokio::time::timeout(Duration::from_secs(5), client.read_to_string(&mut received))
+            .await
+            .unwrap()
+            .unwrap();
+        handle.await.unwrap().unwrap();
+        received
+    }
+
+    #[tokio::test]
+    async fn test_http_get_and_post() {
+        let database = Arc::new(GeoDatabase::new());
+
+        let body = json!([
+            "SET",
+            "fleet",
+            "truck1",
+            json!({"type": "Point", "coordinates": [116.4, 39.9]}).to_string()
+        ])
+        .to_string();
+        let request = format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let received = http_roundtrip(Arc::clone(&database), request.as_bytes()).await;
+        assert!(received.starts_with("HTTP/1.1 200 OK\r\n"));
+
+        let received = http_roundtrip(
+            Arc::clone(&database),
+            b"GET /GET+fleet+truck1 HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        )
+        .await;
+        assert!(received.starts_with("HTTP/1.1 200 OK\r\n"));
+        let (_, body) = received.split_once("\r\n\r\n").unwrap();
+        let body: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["ok"], true);
+        assert_eq!(body["result"]["coordinates"], json!([116.4, 39.9]));
+
+        let received = http_roundtrip(database, b"GET /NOSUCHCOMMAND HTTP/1.1\r\n\r\n").await;
+        assert!(received.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        assert!(received.contains(r#""ok":false"#));
+    }
+
+    #[tokio::test]
+    async fn test_websocket_commands() {
+        let database = Arc::new(GeoDatabase::new());
+        let (server, mut client) = socket_pair().await;
+        let mut connection = ServerConnection::new(server, database);
+        let handle = tokio::spawn(async move { connection.handle().await });
+
+        client
+            .write_all(
+                b"GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                  Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            )
+            .await
+            .unwrap();
+
+        // 客户端帧必须带掩码；命令分成两片发送，中间夹一个 ping
+        let masked = |first: u8, payload: &[u8]| {
+            let mask = [1u8, 2, 3, 4];
+            let mut frame = vec![first, 0x80 | payload.len() as u8];
+            frame.extend_from_slice(&mask);
+            frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+            frame
+        };
+        let mut frames = masked(websocket::OPCODE_TEXT, br#"["PI"#);
+        frames.extend(masked(0x80 | websocket::OPCODE_PING, b"hi"));
+        frames.extend(masked(0x80 | websocket::OPCODE_CONTINUATION, br#"NG"]"#));
+        frames.extend(masked(
+            0x80 | websocket::OPCODE_CLOSE,
+            &1000u16.to_be_bytes(),
+        ));
+        client.write_all(&frames).await.unwrap();
+
+        let mut received = Vec::new();
+        tokio::time::timeout(Duration::from_secs(5), client.read_to_end(&mut received))
+            .await
+            .unwrap()
+            .unwrap();
+        handle.await.unwrap().unwrap();
+
+        let head_end = received.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        let head = String::from_utf8_lossy(&received[..head_end]);
+        assert!(head.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+        assert!(head.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+
+        let mut expected = websocket::encode_frame(websocket::OPCODE_PONG, b"hi");
+        expected.extend(websocket::encode_frame(
+            websocket::OPCODE_TEXT,
+            br#"{"ok":true,"result":"PONG"}"#,
+        ));
+        expected.extend(websocket::encode_frame(
+            websocket::OPCODE_CLOSE,
+            &1000u16.to_be_bytes(),
+        ));
+        assert_eq!(&received[head_end..], &expected[..]);
+    }
+
+    /// 读取直到收到的内容包含 `expected`
+    async fn read_until(client: &mut TcpStream, expected: &str) -> String {
+        let mut received = Vec::new();
+        let mut buf = [0u8; 512];
+        while !String::from_utf8_lossy(&received).contains(expected) {
+            let n = tokio::time::timeout(Duration::from_secs(5), client.read(&mut buf))
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(n > 0);
+            received.extend_from_slice(&buf[..n]);
+        }
+        String::from_utf8(received).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_fence_pushes_events() {
+        let fences = Arc::new(FenceManager::new());
+        let database = Arc::new(GeoDatabase::new());
+        tokio::spawn(Arc::clone(&fences).run(database.subscribe_events()));
+        let (server, mut client) = socket_pair().await;
+        let mut connection =
+            ServerConnection::new(server, Arc::clone(&database)).with_fences(Arc::clone(&fences));
+        let handle = tokio::spawn(async move { connection.handle().await });
+
+        let command = [
+            "NEARBY", "fleet", "POINT", "116.4", "39.9", "RADIUS", "500", "FENCE",
+        ];
+        let mut frame = format!("*{}\r\n", command.len());
+        for arg in command {
+            frame.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+        }
+        client.write_all(frame.as_bytes()).await.unwrap();
+        assert_eq!(read_until(&mut client, "+OK\r\n").await, "+OK\r\n");
+        assert_eq!(fences.len(), 1);
+
+        // 其他连接的写入推送到注册围栏的连接
+        let point = json!({"type": "Point", "coordinates": [116.401, 39.9]}).to_string();
+        database.set("fleet", "truck1", &point).await.unwrap();
+        let received = read_until(&mut client, "}\r\n").await;
+        let (_, event) = received.trim_end().split_once("\r\n").unwrap();
+        let event: serde_json::Value = serde_json::from_str(event).unwrap();
+        assert_eq!(event["detect"], "enter");
+        assert_eq!(event["id"], "truck1");
+
+        // 围栏状态只允许 PING、QUIT 和继续注册围栏
+        client
+            .write_all(b"*3\r\n$3\r\nGET\r\n$5\r\nfleet\r\n$6\r\ntruck1\r\n")
+            .await
+            .unwrap();
+        let received = read_until(&mut client, "\r\n").await;
+        assert!(received.contains("not allowed while the session is fencing"));
+
+        // 连接关闭时注销围栏
+        drop(client);
+        handle.await.unwrap().unwrap();
+        assert!(fences.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fence_uses_server_distance_metric() {
+        let fences = Arc::new(FenceManager::new());
+        let database = Arc::new(GeoDatabase::new().with_distance_metric(DistanceMetric::Euclidean));
+        let (server, mut client) = socket_pair().await;
+        let mut connection =
+            ServerConnection::new(server, Arc::clone(&database)).with_fences(Arc::clone(&fences));
+        let handle = tokio::spawn(async move { connection.handle().await });
+
+        let send = |command: &[&str]| {
+            let mut frame = format!("*{}\r\n", command.len());
+            for arg in command {
+                frame.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
             }
-            RespValue::BulkString(Some(cmd_name)) => {
-                // 简单命令（如直接输入 PING）
-                self.registry.execute(&cmd_name, &[]).await
+            frame
+        };
+        // 服务器默认按平面距离计算，不带 DISTANCE 的围栏同样被拒绝
+        let command = [
+            "NEARBY", "fleet", "POINT", "116.4", "39.9", "RADIUS", "500", "FENCE",
+        ];
+        client.write_all(send(&command).as_bytes()).await.unwrap();
+        assert_eq!(
+            read_until(&mut client, "\r\n").await,
+            "-ERR FENCE only supports DISTANCE HAVERSINE\r\n"
+        );
+        assert!(fences.is_empty());
+
+        let command = [
+            "NEARBY",
+            "fleet",
+            "POINT",
+            "116.4",
+            "39.9",
+            "RADIUS",
+            "500",
+            "DISTANCE",
+            "haversine",
+            "FENCE",
+        ];
+        client.write_all(send(&command).as_bytes()).await.unwrap();
+        assert_eq!(read_until(&mut client, "+OK\r\n").await, "+OK\r\n");
+        assert_eq!(fences.len(), 1);
+
+        drop(client);
+        handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fence_events_use_push_frames_on_resp3() {
+        let fences = Arc::new(FenceManager::new());
+        let database = Arc::new(GeoDatabase::new());
+        tokio::spawn(Arc::clone(&fences).run(database.subscribe_events()));
+        let (server, mut client) = socket_pair().await;
+        let mut connection =
+            ServerConnection::new(server, Arc::clone(&database)).with_fences(Arc::clone(&fences));
+        tokio::spawn(async move { connection.handle().await });
+
+        client
+            .write_all(b"*2\r\n$5\r\nHELLO\r\n$1\r\n3\r\n")
+            .await
+            .unwrap();
+        let received = read_until(&mut client, "resp3\r\n").await;
+        assert!(received.starts_with("%4\r\n"));
+
+        let command = [
+            "NEARBY", "fleet", "POINT", "116.4", "39.9", "RADIUS", "500", "FENCE",
+        ];
+        let mut frame = format!("*{}\r\n", command.len());
+        for arg in command {
+            frame.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+        }
+        client.write_all(frame.as_bytes()).await.unwrap();
+        assert_eq!(read_until(&mut client, "+OK\r\n").await, "+OK\r\n");
+
+        let point = json!({"type": "Point", "coordinates": [116.401, 39.9]}).to_string();
+        database.set("fleet", "truck1", &point).await.unwrap();
+        let received = read_until(&mut client, "}\r\n").await;
+        let (push, consumed) = RespParser::decode(received.as_bytes()).unwrap().unwrap();
+        assert_eq!(consumed, received.len());
+        let RespValue::Push(items) = push else {
+            panic!("expected a push frame, got {:?}", push);
+        };
+        assert_eq!(items[0].as_str(), Some("fence"));
+        let event: serde_json::Value = serde_json::from_str(items[1].as_str().unwrap()).unwrap();
+        assert_eq!(event["detect"], "enter");
+    }
+
+    #[tokio::test]
+    async fn test_pubsub_and_fence_channels() {
+        let fences = Arc::new(FenceManager::new());
+        let database = Arc::new(GeoDatabase::new());
+        tokio::spawn(Arc::clone(&fences).run(database.subscribe_events()));
+        let connect = || async {
+            let (server, client) = socket_pair().await;
+            let mut connection = ServerConnection::new(server, Arc::clone(&database))
+                .with_fences(Arc::clone(&fences));
+            tokio::spawn(async move { connection.handle().await });
+            client
+        };
+        let command = |args: &[&str]| {
+            let mut frame = format!("*{}\r\n", args.len());
+            for arg in args {
+                frame.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
             }
-            _ => Ok(RespResponse::error("ERR invalid command format")),
+            frame
+        };
+        let mut subscriber = connect().await;
+        let mut publisher = connect().await;
+
+        subscriber
+            .write_all(command(&["SUBSCRIBE", "alerts", "warehouse"]).as_bytes())
+            .await
+            .unwrap();
+        let received = read_until(&mut subscriber, "warehouse\r\n:2\r\n").await;
+        assert!(received.starts_with("*3\r\n$9\r\nsubscribe\r\n$6\r\nalerts\r\n:1\r\n"));
+
+        publisher
+            .write_all(command(&["PUBLISH", "alerts", "hello"]).as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(read_until(&mut publisher, "\r\n").await, ":1\r\n");
+        assert_eq!(
+            read_until(&mut subscriber, "hello\r\n").await,
+            "*3\r\n$7\r\nmessage\r\n$6\r\nalerts\r\n$5\r\nhello\r\n"
+        );
+
+        // 围栏频道的事件发布给订阅者
+        publisher
+            .write_all(
+                command(&[
+                    "SETCHAN",
+                    "warehouse",
+                    "NEARBY",
+                    "fleet",
+                    "POINT",
+                    "116.4",
+                    "39.9",
+                    "RADIUS",
+                    "500",
+                ])
+                .as_bytes(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(read_until(&mut publisher, "\r\n").await, "+OK\r\n");
+        // 定义保存在数据库中，重启后重新注册
+        assert_eq!(
+            database.channel_definitions()[0].1[..2],
+            ["NEARBY".to_string(), "fleet".to_string()]
+        );
+        publisher
+            .write_all(command(&["FENCES", "LIST"]).as_bytes())
+            .await
+            .unwrap();
+        let listed = read_until(&mut publisher, "$3\r\n500\r\n").await;
+        assert!(listed.starts_with("*1\r\n*12\r\n$2\r\nid\r\n"));
+        assert!(listed.contains("$7\r\nchannel\r\n$9\r\nwarehouse\r\n"));
+        assert!(listed.contains("$4\r\narea\r\n$6\r\nnearby\r\n"));
+        publisher
+            .write_all(command(&["FENCES", "CONTAINING", "116.4", "39.9"]).as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(read_until(&mut publisher, ":1\r\n").await, "*1\r\n:1\r\n");
+        publisher
+            .write_all(command(&["FENCES", "CONTAINING", "0", "0"]).as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(read_until(&mut publisher, "\r\n").await, "*0\r\n");
+        let point = json!({"type": "Point", "coordinates": [116.401, 39.9]}).to_string();
+        database.set("fleet", "truck1", &point).await.unwrap();
+        let received = read_until(&mut subscriber, "}\r\n").await;
+        let event: serde_json::Value =
+            serde_json::from_str(received.trim_end().rsplit("\r\n").next().unwrap()).unwrap();
+        assert_eq!(event["channel"], "warehouse");
+        assert_eq!(event["detect"], "enter");
+
+        publisher
+            .write_all(command(&["DELCHAN", "warehouse"]).as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(read_until(&mut publisher, "\r\n").await, ":1\r\n");
+        assert!(fences.is_empty());
+        assert!(database.channel_definitions().is_empty());
+
+        // 订阅状态只允许 (P)SUBSCRIBE 系列、PING 和 QUIT，全部退订后恢复
+        subscriber
+            .write_all(command(&["GET", "fleet", "truck1"]).as_bytes())
+            .await
+            .unwrap();
+        let received = read_until(&mut subscriber, "\r\n").await;
+        assert!(received.contains("not allowed while the session is subscribed"));
+        subscriber
+            .write_all(command(&["UNSUBSCRIBE"]).as_bytes())
+            .await
+            .unwrap();
+        read_until(&mut subscriber, ":0\r\n").await;
+        subscriber
+            .write_all(command(&["PUBLISH", "alerts", "bye"]).as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(read_until(&mut subscriber, "\r\n").await, ":0\r\n");
+
+        // 连接关闭时退订
+        publisher
+            .write_all(command(&["PSUBSCRIBE", "a*"]).as_bytes())
+            .await
+            .unwrap();
+        read_until(&mut publisher, ":1\r\n").await;
+        assert_eq!(fences.pubsub().counts(), (0, 1));
+        drop(publisher);
+        for _ in 0..100 {
+            if fences.pubsub().counts() == (0, 0) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(fences.pubsub().counts(), (0, 0));
+    }
+
+    /// 发送命令并读取回复，JSON 内容的 bulk string 解析为 JSON
+    async fn request(client: &mut TcpStream, command: &[u8], expected: &str) -> serde_json::Value {
+        client.write_all(command).await.unwrap();
+        let received = read_until(client, expected).await;
+        match RespParser::decode(received.as_bytes()).unwrap().unwrap().0 {
+            RespValue::BulkString(Some(bytes)) => serde_json::from_slice(&bytes)
+                .unwrap_or_else(|_| json!(String::from_utf8_lossy(&bytes))),
+            RespValue::SimpleString(s) => json!(s),
+            other => json!(other.as_str()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_output_json() {
+        let database = Arc::new(GeoDatabase::new());
+        let point = json!({"type": "Point", "coordinates": [116.4, 39.9]}).to_string();
+        database.set("fleet", "truck1", &point).await.unwrap();
+        let (server, mut client) = socket_pair().await;
+        let mut connection = ServerConnection::new(server, database);
+        tokio::spawn(async move { connection.handle().await });
+
+        assert_eq!(
+            request(&mut client, b"*1\r\n$6\r\nOUTPUT\r\n", "resp\r\n").await,
+            "resp"
+        );
+        let reply = request(
+            &mut client,
+            b"*2\r\n$6\r\nOUTPUT\r\n$4\r\njson\r\n",
+            "}\r\n",
+        )
+        .await;
+        assert_eq!(reply["ok"], true);
+        assert!(reply["elapsed"].is_string());
+
+        let reply = request(
+            &mut client,
+            b"*3\r\n$3\r\nGET\r\n$5\r\nfleet\r\n$6\r\ntruck1\r\n",
+            "}\r\n",
+        )
+        .await;
+        assert_eq!(reply["object"]["coordinates"], json!([116.4, 39.9]));
+        let reply = request(
+            &mut client,
+            b"*3\r\n$3\r\nGET\r\n$5\r\nfleet\r\n$6\r\ntruck9\r\n",
+            "}\r\n",
+        )
+        .await;
+        assert_eq!(reply["ok"], false);
+        assert_eq!(reply["err"], "id not found");
+        let reply = request(&mut client, b"*1\r\n$6\r\nOUTPUT\r\n", "}\r\n").await;
+        assert_eq!(reply["output"], "json");
+
+        // 切回 RESP 后，OUTPUT 自身的回复已经是 RESP
+        let reply = request(
+            &mut client,
+            b"*2\r\n$6\r\nOUTPUT\r\n$4\r\nresp\r\n",
+            "OK\r\n",
+        )
+        .await;
+        assert_eq!(reply, "OK");
+        assert_eq!(
+            request(&mut client, b"*1\r\n$4\r\nPING\r\n", "PONG\r\n").await,
+            "PONG"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fence_over_http_is_rejected() {
+        let database = Arc::new(GeoDatabase::new());
+        let received = http_roundtrip(
+            database,
+            b"GET /NEARBY+fleet+POINT+116.4+39.9+RADIUS+500+FENCE HTTP/1.1\r\n\r\n",
+        )
+        .await;
+        assert!(received.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        assert!(received.contains("FENCE is only supported on RESP and WebSocket connections"));
+    }
+
+    #[tokio::test]
+    async fn test_quit_closes_connection() {
+        let database = Arc::new(GeoDatabase::new());
+        let (server, mut client) = socket_pair().await;
+        let mut connection = ServerConnection::new(server, database);
+
+        // QUIT 之后的命令不再执行
+        client
+            .write_all(b"*1\r\n$4\r\nQUIT\r\n*1\r\n$4\r\nPING\r\n")
+            .await
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(5), connection.handle())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let mut received = String::new();
+        client.read_to_string(&mut received).await.unwrap();
+        assert_eq!(received, "+Goodbye!\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_non_utf8_argument_keeps_connection() {
+        let database = Arc::new(GeoDatabase::new());
+        let (server, mut client) = socket_pair().await;
+        let mut connection = ServerConnection::new(server, database);
+        let handle = tokio::spawn(async move { connection.handle().await });
+
+        // 非 UTF-8 的参数只让这条命令失败，后续命令照常执行
+        client
+            .write_all(b"*3\r\n$3\r\nGET\r\n$5\r\nfleet\r\n$2\r\n\xff\xfe\r\n*1\r\n$4\r\nPING\r\n")
+            .await
+            .unwrap();
+        let expected = "-ERR invalid item ID: expected UTF-8 string\r\n+PONG\r\n";
+        let mut received = Vec::new();
+        let mut buf = [0u8; 128];
+        while received.len() < expected.len() {
+            let n = tokio::time::timeout(Duration::from_secs(5), client.read(&mut buf))
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(n > 0);
+            received.extend_from_slice(&buf[..n]);
         }
+        assert_eq!(String::from_utf8(received).unwrap(), expected);
+
+        drop(client);
+        handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_auth_and_select() {
+        let database = Arc::new(GeoDatabase::new());
+        let (server, mut client) = socket_pair().await;
+        let mut connection = ServerConnection::new(server, Arc::clone(&database))
+            .with_password(Some(Arc::from("secret")))
+            .with_databases(4);
+        tokio::spawn(async move { connection.handle().await });
+
+        let point = r#"{"type":"Point","coordinates":[1,2]}"#;
+        let set = format!(
+            "*4\r\n$3\r\nSET\r\n$5\r\nfleet\r\n$6\r\ntruck1\r\n${}\r\n{}\r\n",
+            point.len(),
+            point
+        );
+        let replies = [
+            (set.as_str(), "-NOAUTH Authentication required.\r\n"),
+            (
+                "*2\r\n$4\r\nAUTH\r\n$5\r\nwrong\r\n",
+                "-WRONGPASS invalid username-password pair or user is disabled.\r\n",
+            ),
+            (
+                "*3\r\n$4\r\nAUTH\r\n$7\r\ndefault\r\n$6\r\nsecret\r\n",
+                "+OK\r\n",
+            ),
+            (
+                "*2\r\n$6\r\nSELECT\r\n$1\r\n4\r\n",
+                "-ERR DB index is out of range, expected 0 to 3\r\n",
+            ),
+            ("*2\r\n$6\r\nSELECT\r\n$1\r\n3\r\n", "+OK\r\n"),
+            (set.as_str(), "+OK\r\n"),
+            ("*1\r\n$4\r\nKEYS\r\n", "*1\r\n$5\r\nfleet\r\n"),
+            ("*2\r\n$6\r\nSELECT\r\n$1\r\n0\r\n", "+OK\r\n"),
+            ("*1\r\n$4\r\nKEYS\r\n", "*-1\r\n"),
+        ];
+        for (command, expected) in replies {
+            client.write_all(command.as_bytes()).await.unwrap();
+            assert_eq!(read_until(&mut client, expected).await, expected);
+        }
+        assert!(database.get("@3:fleet", "truck1").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_http_bearer_auth() {
+        let database = Arc::new(GeoDatabase::new());
+        let roundtrip = |request: &'static [u8]| {
+            let database = Arc::clone(&database);
+            async move {
+                let (server, mut client) = socket_pair().await;
+                let mut connection = ServerConnection::new(server, database)
+                    .with_password(Some(Arc::from("secret")));
+                tokio::spawn(async move { connection.handle().await });
+                client.write_all(request).await.unwrap();
+                let mut received = String::new();
+                client.read_to_string(&mut received).await.unwrap();
+                received
+            }
+        };
+
+        let received = roundtrip(b"GET /PING HTTP/1.1\r\n\r\n").await;
+        assert!(received.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        assert!(received.contains("NOAUTH Authentication required."));
+        let received =
+            roundtrip(b"GET /PING HTTP/1.1\r\nAuthorization: Bearer wrong\r\n\r\n").await;
+        assert!(received.contains("NOAUTH"));
+        let received =
+            roundtrip(b"GET /PING HTTP/1.1\r\nAuthorization: Bearer secret\r\n\r\n").await;
+        assert!(received.starts_with("HTTP/1.1 200 OK\r\n"), "{}", received);
+    }
+
+    #[tokio::test]
+    async fn test_multi_exec() {
+        let database = Arc::new(GeoDatabase::new());
+        let (server, mut client) = socket_pair().await;
+        let mut connection = ServerConnection::new(server, Arc::clone(&database));
+        tokio::spawn(async move { connection.handle().await });
+
+        let point = r#"{"type":"Point","coordinates":[1,2]}"#;
+        let set = |id: &str| {
+            format!(
+                "*4\r\n$3\r\nSET\r\n$5\r\nfleet\r\n${}\r\n{}\r\n${}\r\n{}\r\n",
+                id.len(),
+                id,
+                point.len(),
+                point
+            )
+        };
+        let replies = [
+            (
+                "*1\r\n$4\r\nEXEC\r\n".to_string(),
+                "-ERR EXEC without MULTI\r\n",
+            ),
+            ("*1\r\n$5\r\nMULTI\r\n".to_string(), "+OK\r\n"),
+            (set("truck1"), "+QUEUED\r\n"),
+            (set("truck2"), "+QUEUED\r\n"),
+            (
+                "*3\r\n$3\r\nTTL\r\n$5\r\nfleet\r\n$6\r\ntruck1\r\n".to_string(),
+                "+QUEUED\r\n",
+            ),
+            (
+                "*1\r\n$4\r\nEXEC\r\n".to_string(),
+                "*3\r\n+OK\r\n+OK\r\n:-1\r\n",
+            ),
+        ];
+        for (command, expected) in replies {
+            client.write_all(command.as_bytes()).await.unwrap();
+            assert_eq!(read_until(&mut client, expected).await, expected);
+        }
+        assert!(database.get("fleet", "truck2").await.unwrap().is_some());
+
+        // 排队失败的事务在 EXEC 时整体放弃，DISCARD 丢弃排队的命令
+        let replies = [
+            ("*1\r\n$5\r\nMULTI\r\n".to_string(), "+OK\r\n"),
+            (set("truck3"), "+QUEUED\r\n"),
+            (
+                "*1\r\n$4\r\nNOPE\r\n".to_string(),
+                "-ERR unknown command 'NOPE'\r\n",
+            ),
+            (
+                "*1\r\n$4\r\nEXEC\r\n".to_string(),
+                "-EXECABORT Transaction discarded because of previous errors.\r\n",
+            ),
+            ("*1\r\n$5\r\nMULTI\r\n".to_string(), "+OK\r\n"),
+            (set("truck4"), "+QUEUED\r\n"),
+            ("*1\r\n$7\r\nDISCARD\r\n".to_string(), "+OK\r\n"),
+            ("*1\r\n$4\r\nPING\r\n".to_string(), "+PONG\r\n"),
+        ];
+        for (command, expected) in replies {
+            client.write_all(command.as_bytes()).await.unwrap();
+            assert_eq!(read_until(&mut client, expected).await, expected);
+        }
+        assert!(database.get("fleet", "truck3").await.unwrap().is_none());
+        assert!(database.get("fleet", "truck4").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_large_reply_is_streamed() {
+        let database = Arc::new(GeoDatabase::new());
+        for i in 0..3000 {
+            let point = format!(
+                r#"{{"type":"Point","coordinates":[{},{}],"properties":{{"name":"truck {}"}}}}"#,
+                i % 100,
+                i / 100,
+                i
+            );
+            database
+                .set("fleet", &format!("truck{}", i), &point)
+                .await
+                .unwrap();
+        }
+        let area =
+            r#"{"type":"Polygon","coordinates":[[[-1,-1],[101,-1],[101,31],[-1,31],[-1,-1]]]}"#;
+        let args = [RespValue::bulk("fleet"), RespValue::bulk(area)];
+        let expected = CommandRegistry::new(Arc::clone(&database))
+            .execute("INTERSECTS", &args)
+            .await
+            .unwrap();
+        assert!(expected.len() > 2 * crate::protocol::stream::STREAM_CHUNK_BYTES);
+
+        let (server, mut client) = socket_pair().await;
+        let mut connection = ServerConnection::new(server, database);
+        tokio::spawn(async move { connection.handle().await });
+
+        // 分块写出的回复与完整回复相同，之后的命令仍然按顺序回复
+        let command = format!(
+            "*3\r\n$10\r\nINTERSECTS\r\n$5\r\nfleet\r\n${}\r\n{}\r\n*1\r\n$4\r\nPING\r\n",
+            area.len(),
+            area
+        );
+        client.write_all(command.as_bytes()).await.unwrap();
+        let expected = format!("{}+PONG\r\n", expected);
+        assert_eq!(read_until(&mut client, &expected).await, expected);
     }
 }
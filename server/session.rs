@@ -0,0 +1,417 @@
+//! 连接会话的状态机
+//!
+//! 每个连接（RESP 或 WebSocket）持有一个 [`Session`]。命令执行前由 [`Session::admit`] 统一检查
+//! 当前状态是否允许该命令，并确定命令成功后的状态变化（[`Transition`]）；执行后由
+//! [`Session::complete`] 根据回复应用这一变化，连接的读写循环不再各自判断命令名。
+//!
+//! 目前的状态有未认证、握手、普通、事务、围栏、订阅和关闭中七种。MONITOR 加入时成为新的状态，
+//! 并在 [`SessionState::allows`] 中声明允许的命令，例如订阅状态只允许 (P)SUBSCRIBE 系列、PING 和 QUIT
+
+use crate::protocol::parser::RespValue;
+
+/// 订阅和退订 pub/sub 频道的命令
+const SUBSCRIPTION_COMMANDS: [&str; 4] = ["SUBSCRIBE", "PSUBSCRIBE", "UNSUBSCRIBE", "PUNSUBSCRIBE"];
+
+/// 由连接直接处理、不能在事务中排队的命令（订阅命令之外）
+const CONNECTION_COMMANDS: [&str; 9] = [
+    "MULTI", "HELLO", "AUTH", "SELECT", "OUTPUT", "PUBLISH", "SETCHAN", "DELCHAN", "FENCES",
+];
+
+/// 会话所处的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionState {
+    /// 服务器配置了 `requirepass`，还没有认证；只允许 AUTH、HELLO 和 QUIT
+    Unauthenticated,
+    /// 还没有执行过命令；客户端可以先用 HELLO 协商协议，也可以直接发送命令
+    Handshaking,
+    /// 可以执行所有命令
+    Normal,
+    /// MULTI 之后，命令排队到 EXEC 时一起执行，DISCARD 放弃；连接级的命令、订阅和围栏不能排队
+    Transaction,
+    /// 注册了地理围栏，连接用于接收围栏事件；只允许 PING、QUIT 和继续注册围栏
+    Fencing,
+    /// 订阅了 pub/sub 频道或模式，连接用于接收消息；只允许 (P)SUBSCRIBE 系列、PING 和 QUIT
+    Subscribed,
+    /// 已经回复 QUIT，连接在写完回复后关闭，不再执行命令
+    Closing,
+}
+
+impl SessionState {
+    /// 当前状态是否允许执行指定的命令（命令名已转为大写）
+    pub fn allows(self, command: &str) -> bool {
+        match (self, command) {
+            (Self::Handshaking | Self::Normal, "EXEC" | "DISCARD") => false,
+            (Self::Handshaking | Self::Normal, _) => true,
+            (Self::Transaction, cmd) => {
+                !SUBSCRIPTION_COMMANDS.contains(&cmd) && !CONNECTION_COMMANDS.contains(&cmd)
+            }
+            (Self::Unauthenticated, "AUTH" | "HELLO" | "QUIT") => true,
+            (Self::Fencing | Self::Subscribed, "PING" | "QUIT") => true,
+            (Self::Subscribed, cmd) => SUBSCRIPTION_COMMANDS.contains(&cmd),
+            (Self::Unauthenticated | Self::Fencing | Self::Closing, _) => false,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Self::Unauthenticated => "unauthenticated",
+            Self::Handshaking => "handshaking",
+            Self::Normal => "active",
+            Self::Transaction => "in a transaction",
+            Self::Fencing => "fencing",
+            Self::Subscribed => "subscribed",
+            Self::Closing => "closing",
+        }
+    }
+}
+
+/// 命令对会话状态的影响
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    /// 普通命令：握手阶段结束
+    Command,
+    /// HELLO 握手，带客户端请求的协议版本
+    Hello(Option<u8>),
+    /// QUIT：无论回复如何都进入关闭状态
+    Quit,
+    /// AUTH：成功后可以执行所有命令
+    Auth,
+    /// MULTI：成功后开始排队
+    Multi,
+    /// 事务中的命令：排队而不是执行
+    Queue,
+    /// EXEC 或 DISCARD：无论回复如何都结束事务
+    EndTransaction,
+    /// 带 FENCE 的 NEARBY/INTERSECTS：成功后进入围栏状态
+    Fence,
+    /// (P)SUBSCRIBE 系列：回复中最后的订阅数决定进入还是离开订阅状态
+    Subscription,
+}
+
+#[derive(Debug, Clone)]
+pub struct Session {
+    state: SessionState,
+    /// HELLO 协商的协议版本，没有握手时为 None（按 RESP2 处理）
+    protocol_version: Option<u8>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self {
+            state: SessionState::Handshaking,
+            protocol_version: None,
+        }
+    }
+
+    /// 需要先用 AUTH 认证的会话
+    pub fn unauthenticated() -> Self {
+        Self {
+            state: SessionState::Unauthenticated,
+            protocol_version: None,
+        }
+    }
+
+    /// 通过命令以外的方式（例如 HTTP 的 `Authorization` 头）完成认证
+    pub fn authenticate(&mut self) {
+        if self.state == SessionState::Unauthenticated {
+            self.state = SessionState::Handshaking;
+        }
+    }
+
+    pub fn state(&self) -> SessionState {
+        self.state
+    }
+
+    pub fn protocol_version(&self) -> Option<u8> {
+        self.protocol_version
+    }
+
+    /// 连接是否应该在写完当前回复后关闭
+    pub fn is_closing(&self) -> bool {
+        self.state == SessionState::Closing
+    }
+
+    /// 检查当前状态是否允许执行命令，返回命令成功后要应用的状态变化；
+    /// 不允许时返回错误回复的内容
+    pub fn admit(&self, command: &str, args: &[RespValue]) -> Result<Transition, String> {
+        let name = command.to_uppercase();
+        let fence = matches!(name.as_str(), "NEARBY" | "INTERSECTS")
+            && args.iter().any(|arg| {
+                arg.as_str()
+                    .is_some_and(|s| s.eq_ignore_ascii_case("FENCE"))
+            });
+        let allowed = match self.state {
+            SessionState::Fencing => fence || self.state.allows(&name),
+            SessionState::Transaction => !fence && self.state.allows(&name),
+            state => state.allows(&name),
+        };
+        if !allowed && self.state == SessionState::Unauthenticated {
+            return Err("NOAUTH Authentication required.".to_string());
+        }
+        if !allowed && matches!(self.state, SessionState::Handshaking | SessionState::Normal) {
+            return Err(format!("ERR {} without MULTI", name));
+        }
+        if !allowed {
+            return Err(format!(
+                "ERR command '{}' is not allowed while the session is {}",
+                command,
+                self.state.describe()
+            ));
+        }
+        Ok(match (name.as_str(), args) {
+            ("QUIT", _) => Transition::Quit,
+            ("EXEC" | "DISCARD", _) => Transition::EndTransaction,
+            _ if self.state == SessionState::Transaction => Transition::Queue,
+            ("MULTI", _) => Transition::Multi,
+            ("AUTH", _) => Transition::Auth,
+            _ if fence => Transition::Fence,
+            (cmd, _) if SUBSCRIPTION_COMMANDS.contains(&cmd) => Transition::Subscription,
+            ("HELLO", [version]) => {
+                Transition::Hello(version.as_str().and_then(|v| v.parse().ok()))
+            }
+            _ => Transition::Command,
+        })
+    }
+
+    /// 命令执行完毕后应用状态变化；错误回复不改变状态（QUIT 除外）
+    pub fn complete(&mut self, transition: Transition, reply: &str) {
+        if transition == Transition::Quit {
+            self.state = SessionState::Closing;
+            return;
+        }
+        if transition == Transition::EndTransaction {
+            self.state = SessionState::Normal;
+            return;
+        }
+        if reply.starts_with('-') {
+            return;
+        }
+        if let Transition::Hello(Some(version)) = transition {
+            self.protocol_version = Some(version);
+        }
+        if transition == Transition::Queue {
+            return;
+        }
+        if transition == Transition::Auth {
+            self.state = SessionState::Normal;
+        } else if transition == Transition::Multi {
+            self.state = SessionState::Transaction;
+        } else if transition == Transition::Fence {
+            self.state = SessionState::Fencing;
+        } else if transition == Transition::Subscription {
+            // 每个频道的确认以剩余的订阅数结尾，最后一个确认之后的订阅数为 0 时离开订阅状态
+            self.state = match remaining_subscriptions(reply) {
+                Some(0) => SessionState::Normal,
+                _ => SessionState::Subscribed,
+            };
+        } else if self.state == SessionState::Handshaking {
+            self.state = SessionState::Normal;
+        }
+    }
+}
+
+/// 回复末尾的整数，即 (P)SUBSCRIBE 系列最后一个确认中的订阅数
+fn remaining_subscriptions(reply: &str) -> Option<i64> {
+    reply
+        .trim_end()
+        .rsplit("\r\n")
+        .next()?
+        .strip_prefix(':')?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bulk(s: &str) -> RespValue {
+        RespValue::bulk(s.to_string())
+    }
+
+    #[test]
+    fn test_session_transitions() {
+        let mut session = Session::new();
+        assert_eq!(session.state(), SessionState::Handshaking);
+
+        // 协商失败时仍在握手状态
+        let transition = session.admit("hello", &[bulk("4")]).unwrap();
+        assert_eq!(transition, Transition::Hello(Some(4)));
+        session.complete(transition, "-NOPROTO unsupported\r\n");
+        assert_eq!(session.state(), SessionState::Handshaking);
+        assert_eq!(session.protocol_version(), None);
+
+        let transition = session.admit("HELLO", &[bulk("2")]).unwrap();
+        session.complete(transition, "*8\r\n");
+        assert_eq!(session.state(), SessionState::Normal);
+        assert_eq!(session.protocol_version(), Some(2));
+
+        let transition = session.admit("SET", &[bulk("fleet")]).unwrap();
+        assert_eq!(transition, Transition::Command);
+        session.complete(transition, "+OK\r\n");
+        assert_eq!(session.state(), SessionState::Normal);
+        assert!(!session.is_closing());
+    }
+
+    #[test]
+    fn test_commands_without_handshake() {
+        let mut session = Session::new();
+        // 不带版本的 HELLO 只是问候
+        assert_eq!(session.admit("HELLO", &[]), Ok(Transition::Command));
+
+        let transition = session.admit("GET", &[]).unwrap();
+        session.complete(transition, "$-1\r\n");
+        assert_eq!(session.state(), SessionState::Normal);
+        assert_eq!(session.protocol_version(), None);
+    }
+
+    #[test]
+    fn test_fencing_session() {
+        let mut session = Session::new();
+        let args = [
+            bulk("fleet"),
+            bulk("POINT"),
+            bulk("1"),
+            bulk("2"),
+            bulk("fence"),
+        ];
+        let transition = session.admit("nearby", &args).unwrap();
+        assert_eq!(transition, Transition::Fence);
+
+        // 注册失败时状态不变
+        session.complete(transition, "-ERR FENCE requires RADIUS\r\n");
+        assert_eq!(session.state(), SessionState::Handshaking);
+        session.complete(transition, "+OK\r\n");
+        assert_eq!(session.state(), SessionState::Fencing);
+
+        assert_eq!(session.admit("PING", &[]), Ok(Transition::Command));
+        assert_eq!(session.admit("INTERSECTS", &args), Ok(Transition::Fence));
+        let err = session.admit("GET", &[bulk("fleet")]).unwrap_err();
+        assert_eq!(
+            err,
+            "ERR command 'GET' is not allowed while the session is fencing"
+        );
+        let transition = session.admit("QUIT", &[]).unwrap();
+        session.complete(transition, "+Goodbye!\r\n");
+        assert!(session.is_closing());
+    }
+
+    #[test]
+    fn test_subscribed_session() {
+        let mut session = Session::new();
+        let transition = session.admit("subscribe", &[bulk("alerts")]).unwrap();
+        assert_eq!(transition, Transition::Subscription);
+        session.complete(
+            transition,
+            "*3\r\n$9\r\nsubscribe\r\n$6\r\nalerts\r\n:1\r\n",
+        );
+        assert_eq!(session.state(), SessionState::Subscribed);
+
+        assert_eq!(session.admit("PING", &[]), Ok(Transition::Command));
+        assert!(session.admit("PSUBSCRIBE", &[bulk("a*")]).is_ok());
+        let err = session.admit("PUBLISH", &[bulk("alerts")]).unwrap_err();
+        assert_eq!(
+            err,
+            "ERR command 'PUBLISH' is not allowed while the session is subscribed"
+        );
+
+        // 退订后还有订阅时仍在订阅状态，全部退订后回到普通状态
+        let transition = session.admit("UNSUBSCRIBE", &[]).unwrap();
+        session.complete(
+            transition,
+            "*3\r\n$11\r\nunsubscribe\r\n$6\r\nalerts\r\n:1\r\n",
+        );
+        assert_eq!(session.state(), SessionState::Subscribed);
+        session.complete(
+            transition,
+            "*3\r\n$12\r\npunsubscribe\r\n$2\r\na*\r\n:0\r\n",
+        );
+        assert_eq!(session.state(), SessionState::Normal);
+    }
+
+    #[test]
+    fn test_quit_closes_session() {
+        let mut session = Session::new();
+        let transition = session.admit("quit", &[]).unwrap();
+        session.complete(transition, "+Goodbye!\r\n");
+        assert!(session.is_closing());
+
+        let err = session.admit("PING", &[]).unwrap_err();
+        assert!(err.starts_with("ERR command 'PING' is not allowed"));
+    }
+
+    #[test]
+    fn test_unauthenticated_session() {
+        let mut session = Session::unauthenticated();
+        assert_eq!(
+            session.admit("GET", &[bulk("fleet")]).unwrap_err(),
+            "NOAUTH Authentication required."
+        );
+        assert_eq!(
+            session.admit("PING", &[]).unwrap_err(),
+            "NOAUTH Authentication required."
+        );
+
+        // 认证前可以先协商协议，协商不会结束认证
+        let transition = session.admit("HELLO", &[bulk("3")]).unwrap();
+        session.complete(transition, "%7\r\n");
+        assert_eq!(session.state(), SessionState::Unauthenticated);
+        assert_eq!(session.protocol_version(), Some(3));
+
+        // 密码错误时仍未认证
+        let transition = session.admit("auth", &[bulk("wrong")]).unwrap();
+        assert_eq!(transition, Transition::Auth);
+        session.complete(transition, "-WRONGPASS invalid password\r\n");
+        assert_eq!(session.state(), SessionState::Unauthenticated);
+
+        session.complete(transition, "+OK\r\n");
+        assert_eq!(session.state(), SessionState::Normal);
+        assert_eq!(session.admit("GET", &[]), Ok(Transition::Command));
+
+        let mut session = Session::unauthenticated();
+        session.authenticate();
+        assert_eq!(session.state(), SessionState::Handshaking);
+    }
+
+    #[test]
+    fn test_transaction_session() {
+        let mut session = Session::new();
+        assert_eq!(
+            session.admit("exec", &[]).unwrap_err(),
+            "ERR EXEC without MULTI"
+        );
+
+        let transition = session.admit("MULTI", &[]).unwrap();
+        assert_eq!(transition, Transition::Multi);
+        session.complete(transition, "+OK\r\n");
+        assert_eq!(session.state(), SessionState::Transaction);
+
+        // 排队的命令和排队失败都不改变状态
+        let transition = session.admit("SET", &[bulk("fleet")]).unwrap();
+        assert_eq!(transition, Transition::Queue);
+        session.complete(transition, "+QUEUED\r\n");
+        session.complete(transition, "-ERR unknown command 'NOPE'\r\n");
+        assert_eq!(session.state(), SessionState::Transaction);
+
+        let err = session.admit("MULTI", &[]).unwrap_err();
+        assert_eq!(
+            err,
+            "ERR command 'MULTI' is not allowed while the session is in a transaction"
+        );
+        assert!(session.admit("SUBSCRIBE", &[bulk("alerts")]).is_err());
+        let fence = [bulk("fleet"), bulk("FENCE")];
+        assert!(session.admit("INTERSECTS", &fence).is_err());
+
+        // EXEC 失败（例如 EXECABORT）也结束事务
+        let transition = session.admit("EXEC", &[]).unwrap();
+        assert_eq!(transition, Transition::EndTransaction);
+        session.complete(transition, "-EXECABORT Transaction discarded\r\n");
+        assert_eq!(session.state(), SessionState::Normal);
+    }
+}
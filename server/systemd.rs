@@ -0,0 +1,143 @@
+//! systemd 集成：sd_notify 协议（`Type=notify`）和 PID 文件
+//!
+//! 不依赖 libsystemd：通知就是向 `NOTIFY_SOCKET` 指向的 Unix datagram socket 发送
+//! `KEY=VALUE` 文本，未在 systemd 下运行时所有通知都是空操作
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// 向 systemd 发送状态通知（例如 `READY=1`、`STATUS=...`）
+///
+/// 返回是否实际发送；没有设置 `NOTIFY_SOCKET` 时返回 `Ok(false)`
+pub fn notify(state: &str) -> io::Result<bool> {
+    match std::env::var_os("NOTIFY_SOCKET") {
+        Some(socket) if !socket.is_empty() => {
+            notify_to(&socket.to_string_lossy(), state)?;
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// 通知 systemd 服务已就绪（恢复完成、开始监听）
+pub fn notify_ready(status: &str) -> io::Result<bool> {
+    notify(&format!("READY=1\nSTATUS={}", status))
+}
+
+/// 更新 `systemctl status` 中显示的状态文本
+pub fn notify_status(status: &str) -> io::Result<bool> {
+    notify(&format!("STATUS={}", status))
+}
+
+/// 向指定的 socket 发送通知，`@` 开头表示 Linux 抽象命名空间
+#[cfg(unix)]
+fn notify_to(socket: &str, state: &str) -> io::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+
+    let datagram = UnixDatagram::unbound()?;
+    match socket.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            datagram.send_to_addr(state.as_bytes(), &addr)?;
+        }
+        #[cfg(not(target_os = "linux"))]
+        Some(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "abstract notify sockets are only supported on Linux",
+            ))
+        }
+        None => {
+            datagram.send_to(state.as_bytes(), socket)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn notify_to(_socket: &str, _state: &str) -> io::Result<()> {
+    Ok(())
+}
+
+/// PID 文件：创建时写入当前进程 PID，drop 时删除
+///
+/// 先写临时文件再 rename，systemd 的 `PIDFile=` 不会读到写了一半的内容。
+/// 进程被强制结束时文件会残留，内容是已退出进程的 PID，下次启动直接覆盖
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+        let temp = PathBuf::from(temp);
+        std::fs::write(&temp, format!("{}\n", std::process::id()))?;
+        std::fs::rename(&temp, path)?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_notify_to_socket() {
+        use std::os::unix::net::UnixDatagram;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let socket_path = temp_dir.path().join("notify.sock");
+        let listener = UnixDatagram::bind(&socket_path).unwrap();
+
+        notify_to(socket_path.to_str().unwrap(), "READY=1\nSTATUS=ok").unwrap();
+
+        let mut buf = [0u8; 64];
+        let n = listener.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"READY=1\nSTATUS=ok");
+
+        // socket 不存在时返回错误而不是 panic
+        assert!(notify_to(temp_dir.path().join("missing").to_str().unwrap(), "READY=1").is_err());
+    }
+
+    #[test]
+    fn test_pid_file_lifecycle() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("run").join("spatio.pid");
+
+        // 残留的旧文件被覆盖
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "999999\n").unwrap();
+
+        let pid_file = PidFile::create(&path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+        assert_eq!(pid_file.path(), path);
+
+        drop(pid_file);
+        assert!(!path.exists());
+    }
+}
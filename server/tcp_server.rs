@@ -1,42 +1,112 @@
+use std::future::Future;
 use std::sync::Arc;
-use tokio::net::{TcpListener, TcpStream};
-use tracing::{error, info};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tracing::{error, info, warn};
 
-use crate::server::ServerConnection;
+use crate::protocol::parser::RespValue;
+use crate::server::fence::ChannelSpec;
+use crate::server::{FenceManager, ServerConnection};
 use crate::storage::GeoDatabase;
 use crate::{Result, SpatioConfig};
 
+/// 主动过期的检查间隔
+const ACTIVE_EXPIRE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 每轮主动过期最多删除的对象数，过期对象很多时分摊到多轮，避免长时间占用写锁
+const ACTIVE_EXPIRE_BATCH: usize = 1000;
+
 pub struct TcpServer {
     config: SpatioConfig,
     database: Arc<GeoDatabase>,
+    fences: Arc<FenceManager>,
 }
 
 impl TcpServer {
     pub fn new(config: SpatioConfig, database: GeoDatabase) -> Self {
+        let database = Arc::new(database);
         Self {
             config,
-            database: Arc::new(database),
+            fences: Arc::new(FenceManager::new().with_stats(Arc::clone(&database))),
+            database,
         }
     }
 
+    pub fn database(&self) -> &Arc<GeoDatabase> {
+        &self.database
+    }
+
     pub async fn start(&self) -> Result<()> {
+        self.start_loading(async { Ok(()) }).await
+    }
+
+    /// 先监听端口，再执行启动加载 `load`（加载快照、重放 AOF），完成后开始正常服务
+    ///
+    /// 加载期间已经接受连接，读写数据的命令返回 `LOADING`（见 [`GeoDatabase::begin_loading`]），
+    /// 客户端可以退避重试而不是连接失败；加载失败时返回错误
+    pub async fn start_loading(&self, load: impl Future<Output = Result<()>>) -> Result<()> {
         let addr = format!("{}:{}", self.config.server.host, self.config.server.port);
         let listener = TcpListener::bind(&addr).await?;
 
-        info!("Spatio server listening on {}", addr);
+        info!(
+            "Spatio server listening on {} (RESP, HTTP, WebSocket)",
+            addr
+        );
+
+        let password: Option<Arc<str>> = self.config.server.requirepass.as_deref().map(Arc::from);
+        let accept = self.accept_loop(listener, password.clone());
+        tokio::pin!(accept);
+        self.database.begin_loading();
+        tokio::select! {
+            result = &mut accept => return result,
+            result = load => result?,
+        }
+        self.database.finish_loading();
         info!("Ready to accept connections");
 
+        // 恢复已经完成且端口已经绑定，此时 systemd (Type=notify) 才认为服务启动成功
+        if let Err(e) = crate::server::systemd::notify_ready(&format!("Listening on {}", addr)) {
+            warn!("Failed to notify systemd: {}", e);
+        }
+
+        self.rearm_channels();
+        tokio::spawn(Arc::clone(&self.fences).run(self.database.subscribe_events()));
+        self.spawn_active_expire();
+        if self.config.storage.unload_idle_minutes > 0 {
+            self.spawn_idle_unloader();
+        }
+        if self.config.storage.min_free_disk_mb > 0 {
+            self.spawn_disk_monitor();
+        }
+        if self.config.snapshot.interval > 0 {
+            self.spawn_snapshotter();
+        }
+        if self.config.aof.enabled && self.config.aof.auto_rewrite_enabled {
+            self.spawn_aof_rewriter();
+        }
+
+        #[cfg(feature = "grpc")]
+        if let Some(port) = self.config.server.grpc_port {
+            self.spawn_grpc(port, password).await?;
+        }
+        accept.await
+    }
+
+    async fn accept_loop(&self, listener: TcpListener, password: Option<Arc<str>>) -> Result<()> {
         loop {
             match listener.accept().await {
                 Ok((stream, addr)) => {
                     info!("Accepted connection from {}", addr);
 
                     // 克隆数据库引用以便在异步任务中使用
-                    let database = Arc::clone(&self.database);
+                    let connection = ServerConnection::new(stream, Arc::clone(&self.database))
+                        .with_fences(Arc::clone(&self.fences))
+                        .with_password(password.clone())
+                        .with_databases(self.config.server.databases);
 
                     // 为每个连接创建一个异步任务
                     tokio::spawn(async move {
-                        if let Err(e) = Self::handle_client(stream, database).await {
+                        if let Err(e) = Self::handle_client(connection).await {
                             error!("Error handling client {}: {}", addr, e);
                         }
                     });
@@ -48,8 +118,141 @@ impl TcpServer {
         }
     }
 
-    async fn handle_client(stream: TcpStream, database: Arc<GeoDatabase>) -> Result<()> {
-        let mut connection = ServerConnection::new(stream, database);
+    /// 在 host:grpc_port 上提供 gRPC 服务，端口绑定失败时启动失败
+    #[cfg(feature = "grpc")]
+    async fn spawn_grpc(&self, port: u16, password: Option<Arc<str>>) -> Result<()> {
+        let addr = format!("{}:{}", self.config.server.host, port);
+        let listener = TcpListener::bind(&addr).await?;
+        info!("gRPC listening on {}", addr);
+
+        let service = crate::server::grpc::GrpcService::new(Arc::clone(&self.database));
+        tokio::spawn(async move {
+            if let Err(e) = service.serve(listener, password).await {
+                error!("gRPC server stopped: {}", e);
+            }
+        });
+        Ok(())
+    }
+
+    /// 重新注册从 AOF 和快照恢复的围栏频道
+    fn rearm_channels(&self) {
+        for (name, args) in self.database.channel_definitions() {
+            let values: Vec<RespValue> = std::iter::once(name.as_str())
+                .chain(args.iter().map(String::as_str))
+                .map(RespValue::bulk)
+                .collect();
+            match ChannelSpec::parse(&values, self.database.distance_metric()) {
+                Ok(spec) => {
+                    self.fences.set_channel(spec);
+                }
+                Err(e) => warn!("Failed to restore fence channel {}: {}", name, e),
+            }
+        }
+        if !self.fences.is_empty() {
+            info!("Restored {} fence channels", self.fences.len());
+        }
+    }
+
+    /// 定期删除已经过期的对象（主动过期），DEBUG SET-ACTIVE-EXPIRE 0 时每轮什么都不做
+    fn spawn_active_expire(&self) {
+        let database = Arc::clone(&self.database);
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(ACTIVE_EXPIRE_INTERVAL);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = database.expire_due(ACTIVE_EXPIRE_BATCH).await {
+                    error!("Failed to remove expired objects: {}", e);
+                }
+            }
+        });
+    }
+
+    /// 定期卸载空闲的 collection
+    fn spawn_idle_unloader(&self) {
+        let database = Arc::clone(&self.database);
+        let idle = Duration::from_secs(self.config.storage.unload_idle_minutes * 60);
+        // 检查间隔不超过一分钟，避免空闲时间被过度放大
+        let period = idle.min(Duration::from_secs(60));
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(period);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(e) = database.unload_idle_collections().await {
+                    error!("Failed to unload idle collections: {}", e);
+                }
+            }
+        });
+    }
+
+    /// 定期检查可用磁盘空间
+    fn spawn_disk_monitor(&self) {
+        let database = Arc::clone(&self.database);
+
+        tokio::spawn(async move {
+            let mut ticker =
+                tokio::time::interval(crate::storage::disk::DEFAULT_DISK_CHECK_INTERVAL);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let db = Arc::clone(&database);
+                // statvfs 是阻塞调用，网络文件系统上可能较慢
+                match tokio::task::spawn_blocking(move || db.refresh_disk_space()).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => error!("Failed to check free disk space: {}", e),
+                    Err(e) => error!("Disk space check task failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// 定期保存快照，上一次保存（包括 SAVE/BGSAVE）未完成时跳过本轮
+    fn spawn_snapshotter(&self) {
+        let database = Arc::clone(&self.database);
+        let period = Duration::from_secs(self.config.snapshot.interval);
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(period);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if database.persistence_info().await.snapshot_in_progress {
+                    continue;
+                }
+                match database.save_snapshot().await {
+                    Ok(summary) => info!(
+                        "Saved snapshot: {} collections, {} objects",
+                        summary.collections, summary.objects
+                    ),
+                    Err(e) => error!("Failed to save snapshot: {}", e),
+                }
+            }
+        });
+    }
+
+    /// 每秒检查一次 AOF 大小，达到自动重写阈值时在后台重写
+    fn spawn_aof_rewriter(&self) {
+        let database = Arc::clone(&self.database);
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(1));
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if !database.aof_rewrite_due().await {
+                    continue;
+                }
+                info!("AOF reached the auto rewrite threshold, rewriting in the background");
+                if let Err(e) = database.background_rewrite_aof() {
+                    error!("Failed to start AOF rewrite: {}", e);
+                }
+            }
+        });
+    }
+
+    async fn handle_client(mut connection: ServerConnection) -> Result<()> {
         connection.handle().await
     }
 }
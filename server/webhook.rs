@@ -0,0 +1,262 @@
+//! 围栏频道的 HTTP webhook
+//!
+//! `SETCHAN name WEBHOOK http://host:port/path ...` 为频道配置 webhook 后，频道的每条围栏事件除了发布给
+//! 订阅者，还以 `POST` 请求（`Content-Type: application/json`，请求体就是事件 JSON）发送到该地址。
+//!
+//! 每个频道有一个后台任务按顺序发送，同一频道的事件不会乱序。返回 2xx 之外的状态码或连接失败时
+//! 按指数退避重试，重试用尽后丢弃该事件并记录日志。只支持 `http://`；频道被删除或替换时任务在发完
+//! 已排队的事件后退出
+
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+/// 单次请求（连接、发送和读取状态行）的超时
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 每条事件最多发送的次数
+const MAX_ATTEMPTS: u32 = 3;
+
+/// 第一次重试前的等待时间，之后每次加倍
+const RETRY_BACKOFF: Duration = Duration::from_millis(200);
+
+/// 解析后的 webhook 地址
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookUrl {
+    pub host: String,
+    pub port: u16,
+    /// 以 `/` 开头的路径，包括查询串
+    pub path: String,
+}
+
+impl WebhookUrl {
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let invalid = || format!("ERR invalid webhook URL '{}'", url);
+        let Some(rest) = strip_prefix_ignore_case(url, "http://") else {
+            return Err(format!(
+                "ERR only http:// webhook endpoints are supported, got '{}'",
+                url
+            ));
+        };
+        let (authority, path) = match rest.find(['/', '?']) {
+            Some(i) if rest.as_bytes()[i] == b'/' => (&rest[..i], rest[i..].to_string()),
+            Some(i) => (&rest[..i], format!("/{}", &rest[i..])),
+            None => (rest, "/".to_string()),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            // IPv6 地址写在方括号中，其中的冒号不是端口分隔符
+            Some((host, port)) if !port.contains(']') => {
+                (host, port.parse().map_err(|_| invalid())?)
+            }
+            _ => (authority, 80),
+        };
+        if host.is_empty() || host.contains('@') {
+            return Err(invalid());
+        }
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            path,
+        })
+    }
+
+    /// 请求中 `Host` 头的值
+    fn host_header(&self) -> String {
+        if self.port == 80 {
+            self.host.clone()
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+}
+
+impl std::fmt::Display for WebhookUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "http://{}{}", self.host_header(), self.path)
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = s.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &s[prefix.len()..])
+}
+
+/// 一个频道的 webhook：事件进入队列，由后台任务依次发送
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    url: WebhookUrl,
+    sender: mpsc::UnboundedSender<String>,
+}
+
+impl Webhook {
+    /// 启动发送任务，所有克隆都被丢弃后任务退出；必须在 tokio 运行时中调用
+    pub fn spawn(url: WebhookUrl) -> Self {
+        let (sender, mut events) = mpsc::unbounded_channel::<String>();
+        let target = url.clone();
+        tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                deliver(&target, &event).await;
+            }
+        });
+        Self { url, sender }
+    }
+
+    pub fn url(&self) -> &WebhookUrl {
+        &self.url
+    }
+
+    /// 把事件放入发送队列
+    pub fn send(&self, event: String) {
+        // 任务只会在发送端全部丢弃后退出，这里不会失败
+        let _ = self.sender.send(event);
+    }
+}
+
+/// 发送一条事件，失败时按退避重试
+async fn deliver(url: &WebhookUrl, event: &str) {
+    let mut backoff = RETRY_BACKOFF;
+    for attempt in 1..=MAX_ATTEMPTS {
+        let result = tokio::time::timeout(REQUEST_TIMEOUT, post(url, event))
+            .await
+            .unwrap_or_else(|_| Err("request timed out".to_string()));
+        match result {
+            Ok(()) => return,
+            Err(e) if attempt == MAX_ATTEMPTS => {
+                warn!(
+                    "Dropping fence event for webhook {} after {} attempts: {}",
+                    url, MAX_ATTEMPTS, e
+                );
+            }
+            Err(e) => {
+                warn!("Webhook {} failed (attempt {}): {}", url, attempt, e);
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+        }
+    }
+}
+
+/// 发送一次 POST 请求，状态码不是 2xx 时返回错误
+pub async fn post(url: &WebhookUrl, body: &str) -> Result<(), String> {
+    let mut stream = TcpStream::connect((url.host.trim_matches(['[', ']']), url.port))
+        .await
+        .map_err(|e| format!("failed to connect: {}", e))?;
+    let request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        url.path,
+        url.host_header(),
+        body.len(),
+        body
+    );
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .map_err(|e| format!("failed to send request: {}", e))?;
+
+    // 只需要状态行，读到第一个换行为止
+    let mut head = Vec::new();
+    let mut buf = [0u8; 512];
+    while !head.contains(&b'\n') {
+        let n = stream
+            .read(&mut buf)
+            .await
+            .map_err(|e| format!("failed to read response: {}", e))?;
+        if n == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+    let status_line = String::from_utf8_lossy(&head);
+    let status = status_line
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| "malformed response".to_string())?;
+    if (200..300).contains(&status) {
+        Ok(())
+    } else {
+        Err(format!("endpoint returned status {}", status))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_parse_url() {
+        assert_eq!(
+            WebhookUrl::parse("http://example.com:8080/hooks/fence?x=1").unwrap(),
+            WebhookUrl {
+                host: "example.com".to_string(),
+                port: 8080,
+                path: "/hooks/fence?x=1".to_string(),
+            }
+        );
+        let url = WebhookUrl::parse("HTTP://localhost").unwrap();
+        assert_eq!((url.port, url.path.as_str()), (80, "/"));
+        assert_eq!(url.to_string(), "http://localhost/");
+        assert_eq!(WebhookUrl::parse("http://[::1]:9000/").unwrap().port, 9000);
+
+        assert!(WebhookUrl::parse("https://example.com/")
+            .unwrap_err()
+            .contains("only http://"));
+        assert!(WebhookUrl::parse("http://:80/").is_err());
+        assert!(WebhookUrl::parse("http://host:port/").is_err());
+    }
+
+    /// 接受一个连接，读完请求后回复指定的状态码，返回收到的请求
+    async fn respond_once(listener: &TcpListener, status: &str) -> String {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0u8; 1024];
+        loop {
+            let n = stream.read(&mut buf).await.unwrap();
+            assert!(n > 0, "connection closed before the request was complete");
+            request.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&request);
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length: usize = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("Content-Length: "))
+                    .unwrap()
+                    .parse()
+                    .unwrap();
+                if body.len() >= length {
+                    break;
+                }
+            }
+        }
+        let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status);
+        stream.write_all(response.as_bytes()).await.unwrap();
+        String::from_utf8(request).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_post_and_retry() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let url = WebhookUrl::parse(&format!("http://127.0.0.1:{}/fence", port)).unwrap();
+
+        let webhook = Webhook::spawn(url);
+        webhook.send(r#"{"detect":"enter"}"#.to_string());
+        webhook.send(r#"{"detect":"exit"}"#.to_string());
+
+        // 第一次失败后重试同一条事件，之后按顺序发送下一条
+        let first = respond_once(&listener, "503 Service Unavailable").await;
+        assert!(first.starts_with("POST /fence HTTP/1.1\r\n"));
+        assert!(first.contains(&format!("Host: 127.0.0.1:{}\r\n", port)));
+        assert!(first.contains("Content-Type: application/json\r\n"));
+        assert!(first.ends_with(r#"{"detect":"enter"}"#));
+        let retried = respond_once(&listener, "200 OK").await;
+        assert!(retried.ends_with(r#"{"detect":"enter"}"#));
+        let second = respond_once(&listener, "204 No Content").await;
+        assert!(second.ends_with(r#"{"detect":"exit"}"#));
+    }
+}
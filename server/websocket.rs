@@ -0,0 +1,268 @@
+//! WebSocket (RFC 6455) 的握手和帧编解码
+//!
+//! 只实现服务端需要的部分：计算握手的 `Sec-WebSocket-Accept`、解码客户端发来的帧（必须带掩码）、
+//! 编码不带掩码的服务端帧。不支持扩展（permessage-deflate 等），握手时不协商
+
+/// 握手时拼接在客户端 key 之后的固定 GUID
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// 单个帧的最大负载，与 RESP 批量字符串的上限保持同一量级
+pub const MAX_FRAME_PAYLOAD: usize = 64 * 1024 * 1024;
+
+pub const OPCODE_CONTINUATION: u8 = 0x0;
+pub const OPCODE_TEXT: u8 = 0x1;
+pub const OPCODE_BINARY: u8 = 0x2;
+pub const OPCODE_CLOSE: u8 = 0x8;
+pub const OPCODE_PING: u8 = 0x9;
+pub const OPCODE_PONG: u8 = 0xA;
+
+/// 解码后的帧，负载已去掉掩码
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub fin: bool,
+    pub opcode: u8,
+    pub payload: Vec<u8>,
+}
+
+/// 根据客户端的 `Sec-WebSocket-Key` 计算 `Sec-WebSocket-Accept`
+pub fn accept_key(client_key: &str) -> String {
+    let mut input = client_key.trim().as_bytes().to_vec();
+    input.extend_from_slice(HANDSHAKE_GUID.as_bytes());
+    base64_encode(&sha1(&input))
+}
+
+/// 从缓冲区开头解码一个客户端帧
+///
+/// - `Ok(Some((frame, consumed)))`：完整的帧，占用缓冲区的前 `consumed` 字节
+/// - `Ok(None)`：帧还不完整，需要读取更多数据
+/// - `Err(..)`：违反协议（没有掩码、控制帧过长、负载超过上限），应关闭连接
+pub fn decode_frame(buf: &[u8]) -> Result<Option<(Frame, usize)>, String> {
+    if buf.len() < 2 {
+        return Ok(None);
+    }
+    let fin = buf[0] & 0x80 != 0;
+    let opcode = buf[0] & 0x0F;
+    if buf[1] & 0x80 == 0 {
+        return Err("client frames must be masked".to_string());
+    }
+
+    let (len, mut pos) = match buf[1] & 0x7F {
+        126 => {
+            if buf.len() < 4 {
+                return Ok(None);
+            }
+            (u16::from_be_bytes([buf[2], buf[3]]) as u64, 4)
+        }
+        127 => {
+            if buf.len() < 10 {
+                return Ok(None);
+            }
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&buf[2..10]);
+            (u64::from_be_bytes(bytes), 10)
+        }
+        len => (len as u64, 2),
+    };
+    if opcode & 0x8 != 0 && (len > 125 || !fin) {
+        return Err("control frames must not be fragmented or longer than 125 bytes".to_string());
+    }
+    if len > MAX_FRAME_PAYLOAD as u64 {
+        return Err(format!("frame payload of {} bytes exceeds limit", len));
+    }
+    let len = len as usize;
+
+    if buf.len() < pos + 4 + len {
+        return Ok(None);
+    }
+    let mask = [buf[pos], buf[pos + 1], buf[pos + 2], buf[pos + 3]];
+    pos += 4;
+    let payload = buf[pos..pos + len]
+        .iter()
+        .enumerate()
+        .map(|(i, byte)| byte ^ mask[i % 4])
+        .collect();
+
+    Ok(Some((
+        Frame {
+            fin,
+            opcode,
+            payload,
+        },
+        pos + len,
+    )))
+}
+
+/// 编码一个完整（FIN）的服务端帧，服务端帧不带掩码
+pub fn encode_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    frame.push(0x80 | opcode);
+    match payload.len() {
+        len if len <= 125 => frame.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// SHA-1（FIPS 180-4），只用于握手，不用于任何安全相关的场景
+fn sha1(input: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    let mut message = input.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((input.len() as u64) * 8).to_be_bytes());
+
+    for chunk in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in chunk.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (i, word) in h.iter().enumerate() {
+        digest[i * 4..i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+/// 标准 Base64 编码（带填充）
+fn base64_encode(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut output = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 客户端帧：带掩码
+    fn masked(opcode: u8, fin: bool, payload: &[u8]) -> Vec<u8> {
+        let mask = [0x37, 0xFA, 0x21, 0x3D];
+        let mut frame = vec![if fin { 0x80 } else { 0 } | opcode];
+        match payload.len() {
+            len if len <= 125 => frame.push(0x80 | len as u8),
+            len => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        frame
+    }
+
+    #[test]
+    fn test_accept_key_rfc_example() {
+        // RFC 6455 第 1.3 节的示例
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn test_sha1_and_base64() {
+        let hex: String = sha1(b"abc").iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(hex, "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn test_decode_masked_frames() {
+        let frame = masked(OPCODE_TEXT, true, b"PING");
+        // 逐字节到达时，完整之前都返回 None
+        for end in 0..frame.len() {
+            assert_eq!(decode_frame(&frame[..end]), Ok(None));
+        }
+        let (decoded, consumed) = decode_frame(&frame).unwrap().unwrap();
+        assert_eq!(consumed, frame.len());
+        assert_eq!(decoded.payload, b"PING");
+        assert!(decoded.fin);
+
+        let long = vec![b'x'; 300];
+        let (decoded, _) = decode_frame(&masked(OPCODE_BINARY, false, &long))
+            .unwrap()
+            .unwrap();
+        assert_eq!(decoded.payload, long);
+        assert!(!decoded.fin);
+    }
+
+    #[test]
+    fn test_decode_rejects_protocol_violations() {
+        // 没有掩码
+        assert!(decode_frame(&encode_frame(OPCODE_TEXT, b"hi")).is_err());
+        // 分片的控制帧
+        assert!(decode_frame(&masked(OPCODE_PING, false, b"")).is_err());
+        // 超过上限的长度
+        let mut huge = vec![0x82, 0x80 | 127];
+        huge.extend_from_slice(&(MAX_FRAME_PAYLOAD as u64 + 1).to_be_bytes());
+        assert!(decode_frame(&huge).is_err());
+    }
+
+    #[test]
+    fn test_encode_frame_lengths() {
+        assert_eq!(encode_frame(OPCODE_TEXT, b"ok"), b"\x81\x02ok");
+        let frame = encode_frame(OPCODE_TEXT, &[0; 126]);
+        assert_eq!(&frame[..4], &[0x81, 126, 0, 126]);
+        let frame = encode_frame(OPCODE_BINARY, &vec![0; 70_000]);
+        assert_eq!(frame[1], 127);
+        assert_eq!(frame.len(), 70_000 + 10);
+    }
+}
@@ -0,0 +1,393 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::node::{Entry, Node};
+use crate::rectangle::Rectangle;
+
+/// 只按边界框索引的 R-tree - 遵循 Guttman 论文的 Insert、Search、Delete 和 QuadraticSplit 算法
+///
+/// 数据条目保存对象 ID，同一个 ID 可以有多个条目（例如多部分几何体的每个部分）
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RTreeIndex {
+    /// 根节点
+    root: Option<Box<Node>>,
+    /// 最大条目数M
+    max_entries: usize,
+    /// 最小条目数m（通常为M/2）
+    min_entries: usize,
+}
+
+impl RTreeIndex {
+    /// 创建空树，最小条目数为 `max_entries / 2`
+    pub fn new(max_entries: usize) -> Self {
+        assert!(max_entries >= 2, "Max entries must be at least 2");
+        RTreeIndex {
+            root: None,
+            max_entries,
+            min_entries: max_entries / 2,
+        }
+    }
+
+    /// 检查树是否为空
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// 获取最大条目数
+    pub fn max_entries(&self) -> usize {
+        self.max_entries
+    }
+
+    /// 获取最小条目数
+    pub fn min_entries(&self) -> usize {
+        self.min_entries
+    }
+
+    /// 获取树的深度
+    pub fn depth(&self) -> usize {
+        self.root.as_ref().map_or(0, |node| node.level + 1)
+    }
+
+    /// 获取数据条目数量
+    pub fn len(&self) -> usize {
+        self.root.as_ref().map_or(0, |node| count_entries(node))
+    }
+
+    /// 获取根节点
+    pub fn root(&self) -> Option<&Node> {
+        self.root.as_deref()
+    }
+
+    /// 获取根节点的可变引用，用于批量装载等直接构建树的场景
+    pub fn root_mut(&mut self) -> &mut Option<Box<Node>> {
+        &mut self.root
+    }
+
+    /// 获取根节点的引用
+    pub fn root_ref(&self) -> &Option<Box<Node>> {
+        &self.root
+    }
+
+    /// 插入新的数据条目 - 遵循论文Algorithm Insert
+    pub fn insert(&mut self, rect: Rectangle, data: String) {
+        let entry = Entry::Data { mbr: rect, data };
+
+        // I1: 如果根节点不存在，创建根节点
+        let Some(mut root) = self.root.take() else {
+            let mut root = Node::new_leaf_node();
+            root.add_entry(entry);
+            self.root = Some(Box::new(root));
+            return;
+        };
+
+        // I2 - I3: 从根节点递归下降到叶子节点并添加记录
+        // I4: 根节点分裂时树长高一层
+        if let Some(sibling) = self.insert_into(&mut root, entry) {
+            let mut new_root = Node::new_index_node(root.level + 1);
+            new_root.add_entry(Entry::Node {
+                mbr: root.mbr,
+                node: root,
+            });
+            new_root.add_entry(Entry::Node {
+                mbr: sibling.mbr,
+                node: Box::new(sibling),
+            });
+            root = Box::new(new_root);
+        }
+        self.root = Some(root);
+    }
+
+    /// 把条目插入到以 node 为根的子树 - 遵循论文ChooseLeaf和AdjustTree算法
+    ///
+    /// 下降时逐层选择扩大面积最小的子树，返回时更新沿途的MBR；节点溢出时分裂，
+    /// 分裂出的新节点返回给父节点加入。递归深度等于树高
+    fn insert_into(&self, node: &mut Node, entry: Entry) -> Option<Node> {
+        if node.is_leaf_node() {
+            node.entries.push(entry);
+        } else {
+            // CL3: 选择子树 - 选择扩大面积最小的条目
+            let best_index = self.choose_subtree(&node.entries, entry.mbr());
+            let Some(Entry::Node { mbr, node: child }) = node.entries.get_mut(best_index) else {
+                unreachable!("index node only holds node entries");
+            };
+
+            // CL4: 下降到子节点
+            let split = self.insert_into(child, entry);
+
+            // AT3: 调整父节点中指向子节点的条目的MBR
+            *mbr = child.mbr;
+            if let Some(sibling) = split {
+                // AT4: 子节点分裂，把新节点加入父节点
+                node.entries.push(Entry::Node {
+                    mbr: sibling.mbr,
+                    node: Box::new(sibling),
+                });
+            }
+        }
+
+        if node.entries.len() > self.max_entries {
+            Some(self.split_node(node))
+        } else {
+            node.update_mbr();
+            None
+        }
+    }
+
+    /// 选择子树 - 计算扩大面积最小的条目
+    fn choose_subtree(&self, entries: &[Entry], rect: &Rectangle) -> usize {
+        let mut best_index = 0;
+        let mut min_enlargement = f64::INFINITY;
+        let mut min_area = f64::INFINITY;
+
+        for (i, entry) in entries.iter().enumerate() {
+            let mbr = entry.mbr();
+            let enlargement = mbr.enlargement(rect);
+            let area = mbr.area();
+
+            // 选择扩大面积最小的，如果相同则选择面积最小的
+            if enlargement < min_enlargement || (enlargement == min_enlargement && area < min_area)
+            {
+                min_enlargement = enlargement;
+                min_area = area;
+                best_index = i;
+            }
+        }
+
+        best_index
+    }
+
+    /// 搜索MBR与查询范围相交的数据条目 - 遵循论文Search算法
+    ///
+    /// 用显式栈做深度优先遍历，退化的深树不会耗尽调用栈。
+    /// 同一个 ID 有多个条目与查询范围相交时会返回多次
+    pub fn search_bbox(&self, query: &Rectangle) -> Vec<String> {
+        let mut results = Vec::new();
+        let Some(root) = &self.root else {
+            return results;
+        };
+        let mut stack: Vec<&Entry> = root.entries.iter().rev().collect();
+
+        while let Some(entry) = stack.pop() {
+            if !entry.mbr().intersects(query) {
+                continue;
+            }
+            match entry {
+                Entry::Data { data, .. } => results.push(data.clone()),
+                Entry::Node { node, .. } => stack.extend(node.entries.iter().rev()),
+            }
+        }
+        results
+    }
+
+    /// 删除MBR为 `rect` 的数据条目 - 使用简化的下溢处理策略，没有找到条目时返回 false
+    pub fn delete(&mut self, rect: &Rectangle, data: &str) -> bool {
+        let Some(mut root) = self.root.take() else {
+            return false;
+        };
+
+        // D1 - D3: 递归下降找到并删除条目，返回时处理下溢和MBR
+        let mut orphans = Vec::new();
+        let deleted = self.delete_from(&mut root, rect, data, &mut orphans);
+        self.root = Some(root);
+        if !deleted {
+            return false; // 没有找到要删除的条目
+        }
+
+        // D4: 如果根节点只有一个条目且为索引节点，则缩短树
+        self.shorten_tree();
+
+        // 重新插入下溢叶子节点中的数据条目
+        for (mbr, data) in orphans {
+            self.insert(mbr, data);
+        }
+        true
+    }
+
+    /// 从以 node 为根的子树中删除条目 - 遵循论文FindLeaf和CondenseTree算法
+    ///
+    /// 只在MBR包含目标矩形的子树中查找，返回是否删除了条目。返回时更新沿途的MBR，
+    /// 下溢的叶子节点从父节点中移除，其数据条目放入 orphans 由调用方重新插入；
+    /// 变空的索引节点直接移除。递归深度等于树高
+    fn delete_from(
+        &self,
+        node: &mut Node,
+        rect: &Rectangle,
+        data: &str,
+        orphans: &mut Vec<(Rectangle, String)>,
+    ) -> bool {
+        if node.is_leaf_node() {
+            // 在叶子节点中删除匹配的条目
+            let initial_count = node.entries.len();
+            node.entries.retain(|entry| {
+                !matches!(entry, Entry::Data { mbr, data: entry_data }
+                    if mbr == rect && entry_data == data)
+            });
+            if node.entries.len() == initial_count {
+                return false;
+            }
+            node.update_mbr();
+            return true;
+        }
+
+        let min_entries = self.min_entries;
+        for index in 0..node.entries.len() {
+            let Entry::Node {
+                mbr,
+                node: child_node,
+            } = &mut node.entries[index]
+            else {
+                continue;
+            };
+            if !mbr.contains(rect) || !self.delete_from(child_node, rect, data, orphans) {
+                continue;
+            }
+
+            let underflow = if child_node.is_leaf_node() {
+                child_node.entries.len() < min_entries
+            } else {
+                child_node.entries.is_empty()
+            };
+            if underflow {
+                // 从父节点中移除下溢的子节点，叶子节点的条目稍后重新插入
+                if let Entry::Node {
+                    node: child_node, ..
+                } = node.entries.remove(index)
+                {
+                    orphans.extend(child_node.entries.into_iter().filter_map(
+                        |entry| match entry {
+                            Entry::Data { mbr, data } => Some((mbr, data)),
+                            Entry::Node { .. } => None,
+                        },
+                    ));
+                }
+            } else {
+                *mbr = child_node.mbr;
+            }
+            node.update_mbr();
+            return true;
+        }
+        false
+    }
+
+    /// 缩短树 - 如果根节点只有一个条目且为索引节点，则将其子节点作为新的根节点
+    fn shorten_tree(&mut self) {
+        while let Some(root) = self.root.take() {
+            if !(root.is_index_node() && root.entries.len() == 1) {
+                self.root = Some(root);
+                break;
+            }
+            // 将唯一的子节点提升为新的根节点
+            let mut entries = root.entries;
+            if let Some(Entry::Node { node, .. }) = entries.pop() {
+                self.root = Some(node);
+            } else {
+                // 恢复根节点，防止出错
+                self.root = Some(Box::new(Node::new(root.node_type, root.level)));
+                break;
+            }
+        }
+
+        // 如果根节点为空（所有条目都被删除），则清空树
+        if self
+            .root
+            .as_ref()
+            .is_some_and(|root| root.entries.is_empty())
+        {
+            self.root = None;
+        }
+    }
+}
+
+/// 统计子树中的数据条目数量
+fn count_entries(node: &Node) -> usize {
+    if node.is_leaf_node() {
+        node.entries.len()
+    } else {
+        node.entries
+            .iter()
+            .map(|entry| match entry {
+                Entry::Node { node, .. } => count_entries(node),
+                Entry::Data { .. } => 1,
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    #[test]
+    fn test_choose_subtree() {
+        let index = RTreeIndex::new(4);
+
+        // 创建一些测试条目
+        let entries = vec![
+            Entry::Data {
+                mbr: Rectangle::new(0.0, 0.0, 5.0, 5.0),
+                data: "1".to_string(),
+            },
+            Entry::Data {
+                mbr: Rectangle::new(10.0, 10.0, 15.0, 15.0),
+                data: "2".to_string(),
+            },
+            Entry::Data {
+                mbr: Rectangle::new(20.0, 20.0, 25.0, 25.0),
+                data: "3".to_string(),
+            },
+        ];
+
+        // 测试选择最合适的子树
+        let test_rect = Rectangle::new(2.0, 2.0, 3.0, 3.0);
+        let best_index = index.choose_subtree(&entries, &test_rect);
+
+        // 应该选择第一个条目，因为它与测试矩形重叠
+        assert_eq!(best_index, 0);
+    }
+
+    #[test]
+    fn test_insert_search_delete() {
+        let mut index = RTreeIndex::new(4);
+        for i in 0..100 {
+            let (x, y) = ((i % 10) as f64, (i / 10) as f64);
+            index.insert(Rectangle::from_point(x, y), format!("{}", i));
+        }
+        assert_eq!(index.len(), 100);
+        assert!(index.depth() > 1);
+
+        let mut found = index.search_bbox(&Rectangle::new(2.0, 3.0, 4.0, 4.0));
+        found.sort();
+        assert_eq!(found, ["32", "33", "34", "42", "43", "44"]);
+
+        // 矩形必须与插入时一致
+        assert!(!index.delete(&Rectangle::from_point(0.0, 0.0), "33"));
+        for i in 0..100 {
+            let (x, y) = ((i % 10) as f64, (i / 10) as f64);
+            assert!(index.delete(&Rectangle::from_point(x, y), &format!("{}", i)));
+            assert_eq!(index.len(), 99 - i);
+        }
+        assert!(index.is_empty());
+        assert_eq!(index.depth(), 0);
+    }
+
+    #[test]
+    fn test_multiple_entries_per_id() {
+        let mut index = RTreeIndex::new(4);
+        index.insert(Rectangle::new(0.0, 0.0, 1.0, 1.0), "a".to_string());
+        index.insert(Rectangle::new(5.0, 5.0, 6.0, 6.0), "a".to_string());
+        assert_eq!(
+            index.search_bbox(&Rectangle::new(0.0, 0.0, 10.0, 10.0)),
+            ["a", "a"]
+        );
+        assert!(index.delete(&Rectangle::new(5.0, 5.0, 6.0, 6.0), "a"));
+        assert_eq!(
+            index.search_bbox(&Rectangle::new(0.0, 0.0, 10.0, 10.0)),
+            ["a"]
+        );
+    }
+}
@@ -0,0 +1,24 @@
+//! Spatio 的 R-tree 核心：矩形、节点以及只按边界框索引的插入、搜索、删除和节点分裂
+//!
+//! 不依赖 `geo`、tokio 等，关闭默认的 `std` feature 后为 `no_std`（只需要 `alloc`），
+//! 可以编译到 `wasm32-unknown-unknown` 在浏览器或边缘环境中使用：
+//!
+//! ```text
+//! cargo build -p spatio-rtree --no-default-features --target wasm32-unknown-unknown
+//! ```
+//!
+//! `spatio::rtree::RTree` 在 [`RTreeIndex`] 之上保存几何体、GeoJSON、标签等，并提供持久化。
+//! `serde` feature 为各类型实现序列化，`RTreeIndex` 序列化后的字段依次为根节点、最大和最小条目数
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod index;
+pub mod node;
+pub mod rectangle;
+mod split;
+
+pub use index::RTreeIndex;
+pub use node::{Entry, Node, NodeType};
+pub use rectangle::Rectangle;
@@ -1,10 +1,14 @@
-use super::rectangle::Rectangle;
-use serde::{Deserialize, Serialize};
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::rectangle::Rectangle;
 
 /// R-tree节点类型
 ///
 /// 用于明确区分R-tree中的两种节点类型，避免概念混淆
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NodeType {
     /// 叶子节点：包含用户插入的真实数据条目
     /// 这些节点位于R-tree的叶子层，直接存储用户数据
@@ -19,7 +23,8 @@ pub enum NodeType {
 /// 每个条目都包含一个MBR（最小边界矩形）和对应的内容：
 /// - Data条目：存储用户插入的真实数据，只出现在叶子节点中
 /// - Node条目：存储子节点的引用，只出现在索引节点中
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Entry {
     /// 数据条目：存储用户插入的真实数据
     ///
@@ -104,7 +109,8 @@ impl Entry {
 /// R-tree节点
 ///
 /// R-tree的核心数据结构，表示树中的一个节点
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Node {
     /// 节点的最小边界矩形
     ///
@@ -293,6 +299,7 @@ impl Node {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::string::ToString;
 
     #[test]
     fn test_node_creation() {
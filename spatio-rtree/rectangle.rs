@@ -1,7 +1,6 @@
-use serde::{Deserialize, Serialize};
-
 /// 矩形边界框 - 用于表示R-tree中的最小边界矩形(MBR)
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Rectangle {
     pub min: [f64; 2], // [x_min, y_min]
     pub max: [f64; 2], // [x_max, y_max]
@@ -89,6 +88,13 @@ impl Rectangle {
         ]
     }
 
+    /// 计算点到矩形的最小平面距离的平方（点在矩形内时为0）
+    pub fn distance_squared_to_point(&self, x: f64, y: f64) -> f64 {
+        let dx = (self.min[0] - x).max(0.0).max(x - self.max[0]);
+        let dy = (self.min[1] - y).max(0.0).max(y - self.max[1]);
+        dx * dx + dy * dy
+    }
+
     /// 判断矩形是否为空（面积为0）
     pub fn is_empty(&self) -> bool {
         self.area() == 0.0
@@ -145,6 +151,14 @@ mod tests {
         assert!(!rect1.contains(&rect3));
     }
 
+    #[test]
+    fn test_rectangle_distance_squared_to_point() {
+        let rect = Rectangle::new(0.0, 0.0, 10.0, 10.0);
+        assert_eq!(rect.distance_squared_to_point(5.0, 5.0), 0.0);
+        assert_eq!(rect.distance_squared_to_point(13.0, 5.0), 9.0);
+        assert_eq!(rect.distance_squared_to_point(13.0, 14.0), 25.0);
+    }
+
     #[test]
     fn test_rectangle_contains_point() {
         let rect = Rectangle::new(0.0, 0.0, 10.0, 10.0);
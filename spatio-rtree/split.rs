@@ -1,140 +1,26 @@
-use super::super::node::{Entry, Node};
-use super::super::rectangle::Rectangle;
-use crate::rtree::RTree;
+use alloc::vec;
+use alloc::vec::Vec;
 
-/// 节点分裂算法 - 实现完整的二次分裂(Quadratic Split)
-impl RTree {
-    /// 处理节点溢出 - 使用二次分裂算法
-    pub(crate) fn handle_overflow(&mut self, path: Vec<usize>) {
-        // 如果是根节点溢出，需要特殊处理
-        if path.is_empty() {
-            // 根节点溢出 - 创建新的根节点
-            let old_root = self.root_mut().take().unwrap();
-            let (group1, group2) = self.quadratic_split(old_root.entries);
-
-            // 创建两个新节点
-            let mut node1 = Node::new(old_root.node_type.clone(), old_root.level);
-            node1.entries = group1;
-            node1.update_mbr();
-
-            let mut node2 = Node::new(old_root.node_type.clone(), old_root.level);
-            node2.entries = group2;
-            node2.update_mbr();
-
-            // 创建新的根节点
-            let mut new_root = Node::new_index_node(old_root.level + 1);
-            new_root.add_entry(Entry::Node {
-                mbr: node1.mbr,
-                node: Box::new(node1),
-            });
-            new_root.add_entry(Entry::Node {
-                mbr: node2.mbr,
-                node: Box::new(node2),
-            });
-
-            *self.root_mut() = Some(Box::new(new_root));
-        } else {
-            // 非根节点溢出 - 分裂节点并可能向上传播
-            self.split_and_propagate(path);
-        }
-    }
+use crate::index::RTreeIndex;
+use crate::node::{Entry, Node};
+use crate::rectangle::Rectangle;
 
-    /// 分裂节点并向上传播溢出
+/// 节点分裂算法 - 实现完整的二次分裂(Quadratic Split)
+impl RTreeIndex {
+    /// 分裂溢出的节点 - 使用二次分裂算法
     ///
-    /// 这个方法处理非根节点的分裂，并在必要时向上传播分裂
-    pub(crate) fn split_and_propagate(&mut self, mut path: Vec<usize>) {
-        let max_entries = self.max_entries_internal();
-
-        // 获取要分裂的节点并提取其条目
-        let (entries, node_type, level) = {
-            let node = match self.get_last_node_mut(&path) {
-                Some(node) => node,
-                None => {
-                    println!("Warning: Failed to get node during split_and_propagate");
-                    return;
-                }
-            };
-
-            // 检查是否真的需要分裂
-            if node.entries.len() <= max_entries {
-                // 只需要更新MBR
-                self.adjust_tree_upward(path);
-                return;
-            }
-
-            // 提取节点信息
-            let entries = std::mem::take(&mut node.entries);
-            let node_type = node.node_type.clone();
-            let level = node.level;
-
-            (entries, node_type, level)
-        };
-
-        // 执行二次分裂（现在self没有被借用）
+    /// 原节点保留第一组条目，返回装有第二组条目的同层新节点，由调用方加入父节点
+    pub(crate) fn split_node(&self, node: &mut Node) -> Node {
+        let entries = core::mem::take(&mut node.entries);
         let (group1, group2) = self.quadratic_split(entries);
 
-        // 更新原节点
-        {
-            let node = match self.get_last_node_mut(&path) {
-                Some(node) => node,
-                None => {
-                    println!("Warning: Failed to get node during split group update");
-                    return;
-                }
-            };
-            node.entries = group1;
-            node.update_mbr();
-        }
-
-        // 创建新节点
-        let mut new_node = Node::new(node_type, level);
-        new_node.entries = group2;
-        new_node.update_mbr();
-
-        // 获取父节点路径
-        path.pop();
+        node.entries = group1;
+        node.update_mbr();
 
-        if path.is_empty() {
-            // 父节点是根节点，需要特殊处理
-            let root = self.root_mut().as_mut().unwrap();
-
-            // 添加新节点到根节点
-            root.add_entry(Entry::Node {
-                mbr: new_node.mbr,
-                node: Box::new(new_node),
-            });
-
-            // 检查根节点是否溢出
-            if root.entries.len() > max_entries {
-                self.handle_overflow(vec![]);
-            } else {
-                root.update_mbr();
-            }
-        } else {
-            // 父节点不是根节点
-            let parent = match self.get_last_node_mut(&path) {
-                Some(node) => node,
-                None => {
-                    println!("Warning: Failed to get parent node during split propagation");
-                    return;
-                }
-            };
-
-            // 添加新节点到父节点
-            parent.add_entry(Entry::Node {
-                mbr: new_node.mbr,
-                node: Box::new(new_node),
-            });
-
-            // 检查父节点是否溢出
-            if parent.entries.len() > max_entries {
-                // 递归处理父节点溢出
-                self.split_and_propagate(path);
-            } else {
-                // 只需要向上更新MBR
-                self.adjust_tree_upward(path);
-            }
-        }
+        let mut sibling = Node::new(node.node_type.clone(), node.level);
+        sibling.entries = group2;
+        sibling.update_mbr();
+        sibling
     }
 
     /// 二次分裂算法 - 遵循Gut84.pdf论文Algorithm QuadraticSplit
@@ -144,7 +30,7 @@ impl RTree {
     /// 2. 两个节点之间的重叠最小化
     /// 3. 每个节点至少包含最小条目数
     fn quadratic_split(&self, mut entries: Vec<Entry>) -> (Vec<Entry>, Vec<Entry>) {
-        let min_entries = self.min_entries_internal();
+        let min_entries = self.min_entries();
         let total_entries = entries.len();
 
         // QS1: 选择种子 - 找到浪费空间最大的两个条目作为两组的种子
@@ -283,10 +169,12 @@ impl RTree {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::format;
+    use alloc::string::{String, ToString};
 
     #[test]
     fn test_quadratic_split() {
-        let rtree = RTree::new(3); // 小的max_entries以便测试分裂
+        let rtree = RTreeIndex::new(3); // 小的max_entries以便测试分裂
 
         // 创建一些测试条目
         let entries = vec![
@@ -334,7 +222,7 @@ mod tests {
 
     #[test]
     fn test_node_split_with_overflow() {
-        let mut rtree = RTree::new(3); // 最大3个条目，最小1个
+        let mut rtree = RTreeIndex::new(3); // 最大3个条目，最小1个
 
         // 插入足够多的数据以触发分裂
         rtree.insert(Rectangle::new(0.0, 0.0, 1.0, 1.0), "1".to_string());
@@ -361,9 +249,66 @@ mod tests {
         assert!(results.contains(&"4".to_string()));
     }
 
+    #[test]
+    fn test_split_keeps_parent_mbrs_covering() {
+        // 父节点中的条目 MBR 必须包含子节点的全部条目，否则查询会漏掉刚插入的对象
+        fn check(node: &Node) {
+            for entry in &node.entries {
+                if let Entry::Node { mbr, node } = entry {
+                    for child in &node.entries {
+                        assert!(
+                            mbr.contains(child.mbr()),
+                            "{:?} does not cover {:?}",
+                            mbr,
+                            child.mbr()
+                        );
+                    }
+                    check(node);
+                }
+            }
+        }
+
+        let mut rtree = RTreeIndex::new(4);
+        for i in 0..48 {
+            for j in 0..20 {
+                let (x, y) = (-177.0 + 7.5 * i as f64, -83.0 + 8.5 * j as f64);
+                rtree.insert(Rectangle::from_point(x, y), format!("{}_{}", i, j));
+            }
+        }
+        // 插入到已有节点范围之外、并触发分裂的条目
+        for (k, (x, y)) in [
+            (177.5, 64.73),
+            (179.12, 66.32),
+            (180.0, 89.9),
+            (178.44, -18.14),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            rtree.insert(Rectangle::from_point(x, y), format!("extra_{}", k));
+        }
+
+        let root = rtree.root_ref().as_ref().unwrap();
+        check(root);
+        // 根节点自身的 MBR 与条目一致
+        let expected = root
+            .entries
+            .iter()
+            .skip(1)
+            .fold(*root.entries[0].mbr(), |acc, entry| acc.union(entry.mbr()));
+        assert_eq!(root.mbr.min, expected.min);
+        assert_eq!(root.mbr.max, expected.max);
+        assert_eq!(
+            rtree
+                .search_bbox(&Rectangle::new(177.0, 60.0, 180.0, 90.0))
+                .len(),
+            3
+        );
+    }
+
     #[test]
     fn test_pick_seeds() {
-        let rtree = RTree::new(4);
+        let rtree = RTreeIndex::new(4);
 
         // 创建测试条目：两个靠近的和两个相距很远的
         let entries = vec![
@@ -403,7 +348,7 @@ mod tests {
 
     #[test]
     fn test_calculate_group_mbr() {
-        let rtree = RTree::new(4);
+        let rtree = RTreeIndex::new(4);
 
         // 创建一组条目
         let group = vec![
@@ -429,7 +374,7 @@ mod tests {
 
     #[test]
     fn test_pick_next() {
-        let rtree = RTree::new(4);
+        let rtree = RTreeIndex::new(4);
 
         // 创建两个组
         let group1 = vec![Entry::Data {
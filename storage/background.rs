@@ -0,0 +1,62 @@
+//! 同一时间只能运行一次的后台持久化任务（BGSAVE、BGREWRITEAOF）的状态
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// 后台任务的状态：是否正在运行、最近一次成功的时间和最近一次是否失败
+#[derive(Debug, Default)]
+pub(crate) struct BackgroundJob {
+    in_progress: AtomicBool,
+    /// 最近一次成功完成的 Unix 时间戳（秒），0 表示从未完成
+    last_success: AtomicU64,
+    last_failed: AtomicBool,
+}
+
+impl BackgroundJob {
+    pub(crate) fn in_progress(&self) -> bool {
+        self.in_progress.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn last_success(&self) -> Option<u64> {
+        match self.last_success.load(Ordering::Relaxed) {
+            0 => None,
+            at => Some(at),
+        }
+    }
+
+    pub(crate) fn last_failed(&self) -> bool {
+        self.last_failed.load(Ordering::Relaxed)
+    }
+
+    /// 开始一次任务，已经有任务在运行时返回 None
+    ///
+    /// 返回的守卫在任务结束（包括被取消）时释放
+    pub(crate) fn begin(self: &Arc<Self>) -> Option<JobGuard> {
+        self.in_progress
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
+            .ok()?;
+        Some(JobGuard {
+            job: Arc::clone(self),
+        })
+    }
+
+    /// 记录任务结果：成功时为完成的 Unix 时间戳（秒），失败时为 None
+    pub(crate) fn record_result(&self, finished_at: Option<u64>) {
+        self.last_failed
+            .store(finished_at.is_none(), Ordering::Relaxed);
+        if let Some(at) = finished_at {
+            self.last_success.store(at, Ordering::Relaxed);
+        }
+    }
+}
+
+/// 正在运行的任务，drop 时允许下一次任务开始
+pub(crate) struct JobGuard {
+    job: Arc<BackgroundJob>,
+}
+
+impl Drop for JobGuard {
+    fn drop(&mut self) {
+        self.job.in_progress.store(false, Ordering::Release);
+    }
+}
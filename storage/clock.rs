@@ -0,0 +1,111 @@
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// 时钟抽象
+///
+/// 与时间相关的逻辑（空闲卸载、AOF 时间戳等）都通过时钟读取时间，
+/// 测试中使用 [`MockClock`] 手动推进时间，不需要 sleep
+pub trait Clock: Send + Sync + Debug {
+    /// 单调时间，用于计算时间间隔
+    fn now(&self) -> Instant;
+
+    /// 墙上时间（Unix 纪元以来的纳秒），用于需要持久化的时间戳
+    fn unix_nanos(&self) -> u64;
+}
+
+/// 共享的时钟实例
+pub type SharedClock = Arc<dyn Clock>;
+
+/// 系统时钟
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl SystemClock {
+    pub fn shared() -> SharedClock {
+        Arc::new(SystemClock)
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn unix_nanos(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64
+    }
+}
+
+/// 可手动推进的时钟，只有调用 [`advance`](Self::advance) 时时间才会前进
+#[derive(Debug)]
+pub struct MockClock {
+    start: Instant,
+    unix_start: u64,
+    elapsed: Mutex<Duration>,
+}
+
+impl MockClock {
+    /// 创建时钟，墙上时间从 `unix_nanos` 开始
+    pub fn new(unix_nanos: u64) -> Self {
+        Self {
+            start: Instant::now(),
+            unix_start: unix_nanos,
+            elapsed: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    /// 推进时间
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+    }
+
+    /// 自创建以来推进的总时间
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn unix_nanos(&self) -> u64 {
+        self.unix_start + self.elapsed().as_nanos() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_only_moves_when_advanced() {
+        let clock = MockClock::new(1_000);
+        let t0 = clock.now();
+        assert_eq!(clock.now(), t0);
+        assert_eq!(clock.unix_nanos(), 1_000);
+
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(clock.now().duration_since(t0), Duration::from_secs(5));
+        assert_eq!(clock.unix_nanos(), 1_000 + 5_000_000_000);
+    }
+
+    #[test]
+    fn test_system_clock() {
+        let clock = SystemClock;
+        let t0 = clock.now();
+        assert!(clock.now() >= t0);
+        assert!(clock.unix_nanos() > 0);
+    }
+}
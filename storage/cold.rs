@@ -0,0 +1,227 @@
+use crate::rtree::RTree;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 文件名中直接十六进制编码的名称字节数上限，更长的名称只保留前缀并附加哈希，
+/// 使文件名不超过 NAME_MAX（255 字节）
+const MAX_ENCODED_NAME_BYTES: usize = 96;
+
+/// 冷数据卸载配置
+#[derive(Debug, Clone)]
+pub struct UnloadConfig {
+    /// 空闲多久后卸载
+    pub idle_timeout: Duration,
+    /// 卸载文件存放目录
+    pub dir: PathBuf,
+}
+
+impl UnloadConfig {
+    pub fn new(idle_timeout: Duration, dir: PathBuf) -> Self {
+        Self { idle_timeout, dir }
+    }
+}
+
+/// 已卸载 collection 的常驻元数据
+#[derive(Debug, Clone)]
+pub struct ColdCollection {
+    /// 序列化文件路径
+    pub path: PathBuf,
+    /// 卸载时的对象数量
+    pub item_count: usize,
+    /// 卸载时间
+    pub unloaded_at: Instant,
+}
+
+/// 冷数据管理：记录访问时间、已卸载 collection 的元数据和序列化文件的位置
+///
+/// 只使用同步锁，临界区内不跨越 await；序列化文件的读写由调用方在 `spawn_blocking` 中进行，
+/// 不占用异步运行时的工作线程
+#[derive(Debug)]
+pub struct ColdStorage {
+    config: UnloadConfig,
+    last_access: Mutex<HashMap<String, Instant>>,
+    unloaded: Mutex<HashMap<String, ColdCollection>>,
+    /// 正在重新加载的 collection：同一 collection 的并发访问排队等待同一次加载
+    loading: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+    /// 每次卸载使用新的文件名，后台删除旧文件时不会误删同名 collection 后来写入的文件
+    generation: AtomicU64,
+}
+
+impl ColdStorage {
+    /// 创建冷数据管理器
+    ///
+    /// 卸载目录中残留的文件来自上一次运行，数据会通过 AOF 恢复，因此在启动时清理
+    pub fn new(config: UnloadConfig) -> std::io::Result<Self> {
+        std::fs::create_dir_all(&config.dir)?;
+        for entry in std::fs::read_dir(&config.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "rtree") {
+                std::fs::remove_file(path)?;
+            }
+        }
+
+        Ok(Self {
+            config,
+            last_access: Mutex::new(HashMap::new()),
+            unloaded: Mutex::new(HashMap::new()),
+            loading: Mutex::new(HashMap::new()),
+            generation: AtomicU64::new(0),
+        })
+    }
+
+    pub fn idle_timeout(&self) -> Duration {
+        self.config.idle_timeout
+    }
+
+    /// 记录一次访问
+    pub fn touch(&self, collection_id: &str, now: Instant) {
+        let mut last_access = self.last_access.lock().unwrap();
+        match last_access.get_mut(collection_id) {
+            Some(at) => *at = now,
+            None => {
+                last_access.insert(collection_id.to_string(), now);
+            }
+        }
+    }
+
+    /// 空闲时间超过阈值的 collection
+    pub fn idle_collections(&self, now: Instant) -> Vec<String> {
+        let last_access = self.last_access.lock().unwrap();
+        last_access
+            .iter()
+            .filter(|(_, at)| now.duration_since(**at) >= self.config.idle_timeout)
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    /// 是否存在已卸载的同名 collection
+    pub fn is_unloaded(&self, collection_id: &str) -> bool {
+        self.unloaded.lock().unwrap().contains_key(collection_id)
+    }
+
+    /// 已卸载 collection 的名称
+    pub fn unloaded_names(&self) -> Vec<String> {
+        self.unloaded.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// 已卸载 collection 的名称和卸载时的对象数量
+    pub fn unloaded_counts(&self) -> Vec<(String, usize)> {
+        self.unloaded
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, cold)| (name.clone(), cold.item_count))
+            .collect()
+    }
+
+    /// 已卸载 collection 的 (数量, 对象总数)
+    pub fn unloaded_totals(&self) -> (usize, usize) {
+        let unloaded = self.unloaded.lock().unwrap();
+        let items = unloaded.values().map(|c| c.item_count).sum();
+        (unloaded.len(), items)
+    }
+
+    /// collection 已写入 `path`：记录常驻元数据，停止计算空闲时间
+    pub fn mark_unloaded(
+        &self,
+        collection_id: &str,
+        path: PathBuf,
+        item_count: usize,
+        now: Instant,
+    ) {
+        self.unloaded.lock().unwrap().insert(
+            collection_id.to_string(),
+            ColdCollection {
+                path,
+                item_count,
+                unloaded_at: now,
+            },
+        );
+        self.last_access.lock().unwrap().remove(collection_id);
+    }
+
+    /// 已卸载 collection 的序列化文件，不存在时返回 None
+    pub fn unloaded_path(&self, collection_id: &str) -> Option<PathBuf> {
+        self.unloaded
+            .lock()
+            .unwrap()
+            .get(collection_id)
+            .map(|cold| cold.path.clone())
+    }
+
+    /// 重新加载的 collection 已放回内存：移除元数据并重新开始计算空闲时间，
+    /// 返回可以删除的序列化文件
+    pub fn mark_reloaded(&self, collection_id: &str, now: Instant) -> Option<PathBuf> {
+        let cold = self.unloaded.lock().unwrap().remove(collection_id)?;
+        self.touch(collection_id, now);
+        Some(cold.path)
+    }
+
+    /// collection 的加载锁，重新加载期间持有
+    pub fn loading(&self, collection_id: &str) -> Arc<tokio::sync::Mutex<()>> {
+        Arc::clone(
+            self.loading
+                .lock()
+                .unwrap()
+                .entry(collection_id.to_string())
+                .or_default(),
+        )
+    }
+
+    /// 重新加载结束（无论成败），之后的访问不再等待这次加载
+    pub fn finish_loading(&self, collection_id: &str) {
+        self.loading.lock().unwrap().remove(collection_id);
+    }
+
+    /// 删除 collection 的冷数据和访问记录，返回卸载时的对象数量
+    pub fn remove(&self, collection_id: &str) -> Option<usize> {
+        self.last_access.lock().unwrap().remove(collection_id);
+        let cold = self.unloaded.lock().unwrap().remove(collection_id)?;
+        remove_file_in_background(cold.path);
+        Some(cold.item_count)
+    }
+
+    /// 为一次卸载分配序列化文件路径
+    ///
+    /// 名称可能包含任意字符，文件名使用十六进制编码；超过 [`MAX_ENCODED_NAME_BYTES`] 字节的名称
+    /// 只编码前缀，再附加整个名称的哈希。最后附加本次卸载的序号
+    pub fn file_path(&self, collection_id: &str) -> PathBuf {
+        let bytes = collection_id.as_bytes();
+        let mut name: String = bytes
+            .iter()
+            .take(MAX_ENCODED_NAME_BYTES)
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        if bytes.len() > MAX_ENCODED_NAME_BYTES {
+            let mut hasher = DefaultHasher::new();
+            collection_id.hash(&mut hasher);
+            name.push_str(&format!("-{:016x}", hasher.finish()));
+        }
+        let generation = self.generation.fetch_add(1, Ordering::Relaxed);
+        Path::new(&self.config.dir).join(format!("{}.{}.rtree", name, generation))
+    }
+}
+
+/// 在阻塞线程池中读取序列化文件
+pub(crate) async fn load_rtree(path: PathBuf) -> crate::Result<RTree> {
+    let rtree = tokio::task::spawn_blocking(move || RTree::load_from_file(path)).await??;
+    Ok(rtree)
+}
+
+/// 删除不再需要的序列化文件，运行时内交给阻塞线程池，不等待结果
+pub(crate) fn remove_file_in_background(path: PathBuf) {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => {
+            handle.spawn_blocking(move || {
+                let _ = std::fs::remove_file(path);
+            });
+        }
+        Err(_) => {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use crate::protocol::{CommandError, ErrorCode};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 默认检查间隔
+pub const DEFAULT_DISK_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 查询目录所在文件系统的可用空间（字节），测试中可替换为固定值
+pub type FreeSpaceProbe = Arc<dyn Fn(&Path) -> io::Result<u64> + Send + Sync>;
+
+/// 磁盘空间监控配置
+#[derive(Debug, Clone)]
+pub struct DiskConfig {
+    /// 被监控的目录（通常是 data_dir）
+    pub dir: PathBuf,
+    /// 可用空间低于该值时拒绝写入
+    pub min_free_bytes: u64,
+    /// 检查间隔
+    pub check_interval: Duration,
+}
+
+impl DiskConfig {
+    pub fn new(dir: PathBuf, min_free_bytes: u64) -> Self {
+        Self {
+            dir,
+            min_free_bytes,
+            check_interval: DEFAULT_DISK_CHECK_INTERVAL,
+        }
+    }
+
+    pub fn with_check_interval(mut self, interval: Duration) -> Self {
+        self.check_interval = interval;
+        self
+    }
+}
+
+/// 某一时刻的磁盘空间状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskStatus {
+    pub free_bytes: u64,
+    pub min_free_bytes: u64,
+    pub low: bool,
+}
+
+/// 磁盘空间监控：定期检查可用空间，低于阈值时拒绝写入，读请求不受影响
+///
+/// 磁盘写满时 AOF 只能写入半条记录，重启后需要截断修复；
+/// 提前拒绝写入可以让 AOF 停在完整的记录边界上。
+/// 状态保存在原子变量中，写路径上的检查不需要加锁
+pub struct DiskMonitor {
+    config: DiskConfig,
+    probe: FreeSpaceProbe,
+    free_bytes: AtomicU64,
+    low: AtomicBool,
+}
+
+impl DiskMonitor {
+    /// 创建监控器，首次 [`refresh`](Self::refresh) 之前视为空间充足
+    pub fn new(config: DiskConfig) -> Self {
+        Self {
+            config,
+            probe: Arc::new(free_space),
+            free_bytes: AtomicU64::new(u64::MAX),
+            low: AtomicBool::new(false),
+        }
+    }
+
+    /// 替换可用空间的查询方式
+    pub fn with_probe(mut self, probe: FreeSpaceProbe) -> Self {
+        self.probe = probe;
+        self
+    }
+
+    pub fn config(&self) -> &DiskConfig {
+        &self.config
+    }
+
+    /// 重新查询可用空间并更新状态，返回可用字节数
+    ///
+    /// 查询失败时保留上一次的状态，避免偶发错误导致写入被拒绝或放行
+    pub fn refresh(&self) -> io::Result<u64> {
+        let free_bytes = (self.probe)(&self.config.dir)?;
+        let low = free_bytes < self.config.min_free_bytes;
+
+        self.free_bytes.store(free_bytes, Ordering::Relaxed);
+        let was_low = self.low.swap(low, Ordering::Relaxed);
+        if low && !was_low {
+            tracing::warn!(
+                "💽 Free disk space on {} is {} bytes, below the {} byte threshold; refusing writes",
+                self.config.dir.display(),
+                free_bytes,
+                self.config.min_free_bytes
+            );
+        } else if !low && was_low {
+            tracing::info!(
+                "💽 Free disk space on {} recovered to {} bytes; accepting writes",
+                self.config.dir.display(),
+                free_bytes
+            );
+        }
+
+        Ok(free_bytes)
+    }
+
+    /// 可用空间是否低于阈值
+    pub fn is_low(&self) -> bool {
+        self.low.load(Ordering::Relaxed)
+    }
+
+    pub fn status(&self) -> DiskStatus {
+        DiskStatus {
+            free_bytes: self.free_bytes.load(Ordering::Relaxed),
+            min_free_bytes: self.config.min_free_bytes,
+            low: self.is_low(),
+        }
+    }
+
+    /// 空间不足时返回 `OOM` 错误
+    pub fn check(&self) -> Result<(), CommandError> {
+        if !self.is_low() {
+            return Ok(());
+        }
+        let status = self.status();
+        Err(CommandError::new(
+            ErrorCode::Oom,
+            format!(
+                "DISK free space {} bytes is below the {} byte threshold, writes are refused",
+                status.free_bytes, status.min_free_bytes
+            ),
+        ))
+    }
+}
+
+/// 查询目录所在文件系统对非特权用户可用的空间（字节）
+#[cfg(unix)]
+pub fn free_space(dir: &Path) -> io::Result<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(dir.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+
+    // SAFETY: path 是以 NUL 结尾的 C 字符串，stat 指向足够大小的缓冲区，
+    // 调用成功时由 statvfs 完整填充
+    let ret = unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let stat = unsafe { stat.assume_init() };
+
+    #[allow(clippy::unnecessary_cast)]
+    Ok((stat.f_bavail as u64).saturating_mul(stat.f_frsize as u64))
+}
+
+/// 查询目录所在文件系统对非特权用户可用的空间（字节）
+#[cfg(not(unix))]
+pub fn free_space(_dir: &Path) -> io::Result<u64> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "disk space monitoring is only supported on Unix",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_probe(free: Arc<AtomicU64>) -> FreeSpaceProbe {
+        Arc::new(move |_| Ok(free.load(Ordering::Relaxed)))
+    }
+
+    #[test]
+    fn test_refresh_tracks_threshold() {
+        let free = Arc::new(AtomicU64::new(10_000));
+        let monitor = DiskMonitor::new(DiskConfig::new(PathBuf::from("data"), 4_096))
+            .with_probe(fake_probe(Arc::clone(&free)));
+
+        assert_eq!(monitor.refresh().unwrap(), 10_000);
+        assert!(!monitor.is_low());
+        assert!(monitor.check().is_ok());
+
+        free.store(1_024, Ordering::Relaxed);
+        monitor.refresh().unwrap();
+        assert!(monitor.is_low());
+        let err = monitor.check().unwrap_err();
+        assert_eq!(err.code, ErrorCode::Oom);
+        assert!(err.message.starts_with("DISK "));
+        assert_eq!(
+            monitor.status(),
+            DiskStatus {
+                free_bytes: 1_024,
+                min_free_bytes: 4_096,
+                low: true
+            }
+        );
+
+        free.store(8_192, Ordering::Relaxed);
+        monitor.refresh().unwrap();
+        assert!(!monitor.is_low());
+    }
+
+    #[test]
+    fn test_probe_failure_keeps_previous_state() {
+        let failing = Arc::new(AtomicBool::new(false));
+        let probe_failing = Arc::clone(&failing);
+        let monitor = DiskMonitor::new(DiskConfig::new(PathBuf::from("data"), 4_096)).with_probe(
+            Arc::new(move |_| {
+                if probe_failing.load(Ordering::Relaxed) {
+                    Err(io::Error::other("probe failed"))
+                } else {
+                    Ok(0)
+                }
+            }),
+        );
+
+        monitor.refresh().unwrap();
+        assert!(monitor.is_low());
+
+        failing.store(true, Ordering::Relaxed);
+        assert!(monitor.refresh().is_err());
+        assert!(monitor.is_low());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_free_space_of_temp_dir() {
+        let free = free_space(&std::env::temp_dir()).unwrap();
+        assert!(free > 0);
+        assert!(free_space(Path::new("/definitely/not/a/real/dir")).is_err());
+    }
+}
@@ -0,0 +1,87 @@
+//! 数据库内部的事件总线
+//!
+//! [`GeoDatabase`](super::GeoDatabase) 在修改内存之后、释放 collection 写锁之前发布
+//! [`DatabaseEvent`]，地理围栏、指标等副作用各自订阅，互不影响，也不需要写进存储代码。
+//! 每个订阅者有独立的无界队列：慢的订阅者不会拖慢写入，也不会丢失事件；
+//! 同一个 collection 的事件按写入顺序到达。
+//!
+//! AOF 不通过总线写入：AOF 记录必须与内存修改在同一把锁内完成（取消安全、`mark_applied` 的序号），
+//! 异步消费会破坏这两点，所以 AOF 仍然在写路径上同步追加
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use geo::Geometry;
+use tokio::sync::mpsc;
+
+/// 数据库发布的事件，时间戳为 Unix 纳秒
+#[derive(Debug, Clone)]
+pub enum DatabaseEvent {
+    /// SET 写入了对象
+    ObjectSet {
+        collection: String,
+        id: String,
+        /// 修改前的几何体，新对象为 None
+        previous: Option<Geometry>,
+        geometry: Geometry,
+        geojson: String,
+        timestamp: u64,
+    },
+    /// DELETE 删除了对象，或对象过期被删除
+    ObjectDeleted {
+        collection: String,
+        id: String,
+        /// 删除前的几何体和 GeoJSON
+        geometry: Geometry,
+        geojson: String,
+        timestamp: u64,
+    },
+    /// DROP 删除了整个 collection
+    CollectionDropped {
+        collection: String,
+        objects: usize,
+        timestamp: u64,
+    },
+}
+
+impl DatabaseEvent {
+    pub fn collection(&self) -> &str {
+        match self {
+            DatabaseEvent::ObjectSet { collection, .. }
+            | DatabaseEvent::ObjectDeleted { collection, .. }
+            | DatabaseEvent::CollectionDropped { collection, .. } => collection,
+        }
+    }
+}
+
+/// 订阅者接收事件的一端，丢弃即取消订阅
+pub type EventReceiver = mpsc::UnboundedReceiver<Arc<DatabaseEvent>>;
+
+#[derive(Default)]
+pub(crate) struct EventBus {
+    subscribers: Mutex<Vec<mpsc::UnboundedSender<Arc<DatabaseEvent>>>>,
+    /// 订阅者数量，没有订阅者时写路径不构造事件
+    count: AtomicUsize,
+}
+
+impl EventBus {
+    pub(crate) fn subscribe(&self) -> EventReceiver {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.push(sender);
+        self.count.store(subscribers.len(), Ordering::Relaxed);
+        receiver
+    }
+
+    pub(crate) fn has_subscribers(&self) -> bool {
+        self.count.load(Ordering::Relaxed) > 0
+    }
+
+    /// 发送给所有订阅者，移除已经取消订阅的
+    pub(crate) fn publish(&self, event: DatabaseEvent) {
+        let event = Arc::new(event);
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|sender| sender.send(Arc::clone(&event)).is_ok());
+        self.count.store(subscribers.len(), Ordering::Relaxed);
+    }
+}
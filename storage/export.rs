@@ -0,0 +1,200 @@
+//! EXPORT：把一个 collection 写成 GeoJSON FeatureCollection 文件，供 QGIS 等 GIS 工具读取
+//!
+//! 文件只能写到导出目录（`<data_dir>/export`），客户端给出的是文件名而不是路径，
+//! 不能借此覆盖服务器上的其他文件。与快照相同，先写临时文件，完成后重命名
+
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Map, Value};
+use tokio::io::AsyncWriteExt;
+
+use crate::rtree::algorithms::persistence::{temp_path, ChunkedRecord, PersistenceError};
+
+/// 导出目录中 `file` 的完整路径，`file` 不是单纯的文件名时返回错误
+pub(crate) fn export_path(dir: &Path, file: &str) -> Result<PathBuf, String> {
+    let plain = !file.is_empty()
+        && file != "."
+        && file != ".."
+        && !file.contains(['/', '\\'])
+        && Path::new(file).file_name() == Some(std::ffi::OsStr::new(file));
+    if !plain {
+        return Err(format!(
+            "invalid export file name '{}', expected a file name without directories",
+            file
+        ));
+    }
+    Ok(dir.join(file))
+}
+
+/// 对象对应的 Feature，`id` 为对象 id
+///
+/// 存储的是 Feature 时保留它的 properties；FeatureCollection 的每个成员各导出为一个 Feature，
+/// id 都是这个对象的 id。标签放在 properties 的 `tags` 中（properties 已有同名字段时不覆盖）。
+/// STRING 对象没有几何体，不导出
+fn features(record: ChunkedRecord) -> Vec<Value> {
+    if record.text {
+        return Vec::new();
+    }
+    let Ok(value) = serde_json::from_str::<Value>(&record.geojson) else {
+        return Vec::new();
+    };
+    let members = match value.get("type").and_then(Value::as_str) {
+        Some("FeatureCollection") => match value.get("features") {
+            Some(Value::Array(features)) => features.clone(),
+            _ => Vec::new(),
+        },
+        Some("Feature") => vec![value],
+        _ => vec![json!({ "type": "Feature", "geometry": value })],
+    };
+
+    members
+        .into_iter()
+        .map(|mut feature| {
+            let mut properties = match feature.get_mut("properties").map(Value::take) {
+                Some(Value::Object(properties)) => properties,
+                _ => Map::new(),
+            };
+            if !record.tags.is_empty() && !properties.contains_key("tags") {
+                properties.insert("tags".to_string(), json!(record.tags));
+            }
+            feature["id"] = json!(record.id);
+            feature["properties"] = Value::Object(properties);
+            feature
+        })
+        .collect()
+}
+
+/// 写入 FeatureCollection，返回 Feature 数量
+pub(crate) async fn write_feature_collection(
+    path: &Path,
+    records: Vec<ChunkedRecord>,
+) -> Result<usize, PersistenceError> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let temp_path = temp_path(path);
+    let result = write_features(&temp_path, records).await;
+    match result {
+        Ok(count) => {
+            tokio::fs::rename(&temp_path, path).await?;
+            Ok(count)
+        }
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            Err(e)
+        }
+    }
+}
+
+async fn write_features(
+    path: &Path,
+    records: Vec<ChunkedRecord>,
+) -> Result<usize, PersistenceError> {
+    let mut writer = tokio::io::BufWriter::new(tokio::fs::File::create(path).await?);
+    writer
+        .write_all(b"{\"type\":\"FeatureCollection\",\"features\":[")
+        .await?;
+    let mut count = 0;
+    for record in records {
+        for feature in features(record) {
+            if count > 0 {
+                writer.write_all(b",").await?;
+            }
+            writer.write_all(b"\n").await?;
+            writer.write_all(&serde_json::to_vec(&feature)?).await?;
+            count += 1;
+        }
+    }
+    writer.write_all(b"\n]}\n").await?;
+    writer.flush().await?;
+    writer.get_ref().sync_all().await?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: &str, geojson: Value, tags: &[&str]) -> ChunkedRecord {
+        ChunkedRecord {
+            id: id.to_string(),
+            geojson: geojson.to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            expires_at: None,
+            text: false,
+        }
+    }
+
+    #[test]
+    fn test_export_path() {
+        let dir = Path::new("/data/export");
+        assert_eq!(
+            export_path(dir, "fleet.geojson").unwrap(),
+            dir.join("fleet.geojson")
+        );
+        for file in [
+            "",
+            ".",
+            "..",
+            "../fleet.geojson",
+            "a/b.geojson",
+            "a\\b",
+            "/etc/passwd",
+        ] {
+            assert!(export_path(dir, file).is_err(), "{}", file);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_feature_collection() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("export").join("fleet.geojson");
+        let records = vec![
+            record(
+                "truck1",
+                json!({"type": "Point", "coordinates": [1.0, 2.0]}),
+                &["red"],
+            ),
+            record(
+                "zone1",
+                json!({
+                    "type": "Feature",
+                    "properties": {"name": "depot"},
+                    "geometry": {"type": "Point", "coordinates": [3.0, 4.0]}
+                }),
+                &[],
+            ),
+            record(
+                "group",
+                json!({
+                    "type": "FeatureCollection",
+                    "features": [
+                        {"type": "Feature", "properties": null, "geometry": {"type": "Point", "coordinates": [5.0, 6.0]}},
+                        {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [7.0, 8.0]}}
+                    ]
+                }),
+                &[],
+            ),
+            ChunkedRecord {
+                text: true,
+                ..record("note", json!("hello"), &[])
+            },
+        ];
+
+        assert_eq!(write_feature_collection(&path, records).await.unwrap(), 4);
+        assert!(!temp_path(&path).exists());
+
+        let collection: Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(collection["type"], "FeatureCollection");
+        let features = collection["features"].as_array().unwrap();
+        assert_eq!(features.len(), 4);
+        assert_eq!(features[0]["id"], "truck1");
+        assert_eq!(features[0]["geometry"]["type"], "Point");
+        assert_eq!(features[0]["properties"], json!({"tags": ["red"]}));
+        assert_eq!(features[1]["properties"], json!({"name": "depot"}));
+        assert_eq!(features[2]["id"], "group");
+        assert_eq!(features[2]["properties"], json!({}));
+        assert_eq!(features[3]["geometry"]["coordinates"], json!([7.0, 8.0]));
+    }
+}
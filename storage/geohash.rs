@@ -0,0 +1,107 @@
+//! Geohash 编码和解码
+//!
+//! geohash 把经度和纬度交替二分，每 5 位编码为一个 base32 字符，字符越多格子越小。
+//! 移动端可以用它代替 GeoJSON 传递位置：`SET fleet truck1 HASH 9q8yyk8ytpxr` 存储格子的中心点，
+//! `GET fleet truck1 HASH 7` 返回对象质心的 geohash
+
+/// geohash 的最大字符数，12 个字符的格子边长约 3.7 厘米
+pub const MAX_PRECISION: usize = 12;
+
+/// geohash 使用的 base32 字母表（不含 a、i、l、o）
+const ALPHABET: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// 点 (lon, lat) 的 geohash，precision 为字符数
+pub fn encode(lon: f64, lat: f64, precision: usize) -> String {
+    let (mut lon_range, mut lat_range) = ((-180.0, 180.0), (-90.0, 90.0));
+    let mut hash = String::with_capacity(precision);
+    let (mut bits, mut index, mut even) = (0, 0usize, true);
+    while hash.len() < precision {
+        let (range, value): (&mut (f64, f64), f64) = if even {
+            (&mut lon_range, lon)
+        } else {
+            (&mut lat_range, lat)
+        };
+        let mid = (range.0 + range.1) / 2.0;
+        index <<= 1;
+        if value >= mid {
+            index |= 1;
+            range.0 = mid;
+        } else {
+            range.1 = mid;
+        }
+        even = !even;
+        bits += 1;
+        if bits == 5 {
+            hash.push(ALPHABET[index] as char);
+            (bits, index) = (0, 0);
+        }
+    }
+    hash
+}
+
+/// geohash 对应的格子 `[min_lon, min_lat, max_lon, max_lat]`，不区分大小写
+pub fn bounds(hash: &str) -> std::result::Result<[f64; 4], String> {
+    if hash.is_empty() || hash.len() > MAX_PRECISION {
+        return Err(format!(
+            "ERR invalid geohash: expected 1 to {} characters, got '{}'",
+            MAX_PRECISION, hash
+        ));
+    }
+
+    let (mut lon_range, mut lat_range) = ((-180.0, 180.0), (-90.0, 90.0));
+    let mut even = true;
+    for c in hash.bytes() {
+        let index = ALPHABET
+            .iter()
+            .position(|&a| a == c.to_ascii_lowercase())
+            .ok_or_else(|| format!("ERR invalid geohash: unexpected character in '{}'", hash))?;
+        for shift in (0..5).rev() {
+            let range: &mut (f64, f64) = if even { &mut lon_range } else { &mut lat_range };
+            let mid = (range.0 + range.1) / 2.0;
+            if index >> shift & 1 == 1 {
+                range.0 = mid;
+            } else {
+                range.1 = mid;
+            }
+            even = !even;
+        }
+    }
+    Ok([lon_range.0, lat_range.0, lon_range.1, lat_range.1])
+}
+
+/// geohash 格子的中心点 (lon, lat)
+pub fn decode(hash: &str) -> std::result::Result<(f64, f64), String> {
+    let [min_lon, min_lat, max_lon, max_lat] = bounds(hash)?;
+    Ok(((min_lon + max_lon) / 2.0, (min_lat + max_lat) / 2.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode(-5.6, 42.6, 5), "ezs42");
+        assert_eq!(encode(10.40744, 57.64911, 11), "u4pruydqqvj");
+        assert_eq!(encode(0.0, 0.0, 1), "s");
+    }
+
+    #[test]
+    fn test_decode() {
+        let [min_lon, min_lat, max_lon, max_lat] = bounds("ezs42").unwrap();
+        assert!((min_lon + 5.625).abs() < 1e-9 && (max_lon + 5.581_054_687_5).abs() < 1e-9);
+        assert!(
+            (min_lat - 42.583_007_812_5).abs() < 1e-9 && (max_lat - 42.626_953_125).abs() < 1e-9
+        );
+
+        // 解码后再编码得到同一个 geohash，大写也可以解码
+        let (lon, lat) = decode("9Q8YYK8YTPXR").unwrap();
+        assert_eq!(encode(lon, lat, 12), "9q8yyk8ytpxr");
+        assert!((lon + 122.4194).abs() < 1e-4 && (lat - 37.7749).abs() < 1e-4);
+
+        assert!(decode("").unwrap_err().contains("expected 1 to 12"));
+        assert!(decode("ezs4a")
+            .unwrap_err()
+            .contains("unexpected character"));
+    }
+}
@@ -1,4 +1,7 @@
-use geo::Geometry;
+use geo::{
+    Geometry, GeometryCollection, LineString, MultiLineString, MultiPoint, MultiPolygon, Point,
+    Polygon, Rect,
+};
 use geojson::GeoJson;
 
 // OLD CODE - NOT USED:
@@ -24,8 +27,10 @@ use geojson::GeoJson;
 // }
 
 /// 将 GeoJSON 字符串转为 geo::Geometry<f64>
-/// 支持 GeoJSON 类型：Geometry 和 Feature
-pub(crate) fn geojson_to_geometry(geojson_str: &str) -> crate::Result<Geometry<f64>> {
+/// 支持 GeoJSON 类型：Geometry 和 Feature（取 Feature 的 geometry，properties 由调用方随文档保存）
+///
+/// FeatureCollection 包含多个对象，不能存为一个对象，错误信息提示改用 IMPORT 逐个写入
+pub fn geojson_to_geometry(geojson_str: &str) -> crate::Result<Geometry<f64>> {
     // 解析 GeoJSON 字符串
     let geojson = geojson_str.parse::<GeoJson>()?;
 
@@ -35,10 +40,31 @@ pub(crate) fn geojson_to_geometry(geojson_str: &str) -> crate::Result<Geometry<f
             let geometry = f.geometry.ok_or("Feature 没有 geometry 字段")?;
             Ok(geometry.try_into()?)
         }
-        _ => Err("仅支持 GeoJSON Geometry 和 Feature 类型".into()),
+        GeoJson::FeatureCollection(_) => Err(
+            "FeatureCollection 包含多个对象，不能存为一个对象，请用 IMPORT collection OBJECT 逐个写入"
+                .into(),
+        ),
     }
 }
 
+/// 只提取 Feature 的 properties，几何体在反序列化时直接跳过
+#[derive(serde::Deserialize)]
+struct PropertiesOnly {
+    #[serde(default)]
+    properties: Option<serde_json::Map<String, serde_json::Value>>,
+    #[serde(default, rename = "geometry")]
+    _geometry: serde::de::IgnoredAny,
+}
+
+/// 读取 GeoJSON Feature 的 properties，裸几何体或解析失败时返回 None
+pub(crate) fn geojson_properties(
+    geojson_str: &str,
+) -> Option<serde_json::Map<String, serde_json::Value>> {
+    serde_json::from_str::<PropertiesOnly>(geojson_str)
+        .ok()
+        .and_then(|feature| feature.properties)
+}
+
 // fn geometry_from_geojson_geometry(geom: geojson::Geometry) -> Result<Geometry<f64>> {
 //     match geom.value {
 //         GeoJsonValue::Point(coords) => {
@@ -268,6 +294,14 @@ pub fn geometry_to_geojson(geometry: &Geometry<f64>) -> serde_json::Value {
                 "coordinates": coords
             })
         }
+        Geometry::GeometryCollection(collection) => {
+            let geometries: Vec<serde_json::Value> =
+                collection.iter().map(geometry_to_geojson).collect();
+            json!({
+                "type": "GeometryCollection",
+                "geometries": geometries
+            })
+        }
         _ => {
             // 对于其他几何类型，返回一个占位符
             json!({
@@ -278,11 +312,261 @@ pub fn geometry_to_geojson(geometry: &Geometry<f64>) -> serde_json::Value {
     }
 }
 
+/// 取出对象几何体的第 `index` 个部分（从 0 开始）的 GeoJSON
+///
+/// GeometryCollection 的部分是 `geometries` 中的元素，其他几何体只有一个部分（自身）；
+/// Feature 取其 geometry。序号超出范围时返回 `Err(部分数量)`
+pub fn geojson_part(geojson_str: &str, index: usize) -> std::result::Result<String, usize> {
+    use serde_json::Value;
+
+    let mut geometry = match serde_json::from_str::<Value>(geojson_str) {
+        Ok(mut value) if value["type"] == "Feature" => value["geometry"].take(),
+        Ok(value) => value,
+        Err(_) => return Err(0),
+    };
+
+    if geometry["type"] == "GeometryCollection" {
+        let Value::Array(parts) = geometry["geometries"].take() else {
+            return Err(0);
+        };
+        let count = parts.len();
+        return parts
+            .into_iter()
+            .nth(index)
+            .map(|part| part.to_string())
+            .ok_or(count);
+    }
+
+    match index {
+        0 if geometry.is_object() => Ok(geometry.to_string()),
+        _ => Err(geometry.is_object() as usize),
+    }
+}
+
+/// 把几何体裁剪到矩形范围内（RENDER 的瓦片输出），与范围没有公共部分时返回 None
+///
+/// 点只保留范围内（包括边界上）的点；线和面用布尔运算求与矩形的交集，只与边界接触的部分被丢弃。
+/// GeometryCollection 逐个裁剪成员，去掉裁剪后为空的成员。裁剪结果只剩一个部分时返回单个几何体，
+/// 例如穿过瓦片两次的线返回 MultiLineString，只穿过一次时返回 LineString
+pub fn clip_to_rect(geometry: &Geometry<f64>, rect: &Rect<f64>) -> Option<Geometry<f64>> {
+    let inside = |point: &Point<f64>| {
+        (rect.min().x..=rect.max().x).contains(&point.x())
+            && (rect.min().y..=rect.max().y).contains(&point.y())
+    };
+    let polygons = |polygons: Vec<Polygon<f64>>| clip_polygons(rect, &MultiPolygon::new(polygons));
+
+    match geometry {
+        Geometry::Point(point) => inside(point).then_some(Geometry::Point(*point)),
+        Geometry::MultiPoint(points) => {
+            let mut points: Vec<Point<f64>> = points
+                .iter()
+                .filter(|point| inside(point))
+                .copied()
+                .collect();
+            match points.len() {
+                0 => None,
+                1 => Some(Geometry::Point(points.remove(0))),
+                _ => Some(Geometry::MultiPoint(MultiPoint::new(points))),
+            }
+        }
+        Geometry::Line(line) => clip_lines(
+            rect,
+            &MultiLineString::new(vec![LineString::new(vec![line.start, line.end])]),
+        ),
+        Geometry::LineString(line) => clip_lines(rect, &MultiLineString::new(vec![line.clone()])),
+        Geometry::MultiLineString(lines) => clip_lines(rect, lines),
+        Geometry::Polygon(polygon) => polygons(vec![polygon.clone()]),
+        Geometry::MultiPolygon(multi_polygon) => clip_polygons(rect, multi_polygon),
+        Geometry::Rect(other) => polygons(vec![other.to_polygon()]),
+        Geometry::Triangle(triangle) => polygons(vec![triangle.to_polygon()]),
+        Geometry::GeometryCollection(collection) => {
+            let members: Vec<Geometry<f64>> = collection
+                .iter()
+                .filter_map(|member| clip_to_rect(member, rect))
+                .collect();
+            (!members.is_empty())
+                .then(|| Geometry::GeometryCollection(GeometryCollection::new_from(members)))
+        }
+    }
+}
+
+/// 线与矩形的交集
+fn clip_lines(rect: &Rect<f64>, lines: &MultiLineString<f64>) -> Option<Geometry<f64>> {
+    use geo::BooleanOps;
+
+    let mut clipped: Vec<LineString<f64>> = rect
+        .to_polygon()
+        .clip(lines, false)
+        .into_iter()
+        .filter(|line| line.0.len() >= 2)
+        .collect();
+    match clipped.len() {
+        0 => None,
+        1 => Some(Geometry::LineString(clipped.remove(0))),
+        _ => Some(Geometry::MultiLineString(MultiLineString::new(clipped))),
+    }
+}
+
+/// 面与矩形的交集
+fn clip_polygons(rect: &Rect<f64>, polygons: &MultiPolygon<f64>) -> Option<Geometry<f64>> {
+    use geo::BooleanOps;
+
+    let mut clipped = rect.to_polygon().intersection(polygons);
+    match clipped.0.len() {
+        0 => None,
+        1 => Some(Geometry::Polygon(clipped.0.remove(0))),
+        _ => Some(Geometry::MultiPolygon(clipped)),
+    }
+}
+
+/// 输出坐标允许的最大小数位数（f64 的有效位数约为 15~17 位）
+pub const MAX_COORDINATE_PRECISION: u32 = 15;
+
+/// 把 GeoJSON 中的坐标四舍五入到 `decimals` 位小数（6 位约 11cm）
+///
+/// 只处理 `coordinates` 和 `bbox`，包括 Feature、FeatureCollection 和 GeometryCollection 中嵌套的几何体；
+/// `properties` 原样保留。无法解析的输入原样返回
+pub fn round_coordinates(geojson_str: &str, decimals: u32) -> String {
+    let Ok(mut value) = serde_json::from_str::<serde_json::Value>(geojson_str) else {
+        return geojson_str.to_string();
+    };
+    let scale = 10f64.powi(decimals.min(MAX_COORDINATE_PRECISION) as i32);
+    round_members(&mut value, scale);
+    value.to_string()
+}
+
+fn round_members(value: &mut serde_json::Value, scale: f64) {
+    use serde_json::Value;
+
+    match value {
+        Value::Object(object) => {
+            for (key, member) in object.iter_mut() {
+                match key.as_str() {
+                    "coordinates" | "bbox" => round_numbers(member, scale),
+                    "properties" => {}
+                    _ => round_members(member, scale),
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| round_members(item, scale)),
+        _ => {}
+    }
+}
+
+fn round_numbers(value: &mut serde_json::Value, scale: f64) {
+    use serde_json::Value;
+
+    match value {
+        Value::Number(number) => {
+            if let Some(rounded) = number
+                .as_f64()
+                .map(|n| (n * scale).round() / scale)
+                .and_then(serde_json::Number::from_f64)
+            {
+                *number = rounded;
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| round_numbers(item, scale)),
+        _ => {}
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn test_clip_to_rect() {
+        use geo::{Area, Coord};
+
+        let rect = Rect::new(Coord { x: 0.0, y: 0.0 }, Coord { x: 10.0, y: 10.0 });
+        let clip = |geojson: serde_json::Value| {
+            clip_to_rect(&geojson_to_geometry(&geojson.to_string()).unwrap(), &rect)
+        };
+
+        // 点：边界上的点保留
+        assert!(clip(json!({"type": "Point", "coordinates": [10.0, 5.0]})).is_some());
+        assert!(clip(json!({"type": "Point", "coordinates": [11.0, 5.0]})).is_none());
+        let points = clip(json!({"type": "MultiPoint", "coordinates": [[1.0, 1.0], [20.0, 1.0]]}));
+        assert!(matches!(points, Some(Geometry::Point(_))));
+
+        // 线：穿过范围两次时得到两段
+        let line = clip(json!({
+            "type": "LineString",
+            "coordinates": [[-5.0, 5.0], [5.0, 5.0]]
+        }));
+        let Some(Geometry::LineString(line)) = line else {
+            panic!("expected a LineString");
+        };
+        assert_eq!(line.0.first().unwrap().x.min(line.0.last().unwrap().x), 0.0);
+        let zigzag = clip(json!({
+            "type": "LineString",
+            "coordinates": [[-5.0, 2.0], [5.0, 2.0], [5.0, 20.0], [5.0, 15.0], [-5.0, 8.0], [5.0, 8.0]]
+        }));
+        assert!(matches!(zigzag, Some(Geometry::MultiLineString(lines)) if lines.0.len() == 2));
+
+        // 面：只保留范围内的部分
+        let polygon = clip(json!({
+            "type": "Polygon",
+            "coordinates": [[[5.0, 5.0], [15.0, 5.0], [15.0, 15.0], [5.0, 15.0], [5.0, 5.0]]]
+        }));
+        let Some(Geometry::Polygon(polygon)) = polygon else {
+            panic!("expected a Polygon");
+        };
+        assert!((polygon.unsigned_area() - 25.0).abs() < 1e-9);
+        assert!(clip(json!({
+            "type": "Polygon",
+            "coordinates": [[[20.0, 20.0], [30.0, 20.0], [30.0, 30.0], [20.0, 20.0]]]
+        }))
+        .is_none());
+
+        // GeometryCollection 去掉范围外的成员
+        let collection = clip(json!({
+            "type": "GeometryCollection",
+            "geometries": [
+                {"type": "Point", "coordinates": [1.0, 1.0]},
+                {"type": "Point", "coordinates": [50.0, 50.0]}
+            ]
+        }))
+        .unwrap();
+        assert_eq!(
+            geometry_to_geojson(&collection),
+            json!({
+                "type": "GeometryCollection",
+                "geometries": [{"type": "Point", "coordinates": [1.0, 1.0]}]
+            })
+        );
+    }
+
+    #[test]
+    fn test_round_coordinates() {
+        let feature = json!({
+            "type": "Feature",
+            "properties": {"speed": 12.3456789},
+            "bbox": [116.1234567, 39.9876543, 116.1234567, 39.9876543],
+            "geometry": {"type": "Point", "coordinates": [116.1234567, 39.9876543]}
+        });
+        let rounded: serde_json::Value =
+            serde_json::from_str(&round_coordinates(&feature.to_string(), 3)).unwrap();
+        assert_eq!(rounded["geometry"]["coordinates"], json!([116.123, 39.988]));
+        assert_eq!(rounded["bbox"], json!([116.123, 39.988, 116.123, 39.988]));
+        assert_eq!(rounded["properties"]["speed"], json!(12.3456789));
+
+        let collection = json!({
+            "type": "GeometryCollection",
+            "geometries": [{"type": "LineString", "coordinates": [[1.55, 2.0], [3.0, 4.44]]}]
+        });
+        let rounded: serde_json::Value =
+            serde_json::from_str(&round_coordinates(&collection.to_string(), 1)).unwrap();
+        assert_eq!(
+            rounded["geometries"][0]["coordinates"],
+            json!([[1.6, 2.0], [3.0, 4.4]])
+        );
+
+        assert_eq!(round_coordinates("not json", 2), "not json");
+    }
+
     #[test]
     fn test_point_conversion() {
         let point_json = json!({
@@ -396,4 +680,27 @@ mod tests {
         let result = geojson_to_geometry(&invalid_json.to_string());
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_feature_geojson() {
+        let point = json!({"type": "Point", "coordinates": [1.0, 2.0]});
+        let feature =
+            json!({"type": "Feature", "properties": {"name": "depot"}, "geometry": point});
+        assert!(matches!(
+            geojson_to_geometry(&feature.to_string()).unwrap(),
+            Geometry::Point(_)
+        ));
+        assert_eq!(
+            geojson_properties(&feature.to_string()).unwrap()["name"],
+            "depot"
+        );
+
+        let empty = json!({"type": "Feature", "properties": {}, "geometry": null});
+        let error = geojson_to_geometry(&empty.to_string()).unwrap_err();
+        assert_eq!(error.to_string(), "Feature 没有 geometry 字段");
+
+        let collection = json!({"type": "FeatureCollection", "features": [feature]});
+        let error = geojson_to_geometry(&collection.to_string()).unwrap_err();
+        assert!(error.to_string().contains("IMPORT collection OBJECT"));
+    }
 }
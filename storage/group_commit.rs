@@ -0,0 +1,124 @@
+//! AOF 组提交
+//!
+//! Always 策略下每条记录追加后 fsync，写入吞吐受限于磁盘的 fsync 延迟。启用组提交后，
+//! 写命令只把记录写入 [`AofWriter`] 的缓冲区，回复之前调用 [`GroupCommit::wait`]：
+//! 组提交任务被唤醒后等待一个窗口，收集这段时间内并发写入的记录，一次 write + fsync
+//! 之后通知所有等待的命令。fsync 在释放写入器之后执行，期间新的写入继续追加，由下一次 fsync 覆盖
+//!
+//! 任务在第一次等待时启动，数据库释放后退出
+
+use std::sync::{Arc, Once, Weak};
+use std::time::{Duration, Instant};
+
+use tokio::sync::{watch, Mutex, Notify};
+
+use crate::rtree::algorithms::aof::AofWriter;
+use crate::Result;
+
+/// 最近一次组提交的结果
+#[derive(Debug, Clone, Default)]
+struct SyncState {
+    /// 已经落盘的最大序列号
+    synced_seq: u64,
+    /// 完成的组提交次数
+    attempts: u64,
+    /// 最近一次 fsync 失败的原因，之后成功时清除
+    error: Option<String>,
+}
+
+/// 组提交的等待端和后台任务
+pub(crate) struct GroupCommit {
+    window: Duration,
+    wake: Notify,
+    state: watch::Sender<SyncState>,
+    started: Once,
+}
+
+impl GroupCommit {
+    pub(crate) fn new(window: Duration) -> Self {
+        Self {
+            window,
+            wake: Notify::new(),
+            state: watch::Sender::new(SyncState::default()),
+            started: Once::new(),
+        }
+    }
+
+    /// 等待序列号不大于 `seq` 的记录落盘，开始等待之后的一次 fsync 失败时返回错误
+    ///
+    /// 之前的失败不影响新的等待：唤醒任务重试一次
+    pub(crate) async fn wait(
+        self: &Arc<Self>,
+        writer: &Arc<Mutex<AofWriter>>,
+        seq: u64,
+    ) -> Result<()> {
+        self.started.call_once(|| {
+            tokio::spawn(Arc::clone(self).run(Arc::downgrade(writer)));
+        });
+
+        let mut state = self.state.subscribe();
+        let since = state.borrow().attempts;
+        loop {
+            {
+                let current = state.borrow_and_update();
+                if current.synced_seq >= seq {
+                    return Ok(());
+                }
+                if let (true, Some(error)) = (current.attempts > since, &current.error) {
+                    return Err(format!("AOF fsync failed: {}", error).into());
+                }
+            }
+            // 任务正在 fsync 时保留一次唤醒，完成后立即开始下一组
+            self.wake.notify_one();
+            if state.changed().await.is_err() {
+                return Err("AOF group commit stopped".into());
+            }
+        }
+    }
+
+    /// 组提交任务：每次唤醒后等待一个窗口，再 fsync 期间写入的所有记录
+    async fn run(self: Arc<Self>, writer: Weak<Mutex<AofWriter>>) {
+        loop {
+            self.wake.notified().await;
+            if !self.window.is_zero() {
+                tokio::time::sleep(self.window).await;
+            }
+            let Some(writer) = writer.upgrade() else {
+                return;
+            };
+
+            let prepared = writer.lock().await.prepare_group_sync();
+            let result = match prepared {
+                Ok(Some((file, seq))) => {
+                    let start = Instant::now();
+                    match tokio::task::spawn_blocking(move || file.sync_data()).await {
+                        Ok(Ok(())) => {
+                            let mut writer = writer.lock().await;
+                            writer.complete_group_sync(seq, start.elapsed());
+                            Ok(writer.synced_seq())
+                        }
+                        Ok(Err(e)) => Err(e.to_string()),
+                        Err(e) => Err(e.to_string()),
+                    }
+                }
+                // 降级或 BGREWRITEAOF 已经同步了这些记录
+                Ok(None) => Ok(writer.lock().await.synced_seq()),
+                Err(e) => Err(e.to_string()),
+            };
+
+            self.state.send_modify(|state| {
+                state.attempts += 1;
+                match result {
+                    Ok(seq) => {
+                        state.synced_seq = state.synced_seq.max(seq);
+                        state.error = None;
+                    }
+                    Err(e) => {
+                        tracing::error!("AOF group commit fsync failed: {}", e);
+                        state.error = Some(e);
+                    }
+                }
+            });
+        }
+    }
+}
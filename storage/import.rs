@@ -0,0 +1,113 @@
+//! IMPORT：把 GeoJSON FeatureCollection 中的 Feature 批量写入 collection
+//!
+//! 每个 Feature 成为一个对象，存储的是完整的 Feature（保留 properties），
+//! 对象 id 取 Feature 的 `id`，或者 properties 中指定的字段。
+//! 文件与 EXPORT 使用同一个目录，EXPORT 写出的文件可以直接导入
+
+use serde_json::Value;
+
+/// 解析 FeatureCollection，返回 `(对象 id, Feature 的 GeoJSON)`，顺序与文件中相同
+///
+/// `id_field` 为 None 时使用 Feature 的 `id`，否则使用 `properties.<id_field>`；
+/// id 可以是字符串或数字。任何一个 Feature 缺少 id 时返回错误，指出是第几个 Feature
+pub(crate) fn feature_objects(
+    document: &str,
+    id_field: Option<&str>,
+) -> Result<Vec<(String, String)>, String> {
+    let value: Value =
+        serde_json::from_str(document).map_err(|e| format!("invalid GeoJSON: {}", e))?;
+    if value.get("type").and_then(Value::as_str) != Some("FeatureCollection") {
+        return Err("expected a GeoJSON FeatureCollection".to_string());
+    }
+    let Some(Value::Array(features)) = value.get("features") else {
+        return Err("FeatureCollection has no 'features' array".to_string());
+    };
+
+    features
+        .iter()
+        .enumerate()
+        .map(|(index, feature)| {
+            if feature.get("type").and_then(Value::as_str) != Some("Feature") {
+                return Err(format!("feature {} is not a GeoJSON Feature", index));
+            }
+            let id = match id_field {
+                Some(field) => feature.get("properties").and_then(|p| p.get(field)),
+                None => feature.get("id"),
+            };
+            let id = match id {
+                Some(Value::String(id)) if !id.is_empty() => id.clone(),
+                Some(Value::Number(id)) => id.to_string(),
+                _ => {
+                    return Err(match id_field {
+                        Some(field) => format!("feature {} has no '{}' property", index, field),
+                        None => format!("feature {} has no id", index),
+                    })
+                }
+            };
+            Ok((id, feature.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_feature_objects() {
+        let point = json!({"type": "Point", "coordinates": [1.0, 2.0]});
+        let document = json!({
+            "type": "FeatureCollection",
+            "features": [
+                {"type": "Feature", "id": "truck1", "properties": {"code": "a"}, "geometry": point},
+                {"type": "Feature", "id": 7, "properties": {"code": 8}, "geometry": point}
+            ]
+        })
+        .to_string();
+
+        let objects = feature_objects(&document, None).unwrap();
+        let ids: Vec<_> = objects.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["truck1", "7"]);
+        let stored: Value = serde_json::from_str(&objects[0].1).unwrap();
+        assert_eq!(stored["properties"]["code"], "a");
+
+        let objects = feature_objects(&document, Some("code")).unwrap();
+        let ids: Vec<_> = objects.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "8"]);
+
+        let error = feature_objects(&document, Some("name")).unwrap_err();
+        assert_eq!(error, "feature 0 has no 'name' property");
+    }
+
+    #[test]
+    fn test_feature_objects_rejects_other_documents() {
+        let point = json!({"type": "Point", "coordinates": [1.0, 2.0]});
+        assert!(feature_objects("not json", None)
+            .unwrap_err()
+            .starts_with("invalid GeoJSON"));
+        assert_eq!(
+            feature_objects(&point.to_string(), None).unwrap_err(),
+            "expected a GeoJSON FeatureCollection"
+        );
+
+        let document = json!({
+            "type": "FeatureCollection",
+            "features": [{"type": "Feature", "geometry": point}, point]
+        })
+        .to_string();
+        assert_eq!(
+            feature_objects(&document, None).unwrap_err(),
+            "feature 0 has no id"
+        );
+
+        let document = json!({"type": "FeatureCollection", "features": [point]}).to_string();
+        assert_eq!(
+            feature_objects(&document, None).unwrap_err(),
+            "feature 0 is not a GeoJSON Feature"
+        );
+
+        let empty = json!({"type": "FeatureCollection", "features": []}).to_string();
+        assert!(feature_objects(&empty, None).unwrap().is_empty());
+    }
+}
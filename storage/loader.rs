@@ -0,0 +1,91 @@
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::OnceCell;
+
+/// 加载器返回的一个对象
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadedObject {
+    pub id: String,
+    pub geojson: String,
+    pub tags: Vec<String>,
+}
+
+impl LoadedObject {
+    pub fn new(id: impl Into<String>, geojson: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            geojson: geojson.into(),
+            tags: Vec::new(),
+        }
+    }
+
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+}
+
+/// 加载器返回的 future：`Ok(None)` 表示数据源中也没有这个 collection
+pub type LoadFuture =
+    Pin<Box<dyn Future<Output = crate::Result<Option<Vec<LoadedObject>>>> + Send>>;
+
+/// 用户提供的 collection 加载器，参数是 collection 名称
+///
+/// 嵌入式使用时可以从 S3、PostgreSQL 等外部数据源拉取数据
+pub type CollectionLoader = Arc<dyn Fn(String) -> LoadFuture + Send + Sync>;
+
+/// 一次加载的结果，由同一次加载的所有等待者共享
+pub(crate) type LoadOutcome = std::result::Result<(), String>;
+
+/// 读穿透（read-through）：访问不存在的 collection 时调用加载器填充，每个 collection 只加载一次
+///
+/// 同一个 collection 的并发访问共享同一次加载（single-flight）：第一个请求执行加载器，
+/// 其余请求等待它的结果。发起加载的请求被取消时，由某个等待者接着执行。
+/// 加载成功（包括数据源中不存在）后不再调用加载器，即使之后 collection 被 DROP；
+/// 加载失败不做记录，下次访问重试
+pub(crate) struct ReadThrough {
+    pub(crate) loader: CollectionLoader,
+    /// 正在进行的加载
+    flights: Mutex<HashMap<String, Arc<OnceCell<LoadOutcome>>>>,
+    /// 已经加载过的 collection
+    loaded: Mutex<HashSet<String>>,
+}
+
+impl ReadThrough {
+    pub(crate) fn new(loader: CollectionLoader) -> Self {
+        Self {
+            loader,
+            flights: Mutex::new(HashMap::new()),
+            loaded: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub(crate) fn is_loaded(&self, collection_id: &str) -> bool {
+        self.loaded.lock().unwrap().contains(collection_id)
+    }
+
+    /// 获取（或创建）collection 当前的加载
+    pub(crate) fn flight(&self, collection_id: &str) -> Arc<OnceCell<LoadOutcome>> {
+        Arc::clone(
+            self.flights
+                .lock()
+                .unwrap()
+                .entry(collection_id.to_string())
+                .or_default(),
+        )
+    }
+
+    /// 加载结束：成功时记为已加载；无论成败都移除这次加载，之后的访问不再等待它
+    pub(crate) fn finish(&self, collection_id: &str, outcome: &LoadOutcome) {
+        if outcome.is_ok() {
+            self.loaded
+                .lock()
+                .unwrap()
+                .insert(collection_id.to_string());
+        }
+        self.flights.lock().unwrap().remove(collection_id);
+    }
+}
@@ -0,0 +1,129 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// 锁文件名，位于数据目录下
+pub const LOCK_FILE_NAME: &str = "spatio.lock";
+
+/// 数据目录加锁失败
+#[derive(Debug, thiserror::Error)]
+pub enum DataDirLockError {
+    #[error(
+        "data directory '{}' is already in use by another Spatio server (pid {}); stop it or use a different data_dir",
+        .dir.display(),
+        .pid.map_or_else(|| "unknown".to_string(), |pid| pid.to_string())
+    )]
+    Locked { dir: PathBuf, pid: Option<u32> },
+    #[error("failed to lock data directory '{}': {source}", .dir.display())]
+    Io {
+        dir: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+/// 数据目录的独占锁
+///
+/// 在 `<dir>/spatio.lock` 上加操作系统级别的独占锁（Unix 为 flock，Windows 为 LockFileEx），
+/// 并写入当前进程 PID 便于排查。进程退出（包括崩溃）时锁由操作系统自动释放，
+/// 不会因为残留的锁文件导致无法启动
+#[derive(Debug)]
+pub struct DataDirLock {
+    _file: File,
+    path: PathBuf,
+}
+
+impl DataDirLock {
+    /// 获取数据目录的独占锁，目录已被其他实例锁定时返回 [`DataDirLockError::Locked`]
+    pub fn acquire(dir: &Path) -> Result<Self, DataDirLockError> {
+        let io_error = |source| DataDirLockError::Io {
+            dir: dir.to_path_buf(),
+            source,
+        };
+        let path = dir.join(LOCK_FILE_NAME);
+
+        // 不截断：加锁失败时需要读出持有者的 PID
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(io_error)?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(std::fs::TryLockError::WouldBlock) => {
+                return Err(DataDirLockError::Locked {
+                    dir: dir.to_path_buf(),
+                    pid: read_pid(&mut file),
+                });
+            }
+            Err(std::fs::TryLockError::Error(e)) => return Err(io_error(e)),
+        }
+
+        file.set_len(0).map_err(io_error)?;
+        file.seek(SeekFrom::Start(0)).map_err(io_error)?;
+        writeln!(file, "{}", std::process::id()).map_err(io_error)?;
+        file.sync_all().map_err(io_error)?;
+
+        Ok(Self { _file: file, path })
+    }
+
+    /// 锁文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// 读取锁文件中记录的 PID（Windows 上被锁定的文件可能无法读取，此时返回 None）
+fn read_pid(file: &mut File) -> Option<u32> {
+    let mut content = String::new();
+    file.read_to_string(&mut content).ok()?;
+    content.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_second_lock_fails_with_owner_pid() {
+        let temp_dir = TempDir::new().unwrap();
+        let lock = DataDirLock::acquire(temp_dir.path()).unwrap();
+        assert_eq!(lock.path(), temp_dir.path().join(LOCK_FILE_NAME));
+
+        let err = DataDirLock::acquire(temp_dir.path()).unwrap_err();
+        match &err {
+            DataDirLockError::Locked { pid, .. } => {
+                if cfg!(unix) {
+                    assert_eq!(*pid, Some(std::process::id()));
+                }
+            }
+            other => panic!("expected Locked, got {:?}", other),
+        }
+        assert!(err.to_string().contains("already in use"));
+    }
+
+    #[test]
+    fn test_lock_released_on_drop_and_stale_file_ignored() {
+        let temp_dir = TempDir::new().unwrap();
+        // 上一次运行崩溃后残留的锁文件不影响启动
+        std::fs::write(temp_dir.path().join(LOCK_FILE_NAME), "99999\n").unwrap();
+
+        let lock = DataDirLock::acquire(temp_dir.path()).unwrap();
+        let content = std::fs::read_to_string(lock.path()).unwrap();
+        assert_eq!(content.trim(), std::process::id().to_string());
+        drop(lock);
+
+        DataDirLock::acquire(temp_dir.path()).unwrap();
+    }
+
+    #[test]
+    fn test_missing_directory_is_io_error() {
+        let temp_dir = TempDir::new().unwrap();
+        let err = DataDirLock::acquire(&temp_dir.path().join("missing")).unwrap_err();
+        assert!(matches!(err, DataDirLockError::Io { .. }));
+    }
+}
@@ -1,8 +1,38 @@
+pub mod background;
+pub mod clock;
+pub mod cold;
+pub mod disk;
+pub mod events;
+pub mod export;
 pub mod geo_utils;
+pub mod geohash;
 pub mod geometry_utils;
+pub mod group_commit;
+pub mod import;
+pub mod loader;
+pub mod lock;
+pub mod md5;
+pub mod namespace;
+pub mod pattern;
+pub mod rewrite;
+pub mod rfc7946;
+pub mod snapshot;
+pub mod stats;
 #[allow(clippy::module_inception)]
 pub mod storage;
 
+pub use clock::{Clock, MockClock, SharedClock, SystemClock};
+pub use cold::UnloadConfig;
+pub use disk::{DiskConfig, DiskMonitor, DiskStatus};
+pub use events::{DatabaseEvent, EventReceiver};
 pub use geo_utils::string_to_data_id;
 pub use geometry_utils::geometries_intersect;
-pub use storage::GeoDatabase;
+pub use loader::{CollectionLoader, LoadFuture, LoadedObject};
+pub use lock::{DataDirLock, DataDirLockError};
+pub use namespace::Namespace;
+pub use pattern::{glob_match, is_glob_pattern, GlobPattern};
+pub use snapshot::SnapshotSummary;
+pub use stats::{MinuteStats, StatEvent};
+pub use storage::{
+    CollectionStats, DatabaseStats, GeoDatabase, PersistenceInfo, RecoveryReport, Ttl,
+};
@@ -0,0 +1,94 @@
+//! SELECT 选择的命名空间
+//!
+//! 多个应用共享一个服务器时，各自 `SELECT n` 到不同的命名空间，collection 名称互不冲突。
+//! 命名空间只是 collection 名称的前缀：命名空间 0（默认）使用原始名称，命名空间 n 中的
+//! `fleet` 存储为 `@n:fleet`，AOF、快照和后台任务因此不需要区分命名空间。
+//! 以 `@数字:` 开头的名称保留给命名空间，客户端不能直接使用
+
+/// 命名空间前缀的起始字符
+const PREFIX: char = '@';
+
+/// 命名空间前缀的结束字符
+const SEPARATOR: char = ':';
+
+/// 一个命名空间，0 为默认命名空间
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Namespace(u32);
+
+impl Namespace {
+    pub fn new(index: u32) -> Self {
+        Self(index)
+    }
+
+    pub fn index(self) -> u32 {
+        self.0
+    }
+
+    /// 客户端使用的 collection 名称在存储中的名称
+    pub fn scope(self, name: &str) -> String {
+        match self.0 {
+            0 => name.to_string(),
+            index => format!("{}{}{}{}", PREFIX, index, SEPARATOR, name),
+        }
+    }
+
+    /// 存储中的名称属于这个命名空间时返回客户端使用的名称
+    pub fn unscope(self, stored: &str) -> Option<&str> {
+        match split(stored) {
+            Some((index, name)) if index == self.0 => Some(name),
+            Some(_) => None,
+            None if self.0 == 0 => Some(stored),
+            None => None,
+        }
+    }
+}
+
+/// 名称是否以保留的命名空间前缀开头
+pub fn is_reserved(name: &str) -> bool {
+    split(name).is_some()
+}
+
+/// 去掉名称的命名空间前缀，用于在事件等输出中显示客户端使用的名称
+pub fn unscoped(stored: &str) -> &str {
+    split(stored).map_or(stored, |(_, name)| name)
+}
+
+/// 拆分 `@n:name` 为命名空间和名称
+fn split(stored: &str) -> Option<(u32, &str)> {
+    let rest = stored.strip_prefix(PREFIX)?;
+    let (index, name) = rest.split_once(SEPARATOR)?;
+    if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((index.parse().ok()?, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_namespace_scope() {
+        let default = Namespace::default();
+        assert_eq!(default.scope("fleet"), "fleet");
+        assert_eq!(default.unscope("fleet"), Some("fleet"));
+        assert_eq!(default.unscope("@2:fleet"), None);
+
+        let tenant = Namespace::new(2);
+        assert_eq!(tenant.scope("fleet"), "@2:fleet");
+        assert_eq!(tenant.unscope("@2:fleet"), Some("fleet"));
+        assert_eq!(tenant.unscope("fleet"), None);
+        assert_eq!(tenant.unscope("@3:fleet"), None);
+        assert_eq!(
+            tenant.unscope(&tenant.scope("gps:today")),
+            Some("gps:today")
+        );
+
+        assert!(is_reserved("@1:fleet"));
+        assert!(!is_reserved("@fleet:1"));
+        assert!(!is_reserved("@:fleet"));
+        assert!(!is_reserved("fleet"));
+        assert_eq!(unscoped("@12:fleet"), "fleet");
+        assert_eq!(unscoped("@admin:fleet"), "@admin:fleet");
+    }
+}
@@ -0,0 +1,217 @@
+//! glob 模式匹配
+//!
+//! 语法与 Redis 的 KEYS 相同：`*` 匹配任意长度（包括空）的字符，`?` 匹配单个字符，
+//! `[abc]`、`[a-z]` 匹配字符类中的一个字符，`[^a]` 或 `[!a]` 匹配不在类中的字符，
+//! `\` 转义下一个字符（例如 `\*` 只匹配 `*`）。按 Unicode 字符而不是字节匹配，
+//! 没有结束 `]` 的 `[` 按普通字符处理
+
+/// 是否包含通配符（`*`、`?` 或 `[`）
+pub fn is_glob_pattern(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+/// glob 匹配，见 [`GlobPattern`]
+///
+/// 用于按名称模式批量管理 collection（例如 `DROP gps:2024-01-*`）；
+/// 同一个模式匹配大量文本时先用 [`GlobPattern::new`] 解析一次
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    GlobPattern::new(pattern).matches(text)
+}
+
+/// 解析后的 glob 模式
+#[derive(Debug, Clone)]
+pub struct GlobPattern {
+    tokens: Vec<Token>,
+}
+
+#[derive(Debug, Clone)]
+enum Token {
+    /// `*`
+    Star,
+    /// `?`
+    Any,
+    Char(char),
+    /// `[...]`，ranges 中的每一项是闭区间
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Token {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Token::Star | Token::Any => true,
+            Token::Char(expected) => *expected == c,
+            Token::Class { negated, ranges } => {
+                ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != *negated
+            }
+        }
+    }
+}
+
+impl GlobPattern {
+    pub fn new(pattern: &str) -> Self {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let token = match chars[i] {
+                '*' => Token::Star,
+                '?' => Token::Any,
+                '\\' if i + 1 < chars.len() => {
+                    i += 1;
+                    Token::Char(chars[i])
+                }
+                '[' => match parse_class(&chars[i + 1..]) {
+                    Some((class, len)) => {
+                        i += len;
+                        class
+                    }
+                    None => Token::Char('['),
+                },
+                c => Token::Char(c),
+            };
+            tokens.push(token);
+            i += 1;
+        }
+        Self { tokens }
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        let tokens = &self.tokens;
+        let text: Vec<char> = text.chars().collect();
+
+        let (mut p, mut t) = (0, 0);
+        // 最近一个 `*` 的位置，以及它当前匹配到的文本位置，用于回溯
+        let mut star: Option<(usize, usize)> = None;
+
+        while t < text.len() {
+            match tokens.get(p) {
+                Some(Token::Star) => {
+                    star = Some((p, t));
+                    p += 1;
+                }
+                Some(token) if token.matches(text[t]) => {
+                    p += 1;
+                    t += 1;
+                }
+                _ => match star {
+                    Some((star_p, star_t)) => {
+                        p = star_p + 1;
+                        t = star_t + 1;
+                        star = Some((star_p, star_t + 1));
+                    }
+                    None => return false,
+                },
+            }
+        }
+
+        tokens[p..].iter().all(|token| matches!(token, Token::Star))
+    }
+}
+
+/// 解析 `[` 之后的字符类，返回字符类和消耗的字符数（包括结束的 `]`），没有结束的 `]` 时返回 None
+///
+/// 紧跟在 `[` 或 `[^` 之后的 `]` 是普通字符；类中的 `\` 同样转义下一个字符
+fn parse_class(chars: &[char]) -> Option<(Token, usize)> {
+    let negated = matches!(chars.first(), Some('^' | '!'));
+    let mut i = usize::from(negated);
+    let start = i;
+    let mut ranges = Vec::new();
+
+    loop {
+        let mut lo = *chars.get(i)?;
+        if lo == ']' && i > start {
+            return Some((Token::Class { negated, ranges }, i + 1));
+        }
+        if lo == '\\' {
+            i += 1;
+            lo = *chars.get(i)?;
+        }
+
+        let mut hi = lo;
+        if chars.get(i + 1) == Some(&'-') && chars.get(i + 2).is_some_and(|&c| c != ']') {
+            i += 2;
+            hi = chars[i];
+            if hi == '\\' {
+                i += 1;
+                hi = *chars.get(i)?;
+            }
+        }
+        ranges.push((lo.min(hi), lo.max(hi)));
+        i += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("gps:*", "gps:2024-01-01"));
+        assert!(glob_match("gps:*", "gps:"));
+        assert!(!glob_match("gps:*", "trips:2024-01-01"));
+        assert!(glob_match("*-01-0?", "gps:2024-01-05"));
+        assert!(!glob_match("*-01-0?", "gps:2024-01-15"));
+        assert!(glob_match("a*b*c", "aXXbYYbZc"));
+        assert!(!glob_match("a*b*c", "aXXbYY"));
+        assert!(glob_match("fleet", "fleet"));
+        assert!(!glob_match("fleet", "fleets"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn test_glob_character_classes() {
+        assert!(glob_match("sensor?[0-9]", "sensor_7"));
+        assert!(!glob_match("sensor?[0-9]", "sensor_x"));
+        assert!(glob_match("fleet:[abc]*", "fleet:bus"));
+        assert!(!glob_match("fleet:[abc]*", "fleet:truck"));
+        assert!(glob_match("fleet:[^abc]*", "fleet:truck"));
+        assert!(glob_match("fleet:[!abc]*", "fleet:truck"));
+        assert!(!glob_match("fleet:[^abc]*", "fleet:bus"));
+        // 反向的区间等同于正向
+        assert!(glob_match("v[9-0]", "v5"));
+        // 紧跟在 [ 之后的 ] 和末尾的 - 是普通字符
+        assert!(glob_match("[]x]", "]"));
+        assert!(glob_match("[a-]", "-"));
+        // 没有结束的 [ 按普通字符处理
+        assert!(glob_match("fleet[1", "fleet[1"));
+        assert!(!glob_match("fleet[1", "fleet1"));
+    }
+
+    #[test]
+    fn test_glob_escaping() {
+        assert!(glob_match(r"fleet\*", "fleet*"));
+        assert!(!glob_match(r"fleet\*", "fleets"));
+        assert!(glob_match(r"what\?", "what?"));
+        assert!(!glob_match(r"what\?", "whats"));
+        assert!(glob_match(r"\[1\]", "[1]"));
+        assert!(glob_match(r"[\]\-]", "]"));
+        assert!(glob_match(r"[\]\-]", "-"));
+        assert!(!glob_match(r"[\]\-]", "a"));
+        // 末尾单独的反斜杠匹配它自己
+        assert!(glob_match(r"dir\", r"dir\"));
+    }
+
+    #[test]
+    fn test_glob_unicode() {
+        // ? 和字符类按字符而不是字节匹配
+        assert!(glob_match("车队:?", "车队:京"));
+        assert!(!glob_match("车队:?", "车队:京A"));
+        assert!(glob_match("车队:*", "车队:北京"));
+        assert!(glob_match("[车船]队", "船队"));
+        assert!(glob_match("zone-[α-ω]", "zone-λ"));
+        assert!(!glob_match("zone-[α-ω]", "zone-z"));
+        assert!(glob_match("café*", "café:paris"));
+    }
+
+    #[test]
+    fn test_is_glob_pattern() {
+        assert!(is_glob_pattern("gps:*"));
+        assert!(is_glob_pattern("day-0?"));
+        assert!(is_glob_pattern("sensor[0-9]"));
+        assert!(!is_glob_pattern("fleet"));
+    }
+}
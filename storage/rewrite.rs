@@ -0,0 +1,188 @@
+//! AOF 重写（BGREWRITEAOF 和自动重写）
+//!
+//! 用表示当前数据的最小记录集合替换 AOF：每个对象一条 INSERT 或 STRING（带标签），有 TTL 的对象
+//! 再加一条 EXPIRE，每个围栏频道一条 SETCHAN。过程分三步：
+//!
+//! 1. 在 AOF 锁下刷新缓冲区，记下当前文件长度；
+//! 2. 不持有 AOF 锁，在临时文件中写入重写标记，再逐个 collection 在读锁下复制内容并写入；
+//! 3. 再次拿到 AOF 锁，把第 1 步之后追加的记录复制到临时文件末尾，fsync 后重命名替换 AOF。
+//!
+//! 复制某个 collection 时，写入可能已经追加了序列号更大的记录，这些记录同时出现在复制的内容和
+//! 第 3 步复制的尾部中。每个 collection 只有最后一条记录带有复制时已应用的序列号，
+//! 其余记录不编号（总是重放）：重放时这条记录把 collection 标记为已应用到该序列号，
+//! 尾部中已经包含在复制内容里的记录随之被跳过。
+//!
+//! 重写丢弃了 DROP 等历史记录，早于重写的快照不能再与 AOF 组合使用：启动时比较快照的创建时间和
+//! 重写标记的时间，快照较旧时只重放 AOF。快照和重写不会同时进行，两个时间可以直接比较。
+
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+use crate::rtree::algorithms::aof::{AofCommand, AofFormat};
+use crate::rtree::algorithms::persistence::{temp_path, PersistenceError};
+
+use super::snapshot::{ChannelDefinition, CollectionSink, CollectionSnapshot};
+
+/// 一个 collection 的重写记录
+///
+/// 最后一条记录带有 `applied_seq`，之前的记录不编号
+pub(crate) fn collection_commands(collection: CollectionSnapshot, ts: u64) -> Vec<AofCommand> {
+    let name = collection.name().to_string();
+    let applied_seq = collection.applied_seq();
+
+    let mut commands = Vec::new();
+    for record in collection.into_records() {
+        let command = if record.text {
+            AofCommand::string(name.clone(), record.id.clone(), record.geojson)
+        } else {
+            AofCommand::insert(name.clone(), record.id.clone(), record.geojson)
+        };
+        commands.push(command.with_tags(record.tags).with_timestamp(ts));
+        if let Some(at) = record.expires_at {
+            commands.push(AofCommand::expire(name.clone(), record.id, Some(at)).with_timestamp(ts));
+        }
+    }
+    if let Some(last) = commands.pop() {
+        commands.push(last.with_seq(applied_seq));
+    }
+    commands
+}
+
+/// 重写的 AOF 临时文件，由 `AofWriter::finish_rewrite` 补上尾部并替换 AOF
+pub(crate) struct RewriteFile {
+    writer: tokio::io::BufWriter<tokio::fs::File>,
+    temp_path: PathBuf,
+    /// 记录格式，与 AOF 当前配置的格式相同
+    format: AofFormat,
+    /// 记录的时间戳（Unix 纳秒）
+    ts: u64,
+    objects: usize,
+}
+
+impl RewriteFile {
+    /// 创建临时文件并写入重写标记，`ts` 为重写开始的时刻
+    pub(crate) async fn create(
+        aof_path: &Path,
+        ts: u64,
+        format: AofFormat,
+    ) -> Result<Self, PersistenceError> {
+        let temp_path = temp_path(aof_path);
+        let writer = tokio::io::BufWriter::new(tokio::fs::File::create(&temp_path).await?);
+        let mut file = Self {
+            writer,
+            temp_path,
+            format,
+            ts,
+            objects: 0,
+        };
+        file.write_command(&AofCommand::rewrite(ts)).await?;
+        Ok(file)
+    }
+
+    async fn write_command(&mut self, command: &AofCommand) -> Result<(), PersistenceError> {
+        let mut line = self.format.encode(serde_json::to_string(command)?);
+        line.push('\n');
+        self.writer.write_all(line.as_bytes()).await?;
+        Ok(())
+    }
+
+    /// 写入文件的对象数量
+    pub(crate) fn objects(&self) -> usize {
+        self.objects
+    }
+
+    /// 刷新缓冲区，返回临时文件路径
+    pub(crate) async fn finish(mut self) -> Result<PathBuf, PersistenceError> {
+        self.writer.flush().await?;
+        Ok(self.temp_path)
+    }
+
+    /// 放弃重写，删除临时文件
+    pub(crate) async fn discard(self) {
+        drop(self.writer);
+        let _ = tokio::fs::remove_file(&self.temp_path).await;
+    }
+}
+
+impl CollectionSink for RewriteFile {
+    async fn write_collection(
+        &mut self,
+        collection: CollectionSnapshot,
+    ) -> Result<(), PersistenceError> {
+        let commands = collection_commands(collection, self.ts);
+        for (i, command) in commands.iter().enumerate() {
+            self.write_command(command).await?;
+            if matches!(
+                command,
+                AofCommand::Insert { .. } | AofCommand::String { .. }
+            ) {
+                self.objects += 1;
+            }
+            if (i + 1) % 1024 == 0 {
+                tokio::task::yield_now().await;
+            }
+        }
+        Ok(())
+    }
+
+    /// 频道记录与对象记录一样不编号，加载了快照时跳过；重写开始之后的 SETCHAN/DELCHAN 在尾部中，
+    /// 按顺序重放覆盖这里的定义
+    async fn write_channels(
+        &mut self,
+        channels: Vec<ChannelDefinition>,
+    ) -> Result<(), PersistenceError> {
+        for (name, args) in channels {
+            let command = AofCommand::set_chan(name, args).with_timestamp(self.ts);
+            self.write_command(&command).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtree::RTree;
+    use serde_json::json;
+
+    #[test]
+    fn test_collection_commands() {
+        let mut rtree = RTree::new(4);
+        for id in ["a", "b"] {
+            let point = json!({"type": "Point", "coordinates": [1.0, 2.0]}).to_string();
+            assert!(rtree.insert_geojson(id.to_string(), &point));
+        }
+        rtree.set_tags("a", ["red".to_string()]);
+        rtree.set_expire("b", 5_000);
+        rtree.mark_applied(42);
+
+        let commands = collection_commands(CollectionSnapshot::of("fleet", &rtree), 7);
+        assert_eq!(commands.len(), 3);
+        assert!(commands.iter().all(|cmd| cmd.collection() == "fleet"));
+        assert!(commands.iter().all(|cmd| cmd.timestamp() == 7));
+
+        // 只有最后一条记录带序列号
+        let seqs: Vec<u64> = commands.iter().map(AofCommand::seq).collect();
+        assert_eq!(seqs, vec![0, 0, 42]);
+        assert!(commands.iter().any(|cmd| matches!(
+            cmd,
+            AofCommand::Insert { key, tags, .. } if key == "a" && tags == &["red".to_string()]
+        )));
+        assert!(commands.iter().any(|cmd| matches!(
+            cmd,
+            AofCommand::Expire { key, at: Some(5_000), .. } if key == "b"
+        )));
+
+        // STRING 对象重写为 STRING 记录
+        let mut notes = RTree::new(4);
+        notes.set_string("n".to_string(), "hello".to_string());
+        let commands = collection_commands(CollectionSnapshot::of("notes", &notes), 7);
+        assert!(matches!(
+            &commands[..],
+            [AofCommand::String { key, value, .. }] if key == "n" && value == "hello"
+        ));
+
+        let empty = RTree::new(4);
+        assert!(collection_commands(CollectionSnapshot::of("empty", &empty), 7).is_empty());
+    }
+}
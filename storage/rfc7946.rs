@@ -0,0 +1,516 @@
+//! RFC 7946 严格模式
+//!
+//! 与只接受 RFC 7946 的系统交换数据时，可以为 collection 开启严格模式：
+//! 写入时检查多边形环的方向（外环逆时针、内环顺时针，§3.1.6）、GeometryCollection
+//! 不能嵌套（§3.1.8）以及 bbox 成员（§5），不符合时拒绝写入并逐条列出违规；
+//! 返回时把已有的数据规范化（调整环方向、展开嵌套、去掉无效的 bbox），
+//! 开启严格模式之前写入的对象也能按 RFC 7946 输出
+
+use serde_json::Value;
+use std::fmt;
+
+/// 一处违反 RFC 7946 的地方，`path` 为 JSON 路径，例如 `$.geometry.coordinates[1]`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    /// 多边形外环不是逆时针
+    ExteriorRingClockwise { path: String },
+    /// 多边形内环（洞）不是顺时针
+    InteriorRingCounterclockwise { path: String },
+    /// GeometryCollection 中嵌套了 GeometryCollection
+    NestedGeometryCollection { path: String },
+    /// bbox 成员无效
+    InvalidBbox { path: String, reason: String },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::ExteriorRingClockwise { path } => write!(
+                f,
+                "{}: exterior ring must be counterclockwise (RFC 7946 3.1.6)",
+                path
+            ),
+            Violation::InteriorRingCounterclockwise { path } => write!(
+                f,
+                "{}: interior ring must be clockwise (RFC 7946 3.1.6)",
+                path
+            ),
+            Violation::NestedGeometryCollection { path } => write!(
+                f,
+                "{}: GeometryCollection must not be nested (RFC 7946 3.1.8)",
+                path
+            ),
+            Violation::InvalidBbox { path, reason } => {
+                write!(f, "{}: invalid bbox, {} (RFC 7946 5)", path, reason)
+            }
+        }
+    }
+}
+
+/// 写入严格模式 collection 的 GeoJSON 不符合 RFC 7946
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComplianceError {
+    pub violations: Vec<Violation>,
+}
+
+impl fmt::Display for ComplianceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "GeoJSON is not RFC 7946 compliant: ")?;
+        for (i, violation) in self.violations.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ComplianceError {}
+
+/// 检查 GeoJSON 是否符合 RFC 7946
+///
+/// 不是合法 JSON 时返回 Ok，格式错误由解析几何体时报告
+pub fn validate(geojson: &str) -> Result<(), ComplianceError> {
+    let Ok(value) = serde_json::from_str::<Value>(geojson) else {
+        return Ok(());
+    };
+    let violations = check(&value);
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(ComplianceError { violations })
+    }
+}
+
+/// 找出 GeoJSON 对象中所有违反 RFC 7946 的地方
+pub fn check(value: &Value) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    check_object(value, "$", false, &mut violations);
+    violations
+}
+
+/// 把 GeoJSON 规范化为符合 RFC 7946 的形式，已经符合时原样返回
+///
+/// 反转方向错误的环，把嵌套的 GeometryCollection 展开到外层，去掉无效的 bbox
+pub fn normalize(geojson: &str) -> String {
+    let Ok(mut value) = serde_json::from_str::<Value>(geojson) else {
+        return geojson.to_string();
+    };
+    if normalize_object(&mut value) {
+        value.to_string()
+    } else {
+        geojson.to_string()
+    }
+}
+
+fn check_object(value: &Value, path: &str, in_collection: bool, out: &mut Vec<Violation>) {
+    let Some(object) = value.as_object() else {
+        return;
+    };
+    if let Some(reason) = object.get("bbox").and_then(|bbox| bbox_error(bbox, value)) {
+        out.push(Violation::InvalidBbox {
+            path: path.to_string(),
+            reason,
+        });
+    }
+
+    match object.get("type").and_then(Value::as_str) {
+        Some("Feature") => {
+            if let Some(geometry) = object.get("geometry") {
+                check_object(geometry, &format!("{}.geometry", path), false, out);
+            }
+        }
+        Some("FeatureCollection") => {
+            for (i, feature) in members(value, "features").enumerate() {
+                check_object(feature, &format!("{}.features[{}]", path, i), false, out);
+            }
+        }
+        Some("GeometryCollection") => {
+            if in_collection {
+                out.push(Violation::NestedGeometryCollection {
+                    path: path.to_string(),
+                });
+            }
+            for (i, geometry) in members(value, "geometries").enumerate() {
+                check_object(geometry, &format!("{}.geometries[{}]", path, i), true, out);
+            }
+        }
+        Some("Polygon") => {
+            check_polygon(
+                object.get("coordinates"),
+                &format!("{}.coordinates", path),
+                out,
+            );
+        }
+        Some("MultiPolygon") => {
+            for (i, polygon) in members(value, "coordinates").enumerate() {
+                check_polygon(Some(polygon), &format!("{}.coordinates[{}]", path, i), out);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn check_polygon(rings: Option<&Value>, path: &str, out: &mut Vec<Violation>) {
+    let Some(rings) = rings.and_then(Value::as_array) else {
+        return;
+    };
+    for (i, ring) in rings.iter().enumerate() {
+        let area = signed_area(ring);
+        let path = format!("{}[{}]", path, i);
+        if i == 0 && area < 0.0 {
+            out.push(Violation::ExteriorRingClockwise { path });
+        } else if i > 0 && area > 0.0 {
+            out.push(Violation::InteriorRingCounterclockwise { path });
+        }
+    }
+}
+
+/// 返回 true 表示做了修改
+fn normalize_object(value: &mut Value) -> bool {
+    let mut changed = false;
+    let has_invalid_bbox = value
+        .get("bbox")
+        .is_some_and(|bbox| bbox_error(bbox, value).is_some());
+    let Some(object) = value.as_object_mut() else {
+        return false;
+    };
+    if has_invalid_bbox {
+        object.remove("bbox");
+        changed = true;
+    }
+
+    match object.get("type").and_then(Value::as_str) {
+        Some("Feature") => {
+            if let Some(geometry) = object.get_mut("geometry") {
+                changed |= normalize_object(geometry);
+            }
+        }
+        Some("FeatureCollection") => {
+            if let Some(Value::Array(features)) = object.get_mut("features") {
+                for feature in features {
+                    changed |= normalize_object(feature);
+                }
+            }
+        }
+        Some("GeometryCollection") => {
+            if let Some(Value::Array(geometries)) = object.get_mut("geometries") {
+                let mut flat = Vec::with_capacity(geometries.len());
+                for geometry in geometries.drain(..) {
+                    changed |= flatten_into(geometry, &mut flat);
+                }
+                for geometry in &mut flat {
+                    changed |= normalize_object(geometry);
+                }
+                *geometries = flat;
+            }
+        }
+        Some("Polygon") => {
+            if let Some(rings) = object.get_mut("coordinates") {
+                changed |= rewind_polygon(rings);
+            }
+        }
+        Some("MultiPolygon") => {
+            if let Some(Value::Array(polygons)) = object.get_mut("coordinates") {
+                for rings in polygons {
+                    changed |= rewind_polygon(rings);
+                }
+            }
+        }
+        _ => {}
+    }
+    changed
+}
+
+/// 把嵌套 GeometryCollection 的成员依次放入 `out`，返回 true 表示展开了嵌套
+fn flatten_into(geometry: Value, out: &mut Vec<Value>) -> bool {
+    if geometry.get("type").and_then(Value::as_str) != Some("GeometryCollection") {
+        out.push(geometry);
+        return false;
+    }
+    if let Some(Value::Array(members)) = geometry.get("geometries") {
+        for member in members.clone() {
+            flatten_into(member, out);
+        }
+    }
+    true
+}
+
+fn rewind_polygon(rings: &mut Value) -> bool {
+    let Some(rings) = rings.as_array_mut() else {
+        return false;
+    };
+    let mut changed = false;
+    for (i, ring) in rings.iter_mut().enumerate() {
+        let area = signed_area(ring);
+        if (i == 0 && area < 0.0) || (i > 0 && area > 0.0) {
+            if let Some(positions) = ring.as_array_mut() {
+                positions.reverse();
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+fn members<'a>(value: &'a Value, key: &str) -> impl Iterator<Item = &'a Value> {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+}
+
+/// 环的有向面积（鞋带公式，经纬度平面），逆时针为正
+fn signed_area(ring: &Value) -> f64 {
+    let Some(positions) = ring.as_array() else {
+        return 0.0;
+    };
+    let points: Vec<(f64, f64)> = positions.iter().filter_map(position).collect();
+    points
+        .windows(2)
+        .map(|w| w[0].0 * w[1].1 - w[1].0 * w[0].1)
+        .sum::<f64>()
+        / 2.0
+}
+
+fn position(value: &Value) -> Option<(f64, f64)> {
+    let coords = value.as_array()?;
+    Some((coords.first()?.as_f64()?, coords.get(1)?.as_f64()?))
+}
+
+/// bbox 的问题，没有问题时返回 None
+///
+/// 长度为 4 或 6 的数字数组，纬度南不大于北，经纬度在合法范围内，并且包含对象的所有坐标。
+/// 西边界大于东边界表示跨越反子午线（§5.2）
+fn bbox_error(bbox: &Value, object: &Value) -> Option<String> {
+    let Some(values) = bbox.as_array() else {
+        return Some("must be an array".to_string());
+    };
+    let Some(numbers) = values.iter().map(Value::as_f64).collect::<Option<Vec<_>>>() else {
+        return Some("must contain only numbers".to_string());
+    };
+    let (west, south, east, north) = match numbers.len() {
+        4 => (numbers[0], numbers[1], numbers[2], numbers[3]),
+        6 => {
+            if numbers[2] > numbers[5] {
+                return Some("minimum elevation is greater than maximum".to_string());
+            }
+            (numbers[0], numbers[1], numbers[3], numbers[4])
+        }
+        n => return Some(format!("must have 4 or 6 values, got {}", n)),
+    };
+    if !(-180.0..=180.0).contains(&west) || !(-180.0..=180.0).contains(&east) {
+        return Some("longitude out of range [-180, 180]".to_string());
+    }
+    if !(-90.0..=90.0).contains(&south) || !(-90.0..=90.0).contains(&north) {
+        return Some("latitude out of range [-90, 90]".to_string());
+    }
+    if south > north {
+        return Some("southern latitude is greater than northern".to_string());
+    }
+
+    let mut positions = Vec::new();
+    collect_positions(object, &mut positions);
+    let outside = positions.into_iter().find(|&(lon, lat)| {
+        let lon_inside = if west <= east {
+            (west..=east).contains(&lon)
+        } else {
+            lon >= west || lon <= east
+        };
+        !lon_inside || !(south..=north).contains(&lat)
+    });
+    outside.map(|(lon, lat)| format!("does not contain position [{}, {}]", lon, lat))
+}
+
+/// 收集对象（几何体、Feature 或 FeatureCollection）的所有坐标
+fn collect_positions(value: &Value, out: &mut Vec<(f64, f64)>) {
+    match value.get("type").and_then(Value::as_str) {
+        Some("Feature") => {
+            if let Some(geometry) = value.get("geometry") {
+                collect_positions(geometry, out);
+            }
+        }
+        Some("FeatureCollection") => {
+            for feature in members(value, "features") {
+                collect_positions(feature, out);
+            }
+        }
+        Some("GeometryCollection") => {
+            for geometry in members(value, "geometries") {
+                collect_positions(geometry, out);
+            }
+        }
+        Some(_) => {
+            if let Some(coordinates) = value.get("coordinates") {
+                collect_coordinates(coordinates, out);
+            }
+        }
+        None => {}
+    }
+}
+
+fn collect_coordinates(value: &Value, out: &mut Vec<(f64, f64)>) {
+    if let Some(position) = position(value) {
+        out.push(position);
+    } else if let Some(nested) = value.as_array() {
+        for item in nested {
+            collect_coordinates(item, out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const CCW: &str = "[[0,0],[10,0],[10,10],[0,10],[0,0]]";
+    const CW: &str = "[[0,0],[0,10],[10,10],[10,0],[0,0]]";
+    const HOLE_CW: &str = "[[2,2],[2,4],[4,4],[4,2],[2,2]]";
+    const HOLE_CCW: &str = "[[2,2],[4,2],[4,4],[2,4],[2,2]]";
+
+    fn polygon(rings: &[&str]) -> String {
+        format!(
+            r#"{{"type":"Polygon","coordinates":[{}]}}"#,
+            rings.join(",")
+        )
+    }
+
+    #[test]
+    fn test_winding_order() {
+        assert!(validate(&polygon(&[CCW, HOLE_CW])).is_ok());
+
+        let err = validate(&polygon(&[CW, HOLE_CCW])).unwrap_err();
+        assert_eq!(
+            err.violations,
+            vec![
+                Violation::ExteriorRingClockwise {
+                    path: "$.coordinates[0]".to_string()
+                },
+                Violation::InteriorRingCounterclockwise {
+                    path: "$.coordinates[1]".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            err.to_string(),
+            "GeoJSON is not RFC 7946 compliant: \
+             $.coordinates[0]: exterior ring must be counterclockwise (RFC 7946 3.1.6); \
+             $.coordinates[1]: interior ring must be clockwise (RFC 7946 3.1.6)"
+        );
+
+        let multi = format!(
+            r#"{{"type":"Feature","properties":{{}},"geometry":{{"type":"MultiPolygon","coordinates":[[{}],[{}]]}}}}"#,
+            CCW, CW
+        );
+        assert_eq!(
+            validate(&multi).unwrap_err().violations,
+            vec![Violation::ExteriorRingClockwise {
+                path: "$.geometry.coordinates[1][0]".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_nested_geometry_collection() {
+        let nested = json!({
+            "type": "GeometryCollection",
+            "geometries": [
+                {"type": "Point", "coordinates": [1, 2]},
+                {"type": "GeometryCollection", "geometries": [
+                    {"type": "Point", "coordinates": [3, 4]},
+                    {"type": "GeometryCollection", "geometries": []}
+                ]}
+            ]
+        });
+        assert_eq!(
+            check(&nested),
+            vec![
+                Violation::NestedGeometryCollection {
+                    path: "$.geometries[1]".to_string()
+                },
+                Violation::NestedGeometryCollection {
+                    path: "$.geometries[1].geometries[1]".to_string()
+                },
+            ]
+        );
+
+        // 展开后所有成员位于同一层
+        let normalized: Value = serde_json::from_str(&normalize(&nested.to_string())).unwrap();
+        assert_eq!(
+            normalized["geometries"],
+            json!([
+                {"type": "Point", "coordinates": [1, 2]},
+                {"type": "Point", "coordinates": [3, 4]}
+            ])
+        );
+        assert!(check(&normalized).is_empty());
+    }
+
+    #[test]
+    fn test_bbox_validation() {
+        let point = |bbox: Value| json!({"type": "Point", "coordinates": [1.5, 2.5], "bbox": bbox});
+        assert!(check(&point(json!([1.5, 2.5, 1.5, 2.5]))).is_empty());
+        assert!(check(&point(json!([0, 0, 0, 2, 3, 5]))).is_empty());
+
+        let reason = |bbox: Value| match check(&point(bbox)).as_slice() {
+            [Violation::InvalidBbox { path, reason }] => {
+                assert_eq!(path, "$");
+                reason.clone()
+            }
+            other => panic!("unexpected violations {:?}", other),
+        };
+        assert_eq!(reason(json!([0, 0, 5])), "must have 4 or 6 values, got 3");
+        assert_eq!(reason(json!([0, "0", 5, 5])), "must contain only numbers");
+        assert_eq!(
+            reason(json!([0, 5, 5, 0])),
+            "southern latitude is greater than northern"
+        );
+        assert_eq!(
+            reason(json!([0, 0, 190, 5])),
+            "longitude out of range [-180, 180]"
+        );
+        assert_eq!(
+            reason(json!([2, 0, 5, 5])),
+            "does not contain position [1.5, 2.5]"
+        );
+
+        // 西边界大于东边界时跨越反子午线
+        let fiji = json!({
+            "type": "LineString",
+            "coordinates": [[179.5, -17.0], [-179.5, -17.5]],
+            "bbox": [179.0, -18.0, -179.0, -16.0]
+        });
+        assert!(check(&fiji).is_empty());
+
+        // Feature 的 bbox 覆盖其几何体，无效的 bbox 在输出时去掉
+        let feature = json!({
+            "type": "Feature",
+            "bbox": [5, 5, 6, 6],
+            "properties": {"name": "a"},
+            "geometry": {"type": "Point", "coordinates": [1.5, 2.5]}
+        });
+        assert_eq!(check(&feature).len(), 1);
+        let normalized: Value = serde_json::from_str(&normalize(&feature.to_string())).unwrap();
+        assert!(normalized.get("bbox").is_none());
+        assert_eq!(normalized["properties"]["name"], "a");
+    }
+
+    #[test]
+    fn test_normalize_rewinds_rings() {
+        let normalized = normalize(&polygon(&[CW, HOLE_CCW]));
+        assert!(validate(&normalized).is_ok());
+        let value: Value = serde_json::from_str(&normalized).unwrap();
+        assert_eq!(
+            value["coordinates"][0],
+            serde_json::from_str::<Value>(CCW).unwrap()
+        );
+
+        // 已经符合时原样返回，不重新序列化
+        let compliant = polygon(&[CCW, HOLE_CW]);
+        assert_eq!(normalize(&compliant), compliant);
+        assert_eq!(normalize("not json"), "not json");
+    }
+}
@@ -0,0 +1,429 @@
+//! 时间点快照（SAVE / BGSAVE）
+//!
+//! 一个文件保存所有 collection 的对象（GeoJSON、标签、过期时间）和元数据（R-tree 节点容量、
+//! 已应用的 AOF 序列号），以及围栏频道（SETCHAN）的定义：
+//!
+//! ```text
+//! "SPDB" | 文件头 | collection 头 | 对象块 ... | collection 头 | 对象块 ... | 围栏频道 ... | 结束标记
+//! ```
+//!
+//! 每一项是一帧（4 字节小端长度 + bincode），对象块与分块快照（`RTree::dump_chunked`）使用
+//! 相同的记录。结束标记带有 collection 数量，写了一半的文件在加载时被拒绝；写入时先写临时文件，
+//! fsync 后再重命名，已有的快照不会被破坏。
+//!
+//! 启动时先加载快照，再重放 AOF：每个 collection 保存了快照时已应用的 AOF 序列号，
+//! 序列号不大于它的记录在重放时被跳过，实际只重放快照之后的尾部。围栏频道的记录按名称覆盖，
+//! 在快照的基础上按顺序重放即可。
+//! 各个 collection 分别在自己的读锁下复制，因此快照只在单个 collection 内是一致的
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+
+use crate::rtree::algorithms::persistence::{
+    decode_records, read_frame, sync_parent_dir, temp_path, write_frame, ChunkedRecord,
+    PersistenceError, CHUNKED_VERSION, DEFAULT_CHUNK_SIZE,
+};
+use crate::rtree::RTree;
+
+use super::background::BackgroundJob;
+
+/// 快照文件头标识
+const SNAPSHOT_MAGIC: &[u8; 4] = b"SPDB";
+
+/// 快照格式版本（2：对象记录增加 STRING 对象；3：增加围栏频道）
+const SNAPSHOT_VERSION: u32 = 3;
+
+/// 快照版本对应的对象记录版本（分块快照的格式版本）
+fn record_version(snapshot_version: u32) -> u32 {
+    match snapshot_version {
+        1 => 2,
+        _ => CHUNKED_VERSION,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SnapshotHeader {
+    version: u32,
+    /// 开始写快照的时刻（Unix 纳秒）
+    created_at: u64,
+}
+
+/// 文件头之后的每一节：一个 collection 的元数据（后面跟着对象块）、一个围栏频道，或者结束标记
+#[derive(Debug, Serialize, Deserialize)]
+enum Section {
+    Collection {
+        name: String,
+        max_entries: usize,
+        applied_seq: u64,
+        count: usize,
+    },
+    End {
+        collections: usize,
+    },
+    /// 版本 3 起：频道名称和之后的 SETCHAN 参数
+    Channel {
+        name: String,
+        args: Vec<String>,
+    },
+}
+
+/// 围栏频道的定义：频道名称和之后的 SETCHAN 参数
+pub(crate) type ChannelDefinition = (String, Vec<String>);
+
+/// 加载的快照内容
+pub(crate) struct LoadedSnapshot {
+    pub(crate) collections: Vec<(String, RTree)>,
+    pub(crate) channels: Vec<ChannelDefinition>,
+    pub(crate) summary: SnapshotSummary,
+}
+
+/// 写入或加载的快照包含的 collection 和对象数量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SnapshotSummary {
+    pub collections: usize,
+    pub objects: usize,
+}
+
+/// 一个 collection 在快照时刻的内容，在读锁下复制，之后在锁外写入文件
+pub(crate) struct CollectionSnapshot {
+    name: String,
+    max_entries: usize,
+    applied_seq: u64,
+    records: Vec<ChunkedRecord>,
+}
+
+impl CollectionSnapshot {
+    pub(crate) fn of(name: &str, rtree: &RTree) -> Self {
+        Self {
+            name: name.to_string(),
+            max_entries: rtree.max_entries(),
+            applied_seq: rtree.applied_seq(),
+            records: rtree.chunked_records(),
+        }
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn applied_seq(&self) -> u64 {
+        self.applied_seq
+    }
+
+    pub(crate) fn into_records(self) -> Vec<ChunkedRecord> {
+        self.records
+    }
+}
+
+/// 逐个接收 collection 副本的写入目标：快照文件或重写的 AOF
+pub(crate) trait CollectionSink {
+    fn write_collection(
+        &mut self,
+        collection: CollectionSnapshot,
+    ) -> impl std::future::Future<Output = Result<(), PersistenceError>> + Send;
+
+    /// 写入所有围栏频道的定义，在所有 collection 之后调用
+    fn write_channels(
+        &mut self,
+        channels: Vec<ChannelDefinition>,
+    ) -> impl std::future::Future<Output = Result<(), PersistenceError>> + Send;
+}
+
+/// 逐个 collection 写入快照，[`finish`](Self::finish) 之后才替换目标文件
+pub(crate) struct SnapshotWriter {
+    writer: tokio::io::BufWriter<tokio::fs::File>,
+    path: PathBuf,
+    temp_path: PathBuf,
+    summary: SnapshotSummary,
+}
+
+impl SnapshotWriter {
+    pub(crate) async fn create(path: &Path, created_at: u64) -> Result<Self, PersistenceError> {
+        let temp_path = temp_path(path);
+        let mut writer = tokio::io::BufWriter::new(tokio::fs::File::create(&temp_path).await?);
+        writer.write_all(SNAPSHOT_MAGIC).await?;
+        let header = SnapshotHeader {
+            version: SNAPSHOT_VERSION,
+            created_at,
+        };
+        write_frame(&mut writer, &bincode::serialize(&header)?).await?;
+
+        Ok(Self {
+            writer,
+            path: path.to_path_buf(),
+            temp_path,
+            summary: SnapshotSummary::default(),
+        })
+    }
+
+    /// 写入结束标记，刷盘后原子地替换目标文件
+    pub(crate) async fn finish(mut self) -> Result<SnapshotSummary, PersistenceError> {
+        let end = Section::End {
+            collections: self.summary.collections,
+        };
+        write_frame(&mut self.writer, &bincode::serialize(&end)?).await?;
+        self.writer.flush().await?;
+        self.writer.into_inner().sync_all().await?;
+
+        tokio::fs::rename(&self.temp_path, &self.path).await?;
+        let path = self.path.clone();
+        tokio::task::spawn_blocking(move || sync_parent_dir(&path))
+            .await
+            .map_err(std::io::Error::other)??;
+        Ok(self.summary)
+    }
+
+    /// 放弃写了一半的快照，删除临时文件
+    pub(crate) async fn discard(self) {
+        drop(self.writer);
+        let _ = tokio::fs::remove_file(&self.temp_path).await;
+    }
+}
+
+impl CollectionSink for SnapshotWriter {
+    /// 写入一个 collection，每写完一块对象让出一次执行权
+    async fn write_collection(
+        &mut self,
+        collection: CollectionSnapshot,
+    ) -> Result<(), PersistenceError> {
+        let section = Section::Collection {
+            name: collection.name,
+            max_entries: collection.max_entries,
+            applied_seq: collection.applied_seq,
+            count: collection.records.len(),
+        };
+        write_frame(&mut self.writer, &bincode::serialize(&section)?).await?;
+
+        self.summary.collections += 1;
+        self.summary.objects += collection.records.len();
+        for chunk in collection.records.chunks(DEFAULT_CHUNK_SIZE) {
+            write_frame(&mut self.writer, &bincode::serialize(chunk)?).await?;
+            tokio::task::yield_now().await;
+        }
+        Ok(())
+    }
+
+    async fn write_channels(
+        &mut self,
+        channels: Vec<ChannelDefinition>,
+    ) -> Result<(), PersistenceError> {
+        for (name, args) in channels {
+            let section = Section::Channel { name, args };
+            write_frame(&mut self.writer, &bincode::serialize(&section)?).await?;
+        }
+        Ok(())
+    }
+}
+
+/// 快照开始写入的时刻（Unix 纳秒），只读取文件头
+pub(crate) async fn read_snapshot_created_at(path: &Path) -> Result<u64, PersistenceError> {
+    let (_, header) = open_snapshot(path).await?;
+    Ok(header.created_at)
+}
+
+async fn open_snapshot(
+    path: &Path,
+) -> Result<(tokio::io::BufReader<tokio::fs::File>, SnapshotHeader), PersistenceError> {
+    let file = tokio::fs::File::open(path).await?;
+    let mut reader = tokio::io::BufReader::new(file);
+
+    let mut magic = [0u8; 4];
+    tokio::io::AsyncReadExt::read_exact(&mut reader, &mut magic)
+        .await
+        .map_err(|_| PersistenceError::InvalidFormat)?;
+    if &magic != SNAPSHOT_MAGIC {
+        return Err(PersistenceError::InvalidFormat);
+    }
+    let header: SnapshotHeader = match read_frame(&mut reader).await? {
+        Some(data) => bincode::deserialize(&data)?,
+        None => return Err(PersistenceError::InvalidFormat),
+    };
+    if !(1..=SNAPSHOT_VERSION).contains(&header.version) {
+        return Err(PersistenceError::InvalidFormat);
+    }
+    Ok((reader, header))
+}
+
+/// 读取整个快照，返回每个 collection 重建后的 R-tree 和围栏频道的定义
+///
+/// 文件不完整（缺少结束标记、对象数量不符）或格式不正确时返回 `InvalidFormat`
+pub(crate) async fn read_snapshot(path: &Path) -> Result<LoadedSnapshot, PersistenceError> {
+    let (mut reader, header) = open_snapshot(path).await?;
+    let record_version = record_version(header.version);
+
+    let mut collections = Vec::new();
+    let mut channels = Vec::new();
+    let mut summary = SnapshotSummary::default();
+    loop {
+        let Some(data) = read_frame(&mut reader).await? else {
+            return Err(PersistenceError::InvalidFormat);
+        };
+        match bincode::deserialize(&data)? {
+            Section::Collection {
+                name,
+                max_entries,
+                applied_seq,
+                count,
+            } => {
+                if max_entries < 2 {
+                    return Err(PersistenceError::InvalidFormat);
+                }
+                let mut rtree = RTree::new(max_entries);
+                rtree.defer_indexing();
+                let mut loaded = 0;
+                while loaded < count {
+                    let Some(data) = read_frame(&mut reader).await? else {
+                        return Err(PersistenceError::InvalidFormat);
+                    };
+                    let chunk = decode_records(record_version, &data)?;
+                    if chunk.is_empty() || loaded + chunk.len() > count {
+                        return Err(PersistenceError::InvalidFormat);
+                    }
+                    loaded += chunk.len();
+                    for record in chunk {
+                        rtree.insert_record(record)?;
+                    }
+                    tokio::task::yield_now().await;
+                }
+                rtree.rebuild_index();
+                rtree.mark_applied(applied_seq);
+                summary.collections += 1;
+                summary.objects += count;
+                collections.push((name, rtree));
+            }
+            Section::Channel { name, args } => channels.push((name, args)),
+            Section::End {
+                collections: expected,
+            } => {
+                if expected != collections.len() || read_frame(&mut reader).await?.is_some() {
+                    return Err(PersistenceError::InvalidFormat);
+                }
+                return Ok(LoadedSnapshot {
+                    collections,
+                    channels,
+                    summary,
+                });
+            }
+        }
+    }
+}
+
+/// 数据库的快照配置和状态：SAVE 和 BGSAVE 不会同时进行
+#[derive(Debug)]
+pub(crate) struct SnapshotState {
+    path: PathBuf,
+    job: Arc<BackgroundJob>,
+}
+
+impl SnapshotState {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            job: Arc::default(),
+        }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(crate) fn job(&self) -> &Arc<BackgroundJob> {
+        &self.job
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn point_tree(ids: &[&str], applied_seq: u64) -> RTree {
+        let mut rtree = RTree::new(4);
+        for (i, id) in ids.iter().enumerate() {
+            let point = json!({"type": "Point", "coordinates": [i as f64, 1.0]}).to_string();
+            assert!(rtree.insert_geojson(id.to_string(), &point));
+        }
+        rtree.mark_applied(applied_seq);
+        rtree
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("dump.spdb");
+
+        let mut fleet = point_tree(&["truck1", "truck2"], 7);
+        fleet.set_tags("truck1", ["diesel".to_string()]);
+        fleet.set_expire("truck2", 42);
+        let ids: Vec<String> = (0..DEFAULT_CHUNK_SIZE + 3).map(|i| i.to_string()).collect();
+        let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+        let zones = point_tree(&ids, 0);
+
+        let mut writer = SnapshotWriter::create(&path, 1).await.unwrap();
+        writer
+            .write_collection(CollectionSnapshot::of("fleet", &fleet))
+            .await
+            .unwrap();
+        writer
+            .write_collection(CollectionSnapshot::of("zones", &zones))
+            .await
+            .unwrap();
+        writer
+            .write_collection(CollectionSnapshot::of("empty", &RTree::new(4)))
+            .await
+            .unwrap();
+        let zone = (
+            "zone".to_string(),
+            vec!["NEARBY".to_string(), "fleet".to_string()],
+        );
+        writer.write_channels(vec![zone.clone()]).await.unwrap();
+        let summary = writer.finish().await.unwrap();
+        assert_eq!(summary.collections, 3);
+        assert_eq!(summary.objects, DEFAULT_CHUNK_SIZE + 5);
+        assert!(!temp_path(&path).exists());
+
+        let LoadedSnapshot {
+            collections,
+            channels,
+            summary: loaded,
+        } = read_snapshot(&path).await.unwrap();
+        assert_eq!(loaded, summary);
+        assert_eq!(channels, vec![zone]);
+        let (name, rtree) = &collections[0];
+        assert_eq!(name, "fleet");
+        assert_eq!(rtree.applied_seq(), 7);
+        assert_eq!(rtree.get_tags("truck1"), vec!["diesel".to_string()]);
+        assert_eq!(rtree.expires_at("truck2"), Some(42));
+        assert_eq!(collections[1].1.count(), DEFAULT_CHUNK_SIZE + 3);
+        assert!(collections[2].1.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_truncated_snapshot_is_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("dump.spdb");
+
+        let mut writer = SnapshotWriter::create(&path, 1).await.unwrap();
+        writer
+            .write_collection(CollectionSnapshot::of("fleet", &point_tree(&["a", "b"], 3)))
+            .await
+            .unwrap();
+        writer.finish().await.unwrap();
+
+        let data = std::fs::read(&path).unwrap();
+        for len in [0, 4, data.len() / 2, data.len() - 1] {
+            std::fs::write(&path, &data[..len]).unwrap();
+            assert!(read_snapshot(&path).await.is_err(), "{}", len);
+        }
+
+        // 放弃的快照不替换已有文件
+        std::fs::write(&path, &data).unwrap();
+        let writer = SnapshotWriter::create(&path, 2).await.unwrap();
+        writer.discard().await;
+        assert!(!temp_path(&path).exists());
+        assert_eq!(read_snapshot(&path).await.unwrap().summary.objects, 2);
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// 默认保留的统计历史（小时）
+pub const DEFAULT_STATS_RETENTION_HOURS: u32 = 24;
+
+/// 统计历史最长保留时间（小时）
+pub const MAX_STATS_RETENTION_HOURS: u32 = 24 * 7;
+
+/// 计入统计历史的事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatEvent {
+    /// 执行了一条命令
+    Command,
+    /// GET 找到了对象
+    Hit,
+    /// GET 没有找到对象
+    Miss,
+    /// 空闲 collection 被卸载到磁盘
+    Eviction,
+    /// 围栏推送了一条事件
+    FenceEvent,
+}
+
+/// 一分钟内的计数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MinuteStats {
+    /// 这一分钟开始的 Unix 时间（秒）
+    pub minute: u64,
+    pub commands: u64,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub fence_events: u64,
+}
+
+impl MinuteStats {
+    fn empty(minute: u64) -> Self {
+        Self {
+            minute,
+            ..Self::default()
+        }
+    }
+}
+
+/// 按分钟滚动的操作统计
+///
+/// 只保存有事件发生的分钟，超过保留时间的分钟在记录新事件时丢弃。
+/// 时间由调用方传入（通常来自数据库的时钟），测试中不需要等待
+#[derive(Debug)]
+pub struct OpsHistory {
+    retention_minutes: u64,
+    buckets: Mutex<VecDeque<MinuteStats>>,
+}
+
+impl OpsHistory {
+    /// 创建统计历史，至少保留 1 小时
+    pub fn new(retention_hours: u32) -> Self {
+        Self {
+            retention_minutes: retention_hours.max(1) as u64 * 60,
+            buckets: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// 保留的分钟数
+    pub fn retention_minutes(&self) -> u64 {
+        self.retention_minutes
+    }
+
+    /// 记录一个事件，`unix_secs` 为事件发生的 Unix 时间（秒）
+    pub fn record(&self, event: StatEvent, unix_secs: u64) {
+        let minute = unix_secs - unix_secs % 60;
+        let mut buckets = self.buckets.lock().unwrap();
+
+        // 时钟回拨时计入最近的一分钟，保持按时间递增
+        if buckets.back().is_none_or(|last| last.minute < minute) {
+            buckets.push_back(MinuteStats::empty(minute));
+            let oldest = minute.saturating_sub((self.retention_minutes - 1) * 60);
+            while buckets.front().is_some_and(|first| first.minute < oldest) {
+                buckets.pop_front();
+            }
+        }
+        let Some(bucket) = buckets.back_mut() else {
+            return;
+        };
+        match event {
+            StatEvent::Command => bucket.commands += 1,
+            StatEvent::Hit => bucket.hits += 1,
+            StatEvent::Miss => bucket.misses += 1,
+            StatEvent::Eviction => bucket.evictions += 1,
+            StatEvent::FenceEvent => bucket.fence_events += 1,
+        }
+    }
+
+    /// 截至 `unix_secs` 的最近 `minutes` 分钟（按时间递增，包含当前分钟），没有事件的分钟计数为 0
+    ///
+    /// `minutes` 超过保留时间时按保留时间截断
+    pub fn history(&self, unix_secs: u64, minutes: u64) -> Vec<MinuteStats> {
+        let minutes = minutes.min(self.retention_minutes);
+        let current = unix_secs - unix_secs % 60;
+        let first = current.saturating_sub(minutes.saturating_sub(1) * 60);
+
+        let buckets = self.buckets.lock().unwrap();
+        let mut recorded = buckets
+            .iter()
+            .filter(|bucket| bucket.minute >= first)
+            .peekable();
+        (0..minutes)
+            .map(|i| first + i * 60)
+            .map(
+                |minute| match recorded.next_if(|bucket| bucket.minute == minute) {
+                    Some(bucket) => *bucket,
+                    None => MinuteStats::empty(minute),
+                },
+            )
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const T0: u64 = 1_700_000_040; // 整分钟
+
+    #[test]
+    fn test_history_fills_idle_minutes() {
+        let history = OpsHistory::new(1);
+        history.record(StatEvent::Command, T0);
+        history.record(StatEvent::Hit, T0 + 59);
+        history.record(StatEvent::Command, T0 + 120);
+        history.record(StatEvent::Miss, T0 + 150);
+
+        let minutes = history.history(T0 + 130, 4);
+        assert_eq!(minutes.len(), 4);
+        assert_eq!(minutes[0], MinuteStats::empty(T0 - 60));
+        assert_eq!(
+            minutes[1],
+            MinuteStats {
+                minute: T0,
+                commands: 1,
+                hits: 1,
+                ..MinuteStats::default()
+            }
+        );
+        assert_eq!(minutes[2], MinuteStats::empty(T0 + 60));
+        assert_eq!(minutes[3].commands, 1);
+        assert_eq!(minutes[3].misses, 1);
+    }
+
+    #[test]
+    fn test_history_drops_expired_minutes() {
+        let history = OpsHistory::new(1);
+        history.record(StatEvent::Eviction, T0);
+        history.record(StatEvent::Command, T0 + 59 * 60);
+        assert_eq!(history.history(T0 + 59 * 60, 1000).len(), 60);
+        assert_eq!(history.history(T0 + 59 * 60, 60)[0].evictions, 1);
+
+        // 一小时后最早的一分钟被丢弃
+        history.record(StatEvent::Command, T0 + 60 * 60);
+        assert_eq!(history.buckets.lock().unwrap().len(), 2);
+        let minutes = history.history(T0 + 60 * 60, 60);
+        assert!(minutes.iter().all(|minute| minute.evictions == 0));
+        assert_eq!(minutes.iter().map(|minute| minute.commands).sum::<u64>(), 2);
+    }
+}
@@ -1,13 +1,48 @@
+use crate::protocol::{CommandError, ErrorCode};
 use crate::Result;
 use geo::Geometry;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedRwLockWriteGuard, RwLock};
 
 // 导入 rtree 相关类型
-use crate::rtree::algorithms::aof::{AofCommand, AofConfig, AofWriter};
-use crate::rtree::GeoItem;
+use crate::rtree::algorithms::aggregate::{BinStat, Binning};
+use crate::rtree::algorithms::aof::{self, AofCommand, AofConfig, AofError, AofWriter};
+use crate::rtree::algorithms::cluster::Cluster;
+use crate::rtree::algorithms::cursor::{Page, ScanCursor};
+use crate::rtree::algorithms::filter::ObjectFilter;
+use crate::rtree::algorithms::hull::HullKind;
+use crate::rtree::algorithms::knn::{DistanceMetric, SnapResult};
+use crate::rtree::algorithms::memory::MemoryUsage;
+use crate::rtree::algorithms::overlay::OverlayOp;
+use crate::rtree::algorithms::search::SearchOrder;
+use crate::rtree::algorithms::validate::TreeReport;
+use crate::rtree::rectangle::Rectangle;
 use crate::rtree::RTree;
+use crate::rtree::{GeoItem, StoredValue};
+
+use super::background::{BackgroundJob, JobGuard};
+use super::clock::{SharedClock, SystemClock};
+use super::cold::{self, ColdStorage, UnloadConfig};
+use super::disk::{DiskMonitor, DiskStatus};
+use super::events::{DatabaseEvent, EventBus, EventReceiver};
+use super::geometry_utils::geojson_to_geometry;
+use super::group_commit::GroupCommit;
+use super::loader::{CollectionLoader, LoadOutcome, ReadThrough};
+use super::namespace::Namespace;
+use super::pattern::{glob_match, GlobPattern};
+use super::rewrite::RewriteFile;
+use super::rfc7946;
+use super::snapshot::{
+    read_snapshot, read_snapshot_created_at, CollectionSink, CollectionSnapshot, LoadedSnapshot,
+    SnapshotState, SnapshotSummary, SnapshotWriter,
+};
+use super::stats::{MinuteStats, OpsHistory, StatEvent, DEFAULT_STATS_RETENTION_HOURS};
+
+/// PDEL 每批删除的对象数，每批删除后释放写锁并让出，其他命令可以在批次之间执行
+const PDEL_BATCH_SIZE: usize = 1000;
 
 /// 异步地理数据库，管理多个 Collection (SharedMap架构)
 pub struct GeoDatabase {
@@ -16,6 +51,65 @@ pub struct GeoDatabase {
 
     // AOF Writer (可选)
     aof_writer: Option<Arc<tokio::sync::Mutex<AofWriter>>>,
+
+    // AOF 组提交 (可选)：Always 策略下多个写命令共享一次 fsync，见 [`group_commit`](super::group_commit)
+    group_commit: Option<Arc<GroupCommit>>,
+
+    // 空闲 collection 卸载 (可选)
+    cold: Option<ColdStorage>,
+
+    // 磁盘空间监控 (可选)：空间不足时拒绝写入
+    disk: Option<DiskMonitor>,
+
+    // 时钟：空闲时间和 AOF 时间戳都从这里读取，测试中可替换为 MockClock
+    clock: SharedClock,
+
+    // 输出坐标的默认小数位数 (可选)：请求未指定 PRECISION 时使用
+    output_precision: Option<u32>,
+
+    // NEARBY 默认的距离度量：请求未指定 DISTANCE 时使用
+    distance_metric: DistanceMetric,
+
+    // 按分钟滚动的操作统计（STATS HISTORY）
+    ops_history: OpsHistory,
+
+    // 创建时间，SERVER 命令据此计算运行时长
+    started: Instant,
+
+    // 当前的客户端连接数，由服务端在连接建立和关闭时更新
+    connected_clients: AtomicUsize,
+
+    // 后台是否主动清理过期对象，DEBUG SET-ACTIVE-EXPIRE 可以关闭，只剩读取时的惰性过期
+    active_expire: AtomicBool,
+
+    // 因过期被删除的对象总数
+    expired_objects: AtomicU64,
+
+    // 是否允许 DEBUG SET-ACTIVE-EXPIRE、DEBUG SLEEP 等测试命令
+    debug_testing: bool,
+
+    // 读穿透加载器 (可选)：访问不存在的 collection 时从外部数据源加载
+    read_through: Option<ReadThrough>,
+    /// 开启 RFC 7946 严格模式的 collection（支持 glob 模式）
+    strict_geojson: Vec<String>,
+    /// 修改数据后发布事件，见 [`events`](super::events)
+    events: EventBus,
+    /// 时间点快照 (可选)：SAVE/BGSAVE 写入的文件和保存状态，见 [`snapshot`](super::snapshot)
+    snapshot: Option<SnapshotState>,
+    /// EXPORT 写入文件的目录 (可选)，见 [`export`](super::export)
+    export_dir: Option<std::path::PathBuf>,
+    /// 启动时是否加载了快照：加载之后重放 AOF 时跳过不编号的记录，它们早于快照
+    snapshot_loaded: AtomicBool,
+    /// 服务器是否正在启动加载（快照和 AOF 重放），期间读写数据的命令返回 LOADING
+    loading: AtomicBool,
+    /// AOF 重写（BGREWRITEAOF 和自动重写）的状态，见 [`rewrite`](super::rewrite)
+    aof_rewrite: Arc<BackgroundJob>,
+    /// 快照和 AOF 重写互斥，启动时才能按时间先后判断快照是否早于重写
+    persisting: tokio::sync::Mutex<()>,
+    /// 普通命令执行时持有读锁，EXEC 持有写锁，事务中的命令之间不会插入其他连接的命令
+    transactions: RwLock<()>,
+    /// 围栏频道（SETCHAN）的定义，写入 AOF 和快照；服务端启动时据此重新注册频道
+    channels: std::sync::Mutex<BTreeMap<String, Vec<String>>>,
 }
 
 impl Default for GeoDatabase {
@@ -29,6 +123,29 @@ impl GeoDatabase {
         Self {
             collections: Arc::new(RwLock::new(HashMap::new())),
             aof_writer: None,
+            group_commit: None,
+            cold: None,
+            disk: None,
+            clock: SystemClock::shared(),
+            output_precision: None,
+            distance_metric: DistanceMetric::Haversine,
+            ops_history: OpsHistory::new(DEFAULT_STATS_RETENTION_HOURS),
+            started: Instant::now(),
+            connected_clients: AtomicUsize::new(0),
+            active_expire: AtomicBool::new(true),
+            expired_objects: AtomicU64::new(0),
+            debug_testing: false,
+            read_through: None,
+            strict_geojson: Vec::new(),
+            events: EventBus::default(),
+            snapshot: None,
+            export_dir: None,
+            snapshot_loaded: AtomicBool::new(false),
+            loading: AtomicBool::new(false),
+            aof_rewrite: Arc::default(),
+            persisting: tokio::sync::Mutex::new(()),
+            transactions: RwLock::new(()),
+            channels: std::sync::Mutex::default(),
         }
     }
 
@@ -47,24 +164,311 @@ impl GeoDatabase {
     /// let db = GeoDatabase::with_aof(config).unwrap();
     /// ```
     pub fn with_aof(aof_config: AofConfig) -> crate::Result<Self> {
+        let group_commit = aof_config
+            .group_commit
+            .map(|window| Arc::new(GroupCommit::new(window)));
         let writer = AofWriter::new(aof_config)?;
 
         Ok(Self {
             collections: Arc::new(RwLock::new(HashMap::new())),
             aof_writer: Some(Arc::new(tokio::sync::Mutex::new(writer))),
+            group_commit,
+            cold: None,
+            disk: None,
+            clock: SystemClock::shared(),
+            output_precision: None,
+            distance_metric: DistanceMetric::Haversine,
+            ops_history: OpsHistory::new(DEFAULT_STATS_RETENTION_HOURS),
+            started: Instant::now(),
+            connected_clients: AtomicUsize::new(0),
+            active_expire: AtomicBool::new(true),
+            expired_objects: AtomicU64::new(0),
+            debug_testing: false,
+            read_through: None,
+            strict_geojson: Vec::new(),
+            events: EventBus::default(),
+            snapshot: None,
+            export_dir: None,
+            snapshot_loaded: AtomicBool::new(false),
+            loading: AtomicBool::new(false),
+            aof_rewrite: Arc::default(),
+            persisting: tokio::sync::Mutex::new(()),
+            transactions: RwLock::new(()),
+            channels: std::sync::Mutex::default(),
         })
     }
 
+    /// 允许 DEBUG SET-ACTIVE-EXPIRE、DEBUG SLEEP 等只用于测试和诊断的命令
+    pub fn with_debug_testing(mut self, enabled: bool) -> Self {
+        self.debug_testing = enabled;
+        self
+    }
+
+    pub fn debug_testing_enabled(&self) -> bool {
+        self.debug_testing
+    }
+
+    /// 设置读穿透加载器（嵌入式使用）
+    ///
+    /// 读写一个内存和磁盘中都不存在的 collection 时，先调用 `loader` 加载它的对象，
+    /// 每个 collection 只成功加载一次，并发访问共享同一次加载。加载的对象与 SET 一样写入 AOF，
+    /// 重启后从 AOF 恢复，不会再次加载。加载失败时访问返回错误，下次访问重试
+    ///
+    /// ```no_run
+    /// use spatio::storage::{GeoDatabase, LoadedObject};
+    /// use std::sync::Arc;
+    ///
+    /// let db = GeoDatabase::new().with_loader(Arc::new(|collection: String| {
+    ///     Box::pin(async move {
+    ///         // 例如从 PostgreSQL 查询这个 collection 的对象
+    ///         let point = r#"{"type":"Point","coordinates":[116.4,39.9]}"#;
+    ///         Ok(Some(vec![LoadedObject::new(format!("{}-1", collection), point)]))
+    ///     })
+    /// }));
+    /// ```
+    pub fn with_loader(mut self, loader: CollectionLoader) -> Self {
+        self.read_through = Some(ReadThrough::new(loader));
+        self
+    }
+
+    pub fn read_through_enabled(&self) -> bool {
+        self.read_through.is_some()
+    }
+
+    /// 订阅数据库事件（SET、DELETE、过期删除和 DROP），丢弃接收端即取消订阅
+    ///
+    /// 只收到订阅之后发生的修改
+    pub fn subscribe_events(&self) -> EventReceiver {
+        self.events.subscribe()
+    }
+
+    /// 替换时钟（默认使用系统时钟），测试中传入 [`MockClock`](super::clock::MockClock) 即可不依赖 sleep
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 启用空闲 collection 卸载
+    ///
+    /// 超过 `idle_timeout` 未访问的 collection 会被序列化到磁盘并从内存释放，
+    /// 名称和对象数量等元数据保持常驻，下次访问时自动重新加载。
+    /// 卸载由 [`unload_idle_collections`](Self::unload_idle_collections) 执行，需要定期调用。
+    pub fn with_idle_unloading(mut self, config: UnloadConfig) -> crate::Result<Self> {
+        let cold = ColdStorage::new(config)?;
+
+        // 已存在（例如从 AOF 恢复）的 collection 从现在开始计算空闲时间
+        if let Ok(collections) = self.collections.try_read() {
+            for collection_id in collections.keys() {
+                cold.touch(collection_id, self.clock.now());
+            }
+        }
+
+        self.cold = Some(cold);
+        Ok(self)
+    }
+
+    /// 启用磁盘空间监控
+    ///
+    /// 可用空间低于阈值时 set/delete/drop 返回 `OOM` 错误，读请求不受影响。
+    /// 可用空间由 [`refresh_disk_space`](Self::refresh_disk_space) 更新，需要定期调用
+    pub fn with_disk_monitor(mut self, monitor: DiskMonitor) -> Self {
+        self.disk = Some(monitor);
+        self
+    }
+
+    /// 启用时间点快照，SAVE/BGSAVE 将所有 collection 写入 `path`
+    ///
+    /// 启动时由调用方在重放 AOF 之前用 [`load_snapshot`](Self::load_snapshot) 加载
+    pub fn with_snapshot_path(mut self, path: std::path::PathBuf) -> Self {
+        self.snapshot = Some(SnapshotState::new(path));
+        self
+    }
+
+    /// 快照文件路径，未启用快照时返回 None
+    pub fn snapshot_path(&self) -> Option<&std::path::Path> {
+        self.snapshot.as_ref().map(SnapshotState::path)
+    }
+
+    /// 允许 EXPORT 把 collection 导出到 `dir` 中的文件，IMPORT FILE 也从这个目录读取文件
+    pub fn with_export_dir(mut self, dir: std::path::PathBuf) -> Self {
+        self.export_dir = Some(dir);
+        self
+    }
+
+    /// EXPORT 和 IMPORT FILE 的文件目录，未启用时返回 None
+    pub fn export_dir(&self) -> Option<&std::path::Path> {
+        self.export_dir.as_deref()
+    }
+
+    /// 为名称匹配 `patterns`（支持 glob）的 collection 开启 RFC 7946 严格模式
+    ///
+    /// SET 拒绝环方向错误、嵌套 GeometryCollection 或 bbox 无效的 GeoJSON，
+    /// GET/INTERSECTS/NEARBY 按 RFC 7946 规范化返回的 GeoJSON，见 [`rfc7946`](super::rfc7946)
+    pub fn with_strict_geojson(mut self, patterns: Vec<String>) -> Self {
+        self.strict_geojson = patterns;
+        self
+    }
+
+    /// collection 是否开启了 RFC 7946 严格模式
+    pub fn strict_geojson(&self, collection_id: &str) -> bool {
+        self.strict_geojson
+            .iter()
+            .any(|pattern| glob_match(pattern, collection_id))
+    }
+
+    pub fn strict_geojson_patterns(&self) -> &[String] {
+        &self.strict_geojson
+    }
+
+    /// 设置输出坐标的默认小数位数，GET/INTERSECTS/NEARBY 返回的 GeoJSON 会按此四舍五入
+    ///
+    /// 只影响输出，存储的数据保持原始精度；单个请求可以用 `PRECISION n` 覆盖
+    pub fn with_output_precision(mut self, decimals: u32) -> Self {
+        self.output_precision = Some(decimals);
+        self
+    }
+
+    /// 输出坐标的默认小数位数，None 表示原样输出
+    pub fn output_precision(&self) -> Option<u32> {
+        self.output_precision
+    }
+
+    /// 设置 NEARBY 默认的距离度量，单个请求可以用 `DISTANCE name` 覆盖
+    pub fn with_distance_metric(mut self, metric: DistanceMetric) -> Self {
+        self.distance_metric = metric;
+        self
+    }
+
+    /// NEARBY 默认的距离度量，默认为 Haversine
+    pub fn distance_metric(&self) -> DistanceMetric {
+        self.distance_metric
+    }
+
+    /// 设置按分钟统计的保留时间（小时），默认 [`DEFAULT_STATS_RETENTION_HOURS`]
+    pub fn with_stats_retention(mut self, hours: u32) -> Self {
+        self.ops_history = OpsHistory::new(hours);
+        self
+    }
+
+    /// 记录一个统计事件，计入当前分钟
+    pub fn record_stat(&self, event: StatEvent) {
+        self.ops_history
+            .record(event, self.clock.unix_nanos() / 1_000_000_000);
+    }
+
+    /// 最近 `minutes` 分钟的统计（按时间递增，包含当前分钟），超过保留时间时截断
+    pub fn stats_history(&self, minutes: u64) -> Vec<MinuteStats> {
+        self.ops_history
+            .history(self.clock.unix_nanos() / 1_000_000_000, minutes)
+    }
+
+    /// 统计历史保留的分钟数
+    pub fn stats_retention_minutes(&self) -> u64 {
+        self.ops_history.retention_minutes()
+    }
+
+    /// 数据库创建以来的时长
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// 记录一个新的客户端连接
+    pub fn client_connected(&self) {
+        self.connected_clients.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 记录一个客户端连接关闭
+    pub fn client_disconnected(&self) {
+        self.connected_clients.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// 当前的客户端连接数
+    pub fn connected_clients(&self) -> usize {
+        self.connected_clients.load(Ordering::Relaxed)
+    }
+
+    /// 重新检查可用磁盘空间，未启用监控时不做任何事
+    pub fn refresh_disk_space(&self) -> std::io::Result<()> {
+        if let Some(disk) = &self.disk {
+            disk.refresh()?;
+        }
+        Ok(())
+    }
+
+    /// 可用磁盘空间不足时返回 `OOM` 错误
+    pub fn check_disk_space(&self) -> std::result::Result<(), CommandError> {
+        self.disk.as_ref().map_or(Ok(()), DiskMonitor::check)
+    }
+
+    /// 磁盘空间状态，未启用监控时返回 None
+    pub fn disk_status(&self) -> Option<DiskStatus> {
+        self.disk.as_ref().map(DiskMonitor::status)
+    }
+
+    /// 标记开始启动加载，直到 [`finish_loading`](Self::finish_loading)
+    ///
+    /// 服务器先监听端口再加载快照和重放 AOF，期间连接上来的客户端收到 LOADING 而不是连接失败
+    pub fn begin_loading(&self) {
+        self.loading.store(true, Ordering::Release);
+    }
+
+    /// 标记启动加载完成
+    pub fn finish_loading(&self) {
+        self.loading.store(false, Ordering::Release);
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.loading.load(Ordering::Acquire)
+    }
+
+    /// 正在启动加载时返回 `LOADING` 错误
+    pub fn check_loaded(&self) -> std::result::Result<(), CommandError> {
+        if self.is_loading() {
+            return Err(CommandError::new(
+                ErrorCode::Loading,
+                "Spatio is loading the dataset in memory",
+            ));
+        }
+        Ok(())
+    }
+
+    /// 是否启用了 AOF 持久化
+    pub fn aof_enabled(&self) -> bool {
+        self.aof_writer.is_some()
+    }
+
+    /// 是否为纯内存实例：没有 AOF、快照和冷数据文件，重启后数据全部丢失
+    pub fn is_ephemeral(&self) -> bool {
+        self.aof_writer.is_none() && self.cold.is_none() && self.snapshot.is_none()
+    }
+
+    /// 是否启用了空闲 collection 卸载
+    pub fn idle_unloading_enabled(&self) -> bool {
+        self.cold.is_some()
+    }
+
     /// 从 AOF 文件恢复数据，返回 (命令数, 错误数)
     pub async fn recover_from_aof(
         &self,
         aof_path: std::path::PathBuf,
     ) -> crate::Result<(usize, usize)> {
+        let report = self.recover_from_aof_with_report(aof_path).await?;
+        Ok((report.commands, report.parse_errors.len()))
+    }
+
+    /// 从 AOF 文件恢复数据，返回详细的恢复报告
+    ///
+    /// 除解析错误外，还会记录重放过程中发现的不一致，例如无法解析的 GeoJSON、
+    /// 删除不存在的对象等。用于启动恢复和 `--verify-recovery` 演练模式。
+    pub async fn recover_from_aof_with_report(
+        &self,
+        aof_path: std::path::PathBuf,
+    ) -> crate::Result<RecoveryReport> {
         use crate::rtree::algorithms::aof::{AofCommand, AofReader};
 
         // 检查文件是否存在
         if !aof_path.exists() {
-            return Ok((0, 0));
+            return Ok(RecoveryReport::default());
         }
 
         // 打开 AOF Reader
@@ -73,24 +477,84 @@ impl GeoDatabase {
         // 恢复所有命令
         let result = reader.recover_all()?;
 
+        let mut report = RecoveryReport {
+            commands: result.commands.len(),
+            parse_errors: result.errors.iter().map(|e| e.to_string()).collect(),
+            inconsistencies: Vec::new(),
+            total_lines: result.total_lines,
+            corrupted_tail: reader.corrupted_tail(),
+            ..Default::default()
+        };
+
         // 重放命令（直接操作数据，不写入 AOF）
+        // 带序列号的记录如果不大于 collection 已应用的序列号则跳过，重复重放不会重复生效
+        let snapshot_loaded = self.snapshot_loaded.load(Ordering::Relaxed);
+        // 有插入的 collection 在重放期间暂停维护索引，重放结束后批量装载
+        let mut deferred: HashMap<String, Arc<RwLock<RTree>>> = HashMap::new();
         for cmd in &result.commands {
+            let seq = cmd.seq();
+            report.last_seq = report.last_seq.max(seq);
+
+            // 不编号的记录（重写时的数据、升级之前的旧记录）都早于快照，已经包含在快照中
+            if seq == 0 && snapshot_loaded && !matches!(cmd, AofCommand::Rewrite { .. }) {
+                report.skipped += 1;
+                continue;
+            }
+
             match cmd {
                 AofCommand::Insert {
                     collection,
                     key,
                     geojson,
+                    tags,
                     ..
                 } => {
-                    // 直接插入，不触发 AOF 写入
-                    let coll = self.get_or_create_collection(collection).await;
+                    // 直接插入，不触发 AOF 写入，也不触发读穿透加载
+                    let coll = match self.resident_collection(collection).await? {
+                        Some(coll) => coll,
+                        None => self.create_collection(collection).await,
+                    };
                     let mut rtree = coll.write().await;
+                    if already_applied(seq, &rtree) {
+                        report.skipped += 1;
+                        continue;
+                    }
+                    if !deferred.contains_key(collection) {
+                        rtree.defer_indexing();
+                        deferred.insert(collection.clone(), coll.clone());
+                    }
                     if !rtree.insert_geojson(key.clone(), geojson) {
                         eprintln!(
                             "⚠️  Failed to recover AOF command: INSERT {} {}",
                             collection, key
                         );
+                        report
+                            .inconsistencies
+                            .push(format!("INSERT {} {}: invalid GeoJSON", collection, key));
+                    } else {
+                        rtree.set_tags(key, tags.iter().cloned());
+                    }
+                    rtree.mark_applied(seq);
+                }
+                AofCommand::String {
+                    collection,
+                    key,
+                    value,
+                    tags,
+                    ..
+                } => {
+                    let coll = match self.resident_collection(collection).await? {
+                        Some(coll) => coll,
+                        None => self.create_collection(collection).await,
+                    };
+                    let mut rtree = coll.write().await;
+                    if already_applied(seq, &rtree) {
+                        report.skipped += 1;
+                        continue;
                     }
+                    rtree.set_string(key.clone(), value.clone());
+                    rtree.set_tags(key, tags.iter().cloned());
+                    rtree.mark_applied(seq);
                 }
                 AofCommand::Delete {
                     collection, key, ..
@@ -101,30 +565,279 @@ impl GeoDatabase {
                         let coll = coll.clone();
                         drop(collections);
                         let mut rtree = coll.write().await;
+                        if already_applied(seq, &rtree) {
+                            report.skipped += 1;
+                            continue;
+                        }
+                        if !rtree.contains(key) {
+                            report.inconsistencies.push(format!(
+                                "DELETE {} {}: object does not exist",
+                                collection, key
+                            ));
+                        }
                         rtree.delete(key);
+                        rtree.mark_applied(seq);
+                    } else {
+                        report.inconsistencies.push(format!(
+                            "DELETE {} {}: collection does not exist",
+                            collection, key
+                        ));
+                    }
+                }
+                AofCommand::Expire {
+                    collection,
+                    key,
+                    at,
+                    ..
+                } => {
+                    // 过期时刻是绝对时间，恢复后已经过期的对象由惰性/主动过期删除
+                    let coll = self.collections.read().await.get(collection).cloned();
+                    let Some(coll) = coll else {
+                        report.inconsistencies.push(format!(
+                            "EXPIRE {} {}: collection does not exist",
+                            collection, key
+                        ));
+                        continue;
+                    };
+                    let mut rtree = coll.write().await;
+                    if already_applied(seq, &rtree) {
+                        report.skipped += 1;
+                        continue;
+                    }
+                    let exists = match at {
+                        Some(at) => rtree.set_expire(key, *at),
+                        None => {
+                            rtree.persist(key);
+                            rtree.contains(key)
+                        }
+                    };
+                    if !exists {
+                        report.inconsistencies.push(format!(
+                            "EXPIRE {} {}: object does not exist",
+                            collection, key
+                        ));
                     }
+                    rtree.mark_applied(seq);
                 }
                 AofCommand::Drop { collection, .. } => {
-                    // 直接删除 collection
+                    // 直接删除 collection；已经应用过更新记录的 collection 说明 DROP 早已生效
                     let mut collections = self.collections.write().await;
+                    if let Some(coll) = collections.get(collection) {
+                        if already_applied(seq, &*coll.read().await) {
+                            report.skipped += 1;
+                            continue;
+                        }
+                    }
                     collections.remove(collection);
+                    deferred.remove(collection);
+                    if let Some(cold) = &self.cold {
+                        cold.remove(collection);
+                    }
+                }
+                // 按名称覆盖，不需要按序列号跳过
+                AofCommand::SetChan { name, args, .. } => {
+                    self.channels
+                        .lock()
+                        .unwrap()
+                        .insert(name.clone(), args.clone());
                 }
+                AofCommand::DelChan { name, .. } => {
+                    self.channels.lock().unwrap().remove(name);
+                }
+                AofCommand::Rewrite { .. } => {}
             }
         }
 
-        Ok((result.commands.len(), result.errors.len()))
+        for coll in deferred.into_values() {
+            coll.write().await.rebuild_index();
+        }
+
+        // 之后写入的记录从最大序列号之后继续编号
+        if let Some(mut writer) = self.lock_aof().await {
+            writer.resume_after(report.last_seq);
+        }
+
+        Ok(report)
     }
 
-    /// 获取或创建collection (异步版本)
-    async fn get_or_create_collection(&self, collection_id: &str) -> Arc<RwLock<RTree>> {
-        // 1. 先尝试读锁获取现有collection
+    /// 获取已存在的 collection，已卸载的 collection 会被重新加载；
+    /// 都不存在且设置了加载器时，通过加载器加载
+    async fn collection(&self, collection_id: &str) -> Result<Option<Arc<RwLock<RTree>>>> {
+        if let Some(collection) = self.resident_collection(collection_id).await? {
+            return Ok(Some(collection));
+        }
+        match &self.read_through {
+            Some(read_through) => self.load_through(read_through, collection_id).await,
+            None => Ok(None),
+        }
+    }
+
+    /// 获取分析类命令（AGG、CLUSTER、HULL、GEOMOP、SNAP）操作的 collection，不存在时返回 NOINDEX
+    async fn indexed_collection(&self, collection_id: &str) -> Result<Arc<RwLock<RTree>>> {
+        self.collection(collection_id).await?.ok_or_else(|| {
+            CommandError::new(
+                ErrorCode::NoIndex,
+                format!("collection '{}' does not exist", collection_id),
+            )
+            .into()
+        })
+    }
+
+    /// 获取内存中或已卸载到磁盘的 collection，已卸载的会被重新加载
+    async fn resident_collection(&self, collection_id: &str) -> Result<Option<Arc<RwLock<RTree>>>> {
         {
             let collections = self.collections.read().await;
             if let Some(collection) = collections.get(collection_id) {
-                return collection.clone();
+                if let Some(cold) = &self.cold {
+                    cold.touch(collection_id, self.clock.now());
+                }
+                return Ok(Some(collection.clone()));
+            }
+        }
+
+        match &self.cold {
+            Some(cold) if cold.is_unloaded(collection_id) => {
+                self.reload_collection(cold, collection_id).await
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// 通过加载器加载 collection，并发的访问共享同一次加载
+    async fn load_through(
+        &self,
+        read_through: &ReadThrough,
+        collection_id: &str,
+    ) -> Result<Option<Arc<RwLock<RTree>>>> {
+        if !read_through.is_loaded(collection_id) {
+            let flight = read_through.flight(collection_id);
+            let outcome = flight
+                .get_or_init(|| async {
+                    // 上一次加载可能刚好在取得 flight 之前结束
+                    if read_through.is_loaded(collection_id) {
+                        return Ok(());
+                    }
+                    let outcome: LoadOutcome = self
+                        .populate(read_through, collection_id)
+                        .await
+                        .map_err(|e| e.to_string());
+                    read_through.finish(collection_id, &outcome);
+                    outcome
+                })
+                .await;
+            if let Err(e) = outcome {
+                return Err(format!("failed to load collection '{}': {}", collection_id, e).into());
+            }
+        }
+        Ok(self.collections.read().await.get(collection_id).cloned())
+    }
+
+    /// 调用加载器并把对象写入新的 collection（同时记录 AOF）
+    async fn populate(&self, read_through: &ReadThrough, collection_id: &str) -> Result<()> {
+        let Some(objects) = (read_through.loader)(collection_id.to_string()).await? else {
+            return Ok(());
+        };
+
+        // 先在锁外建好树，加载器返回的数据有误时不留下半个 collection
+        let mut rtree = RTree::new(10);
+        for object in &objects {
+            if !rtree.insert_geojson(object.id.clone(), &object.geojson) {
+                return Err(format!("invalid GeoJSON for object '{}'", object.id).into());
+            }
+            rtree.set_tags(&object.id, object.tags.iter().cloned());
+        }
+
+        let mut collections = self.collections.write().await;
+        // 加载期间 collection 已经由其他途径创建（例如 AOF 恢复），保留已有数据
+        if collections.contains_key(collection_id) {
+            return Ok(());
+        }
+        let mut aof = self.lock_aof().await;
+        if let Some(writer) = aof.as_mut() {
+            let ts = self.clock.unix_nanos();
+            for object in &objects {
+                let cmd = AofCommand::insert(
+                    collection_id.to_string(),
+                    object.id.clone(),
+                    object.geojson.clone(),
+                )
+                .with_tags(object.tags.clone())
+                .with_timestamp(ts);
+                let seq = writer.append(&cmd).map_err(aof_write_error)?;
+                rtree.mark_applied(seq);
+            }
+        }
+        collections.insert(collection_id.to_string(), Arc::new(RwLock::new(rtree)));
+        if let Some(cold) = &self.cold {
+            cold.touch(collection_id, self.clock.now());
+        }
+        tracing::info!(
+            "Loaded collection '{}' ({} objects) through the loader",
+            collection_id,
+            objects.len()
+        );
+        Ok(())
+    }
+
+    /// 从磁盘重新加载已卸载的 collection
+    ///
+    /// 同一 collection 的并发访问在它的加载锁上排队，文件在阻塞线程池中读取，
+    /// 外层写锁只在放回 collection 时短暂持有，其他 collection 不受影响
+    async fn reload_collection(
+        &self,
+        cold: &ColdStorage,
+        collection_id: &str,
+    ) -> Result<Option<Arc<RwLock<RTree>>>> {
+        let loading = cold.loading(collection_id);
+        let _loading = loading.lock().await;
+
+        // 双检查：等待加载锁期间可能已被其他任务加载
+        if let Some(collection) = self.collections.read().await.get(collection_id) {
+            return Ok(Some(collection.clone()));
+        }
+        let Some(path) = cold.unloaded_path(collection_id) else {
+            return Ok(None);
+        };
+
+        let rtree = match cold::load_rtree(path.clone()).await {
+            Ok(rtree) => rtree,
+            Err(e) => {
+                // 保留元数据和文件，便于之后重试
+                cold.finish_loading(collection_id);
+                return Err(e);
             }
-        } // 读锁自动释放
+        };
+
+        let mut collections = self.collections.write().await;
+        // 读取文件期间 collection 被 DROP 或者被快照替换，丢弃读到的数据
+        if cold.unloaded_path(collection_id).as_ref() != Some(&path) {
+            cold.finish_loading(collection_id);
+            return Ok(collections.get(collection_id).cloned());
+        }
+        let collection = Arc::new(RwLock::new(rtree));
+        collections.insert(collection_id.to_string(), collection.clone());
+        if let Some(path) = cold.mark_reloaded(collection_id, self.clock.now()) {
+            cold::remove_file_in_background(path);
+        }
+        cold.finish_loading(collection_id);
+        drop(collections);
+
+        tracing::info!("Reloaded idle collection '{}'", collection_id);
+        Ok(Some(collection))
+    }
+
+    /// 获取或创建collection (异步版本)
+    async fn get_or_create_collection(&self, collection_id: &str) -> Result<Arc<RwLock<RTree>>> {
+        // 1. 先尝试获取现有collection（包括已卸载的）
+        if let Some(collection) = self.collection(collection_id).await? {
+            return Ok(collection);
+        }
 
+        Ok(self.create_collection(collection_id).await)
+    }
+
+    /// 创建 collection，已存在时返回已有的
+    async fn create_collection(&self, collection_id: &str) -> Arc<RwLock<RTree>> {
         // 2. 需要创建新collection，获取写锁
         let mut collections = self.collections.write().await;
 
@@ -136,119 +849,1287 @@ impl GeoDatabase {
         // 4. 创建新collection
         let new_collection = Arc::new(RwLock::new(RTree::new(10)));
         collections.insert(collection_id.to_string(), new_collection.clone());
+        if let Some(cold) = &self.cold {
+            cold.touch(collection_id, self.clock.now());
+        }
 
         new_collection
     }
 
-    /// 异步存储一个对象到指定 Collection
-    pub async fn set(&self, collection_id: &str, item_id: &str, geojson_str: &str) -> Result<()> {
-        // 1. 先修改内存（Redis 风格：内存优先）
-        let collection = self.get_or_create_collection(collection_id).await;
-        let mut rtree = collection.write().await;
-
-        // insert_geojson 内部会验证，如果失败直接返回错误
-        if !rtree.insert_geojson(item_id.to_string(), geojson_str) {
-            return Err(
-                "Failed to insert GeoJSON: invalid format or bbox calculation error".into(),
-            );
-        }
-
-        // 2. 内存插入成功后，再记录 AOF（如果启用）
-        if let Some(aof_writer) = &self.aof_writer {
-            let cmd = AofCommand::insert(
-                collection_id.to_string(),
-                item_id.to_string(),
-                geojson_str.to_string(),
-            );
-
-            let mut writer = aof_writer.lock().await;
-            writer.append(&cmd)?;
+    /// 获取 collection 的写锁，`create` 为 true 时不存在则创建
+    ///
+    /// 同一个 collection 的写操作都在这把锁下修改内存并追加 AOF，而 tokio 的 RwLock 按请求顺序
+    /// 公平授予，因此同一对象的并发写入在索引和 AOF 中的先后顺序一致（最后获得锁的写入生效），
+    /// 不需要额外的按对象加锁。等待写锁期间 collection 可能被 DROP 或卸载，此时重新查找，
+    /// 避免写入落在已移除的树上却仍记录到 AOF（重放时对象会“复活”）
+    async fn write_collection(
+        &self,
+        collection_id: &str,
+        create: bool,
+    ) -> Result<Option<OwnedRwLockWriteGuard<RTree>>> {
+        loop {
+            let collection = if create {
+                Some(self.get_or_create_collection(collection_id).await?)
+            } else {
+                self.collection(collection_id).await?
+            };
+            let Some(collection) = collection else {
+                return Ok(None);
+            };
+
+            let rtree = collection.write_owned().await;
+            if !rtree.is_detached() {
+                return Ok(Some(rtree));
+            }
         }
-
-        Ok(())
     }
 
-    /// 异步从指定 Collection 获取一个 GeoJSON 对象
-    pub async fn get(&self, collection_id: &str, item_id: &str) -> Result<Option<GeoItem>> {
-        // 1. 获取collection的引用
-        let collections = self.collections.read().await;
-        let collection = match collections.get(collection_id) {
-            Some(coll) => coll.clone(),
-            None => return Ok(None),
+    /// 卸载空闲超时的 collection，返回本次卸载的数量
+    ///
+    /// 序列化期间只持有该 collection 的写锁（文件在阻塞线程池中写入），外层写锁只在移除时短暂持有，
+    /// 移除前确认没有被 DROP 或替换。正在被使用（内层锁被占用）的 collection 会跳过，留到下次处理。
+    pub async fn unload_idle_collections(&self) -> Result<usize> {
+        let Some(cold) = &self.cold else {
+            return Ok(0);
         };
-        drop(collections); // 早释放外层锁
 
-        // 2. 获取collection数据的读锁
-        let rtree = collection.read().await;
+        let idle = cold.idle_collections(self.clock.now());
+        let mut unloaded = 0;
+        for collection_id in idle {
+            let Some(collection) = self.collections.read().await.get(&collection_id).cloned()
+            else {
+                continue;
+            };
+            let Ok(rtree) = Arc::clone(&collection).try_write_owned() else {
+                continue;
+            };
+            if rtree.is_detached() {
+                continue;
+            }
 
-        // 3. 读取数据
-        let result = rtree.get(item_id);
+            let path = cold.file_path(&collection_id);
+            let target = path.clone();
+            let dumped = tokio::task::spawn_blocking(move || {
+                let result = rtree.dump_to_file(&target);
+                (rtree, result)
+            })
+            .await;
+            let mut rtree = match dumped {
+                Ok((rtree, Ok(()))) => rtree,
+                Ok((_, Err(e))) => {
+                    tracing::warn!("Failed to unload collection '{}': {}", collection_id, e);
+                    cold::remove_file_in_background(path);
+                    continue;
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to unload collection '{}': {}", collection_id, e);
+                    continue;
+                }
+            };
+
+            let mut collections = self.collections.write().await;
+            if !collections
+                .get(&collection_id)
+                .is_some_and(|current| Arc::ptr_eq(current, &collection))
+            {
+                cold::remove_file_in_background(path);
+                continue;
+            }
+            cold.mark_unloaded(&collection_id, path, rtree.count(), self.clock.now());
+            // 已拿到引用、正在等待写锁的写操作会重新加载 collection 后再写入
+            rtree.detach();
+            drop(rtree);
+            collections.remove(&collection_id);
+            drop(collections);
+
+            self.record_stat(StatEvent::Eviction);
+            unloaded += 1;
+            tracing::info!("Unloaded idle collection '{}'", collection_id);
+        }
 
-        Ok(result)
+        Ok(unloaded)
     }
 
-    /// 异步从指定 Collection 删除一个 GeoJSON 对象
-    /// 返回 true 表示确实删除了一个存在的 item，false 表示 item 不存在
-    pub async fn delete(&self, collection_id: &str, item_id: &str) -> Result<bool> {
-        let collections = self.collections.read().await;
-        let collection = match collections.get(collection_id) {
-            Some(coll) => coll.clone(),
-            None => return Ok(false),
-        };
-        drop(collections);
-
-        let mut rtree = collection.write().await;
-
-        // 检查 item 是否存在
-        let exists = rtree.get(item_id).is_some();
-
-        if exists {
-            // 1. 先从内存删除（Redis 风格：内存优先）
-            rtree.delete(item_id);
+    /// 加载快照，替换同名的 collection，返回加载的 collection 和对象数量
+    ///
+    /// 在重放 AOF 之前调用：快照中每个 collection 已应用的序列号使重放跳过快照已经包含的记录。
+    /// 之后写入的 AOF 记录从快照中最大的序列号之后继续编号，即使 AOF 文件比快照旧。
+    ///
+    /// 快照早于 AOF 最近一次重写时不加载，返回 None：重写后的 AOF 本身就包含全部数据，
+    /// 而旧快照中已经被删除的 collection 在 AOF 中不再有 DROP 记录
+    pub async fn load_snapshot(&self, path: &std::path::Path) -> Result<Option<SnapshotSummary>> {
+        if let Some(aof_writer) = &self.aof_writer {
+            let aof_path = aof_writer.lock().await.config().file_path.clone();
+            if let Some(rewritten) = aof::rewritten_at(&aof_path)? {
+                if read_snapshot_created_at(path).await? < rewritten {
+                    return Ok(None);
+                }
+            }
+        }
 
-            // 2. 再记录 AOF（如果启用）
-            if let Some(aof_writer) = &self.aof_writer {
-                let cmd = AofCommand::delete(collection_id.to_string(), item_id.to_string());
+        let LoadedSnapshot {
+            collections: loaded,
+            channels,
+            summary,
+        } = read_snapshot(path).await?;
+        self.channels.lock().unwrap().extend(channels);
+        let max_seq = loaded
+            .iter()
+            .map(|(_, rtree)| rtree.applied_seq())
+            .max()
+            .unwrap_or(0);
 
-                let mut writer = aof_writer.lock().await;
-                writer.append(&cmd)?;
+        let mut collections = self.collections.write().await;
+        for (name, rtree) in loaded {
+            if let Some(cold) = &self.cold {
+                cold.remove(&name);
+                cold.touch(&name, self.clock.now());
             }
+            collections.insert(name, Arc::new(RwLock::new(rtree)));
+        }
+        drop(collections);
 
-            Ok(true)
-        } else {
-            Ok(false)
+        if let Some(mut writer) = self.lock_aof().await {
+            writer.resume_after(max_seq);
         }
+        self.snapshot_loaded.store(true, Ordering::Relaxed);
+        Ok(Some(summary))
     }
 
-    /// 异步获取所有 Collection 的名称
-    pub async fn collection_names(&self) -> Vec<String> {
+    /// 把 collection 导出为导出目录中的 GeoJSON FeatureCollection 文件（EXPORT），返回 Feature 数量，
+    /// collection 不存在时返回 None
+    ///
+    /// 在读锁下复制对象，写文件时不阻塞写入；已过期但还没有清理的对象不导出
+    pub async fn export_collection(
+        &self,
+        collection_id: &str,
+        file: &str,
+    ) -> Result<Option<usize>> {
+        let Some(dir) = &self.export_dir else {
+            return Err("EXPORT is not enabled (no data directory)".into());
+        };
+        let path = super::export::export_path(dir, file)?;
+        let Some(collection) = self.collection(collection_id).await? else {
+            return Ok(None);
+        };
+
+        let now = self.clock.unix_nanos();
+        let records: Vec<_> = CollectionSnapshot::of(collection_id, &*collection.read().await)
+            .into_records()
+            .into_iter()
+            .filter(|record| record.expires_at.is_none_or(|at| at > now))
+            .collect();
+        let count = super::export::write_feature_collection(&path, records).await?;
+        Ok(Some(count))
+    }
+
+    /// 从导出目录中的 GeoJSON FeatureCollection 文件批量导入对象（IMPORT FILE），返回导入的对象数量
+    pub async fn import_file(
+        &self,
+        collection_id: &str,
+        file: &str,
+        id_field: Option<&str>,
+    ) -> Result<usize> {
+        let Some(dir) = &self.export_dir else {
+            return Err("IMPORT FILE is not enabled (no data directory)".into());
+        };
+        let path = super::export::export_path(dir, file)?;
+        let document = tokio::fs::read_to_string(&path)
+            .await
+            .map_err(|e| format!("failed to read '{}': {}", file, e))?;
+        self.import_geojson(collection_id, document, id_field).await
+    }
+
+    /// 把 GeoJSON FeatureCollection 中的 Feature 批量写入 collection（IMPORT），返回写入的对象数量
+    ///
+    /// 对象 id 取 Feature 的 `id`，`id_field` 不为 None 时取 properties 中的这个字段，
+    /// 已有的同名对象被替换（标签和过期时间一并清除，与 SET 相同）。所有 Feature 先在锁外解析和校验，
+    /// 任何一个无效时不写入任何对象。之后在一次加锁中修改内存：导入的对象不少于 collection 中
+    /// 已有的对象时暂停维护索引，最后按 STR 批量装载整棵树；AOF 记录连续追加，整批只同步一次
+    pub async fn import_geojson(
+        &self,
+        collection_id: &str,
+        document: String,
+        id_field: Option<&str>,
+    ) -> Result<usize> {
+        self.check_disk_space()?;
+        let strict = self.strict_geojson(collection_id);
+        let id_field = id_field.map(str::to_string);
+        let objects = tokio::task::spawn_blocking(move || -> Result<Vec<(String, String)>> {
+            let objects = super::import::feature_objects(&document, id_field.as_deref())?;
+            for (id, geojson) in &objects {
+                if strict {
+                    rfc7946::validate(geojson)?;
+                }
+                geojson_to_geometry(geojson)
+                    .map_err(|e| format!("invalid GeoJSON for object '{}': {}", id, e))?;
+            }
+            Ok(objects)
+        })
+        .await
+        .map_err(|e| e.to_string())??;
+        if objects.is_empty() {
+            return Ok(0);
+        }
+
+        let mut rtree = self
+            .write_collection(collection_id, true)
+            .await?
+            .ok_or_else(|| format!("Failed to create collection '{}'", collection_id))?;
+        // 与 set 相同，先拿到 AOF 锁再修改内存
+        let mut aof = self.lock_aof().await;
+        let now = self.clock.unix_nanos();
+
+        let bulk = objects.len() >= rtree.count();
+        if bulk {
+            rtree.defer_indexing();
+        }
+        let mut imported = Vec::with_capacity(objects.len());
+        let mut failed = None;
+        for (id, geojson) in objects {
+            let previous = if self.events.has_subscribers() {
+                rtree.get_geometry(&id).cloned()
+            } else {
+                None
+            };
+            // 已经校验过，只有边界框无法计算时才会失败
+            if !rtree.insert_geojson(id.clone(), &geojson) {
+                failed = Some(id);
+                break;
+            }
+            if self.events.has_subscribers() {
+                if let Some(geometry) = rtree.get_geometry(&id) {
+                    self.events.publish(DatabaseEvent::ObjectSet {
+                        collection: collection_id.to_string(),
+                        id: id.clone(),
+                        previous,
+                        geometry: geometry.clone(),
+                        geojson: geojson.clone(),
+                        timestamp: now,
+                    });
+                }
+            }
+            imported.push((id, geojson));
+        }
+        if bulk {
+            rtree.rebuild_index();
+        }
+
+        // 失败之前已经写入内存的对象同样记录到 AOF，保持内存与 AOF 一致
+        let count = imported.len();
+        if let Some(writer) = aof.as_mut() {
+            let cmds: Vec<_> = imported
+                .into_iter()
+                .map(|(id, geojson)| {
+                    AofCommand::insert(collection_id.to_string(), id, geojson).with_timestamp(now)
+                })
+                .collect();
+            let seq = writer.append_batch(&cmds).map_err(aof_write_error)?;
+            rtree.mark_applied(seq);
+        }
+
+        match failed {
+            Some(id) => Err(format!(
+                "Failed to insert GeoJSON for object '{}': bbox calculation error ({} objects imported)",
+                id, count
+            )
+            .into()),
+            None => Ok(count),
+        }
+    }
+
+    /// AOF 中从 `pos` 开始的 `size` 个字节的 MD5（AOFMD5），复制时比较两个节点的 AOF 是否一致
+    ///
+    /// 先把缓冲区写入文件，范围超出文件长度时返回错误。在 AOF 锁下打开文件后释放锁计算，
+    /// 期间的重写替换的是路径，不影响已经打开的文件
+    pub async fn aof_md5(&self, pos: u64, size: u64) -> Result<String> {
+        use std::io::{Read, Seek, SeekFrom};
+
+        let Some(aof_writer) = &self.aof_writer else {
+            return Err("AOF is not enabled".into());
+        };
+        let mut file = {
+            let mut writer = aof_writer.lock().await;
+            let len = writer.flush_to_file()?;
+            if pos.checked_add(size).is_none_or(|end| end > len) {
+                return Err(format!(
+                    "range {}+{} is beyond the end of the AOF ({} bytes)",
+                    pos, size, len
+                )
+                .into());
+            }
+            std::fs::File::open(&writer.config().file_path)?
+        };
+
+        let digest = tokio::task::spawn_blocking(move || -> std::io::Result<String> {
+            file.seek(SeekFrom::Start(pos))?;
+            let mut md5 = super::md5::Md5::new();
+            let mut buf = vec![0u8; 64 * 1024];
+            let mut remaining = size;
+            while remaining > 0 {
+                let want = remaining.min(buf.len() as u64) as usize;
+                file.read_exact(&mut buf[..want])?;
+                md5.update(&buf[..want]);
+                remaining -= want as u64;
+            }
+            Ok(md5.hex_digest())
+        })
+        .await??;
+        Ok(digest)
+    }
+
+    /// 将所有 collection（包括已卸载的）写入快照文件（SAVE），返回写入的数量
+    ///
+    /// 未启用快照或者已有保存在进行时返回错误
+    pub async fn save_snapshot(&self) -> Result<SnapshotSummary> {
+        let guard = self.begin_snapshot()?;
+        self.write_snapshot(guard).await
+    }
+
+    /// 在后台任务中保存快照（BGSAVE），立即返回；结果记录在 [`persistence_info`](Self::persistence_info) 中
+    ///
+    /// 未启用快照或者已有保存在进行时返回错误
+    pub fn background_save(self: &Arc<Self>) -> Result<()> {
+        let guard = self.begin_snapshot()?;
+        let database = Arc::clone(self);
+        tokio::spawn(async move {
+            match database.write_snapshot(guard).await {
+                Ok(summary) => tracing::info!(
+                    "Background save finished: {} collections, {} objects",
+                    summary.collections,
+                    summary.objects
+                ),
+                Err(e) => tracing::error!("Background save failed: {}", e),
+            }
+        });
+        Ok(())
+    }
+
+    fn begin_snapshot(&self) -> Result<JobGuard> {
+        let snapshot = self
+            .snapshot
+            .as_ref()
+            .ok_or("snapshot persistence is not enabled")?;
+        Ok(snapshot.job().begin().ok_or_else(|| {
+            CommandError::new(ErrorCode::Busy, "a snapshot save is already in progress")
+        })?)
+    }
+
+    async fn write_snapshot(&self, _guard: JobGuard) -> Result<SnapshotSummary> {
+        let Some(snapshot) = &self.snapshot else {
+            return Err("snapshot persistence is not enabled".into());
+        };
+        let _persisting = self.persisting.lock().await;
+        let mut writer = SnapshotWriter::create(snapshot.path(), self.clock.unix_nanos()).await?;
+        let result = match self.copy_all(&mut writer).await {
+            Ok(()) => writer.finish().await.map_err(Into::into),
+            Err(e) => {
+                writer.discard().await;
+                Err(e)
+            }
+        };
+        let saved_at = result
+            .is_ok()
+            .then(|| self.clock.unix_nanos() / 1_000_000_000);
+        snapshot.job().record_result(saved_at);
+        result
+    }
+
+    /// 重写 AOF，返回重写后的文件大小（字节），见 [`rewrite`](super::rewrite)
+    ///
+    /// 未启用 AOF 或者已有重写在进行时返回错误
+    pub async fn rewrite_aof(&self) -> Result<u64> {
+        let guard = self.begin_aof_rewrite()?;
+        self.write_aof_rewrite(guard).await
+    }
+
+    /// 在后台任务中重写 AOF（BGREWRITEAOF 和自动重写），立即返回；
+    /// 结果记录在 [`persistence_info`](Self::persistence_info) 中
+    pub fn background_rewrite_aof(self: &Arc<Self>) -> Result<()> {
+        let guard = self.begin_aof_rewrite()?;
+        let database = Arc::clone(self);
+        tokio::spawn(async move {
+            match database.write_aof_rewrite(guard).await {
+                Ok(size) => tracing::info!("Background AOF rewrite finished: {} bytes", size),
+                Err(e) => tracing::error!("Background AOF rewrite failed: {}", e),
+            }
+        });
+        Ok(())
+    }
+
+    /// AOF 是否达到自动重写的阈值（已有重写在进行时为 false）
+    pub async fn aof_rewrite_due(&self) -> bool {
+        match &self.aof_writer {
+            Some(writer) => !self.aof_rewrite.in_progress() && writer.lock().await.rewrite_due(),
+            None => false,
+        }
+    }
+
+    fn begin_aof_rewrite(&self) -> Result<JobGuard> {
+        if self.aof_writer.is_none() {
+            return Err("AOF is not enabled".into());
+        }
+        Ok(self.aof_rewrite.begin().ok_or_else(|| {
+            CommandError::new(ErrorCode::Busy, "an AOF rewrite is already in progress")
+        })?)
+    }
+
+    async fn write_aof_rewrite(&self, _guard: JobGuard) -> Result<u64> {
+        let Some(aof_writer) = &self.aof_writer else {
+            return Err("AOF is not enabled".into());
+        };
+        let _persisting = self.persisting.lock().await;
+        let result = self.rewrite_aof_file(aof_writer).await;
+        let finished_at = result
+            .is_ok()
+            .then(|| self.clock.unix_nanos() / 1_000_000_000);
+        self.aof_rewrite.record_result(finished_at);
+        result
+    }
+
+    async fn rewrite_aof_file(&self, aof_writer: &tokio::sync::Mutex<AofWriter>) -> Result<u64> {
+        let started_at = self.clock.unix_nanos();
+        let (aof_path, format, offset) = {
+            let mut writer = aof_writer.lock().await;
+            let offset = writer.begin_rewrite().map_err(aof_write_error)?;
+            let config = writer.config();
+            (config.file_path.clone(), config.format, offset)
+        };
+
+        let mut file = RewriteFile::create(&aof_path, started_at, format).await?;
+        if let Err(e) = self.copy_all(&mut file).await {
+            file.discard().await;
+            return Err(e);
+        }
+        let objects = file.objects();
+        let temp_path = file.finish().await?;
+
+        // 复制尾部时持有 AOF 锁，新的写入等待替换完成后追加到新文件
+        let size = match aof_writer.lock().await.finish_rewrite(&temp_path, offset) {
+            Ok(size) => size,
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&temp_path).await;
+                return Err(e.into());
+            }
+        };
+        tracing::info!("Rewrote AOF with {} objects ({} bytes)", objects, size);
+        Ok(size)
+    }
+
+    /// 写入所有 collection 和围栏频道
+    async fn copy_all<S: CollectionSink + Send>(&self, writer: &mut S) -> Result<()> {
+        self.copy_collections(writer).await?;
+        writer.write_channels(self.channel_definitions()).await?;
+        Ok(())
+    }
+
+    /// 逐个复制并写入 collection（包括已卸载的）：每个 collection 只在复制时持有读锁，
+    /// 写文件时不阻塞写入
+    async fn copy_collections<S: CollectionSink + Send>(&self, writer: &mut S) -> Result<()> {
+        let resident: Vec<(String, Arc<RwLock<RTree>>)> = self
+            .collections
+            .read()
+            .await
+            .iter()
+            .map(|(name, collection)| (name.clone(), Arc::clone(collection)))
+            .collect();
+
+        let mut written = std::collections::HashSet::new();
+        for (name, collection) in resident {
+            let copy = {
+                let rtree = collection.read().await;
+                // 等待读锁期间被卸载的 collection 在下面从冷数据中读取
+                if rtree.is_detached() {
+                    continue;
+                }
+                CollectionSnapshot::of(&name, &rtree)
+            };
+            writer.write_collection(copy).await?;
+            written.insert(name);
+        }
+
+        let Some(cold) = &self.cold else {
+            return Ok(());
+        };
+        for name in cold.unloaded_names() {
+            if written.contains(&name) {
+                continue;
+            }
+            let loaded = match cold.unloaded_path(&name) {
+                Some(path) => match cold::load_rtree(path.clone()).await {
+                    Ok(rtree) => Some(rtree),
+                    // 读取前被重新加载（文件已删除）时从内存读取，否则是真正的读取错误
+                    Err(_) if cold.unloaded_path(&name).as_ref() != Some(&path) => None,
+                    Err(e) => return Err(e),
+                },
+                None => None,
+            };
+            let copy = match loaded {
+                Some(rtree) => CollectionSnapshot::of(&name, &rtree),
+                // 在列出之后被重新加载
+                None => match self.collections.read().await.get(&name).cloned() {
+                    Some(collection) => CollectionSnapshot::of(&name, &*collection.read().await),
+                    None => continue,
+                },
+            };
+            writer.write_collection(copy).await?;
+        }
+        Ok(())
+    }
+
+    /// 获取 AOF writer 的锁，未启用 AOF 时返回 None
+    ///
+    /// 写操作在修改内存之前调用，使得修改内存到追加 AOF 之间没有 await 点：
+    /// 客户端断开导致任务被取消时，不会出现内存已修改但 AOF 没有记录的情况
+    async fn lock_aof(&self) -> Option<tokio::sync::MutexGuard<'_, AofWriter>> {
+        match &self.aof_writer {
+            Some(aof_writer) => Some(aof_writer.lock().await),
+            None => None,
+        }
+    }
+
+    /// 保存围栏频道的定义（SETCHAN），替换同名的定义；`args` 为频道名称之后的参数
+    ///
+    /// 启用 AOF 时追加一条 SETCHAN 记录，写命令一样需要在回复前调用 [`wait_durable`](Self::wait_durable)
+    pub async fn set_channel(&self, name: &str, args: Vec<String>) -> Result<()> {
+        // 持有 AOF 锁直到修改内存，AOF 中的顺序与内存中的最终结果一致
+        let mut aof = self.lock_aof().await;
+        if let Some(writer) = aof.as_mut() {
+            let cmd = AofCommand::set_chan(name.to_string(), args.clone())
+                .with_timestamp(self.clock.unix_nanos());
+            writer.append(&cmd).map_err(aof_write_error)?;
+        }
+        self.channels.lock().unwrap().insert(name.to_string(), args);
+        Ok(())
+    }
+
+    /// 删除围栏频道的定义（DELCHAN），定义不存在时返回 false，不写入 AOF
+    pub async fn delete_channel(&self, name: &str) -> Result<bool> {
+        let mut aof = self.lock_aof().await;
+        if !self.channels.lock().unwrap().contains_key(name) {
+            return Ok(false);
+        }
+        if let Some(writer) = aof.as_mut() {
+            let cmd =
+                AofCommand::del_chan(name.to_string()).with_timestamp(self.clock.unix_nanos());
+            writer.append(&cmd).map_err(aof_write_error)?;
+        }
+        self.channels.lock().unwrap().remove(name);
+        Ok(true)
+    }
+
+    /// 保存的围栏频道定义，按名称排序
+    pub fn channel_definitions(&self) -> Vec<(String, Vec<String>)> {
+        self.channels
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, args)| (name.clone(), args.clone()))
+            .collect()
+    }
+
+    /// 等待目前为止追加的 AOF 记录落盘
+    ///
+    /// 只在 Always 策略启用组提交时需要等待：写命令回复之前调用，同一窗口内的写命令共享一次 fsync。
+    /// 其他情况下记录已经按同步策略处理，立即返回
+    pub async fn wait_durable(&self) -> Result<()> {
+        let (Some(aof_writer), Some(group_commit)) = (&self.aof_writer, &self.group_commit) else {
+            return Ok(());
+        };
+        let seq = {
+            let writer = aof_writer.lock().await;
+            if !writer.sync_pending() {
+                return Ok(());
+            }
+            writer.last_seq()
+        };
+        group_commit.wait(aof_writer, seq).await
+    }
+
+    /// 执行一个命令期间持有，与 [`exclusive`](Self::exclusive) 互斥
+    pub async fn shared(&self) -> tokio::sync::RwLockReadGuard<'_, ()> {
+        self.transactions.read().await
+    }
+
+    /// 执行一个事务（EXEC）期间持有：等待正在执行的命令完成，之后其他命令等待事务结束
+    pub async fn exclusive(&self) -> tokio::sync::RwLockWriteGuard<'_, ()> {
+        self.transactions.write().await
+    }
+
+    /// 异步存储一个对象到指定 Collection
+    pub async fn set(&self, collection_id: &str, item_id: &str, geojson_str: &str) -> Result<()> {
+        self.set_with_tags(collection_id, item_id, geojson_str, &[])
+            .await
+    }
+
+    /// 存储一个对象并设置其标签，替换对象原有的几何体和标签
+    pub async fn set_with_tags(
+        &self,
+        collection_id: &str,
+        item_id: &str,
+        geojson_str: &str,
+        tags: &[String],
+    ) -> Result<()> {
+        self.set_with_ttl(collection_id, item_id, geojson_str, tags, None)
+            .await
+    }
+
+    /// 存储一个对象，`ttl` 不为 None 时同时设置过期时间（SET ... EX），否则清除原有的过期时间
+    ///
+    /// 过期时间与对象在同一次加锁中设置，AOF 中紧跟在插入记录之后写一条 EXPIRE 记录
+    pub async fn set_with_ttl(
+        &self,
+        collection_id: &str,
+        item_id: &str,
+        geojson_str: &str,
+        tags: &[String],
+        ttl: Option<Duration>,
+    ) -> Result<()> {
+        self.check_disk_space()?;
+        if self.strict_geojson(collection_id) {
+            rfc7946::validate(geojson_str)?;
+        }
+        let mut rtree = self
+            .write_collection(collection_id, true)
+            .await?
+            .ok_or_else(|| format!("Failed to create collection '{}'", collection_id))?;
+        // 在修改内存之前拿到 AOF 锁：之后不再有 await，任务在此之后不会被取消，
+        // 内存修改和 AOF 记录要么都发生，要么都不发生
+        let mut aof = self.lock_aof().await;
+
+        // 修改前的几何体，只有存在订阅者时才需要
+        let previous = if self.events.has_subscribers() {
+            rtree.get_geometry(item_id).cloned()
+        } else {
+            None
+        };
+
+        // 1. 先修改内存（Redis 风格：内存优先）
+        // try_insert_geojson 内部会验证，失败时原有对象保持不变，把原因返回给客户端
+        rtree
+            .try_insert_geojson(item_id.to_string(), geojson_str)
+            .map_err(|e| format!("invalid GeoJSON: {}", e))?;
+        rtree.set_tags(item_id, tags.iter().cloned());
+        let now = self.clock.unix_nanos();
+        let expire_at =
+            ttl.map(|ttl| now.saturating_add(ttl.as_nanos().min(u64::MAX as u128) as u64));
+        if let Some(at) = expire_at {
+            rtree.set_expire(item_id, at);
+        }
+
+        if self.events.has_subscribers() {
+            if let Some(geometry) = rtree.get_geometry(item_id) {
+                self.events.publish(DatabaseEvent::ObjectSet {
+                    collection: collection_id.to_string(),
+                    id: item_id.to_string(),
+                    previous,
+                    geometry: geometry.clone(),
+                    geojson: geojson_str.to_string(),
+                    timestamp: now,
+                });
+            }
+        }
+
+        // 2. 内存插入成功后，再记录 AOF（如果启用）
+        if let Some(writer) = aof.as_mut() {
+            let cmd = AofCommand::insert(
+                collection_id.to_string(),
+                item_id.to_string(),
+                geojson_str.to_string(),
+            )
+            .with_tags(tags.to_vec())
+            .with_timestamp(now);
+            let mut seq = writer.append(&cmd).map_err(aof_write_error)?;
+            if let Some(at) = expire_at {
+                let cmd =
+                    AofCommand::expire(collection_id.to_string(), item_id.to_string(), Some(at))
+                        .with_timestamp(now);
+                seq = writer.append(&cmd).map_err(aof_write_error)?;
+            }
+            rtree.mark_applied(seq);
+        }
+
+        Ok(())
+    }
+
+    /// 存储一个 STRING 对象（`SET ... STRING value`），替换同 ID 的几何体或文本及其标签，
+    /// `ttl` 的含义与 [`set_with_ttl`](Self::set_with_ttl) 相同
+    ///
+    /// STRING 对象不进入空间索引，也不产生地理围栏事件；覆盖几何体时照常发布删除事件
+    pub async fn set_string(
+        &self,
+        collection_id: &str,
+        item_id: &str,
+        value: &str,
+        tags: &[String],
+        ttl: Option<Duration>,
+    ) -> Result<()> {
+        self.check_disk_space()?;
+        let mut rtree = self
+            .write_collection(collection_id, true)
+            .await?
+            .ok_or_else(|| format!("Failed to create collection '{}'", collection_id))?;
+        // 与 set_with_ttl 相同，先拿到 AOF 锁再修改内存，保证取消安全
+        let mut aof = self.lock_aof().await;
+
+        let now = self.clock.unix_nanos();
+        if rtree.get_geometry(item_id).is_some() {
+            self.delete_and_publish(&mut rtree, collection_id, item_id, now);
+        }
+        rtree.set_string(item_id.to_string(), value.to_string());
+        rtree.set_tags(item_id, tags.iter().cloned());
+        let expire_at =
+            ttl.map(|ttl| now.saturating_add(ttl.as_nanos().min(u64::MAX as u128) as u64));
+        if let Some(at) = expire_at {
+            rtree.set_expire(item_id, at);
+        }
+
+        if let Some(writer) = aof.as_mut() {
+            let cmd = AofCommand::string(
+                collection_id.to_string(),
+                item_id.to_string(),
+                value.to_string(),
+            )
+            .with_tags(tags.to_vec())
+            .with_timestamp(now);
+            let mut seq = writer.append(&cmd).map_err(aof_write_error)?;
+            if let Some(at) = expire_at {
+                let cmd =
+                    AofCommand::expire(collection_id.to_string(), item_id.to_string(), Some(at))
+                        .with_timestamp(now);
+                seq = writer.append(&cmd).map_err(aof_write_error)?;
+            }
+            rtree.mark_applied(seq);
+        }
+
+        Ok(())
+    }
+
+    /// 获取对象的值（几何体或 STRING 文本），过期处理和命中统计与 [`get`](Self::get) 相同
+    pub async fn get_value(
+        &self,
+        collection_id: &str,
+        item_id: &str,
+    ) -> Result<Option<StoredValue>> {
+        let collection = match self.collection(collection_id).await? {
+            Some(coll) => coll,
+            None => return Ok(None),
+        };
+
+        let rtree = collection.read().await;
+        let mut result = rtree.get_value(item_id);
+        if result.is_some() && rtree.is_expired(item_id, self.clock.unix_nanos()) {
+            drop(rtree);
+            self.remove_expired(collection_id, &[item_id.to_string()])
+                .await?;
+            result = None;
+        }
+        self.record_stat(if result.is_some() {
+            StatEvent::Hit
+        } else {
+            StatEvent::Miss
+        });
+
+        Ok(result)
+    }
+
+    /// 异步从指定 Collection 获取一个 GeoJSON 对象
+    pub async fn get(&self, collection_id: &str, item_id: &str) -> Result<Option<GeoItem>> {
+        // 1. 获取collection的引用
+        let collection = match self.collection(collection_id).await? {
+            Some(coll) => coll,
+            None => return Ok(None),
+        };
+
+        // 2. 获取collection数据的读锁
+        let rtree = collection.read().await;
+
+        // 3. 读取数据，已过期的对象在这里删除（惰性过期）
+        let mut result = rtree.get(item_id);
+        if result.is_some() && rtree.is_expired(item_id, self.clock.unix_nanos()) {
+            drop(rtree);
+            self.remove_expired(collection_id, &[item_id.to_string()])
+                .await?;
+            result = None;
+        }
+        self.record_stat(if result.is_some() {
+            StatEvent::Hit
+        } else {
+            StatEvent::Miss
+        });
+
+        Ok(result)
+    }
+
+    /// 异步从指定 Collection 删除一个 GeoJSON 对象
+    /// 返回 true 表示确实删除了一个存在的 item，false 表示 item 不存在
+    pub async fn delete(&self, collection_id: &str, item_id: &str) -> Result<bool> {
+        self.check_disk_space()?;
+        let Some(mut rtree) = self.write_collection(collection_id, false).await? else {
+            return Ok(false);
+        };
+
+        // 检查 item 是否存在
+        let exists = rtree.contains(item_id);
+
+        if exists {
+            // 与 set 相同，先拿到 AOF 锁再修改内存，保证取消安全
+            let mut aof = self.lock_aof().await;
+
+            // 1. 先从内存删除（Redis 风格：内存优先）
+            self.delete_and_publish(&mut rtree, collection_id, item_id, self.clock.unix_nanos());
+
+            // 2. 再记录 AOF（如果启用）
+            if let Some(writer) = aof.as_mut() {
+                let cmd = AofCommand::delete(collection_id.to_string(), item_id.to_string())
+                    .with_timestamp(self.clock.unix_nanos());
+                let seq = writer.append(&cmd).map_err(aof_write_error)?;
+                rtree.mark_applied(seq);
+            }
+
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// 删除 ID 匹配 glob 模式的所有对象（PDEL），返回删除的数量
+    ///
+    /// 先在读锁下从 ID 索引中收集匹配的 ID，再每 [`PDEL_BATCH_SIZE`] 个一批取得写锁删除，
+    /// 一次删除大量对象也不会长时间占住写锁。每个对象写一条 AOF DELETE 记录；
+    /// 批次之间被其他连接删除的对象跳过，新写入的匹配对象不会被删除
+    pub async fn pdel(&self, collection_id: &str, pattern: &str) -> Result<usize> {
+        self.check_disk_space()?;
+        let Some(collection) = self.collection(collection_id).await? else {
+            return Ok(0);
+        };
+        let ids = collection.read().await.ids_matching(pattern);
+        drop(collection);
+
+        let mut deleted = 0;
+        for batch in ids.chunks(PDEL_BATCH_SIZE) {
+            let Some(mut rtree) = self.write_collection(collection_id, false).await? else {
+                break;
+            };
+            let now = self.clock.unix_nanos();
+            let mut aof = self.lock_aof().await;
+            for item_id in batch {
+                if !rtree.contains(item_id) {
+                    continue;
+                }
+                self.delete_and_publish(&mut rtree, collection_id, item_id, now);
+                deleted += 1;
+                if let Some(writer) = aof.as_mut() {
+                    let cmd = AofCommand::delete(collection_id.to_string(), item_id.to_string())
+                        .with_timestamp(now);
+                    let seq = writer.append(&cmd).map_err(aof_write_error)?;
+                    rtree.mark_applied(seq);
+                }
+            }
+            drop(aof);
+            drop(rtree);
+            tokio::task::yield_now().await;
+        }
+        Ok(deleted)
+    }
+
+    /// 修改对象的 GeoJSON 文档（JSET / JDEL），保留对象的标签和过期时间，对象不存在时返回 None
+    ///
+    /// `edit` 在写锁下修改解析后的文档，返回 false 表示文档没有变化，此时不写 AOF。修改后的文档
+    /// 必须仍是有效的 GeoJSON，否则返回错误且对象保持不变；路径可能指向几何体，因此对象重新
+    /// 建立索引，AOF 中与 SET 一样记录完整的文档，重启后修改仍然存在
+    pub async fn update_geojson<F>(
+        &self,
+        collection_id: &str,
+        item_id: &str,
+        edit: F,
+    ) -> Result<Option<bool>>
+    where
+        F: FnOnce(&mut serde_json::Value) -> std::result::Result<bool, String> + Send,
+    {
+        self.check_disk_space()?;
+        let Some(mut rtree) = self.write_collection(collection_id, false).await? else {
+            return Ok(None);
+        };
+        let now = self.clock.unix_nanos();
+        let Some(geojson) = rtree.get_geojson(item_id) else {
+            return Ok(None);
+        };
+        if rtree.is_expired(item_id, now) {
+            return Ok(None);
+        }
+
+        let mut document: serde_json::Value = serde_json::from_str(geojson)?;
+        if !edit(&mut document)? {
+            return Ok(Some(false));
+        }
+        let geojson_str = document.to_string();
+        if self.strict_geojson(collection_id) {
+            rfc7946::validate(&geojson_str)?;
+        }
+        // insert_geojson 会先删除旧对象，无效的文档必须在此之前拒绝
+        geojson_to_geometry(&geojson_str)?;
+
+        let mut aof = self.lock_aof().await;
+        let previous = if self.events.has_subscribers() {
+            rtree.get_geometry(item_id).cloned()
+        } else {
+            None
+        };
+        let tags = rtree.get_tags(item_id);
+        let expire_at = rtree.expires_at(item_id);
+
+        if !rtree.insert_geojson(item_id.to_string(), &geojson_str) {
+            return Err(
+                "Failed to insert GeoJSON: invalid format or bbox calculation error".into(),
+            );
+        }
+        rtree.set_tags(item_id, tags.iter().cloned());
+        if let Some(at) = expire_at {
+            rtree.set_expire(item_id, at);
+        }
+
+        if self.events.has_subscribers() {
+            if let Some(geometry) = rtree.get_geometry(item_id) {
+                self.events.publish(DatabaseEvent::ObjectSet {
+                    collection: collection_id.to_string(),
+                    id: item_id.to_string(),
+                    previous,
+                    geometry: geometry.clone(),
+                    geojson: geojson_str.clone(),
+                    timestamp: now,
+                });
+            }
+        }
+
+        if let Some(writer) = aof.as_mut() {
+            let cmd =
+                AofCommand::insert(collection_id.to_string(), item_id.to_string(), geojson_str)
+                    .with_tags(tags)
+                    .with_timestamp(now);
+            let mut seq = writer.append(&cmd).map_err(aof_write_error)?;
+            if let Some(at) = expire_at {
+                let cmd =
+                    AofCommand::expire(collection_id.to_string(), item_id.to_string(), Some(at))
+                        .with_timestamp(now);
+                seq = writer.append(&cmd).map_err(aof_write_error)?;
+            }
+            rtree.mark_applied(seq);
+        }
+
+        Ok(Some(true))
+    }
+
+    /// 设置对象在 `ttl` 之后过期，对象不存在时返回 false；`ttl` 为 0 时立即删除对象
+    pub async fn expire(&self, collection_id: &str, item_id: &str, ttl: Duration) -> Result<bool> {
+        if ttl.is_zero() {
+            return self.delete(collection_id, item_id).await;
+        }
+        let at = self
+            .clock
+            .unix_nanos()
+            .saturating_add(ttl.as_nanos().min(u64::MAX as u128) as u64);
+        self.update_expire(collection_id, item_id, Some(at)).await
+    }
+
+    /// 清除对象的过期时间，对象原本有 TTL 时返回 true
+    pub async fn persist(&self, collection_id: &str, item_id: &str) -> Result<bool> {
+        self.update_expire(collection_id, item_id, None).await
+    }
+
+    /// 修改对象的过期时刻并记录 AOF，`at` 为 None 表示清除
+    async fn update_expire(
+        &self,
+        collection_id: &str,
+        item_id: &str,
+        at: Option<u64>,
+    ) -> Result<bool> {
+        self.check_disk_space()?;
+        let Some(mut rtree) = self.write_collection(collection_id, false).await? else {
+            return Ok(false);
+        };
+        let now = self.clock.unix_nanos();
+        if !rtree.contains(item_id) || rtree.is_expired(item_id, now) {
+            return Ok(false);
+        }
+        if at.is_none() && rtree.expires_at(item_id).is_none() {
+            return Ok(false);
+        }
+
+        // 与 set 相同，先拿到 AOF 锁再修改内存，保证取消安全
+        let mut aof = self.lock_aof().await;
+        match at {
+            Some(at) => rtree.set_expire(item_id, at),
+            None => rtree.persist(item_id),
+        };
+        if let Some(writer) = aof.as_mut() {
+            let cmd = AofCommand::expire(collection_id.to_string(), item_id.to_string(), at)
+                .with_timestamp(now);
+            let seq = writer.append(&cmd).map_err(aof_write_error)?;
+            rtree.mark_applied(seq);
+        }
+        Ok(true)
+    }
+
+    /// 对象的剩余存活时间
+    pub async fn ttl(&self, collection_id: &str, item_id: &str) -> Result<Ttl> {
+        let Some(collection) = self.collection(collection_id).await? else {
+            return Ok(Ttl::Missing);
+        };
+        let rtree = collection.read().await;
+        if !rtree.contains(item_id) {
+            return Ok(Ttl::Missing);
+        }
+        let now = self.clock.unix_nanos();
+        match rtree.expires_at(item_id) {
+            None => Ok(Ttl::Persistent),
+            Some(at) if at > now => Ok(Ttl::Expires(Duration::from_nanos(at - now))),
+            Some(_) => {
+                drop(rtree);
+                self.remove_expired(collection_id, &[item_id.to_string()])
+                    .await?;
+                Ok(Ttl::Missing)
+            }
+        }
+    }
+
+    /// 开启或关闭主动过期（DEBUG SET-ACTIVE-EXPIRE）
+    pub fn set_active_expire(&self, enabled: bool) {
+        self.active_expire.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn active_expire_enabled(&self) -> bool {
+        self.active_expire.load(Ordering::Relaxed)
+    }
+
+    /// 因过期被删除的对象总数
+    pub fn expired_objects(&self) -> u64 {
+        self.expired_objects.load(Ordering::Relaxed)
+    }
+
+    /// 主动过期：删除已加载 collection 中已经过期的对象，最多 `limit` 个（0 表示不限制），
+    /// 返回删除的数量。主动过期被关闭时不做任何事，需要定期调用
+    ///
+    /// 已卸载的 collection 不会为此重新加载，其中过期的对象在下次加载后清理
+    pub async fn expire_due(&self, limit: usize) -> Result<usize> {
+        if !self.active_expire_enabled() {
+            return Ok(0);
+        }
+
+        let collections: Vec<(String, Arc<RwLock<RTree>>)> = self
+            .collections
+            .read()
+            .await
+            .iter()
+            .map(|(name, collection)| (name.clone(), Arc::clone(collection)))
+            .collect();
+
+        let now = self.clock.unix_nanos();
+        let mut removed = 0;
+        for (name, collection) in collections {
+            let remaining = if limit == 0 { 0 } else { limit - removed };
+            let ids = collection.read().await.expired_ids(now, remaining);
+            if !ids.is_empty() {
+                removed += self.remove_expired(&name, &ids).await?;
+            }
+            if limit > 0 && removed >= limit {
+                break;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// 删除已经过期的对象，每个对象写一条 AOF DELETE 记录（与 Redis 传播 DEL 相同），
+    /// 返回实际删除的数量。等待写锁期间被重新设置了 TTL 或覆盖的对象保留
+    async fn remove_expired(&self, collection_id: &str, item_ids: &[String]) -> Result<usize> {
+        let Some(mut rtree) = self.write_collection(collection_id, false).await? else {
+            return Ok(0);
+        };
+        let now = self.clock.unix_nanos();
+        let mut aof = self.lock_aof().await;
+
+        let mut removed = 0;
+        for item_id in item_ids {
+            if !rtree.is_expired(item_id, now) {
+                continue;
+            }
+            self.delete_and_publish(&mut rtree, collection_id, item_id, now);
+            removed += 1;
+            if let Some(writer) = aof.as_mut() {
+                let cmd = AofCommand::delete(collection_id.to_string(), item_id.to_string())
+                    .with_timestamp(now);
+                let seq = writer.append(&cmd).map_err(aof_write_error)?;
+                rtree.mark_applied(seq);
+            }
+        }
+        self.expired_objects
+            .fetch_add(removed as u64, Ordering::Relaxed);
+        Ok(removed)
+    }
+
+    /// 删除对象，存在订阅者时发布 [`DatabaseEvent::ObjectDeleted`]
+    fn delete_and_publish(&self, rtree: &mut RTree, collection_id: &str, item_id: &str, now: u64) {
+        let previous = if self.events.has_subscribers() {
+            rtree.get(item_id)
+        } else {
+            None
+        };
+        rtree.delete(item_id);
+        if let Some(previous) = previous {
+            self.events.publish(DatabaseEvent::ObjectDeleted {
+                collection: collection_id.to_string(),
+                id: item_id.to_string(),
+                geometry: previous.geometry,
+                geojson: previous.geojson,
+                timestamp: now,
+            });
+        }
+    }
+
+    /// 异步获取所有 Collection 的名称
+    pub async fn collection_names(&self) -> Vec<String> {
+        let collections = self.collections.read().await;
+        let mut names: Vec<String> = collections.keys().cloned().collect();
+        if let Some(cold) = &self.cold {
+            names.extend(cold.unloaded_names());
+        }
+        names
+    }
+
+    /// 所有 collection 的名称和对象数量（按名称排序），已卸载的 collection 使用常驻元数据
+    pub async fn collection_counts(&self) -> Vec<(String, usize)> {
+        let collections = self.collections.read().await;
+        let mut counts = Vec::with_capacity(collections.len());
+        for (name, collection) in collections.iter() {
+            counts.push((name.clone(), collection.read().await.count()));
+        }
+        drop(collections);
+
+        if let Some(cold) = &self.cold {
+            counts.extend(cold.unloaded_counts());
+        }
+        counts.sort_unstable();
+        counts
+    }
+
+    /// 每个已加载 collection 的内存占用估算（按名称排序），已卸载的 collection 不占内存，不列出
+    ///
+    /// 需要遍历所有对象，只用于诊断（MEMORY 命令），不要放在热路径上
+    pub async fn memory_usage(&self) -> Vec<(String, MemoryUsage)> {
+        let collections = self.collections.read().await;
+        let mut usage = Vec::with_capacity(collections.len());
+        for (name, collection) in collections.iter() {
+            usage.push((name.clone(), collection.read().await.memory_usage()));
+        }
+        usage.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        usage
+    }
+
+    /// 单个 collection 的内存占用估算；不存在时返回 `None`，已卸载时为 0
+    pub async fn collection_memory_usage(&self, collection_id: &str) -> Option<MemoryUsage> {
         let collections = self.collections.read().await;
-        collections.keys().cloned().collect()
+        if let Some(collection) = collections.get(collection_id) {
+            return Some(collection.read().await.memory_usage());
+        }
+        self.cold
+            .as_ref()
+            .filter(|cold| cold.is_unloaded(collection_id))
+            .map(|_| MemoryUsage::default())
+    }
+
+    /// 删除名称匹配 glob 模式的所有 collection，返回被删除的 collection 及其对象数量
+    ///
+    /// `dry_run` 为 true 时只列出将被删除的 collection，不做任何修改。
+    /// 每个 collection 单独删除并各自写入一条 AOF DROP 记录
+    pub async fn drop_matching(
+        &self,
+        pattern: &str,
+        dry_run: bool,
+    ) -> Result<Vec<(String, usize)>> {
+        self.drop_matching_in(Namespace::default(), pattern, dry_run)
+            .await
+    }
+
+    /// 与 [`drop_matching`](Self::drop_matching) 相同，但只匹配命名空间中的 collection，
+    /// 模式和返回的名称都是命名空间中的名称（不带 `@n:` 前缀）
+    pub async fn drop_matching_in(
+        &self,
+        namespace: Namespace,
+        pattern: &str,
+        dry_run: bool,
+    ) -> Result<Vec<(String, usize)>> {
+        let pattern = GlobPattern::new(pattern);
+        let mut matched: Vec<(String, usize)> = self
+            .collection_counts()
+            .await
+            .into_iter()
+            .filter_map(|(name, count)| {
+                let name = namespace.unscope(&name)?;
+                pattern.matches(name).then(|| (name.to_string(), count))
+            })
+            .collect();
+
+        if !dry_run {
+            self.check_disk_space()?;
+            for (name, count) in matched.iter_mut() {
+                *count = self.drop_collection(&namespace.scope(name)).await?;
+            }
+        }
+
+        Ok(matched)
     }
 
     /// 异步删除整个 Collection，返回删除的项目数量
     pub async fn drop_collection(&self, collection_id: &str) -> Result<usize> {
+        self.check_disk_space()?;
         let mut collections = self.collections.write().await;
 
+        // 拿住 collection 的写锁，等待正在进行的写操作完成
+        let rtree = match collections.get(collection_id) {
+            Some(collection) => Some(Arc::clone(collection).write_owned().await),
+            None => None,
+        };
+
+        // 先拿到 AOF 锁，删除和 AOF 记录之间不再有 await，保证取消安全
+        let mut aof = self.lock_aof().await;
+
         // 1. 先从内存删除并获取统计信息（Redis 风格：内存优先）
-        let count = if let Some(collection) = collections.get(collection_id) {
-            let rtree = collection.read().await;
-            rtree.count()
-        } else {
-            0 // collection 不存在，返回 0
+        // 标记为已移除，在写锁上等待的写操作会重新查找而不是写入被删除的树
+        let mut existed = rtree.is_some();
+        let count = match rtree {
+            Some(mut rtree) => {
+                rtree.detach();
+                rtree.count()
+            }
+            None => 0, // collection 不存在，返回 0
         };
 
+        // 清理冷数据：已卸载的 collection 删除磁盘文件，并使用常驻元数据中的数量
+        let cold_count = self
+            .cold
+            .as_ref()
+            .and_then(|cold| cold.remove(collection_id));
+        existed |= cold_count.is_some();
+        let count = cold_count.unwrap_or(count);
+
         // 删除 collection
         collections.remove(collection_id);
+        if existed && self.events.has_subscribers() {
+            self.events.publish(DatabaseEvent::CollectionDropped {
+                collection: collection_id.to_string(),
+                objects: count,
+                timestamp: self.clock.unix_nanos(),
+            });
+        }
 
         // 释放写锁（AOF 写入可能较慢，不需要持有锁）
         drop(collections);
 
         // 2. 内存删除成功后，再记录 AOF（如果启用）
-        if let Some(aof_writer) = &self.aof_writer {
-            let cmd = AofCommand::drop(collection_id.to_string());
-            let mut writer = aof_writer.lock().await;
-            writer.append(&cmd)?;
+        if let Some(writer) = aof.as_mut() {
+            let cmd =
+                AofCommand::drop(collection_id.to_string()).with_timestamp(self.clock.unix_nanos());
+            writer.append(&cmd).map_err(aof_write_error)?;
         }
 
         Ok(count)
@@ -258,19 +2139,121 @@ impl GeoDatabase {
     pub async fn stats(&self) -> Result<DatabaseStats> {
         let collections = self.collections.read().await;
         let mut total_items = 0;
+        let mut expiring_items = 0;
 
         // 需要访问每个collection来获取item数量
         for collection in collections.values() {
             let data = collection.read().await;
             total_items += data.count();
+            expiring_items += data.expiring_count();
         }
 
+        // 已卸载的 collection 使用常驻元数据统计
+        let (unloaded_collections, unloaded_items) = self
+            .cold
+            .as_ref()
+            .map(|cold| cold.unloaded_totals())
+            .unwrap_or((0, 0));
+
         Ok(DatabaseStats {
-            collections_count: collections.len(),
-            total_items,
+            collections_count: collections.len() + unloaded_collections,
+            total_items: total_items + unloaded_items,
+            unloaded_collections,
+            expiring_items,
+        })
+    }
+
+    /// 单个 collection 的对象数、范围和内存估算；不存在时返回 `None`
+    ///
+    /// 已卸载的 collection 不会被加载，只有常驻元数据中的对象数
+    pub async fn collection_stats(&self, collection_id: &str) -> Option<CollectionStats> {
+        let collections = self.collections.read().await;
+        if let Some(collection) = collections.get(collection_id) {
+            let rtree = collection.read().await;
+            return Some(CollectionStats {
+                objects: rtree.count(),
+                bounds: rtree.root_mbr().copied().filter(|_| rtree.count() > 0),
+                memory: rtree.memory_usage(),
+                unloaded: false,
+            });
+        }
+        let cold = self.cold.as_ref()?;
+        let (_, objects) = cold
+            .unloaded_counts()
+            .into_iter()
+            .find(|(name, _)| name == collection_id)?;
+        Some(CollectionStats {
+            objects,
+            bounds: None,
+            memory: MemoryUsage::default(),
+            unloaded: true,
         })
     }
 
+    /// 检查 collection 的 R-tree 结构（见 [`RTree::validate`]），不存在时返回 `None`
+    ///
+    /// 已卸载的 collection 会被重新加载；检查期间持有读锁，写入需要等待
+    pub async fn validate_collection(&self, collection_id: &str) -> Result<Option<TreeReport>> {
+        let Some(collection) = self.collection(collection_id).await? else {
+            return Ok(None);
+        };
+        let rtree = collection.read().await;
+        Ok(Some(rtree.validate()))
+    }
+
+    /// 异步获取持久化状态信息
+    pub async fn persistence_info(&self) -> PersistenceInfo {
+        let mut info = self.aof_info().await;
+        info.loading = self.is_loading();
+        if let Some(snapshot) = &self.snapshot {
+            info.snapshot_enabled = true;
+            info.snapshot_in_progress = snapshot.job().in_progress();
+            info.snapshot_last_save_time = snapshot.job().last_success();
+            info.snapshot_last_failed = snapshot.job().last_failed();
+        }
+        info
+    }
+
+    async fn aof_info(&self) -> PersistenceInfo {
+        let info = self.aof_writer_info().await;
+        PersistenceInfo {
+            aof_rewrite_in_progress: self.aof_rewrite.in_progress(),
+            aof_last_rewrite_time: self.aof_rewrite.last_success(),
+            aof_last_rewrite_failed: self.aof_rewrite.last_failed(),
+            ..info
+        }
+    }
+
+    async fn aof_writer_info(&self) -> PersistenceInfo {
+        let Some(aof_writer) = &self.aof_writer else {
+            return PersistenceInfo {
+                ephemeral: self.is_ephemeral(),
+                ..Default::default()
+            };
+        };
+
+        let writer = aof_writer.lock().await;
+        PersistenceInfo {
+            aof_enabled: true,
+            ephemeral: false,
+            sync_policy: writer.config().sync_policy.as_str(),
+            effective_sync_policy: writer.effective_sync_policy().as_str(),
+            write_stalled: writer.is_stalled(),
+            last_fsync_latency_ms: writer
+                .last_fsync_latency()
+                .map(|latency| latency.as_secs_f64() * 1000.0),
+            bytes_written: writer.bytes_written(),
+            buffered_bytes: writer.buffered_bytes(),
+            last_fsync_time: writer
+                .last_fsync_at()
+                .and_then(|at| at.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|since_epoch| since_epoch.as_secs()),
+            aof_current_size: writer.file_size(),
+            aof_base_size: writer.rewrite_base_size(),
+            ..Default::default()
+        }
+    }
+
     /// 异步空间查询：返回与指定几何体相交或包含在其中的所有对象
     /// within: true = 完全包含在 geometry 内部, false = 与 geometry 相交
     pub async fn intersects(
@@ -279,77 +2262,472 @@ impl GeoDatabase {
         geometry: &Geometry,
         limit: usize,
         within: bool,
+    ) -> Result<Vec<GeoItem>> {
+        self.intersects_ordered(collection_id, geometry, limit, within, SearchOrder::Tree)
+            .await
+    }
+
+    /// 带遍历顺序提示的空间查询，limit 在树遍历过程中生效
+    pub async fn intersects_ordered(
+        &self,
+        collection_id: &str,
+        geometry: &Geometry,
+        limit: usize,
+        within: bool,
+        order: SearchOrder,
     ) -> Result<Vec<GeoItem>> {
         // 1. 获取 collection
-        let collections = self.collections.read().await;
-        let collection = match collections.get(collection_id) {
-            Some(coll) => coll.clone(),
+        let collection = match self.collection(collection_id).await? {
+            Some(coll) => coll,
+            None => return Ok(Vec::new()), // collection 不存在，返回空结果
+        };
+
+        // 2. 获取 collection 数据的读锁
+        let data = collection.read().await;
+
+        let search_results = data.search_ordered(geometry, limit, within, order);
+
+        Ok(search_results)
+    }
+
+    /// 带标签过滤的空间查询：只返回同时带有所有 `tags` 的对象
+    pub async fn intersects_tagged(
+        &self,
+        collection_id: &str,
+        geometry: &Geometry,
+        limit: usize,
+        within: bool,
+        order: SearchOrder,
+        tags: &[String],
+    ) -> Result<Vec<GeoItem>> {
+        let collection = match self.collection(collection_id).await? {
+            Some(coll) => coll,
+            None => return Ok(Vec::new()),
+        };
+
+        let data = collection.read().await;
+        Ok(data.search_tagged(geometry, limit, within, order, tags))
+    }
+
+    /// 按 ID 分页遍历 collection 中的对象 ID（包括 STRING 对象），collection 不存在时返回空页
+    pub async fn scan(
+        &self,
+        collection_id: &str,
+        cursor: &ScanCursor,
+        count: usize,
+    ) -> Result<Page<String>> {
+        let collection = match self.collection(collection_id).await? {
+            Some(coll) => coll,
+            None => return Ok(Page::default()),
+        };
+
+        let data = collection.read().await;
+        Ok(data.scan_ids(cursor, count))
+    }
+
+    /// 按 ID 分页的空间查询，`tags` 不为空时只返回同时带有所有标签的对象
+    pub async fn intersects_page(
+        &self,
+        collection_id: &str,
+        geometry: &Geometry,
+        within: bool,
+        tags: &[String],
+        cursor: &ScanCursor,
+        count: usize,
+    ) -> Result<Page> {
+        let collection = match self.collection(collection_id).await? {
+            Some(coll) => coll,
+            None => return Ok(Page::default()),
+        };
+
+        let data = collection.read().await;
+        Ok(data.search_page(geometry, within, tags, cursor, count))
+    }
+
+    /// 空间查询，只返回匹配对象的 ID；`tags` 不为空时只返回同时带有所有标签的对象
+    ///
+    /// 结果较大的回复先取得 ID，再用 [`items`](Self::items) 分批复制对象，
+    /// 不必在内存中同时保存所有匹配对象的 GeoJSON
+    pub async fn intersects_ids(
+        &self,
+        collection_id: &str,
+        geometry: &Geometry,
+        limit: usize,
+        within: bool,
+        order: SearchOrder,
+        tags: &[String],
+    ) -> Result<Vec<String>> {
+        let collection = match self.collection(collection_id).await? {
+            Some(coll) => coll,
+            None => return Ok(Vec::new()),
+        };
+
+        let data = collection.read().await;
+        Ok(if tags.is_empty() {
+            data.search_ids(geometry, limit, within, order)
+        } else {
+            data.search_tagged_ids(geometry, limit, within, order, tags)
+        })
+    }
+
+    /// 与 [`intersects_page`](Self::intersects_page) 相同，但页中只有对象 ID
+    pub async fn intersects_page_ids(
+        &self,
+        collection_id: &str,
+        geometry: &Geometry,
+        within: bool,
+        tags: &[String],
+        cursor: &ScanCursor,
+        count: usize,
+    ) -> Result<Page<String>> {
+        let collection = match self.collection(collection_id).await? {
+            Some(coll) => coll,
+            None => return Ok(Page::default()),
+        };
+
+        let data = collection.read().await;
+        Ok(data.search_page_ids(geometry, within, tags, cursor, count))
+    }
+
+    /// 按 ID 批量复制对象，结果与 `ids` 一一对应，不存在（或已被删除）的对象为 None
+    ///
+    /// 只在复制期间持有 collection 的读锁；不做惰性过期，也不计入命中统计
+    pub async fn items(&self, collection_id: &str, ids: &[String]) -> Result<Vec<Option<GeoItem>>> {
+        let collection = match self.collection(collection_id).await? {
+            Some(coll) => coll,
+            None => return Ok(vec![None; ids.len()]),
+        };
+
+        let data = collection.read().await;
+        Ok(ids.iter().map(|id| data.get(id)).collect())
+    }
+
+    /// 获取对象的标签，collection 或对象不存在时返回空列表
+    pub async fn tags(&self, collection_id: &str, item_id: &str) -> Result<Vec<String>> {
+        let collection = match self.collection(collection_id).await? {
+            Some(coll) => coll,
+            None => return Ok(Vec::new()),
+        };
+
+        let data = collection.read().await;
+        Ok(data.get_tags(item_id))
+    }
+
+    /// 空间聚合：统计范围内每个网格/六边形格子的对象数量，以及可选数值字段的和与平均值
+    pub async fn aggregate(
+        &self,
+        collection_id: &str,
+        bounds: &Rectangle,
+        binning: Binning,
+        field: Option<&str>,
+    ) -> Result<Vec<BinStat>> {
+        let collection = self.indexed_collection(collection_id).await?;
+
+        let data = collection.read().await;
+        Ok(data.aggregate(bounds, binning, field))
+    }
+
+    /// DBSCAN 密度聚类：`eps` 为邻域半径（米），`min_points` 为核心点的最少邻居数
+    pub async fn cluster(
+        &self,
+        collection_id: &str,
+        eps: f64,
+        min_points: usize,
+        bounds: Option<&Rectangle>,
+    ) -> Result<Vec<Cluster>> {
+        let collection = self.indexed_collection(collection_id).await?;
+
+        let data = collection.read().await;
+        Ok(data.cluster(eps, min_points, bounds))
+    }
+
+    /// 计算同一 collection 中两个面状对象的交集/并集/差集/对称差
+    /// collection 不存在时返回 NOINDEX，任一对象不存在时返回 None
+    pub async fn overlay(
+        &self,
+        collection_id: &str,
+        first_id: &str,
+        second_id: &str,
+        op: OverlayOp,
+    ) -> Result<Option<Geometry>> {
+        let collection = self.indexed_collection(collection_id).await?;
+
+        let data = collection.read().await;
+        data.overlay(first_id, second_id, op)
+            .map_err(|message| CommandError::new(ErrorCode::WrongType, message).into())
+    }
+
+    /// 计算 collection 或其中与 `area` 相交的对象的外包几何（凸包/凹包）
+    pub async fn hull(
+        &self,
+        collection_id: &str,
+        area: Option<&Geometry>,
+        kind: HullKind,
+    ) -> Result<Option<Geometry>> {
+        let collection = self.indexed_collection(collection_id).await?;
+
+        let data = collection.read().await;
+        Ok(data.hull(area, kind))
+    }
+
+    /// 查找最近的 k 个对象（KNN 查询）
+    ///
+    /// # Arguments
+    /// * `collection_id` - Collection 名称
+    /// * `query_lon` - 查询点的经度
+    /// * `query_lat` - 查询点的纬度
+    /// * `k` - 返回最近的 k 个对象（0 表示不限制数量，配合 max_radius 使用）
+    /// * `max_radius` - 最大搜索半径（米），None 表示不限制半径
+    ///
+    /// # Returns
+    ///
+    /// 返回一个元组数组 `Vec<(GeoItem, f64)>`，其中：
+    /// - `GeoItem` - 查询到的地理对象
+    /// - `f64` - 该对象到查询点的距离（米）
+    ///
+    /// 结果按距离升序排列（最近的在前）
+    ///
+    /// # Note
+    ///
+    /// k 和 max_radius 至少需要提供一个：
+    /// - 如果只提供 k，返回最近的 k 个对象
+    /// - 如果只提供 max_radius，返回半径内所有对象
+    /// - 如果两者都提供，返回半径内最近的 k 个对象
+    pub async fn nearby(
+        &self,
+        collection_id: &str,
+        query_lon: f64,
+        query_lat: f64,
+        k: usize,
+        max_radius: Option<f64>,
+    ) -> Result<Vec<(GeoItem, f64)>> {
+        self.nearby_approx(collection_id, query_lon, query_lat, k, max_radius, 0.0)
+            .await
+    }
+
+    /// 近似 KNN 查询，`epsilon` 控制提前终止的激进程度（0 表示精确查询）
+    ///
+    /// 参见 [`RTree::nearby_approx`](crate::rtree::RTree::nearby_approx)
+    pub async fn nearby_approx(
+        &self,
+        collection_id: &str,
+        query_lon: f64,
+        query_lat: f64,
+        k: usize,
+        max_radius: Option<f64>,
+        epsilon: f64,
+    ) -> Result<Vec<(GeoItem, f64)>> {
+        // 1. 获取 collection
+        let collection = match self.collection(collection_id).await? {
+            Some(coll) => coll,
             None => return Ok(Vec::new()), // collection 不存在，返回空结果
         };
-        drop(collections); // 早释放外层锁
 
-        // 2. 获取 collection 数据的读锁
+        // 2. 获取 collection 数据的读锁
+        let data = collection.read().await;
+
+        // 3. 调用 KNN 算法
+        let knn_results = data.nearby_approx(query_lon, query_lat, k, max_radius, epsilon);
+
+        Ok(knn_results)
+    }
+
+    /// 区域内的 KNN 查询：只在完全包含于 `region` 内的对象中查找
+    ///
+    /// 参见 [`RTree::nearby_within`](crate::rtree::RTree::nearby_within)
+    #[allow(clippy::too_many_arguments)]
+    pub async fn nearby_within(
+        &self,
+        collection_id: &str,
+        query_lon: f64,
+        query_lat: f64,
+        k: usize,
+        max_radius: Option<f64>,
+        epsilon: f64,
+        region: &Geometry,
+    ) -> Result<Vec<(GeoItem, f64)>> {
+        let collection = match self.collection(collection_id).await? {
+            Some(coll) => coll,
+            None => return Ok(Vec::new()),
+        };
+
         let data = collection.read().await;
+        Ok(data.nearby_within(query_lon, query_lat, k, max_radius, epsilon, region))
+    }
 
-        let search_results = data.search(geometry, limit, within);
+    /// 带标签过滤的 KNN 查询：只在同时带有所有 `tags` 的对象中查找，
+    /// `region` 不为 None 时只保留完全包含在其中的对象，同时还要满足 `filter`
+    #[allow(clippy::too_many_arguments)]
+    pub async fn nearby_tagged(
+        &self,
+        collection_id: &str,
+        query_lon: f64,
+        query_lat: f64,
+        k: usize,
+        max_radius: Option<f64>,
+        tags: &[String],
+        region: Option<&Geometry>,
+        filter: &ObjectFilter,
+        metric: DistanceMetric,
+    ) -> Result<Vec<(GeoItem, f64)>> {
+        let collection = match self.collection(collection_id).await? {
+            Some(coll) => coll,
+            None => return Ok(Vec::new()),
+        };
 
-        Ok(search_results)
+        let data = collection.read().await;
+        Ok(data.nearby_tagged(
+            query_lon, query_lat, k, max_radius, tags, region, filter, metric,
+        ))
     }
 
-    /// 查找最近的 k 个对象（KNN 查询）
-    ///
-    /// # Arguments
-    /// * `collection_id` - Collection 名称
-    /// * `query_lon` - 查询点的经度
-    /// * `query_lat` - 查询点的纬度
-    /// * `k` - 返回最近的 k 个对象（0 表示不限制数量，配合 max_radius 使用）
-    /// * `max_radius` - 最大搜索半径（米），None 表示不限制半径
-    ///
-    /// # Returns
-    ///
-    /// 返回一个元组数组 `Vec<(GeoItem, f64)>`，其中：
-    /// - `GeoItem` - 查询到的地理对象
-    /// - `f64` - 该对象到查询点的距离（米）
-    ///
-    /// 结果按距离升序排列（最近的在前）
-    ///
-    /// # Note
-    ///
-    /// k 和 max_radius 至少需要提供一个：
-    /// - 如果只提供 k，返回最近的 k 个对象
-    /// - 如果只提供 max_radius，返回半径内所有对象
-    /// - 如果两者都提供，返回半径内最近的 k 个对象
-    pub async fn nearby(
+    /// 带过滤条件（几何类型、属性范围）和距离度量的 KNN 查询，过滤在遍历时进行，
+    /// 参见 [`RTree::nearby_filtered`](crate::rtree::RTree::nearby_filtered)
+    #[allow(clippy::too_many_arguments)]
+    pub async fn nearby_filtered(
         &self,
         collection_id: &str,
         query_lon: f64,
         query_lat: f64,
         k: usize,
         max_radius: Option<f64>,
+        epsilon: f64,
+        region: Option<&Geometry>,
+        filter: &ObjectFilter,
+        metric: DistanceMetric,
     ) -> Result<Vec<(GeoItem, f64)>> {
-        // 1. 获取 collection
-        let collections = self.collections.read().await;
-        let collection = match collections.get(collection_id) {
-            Some(coll) => coll.clone(),
-            None => return Ok(Vec::new()), // collection 不存在，返回空结果
+        let collection = match self.collection(collection_id).await? {
+            Some(coll) => coll,
+            None => return Ok(Vec::new()),
         };
-        drop(collections); // 早释放外层锁
 
-        // 2. 获取 collection 数据的读锁
         let data = collection.read().await;
+        Ok(data.nearby_filtered(
+            query_lon, query_lat, k, max_radius, epsilon, region, filter, metric,
+        ))
+    }
 
-        // 3. 调用 KNN 算法
-        let knn_results = data.nearby(query_lon, query_lat, k, max_radius);
+    /// 将点吸附到 collection 中最近的线上，返回投影点和偏移距离（米）
+    pub async fn snap(
+        &self,
+        collection_id: &str,
+        query_lon: f64,
+        query_lat: f64,
+    ) -> Result<Option<SnapResult>> {
+        let collection = self.indexed_collection(collection_id).await?;
 
-        Ok(knn_results)
+        let data = collection.read().await;
+        Ok(data.snap(query_lon, query_lat))
     }
 }
 
+/// AOF 追加失败时返回 READONLY：数据无法持久化，客户端应退避后重试写入
+fn aof_write_error(error: AofError) -> CommandError {
+    CommandError::new(ErrorCode::ReadOnly, format!("AOF append failed: {}", error))
+}
+
 /// 数据库统计信息
 #[derive(Debug)]
 pub struct DatabaseStats {
     pub collections_count: usize,
     pub total_items: usize,
+    /// 已卸载到磁盘的 collection 数量（已计入 collections_count）
+    pub unloaded_collections: usize,
+    /// 已加载的 collection 中带 TTL 的对象数量
+    pub expiring_items: usize,
+}
+
+/// 单个 collection 的统计（STATS 命令）
+#[derive(Debug, Clone)]
+pub struct CollectionStats {
+    pub objects: usize,
+    /// 所有对象的外接矩形，collection 为空或已卸载时为 None
+    pub bounds: Option<Rectangle>,
+    /// 内存占用估算，已卸载时为 0
+    pub memory: MemoryUsage,
+    /// 是否已卸载到磁盘
+    pub unloaded: bool,
+}
+
+/// 对象的剩余存活时间（TTL 命令）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ttl {
+    /// 对象不存在（或已经过期）
+    Missing,
+    /// 对象没有过期时间
+    Persistent,
+    /// 对象在这段时间之后过期
+    Expires(Duration),
+}
+
+/// AOF 恢复报告
+#[derive(Debug, Default)]
+pub struct RecoveryReport {
+    /// 成功解析的命令数
+    pub commands: usize,
+    /// 解析失败的记录（错误描述）
+    pub parse_errors: Vec<String>,
+    /// 重放过程中发现的不一致（命令描述）
+    pub inconsistencies: Vec<String>,
+    /// AOF 总行数（包括空行）
+    pub total_lines: usize,
+    /// 序列号不大于已应用序列号、因此被跳过的记录数
+    pub skipped: usize,
+    /// 遇到的最大序列号
+    pub last_seq: u64,
+    /// 文件末尾写了一半的分帧记录，恢复时忽略（启动时已按 `aof.load_truncated` 截断）
+    pub corrupted_tail: Option<aof::CorruptedTail>,
+}
+
+/// 带序列号的记录是否已经应用到 collection
+fn already_applied(seq: u64, rtree: &RTree) -> bool {
+    seq != 0 && seq <= rtree.applied_seq()
+}
+
+impl RecoveryReport {
+    /// 是否完全一致（无解析错误、无不一致）
+    pub fn is_clean(&self) -> bool {
+        self.parse_errors.is_empty() && self.inconsistencies.is_empty()
+    }
+}
+
+/// 持久化状态信息
+#[derive(Debug, Default)]
+pub struct PersistenceInfo {
+    pub aof_enabled: bool,
+    /// 纯内存实例：没有任何数据写入磁盘
+    pub ephemeral: bool,
+    /// 配置的同步策略
+    pub sync_policy: &'static str,
+    /// 实际生效的同步策略（停顿降级时与配置不同）
+    pub effective_sync_policy: &'static str,
+    /// 是否检测到 fsync 停顿
+    pub write_stalled: bool,
+    /// 最近一次 fsync 耗时（毫秒）
+    pub last_fsync_latency_ms: Option<f64>,
+    pub bytes_written: u64,
+    /// 缓冲区中尚未写入文件的字节数
+    pub buffered_bytes: usize,
+    /// 最近一次 fsync 的 Unix 时间戳（秒）
+    pub last_fsync_time: Option<u64>,
+    /// 是否正在启动加载
+    pub loading: bool,
+    /// 是否启用了时间点快照（SAVE/BGSAVE）
+    pub snapshot_enabled: bool,
+    pub snapshot_in_progress: bool,
+    /// 最近一次成功保存快照的 Unix 时间戳（秒）
+    pub snapshot_last_save_time: Option<u64>,
+    /// 最近一次保存快照是否失败
+    pub snapshot_last_failed: bool,
+    pub aof_rewrite_in_progress: bool,
+    /// 最近一次成功重写 AOF 的 Unix 时间戳（秒）
+    pub aof_last_rewrite_time: Option<u64>,
+    /// 最近一次重写 AOF 是否失败
+    pub aof_last_rewrite_failed: bool,
+    /// AOF 文件当前大小（字节）
+    pub aof_current_size: u64,
+    /// 启动或最近一次重写后的 AOF 大小，自动重写按它计算增长比例
+    pub aof_base_size: u64,
 }
 
 #[cfg(test)]
@@ -668,29 +3046,639 @@ mod tests {
             db.set("cities", "beijing", &point1.to_string())
                 .await
                 .unwrap();
-            db.set("cities", "shanghai", &point2.to_string())
+            db.set("cities", "shanghai", &point2.to_string())
+                .await
+                .unwrap();
+
+            // 验证数据已写入
+            assert!(db.get("cities", "beijing").await.unwrap().is_some());
+            assert!(db.get("cities", "shanghai").await.unwrap().is_some());
+        }
+
+        // 2. 创建新的数据库实例并从 AOF 恢复
+        {
+            let config = AofConfig::new(aof_path.clone());
+            let db = GeoDatabase::with_aof(config).unwrap();
+
+            // 恢复数据
+            let (commands, errors) = db.recover_from_aof(aof_path).await.unwrap();
+            assert_eq!(commands, 2);
+            assert_eq!(errors, 0);
+
+            // 验证数据已恢复
+            assert!(db.get("cities", "beijing").await.unwrap().is_some());
+            assert!(db.get("cities", "shanghai").await.unwrap().is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_aof_recovers_tags() {
+        use crate::rtree::algorithms::aof::AofConfig;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("tags.aof");
+        let point = json!({"type": "Point", "coordinates": [116.4, 39.9]}).to_string();
+        let tags = vec!["bus".to_string(), "line42".to_string()];
+
+        {
+            let db = GeoDatabase::with_aof(AofConfig::new(aof_path.clone())).unwrap();
+            db.set_with_tags("fleet", "bus1", &point, &tags)
+                .await
+                .unwrap();
+            db.set("fleet", "car1", &point).await.unwrap();
+        }
+
+        let db = GeoDatabase::with_aof(AofConfig::new(aof_path.clone())).unwrap();
+        db.recover_from_aof(aof_path).await.unwrap();
+        assert_eq!(db.tags("fleet", "bus1").await.unwrap(), tags);
+        assert!(db.tags("fleet", "car1").await.unwrap().is_empty());
+
+        let found = db
+            .nearby_tagged(
+                "fleet",
+                116.4,
+                39.9,
+                10,
+                None,
+                &["line42".to_string()],
+                None,
+                &ObjectFilter::default(),
+                DistanceMetric::Haversine,
+            )
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0.id, "bus1");
+    }
+
+    #[tokio::test]
+    async fn test_update_geojson_is_recovered_from_aof() {
+        use crate::rtree::algorithms::aof::AofConfig;
+        use std::time::Duration;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("jset.aof");
+        let point = json!({"type": "Point", "coordinates": [116.4, 39.9]}).to_string();
+        let tags = vec!["bus".to_string()];
+
+        {
+            let db = GeoDatabase::with_aof(AofConfig::new(aof_path.clone())).unwrap();
+            db.set_with_ttl(
+                "fleet",
+                "bus1",
+                &point,
+                &tags,
+                Some(Duration::from_secs(600)),
+            )
+            .await
+            .unwrap();
+            let updated = db
+                .update_geojson("fleet", "bus1", |document| {
+                    document["driver"] = json!("Tom");
+                    Ok(true)
+                })
+                .await
+                .unwrap();
+            assert_eq!(updated, Some(true));
+            let missing = db
+                .update_geojson("fleet", "bus2", |_| Ok(true))
+                .await
+                .unwrap();
+            assert_eq!(missing, None);
+        }
+
+        // 修改后的文档、标签和过期时间都能从 AOF 恢复
+        let db = GeoDatabase::with_aof(AofConfig::new(aof_path.clone())).unwrap();
+        db.recover_from_aof(aof_path).await.unwrap();
+        let item = db.get("fleet", "bus1").await.unwrap().unwrap();
+        let document: serde_json::Value = serde_json::from_str(&item.geojson).unwrap();
+        assert_eq!(document["driver"], "Tom");
+        assert_eq!(db.tags("fleet", "bus1").await.unwrap(), tags);
+        assert!(matches!(
+            db.ttl("fleet", "bus1").await.unwrap(),
+            Ttl::Expires(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_set_with_ttl_is_recovered_from_aof() {
+        use crate::rtree::algorithms::aof::{AofConfig, AofSyncPolicy};
+        use crate::storage::clock::MockClock;
+        use std::time::Duration;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("set-ex.aof");
+        let point = json!({"type": "Point", "coordinates": [116.4, 39.9]}).to_string();
+        let clock = Arc::new(MockClock::new(1_000_000_000));
+        let config = || AofConfig::new(aof_path.clone()).set_sync_policy(AofSyncPolicy::Always);
+
+        {
+            let db = GeoDatabase::with_aof(config())
+                .unwrap()
+                .with_clock(clock.clone());
+            db.set_with_ttl("fleet", "bus1", &point, &[], Some(Duration::from_secs(10)))
+                .await
+                .unwrap();
+            db.set("fleet", "bus2", &point).await.unwrap();
+        }
+
+        // 过期时刻是绝对时间：恢复时已经过期的对象不会重新出现
+        clock.advance(Duration::from_secs(10));
+        let db = GeoDatabase::with_aof(config())
+            .unwrap()
+            .with_clock(clock.clone());
+        let report = db.recover_from_aof_with_report(aof_path).await.unwrap();
+        assert!(report.is_clean());
+        assert!(db.get("fleet", "bus1").await.unwrap().is_none());
+        assert_eq!(db.ttl("fleet", "bus2").await.unwrap(), Ttl::Persistent);
+        assert_eq!(db.expired_objects(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_then_aof_tail_recovery() {
+        use crate::rtree::algorithms::aof::{AofConfig, AofSyncPolicy};
+        use crate::storage::clock::MockClock;
+        use std::time::Duration;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("snapshot.aof");
+        let snapshot_path = temp_dir.path().join("dump.spdb");
+        let point = json!({"type": "Point", "coordinates": [116.4, 39.9]}).to_string();
+        let clock = Arc::new(MockClock::new(1_000_000_000));
+        let open = || {
+            GeoDatabase::with_aof(
+                AofConfig::new(aof_path.clone()).set_sync_policy(AofSyncPolicy::Always),
+            )
+            .unwrap()
+            .with_clock(clock.clone())
+            .with_snapshot_path(snapshot_path.clone())
+        };
+
+        {
+            let db = open();
+            db.set("fleet", "bus1", &point).await.unwrap();
+            db.set_with_ttl("fleet", "bus2", &point, &[], Some(Duration::from_secs(60)))
+                .await
+                .unwrap();
+            db.set("zones", "z1", &point).await.unwrap();
+            let summary = db.save_snapshot().await.unwrap();
+            assert_eq!((summary.collections, summary.objects), (2, 3));
+
+            // 快照之后的写入只在 AOF 中
+            db.delete("fleet", "bus1").await.unwrap();
+            db.set("fleet", "bus3", &point).await.unwrap();
+            db.drop_collection("zones").await.unwrap();
+        }
+
+        let db = open();
+        let summary = db.load_snapshot(&snapshot_path).await.unwrap().unwrap();
+        assert_eq!((summary.collections, summary.objects), (2, 3));
+        let report = db
+            .recover_from_aof_with_report(aof_path.clone())
+            .await
+            .unwrap();
+        assert!(report.is_clean(), "{:?}", report.inconsistencies);
+        // 快照已经包含的 3 条 SET 和 1 条 EXPIRE 被跳过
+        assert_eq!(report.skipped, 4);
+        assert!(db.get("fleet", "bus1").await.unwrap().is_none());
+        assert!(db.get("fleet", "bus3").await.unwrap().is_some());
+        assert!(matches!(
+            db.ttl("fleet", "bus2").await.unwrap(),
+            Ttl::Expires(_)
+        ));
+        assert!(db
+            .collection_counts()
+            .await
+            .iter()
+            .all(|(name, _)| name != "zones"));
+
+        // 之后的写入接着快照和 AOF 中最大的序列号编号，下次恢复不会被误跳过
+        db.set("fleet", "bus4", &point).await.unwrap();
+        drop(db);
+        let db = open();
+        db.load_snapshot(&snapshot_path).await.unwrap().unwrap();
+        db.recover_from_aof(aof_path).await.unwrap();
+        assert!(db.get("fleet", "bus4").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_aof_rewrite_recovery() {
+        use crate::rtree::algorithms::aof::{self, AofConfig, AofSyncPolicy};
+        use crate::storage::clock::MockClock;
+        use std::time::Duration;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("rewrite.aof");
+        let snapshot_path = temp_dir.path().join("dump.spdb");
+        let point = json!({"type": "Point", "coordinates": [116.4, 39.9]}).to_string();
+        let clock = Arc::new(MockClock::new(1_000_000_000));
+        let open = || {
+            GeoDatabase::with_aof(
+                AofConfig::new(aof_path.clone()).set_sync_policy(AofSyncPolicy::Always),
+            )
+            .unwrap()
+            .with_clock(clock.clone())
+            .with_snapshot_path(snapshot_path.clone())
+        };
+
+        {
+            let db = open();
+            for _ in 0..10 {
+                db.set("fleet", "bus1", &point).await.unwrap();
+            }
+            db.set_with_ttl("fleet", "bus2", &point, &[], Some(Duration::from_secs(60)))
+                .await
+                .unwrap();
+            db.set("zones", "z1", &point).await.unwrap();
+            db.save_snapshot().await.unwrap();
+
+            // 快照之后删除的 collection 在重写后的 AOF 中没有 DROP 记录
+            clock.advance(Duration::from_secs(1));
+            db.drop_collection("zones").await.unwrap();
+            let before = std::fs::metadata(&aof_path).unwrap().len();
+            let size = db.rewrite_aof().await.unwrap();
+            assert!(size < before);
+            assert_eq!(size, std::fs::metadata(&aof_path).unwrap().len());
+            assert_eq!(aof::rewritten_at(&aof_path).unwrap(), Some(2_000_000_000));
+
+            let info = db.persistence_info().await;
+            assert_eq!(info.aof_last_rewrite_time, Some(2));
+            assert_eq!((info.aof_current_size, info.aof_base_size), (size, size));
+
+            // 重写之后的写入追加到新文件
+            db.set("fleet", "bus3", &point).await.unwrap();
+        }
+
+        // 快照早于重写，不加载
+        let db = open();
+        assert!(db.load_snapshot(&snapshot_path).await.unwrap().is_none());
+        let report = db
+            .recover_from_aof_with_report(aof_path.clone())
+            .await
+            .unwrap();
+        assert!(report.is_clean(), "{:?}", report.inconsistencies);
+        for id in ["bus1", "bus2", "bus3"] {
+            assert!(db.get("fleet", id).await.unwrap().is_some());
+        }
+        assert!(matches!(
+            db.ttl("fleet", "bus2").await.unwrap(),
+            Ttl::Expires(_)
+        ));
+        assert!(db
+            .collection_counts()
+            .await
+            .iter()
+            .all(|(name, _)| name != "zones"));
+
+        // 重写之后的快照与 AOF 组合使用，重写时不编号的记录被跳过
+        clock.advance(Duration::from_secs(1));
+        db.save_snapshot().await.unwrap();
+        db.delete("fleet", "bus1").await.unwrap();
+        drop(db);
+
+        let db = open();
+        assert!(db.load_snapshot(&snapshot_path).await.unwrap().is_some());
+        let report = db.recover_from_aof_with_report(aof_path).await.unwrap();
+        assert!(report.is_clean(), "{:?}", report.inconsistencies);
+        assert!(db.get("fleet", "bus1").await.unwrap().is_none());
+        assert!(db.get("fleet", "bus3").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_fence_channels_are_recovered() {
+        use crate::rtree::algorithms::aof::{AofConfig, AofSyncPolicy};
+        use crate::storage::clock::MockClock;
+        use std::time::Duration;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("channels.aof");
+        let snapshot_path = temp_dir.path().join("dump.spdb");
+        let clock = Arc::new(MockClock::new(1_000_000_000));
+        let open = || {
+            GeoDatabase::with_aof(
+                AofConfig::new(aof_path.clone()).set_sync_policy(AofSyncPolicy::Always),
+            )
+            .unwrap()
+            .with_clock(clock.clone())
+            .with_snapshot_path(snapshot_path.clone())
+        };
+        let definition = |radius: &str| -> Vec<String> {
+            [
+                "NEARBY", "fleet", "POINT", "116.4", "39.9", "RADIUS", radius,
+            ]
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+        };
+        let names = |db: &GeoDatabase| -> Vec<String> {
+            db.channel_definitions()
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect()
+        };
+
+        {
+            let db = open();
+            db.set_channel("depot", definition("100")).await.unwrap();
+            db.set_channel("warehouse", definition("500"))
+                .await
+                .unwrap();
+            db.set_channel("depot", definition("200")).await.unwrap();
+            assert!(db.delete_channel("warehouse").await.unwrap());
+            assert!(!db.delete_channel("warehouse").await.unwrap());
+            db.save_snapshot().await.unwrap();
+            // 快照之后的修改只在 AOF 中
+            db.set_channel("yard", definition("300")).await.unwrap();
+            assert!(db.delete_channel("depot").await.unwrap());
+        }
+
+        // 只重放 AOF
+        let db = open();
+        db.recover_from_aof(aof_path.clone()).await.unwrap();
+        assert_eq!(names(&db), vec!["yard"]);
+        drop(db);
+
+        // 快照加上 AOF 尾部
+        let db = open();
+        db.load_snapshot(&snapshot_path).await.unwrap().unwrap();
+        assert_eq!(names(&db), vec!["depot"]);
+        assert_eq!(db.channel_definitions()[0].1, definition("200"));
+        db.recover_from_aof(aof_path.clone()).await.unwrap();
+        assert_eq!(names(&db), vec!["yard"]);
+
+        // 重写后的 AOF 保留当前的频道
+        clock.advance(Duration::from_secs(1));
+        db.rewrite_aof().await.unwrap();
+        db.set_channel("gate", definition("50")).await.unwrap();
+        drop(db);
+        let db = open();
+        assert!(db.load_snapshot(&snapshot_path).await.unwrap().is_none());
+        db.recover_from_aof(aof_path).await.unwrap();
+        assert_eq!(names(&db), vec!["gate", "yard"]);
+        assert_eq!(db.channel_definitions()[1].1, definition("300"));
+    }
+
+    #[tokio::test]
+    async fn test_aof_format_switch_and_rewrite() {
+        use crate::rtree::algorithms::aof::{self, AofConfig, AofFormat};
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("framed.aof");
+        let point = json!({"type": "Point", "coordinates": [116.4, 39.9]}).to_string();
+
+        // 先写 JSON Lines，切换为分帧格式后继续追加，文件中两种格式混合
+        {
+            let db = GeoDatabase::with_aof(AofConfig::new(aof_path.clone())).unwrap();
+            db.set("fleet", "bus1", &point).await.unwrap();
+        }
+        let open = || {
+            GeoDatabase::with_aof(AofConfig::new(aof_path.clone()).with_format(AofFormat::Framed))
+                .unwrap()
+        };
+        {
+            let db = open();
+            db.recover_from_aof(aof_path.clone()).await.unwrap();
+            db.set("fleet", "bus2", &point).await.unwrap();
+            db.delete("fleet", "bus1").await.unwrap();
+        }
+        let content = std::fs::read_to_string(&aof_path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert!(lines[0].starts_with('{'));
+        assert!(lines[1..].iter().all(|line| line.starts_with("#1 ")));
+
+        // 重写后整个文件使用分帧格式
+        {
+            let db = open();
+            let report = db
+                .recover_from_aof_with_report(aof_path.clone())
+                .await
+                .unwrap();
+            assert!(report.is_clean(), "{:?}", report.parse_errors);
+            assert_eq!(report.last_seq, 3);
+            db.rewrite_aof().await.unwrap();
+            db.set("fleet", "bus3", &point).await.unwrap();
+        }
+        let content = std::fs::read_to_string(&aof_path).unwrap();
+        assert!(content.lines().all(|line| line.starts_with("#1 ")));
+        assert!(aof::rewritten_at(&aof_path).unwrap().is_some());
+
+        let db = open();
+        let report = db.recover_from_aof_with_report(aof_path).await.unwrap();
+        assert!(report.is_clean(), "{:?}", report.parse_errors);
+        assert!(report.corrupted_tail.is_none());
+        assert!(db.get("fleet", "bus1").await.unwrap().is_none());
+        assert!(db.get("fleet", "bus2").await.unwrap().is_some());
+        assert!(db.get("fleet", "bus3").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_aof_recovery_bulk_loads_index() {
+        use crate::rtree::algorithms::aof::{AofConfig, AofSyncPolicy};
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("bulk.aof");
+        let open = || {
+            GeoDatabase::with_aof(
+                AofConfig::new(aof_path.clone()).set_sync_policy(AofSyncPolicy::Always),
+            )
+            .unwrap()
+        };
+        let point = |i: usize| {
+            json!({"type": "Point", "coordinates": [(i % 20) as f64, (i / 20) as f64]}).to_string()
+        };
+
+        {
+            let db = open();
+            for i in 0..400 {
+                db.set("fleet", &i.to_string(), &point(i)).await.unwrap();
+            }
+            for i in (0..400).step_by(2) {
+                db.delete("fleet", &i.to_string()).await.unwrap();
+            }
+        }
+
+        let db = open();
+        db.recover_from_aof(aof_path).await.unwrap();
+        let area = json_to_geometry(&json!({
+            "type": "Polygon",
+            "coordinates": [[[-0.5, -0.5], [9.5, -0.5], [9.5, 4.5], [-0.5, 4.5], [-0.5, -0.5]]]
+        }));
+        // 10 x 5 个点中奇数编号的一半
+        let found = db.intersects("fleet", &area, 0, false).await.unwrap();
+        assert_eq!(found.len(), 25);
+        assert!(found
+            .iter()
+            .all(|item| item.id.parse::<usize>().unwrap() % 2 == 1));
+
+        // 恢复之后的写入照常维护索引
+        db.set("fleet", "new", &point(0)).await.unwrap();
+        db.delete("fleet", "1").await.unwrap();
+        let found = db.intersects("fleet", &area, 0, false).await.unwrap();
+        assert_eq!(found.len(), 25);
+    }
+
+    #[tokio::test]
+    async fn test_import_geojson_bulk_loads_and_replays() {
+        use crate::rtree::algorithms::aof::{AofConfig, AofSyncPolicy};
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("import.aof");
+        let open = || {
+            GeoDatabase::with_aof(
+                AofConfig::new(aof_path.clone()).set_sync_policy(AofSyncPolicy::Always),
+            )
+            .unwrap()
+        };
+        let feature = |i: usize| {
+            json!({
+                "type": "Feature",
+                "id": i,
+                "properties": {"name": format!("truck{}", i)},
+                "geometry": {"type": "Point", "coordinates": [(i % 20) as f64, (i / 20) as f64]}
+            })
+        };
+        let collection = |features: Vec<serde_json::Value>| {
+            json!({"type": "FeatureCollection", "features": features}).to_string()
+        };
+        let area = json_to_geometry(&json!({
+            "type": "Polygon",
+            "coordinates": [[[-0.5, -0.5], [9.5, -0.5], [9.5, 4.5], [-0.5, 4.5], [-0.5, -0.5]]]
+        }));
+
+        {
+            let db = open();
+            let point = json!({"type": "Point", "coordinates": [0.0, 0.0]}).to_string();
+            db.set_with_tags("fleet", "0", &point, &["old".to_string()])
+                .await
+                .unwrap();
+
+            // 任何一个 Feature 无效时不导入任何对象
+            let mut features: Vec<_> = (0..10).map(feature).collect();
+            features.push(json!({"type": "Feature", "id": "bad", "geometry": {"type": "Point"}}));
+            assert!(db
+                .import_geojson("fleet", collection(features), None)
+                .await
+                .is_err());
+            assert_eq!(db.collection_stats("fleet").await.unwrap().objects, 1);
+
+            let count = db
+                .import_geojson("fleet", collection((0..400).map(feature).collect()), None)
+                .await
+                .unwrap();
+            assert_eq!(count, 400);
+            assert!(db.tags("fleet", "0").await.unwrap().is_empty());
+            assert!(db
+                .validate_collection("fleet")
+                .await
+                .unwrap()
+                .unwrap()
+                .is_valid());
+            let found = db.intersects("fleet", &area, 0, false).await.unwrap();
+            assert_eq!(found.len(), 50);
+
+            // 少量对象导入已有的大 collection 时逐个插入索引
+            let count = db
+                .import_geojson("fleet", collection(vec![feature(400)]), None)
+                .await
+                .unwrap();
+            assert_eq!(count, 1);
+            assert!(db
+                .validate_collection("fleet")
+                .await
+                .unwrap()
+                .unwrap()
+                .is_valid());
+        }
+
+        let db = open();
+        db.recover_from_aof(aof_path).await.unwrap();
+        assert_eq!(db.collection_stats("fleet").await.unwrap().objects, 401);
+        let found = db.intersects("fleet", &area, 0, false).await.unwrap();
+        assert_eq!(found.len(), 50);
+        let item = db.get("fleet", "7").await.unwrap().unwrap();
+        assert!(item.geojson.contains("truck7"));
+    }
+
+    #[tokio::test]
+    async fn test_aof_rewrite_requires_aof() {
+        let db = Arc::new(GeoDatabase::new());
+        assert!(db.rewrite_aof().await.is_err());
+        assert!(db.background_rewrite_aof().is_err());
+        assert!(!db.aof_rewrite_due().await);
+    }
+
+    #[tokio::test]
+    async fn test_aof_recovers_expirations() {
+        use crate::rtree::algorithms::aof::{AofCommand, AofConfig, AofReader, AofSyncPolicy};
+        use crate::storage::clock::MockClock;
+        use std::time::Duration;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("ttl.aof");
+        let point = json!({"type": "Point", "coordinates": [116.4, 39.9]}).to_string();
+        let clock = Arc::new(MockClock::new(1_000_000_000));
+        let config = || AofConfig::new(aof_path.clone()).set_sync_policy(AofSyncPolicy::Always);
+
+        {
+            let db = GeoDatabase::with_aof(config())
+                .unwrap()
+                .with_clock(clock.clone());
+            for id in ["bus1", "bus2", "bus3"] {
+                db.set("fleet", id, &point).await.unwrap();
+            }
+            db.expire("fleet", "bus1", Duration::from_secs(10))
+                .await
+                .unwrap();
+            db.expire("fleet", "bus2", Duration::from_secs(60))
+                .await
+                .unwrap();
+            db.expire("fleet", "bus3", Duration::from_secs(10))
                 .await
                 .unwrap();
-
-            // 验证数据已写入
-            assert!(db.get("cities", "beijing").await.unwrap().is_some());
-            assert!(db.get("cities", "shanghai").await.unwrap().is_some());
+            assert!(db.persist("fleet", "bus3").await.unwrap());
         }
 
-        // 2. 创建新的数据库实例并从 AOF 恢复
-        {
-            let config = AofConfig::new(aof_path.clone());
-            let db = GeoDatabase::with_aof(config).unwrap();
-
-            // 恢复数据
-            let (commands, errors) = db.recover_from_aof(aof_path).await.unwrap();
-            assert_eq!(commands, 2);
-            assert_eq!(errors, 0);
-
-            // 验证数据已恢复
-            assert!(db.get("cities", "beijing").await.unwrap().is_some());
-            assert!(db.get("cities", "shanghai").await.unwrap().is_some());
+        let db = GeoDatabase::with_aof(config())
+            .unwrap()
+            .with_clock(clock.clone());
+        let report = db
+            .recover_from_aof_with_report(aof_path.clone())
+            .await
+            .unwrap();
+        assert!(report.is_clean());
+        assert_eq!(
+            db.ttl("fleet", "bus1").await.unwrap(),
+            Ttl::Expires(Duration::from_secs(10))
+        );
+        assert_eq!(db.ttl("fleet", "bus3").await.unwrap(), Ttl::Persistent);
+        assert_eq!(db.stats().await.unwrap().expiring_items, 2);
+
+        // 主动过期删除对象并写入 AOF DELETE
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(db.expire_due(0).await.unwrap(), 1);
+        assert_eq!(db.ttl("fleet", "bus1").await.unwrap(), Ttl::Missing);
+        assert_eq!(db.expired_objects(), 1);
+        drop(db);
+
+        let mut reader = AofReader::open(aof_path).unwrap();
+        let mut last = None;
+        while let Some(cmd) = reader.read_next().unwrap() {
+            last = Some(cmd);
         }
+        assert!(matches!(
+            last,
+            Some(AofCommand::Delete { ref key, .. }) if key == "bus1"
+        ));
     }
 
     #[tokio::test]
@@ -797,6 +3785,548 @@ mod tests {
         assert!(db.get("cities", "beijing").await.unwrap().is_none());
     }
 
+    #[tokio::test]
+    async fn test_recover_with_report_inconsistencies() {
+        use crate::rtree::algorithms::aof::{AofCommand, AofConfig, AofWriter};
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("test.aof");
+
+        {
+            let mut writer = AofWriter::new(AofConfig::new(aof_path.clone())).unwrap();
+            let point = json!({"type": "Point", "coordinates": [116.4, 39.9]}).to_string();
+            writer
+                .append(&AofCommand::insert(
+                    "cities".to_string(),
+                    "beijing".to_string(),
+                    point,
+                ))
+                .unwrap();
+            writer
+                .append(&AofCommand::insert(
+                    "cities".to_string(),
+                    "broken".to_string(),
+                    "not geojson".to_string(),
+                ))
+                .unwrap();
+            writer
+                .append(&AofCommand::delete(
+                    "cities".to_string(),
+                    "ghost".to_string(),
+                ))
+                .unwrap();
+            writer
+                .append(&AofCommand::delete(
+                    "missing".to_string(),
+                    "ghost".to_string(),
+                ))
+                .unwrap();
+            writer.flush().unwrap();
+        }
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&aof_path)
+            .and_then(|mut f| std::io::Write::write_all(&mut f, b"{corrupted\n"))
+            .unwrap();
+
+        let db = GeoDatabase::new();
+        let report = db.recover_from_aof_with_report(aof_path).await.unwrap();
+
+        assert_eq!(report.commands, 4);
+        assert_eq!(report.parse_errors.len(), 1);
+        assert_eq!(report.inconsistencies.len(), 3);
+        assert_eq!(report.total_lines, 5);
+        assert!(!report.is_clean());
+
+        // 有效数据仍然被恢复
+        assert!(db.get("cities", "beijing").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_replay_is_idempotent() {
+        use crate::rtree::algorithms::aof::{AofConfig, AofSyncPolicy};
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("test.aof");
+        let point = json!({"type": "Point", "coordinates": [116.4, 39.9]}).to_string();
+
+        {
+            let config = AofConfig::new(aof_path.clone()).set_sync_policy(AofSyncPolicy::Always);
+            let db = GeoDatabase::with_aof(config).unwrap();
+            db.set("fleet", "truck1", &point).await.unwrap();
+            db.set("fleet", "truck2", &point).await.unwrap();
+            db.delete("fleet", "truck1").await.unwrap();
+
+            let collection = db.collection("fleet").await.unwrap().unwrap();
+            assert_eq!(collection.read().await.applied_seq(), 3);
+        }
+
+        // 第一次重放应用全部记录，第二次重放（例如部分恢复后重试）全部跳过
+        let db = GeoDatabase::new();
+        let report = db
+            .recover_from_aof_with_report(aof_path.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            (report.commands, report.skipped, report.last_seq),
+            (3, 0, 3)
+        );
+
+        let report = db
+            .recover_from_aof_with_report(aof_path.clone())
+            .await
+            .unwrap();
+        assert_eq!(report.skipped, 3);
+        assert!(report.is_clean());
+        assert!(db.get("fleet", "truck1").await.unwrap().is_none());
+        assert_eq!(db.stats().await.unwrap().total_items, 1);
+
+        // 快照保存已应用的序列号，从快照加载后重放同样不会重复应用
+        let snapshot_path = temp_dir.path().join("fleet.json");
+        let collection = db.collection("fleet").await.unwrap().unwrap();
+        collection
+            .read()
+            .await
+            .dump_to_file(&snapshot_path)
+            .unwrap();
+        let snapshot = RTree::load_from_file(&snapshot_path).unwrap();
+        assert_eq!(snapshot.applied_seq(), 3);
+
+        // 重新打开 AOF 后从最大序列号之后继续编号
+        let config = AofConfig::new(aof_path.clone());
+        let db = GeoDatabase::with_aof(config).unwrap();
+        db.recover_from_aof(aof_path).await.unwrap();
+        db.set("fleet", "truck3", &point).await.unwrap();
+        let collection = db.collection("fleet").await.unwrap().unwrap();
+        assert_eq!(collection.read().await.applied_seq(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_group_commit_waits_for_fsync() {
+        use crate::rtree::algorithms::aof::{AofConfig, AofSyncPolicy};
+        use std::time::Duration;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("test.aof");
+        let config = AofConfig::new(aof_path.clone())
+            .set_sync_policy(AofSyncPolicy::Always)
+            .with_group_commit(Duration::from_millis(5));
+        let db = Arc::new(GeoDatabase::with_aof(config).unwrap());
+        let point = json!({"type": "Point", "coordinates": [116.4, 39.9]}).to_string();
+
+        // 写入后记录还在缓冲区中，等待组提交
+        db.set("fleet", "truck0", &point).await.unwrap();
+        assert!(db.aof_writer.as_ref().unwrap().lock().await.sync_pending());
+        db.wait_durable().await.unwrap();
+        assert_eq!(db.aof_writer.as_ref().unwrap().lock().await.synced_seq(), 1);
+
+        // 并发的写入共享 fsync，每个写入返回时自己的记录已经落盘
+        let writers: Vec<_> = (1..=20)
+            .map(|i| {
+                let db = Arc::clone(&db);
+                let point = point.clone();
+                tokio::spawn(async move {
+                    db.set("fleet", &format!("truck{}", i), &point)
+                        .await
+                        .unwrap();
+                    let seq = db
+                        .collection("fleet")
+                        .await
+                        .unwrap()
+                        .unwrap()
+                        .read()
+                        .await
+                        .applied_seq();
+                    db.wait_durable().await.unwrap();
+                    let writer = db.aof_writer.as_ref().unwrap().lock().await;
+                    assert!(writer.synced_seq() >= seq);
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.await.unwrap();
+        }
+
+        let writer = db.aof_writer.as_ref().unwrap().lock().await;
+        assert_eq!(writer.synced_seq(), 21);
+        assert!(!writer.sync_pending());
+        assert!(writer.last_fsync_at().is_some());
+        drop(writer);
+        assert_eq!(
+            std::fs::read_to_string(&aof_path).unwrap().lines().count(),
+            21
+        );
+
+        // 没有等待落盘的记录时立即返回
+        db.wait_durable().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_writes_leave_no_partial_state() {
+        use crate::rtree::algorithms::aof::{AofConfig, AofSyncPolicy};
+        use std::time::Duration;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("test.aof");
+        let config = AofConfig::new(aof_path.clone()).set_sync_policy(AofSyncPolicy::Always);
+        let db = GeoDatabase::with_aof(config).unwrap();
+
+        let point = json!({"type": "Point", "coordinates": [116.4, 39.9]}).to_string();
+        db.set("fleet", "truck1", &point).await.unwrap();
+
+        // 占住 AOF 锁，模拟写入过程中客户端断开、任务被取消
+        let aof_guard = db.aof_writer.as_ref().unwrap().lock().await;
+
+        let set =
+            tokio::time::timeout(Duration::from_millis(50), db.set("fleet", "truck2", &point));
+        assert!(set.await.is_err());
+        let delete = tokio::time::timeout(Duration::from_millis(50), db.delete("fleet", "truck1"));
+        assert!(delete.await.is_err());
+        let drop_collection =
+            tokio::time::timeout(Duration::from_millis(50), db.drop_collection("fleet"));
+        assert!(drop_collection.await.is_err());
+
+        // 被取消的写操作没有修改内存，也没有留下 collection 锁
+        assert!(db.get("fleet", "truck2").await.unwrap().is_none());
+        assert!(db.get("fleet", "truck1").await.unwrap().is_some());
+        let collection = db.collection("fleet").await.unwrap().unwrap();
+        assert!(collection.try_write().is_ok());
+        drop(aof_guard);
+
+        // AOF 中只有第一次写入的完整记录，重放结果与内存一致
+        db.set("fleet", "truck3", &point).await.unwrap();
+        let replay = GeoDatabase::new();
+        let (commands, errors) = replay.recover_from_aof(aof_path).await.unwrap();
+        assert_eq!((commands, errors), (2, 0));
+        assert!(replay.get("fleet", "truck1").await.unwrap().is_some());
+        assert!(replay.get("fleet", "truck2").await.unwrap().is_none());
+        assert!(replay.get("fleet", "truck3").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_idle_collection_unload_and_reload() {
+        use crate::storage::UnloadConfig;
+        use std::time::Duration;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let cold_dir = temp_dir.path().join("cold");
+        let db = GeoDatabase::new()
+            .with_idle_unloading(UnloadConfig::new(Duration::ZERO, cold_dir.clone()))
+            .unwrap();
+
+        let point = json!({"type": "Point", "coordinates": [116.4, 39.9]}).to_string();
+        db.set("layer:a", "p1", &point).await.unwrap();
+        db.set("layer:a", "p2", &point).await.unwrap();
+        db.set("layer:b", "p1", &point).await.unwrap();
+
+        // 空闲阈值为 0，所有 collection 都会被卸载
+        assert_eq!(db.unload_idle_collections().await.unwrap(), 2);
+        assert_eq!(std::fs::read_dir(&cold_dir).unwrap().count(), 2);
+
+        // 元数据常驻：名称和统计仍然可用
+        let mut names = db.collection_names().await;
+        names.sort();
+        assert_eq!(names, vec!["layer:a", "layer:b"]);
+        let stats = db.stats().await.unwrap();
+        assert_eq!(stats.collections_count, 2);
+        assert_eq!(stats.total_items, 3);
+        assert_eq!(stats.unloaded_collections, 2);
+
+        // 访问时自动重新加载
+        assert!(db.get("layer:a", "p2").await.unwrap().is_some());
+        let stats = db.stats().await.unwrap();
+        assert_eq!(stats.unloaded_collections, 1);
+        assert_eq!(stats.total_items, 3);
+
+        // 写入已卸载的 collection 不会覆盖旧数据
+        db.set("layer:b", "p2", &point).await.unwrap();
+        assert!(db.get("layer:b", "p1").await.unwrap().is_some());
+
+        // 删除已卸载的 collection 返回常驻的对象数量并清理文件
+        db.unload_idle_collections().await.unwrap();
+        assert_eq!(db.drop_collection("layer:a").await.unwrap(), 2);
+        wait_for_files(&cold_dir, 1).await;
+        assert_eq!(db.collection_names().await, vec!["layer:b"]);
+    }
+
+    /// 等待后台删除冷数据文件，直到目录中剩下 `expected` 个文件
+    async fn wait_for_files(dir: &std::path::Path, expected: usize) {
+        for _ in 0..100 {
+            if std::fs::read_dir(dir).unwrap().count() == expected {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(std::fs::read_dir(dir).unwrap().count(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_idle_unloading_long_collection_name() {
+        use crate::storage::UnloadConfig;
+        use std::time::Duration;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let cold_dir = temp_dir.path().join("cold");
+        let db = GeoDatabase::new()
+            .with_idle_unloading(UnloadConfig::new(Duration::ZERO, cold_dir.clone()))
+            .unwrap();
+
+        // 十六进制编码后远超 NAME_MAX（255 字节）
+        let long_a = format!("tiles:{}", "a".repeat(300));
+        let long_b = format!("tiles:{}", "a".repeat(301));
+        let point = json!({"type": "Point", "coordinates": [116.4, 39.9]}).to_string();
+        db.set(&long_a, "p1", &point).await.unwrap();
+        db.set(&long_b, "p1", &point).await.unwrap();
+        db.set(&long_b, "p2", &point).await.unwrap();
+
+        assert_eq!(db.unload_idle_collections().await.unwrap(), 2);
+        for entry in std::fs::read_dir(&cold_dir).unwrap() {
+            assert!(entry.unwrap().file_name().len() <= 255);
+        }
+
+        // 前缀相同的名称各自重新加载自己的数据
+        assert!(db.get(&long_a, "p1").await.unwrap().is_some());
+        assert!(db.get(&long_a, "p2").await.unwrap().is_none());
+        assert!(db.get(&long_b, "p2").await.unwrap().is_some());
+        wait_for_files(&cold_dir, 0).await;
+    }
+
+    #[tokio::test]
+    async fn test_idle_reload_does_not_block_other_collections() {
+        use crate::storage::UnloadConfig;
+        use std::time::Duration;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let db = Arc::new(
+            GeoDatabase::new()
+                .with_idle_unloading(UnloadConfig::new(
+                    Duration::ZERO,
+                    temp_dir.path().join("cold"),
+                ))
+                .unwrap(),
+        );
+        let point = json!({"type": "Point", "coordinates": [116.4, 39.9]}).to_string();
+        db.set("cold", "p1", &point).await.unwrap();
+        db.unload_idle_collections().await.unwrap();
+
+        // 持有加载锁模拟一次很慢的重新加载：其他 collection 的读写不受影响
+        let loading = db.cold.as_ref().unwrap().loading("cold");
+        let guard = loading.lock().await;
+        let reload = tokio::spawn({
+            let db = Arc::clone(&db);
+            async move { db.get("cold", "p1").await.unwrap().is_some() }
+        });
+        tokio::time::timeout(Duration::from_secs(1), async {
+            db.set("hot", "p1", &point).await.unwrap();
+            assert!(db.get("hot", "p1").await.unwrap().is_some());
+        })
+        .await
+        .expect("other collections must not wait for the reload");
+        assert!(!reload.is_finished());
+
+        drop(guard);
+        assert!(reload.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_idle_unloading_with_mock_clock() {
+        use crate::storage::clock::MockClock;
+        use crate::storage::UnloadConfig;
+        use std::time::Duration;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let clock = Arc::new(MockClock::default());
+        let db = GeoDatabase::new()
+            .with_clock(clock.clone())
+            .with_idle_unloading(UnloadConfig::new(
+                Duration::from_secs(60),
+                temp_dir.path().join("cold"),
+            ))
+            .unwrap();
+
+        let point = json!({"type": "Point", "coordinates": [116.4, 39.9]}).to_string();
+        db.set("active", "p1", &point).await.unwrap();
+        db.set("idle", "p1", &point).await.unwrap();
+
+        clock.advance(Duration::from_secs(59));
+        assert_eq!(db.unload_idle_collections().await.unwrap(), 0);
+
+        // 访问 active 会重新计时，只有 idle 超时
+        db.get("active", "p1").await.unwrap();
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(db.unload_idle_collections().await.unwrap(), 1);
+        assert_eq!(db.stats().await.unwrap().unloaded_collections, 1);
+
+        clock.advance(Duration::from_secs(59));
+        assert_eq!(db.unload_idle_collections().await.unwrap(), 1);
+
+        // 卸载计入 STATS HISTORY 的 evictions
+        let history = db.stats_history(5);
+        assert_eq!(history.iter().map(|m| m.evictions).sum::<u64>(), 2);
+        assert_eq!(history.iter().map(|m| m.hits).sum::<u64>(), 1);
+    }
+
+    /// 记录调用次数的加载器：`zones` 有一个对象，`broken` 第一次失败，其他 collection 不存在
+    fn counting_loader(calls: Arc<std::sync::atomic::AtomicUsize>) -> super::CollectionLoader {
+        use crate::storage::LoadedObject;
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let broken_failed = Arc::new(AtomicBool::new(false));
+        Arc::new(move |collection: String| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            let first_broken =
+                collection == "broken" && !broken_failed.swap(true, Ordering::SeqCst);
+            Box::pin(async move {
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                let point = json!({"type": "Point", "coordinates": [116.4, 39.9]}).to_string();
+                match collection.as_str() {
+                    "zones" => Ok(Some(vec![
+                        LoadedObject::new("z1", point).with_tags(vec!["depot".to_string()])
+                    ])),
+                    "broken" if first_broken => Err("source unavailable".into()),
+                    "broken" => Ok(Some(vec![LoadedObject::new("b1", point)])),
+                    _ => Ok(None),
+                }
+            })
+        })
+    }
+
+    #[tokio::test]
+    async fn test_read_through_single_flight() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let db = Arc::new(GeoDatabase::new().with_loader(counting_loader(Arc::clone(&calls))));
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let db = Arc::clone(&db);
+                tokio::spawn(async move { db.get("zones", "z1").await.unwrap() })
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.await.unwrap().unwrap().id, "z1");
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(db.tags("zones", "z1").await.unwrap(), vec!["depot"]);
+
+        // 数据源中不存在的 collection 也只查询一次
+        assert!(db.get("nothing", "x").await.unwrap().is_none());
+        assert!(db.get("nothing", "x").await.unwrap().is_none());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // 加载失败时返回错误，下次访问重试
+        let err = db.get("broken", "b1").await.unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("failed to load collection 'broken'"));
+        assert_eq!(db.get("broken", "b1").await.unwrap().unwrap().id, "b1");
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+        // 写入不存在的 collection 前也会先加载
+        let point = json!({"type": "Point", "coordinates": [1.0, 2.0]}).to_string();
+        let calls_before = calls.load(Ordering::SeqCst);
+        db.set("fresh", "f1", &point).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), calls_before + 1);
+        assert_eq!(db.stats().await.unwrap().total_items, 3);
+    }
+
+    #[tokio::test]
+    async fn test_read_through_objects_are_recovered_from_aof() {
+        use crate::rtree::algorithms::aof::AofConfig;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("loaded.aof");
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        {
+            let db = GeoDatabase::with_aof(AofConfig::new(aof_path.clone()))
+                .unwrap()
+                .with_loader(counting_loader(Arc::clone(&calls)));
+            assert!(db.get("zones", "z1").await.unwrap().is_some());
+        }
+
+        // 恢复时不调用加载器，恢复出的 collection 也不会再加载
+        let db = GeoDatabase::with_aof(AofConfig::new(aof_path.clone()))
+            .unwrap()
+            .with_loader(counting_loader(Arc::clone(&calls)));
+        db.recover_from_aof(aof_path).await.unwrap();
+        assert_eq!(db.tags("zones", "z1").await.unwrap(), vec!["depot"]);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_aof_timestamps_use_clock() {
+        use crate::rtree::algorithms::aof::{AofConfig, AofReader, AofSyncPolicy};
+        use crate::storage::clock::MockClock;
+        use std::time::Duration;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("test.aof");
+        let clock = Arc::new(MockClock::new(1_000));
+        let db = GeoDatabase::with_aof(
+            AofConfig::new(aof_path.clone()).set_sync_policy(AofSyncPolicy::Always),
+        )
+        .unwrap()
+        .with_clock(clock.clone());
+
+        let point = json!({"type": "Point", "coordinates": [116.4, 39.9]}).to_string();
+        db.set("cities", "beijing", &point).await.unwrap();
+        clock.advance(Duration::from_nanos(500));
+        db.delete("cities", "beijing").await.unwrap();
+        drop(db);
+
+        let mut reader = AofReader::open(aof_path).unwrap();
+        let mut timestamps = Vec::new();
+        while let Some(cmd) = reader.read_next().unwrap() {
+            timestamps.push(cmd.timestamp());
+        }
+        assert_eq!(timestamps, vec![1_000, 1_500]);
+    }
+
+    #[tokio::test]
+    async fn test_persistence_info() {
+        use crate::rtree::algorithms::aof::{AofConfig, AofSyncPolicy};
+        use tempfile::TempDir;
+
+        // 未启用 AOF
+        let db = GeoDatabase::new();
+        let info = db.persistence_info().await;
+        assert!(!info.aof_enabled);
+        assert!(!info.write_stalled);
+
+        // 启用 AOF 并模拟慢磁盘（阈值为 0）
+        let temp_dir = TempDir::new().unwrap();
+        let config = AofConfig::new(temp_dir.path().join("test.aof"))
+            .set_sync_policy(AofSyncPolicy::Always)
+            .with_stall_detection(std::time::Duration::ZERO, true);
+        let db = GeoDatabase::with_aof(config).unwrap();
+
+        let point = json!({"type": "Point", "coordinates": [116.4, 39.9]}).to_string();
+        for i in 0..3 {
+            db.set("cities", &format!("c{}", i), &point).await.unwrap();
+        }
+
+        let info = db.persistence_info().await;
+        assert!(info.aof_enabled);
+        assert!(info.write_stalled);
+        assert_eq!(info.sync_policy, "always");
+        assert_eq!(info.effective_sync_policy, "everysec");
+        assert!(info.last_fsync_latency_ms.is_some());
+        assert!(info.bytes_written > 0);
+    }
+
     #[tokio::test]
     async fn test_aof_recover_nonexistent_file() {
         use crate::rtree::algorithms::aof::AofConfig;
@@ -815,4 +4345,371 @@ mod tests {
 
         // temp_dir 离开作用域时自动删除
     }
+
+    #[tokio::test]
+    async fn test_low_disk_refuses_writes_without_touching_aof() {
+        use crate::rtree::algorithms::aof::{AofConfig, AofSyncPolicy};
+        use crate::storage::{DiskConfig, DiskMonitor};
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("test.aof");
+        let config = AofConfig::new(aof_path.clone()).set_sync_policy(AofSyncPolicy::Always);
+        let monitor = DiskMonitor::new(DiskConfig::new(temp_dir.path().into(), 4096))
+            .with_probe(Arc::new(|_| Ok(0)));
+        let db = GeoDatabase::with_aof(config)
+            .unwrap()
+            .with_disk_monitor(monitor);
+
+        let point = json!({"type": "Point", "coordinates": [116.4, 39.9]}).to_string();
+        db.set("cities", "beijing", &point).await.unwrap();
+        let aof_len = std::fs::metadata(&aof_path).unwrap().len();
+
+        db.refresh_disk_space().unwrap();
+        assert!(db.disk_status().unwrap().low);
+
+        let err = db.set("cities", "shanghai", &point).await.unwrap_err();
+        assert_eq!(CommandError::code_of(err.as_ref()), ErrorCode::Oom);
+        let err = db.delete("cities", "beijing").await.unwrap_err();
+        assert_eq!(CommandError::code_of(err.as_ref()), ErrorCode::Oom);
+        assert!(db.drop_collection("cities").await.is_err());
+        assert!(db.drop_matching("*", false).await.is_err());
+
+        // 读和 DRYRUN 不受影响，AOF 没有新的记录
+        assert!(db.get("cities", "beijing").await.unwrap().is_some());
+        assert_eq!(db.drop_matching("*", true).await.unwrap().len(), 1);
+        assert_eq!(std::fs::metadata(&aof_path).unwrap().len(), aof_len);
+    }
+
+    #[tokio::test]
+    async fn test_write_waiting_on_dropped_collection_recreates_it() {
+        let db = Arc::new(GeoDatabase::new());
+        let point = json!({"type": "Point", "coordinates": [1.0, 2.0]}).to_string();
+        db.set("fleet", "old", &point).await.unwrap();
+
+        // 模拟 DROP：先占住 collection 的写锁，让 SET 在锁上等待
+        let collection = db.collection("fleet").await.unwrap().unwrap();
+        let mut guard = collection.write_owned().await;
+        let writer = {
+            let db = Arc::clone(&db);
+            let point = point.clone();
+            tokio::spawn(async move { db.set("fleet", "new", &point).await })
+        };
+        tokio::task::yield_now().await;
+
+        guard.detach();
+        db.collections.write().await.remove("fleet");
+        drop(guard);
+        writer.await.unwrap().unwrap();
+
+        // SET 写入了重新创建的 collection，而不是被删除的那一棵树
+        assert!(db.get("fleet", "new").await.unwrap().is_some());
+        assert!(db.get("fleet", "old").await.unwrap().is_none());
+        assert_eq!(db.collection_counts().await, vec![("fleet".to_string(), 1)]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_writes_match_aof_replay() {
+        use crate::rtree::algorithms::aof::{AofConfig, AofSyncPolicy};
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("ordering.aof");
+        let config = AofConfig::new(aof_path.clone()).set_sync_policy(AofSyncPolicy::Always);
+        let db = Arc::new(GeoDatabase::with_aof(config).unwrap());
+
+        // 同一 id 的并发 SET，夹杂 DELETE 和 DROP
+        let mut tasks = Vec::new();
+        for i in 0..200 {
+            let db = Arc::clone(&db);
+            tasks.push(tokio::spawn(async move {
+                let point = json!({"type": "Point", "coordinates": [i as f64 / 10.0, 1.0]});
+                match i % 20 {
+                    7 => {
+                        db.delete("fleet", "truck").await.unwrap();
+                    }
+                    13 => {
+                        db.drop_collection("fleet").await.unwrap();
+                    }
+                    _ => db.set("fleet", "truck", &point.to_string()).await.unwrap(),
+                }
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+        let live = db
+            .get("fleet", "truck")
+            .await
+            .unwrap()
+            .map(|item| item.geojson);
+        drop(db);
+
+        // 内存中的最终状态必须和按 AOF 顺序重放的结果一致
+        let replayed = GeoDatabase::new();
+        replayed.recover_from_aof(aof_path).await.unwrap();
+        let recovered = replayed
+            .get("fleet", "truck")
+            .await
+            .unwrap()
+            .map(|item| item.geojson);
+        assert_eq!(live, recovered);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_scan_during_concurrent_writes() {
+        use crate::rtree::algorithms::cursor::ScanCursor;
+        use std::collections::HashMap;
+
+        let db = Arc::new(GeoDatabase::new());
+        let point = |x: f64| json!({"type": "Point", "coordinates": [x, 1.0]}).to_string();
+        // stable_* 在整个分页过程中不被修改，churn_* 被并发地修改、删除和重新插入
+        for i in 0..100 {
+            db.set("fleet", &format!("stable_{:03}", i), &point(i as f64))
+                .await
+                .unwrap();
+            db.set("fleet", &format!("churn_{:03}", i), &point(i as f64))
+                .await
+                .unwrap();
+        }
+
+        let writer = {
+            let db = Arc::clone(&db);
+            tokio::spawn(async move {
+                for round in 0..20 {
+                    for i in (0..100).step_by(7) {
+                        let id = format!("churn_{:03}", (i + round) % 100);
+                        if round % 3 == 0 {
+                            db.delete("fleet", &id).await.unwrap();
+                        } else {
+                            db.set("fleet", &id, &point(round as f64)).await.unwrap();
+                        }
+                    }
+                    tokio::task::yield_now().await;
+                }
+            })
+        };
+
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        let mut cursor = ScanCursor::default();
+        loop {
+            let page = db.scan("fleet", &cursor, 7).await.unwrap();
+            for id in page.items {
+                *seen.entry(id).or_default() += 1;
+            }
+            match page.next {
+                Some(next) => cursor = next,
+                None => break,
+            }
+            tokio::task::yield_now().await;
+        }
+        writer.await.unwrap();
+
+        // 未被修改的对象恰好返回一次
+        for i in 0..100 {
+            assert_eq!(seen.get(&format!("stable_{:03}", i)), Some(&1));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_feature_members_round_trip() {
+        use crate::rtree::algorithms::aof::{AofConfig, AofSyncPolicy};
+        use crate::storage::UnloadConfig;
+        use std::time::Duration;
+        use tempfile::TempDir;
+
+        // Feature 的 id、properties 和其他成员按原样保存，不会被替换为只有几何体的 GeoJSON
+        let feature = r#"{"type":"Feature","id":42,"properties":{"name":"北京站","tags":["rail"],"capacity":{"platforms":8}},"geometry":{"type":"Point","coordinates":[116.4272,39.9027]},"title":"station"}"#;
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("features.aof");
+        let config = AofConfig::new(aof_path.clone()).set_sync_policy(AofSyncPolicy::Always);
+        let db = GeoDatabase::with_aof(config)
+            .unwrap()
+            .with_idle_unloading(UnloadConfig::new(
+                Duration::ZERO,
+                temp_dir.path().join("cold"),
+            ))
+            .unwrap();
+        db.set("stations", "bj", feature).await.unwrap();
+
+        let get = db.get("stations", "bj").await.unwrap().unwrap();
+        assert_eq!(get.geojson, feature);
+        let area = json_to_geometry(&json!({
+            "type": "Polygon",
+            "coordinates": [[[116.0, 39.0], [117.0, 39.0], [117.0, 40.0], [116.0, 40.0], [116.0, 39.0]]]
+        }));
+        let found = db.intersects("stations", &area, 0, false).await.unwrap();
+        assert_eq!(found[0].geojson, feature);
+        let nearest = db.nearby("stations", 116.4, 39.9, 1, None).await.unwrap();
+        assert_eq!(nearest[0].0.geojson, feature);
+
+        // 卸载到磁盘后重新加载
+        assert_eq!(db.unload_idle_collections().await.unwrap(), 1);
+        let reloaded = db.get("stations", "bj").await.unwrap().unwrap();
+        assert_eq!(reloaded.geojson, feature);
+        drop(db);
+
+        // 从 AOF 重放
+        let replay = GeoDatabase::new();
+        replay.recover_from_aof(aof_path).await.unwrap();
+        let recovered = replay.get("stations", "bj").await.unwrap().unwrap();
+        assert_eq!(recovered.geojson, feature);
+    }
+
+    #[tokio::test]
+    async fn test_string_objects_round_trip() {
+        use crate::rtree::algorithms::aof::{AofConfig, AofSyncPolicy};
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let aof_path = temp_dir.path().join("strings.aof");
+        let config = AofConfig::new(aof_path.clone()).set_sync_policy(AofSyncPolicy::Always);
+        let db = GeoDatabase::with_aof(config).unwrap();
+        db.set(
+            "fleet",
+            "truck1",
+            r#"{"type":"Point","coordinates":[1.0,2.0]}"#,
+        )
+        .await
+        .unwrap();
+        db.set(
+            "fleet",
+            "truck2",
+            r#"{"type":"Point","coordinates":[1.0,2.0]}"#,
+        )
+        .await
+        .unwrap();
+        db.set_string("fleet", "note", "hello", &["memo".to_string()], None)
+            .await
+            .unwrap();
+        // 覆盖几何体，对象从空间索引中移除
+        db.set_string(
+            "fleet",
+            "truck2",
+            "parked",
+            &[],
+            Some(Duration::from_secs(100)),
+        )
+        .await
+        .unwrap();
+
+        let area = json_to_geometry(&json!({
+            "type": "Polygon",
+            "coordinates": [[[0.0, 0.0], [3.0, 0.0], [3.0, 3.0], [0.0, 3.0], [0.0, 0.0]]]
+        }));
+        let found = db.intersects("fleet", &area, 0, false).await.unwrap();
+        assert_eq!(found.len(), 1);
+        let page = db.scan("fleet", &ScanCursor::default(), 0).await.unwrap();
+        assert_eq!(page.items, vec!["note", "truck1", "truck2"]);
+        assert!(db.get("fleet", "note").await.unwrap().is_none());
+        drop(db);
+
+        let replay = GeoDatabase::new();
+        replay.recover_from_aof(aof_path).await.unwrap();
+        assert!(matches!(
+            replay.get_value("fleet", "note").await.unwrap(),
+            Some(StoredValue::Text(text)) if text == "hello"
+        ));
+        assert_eq!(replay.tags("fleet", "note").await.unwrap(), vec!["memo"]);
+        assert!(matches!(
+            replay.ttl("fleet", "truck2").await.unwrap(),
+            Ttl::Expires(_)
+        ));
+        assert_eq!(replay.collection_stats("fleet").await.unwrap().objects, 3);
+        assert!(replay.delete("fleet", "note").await.unwrap());
+        assert!(replay.get_value("fleet", "note").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_strict_geojson_rejects_non_compliant_writes() {
+        let db = GeoDatabase::new().with_strict_geojson(vec!["export:*".to_string()]);
+        assert!(db.strict_geojson("export:parcels"));
+        assert!(!db.strict_geojson("parcels"));
+
+        let clockwise = r#"{"type":"Polygon","coordinates":[[[0,0],[0,1],[1,1],[1,0],[0,0]]]}"#;
+        let err = db
+            .set("export:parcels", "p1", clockwise)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            err,
+            "GeoJSON is not RFC 7946 compliant: \
+             $.coordinates[0]: exterior ring must be counterclockwise (RFC 7946 3.1.6)"
+        );
+        assert!(db.get("export:parcels", "p1").await.unwrap().is_none());
+
+        // 其他 collection 不受影响
+        db.set("parcels", "p1", clockwise).await.unwrap();
+
+        let counterclockwise =
+            r#"{"type":"Polygon","coordinates":[[[0,0],[1,0],[1,1],[0,1],[0,0]]]}"#;
+        db.set("export:parcels", "p1", counterclockwise)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_event_bus_publishes_changes() {
+        use crate::storage::clock::MockClock;
+
+        let clock = Arc::new(MockClock::new(1_000));
+        let db = GeoDatabase::new().with_clock(clock.clone());
+        let point = |lon: f64| json!({"type": "Point", "coordinates": [lon, 0.0]}).to_string();
+
+        // 没有订阅者时不构造事件
+        db.set("fleet", "truck0", &point(0.0)).await.unwrap();
+        let mut events = db.subscribe_events();
+        let mut metrics = db.subscribe_events();
+
+        db.set("fleet", "truck1", &point(1.0)).await.unwrap();
+        db.set("fleet", "truck1", &point(2.0)).await.unwrap();
+        db.delete("fleet", "truck1").await.unwrap();
+        db.set("fleet", "truck2", &point(3.0)).await.unwrap();
+        db.expire("fleet", "truck2", Duration::from_secs(1))
+            .await
+            .unwrap();
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(db.expire_due(10).await.unwrap(), 1);
+        assert_eq!(db.drop_collection("fleet").await.unwrap(), 1);
+        assert_eq!(db.drop_collection("fleet").await.unwrap(), 0);
+
+        let mut received = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            received.push(match event.as_ref() {
+                DatabaseEvent::ObjectSet { id, previous, .. } => {
+                    format!("set {} previous={}", id, previous.is_some())
+                }
+                DatabaseEvent::ObjectDeleted { id, geojson, .. } => {
+                    assert!(geojson.contains("coordinates"));
+                    format!("del {}", id)
+                }
+                DatabaseEvent::CollectionDropped {
+                    collection,
+                    objects,
+                    ..
+                } => format!("drop {} {}", collection, objects),
+            });
+        }
+        assert_eq!(
+            received,
+            vec![
+                "set truck1 previous=false",
+                "set truck1 previous=true",
+                "del truck1",
+                "set truck2 previous=false",
+                "del truck2",
+                "drop fleet 1",
+            ]
+        );
+
+        // 每个订阅者收到完整的事件流，取消订阅不影响其他订阅者
+        drop(events);
+        db.set("fleet", "truck3", &point(4.0)).await.unwrap();
+        let mut count = 0;
+        while metrics.try_recv().is_ok() {
+            count += 1;
+        }
+        assert_eq!(count, 7);
+    }
 }